@@ -0,0 +1,48 @@
+//! Throughput of the autocorrelation backends on typical analysis windows.
+//! Run with `cargo bench --features fft` to compare against the FFT path.
+
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+
+use rust_bpm_analyzer::core_bpm::correlation;
+
+// (name, window length, first lag, last lag)
+const CASES: [(&str, usize, usize, usize); 3] = [
+    // Coarse search: 2 s at ~545 Hz, 100-310 BPM
+    ("coarse", 1090, 105, 327),
+    // Fine refinement: 2 s at 24 kHz, +/- 50 samples
+    ("fine", 48000, 5950, 6050),
+    // Full-range search on the fine buffer (batch / multi-stream worst case)
+    ("fine_full_range", 24000, 4645, 14400),
+];
+
+fn click_envelope(len: usize, period: usize) -> Vec<f32> {
+    let mut signal: Vec<f32> = (0..len)
+        .map(|i| if i % period < period / 10 { 1.0 } else { 0.0 })
+        .collect();
+    let mean = signal.iter().sum::<f32>() / len as f32;
+    signal.iter_mut().for_each(|x| *x -= mean);
+    signal
+}
+
+fn bench_autocorrelation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("autocorrelation");
+    for (name, len, start_lag, end_lag) in CASES {
+        let signal = click_envelope(len, (start_lag + end_lag) / 2);
+        let mut out = vec![0.0; end_lag + 1];
+
+        group.bench_function(BenchmarkId::new("direct", name), |b| {
+            b.iter(|| correlation::direct(black_box(&signal), start_lag, end_lag, &mut out))
+        });
+
+        #[cfg(feature = "fft")]
+        group.bench_function(BenchmarkId::new("fft", name), |b| {
+            b.iter(|| {
+                correlation::fft::autocorrelation(black_box(&signal), start_lag, end_lag, &mut out)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_autocorrelation);
+criterion_main!(benches);
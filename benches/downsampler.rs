@@ -0,0 +1,134 @@
+//! Chunk average vs polyphase decimation of the envelope stages.
+//! Prints the BPM error of the full analyzer on synthetic signals, then times one
+//! analysis hop with each downsampler. Run with `cargo bench --bench downsampler`.
+
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group};
+
+use rust_bpm_analyzer::core_bpm::analyzer::{BpmAnalyzer, BpmAnalyzerConfig};
+use rust_bpm_analyzer::core_bpm::decimator::Downsampler;
+use rust_bpm_analyzer::core_bpm::testsig;
+
+const SAMPLE_RATE: u32 = 44100;
+const HOP: usize = (SAMPLE_RATE / 2) as usize;
+const SIGNAL_S: f32 = 8.0;
+const TEMPOS: [f32; 10] = [
+    100.0, 112.5, 124.0, 127.3, 135.0, 148.8, 160.0, 174.0, 201.7, 280.0,
+];
+
+// (fine stage, coarse stage)
+const SETUPS: [(&str, Downsampler, Downsampler); 3] = [
+    ("average", Downsampler::Average, Downsampler::Average),
+    (
+        "polyphase_coarse",
+        Downsampler::Average,
+        Downsampler::Polyphase,
+    ),
+    ("polyphase", Downsampler::Polyphase, Downsampler::Polyphase),
+];
+
+fn analyzer(fine: Downsampler, coarse: Downsampler) -> BpmAnalyzer {
+    let config = BpmAnalyzerConfig {
+        fine_downsampler: fine,
+        coarse_downsampler: coarse,
+        ..Default::default()
+    };
+    BpmAnalyzer::new(SAMPLE_RATE, Some(config)).expect("analyzer")
+}
+
+// Test signal at the given BPM
+type Generator = Box<dyn Fn(f32) -> Vec<f32>>;
+
+fn signals() -> Vec<(&'static str, Generator)> {
+    let len = (SAMPLE_RATE as f32 * SIGNAL_S) as usize;
+    vec![
+        (
+            "click -6 dB",
+            Box::new(move |bpm| {
+                let mut signal = testsig::click_track(SAMPLE_RATE, bpm, len);
+                testsig::add_noise(&mut signal, -6.0, 1);
+                signal
+            }),
+        ),
+        (
+            "swung kick -6 dB",
+            Box::new(move |bpm| {
+                let mut signal = testsig::kick_pattern(SAMPLE_RATE, bpm, 0.1, len);
+                testsig::add_noise(&mut signal, -6.0, 2);
+                signal
+            }),
+        ),
+        (
+            "kick -10 dB",
+            Box::new(move |bpm| {
+                let mut signal = testsig::kick_pattern(SAMPLE_RATE, bpm, 0.0, len);
+                testsig::add_noise(&mut signal, -10.0, 3);
+                signal
+            }),
+        ),
+    ]
+}
+
+/// Detection rate and BPM error of the last result, per signal and downsampler.
+/// The table is printed once everything ran, the analyzer logs its setup on creation.
+fn report_accuracy() {
+    let mut rows = Vec::new();
+    for (signal_name, generate) in signals() {
+        let inputs: Vec<(f32, Vec<f32>)> = TEMPOS.iter().map(|&bpm| (bpm, generate(bpm))).collect();
+        for (name, fine, coarse) in SETUPS {
+            let mut errors = Vec::new();
+            for (bpm, signal) in &inputs {
+                let mut analyzer = analyzer(fine, coarse);
+                let mut last = None;
+                for chunk in signal.chunks(HOP) {
+                    if let Ok(Some(result)) = analyzer.process(chunk) {
+                        last = Some(result.bpm);
+                    }
+                }
+                if let Some(found) = last {
+                    errors.push((found - bpm).abs());
+                }
+            }
+            let mean = errors.iter().sum::<f32>() / errors.len().max(1) as f32;
+            let max = errors.iter().cloned().fold(0.0, f32::max);
+            rows.push(format!(
+                "{:<18} {:<18} {:>5}/{:<2} {:>10.3} {:>10.3}",
+                signal_name,
+                name,
+                errors.len(),
+                TEMPOS.len(),
+                mean,
+                max
+            ));
+        }
+    }
+
+    println!(
+        "\n{:<18} {:<18} {:>8} {:>10} {:>10}",
+        "signal", "downsampler", "detected", "mean err", "max err"
+    );
+    for row in rows {
+        println!("{}", row);
+    }
+    println!();
+}
+
+fn bench_process(c: &mut Criterion) {
+    let signal = testsig::click_track(SAMPLE_RATE, 128.0, HOP * 8);
+    let mut group = c.benchmark_group("process_hop");
+    for (name, fine, coarse) in SETUPS {
+        let mut analyzer = analyzer(fine, coarse);
+        let mut chunks = signal.chunks(HOP).cycle();
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| analyzer.process(black_box(chunks.next().unwrap())))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_process);
+
+fn main() {
+    report_accuracy();
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -0,0 +1,757 @@
+use crate::core_bpm::analyzer::{BpmAnalyzerConfig, TempoEstimator};
+use crate::core_bpm::beat_grid::BeatGridConfig;
+use crate::core_bpm::decimator::Downsampler;
+use crate::core_bpm::governor::GovernorConfig;
+use crate::core_bpm::histogram::TempoHistogram;
+use crate::core_bpm::key::KeyConfig;
+use crate::core_bpm::monitor::MonitorConfig;
+use crate::core_bpm::preset::AnalyzerPreset;
+use crate::core_bpm::recorder::RecordingConfig;
+use crate::core_bpm::set_stats::StatsConfig;
+use crate::core_bpm::software_agc::SoftwareAgcConfig;
+use crate::core_bpm::verification::VerificationConfig;
+use crate::logging::LogConfig;
+use crate::metrics::MetricsConfig;
+use crate::network_sync::artnet::ArtNetConfig;
+use crate::network_sync::election::ElectionConfig;
+use crate::network_sync::follow::LinkConfig;
+use crate::outputs::OutputsConfig;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{info, warn};
+
+const CONFIG_ENV: &str = "BPM_ANALYZER_CONFIG";
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// Optional TOML configuration file. Every section falls back to its defaults
+/// when missing, so an absent file behaves like the hard-coded settings.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub artnet: ArtNetConfig,
+    pub governor: GovernorConfig,
+    pub trigger: PhraseTriggerConfig,
+    pub api: ApiConfig,
+    pub analysis: AnalysisConfig,
+    pub agc: AgcConfig,
+    pub audio: AudioConfig,
+    pub alsa: AlsaConfig,
+    pub display: DisplayConfig,
+    pub ledstrip: LedStripConfig,
+    pub gate: GateConfig,
+    pub link: LinkConfig,
+    pub button: ButtonConfig,
+    pub update: UpdateConfig,
+    pub log: LogConfig,
+    pub metrics: MetricsConfig,
+    pub recording: RecordingConfig,
+    pub key: KeyConfig,
+    pub verification: VerificationConfig,
+    pub software_agc: SoftwareAgcConfig,
+    pub session: SessionConfig,
+    pub beat_grid: BeatGridConfig,
+    pub stats: StatsConfig,
+    pub midi_out: MidiOutConfig,
+    pub outputs: OutputsConfig,
+    pub discovery: DiscoveryConfig,
+    pub election: ElectionConfig,
+    pub monitor: MonitorConfig,
+}
+
+/// `[analysis]` section: overrides applied on top of `BpmAnalyzerConfig::default()`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AnalysisConfig {
+    /// Disable for live/rock sets where dynamics trigger false drops
+    pub drop_detection: bool,
+    /// Preset file replacing all analyzer settings (`--preset <file>` takes precedence)
+    pub preset: Option<PathBuf>,
+    /// Sliding period and bin width of the tempo histogram
+    pub histogram_period_s: u64,
+    pub histogram_bin_width: f32,
+    /// Decimation of both envelope stages (`Polyphase` avoids aliasing)
+    pub downsampler: Downsampler,
+    /// Coarse and fine searches on their own threads (see `BpmAnalyzerConfig::pipelined`)
+    pub pipelined: bool,
+    /// Audio between two analyses (see `BpmAnalyzerConfig::hop_duration`)
+    pub hop_ms: u64,
+    /// Detections of another tempo that replace the current one (see
+    /// `BpmAnalyzerConfig::tempo_change_hops`)
+    pub tempo_change_hops: usize,
+    pub tempo_change_tolerance: f32,
+    /// Coarse tempo search (`CombFilter` holds better on sparse percussion)
+    pub estimator: TempoEstimator,
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        Self {
+            drop_detection: true,
+            preset: None,
+            histogram_period_s: 600,
+            histogram_bin_width: 1.0,
+            downsampler: Downsampler::Average,
+            pipelined: false,
+            hop_ms: 500,
+            tempo_change_hops: 2,
+            tempo_change_tolerance: 3.0,
+            estimator: TempoEstimator::Autocorrelation,
+        }
+    }
+}
+
+/// `[agc]` section: automatic capture gain (headless only). Also the payload
+/// of the live tuning API.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AgcConfig {
+    /// Target RMS level
+    pub setpoint: f32,
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+    /// Number of audio packets averaged for the RMS measurement
+    pub window: usize,
+}
+
+impl Default for AgcConfig {
+    fn default() -> Self {
+        Self {
+            setpoint: 0.25,
+            kp: 15.0,
+            ki: 1.5,
+            kd: 0.0,
+            window: 8,
+        }
+    }
+}
+
+impl AgcConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.setpoint > 0.0 && self.setpoint <= 1.0) {
+            return Err("setpoint must be in ]0, 1]".to_string());
+        }
+        if ![self.kp, self.ki, self.kd]
+            .iter()
+            .all(|g| g.is_finite() && *g >= 0.0)
+        {
+            return Err("PID gains must be finite and positive".to_string());
+        }
+        if self.window == 0 {
+            return Err("window must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// `[audio]` section: audio capture of every mode.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// Input muted after the stream starts (codec power-up noise), 0 to disable
+    pub warmup_ms: u64,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self { warmup_ms: 1000 }
+    }
+}
+
+impl AudioConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.warmup_ms > 10_000 {
+            return Err("warmup_ms must be at most 10000".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// `[alsa]` section: mixer driven by the capture gain control (headless only).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AlsaConfig {
+    /// Card of the mixer ("hw:0", "hw:CARD=sndrpihifiberry"...)
+    pub card: String,
+    /// Capture volume control ("Capture", "ADC"...), first one of the card when unset
+    pub control: Option<String>,
+}
+
+impl Default for AlsaConfig {
+    fn default() -> Self {
+        Self {
+            card: "hw:0".to_string(),
+            control: None,
+        }
+    }
+}
+
+impl AlsaConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.card.trim().is_empty() {
+            return Err("card must not be empty".to_string());
+        }
+        if self.control.as_deref().is_some_and(|c| c.trim().is_empty()) {
+            return Err("control must not be empty when set".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Screen driven by the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayKind {
+    /// Monochrome I2C OLED, 128x64 or 128x32
+    #[default]
+    Ssd1306,
+    /// Color SPI TFT, 240x240
+    St7789,
+    /// Color SPI TFT, 240x320
+    Ili9341,
+}
+
+/// `[display]` section: screen of the device (headless only).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    pub kind: DisplayKind,
+    /// I2C bus the OLED is wired to
+    pub i2c_bus: String,
+    /// OLED I2C address (`0x3C`, `0x3D`), both are probed when unset
+    pub address: Option<u8>,
+    /// OLED height: 64 (128x64) or 32 (128x32, without the status icons)
+    pub height: u32,
+    /// 0, or 180 for a screen mounted upside down. TFTs also take 90 and 270.
+    pub rotation: u32,
+    /// SPI device of the TFT, not the one of `[ledstrip]`
+    pub spi_device: String,
+    /// GPIO lines of the TFT data/command, reset and backlight pins
+    pub gpio_chip: String,
+    pub dc_line: u32,
+    pub reset_line: Option<u32>,
+    pub backlight_line: Option<u32>,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            kind: DisplayKind::Ssd1306,
+            i2c_bus: "/dev/i2c-2".to_string(),
+            address: None,
+            height: 64,
+            rotation: 0,
+            spi_device: "/dev/spidev0.1".to_string(),
+            gpio_chip: "/dev/gpiochip4".to_string(),
+            dc_line: 25,
+            reset_line: Some(27),
+            backlight_line: None,
+        }
+    }
+}
+
+impl DisplayConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.kind == DisplayKind::Ssd1306 {
+            if self.i2c_bus.trim().is_empty() {
+                return Err("i2c_bus must not be empty".to_string());
+            }
+            if self.address.is_some_and(|a| !(0x03..=0x77).contains(&a)) {
+                return Err("address must be a 7-bit I2C address".to_string());
+            }
+            if self.height != 64 && self.height != 32 {
+                return Err("height must be 64 or 32".to_string());
+            }
+            if self.rotation != 0 && self.rotation != 180 {
+                return Err("rotation must be 0 or 180".to_string());
+            }
+            return Ok(());
+        }
+        if self.spi_device.trim().is_empty() || self.gpio_chip.trim().is_empty() {
+            return Err("spi_device and gpio_chip must not be empty".to_string());
+        }
+        if ![0, 90, 180, 270].contains(&self.rotation) {
+            return Err("rotation must be 0, 90, 180 or 270".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Animation of the LED strip
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedStripPattern {
+    /// The whole strip flashes on the beat
+    #[default]
+    Pulse,
+    /// A dot goes round the strip once per bar
+    Chase,
+    /// Bar following the input level
+    VuMeter,
+}
+
+/// `[ledstrip]` section: WS2812 strip on an SPI bus (headless only).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LedStripConfig {
+    pub enabled: bool,
+    pub spi_device: String,
+    /// Number of LEDs
+    pub length: usize,
+    pub pattern: LedStripPattern,
+    /// (R, G, B)
+    pub color: [u8; 3],
+}
+
+impl Default for LedStripConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            spi_device: "/dev/spidev0.0".to_string(),
+            length: 30,
+            pattern: LedStripPattern::Pulse,
+            color: [255, 40, 0],
+        }
+    }
+}
+
+impl LedStripConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.spi_device.trim().is_empty() {
+            return Err("spi_device must not be empty".to_string());
+        }
+        if !(1..=1000).contains(&self.length) {
+            return Err("length must be in [1, 1000]".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// `[gate]` section: analysis only runs while an external gate is open, for
+/// installations where another system knows when the program material is playing.
+/// The gate follows the last source that changed it (GPIO input, held MIDI note or
+/// `POST /gate`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GateConfig {
+    pub enabled: bool,
+    /// GPIO input opening the gate (headless only), none when `gpio_line` is unset
+    pub gpio_chip: String,
+    pub gpio_line: Option<u32>,
+    /// Gate open while the line is low
+    pub active_low: bool,
+    /// MIDI note holding the gate open (desktop only)
+    pub midi_note: Option<u8>,
+    /// MIDI channel (0-15) of `midi_note`, any channel when unset
+    pub midi_channel: Option<u8>,
+}
+
+impl Default for GateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            gpio_chip: "/dev/gpiochip4".to_string(),
+            gpio_line: None,
+            active_low: false,
+            midi_note: None,
+            midi_channel: None,
+        }
+    }
+}
+
+impl GateConfig {
+    /// Whether a MIDI note event drives the gate.
+    pub fn matches_midi(&self, channel: u8, note: u8) -> bool {
+        self.midi_note == Some(note) && self.midi_channel.is_none_or(|c| c == channel)
+    }
+}
+
+/// `[api]` section: embedded HTTP API.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// Pre-shared key signing the POST commands (open when unset), see `api_auth`
+    pub key: Option<String>,
+}
+
+/// `[trigger]` section: pulse at the start of every phrase, on a GPIO line
+/// (headless only), as `<prefix>/phrase` on OSC and `phrase_note` on MIDI.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PhraseTriggerConfig {
+    pub enabled: bool,
+    /// Phrase length in bars
+    pub bars: u32,
+    pub beats_per_bar: u32,
+    pub pulse_ms: u64,
+    pub gpio_chip: String,
+    pub gpio_line: u32,
+}
+
+impl Default for PhraseTriggerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bars: 8,
+            beats_per_bar: 4,
+            pulse_ms: 100,
+            gpio_chip: "/dev/gpiochip4".to_string(),
+            gpio_line: 4,
+        }
+    }
+}
+
+/// Command run by a press of the device button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ButtonCommand {
+    None,
+    /// Next OLED page
+    NextPage,
+    /// Pause or resume the analysis (like `POST /analysis`)
+    ToggleAnalysis,
+    /// Restart the automatic gain convergence (like `POST /autogain`)
+    AutoGain,
+    /// Save the last seconds of audio to a WAV file (like `POST /recording`)
+    SaveRecording,
+    /// Self-update from GitHub (like `POST /update`)
+    Update,
+    /// Clean power off (like `POST /shutdown`)
+    Shutdown,
+}
+
+/// `[button]` section: on-device menu of the front button (headless only).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ButtonConfig {
+    pub single_press: ButtonCommand,
+    pub double_press: ButtonCommand,
+    pub long_press: ButtonCommand,
+}
+
+impl Default for ButtonConfig {
+    fn default() -> Self {
+        Self {
+            single_press: ButtonCommand::NextPage,
+            double_press: ButtonCommand::ToggleAnalysis,
+            long_press: ButtonCommand::AutoGain,
+        }
+    }
+}
+
+/// Release channel followed by the self-update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum UpdateChannel {
+    /// Releases only
+    Stable,
+    /// Pre-releases too (`0.3.0-beta.1`)
+    Beta,
+}
+
+impl UpdateChannel {
+    /// Whether a release version belongs to the channel.
+    pub fn accepts(self, version: &str) -> bool {
+        match self {
+            UpdateChannel::Stable => !version.contains('-'),
+            UpdateChannel::Beta => true,
+        }
+    }
+}
+
+/// `[update]` section: self-update of the devices from GitHub releases (headless only).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    pub channel: UpdateChannel,
+    /// Hours between update checks, on top of the one when Ethernet comes up
+    /// (0: only then)
+    pub check_interval_h: u64,
+    /// Seconds a new version has to start the analysis before the previous one
+    /// is restored
+    pub health_check_s: u64,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            channel: UpdateChannel::Stable,
+            check_interval_h: 24,
+            health_check_s: 60,
+        }
+    }
+}
+
+impl UpdateConfig {
+    pub fn check_interval(&self) -> Option<Duration> {
+        (self.check_interval_h > 0).then(|| Duration::from_secs(self.check_interval_h * 3600))
+    }
+}
+
+/// `[session]` section: reference tempo, octave preference and capture gain of
+/// the devices, saved to disk and restored at boot (headless only).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    pub enabled: bool,
+    pub path: PathBuf,
+    /// Minimum seconds between two writes, spares the SD card
+    pub save_interval_s: u64,
+    /// A saved session older than this belongs to another set and is ignored
+    pub max_age_s: u64,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: PathBuf::from("session.toml"),
+            save_interval_s: 30,
+            max_age_s: 1800,
+        }
+    }
+}
+
+impl SessionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.path.as_os_str().is_empty() {
+            return Err("path must not be empty".to_string());
+        }
+        if self.max_age_s == 0 {
+            return Err("max_age_s must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// `[midi_out]` section: MIDI notes on beats, downbeats and drops for lighting
+/// software that follows MIDI notes only. Notes are 0-127, `None` sends nothing.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MidiOutConfig {
+    pub enabled: bool,
+    /// Part of the output port name, the first port when unset
+    pub port: Option<String>,
+    /// MIDI channel, 1-16
+    pub channel: u8,
+    /// Every beat, velocity following the input level
+    pub beat_note: Option<u8>,
+    /// First beat of each bar, sent instead of `beat_note`
+    pub downbeat_note: Option<u8>,
+    pub drop_note: Option<u8>,
+    /// First beat of each phrase, with `[trigger] enabled`
+    pub phrase_note: Option<u8>,
+    /// Velocity of a beat in silence, full level gives 127
+    pub min_velocity: u8,
+    /// Time before the Note Off
+    pub note_ms: u64,
+}
+
+impl Default for MidiOutConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: None,
+            channel: 1,
+            beat_note: Some(36),
+            downbeat_note: Some(37),
+            drop_note: Some(38),
+            phrase_note: None,
+            min_velocity: 40,
+            note_ms: 50,
+        }
+    }
+}
+
+impl MidiOutConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=16).contains(&self.channel) {
+            return Err("channel must be between 1 and 16".to_string());
+        }
+        let notes = [
+            self.beat_note,
+            self.downbeat_note,
+            self.drop_note,
+            self.phrase_note,
+        ];
+        if notes.iter().flatten().any(|&note| note > 127) || self.min_velocity > 127 {
+            return Err("notes and min_velocity must be between 0 and 127".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// `[discovery]` section: address families of the mDNS announcement and browsing.
+/// IPv6 uses the link-local group `ff02::fb`, for IPv6-only networks.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DiscoveryConfig {
+    pub ipv4: bool,
+    pub ipv6: bool,
+    /// API addresses (`ip:port`) of devices reached over unicast when mDNS finds
+    /// nothing, on venue networks that block multicast
+    pub static_peers: Vec<SocketAddr>,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            ipv4: true,
+            ipv6: true,
+            static_peers: Vec::new(),
+        }
+    }
+}
+
+impl DiscoveryConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.ipv4 && !self.ipv6 {
+            return Err("ipv4 and ipv6 cannot both be disabled".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl AppConfig {
+    /// Path from `BPM_ANALYZER_CONFIG`, or `config.toml` in the working directory.
+    pub fn path() -> PathBuf {
+        std::env::var_os(CONFIG_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH))
+    }
+
+    /// Analyzer settings from the preset file if any, otherwise from the `[analysis]` section.
+    pub fn analyzer_config(&self) -> BpmAnalyzerConfig {
+        if let Some(path) = &self.analysis.preset {
+            match AnalyzerPreset::load(path) {
+                Ok(preset) => {
+                    info!("Preset '{}' loaded from {:?}", preset.name, path);
+                    // Threading, hop and input level depend on the host, not on the music
+                    return BpmAnalyzerConfig {
+                        pipelined: self.analysis.pipelined,
+                        hop_duration: Duration::from_millis(self.analysis.hop_ms),
+                        software_agc: self.software_agc,
+                        ..preset.analyzer
+                    };
+                }
+                Err(e) => warn!("Invalid preset {:?}: {}. Using config.", path, e),
+            }
+        }
+        BpmAnalyzerConfig {
+            drop_detection: self.analysis.drop_detection,
+            fine_downsampler: self.analysis.downsampler,
+            coarse_downsampler: self.analysis.downsampler,
+            pipelined: self.analysis.pipelined,
+            hop_duration: Duration::from_millis(self.analysis.hop_ms),
+            tempo_change_hops: self.analysis.tempo_change_hops,
+            tempo_change_tolerance: self.analysis.tempo_change_tolerance,
+            software_agc: self.software_agc,
+            estimator: self.analysis.estimator,
+            ..Default::default()
+        }
+    }
+
+    pub fn tempo_histogram(&self) -> TempoHistogram {
+        TempoHistogram::new(
+            Duration::from_secs(self.analysis.histogram_period_s),
+            self.analysis.histogram_bin_width,
+        )
+    }
+
+    /// Muting after the capture starts, `None` when disabled. An invalid
+    /// `[audio]` section falls back to the default.
+    pub fn audio_warmup(&self) -> Option<Duration> {
+        let audio = match self.audio.validate() {
+            Ok(()) => self.audio.clone(),
+            Err(e) => {
+                warn!("Invalid [audio] section ({}), using defaults", e);
+                AudioConfig::default()
+            }
+        };
+        (audio.warmup_ms > 0).then(|| Duration::from_millis(audio.warmup_ms))
+    }
+
+    /// Loads the config file, falling back to defaults if it is missing or invalid.
+    pub fn load() -> Self {
+        let path = Self::path();
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(content) => match toml::from_str(&content) {
+                Ok(config) => {
+                    info!("Config loaded from {:?}", path);
+                    config
+                }
+                Err(e) => {
+                    warn!("Invalid config {:?}: {}. Using defaults.", path, e);
+                    Self::default()
+                }
+            },
+            Err(_) => Self::default(),
+        };
+        if let Some(preset) = cli_preset() {
+            config.analysis.preset = Some(preset);
+        }
+        config
+    }
+}
+
+/// Value of the `--preset <file>` command line flag.
+fn cli_preset() -> Option<PathBuf> {
+    cli_option("--preset").flatten().map(PathBuf::from)
+}
+
+/// Command line option given as `<flag> <value>` or `<flag>=<value>`: `None`
+/// without the flag, `Some(None)` when no value follows it (last argument, or
+/// another `--` flag next).
+pub fn cli_option(flag: &str) -> Option<Option<String>> {
+    parse_option(std::env::args().skip(1), flag)
+}
+
+/// Values of a repeatable option, `<flag> <value>...` up to the next flag or
+/// `<flag>=<value>`.
+pub fn cli_values(flag: &str) -> Vec<String> {
+    parse_values(std::env::args().skip(1), flag)
+}
+
+/// `cli_option` on the given arguments, without the program name.
+pub fn parse_option<I>(args: I, flag: &str) -> Option<Option<String>>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return Some(args.next_if(|next| !next.starts_with("--")));
+        }
+        if let Some(value) = inline_value(&arg, flag) {
+            return Some(Some(value.to_string()));
+        }
+    }
+    None
+}
+
+/// `cli_values` on the given arguments, without the program name.
+pub fn parse_values<I>(args: I, flag: &str) -> Vec<String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut values = Vec::new();
+    let mut collecting = false;
+    for arg in args {
+        if let Some(value) = inline_value(&arg, flag) {
+            values.push(value.to_string());
+            collecting = false;
+        } else if arg == flag {
+            collecting = true;
+        } else if arg.starts_with("--") {
+            collecting = false;
+        } else if collecting {
+            values.push(arg);
+        }
+    }
+    values
+}
+
+// Value of `<flag>=<value>`
+fn inline_value<'a>(arg: &'a str, flag: &str) -> Option<&'a str> {
+    arg.strip_prefix(flag)?.strip_prefix('=')
+}
@@ -1,14 +1,23 @@
+use super::beat_pll::{BeatPll, PhaseLock};
+use super::comb;
+use super::correlation;
+use super::decimator::{Downsampler, PolyphaseDecimator};
+use super::drop_detector::{self, DropDetector};
+use super::groove;
+use super::ioi::{self, TempoHypothesis};
+use super::key::Key;
+use super::pipeline::{AnalysisPipeline, AnalysisWindow};
+use super::sections::{FeatureTracker, Section, SectionClassifier};
+use super::software_agc::{SoftwareAgc, SoftwareAgcConfig};
+use super::tempo_tracker::{Observation, TempoTracker};
+use super::testsig;
 use aubio::Tempo;
 use biquad::*;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 use std::u32;
-
-#[derive(Debug, Clone, Copy)]
-struct BpmHistoryEntry {
-    bpm: f32,
-    timestamp: Instant,
-}
+use tracing::{error, info};
 
 #[derive(Debug, Clone, Copy)]
 pub struct AnalysisResult {
@@ -16,7 +25,188 @@ pub struct AnalysisResult {
     pub is_drop: bool,
     pub confidence: f32,
     pub coarse_confidence: f32,
+    /// Age of the last beat of the phase loop grid on drops, for the Link
+    /// downbeat re-sync
     pub beat_offset: Option<Duration>,
+    /// Beat phase loop lock, jitter and drift correction
+    pub beat_phase: Option<PhaseLock>,
+    /// Set on the hop where the analyzer moved to a new tempo (next track, tempo ride)
+    pub tempo_change: Option<TempoChange>,
+    /// Off-beat 8th position in percent of a beat (50 straight, 66.7 triplet
+    /// shuffle), `None` without clear off-beats
+    pub swing: Option<f32>,
+    /// Probability of the reported tempo given the recent detections, see
+    /// `TempoTracker`
+    pub tracking_confidence: f32,
+    /// Part of the track (breakdown, build-up, drop...), see `SectionClassifier`
+    pub section: Section,
+    /// Musical key, filled by `LiveAnalysis` with `[key] enabled`
+    pub key: Option<Key>,
+    /// Tempo to pass on to Link and the outputs. `bpm` itself, or with
+    /// `[verification] enabled` the last tempo confirmed by the long window
+    /// (`None` before the first confirmation), filled by `LiveAnalysis`
+    pub verified_bpm: Option<f32>,
+}
+
+/// The tempo tracker moved to another, non-harmonic tempo, after about
+/// `tempo_change_hops` detections of it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TempoChange {
+    pub from: f32,
+    pub to: f32,
+}
+
+/// Input level (mean square of the raw window) below which a window is silence,
+/// about -23 dB
+pub const NOISE_GATE: f32 = 0.005;
+
+/// Shortest analysis hop accepted by `BpmAnalyzer::new`
+pub const MIN_HOP: Duration = Duration::from_millis(250);
+/// Longest analysis hop, the shortest window of the CPU governor
+pub const MAX_HOP: Duration = Duration::from_millis(1250);
+
+// Rejections kept for the state dump
+const REJECTION_LOG: usize = 16;
+// Time without detection after which the tempo tracker starts over
+const TRACKING_TIMEOUT: Duration = Duration::from_secs(10);
+// Weight of the latest window in the reported swing
+const SWING_SMOOTHING: f32 = 0.3;
+// Tempos a third or a quarter away from the correlation peak, that the onset
+// intervals can pick instead with `TempoEstimator::IoiFusion`
+const METRICAL_RATIOS: [f32; 4] = [2.0 / 3.0, 3.0 / 4.0, 4.0 / 3.0, 3.0 / 2.0];
+// Distance, in share of the tempo, of an IOI hypothesis still backing a lag
+const IOI_TOLERANCE: f32 = 0.03;
+
+/// Why an analysis hop produced no BPM.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Rejection {
+    /// Analysis window not full yet
+    BufferFilling,
+    /// Input below the noise gate
+    Silence,
+    /// Coarse envelope without energy
+    NoEnergy,
+    /// Coarse lag search failed (no peak or confidence below threshold)
+    Coarse(&'static str),
+    /// Fine lag search failed (no peak or confidence below threshold)
+    Fine(&'static str),
+    /// Autocorrelation tempo not matching any multiple of the aubio tempo
+    AubioMismatch { bpm: f32, aubio_bpm: f32 },
+    /// Pipeline threads still busy with earlier windows, this one was skipped
+    PipelineBusy,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RejectionRecord {
+    pub reason: Rejection,
+    pub age_s: f32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BufferFill {
+    pub name: &'static str,
+    pub len: usize,
+    pub capacity: usize,
+    pub rate: f32,
+}
+
+/// Analyzer internals serialized by the state dump, to debug a tempo stuck on a
+/// wrong value from data. Read back by `BpmAnalyzer::restore`, which only uses
+/// the tempo tracking part.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnalyzerState {
+    pub sample_rate: u32,
+    /// Detections decoded by the tempo tracker, oldest first, as (bpm, age in
+    /// seconds)
+    pub history: Vec<(f32, f32)>,
+    /// Tempo decoded by the tracker, i.e. the BPM currently reported
+    pub reference_bpm: Option<f32>,
+    pub tracking_confidence: f32,
+    /// Smoothed swing of the tracked tempo
+    pub swing: Option<f32>,
+    /// Tempo passed to `restore_reference`, not seeded into the tracker yet
+    pub pending_reference: Option<f32>,
+    /// Last aubio tempo used for cross-validation (0 when aubio had none)
+    pub aubio_bpm: f32,
+    pub aubio_confidence: f32,
+    pub octave: OctavePreference,
+    /// Effective settings: BPM range, thresholds, window
+    pub config: BpmAnalyzerConfig,
+    #[serde(skip_deserializing)]
+    pub buffers: Vec<BufferFill>,
+    /// Coarse lag before and after octave correction
+    pub raw_lag: Option<usize>,
+    pub chosen_lag: Option<usize>,
+    /// Most recent rejections, oldest first
+    #[serde(skip_deserializing)]
+    pub rejections: Vec<RejectionRecord>,
+}
+
+/// Snapshot of the coarse energy envelope, used by the GUI to plot what the analyzer sees.
+#[derive(Debug, Clone, Default)]
+pub struct EnvelopeFrame {
+    /// Envelope normalized to 0..1, oldest sample first
+    pub samples: Vec<f32>,
+    /// Beat positions projected on the window (0.0 = oldest, 1.0 = newest)
+    pub beat_markers: Vec<f32>,
+}
+
+/// Internals of the last coarse search, exposed for threshold tuning.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisDebug {
+    /// Estimator of the coarse search, i.e. what `coarse_curve` holds
+    pub estimator: TempoEstimator,
+    /// One value per lag from `first_lag`: smoothed coarse correlation divided by
+    /// window energy, or resonator energy with `TempoEstimator::CombFilter`
+    pub coarse_curve: Vec<f32>,
+    pub first_lag: usize,
+    pub coarse_rate: f32,
+    /// Correlation peak before octave correction
+    pub raw_lag: Option<usize>,
+    /// Lag kept after `check_harmonics`
+    pub chosen_lag: Option<usize>,
+    /// Half and double of the raw lag, when inside the searched range
+    pub harmonic_candidates: Vec<usize>,
+    /// Tempos of the onset intervals with `TempoEstimator::IoiFusion`, strongest first
+    pub tempo_hypotheses: Vec<TempoHypothesis>,
+}
+
+/// Settings of the coarse and fine searches, copied with each window so that the
+/// searches can run on the pipeline threads.
+#[derive(Debug, Clone, Copy)]
+pub struct SearchParams {
+    pub coarse_min_lag: usize,
+    pub coarse_max_lag: usize,
+    pub coarse_step: usize,
+    pub coarse_rate: f32,
+    pub fine_rate: f32,
+    pub thresholds: ConfidenceThreshold,
+    pub octave: OctavePreference,
+    pub drop_detection: bool,
+    pub estimator: TempoEstimator,
+}
+
+/// Scratch buffers of the coarse and fine searches.
+#[derive(Debug, Default)]
+pub struct SearchScratch {
+    coarse_vec: Vec<f32>,
+    coarse_centered: Vec<f32>,
+    fine_vec: Vec<f32>,
+    fine_centered: Vec<f32>,
+    fine_curve: Vec<f32>,
+    onset_strength: Vec<f32>,
+    comb_output: Vec<f32>,
+    onsets: Vec<ioi::Onset>,
+}
+
+/// Tempo found on one window, before the aubio cross-validation and the tempo tracker.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowEstimate {
+    pub bpm: f32,
+    pub confidence: f32,
+    pub coarse_confidence: f32,
+    pub is_drop: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -25,43 +215,152 @@ pub struct NormalizationResult {
     pub energy_mean: f32,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BpmAnalyzerConfig {
+    #[serde(rename = "window_ms", with = "duration_ms")]
     pub window_duration: Duration,
+    /// Audio queued by `push_samples` between two analyses, from `MIN_HOP`.
+    /// Shorter hops follow the music faster for more CPU.
+    #[serde(rename = "hop_ms", with = "duration_ms")]
+    pub hop_duration: Duration,
     pub min_bpm: f32,
     pub max_bpm: f32,
     pub thresholds: ConfidenceThreshold,
+    /// Drop detection (and the downbeat re-sync it triggers). Live band dynamics
+    /// produce constant false drops, so it can be turned off for non-electronic music.
+    pub drop_detection: bool,
+    /// Input filter applied before envelope extraction
+    pub filter: FilterType,
+    pub filter_order: FilterOrder,
+    /// Decimation of the rectified audio into the fine envelope, and of the fine
+    /// envelope into the coarse one
+    pub fine_downsampler: Downsampler,
+    pub coarse_downsampler: Downsampler,
+    /// Runs the coarse and fine searches on two threads, one window apart, so that
+    /// `process` only ingests audio. Results come one hop later.
+    pub pipelined: bool,
+    /// Detections away from the tracked tempo (and from its half and double) by
+    /// more than `tempo_change_tolerance` BPM that outweigh it in the tempo
+    /// tracker. The tolerance is also the spread of the detections around the
+    /// tempo.
+    pub tempo_change_hops: usize,
+    pub tempo_change_tolerance: f32,
+    /// Envelope normalization for inputs without hardware gain control
+    pub software_agc: SoftwareAgcConfig,
+    /// Coarse tempo search, the fine refinement is the same for all
+    pub estimator: TempoEstimator,
 }
 
 impl Default for BpmAnalyzerConfig {
     fn default() -> Self {
         Self {
             window_duration: Duration::from_millis(2000),
+            hop_duration: Duration::from_millis(500),
             min_bpm: 100.0,
             max_bpm: 310.0,
             thresholds: ConfidenceThreshold {
                 fine_confidence: 0.4,
                 coarse_confidence: 0.4,
             },
+            drop_detection: true,
+            filter: FilterType::BandPass(100.0, 500.0),
+            filter_order: FilterOrder::Order4,
+            fine_downsampler: Downsampler::Average,
+            coarse_downsampler: Downsampler::Average,
+            pipelined: false,
+            tempo_change_hops: 2,
+            tempo_change_tolerance: 3.0,
+            software_agc: SoftwareAgcConfig::default(),
+            estimator: TempoEstimator::Autocorrelation,
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-#[allow(dead_code)]
+impl BpmAnalyzerConfig {
+    /// Checks the settings a preset or the preferences can set: the lag search
+    /// needs a BPM range within 30..=400 and a window holding a beat at `min_bpm`.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_HOP..=MAX_HOP).contains(&self.hop_duration) {
+            return Err(format!(
+                "hop_ms must be in [{}, {}]",
+                MIN_HOP.as_millis(),
+                MAX_HOP.as_millis()
+            ));
+        }
+        if !(self.min_bpm >= 30.0 && self.min_bpm < self.max_bpm && self.max_bpm <= 400.0) {
+            return Err("BPM range must satisfy 30 <= min < max <= 400".to_string());
+        }
+        let thresholds = [
+            self.thresholds.fine_confidence,
+            self.thresholds.coarse_confidence,
+        ];
+        if !thresholds.iter().all(|t| (0.0..=1.0).contains(t)) {
+            return Err("Confidence thresholds must be between 0 and 1".to_string());
+        }
+        if self.window_duration.as_secs_f32() < 60.0 / self.min_bpm {
+            return Err(format!(
+                "window_ms must hold a beat at min_bpm ({:.0} ms)",
+                60_000.0 / self.min_bpm
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Durations are written as milliseconds in preset files
+mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(d.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_millis(u64::deserialize(d)?))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum FilterType {
     LowPass(f32),       // Cutoff
     HighPass(f32),      // Cutoff
     BandPass(f32, f32), // Low Cutoff, High Cutoff
 }
 
-#[derive(Clone, Copy, Debug)]
-#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum FilterOrder {
     Order2,
     Order4,
 }
-#[derive(Clone, Copy, Debug)]
+/// Octave the user wants when the correlation is ambiguous between x and 2x,
+/// e.g. `Double` pins DnB at 174 instead of 87.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OctavePreference {
+    Half,
+    #[default]
+    Normal,
+    Double,
+}
+
+/// How the coarse search finds the beat period in the envelope.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TempoEstimator {
+    /// Peak of the envelope autocorrelation
+    #[default]
+    Autocorrelation,
+    /// Strongest resonator of a comb filter bank fed with the envelope onsets (see
+    /// `comb`). Holds better on sparse percussion, where the autocorrelation
+    /// drowns in the energy between the hits.
+    CombFilter,
+    /// Autocorrelation peak checked against the tempos of the onset intervals
+    /// (see `ioi`), which settle the 3:2 and 4:3 ambiguities of syncopated
+    /// patterns where the correlation peaks on a dotted figure.
+    IoiFusion,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct ConfidenceThreshold {
     pub fine_confidence: f32,
     pub coarse_confidence: f32,
@@ -74,6 +373,8 @@ pub struct SamplingConfig {
     pub step: usize,
     pub min_lag: usize,
     pub max_lag: usize,
+    // Anti-aliasing decimator, chunk average when unset
+    decimator: Option<PolyphaseDecimator>,
 }
 impl SamplingConfig {
     pub fn new(rate: f32, duration: Duration, step: usize, min_bpm: f32, max_bpm: f32) -> Self {
@@ -87,7 +388,38 @@ impl SamplingConfig {
             step,
             min_lag,
             max_lag,
+            decimator: None,
+        }
+    }
+
+    pub fn with_downsampler(mut self, downsampler: Downsampler) -> Self {
+        self.decimator = match downsampler {
+            Downsampler::Average => None,
+            Downsampler::Polyphase => Some(PolyphaseDecimator::new(self.step)),
+        };
+        self
+    }
+
+    /// Maps every sample with `map` and decimates by `step` with the configured downsampler.
+    pub fn downsample<F>(&mut self, samples: &[f32], output: &mut Vec<f32>, mut map: F)
+    where
+        F: FnMut(f32) -> f32,
+    {
+        let Some(decimator) = &mut self.decimator else {
+            self.update_buffer(samples, output, |chunk| {
+                let sum: f32 = chunk.iter().map(|&x| map(x)).sum();
+                sum / chunk.len() as f32
+            });
+            return;
+        };
+
+        output.clear();
+        for &x in samples {
+            if let Some(y) = decimator.push(map(x)) {
+                output.push(y);
+            }
         }
+        self.push_samples(output);
     }
 
     pub fn update_buffer<F>(&mut self, samples: &[f32], output: &mut Vec<f32>, mut transform: F)
@@ -101,7 +433,12 @@ impl SamplingConfig {
             output.push(val);
         }
 
-        for &sample in output.iter() {
+        self.push_samples(output);
+    }
+
+    /// Appends already-downsampled values, dropping the oldest ones once the window is full.
+    pub fn push_samples(&mut self, samples: &[f32]) {
+        for &sample in samples {
             if self.buffer.len() >= self.buffer.capacity() {
                 self.buffer.pop_front();
             }
@@ -186,7 +523,7 @@ impl AudioFilter {
 
         Ok(Self { chain })
     }
-    fn process(&mut self, sample: f32) -> f32 {
+    pub fn process(&mut self, sample: f32) -> f32 {
         let mut out = sample;
         for filter in &mut self.chain {
             out = filter.run(out);
@@ -199,8 +536,12 @@ pub struct BpmAnalyzer {
     // Configuration
     pub config: BpmAnalyzerConfig,
 
-    // Structured history (BPM, Energy, Time)
-    history: VecDeque<BpmHistoryEntry>,
+    // Tempo decoded from the recent detections
+    tracker: TempoTracker,
+    // Smoothed swing, reset with the tracker
+    swing: Option<f32>,
+    // Tempo saved before a restart, seeded into the tracker on the next detection
+    restored_reference: Option<f32>,
 
     // Sampling Configs (Buffers + Rates)
     fine_config: SamplingConfig,
@@ -209,18 +550,46 @@ pub struct BpmAnalyzer {
 
     // Main Filter
     input_filter: AudioFilter,
+    // Envelope gain, with `software_agc.enabled`
+    software_agc: Option<SoftwareAgc>,
+    // Sub and mid/high band energies of the input, a drop needs the bass back
+    drop_detector: DropDetector,
+    // Band energies and onsets of each hop, and the section they point to
+    section_features: FeatureTracker,
+    sections: SectionClassifier,
 
     // Scratch buffers for memory optimization
-    scratch_fine_vec: Vec<f32>,
-    scratch_fine_centered: Vec<f32>,
-    scratch_coarse_vec: Vec<f32>,
-    scratch_coarse_centered: Vec<f32>,
+    scratch: SearchScratch,
     scratch_processing: Vec<f32>,
-    scratch_bpm_sort: Vec<f32>,
+    // 16-bit input converted by `process_i16`
+    scratch_input: Vec<f32>,
+    // Audio queued by `push_samples` for the next hop
+    pending: Vec<f32>,
+
+    // Coarse/fine threads when `config.pipelined` is set
+    pipeline: Option<AnalysisPipeline>,
+    // Bumped when buffers, resolution or octave change, to drop windows still in the pipeline
+    generation: u64,
+
+    // Last coarse search internals
+    debug: AnalysisDebug,
+
+    // Bias of the octave correction, set at runtime
+    octave: OctavePreference,
+
+    // Recent rejections and last aubio estimate, for the state dump
+    rejections: VecDeque<(Instant, Rejection)>,
+    aubio_estimate: (f32, f32),
 
     // Ajout : tempo aubio
     aubio_tempo: Tempo,
     aubio_hop_s: usize,
+    // Number of samples fed to aubio, used to place its last beat in our window
+    aubio_samples_fed: u64,
+    // Beat grid following aubio's onsets across hops, and the last onset it saw (s)
+    beat_pll: BeatPll,
+    pll_onset_s: f32,
+    sample_rate: u32,
 }
 
 impl BpmAnalyzer {
@@ -229,6 +598,8 @@ impl BpmAnalyzer {
         config: Option<BpmAnalyzerConfig>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         let config = config.unwrap_or_default();
+        config.validate()?;
+        let hop_samples = Self::hop_samples_at(&config, sample_rate);
 
         // Coarse-Fine Strategy
         // Fine Rate : ~11000 Hz (Precision/CPU Trade-off)
@@ -252,14 +623,16 @@ impl BpmAnalyzer {
             fine_step,
             config.min_bpm,
             config.max_bpm,
-        );
+        )
+        .with_downsampler(config.fine_downsampler);
         let coarse_config = SamplingConfig::new(
             coarse_rate,
             window_duration,
             coarse_step,
             config.min_bpm,
             config.max_bpm,
-        );
+        )
+        .with_downsampler(config.coarse_downsampler);
         let raw_config = SamplingConfig::new(
             fine_rate,
             window_duration,
@@ -267,12 +640,17 @@ impl BpmAnalyzer {
             config.min_bpm,
             config.max_bpm,
         );
-        // Main filter configuration : BandPass 100Hz - 200Hz
-        let input_filter = AudioFilter::new(
-            FilterType::BandPass(100.0, 500.0),
-            sample_rate as f32,
-            FilterOrder::Order4,
-        )?;
+        // Main filter configuration (default: BandPass 100Hz - 500Hz)
+        let input_filter =
+            AudioFilter::new(config.filter, sample_rate as f32, config.filter_order)?;
+        let software_agc = if config.software_agc.enabled {
+            config.software_agc.validate()?;
+            Some(SoftwareAgc::new(&config.software_agc, sample_rate as f32))
+        } else {
+            None
+        };
+        let drop_detector = DropDetector::new(sample_rate)?;
+        let section_features = FeatureTracker::new(sample_rate)?;
 
         // Taille de fenêtre raisonnable pour aubio (2048, hop 1024)
         // Calcule hop_s pour ~20ms, arrondi à la puissance de 2 la plus proche
@@ -289,32 +667,98 @@ impl BpmAnalyzer {
 
         aubio_tempo.set_threshold(0.1);
 
-        println!("BPM Analyzer Configured:");
-        println!("  Sample Rate: {} Hz", sample_rate);
-        println!("  Fine Rate: {:.2} Hz (Step {})", fine_rate, fine_step);
-        println!(
+        let pipeline = if config.pipelined {
+            Some(AnalysisPipeline::new()?)
+        } else {
+            None
+        };
+
+        info!("BPM Analyzer Configured:");
+        info!("  Sample Rate: {} Hz", sample_rate);
+        info!("  Fine Rate: {:.2} Hz (Step {})", fine_rate, fine_step);
+        info!(
             "  Coarse Rate: {:.2} Hz (Step {})",
             coarse_rate, coarse_step
         );
 
+        let tracker = TempoTracker::new(
+            config.min_bpm,
+            config.max_bpm,
+            config.tempo_change_tolerance,
+            config.tempo_change_hops,
+        );
+
         Ok(Self {
             config,
-            history: VecDeque::with_capacity(3),
+            tracker,
+            swing: None,
+            restored_reference: None,
             fine_config,
             coarse_config,
             raw_config,
             input_filter,
-            scratch_fine_vec: Vec::with_capacity(4096),
-            scratch_fine_centered: Vec::with_capacity(4096),
-            scratch_coarse_vec: Vec::with_capacity(1024),
-            scratch_coarse_centered: Vec::with_capacity(1024),
+            software_agc,
+            drop_detector,
+            section_features,
+            sections: SectionClassifier::new(),
+            scratch: SearchScratch {
+                coarse_vec: Vec::with_capacity(1024),
+                coarse_centered: Vec::with_capacity(1024),
+                fine_vec: Vec::with_capacity(4096),
+                fine_centered: Vec::with_capacity(4096),
+                fine_curve: Vec::with_capacity(128),
+                onset_strength: Vec::new(),
+                comb_output: Vec::new(),
+                onsets: Vec::new(),
+            },
             scratch_processing: Vec::with_capacity(1024),
-            scratch_bpm_sort: Vec::with_capacity(3),
+            scratch_input: Vec::new(),
+            pending: Vec::with_capacity(hop_samples),
+            pipeline,
+            generation: 0,
+            debug: AnalysisDebug::default(),
+            octave: OctavePreference::Normal,
+            rejections: VecDeque::with_capacity(REJECTION_LOG),
+            aubio_estimate: (0.0, 0.0),
             aubio_tempo,
             aubio_hop_s: hop_s,
+            aubio_samples_fed: 0,
+            beat_pll: BeatPll::new(),
+            pll_onset_s: 0.0,
+            sample_rate,
         })
     }
 
+    /// Returns the current coarse envelope with the beat grid overlaid.
+    /// Beat markers are only present once the tracker has a tempo.
+    pub fn envelope_frame(&self) -> EnvelopeFrame {
+        let buffer = &self.coarse_config.buffer;
+        let max = buffer.iter().cloned().fold(0.0, f32::max);
+        let samples: Vec<f32> = if max > 0.0 {
+            buffer.iter().map(|x| x / max).collect()
+        } else {
+            vec![0.0; buffer.len()]
+        };
+
+        let mut beat_markers = Vec::new();
+        let window_s = buffer.len() as f32 / self.coarse_config.rate;
+        if let (Some(bpm), Some(age)) = (self.tracker.tempo(), self.last_beat_age()) {
+            let period = 60.0 / bpm;
+            let mut age = age.as_secs_f32();
+            if period > 0.0 {
+                while age < window_s {
+                    beat_markers.push(1.0 - age / window_s);
+                    age += period;
+                }
+            }
+        }
+
+        EnvelopeFrame {
+            samples,
+            beat_markers,
+        }
+    }
+
     fn normalize_window(
         buffer: &VecDeque<f32>,
         out_vec: &mut Vec<f32>,
@@ -353,26 +797,162 @@ impl BpmAnalyzer {
         }
     }
 
+    /// Section of the track at the last hop, see `SectionClassifier`.
+    pub fn section(&self) -> Section {
+        self.sections.section()
+    }
+
+    /// Time elapsed between the last beat and the newest analyzed sample: beat of
+    /// the phase loop grid once locked, last beat detected by aubio before.
+    /// Returns `None` until aubio has reported a beat.
+    pub fn last_beat_age(&self) -> Option<Duration> {
+        match self.beat_pll.beat_age() {
+            Some(age) => Some(Duration::from_secs_f32(age)),
+            None => self.aubio_beat_age(),
+        }
+    }
+
+    // Age of aubio's last beat at the last sample it was fed
+    fn aubio_beat_age(&self) -> Option<Duration> {
+        let last_s = self.aubio_tempo.get_last_s();
+        if last_s <= 0.0 {
+            return None;
+        }
+        let fed_s = self.aubio_samples_fed as f32 / self.sample_rate as f32;
+        Some(Duration::from_secs_f32((fed_s - last_s).max(0.0)))
+    }
+
+    /// Snapshot of the tempo tracker, thresholds, buffer levels and recent
+    /// rejections, for the state dump and `restore`.
+    pub fn snapshot(&self) -> AnalyzerState {
+        let now = Instant::now();
+        let buffer = |name, config: &SamplingConfig| BufferFill {
+            name,
+            len: config.buffer.len(),
+            capacity: config.buffer.capacity(),
+            rate: config.rate,
+        };
+        AnalyzerState {
+            sample_rate: self.sample_rate,
+            history: self
+                .tracker
+                .observations()
+                .map(|o| (o.bpm, now.duration_since(o.at).as_secs_f32()))
+                .collect(),
+            reference_bpm: self.tracker.tempo(),
+            tracking_confidence: self.tracker.confidence(),
+            swing: self.swing,
+            pending_reference: self.restored_reference,
+            aubio_bpm: self.aubio_estimate.0,
+            aubio_confidence: self.aubio_estimate.1,
+            octave: self.octave,
+            config: self.config,
+            buffers: vec![
+                buffer("raw", &self.raw_config),
+                buffer("fine", &self.fine_config),
+                buffer("coarse", &self.coarse_config),
+            ],
+            raw_lag: self.debug.raw_lag,
+            chosen_lag: self.debug.chosen_lag,
+            rejections: self
+                .rejections
+                .iter()
+                .map(|&(at, reason)| RejectionRecord {
+                    reason,
+                    age_s: now.duration_since(at).as_secs_f32(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Resumes the tempo tracking of a `snapshot`, taken from this analyzer or
+    /// from another one (other sample rate or settings, previous run): tracked
+    /// detections and tempo, swing, octave preference and restored tempo. The
+    /// settings, buffered audio and beat phase stay those of this analyzer.
+    /// Detection ages count from now: the time between the snapshot and the
+    /// restore is not counted.
+    pub fn restore(&mut self, state: &AnalyzerState) {
+        let now = Instant::now();
+        self.set_octave_preference(state.octave);
+        let observations = state.history.iter().map(|&(bpm, age)| Observation {
+            bpm,
+            at: Duration::try_from_secs_f32(age)
+                .ok()
+                .and_then(|age| now.checked_sub(age))
+                .unwrap_or(now),
+        });
+        self.tracker
+            .restore(observations, state.reference_bpm, state.tracking_confidence);
+        self.swing = state.swing;
+        self.restored_reference = state.pending_reference;
+    }
+
+    // Records why the current hop gave no result
+    fn reject(&mut self, reason: Rejection) -> Option<AnalysisResult> {
+        if self.rejections.len() >= REJECTION_LOG {
+            self.rejections.pop_front();
+        }
+        self.rejections.push_back((Instant::now(), reason));
+        None
+    }
+
+    /// Returns the last coarse correlation curve and the lags picked from it.
+    pub fn debug(&self) -> &AnalysisDebug {
+        &self.debug
+    }
+
+    /// Biases the octave correction towards the slower or faster tempo.
+    /// The tracker is cleared so the new octave shows up on the next result.
+    pub fn set_octave_preference(&mut self, octave: OctavePreference) {
+        if self.octave != octave {
+            self.octave = octave;
+            self.tracker.clear();
+            self.generation += 1;
+        }
+    }
+
+    pub fn octave_preference(&self) -> OctavePreference {
+        self.octave
+    }
+
+    /// Tempo locked by the tempo tracker, `None` until it has decoded one.
+    pub fn reference_bpm(&self) -> Option<f32> {
+        self.tracker.tempo()
+    }
+
+    /// Changes the confidence thresholds from the next hop, without restarting.
+    pub fn set_thresholds(&mut self, thresholds: ConfidenceThreshold) {
+        self.config.thresholds = thresholds;
+    }
+
+    /// Level of the analysis window: mean square of the raw input over the
+    /// window, compared to `NOISE_GATE`.
+    pub fn input_level(&self) -> f32 {
+        // The raw buffer holds the mean square of each decimation chunk
+        self.raw_config.buffer.iter().sum::<f32>() / self.raw_config.buffer.len().max(1) as f32
+    }
+
+    /// Resumes from a tempo locked before a restart: the next detection finds it
+    /// in the tracker as if it had been detected, so it is reported until
+    /// `tempo_change_hops` detections of another tempo replace it.
+    pub fn restore_reference(&mut self, bpm: f32) {
+        self.restored_reference = Some(bpm);
+    }
+
     fn search_correlation(
-        &self,
         centered_signal: &[f32],
         energy: f32,
         min_lag: usize,
         max_lag: usize,
         min_confidence: f32,
+        out_curve: &mut Vec<f32>,
     ) -> Result<(usize, f32, f32), &'static str> {
         let safe_max_lag = centered_signal.len().saturating_sub(1);
         let start_lag = min_lag.max(1);
         let end_lag = max_lag.min(safe_max_lag);
 
         let mut corrs = vec![0.0; end_lag + 1];
-        for lag in start_lag..=end_lag {
-            let mut corr = 0.0;
-            for i in 0..(centered_signal.len() - lag) {
-                corr += centered_signal[i] * centered_signal[i + lag];
-            }
-            corrs[lag] = corr;
-        }
+        correlation::autocorrelation(centered_signal, start_lag, end_lag, &mut corrs);
 
         // Lissage par moyenne mobile (fenêtre 3)
         let mut corrs_smoothed = corrs.clone();
@@ -380,6 +960,16 @@ impl BpmAnalyzer {
             corrs_smoothed[lag] = (corrs[lag - 1] + corrs[lag] + corrs[lag + 1]) / 3.0;
         }
 
+        // Keep the normalized curve for the caller
+        out_curve.clear();
+        if start_lag <= end_lag {
+            out_curve.extend(
+                corrs_smoothed[start_lag..=end_lag]
+                    .iter()
+                    .map(|c| if energy > 0.0 { c / energy } else { 0.0 }),
+            );
+        }
+
         let mut best_lag = 0;
         let mut max_corr = 0.0;
         for lag in start_lag..=end_lag {
@@ -403,44 +993,132 @@ impl BpmAnalyzer {
         Ok((best_lag, confidence, max_corr))
     }
 
+    // Lag of the strongest comb resonator and its contrast. The octave correction
+    // stays on the autocorrelation, so the correlation at that lag comes along.
+    fn search_resonators(
+        normalized: &[f32],
+        centered_signal: &[f32],
+        params: &SearchParams,
+        onsets: &mut Vec<f32>,
+        output: &mut Vec<f32>,
+        out_curve: &mut Vec<f32>,
+    ) -> Result<(usize, f32, f32), &'static str> {
+        let min_lag = params.coarse_min_lag.max(1);
+        comb::onsets(normalized, params.coarse_rate, onsets);
+        comb::resonator_energies(
+            onsets,
+            params.coarse_rate,
+            min_lag,
+            params.coarse_max_lag,
+            output,
+            out_curve,
+        );
+
+        let Some((index, confidence)) = comb::best_resonator(out_curve) else {
+            return Err("No resonance found");
+        };
+        if confidence < params.thresholds.coarse_confidence {
+            return Err("Confidence too low");
+        }
+
+        let lag = min_lag + index;
+        let corr = Self::local_peak(centered_signal, lag, lag, lag).map_or(0.0, |(_, corr)| corr);
+        Ok((lag, confidence, corr))
+    }
+
+    // Weighs the correlation peak and its 3:2 and 4:3 neighbours by the IOI
+    // hypotheses backing them. Returns the neighbour that wins, if one does, with
+    // its correlation. The window confidence stays the one of the peak.
+    fn fuse_onset_intervals(
+        params: &SearchParams,
+        scratch: &mut SearchScratch,
+        debug: &mut AnalysisDebug,
+        peak_lag: usize,
+    ) -> Option<(usize, f32)> {
+        let rate = params.coarse_rate;
+        comb::onsets(&scratch.coarse_vec, rate, &mut scratch.onset_strength);
+        ioi::pick_onsets(&scratch.onset_strength, rate, &mut scratch.onsets);
+        debug.tempo_hypotheses = ioi::induce(
+            &scratch.onsets,
+            rate,
+            rate * 60.0 / params.coarse_max_lag as f32,
+            rate * 60.0 / params.coarse_min_lag.max(1) as f32,
+        );
+
+        // Best value of the normalized curve within two lags of `lag`
+        let curve_peak = |lag: usize| {
+            (lag.saturating_sub(2)..=lag + 2)
+                .filter_map(|l| {
+                    let value = debug.coarse_curve.get(l.checked_sub(debug.first_lag)?)?;
+                    Some((l, *value))
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+        };
+        let score = |(lag, value): (usize, f32)| {
+            let bpm = rate * 60.0 / lag as f32;
+            value * (1.0 + ioi::support(&debug.tempo_hypotheses, bpm, IOI_TOLERANCE))
+        };
+
+        let peak_score = score(curve_peak(peak_lag)?);
+        let (lag, _) = METRICAL_RATIOS
+            .iter()
+            .filter_map(|ratio| curve_peak((peak_lag as f32 * ratio).round() as usize))
+            .filter(|&candidate| candidate.1 > 0.0 && score(candidate) > peak_score)
+            .max_by(|a, b| score(*a).total_cmp(&score(*b)))?;
+        let corr = Self::local_peak(&scratch.coarse_centered, lag, lag, lag)?.1;
+        Some((lag, corr))
+    }
+
     fn check_harmonics(
-        &self,
+        octave: OctavePreference,
         initial_lag: usize,
         initial_corr: f32,
         centered_signal: &[f32],
         min_lag: usize,
+        max_lag: usize,
     ) -> usize {
-        let mut best_lag = initial_lag;
-
-        // 1. Check 2x BPM (Half Lag)
-        let half_lag = initial_lag / 2;
-        if half_lag >= min_lag {
-            // Recherche locale autour de half_lag (±5)
-            let mut max_half_corr = 0.0;
-            let mut best_half_lag = half_lag;
-            for lag in half_lag.saturating_sub(5)..=half_lag + 5 {
-                if lag < min_lag || lag >= centered_signal.len() {
-                    continue;
-                }
-                let mut corr = 0.0;
-                for i in 0..(centered_signal.len() - lag) {
-                    corr += centered_signal[i] * centered_signal[i + lag];
-                }
-                if corr > max_half_corr {
-                    max_half_corr = corr;
-                    best_half_lag = lag;
-                }
-            }
+        // Other octave to try, and the share of the initial peak it needs to be preferred
+        let (candidate, ratio) = match octave {
+            // 2x BPM (Half Lag)
+            OctavePreference::Normal => (initial_lag / 2, 0.5),
+            OctavePreference::Double => (initial_lag / 2, 0.25),
+            // BPM / 2 (Double Lag)
+            OctavePreference::Half => (initial_lag * 2, 0.5),
+        };
+
+        match Self::local_peak(centered_signal, candidate, min_lag, max_lag) {
+            Some((lag, corr)) if corr > initial_corr * ratio => lag,
+            _ => initial_lag,
+        }
+    }
 
-            if max_half_corr > (initial_corr * 0.5) {
-                best_lag = best_half_lag;
+    // Recherche locale du maximum de corrélation autour de `center` (±5)
+    fn local_peak(
+        centered_signal: &[f32],
+        center: usize,
+        min_lag: usize,
+        max_lag: usize,
+    ) -> Option<(usize, f32)> {
+        if center < min_lag || center > max_lag {
+            return None;
+        }
+        let mut best: Option<(usize, f32)> = None;
+        for lag in center.saturating_sub(5)..=center + 5 {
+            if lag < min_lag || lag > max_lag || lag >= centered_signal.len() {
+                continue;
+            }
+            let mut corr = 0.0;
+            for i in 0..(centered_signal.len() - lag) {
+                corr += centered_signal[i] * centered_signal[i + lag];
+            }
+            if best.is_none_or(|(_, c)| corr > c) {
+                best = Some((lag, corr));
             }
         }
-        best_lag
+        best
     }
 
     fn parabolic_interpolation(
-        &self,
         best_lag: usize,
         max_corr: f32,
         centered_signal: &[f32],
@@ -471,61 +1149,35 @@ impl BpmAnalyzer {
         refined_lag
     }
 
-    fn check_drop(&self, samples: &[f32], threshold: Option<f32>) -> bool {
-        let split_index = (samples.len()) / 2; // 50% of the buffer
-
-        let threshold = threshold.unwrap_or(1.3);
-
-        // 1. History Energy (0..75%)
-        let mut history_sum_sq = 0.0;
-        for i in 0..split_index {
-            let val = samples[i];
-            history_sum_sq += val * val;
-        }
-        let history_count = split_index.max(1);
-        let history_energy = history_sum_sq / history_count as f32;
-
-        // 2. Recent Energy (75%..100%)
-        let mut recent_sum_sq = 0.0;
-        for i in split_index..samples.len() {
-            let val = samples[i];
-            recent_sum_sq += val * val;
-        }
-        let recent_count = (samples.len() - split_index).max(1);
-        let current_energy = recent_sum_sq / recent_count as f32;
-
-        // 3. Detection
-        (current_energy > history_energy * threshold) && (current_energy > 0.04)
-    }
-
     pub fn process(
         &mut self,
         new_samples: &[f32],
     ) -> Result<Option<AnalysisResult>, Box<dyn std::error::Error>> {
         // 1. Filtering and Downsampling (Input -> Fine)
         self.fine_config
-            .update_buffer(new_samples, &mut self.scratch_processing, |chunk| {
-                let mut sum = 0.0;
-                for &x in chunk {
-                    // Apply filter
-                    let y = self.input_filter.process(x);
-                    sum += y.abs(); // Rectification
+            .downsample(new_samples, &mut self.scratch_processing, |x| {
+                // Apply filter, then rectification, then the software gain
+                let envelope = self.input_filter.process(x).abs();
+                match &mut self.software_agc {
+                    Some(agc) => agc.process(envelope),
+                    None => envelope,
                 }
-                sum / chunk.len() as f32
             });
 
         // 2. Downsampling (Fine -> Coarse)
-        // Use scratch_coarse_vec as temporary buffer for this step output
+        // Use the coarse scratch as temporary buffer for this step output
         // because it will be overwritten during coarse normalization right after.
-        self.coarse_config.update_buffer(
+        self.coarse_config.downsample(
             &self.scratch_processing,
-            &mut self.scratch_coarse_vec,
-            |chunk| {
-                let sum: f32 = chunk.iter().sum();
-                sum / chunk.len() as f32
-            },
+            &mut self.scratch.coarse_vec,
+            |x| x,
         );
 
+        if self.config.drop_detection {
+            self.drop_detector.push(new_samples);
+        }
+        self.section_features.push(new_samples);
+
         // 3. Update Raw Config (Input -> Raw)
         // Reuse scratch_processing as temporary buffer
         self.raw_config
@@ -537,106 +1189,537 @@ impl BpmAnalyzer {
                 sum_sq / chunk.len() as f32
             });
 
+        let fed_before = self.aubio_samples_fed;
+        let mut processed = if self.pipeline.is_some() {
+            self.analyze_pipelined(new_samples)
+        } else {
+            self.analyze(Some(new_samples))
+        };
+        let bpm = match &processed {
+            Ok(Some(result)) => Some(result.bpm),
+            _ => None,
+        };
+        self.track_phase(new_samples.len(), fed_before, bpm);
+        // Every hop, with or without a detection
+        let is_drop = matches!(&processed, Ok(Some(result)) if result.is_drop);
+        let section = self.sections.update(self.section_features.take(), is_drop);
+        if let Ok(Some(result)) = &mut processed {
+            result.section = section;
+            result.beat_offset = self
+                .beat_pll
+                .beat_age()
+                .filter(|_| result.is_drop)
+                .map(Duration::from_secs_f32);
+            result.beat_phase = self.beat_pll.stats();
+        }
+        processed
+    }
+
+    // Moves the beat phase loop over the hop, then pulls it towards the beat aubio
+    // found in it. Aubio is not fed on every hop, an onset is only fresh when it was.
+    fn track_phase(&mut self, new_samples: usize, fed_before: u64, bpm: Option<f32>) {
+        if let Some(bpm) = bpm {
+            self.beat_pll.set_tempo(bpm);
+        }
+        self.beat_pll
+            .advance(new_samples as f32 / self.sample_rate as f32);
+        let fed = self.aubio_samples_fed - fed_before;
+        let last_s = self.aubio_tempo.get_last_s();
+        if fed == 0 || last_s == self.pll_onset_s {
+            return;
+        }
+        self.pll_onset_s = last_s;
+        if let Some(age) = self.aubio_beat_age() {
+            // Tail of the hop shorter than an aubio hop, not fed yet
+            let unfed = (new_samples as u64).saturating_sub(fed) as f32 / self.sample_rate as f32;
+            self.beat_pll.observe(age.as_secs_f32() + unfed);
+        }
+    }
+
+    /// Queues capture audio for the next hop (see `hop_ready`).
+    pub fn push_samples(&mut self, samples: &[f32]) {
+        self.pending.extend_from_slice(samples);
+    }
+
+    fn hop_samples_at(config: &BpmAnalyzerConfig, sample_rate: u32) -> usize {
+        (sample_rate as f32 * config.hop_duration.as_secs_f32()) as usize
+    }
+
+    /// Whether `config.hop_duration` of audio is queued for `process_pending`.
+    pub fn hop_ready(&self) -> bool {
+        self.pending.len() >= Self::hop_samples_at(&self.config, self.sample_rate)
+    }
+
+    /// Samples queued by `push_samples`.
+    pub fn pending_samples(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Analyzes the queued audio at once, see `process`.
+    pub fn process_pending(
+        &mut self,
+    ) -> Result<Option<AnalysisResult>, Box<dyn std::error::Error>> {
+        let mut pending = std::mem::take(&mut self.pending);
+        let result = self.process(&pending);
+        pending.clear();
+        self.pending = pending;
+        result
+    }
+
+    /// Drops the queued audio (analysis paused or gated).
+    pub fn clear_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    /// `process` for 16-bit input (ALSA `S16_LE` capture, 16-bit WAV files). The
+    /// samples are scaled like cpal does into a buffer kept across calls, so the
+    /// hot path allocates nothing once it has grown to the hop size.
+    pub fn process_i16(
+        &mut self,
+        new_samples: &[i16],
+    ) -> Result<Option<AnalysisResult>, Box<dyn std::error::Error>> {
+        let mut input = std::mem::take(&mut self.scratch_input);
+        input.clear();
+        input.extend(new_samples.iter().map(|&x| x as f32 / 32768.0));
+        let result = self.process(&input);
+        self.scratch_input = input;
+        result
+    }
+
+    /// Check of the full analysis path: a synthetic click track at `bpm` goes through
+    /// a scratch analyzer with the same settings, and the same tempo must come out.
+    /// The live analyzer is left untouched. The check takes a few seconds of CPU:
+    /// the returned closure is meant to run off the analysis loop (`spawn_blocking`).
+    pub fn self_check(&self, bpm: f32) -> impl FnOnce() -> Result<f32, String> + Send + 'static {
+        let sample_rate = self.sample_rate;
+        // The check needs its result right away: run it without the pipeline
+        let config = BpmAnalyzerConfig {
+            pipelined: false,
+            ..self.config
+        };
+        let octave = self.octave;
+        move || {
+            let mut analyzer = BpmAnalyzer::new(sample_rate, Some(config))
+                .map_err(|e| format!("Analyzer error: {}", e))?;
+            analyzer.set_octave_preference(octave);
+            let hop = (sample_rate / 2) as usize;
+            let duration = config.window_duration + Duration::from_secs(2);
+            let total = (sample_rate as f32 * duration.as_secs_f32()) as usize;
+            let signal = testsig::click_track(sample_rate, bpm, total);
+
+            let mut detected = None;
+            for chunk in signal.chunks(hop) {
+                if let Some(result) = analyzer
+                    .process(chunk)
+                    .map_err(|e| format!("Analysis error: {}", e))?
+                {
+                    detected = Some(result.bpm);
+                }
+            }
+
+            match detected {
+                Some(found) if (found - bpm).abs() <= 1.0 => Ok(found),
+                Some(found) => Err(format!("Expected {:.1} BPM, detected {:.1}", bpm, found)),
+                None => Err(format!("No tempo detected on {:.1} BPM click track", bpm)),
+            }
+        }
+    }
+
+    /// Changes the coarse decimation and the analysis window, keeping the newest
+    /// buffered audio. The coarse buffer is rebuilt from the fine one.
+    /// A longer window needs to fill up again before results come back.
+    pub fn set_resolution(&mut self, coarse_step: usize, window_duration: Duration) {
+        let (min_bpm, max_bpm) = (self.config.min_bpm, self.config.max_bpm);
+        let fine_rate = self.fine_config.rate;
+        self.config.window_duration = window_duration;
+
+        let mut fine_config = SamplingConfig::new(
+            fine_rate,
+            window_duration,
+            self.fine_config.step,
+            min_bpm,
+            max_bpm,
+        );
+        let mut coarse_config = SamplingConfig::new(
+            fine_rate / coarse_step as f32,
+            window_duration,
+            coarse_step,
+            min_bpm,
+            max_bpm,
+        )
+        .with_downsampler(self.config.coarse_downsampler);
+        let mut raw_config = SamplingConfig::new(
+            self.raw_config.rate,
+            window_duration,
+            self.raw_config.step,
+            min_bpm,
+            max_bpm,
+        );
+
+        self.scratch_processing.clear();
+        self.scratch_processing
+            .extend(self.fine_config.buffer.iter().copied());
+        fine_config.push_samples(&self.scratch_processing);
+        // Same rate: the fine decimator state carries over
+        fine_config.decimator = self.fine_config.decimator.take();
+        coarse_config.downsample(
+            &self.scratch_processing,
+            &mut self.scratch.coarse_vec,
+            |x| x,
+        );
+
+        self.scratch_processing.clear();
+        self.scratch_processing
+            .extend(self.raw_config.buffer.iter().copied());
+        raw_config.push_samples(&self.scratch_processing);
+
+        self.fine_config = fine_config;
+        self.coarse_config = coarse_config;
+        self.raw_config = raw_config;
+        self.generation += 1;
+
+        info!(
+            "BPM Analyzer resolution: Coarse Rate {:.2} Hz (Step {}), Window {:?}",
+            self.coarse_config.rate, coarse_step, window_duration
+        );
+    }
+
+    /// Warm restart after an audio stream reset or a capture rate change, so that a
+    /// short device hiccup does not blank the tempo. Tempo tracker, restored tempo,
+    /// octave and swing are kept, windows still in the pipeline and the audio
+    /// queued for the next hop are dropped. At another rate the buffered envelopes
+    /// are resampled and the coarse one is rebuilt at the current resolution, so
+    /// results come back on the next hop instead of after a full window. The input
+    /// filter is redesigned for the new rate, the software gain keeps its level and
+    /// aubio starts over.
+    pub fn reset_soft(&mut self, sample_rate: u32) -> Result<(), Box<dyn std::error::Error>> {
+        self.generation += 1;
+        // Audio is missing around the restart, the beat phase is lost
+        self.beat_pll.reset();
+        self.pending.clear();
+        if sample_rate == self.sample_rate {
+            return Ok(());
+        }
+
+        let mut fresh = Self::new(sample_rate, Some(self.config))?;
+        let ratio = fresh.fine_config.rate / self.fine_config.rate;
+        let fine = Self::resample(&self.fine_config.buffer, ratio);
+        fresh.fine_config.push_samples(&fine);
+        let raw = Self::resample(&self.raw_config.buffer, ratio);
+        fresh.raw_config.push_samples(&raw);
+        // Resolution set by the governor
+        let coarse_step = self.coarse_config.step;
+        fresh.coarse_config = SamplingConfig::new(
+            fresh.fine_config.rate / coarse_step as f32,
+            self.config.window_duration,
+            coarse_step,
+            self.config.min_bpm,
+            self.config.max_bpm,
+        )
+        .with_downsampler(self.config.coarse_downsampler);
+        fresh
+            .coarse_config
+            .downsample(&fine, &mut fresh.scratch.coarse_vec, |x| x);
+
+        if let (Some(previous), Some(agc)) = (&self.software_agc, &mut fresh.software_agc) {
+            agc.resume_from(previous);
+        }
+        fresh.restore(&self.snapshot());
+        fresh.beat_pll = self.beat_pll.clone();
+        fresh.rejections = std::mem::take(&mut self.rejections);
+        // Band energies do not depend on the rate, the track goes on
+        fresh.sections = std::mem::take(&mut self.sections);
+        *self = fresh;
+        Ok(())
+    }
+
+    // Linear interpolation of an envelope to `ratio` times as many samples
+    fn resample(buffer: &VecDeque<f32>, ratio: f32) -> Vec<f32> {
+        let Some(last) = buffer.len().checked_sub(1) else {
+            return Vec::new();
+        };
+        let len = (buffer.len() as f32 * ratio).round() as usize;
+        (0..len)
+            .map(|i| {
+                let position = i as f32 / ratio;
+                let index = (position as usize).min(last);
+                let next = buffer[(index + 1).min(last)];
+                let frac = (position - index as f32).clamp(0.0, 1.0);
+                buffer[index] + (next - buffer[index]) * frac
+            })
+            .collect()
+    }
+
+    /// Sample rate expected by `process_envelope`.
+    pub fn fine_rate(&self) -> f32 {
+        self.fine_config.rate
+    }
+
+    /// Runs the tempo estimation on an onset envelope computed by an external front-end
+    /// (sampled at `fine_rate()`), skipping the filter and rectification stage.
+    /// The noise gate and the aubio cross-validation need raw audio and are bypassed,
+    /// drops are detected on the envelope alone.
+    pub fn process_envelope(
+        &mut self,
+        envelope: &[f32],
+    ) -> Result<Option<AnalysisResult>, Box<dyn std::error::Error>> {
+        // 1. Envelope goes straight into the fine buffer
+        self.fine_config.push_samples(envelope);
+
+        // 2. Downsampling (Fine -> Coarse)
+        self.coarse_config
+            .downsample(envelope, &mut self.scratch.coarse_vec, |x| x);
+
+        if self.config.drop_detection {
+            self.drop_detector
+                .push_envelope(envelope, self.fine_config.rate);
+        }
+
+        self.analyze(None)
+    }
+
+    /// Tempo estimation on the current fine/coarse buffers.
+    /// `raw_samples` is the audio that was just ingested, if any.
+    fn analyze(
+        &mut self,
+        raw_samples: Option<&[f32]>,
+    ) -> Result<Option<AnalysisResult>, Box<dyn std::error::Error>> {
+        if let Some(reason) = self.check_window(raw_samples.is_some()) {
+            return Ok(self.reject(reason));
+        }
+
+        let params = self.search_params();
+        let estimate = match Self::coarse_search(
+            &self.coarse_config.buffer,
+            &params,
+            &mut self.scratch,
+            &mut self.debug,
+        )
+        .and_then(|(lag, coarse_confidence)| {
+            Self::fine_search(
+                &self.fine_config.buffer,
+                lag,
+                coarse_confidence,
+                &params,
+                &mut self.scratch,
+            )
+        }) {
+            Ok(estimate) => estimate,
+            Err(reason) => return Ok(self.reject(reason)),
+        };
+
+        let aubio_bpm = match raw_samples {
+            Some(new_samples) => self.feed_aubio(new_samples),
+            None => 0.0,
+        };
+        Ok(self.accept(estimate, aubio_bpm))
+    }
+
+    /// Pipelined `analyze`: collects the windows the pipeline threads finished since
+    /// the last hop, then queues the current one.
+    fn analyze_pipelined(
+        &mut self,
+        new_samples: &[f32],
+    ) -> Result<Option<AnalysisResult>, Box<dyn std::error::Error>> {
+        let aubio_bpm = self.feed_aubio(new_samples);
+
+        let mut result = None;
+        while let Some(done) = self.pipeline.as_ref().and_then(|p| p.try_recv()) {
+            // Queued before a reset, resolution or octave change
+            if done.generation != self.generation {
+                continue;
+            }
+            self.debug = done.debug;
+            match done.estimate {
+                Ok(estimate) => result = self.accept(estimate, aubio_bpm).or(result),
+                Err(reason) => {
+                    self.reject(reason);
+                }
+            }
+        }
+
+        if let Some(reason) = self.check_window(true) {
+            self.reject(reason);
+            return Ok(result);
+        }
+        let window = AnalysisWindow {
+            coarse: self.coarse_config.buffer.clone(),
+            fine: self.fine_config.buffer.clone(),
+            params: self.search_params(),
+            generation: self.generation,
+        };
+        if self.pipeline.as_ref().is_some_and(|p| !p.submit(window)) {
+            self.reject(Rejection::PipelineBusy);
+        }
+        Ok(result)
+    }
+
+    // Why the current window can't be analyzed, if it can't
+    fn check_window(&self, has_audio: bool) -> Option<Rejection> {
         // Wait for buffer to be full
         if self.coarse_config.buffer.len() < self.coarse_config.buffer.capacity() {
-            return Ok(None);
+            return Some(Rejection::BufferFilling);
         }
 
         // ============================================================
         // NOISE GATE (Pre-Analysis)
         // ============================================================
         // Check if there is enough signal volume to justify analysis.
-        // We use the raw buffer (amplitude envelope) to check the input level.
-        let raw_level =
-            self.raw_config.buffer.iter().sum::<f32>() / self.raw_config.buffer.len().max(1) as f32;
+        // Below the gate, we consider it silence/noise.
+        if has_audio && self.input_level() < NOISE_GATE {
+            return Some(Rejection::Silence);
+        }
+        None
+    }
 
-        // Threshold: 0.005 (approx -46dB). Below this, we consider it silence/noise.
-        if raw_level < 0.005 {
-            return Ok(None);
+    fn search_params(&self) -> SearchParams {
+        SearchParams {
+            coarse_min_lag: self.coarse_config.min_lag,
+            coarse_max_lag: self.coarse_config.max_lag,
+            coarse_step: self.coarse_config.step,
+            coarse_rate: self.coarse_config.rate,
+            fine_rate: self.fine_config.rate,
+            thresholds: self.config.thresholds,
+            octave: self.octave,
+            drop_detection: self.config.drop_detection,
+            estimator: self.config.estimator,
         }
+    }
 
+    /// Coarse lag search with octave correction on a coarse window.
+    /// Returns the lag (in coarse samples) and its confidence.
+    pub fn coarse_search(
+        coarse: &VecDeque<f32>,
+        params: &SearchParams,
+        scratch: &mut SearchScratch,
+        debug: &mut AnalysisDebug,
+    ) -> Result<(usize, f32), Rejection> {
         // ============================================================
         // STEP 1 : COARSE SEARCH
         // ============================================================
 
         let norm_res_coarse = Self::normalize_window(
-            &self.coarse_config.buffer,
-            &mut self.scratch_coarse_vec,
-            &mut self.scratch_coarse_centered,
+            coarse,
+            &mut scratch.coarse_vec,
+            &mut scratch.coarse_centered,
         );
 
         if norm_res_coarse.energy_mean <= 0.001 {
-            return Ok(None);
-        }
-
-        let (best_lag_c, coarse_conf, max_corr_c) = match self.search_correlation(
-            &self.scratch_coarse_centered,
-            norm_res_coarse.energy_sum,
-            self.coarse_config.min_lag,
-            self.coarse_config.max_lag,
-            self.config.thresholds.coarse_confidence,
-        ) {
-            Ok(res) => res,
-            Err(_) => return Ok(None),
+            return Err(Rejection::NoEnergy);
+        }
+
+        debug.estimator = params.estimator;
+        debug.first_lag = params.coarse_min_lag.max(1);
+        debug.coarse_rate = params.coarse_rate;
+        debug.raw_lag = None;
+        debug.chosen_lag = None;
+        debug.harmonic_candidates.clear();
+        debug.tempo_hypotheses.clear();
+
+        let (best_lag_c, coarse_conf, max_corr_c) = match params.estimator {
+            TempoEstimator::Autocorrelation | TempoEstimator::IoiFusion => {
+                Self::search_correlation(
+                    &scratch.coarse_centered,
+                    norm_res_coarse.energy_sum,
+                    params.coarse_min_lag,
+                    params.coarse_max_lag,
+                    params.thresholds.coarse_confidence,
+                    &mut debug.coarse_curve,
+                )
+            }
+            TempoEstimator::CombFilter => Self::search_resonators(
+                &scratch.coarse_vec,
+                &scratch.coarse_centered,
+                params,
+                &mut scratch.onset_strength,
+                &mut scratch.comb_output,
+                &mut debug.coarse_curve,
+            ),
+        }
+        .map_err(Rejection::Coarse)?;
+
+        let (best_lag_c, max_corr_c) = match params.estimator {
+            TempoEstimator::IoiFusion => {
+                Self::fuse_onset_intervals(params, scratch, debug, best_lag_c)
+                    .unwrap_or((best_lag_c, max_corr_c))
+            }
+            _ => (best_lag_c, max_corr_c),
         };
 
         // Correction d'octave sur le lag coarse (avant passage au fin, value);
-        let best_lag_c_harm = self.check_harmonics(
+        let best_lag_c_harm = Self::check_harmonics(
+            params.octave,
             best_lag_c,
             max_corr_c,
-            &self.scratch_coarse_centered,
-            self.coarse_config.min_lag,
+            &scratch.coarse_centered,
+            params.coarse_min_lag,
+            params.coarse_max_lag,
+        );
+        let last_lag = debug.first_lag + debug.coarse_curve.len();
+        debug.raw_lag = Some(best_lag_c);
+        debug.chosen_lag = Some(best_lag_c_harm);
+        let first_lag = debug.first_lag;
+        debug.harmonic_candidates.extend(
+            [best_lag_c / 2, best_lag_c * 2]
+                .into_iter()
+                .filter(|&lag| lag >= first_lag && lag < last_lag),
         );
-        let best_lag_c = best_lag_c_harm;
+        Ok((best_lag_c_harm, coarse_conf))
+    }
+
+    /// Refines the coarse lag on the fine window and checks it for a drop.
+    pub fn fine_search(
+        fine: &VecDeque<f32>,
+        coarse_lag: usize,
+        coarse_confidence: f32,
+        params: &SearchParams,
+        scratch: &mut SearchScratch,
+    ) -> Result<WindowEstimate, Rejection> {
         // ============================================================
         // STEP 2 : REFINEMENT (FINE)
         // ============================================================
 
         // Convert Coarse Lag to Fine
         // Ratio = fine_rate / coarse_rate = coarse_step
-        let center_lag_f = best_lag_c * self.coarse_config.step;
+        let center_lag_f = coarse_lag * params.coarse_step;
 
         // Fine search window
         let search_radius = 50;
         let min_lag_f = center_lag_f.saturating_sub(search_radius);
         let max_lag_f = center_lag_f + search_radius;
 
-        let norm_res_fine = Self::normalize_window(
-            &self.fine_config.buffer,
-            &mut self.scratch_fine_vec,
-            &mut self.scratch_fine_centered,
-        );
+        let norm_res_fine =
+            Self::normalize_window(fine, &mut scratch.fine_vec, &mut scratch.fine_centered);
 
         // Ensure we stay within buffer bounds
-        let safe_max_lag = self.scratch_fine_centered.len().saturating_sub(1);
+        let safe_max_lag = scratch.fine_centered.len().saturating_sub(1);
         let start_lag = min_lag_f.max(1);
         let end_lag = max_lag_f.min(safe_max_lag);
 
-        let (best_lag_f, confidence, max_corr_f) = match self.search_correlation(
-            &self.scratch_fine_centered,
+        let (best_lag_f, confidence, max_corr_f) = Self::search_correlation(
+            &scratch.fine_centered,
             norm_res_fine.energy_sum,
             min_lag_f,
             max_lag_f,
-            self.config.thresholds.fine_confidence,
-        ) {
-            Ok(res) => res,
-            Err(_) => return Ok(None),
-        };
+            params.thresholds.fine_confidence,
+            &mut scratch.fine_curve,
+        )
+        .map_err(Rejection::Fine)?;
 
         // ============================================================
         // STEP 3 : PARABOLIC INTERPOLATION
         // ============================================================
 
-        let refined_lag = self.parabolic_interpolation(
+        let refined_lag = Self::parabolic_interpolation(
             best_lag_f,
             max_corr_f,
-            &self.scratch_fine_centered,
+            &scratch.fine_centered,
             start_lag,
             end_lag,
         );
 
         // Final BPM calculation rounded to nearest 0.1
-        let bpm = (self.fine_config.rate * 60.0 / refined_lag * 10.0).round() / 10.0;
+        let bpm = (params.fine_rate * 60.0 / refined_lag * 10.0).round() / 10.0;
 
         // ============================================================
         // DROP DETECTION (IMPROVED - Intra-Window Comparison)
@@ -644,35 +1727,65 @@ impl BpmAnalyzer {
         // Calculate Drop BEFORE validating BPM for history
         // Increase threshold (1.5 instead of 1.3) and require minimal confidence
 
-        let is_drop = confidence > 0.6 && self.check_drop(&self.scratch_fine_vec, Some(1.4));
+        let is_drop = params.drop_detection
+            && confidence > 0.6
+            && drop_detector::envelope_surge(&scratch.fine_vec, 1.4);
 
-        // ============================================================
-        // HISTORY MANAGEMENT AND SMOOTHING
-        // ============================================================
-
-        let now = Instant::now();
-        // 1. Reset if prolonged silence (> 10s)
-        if let Some(last_entry) = self.history.back() {
-            if now.duration_since(last_entry.timestamp).as_secs_f32() > 10.0 {
-                self.history.clear();
-            }
-        }
+        Ok(WindowEstimate {
+            bpm,
+            confidence,
+            coarse_confidence,
+            is_drop,
+        })
+    }
 
-        // Met à jour aubio avec les nouvelles données entrantes
+    // Met à jour aubio avec les nouvelles données entrantes et retourne son tempo
+    // le plus confiant sur ces données (0 si aucun)
+    fn feed_aubio(&mut self, new_samples: &[f32]) -> f32 {
         // On découpe new_samples en tranches de hop_s pour alimenter aubio correctement
-        let mut idx = 0;
         let (mut aubio_bpm, mut aubio_confidence) = (0.0, 0.0);
+        let mut idx = 0;
         while idx + self.aubio_hop_s <= new_samples.len() {
             let slice = &new_samples[idx..idx + self.aubio_hop_s];
             if let Err(e) = self.aubio_tempo.do_result(slice) {
-                eprintln!("[aubio] Erreur do_result: {}", e);
+                error!("[aubio] Erreur do_result: {}", e);
             }
+            self.aubio_samples_fed += self.aubio_hop_s as u64;
             if self.aubio_tempo.get_confidence() > aubio_confidence {
                 aubio_confidence = self.aubio_tempo.get_confidence();
                 aubio_bpm = self.aubio_tempo.get_bpm();
             }
             idx += self.aubio_hop_s;
         }
+        self.aubio_estimate = (aubio_bpm, aubio_confidence);
+        aubio_bpm
+    }
+
+    // Cross-validates a window estimate with aubio and tracks the tempo through it
+    fn accept(&mut self, estimate: WindowEstimate, aubio_bpm: f32) -> Option<AnalysisResult> {
+        let bpm = estimate.bpm;
+
+        // ============================================================
+        // TEMPO TRACKING
+        // ============================================================
+
+        let now = Instant::now();
+        // 1. Start over after a prolonged silence
+        if let Some(last) = self.tracker.observations().last()
+            && now.duration_since(last.at) > TRACKING_TIMEOUT
+        {
+            self.tracker.clear();
+            self.swing = None;
+        }
+
+        // Tempo restored after a restart
+        if let Some(reference) = self
+            .restored_reference
+            .take()
+            .filter(|_| self.tracker.is_empty())
+        {
+            self.tracker.seed(reference, now);
+        }
 
         // --- Validation croisée autocorrélation / aubio ---
         if aubio_bpm != 0.0 {
@@ -686,46 +1799,55 @@ impl BpmAnalyzer {
             }
             if !bpm_valid {
                 // Les BPM ne correspondent pas, on ne valide pas la détection
-                return Ok(None);
+                return self.reject(Rejection::AubioMismatch { bpm, aubio_bpm });
             }
         }
 
-        // 5. Update history
-        if self.history.len() >= 3 {
-            self.history.pop_front();
-        }
-        self.history.push_back(BpmHistoryEntry {
-            bpm: bpm,
-            timestamp: now,
+        // 2. Decode the tempo with this detection
+        let tracked = self.tracker.update(bpm, now);
+        let tempo_change = tracked.changed_from.map(|from| TempoChange {
+            from,
+            to: tracked.bpm,
         });
+        if tempo_change.is_some() {
+            self.swing = None;
+        }
+        let swing = self.update_swing(bpm);
+
+        Some(AnalysisResult {
+            bpm: tracked.bpm,
+            coarse_confidence: estimate.coarse_confidence,
+            // The envelope surged in the window, and the sub band with it
+            is_drop: estimate.is_drop && self.drop_detector.is_bass_return(),
+            confidence: estimate.confidence,
+            // Filled by `process` from the beat phase loop
+            beat_offset: None,
+            beat_phase: None,
+            tempo_change,
+            swing,
+            tracking_confidence: tracked.confidence,
+            // Filled by `process`
+            section: Section::default(),
+            key: None,
+            verified_bpm: Some(tracked.bpm),
+        })
+    }
 
-        // 6. Calculate smoothed values
-        // Median BPM
-        self.scratch_bpm_sort.clear();
-        self.scratch_bpm_sort
-            .extend(self.history.iter().map(|e| e.bpm));
-        self.scratch_bpm_sort
-            .sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-
-        let smoothed_bpm = if !self.scratch_bpm_sort.is_empty() {
-            self.scratch_bpm_sort[self.scratch_bpm_sort.len() / 2]
-        } else {
-            bpm
+    // Swing of the window at the detected tempo, smoothed over the hops
+    fn update_swing(&mut self, bpm: f32) -> Option<f32> {
+        self.scratch_processing.clear();
+        self.scratch_processing
+            .extend(self.raw_config.buffer.iter().copied());
+        let Some(swing) =
+            groove::estimate_swing(&self.scratch_processing, self.raw_config.rate, bpm)
+        else {
+            return self.swing;
         };
-
-        // Utilise le dernier beat détecté par aubio pour la resynchronisation
-        let beat_offset = if is_drop {
-            Some(Duration::from_secs_f32(self.aubio_tempo.get_last_s()))
-        } else {
-            None
+        let smoothed = match self.swing {
+            Some(previous) => previous + SWING_SMOOTHING * (swing - previous),
+            None => swing,
         };
-
-        Ok(Some(AnalysisResult {
-            bpm: smoothed_bpm,
-            coarse_confidence: coarse_conf,
-            is_drop,
-            confidence,
-            beat_offset,
-        }))
+        self.swing = Some(smoothed);
+        self.swing
     }
 }
@@ -1,15 +1,29 @@
+pub use super::audio_channel::AudioMessage;
+use super::audio_channel::AudioSender;
+use super::meter::LevelMeter;
+use super::monitor::{MonitorConfig, MonitorMix, MonitorSource};
+use super::simulated::{SIMULATED_DEVICE, SimulatedSource};
 use cpal::Sample;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use std::collections::VecDeque;
-use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, channel};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
 
-pub enum AudioMessage {
-    Samples(Vec<f32>),
-    Reset,
-    SampleRateChanged(u32),
-}
+/// Pseudo device capturing what the system is playing (WASAPI loopback on the
+/// default output). On macOS and Linux, route the output to a virtual input
+/// (BlackHole, PulseAudio/PipeWire monitor source) and pick that input instead.
+pub const SYSTEM_AUDIO_DEVICE: &str = "System Audio";
+
+// Interval between checks that the selected device is plugged in again, while
+// capturing the default input instead
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+// Packets of the simulated device, about the size of a capture callback
+const SIMULATED_PACKET: Duration = Duration::from_millis(10);
+/// Interval of the `AudioMessage::Level` measurements
+pub const METER_PERIOD: Duration = Duration::from_millis(50);
 
 #[derive(Clone, Copy)]
 pub struct PolicyAudioRestart {
@@ -31,49 +45,59 @@ impl Default for PolicyAudioRestart {
 enum ControlMessage {
     Stop,
     Error(String),
+    // The device of the stream went away (unplugged)
+    DeviceLost(String),
 }
 pub struct AudioCapture {
     control_sender: Sender<ControlMessage>,
     thread_handle: Option<thread::JoinHandle<()>>,
     device_name: Option<String>,
     // Fields needed for restarting
-    data_sender: Sender<AudioMessage>,
+    data_sender: AudioSender,
     sample_rate: u32,
     restart_policy: PolicyAudioRestart,
     buffer_duration: Option<Duration>,
+    warmup_duration: Option<Duration>,
 }
 struct AudioWorker {
-    data_sender: Sender<AudioMessage>,
+    data_sender: AudioSender,
     control_sender: Sender<ControlMessage>,
     control_receiver: Receiver<ControlMessage>,
     device_name: Option<String>,
+    // Selected device is missing, capturing the default input instead
+    fallback: bool,
     error_count: u32,
     crash_timestamps: VecDeque<Instant>,
     sample_rate: u32,
     restart_policy: PolicyAudioRestart,
     buffer_duration: Option<Duration>,
+    warmup_duration: Option<Duration>,
 }
 
 impl AudioWorker {
+    #[allow(clippy::too_many_arguments)]
     fn new(
-        data_sender: Sender<AudioMessage>,
+        data_sender: AudioSender,
         control_sender: Sender<ControlMessage>,
         control_receiver: Receiver<ControlMessage>,
         device_name: Option<String>,
         sample_rate: u32,
         restart_policy: PolicyAudioRestart,
         buffer_duration: Option<Duration>,
+        warmup_duration: Option<Duration>,
     ) -> Self {
         Self {
             data_sender,
             control_sender,
             control_receiver,
             device_name,
+            fallback: false,
             error_count: 0,
             crash_timestamps: VecDeque::with_capacity(restart_policy.max_restarts),
             sample_rate,
             restart_policy,
             buffer_duration,
+            warmup_duration,
         }
     }
 
@@ -95,43 +119,72 @@ impl AudioWorker {
     }
 
     fn run(&mut self) {
+        let simulated = self
+            .device_name
+            .as_deref()
+            .and_then(|name| SimulatedSource::open(name, self.sample_rate));
+        if let Some(source) = simulated {
+            match source {
+                Ok(source) => self.run_simulated(source),
+                Err(e) => error!("Simulated input unavailable: {}", e),
+            }
+            return;
+        }
         loop {
             match self.initialize_stream() {
                 Ok(stream) => {
-                    println!("Audio stream started successfully.");
+                    info!("Audio stream started successfully.");
 
-                    match self.control_receiver.recv() {
-                        Ok(ControlMessage::Stop) => {
-                            println!("Stopping audio capture...");
-                            break;
-                        }
-                        Ok(ControlMessage::Error(e)) => {
-                            self.error_count += 1;
-                            eprintln!(
-                                "Stream error (count: {}): {}. Restarting...",
-                                self.error_count, e
-                            );
-                            if self.should_stop_restarting() {
-                                eprintln!(
-                                    "Too many errors in short time (5 errors in < 3s). Stopping."
+                    loop {
+                        match self.control_receiver.recv_timeout(DEVICE_POLL_INTERVAL) {
+                            Ok(ControlMessage::Stop) => {
+                                info!("Stopping audio capture...");
+                                return;
+                            }
+                            Ok(ControlMessage::DeviceLost(e)) if self.fall_back(&e) => break,
+                            Ok(ControlMessage::Error(e) | ControlMessage::DeviceLost(e)) => {
+                                self.error_count += 1;
+                                warn!(
+                                    "Stream error (count: {}): {}. Restarting...",
+                                    self.error_count, e
                                 );
+                                if self.should_stop_restarting() {
+                                    error!(
+                                        "Too many errors in short time (5 errors in < 3s). Stopping."
+                                    );
+                                    return;
+                                }
                                 break;
                             }
+                            Err(RecvTimeoutError::Timeout) => {
+                                if self.selected_device_back() {
+                                    break;
+                                }
+                            }
+                            Err(RecvTimeoutError::Disconnected) => return,
                         }
-                        Err(_) => break,
                     }
                     drop(stream);
                 }
                 Err(e) => {
+                    // Unplugged device: switch to the default input right away
+                    if self
+                        .selected_device()
+                        .is_some_and(|name| !device_listed(name))
+                        && self.fall_back(&e.to_string())
+                    {
+                        continue;
+                    }
+
                     self.error_count += 1;
                     let delay = self.restart_policy.retry_delay;
-                    eprintln!(
+                    warn!(
                         "Failed to initialize stream (count: {}): {}. Retrying in {:?}...",
                         self.error_count, e, delay
                     );
 
                     if self.should_stop_restarting() {
-                        eprintln!("Too many errors in short time. Stopping.");
+                        error!("Too many errors in short time. Stopping.");
                         break;
                     }
 
@@ -149,10 +202,94 @@ impl AudioWorker {
         }
     }
 
+    /// Sends the simulated audio in real time until stopped.
+    fn run_simulated(&mut self, mut source: SimulatedSource) {
+        let name = self.device_name.clone().unwrap_or_default();
+        info!("Input device: {} (no capture)", name);
+        self.data_sender.set_channels(1);
+        let _ = self.data_sender.send(AudioMessage::Reset);
+        let _ = self.data_sender.send(AudioMessage::DeviceChanged(name));
+        let _ = self
+            .data_sender
+            .send(AudioMessage::SampleRateChanged(source.sample_rate()));
+
+        let packet_len =
+            (source.sample_rate() as f64 * SIMULATED_PACKET.as_secs_f64()).max(1.0) as usize;
+        let mut meter = LevelMeter::new(source.sample_rate(), 1, METER_PERIOD);
+        let mut next_packet = Instant::now();
+        loop {
+            let packet = source.next_packet(packet_len);
+            let level = meter.push(&packet);
+            if self
+                .data_sender
+                .send(AudioMessage::Samples(packet))
+                .is_err()
+            {
+                return;
+            }
+            if let Some((peak, rms)) = level {
+                let _ = self.data_sender.send(AudioMessage::Level { peak, rms });
+            }
+            // Paced on a fixed schedule so the stream does not drift from real time
+            next_packet += SIMULATED_PACKET;
+            let wait = next_packet.saturating_duration_since(Instant::now());
+            match self.control_receiver.recv_timeout(wait) {
+                Ok(ControlMessage::Stop) | Err(RecvTimeoutError::Disconnected) => {
+                    info!("Stopping audio capture...");
+                    return;
+                }
+                Ok(ControlMessage::Error(_) | ControlMessage::DeviceLost(_))
+                | Err(RecvTimeoutError::Timeout) => {}
+            }
+        }
+    }
+
+    // Device picked by the user, `None` for the default input and the loopback
+    fn selected_device(&self) -> Option<&str> {
+        self.device_name
+            .as_deref()
+            .filter(|&n| n != SYSTEM_AUDIO_DEVICE)
+    }
+
+    /// Switches to the default input once the selected device is gone. Returns
+    /// true if the stream must be restarted.
+    fn fall_back(&mut self, reason: &str) -> bool {
+        let Some(name) = self.selected_device().filter(|_| !self.fallback) else {
+            return false;
+        };
+        warn!(
+            "Device '{}' disappeared ({}), falling back to default input",
+            name, reason
+        );
+        self.fallback = true;
+        true
+    }
+
+    /// While on the default input, whether the selected device is listed again.
+    /// Only used that way round: ALSA leaves out the devices it finds busy, the
+    /// one being captured included, so a missing name does not mean unplugged.
+    /// Stream errors tell that instead (`ControlMessage::DeviceLost`).
+    fn selected_device_back(&mut self) -> bool {
+        let Some(name) = self.selected_device().filter(|_| self.fallback) else {
+            return false;
+        };
+        if !device_listed(name) {
+            return false;
+        }
+        info!("Device '{}' is back, switching to it", name);
+        self.fallback = false;
+        true
+    }
+
     fn initialize_stream(&self) -> Result<cpal::Stream, Box<dyn std::error::Error>> {
         let host = cpal::default_host();
 
-        let device = if let Some(name) = &self.device_name {
+        let loopback = self.device_name.as_deref() == Some(SYSTEM_AUDIO_DEVICE);
+        let device = if loopback {
+            // cpal opens an input stream on an output device in loopback mode (WASAPI)
+            host.default_output_device()
+                .ok_or("No output device available for loopback")?
+        } else if let Some(name) = self.device_name.as_ref().filter(|_| !self.fallback) {
             host.input_devices()?
                 .find(|d| d.name().map(|n| n == *name).unwrap_or(false))
                 .ok_or(format!("Device '{}' not found", name))?
@@ -161,10 +298,17 @@ impl AudioWorker {
                 .ok_or("No input device available")?
         };
 
-        println!("Input device: {}", device.name()?);
+        info!(
+            "Input device: {}{}",
+            device.name()?,
+            if loopback { " (loopback)" } else { "" }
+        );
         let target_sample_rate = cpal::SampleRate(self.sample_rate);
-        let supported_configs = device.supported_input_configs()?;
-        let configs: Vec<_> = supported_configs.collect();
+        let configs: Vec<_> = if loopback {
+            device.supported_output_configs()?.collect()
+        } else {
+            device.supported_input_configs()?.collect()
+        };
 
         let mut best_config = None;
         let mut min_diff = u32::MAX;
@@ -208,13 +352,13 @@ impl AudioWorker {
         let supported_config = match best_config {
             Some(c) => c.with_sample_rate(selected_rate),
             None => {
-                eprintln!("Error: No supported configuration found.");
+                error!("Error: No supported configuration found.");
                 return Err("No supported input config found".into());
             }
         };
 
         if selected_rate != target_sample_rate {
-            println!(
+            info!(
                 "Requested sample rate {} Hz not supported. Using closest: {} Hz",
                 target_sample_rate.0, selected_rate.0
             );
@@ -229,7 +373,7 @@ impl AudioWorker {
                 cpal::SupportedBufferSize::Range { min, max } => {
                     let frames = requested_frames.clamp(*min, *max);
                     if frames != requested_frames {
-                        println!(
+                        info!(
                             "Buffer size adjusted to match device capabilities: {} -> {}",
                             requested_frames, frames
                         );
@@ -245,12 +389,18 @@ impl AudioWorker {
         let mut config: cpal::StreamConfig = supported_config.into();
         config.buffer_size = buffer_size;
 
-        println!("Selected input config: {:?}", config);
+        info!("Selected input config: {:?}", config);
 
         let control_sender = self.control_sender.clone();
-        let err_fn = move |err| {
-            eprintln!("an error occurred on stream: {}", err);
-            let _ = control_sender.send(ControlMessage::Error(format!("{}", err)));
+        let err_fn = move |err: cpal::StreamError| {
+            error!("an error occurred on stream: {}", err);
+            let message = match err {
+                cpal::StreamError::DeviceNotAvailable => {
+                    ControlMessage::DeviceLost(err.to_string())
+                }
+                _ => ControlMessage::Error(err.to_string()),
+            };
+            let _ = control_sender.send(message);
         };
 
         let stream = match sample_format {
@@ -298,18 +448,44 @@ impl AudioWorker {
     {
         let sender = self.data_sender.clone();
 
+        // Warm-up: some codecs output garbage right after power-up,
+        // so the first samples of each stream are consumed but never forwarded
+        let mut warmup_samples = self
+            .warmup_duration
+            .map(|d| {
+                (config.sample_rate.0 as f64 * d.as_secs_f64()) as usize * config.channels as usize
+            })
+            .unwrap_or(0);
+        if warmup_samples > 0 {
+            info!("Audio warm-up: muting first {:?}", self.warmup_duration);
+        }
+
         // Notify main thread that a new stream is starting
+        sender.set_channels(config.channels);
         let _ = sender.send(AudioMessage::Reset);
+        let _ = sender.send(AudioMessage::DeviceChanged(device.name()?));
         // Notify about the actual sample rate being used
         let _ = sender.send(AudioMessage::SampleRateChanged(config.sample_rate.0));
 
+        let mut meter = LevelMeter::new(config.sample_rate.0, config.channels, METER_PERIOD);
         let stream = device.build_input_stream(
             config,
             move |data: &[T], _: &_| {
-                let buffer: Vec<f32> = data.iter().map(|&s| f32::from_sample(s)).collect();
+                if warmup_samples > 0 {
+                    warmup_samples = warmup_samples.saturating_sub(data.len());
+                    return;
+                }
+                // Converted in place into a buffer given back by the consumer
+                let mut buffer = sender.buffer();
+                buffer.extend(data.iter().map(|&s| f32::from_sample(s)));
+                let level = meter.push(&buffer);
 
                 if let Err(_e) = sender.send(AudioMessage::Samples(buffer)) {
                     // Receiver dropped, stop sending
+                    return;
+                }
+                if let Some((peak, rms)) = level {
+                    let _ = sender.send(AudioMessage::Level { peak, rms });
                 }
             },
             err_fn,
@@ -322,13 +498,22 @@ impl AudioWorker {
     }
 }
 
+// Whether an input device of that name is listed, without opening it
+fn device_listed(name: &str) -> bool {
+    cpal::default_host()
+        .input_devices()
+        .map(|mut devices| devices.any(|d| d.name().is_ok_and(|n| n == name)))
+        .unwrap_or(false)
+}
+
 impl AudioCapture {
     pub fn new(
-        data_sender: Sender<AudioMessage>,
+        data_sender: AudioSender,
         device_name: Option<String>,
         sample_rate: u32,
         restart_policy: Option<PolicyAudioRestart>,
         buffer_duration: Option<Duration>,
+        warmup_duration: Option<Duration>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         let (control_sender, control_receiver) = channel();
         let policy = restart_policy.unwrap_or_default();
@@ -341,6 +526,7 @@ impl AudioCapture {
             sample_rate,
             policy,
             buffer_duration,
+            warmup_duration,
         );
 
         let thread_handle = thread::spawn(move || {
@@ -355,29 +541,31 @@ impl AudioCapture {
             sample_rate,
             restart_policy: policy,
             buffer_duration,
+            warmup_duration,
         })
     }
 
-    #[allow(dead_code)]
     pub fn list_devices() -> Result<Vec<String>, Box<dyn std::error::Error>> {
         let host = cpal::default_host();
         let devices = host.input_devices()?;
         let mut names = Vec::new();
+        if cfg!(target_os = "windows") {
+            names.push(SYSTEM_AUDIO_DEVICE.to_string());
+        }
         for device in devices {
             if let Ok(name) = device.name() {
                 names.push(name);
             }
         }
+        names.push(SIMULATED_DEVICE.to_string());
         Ok(names)
     }
 
-    #[allow(dead_code)]
     pub fn default_device_name() -> Option<String> {
         let host = cpal::default_host();
         host.default_input_device().and_then(|d| d.name().ok())
     }
 
-    #[allow(dead_code)]
     pub fn set_device(
         &mut self,
         device_name: Option<String>,
@@ -399,6 +587,7 @@ impl AudioCapture {
             self.sample_rate,
             self.restart_policy,
             self.buffer_duration,
+            self.warmup_duration,
         );
 
         let thread_handle = thread::spawn(move || {
@@ -422,3 +611,162 @@ impl Drop for AudioCapture {
         }
     }
 }
+
+/// Output stream playing the captured input, a click on the detected beats, or
+/// both, on an output device (see `MonitorConfig`). The stream stops when
+/// dropped.
+pub struct AudioMonitor {
+    mix: Arc<Mutex<MonitorMix>>,
+    _stream: cpal::Stream,
+}
+
+impl AudioMonitor {
+    pub fn new(config: &MonitorConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let output = OutputDevice::open(config.device.as_deref())?;
+        info!(
+            "Monitor output: {} ({:?}, {} Hz)",
+            output.name,
+            config.source,
+            output.sample_rate()
+        );
+        let mix = Arc::new(Mutex::new(MonitorMix::new(
+            output.sample_rate(),
+            config.source,
+            config.volume,
+        )));
+        let render_mix = mix.clone();
+        let stream = output.play(move |out, playback| {
+            // Never wait on the analysis thread here: a missed callback plays
+            // silence
+            match render_mix.try_lock() {
+                Ok(mut mix) => mix.render(out, playback),
+                Err(_) => out.fill(0.0),
+            }
+        })?;
+
+        Ok(Self {
+            mix,
+            _stream: stream,
+        })
+    }
+
+    /// Queues samples of the analyzed stream (mono).
+    pub fn push(&self, samples: &[f32]) {
+        if let Ok(mut mix) = self.mix.lock() {
+            mix.push_input(samples, Instant::now());
+        }
+    }
+
+    pub fn set_input_rate(&self, rate: u32) {
+        if let Ok(mut mix) = self.mix.lock() {
+            mix.set_input_rate(rate);
+        }
+    }
+
+    /// Follows the beat grid: `bpm`, last beat `beat_age` ago. The click stops
+    /// without a beat.
+    pub fn set_beat(&self, bpm: f32, beat_age: Option<Duration>) {
+        if let Ok(mut mix) = self.mix.lock() {
+            match beat_age {
+                Some(age) => mix.set_beat(bpm, age, Instant::now()),
+                None => mix.clear_beat(),
+            }
+        }
+    }
+
+    pub fn set_source(&self, source: MonitorSource) {
+        if let Ok(mut mix) = self.mix.lock() {
+            mix.set_source(source);
+        }
+    }
+
+    pub fn list_devices() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let host = cpal::default_host();
+        Ok(host
+            .output_devices()?
+            .filter_map(|device| device.name().ok())
+            .collect())
+    }
+}
+
+/// Output device in its default config, see `play`.
+pub struct OutputDevice {
+    device: cpal::Device,
+    pub name: String,
+    config: cpal::StreamConfig,
+    sample_format: cpal::SampleFormat,
+}
+
+impl OutputDevice {
+    /// The named output device, the default one when unset.
+    pub fn open(name: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        let host = cpal::default_host();
+        let device = match name {
+            Some(name) => host
+                .output_devices()?
+                .find(|d| d.name().map(|n| n == name).unwrap_or(false))
+                .ok_or(format!("Output device '{}' not found", name))?,
+            None => host
+                .default_output_device()
+                .ok_or("No output device available")?,
+        };
+        let supported = device.default_output_config()?;
+        Ok(Self {
+            name: device.name()?,
+            sample_format: supported.sample_format(),
+            config: supported.into(),
+            device,
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.config.sample_rate.0
+    }
+
+    /// Starts a stream filled by `render` with mono samples, given the time the
+    /// first one is played. The same sample goes to every channel.
+    pub fn play(
+        &self,
+        render: impl FnMut(&mut [f32], Instant) + Send + 'static,
+    ) -> Result<cpal::Stream, Box<dyn std::error::Error>> {
+        let stream = match self.sample_format {
+            cpal::SampleFormat::I16 => self.build_stream::<i16>(render)?,
+            cpal::SampleFormat::U16 => self.build_stream::<u16>(render)?,
+            cpal::SampleFormat::F32 => self.build_stream::<f32>(render)?,
+            sample_format => {
+                return Err(format!("Unsupported output format: {:?}", sample_format).into());
+            }
+        };
+        stream.play()?;
+        Ok(stream)
+    }
+
+    fn build_stream<T>(
+        &self,
+        mut render: impl FnMut(&mut [f32], Instant) + Send + 'static,
+    ) -> Result<cpal::Stream, Box<dyn std::error::Error>>
+    where
+        T: cpal::SizedSample + cpal::FromSample<f32>,
+    {
+        let channels = self.config.channels as usize;
+        let mut mono = Vec::new();
+        let stream = self.device.build_output_stream(
+            &self.config,
+            move |data: &mut [T], info: &cpal::OutputCallbackInfo| {
+                mono.resize(data.len() / channels, 0.0);
+                let timestamp = info.timestamp();
+                let latency = timestamp
+                    .playback
+                    .duration_since(&timestamp.callback)
+                    .unwrap_or_default();
+                render(&mut mono, Instant::now() + latency);
+                for (frame, &sample) in data.chunks_mut(channels).zip(&mono) {
+                    frame.fill(T::from_sample(sample));
+                }
+            },
+            |err| error!("an error occurred on the output stream: {}", err),
+            None,
+        )?;
+        Ok(stream)
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+pub enum AudioMessage {
+    Samples(Vec<f32>),
+    Reset,
+    SampleRateChanged(u32),
+    /// Name of the input actually captured (differs from the selection after a failover)
+    DeviceChanged(String),
+    /// The consumer fell behind and the oldest sample packets were discarded.
+    /// Delivered in place of the dropped packets, in order with the other messages.
+    Overrun {
+        dropped_frames: u64,
+    },
+    /// Peak and RMS of the input (0..1 full scale) over the last `METER_PERIOD`
+    Level {
+        peak: f32,
+        rms: f32,
+    },
+}
+
+/// Every sender is gone and the queue is empty.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RecvError;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RecvTimeoutError {
+    Timeout,
+    Disconnected,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    Disconnected,
+}
+
+/// Receiver dropped, returns the message that could not be queued.
+#[derive(Debug)]
+pub struct SendError(pub AudioMessage);
+
+impl std::fmt::Debug for AudioMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AudioMessage::Samples(samples) => write!(f, "Samples({})", samples.len()),
+            AudioMessage::Reset => write!(f, "Reset"),
+            AudioMessage::SampleRateChanged(rate) => write!(f, "SampleRateChanged({})", rate),
+            AudioMessage::DeviceChanged(name) => write!(f, "DeviceChanged({:?})", name),
+            AudioMessage::Overrun { dropped_frames } => {
+                write!(f, "Overrun {{ dropped_frames: {} }}", dropped_frames)
+            }
+            AudioMessage::Level { peak, rms } => {
+                write!(f, "Level {{ peak: {:.3}, rms: {:.3} }}", peak, rms)
+            }
+        }
+    }
+}
+
+struct Queue {
+    messages: VecDeque<AudioMessage>,
+    // Samples currently queued, all channels
+    queued_samples: usize,
+    // Interleaved channels of the running stream, to count dropped frames
+    channels: usize,
+    senders: usize,
+    receiver_alive: bool,
+}
+
+// Consumed packet buffers kept for the capture callback
+const SPARE_BUFFERS: usize = 16;
+
+struct Shared {
+    queue: Mutex<Queue>,
+    available: Condvar,
+    capacity_frames: usize,
+    // Emptied packet buffers, reused by `AudioSender::buffer`
+    spare: Mutex<Vec<Vec<f32>>>,
+}
+
+impl Shared {
+    fn recycle(&self, mut buffer: Vec<f32>) {
+        buffer.clear();
+        if let Ok(mut spare) = self.spare.lock()
+            && spare.len() < SPARE_BUFFERS
+        {
+            spare.push(buffer);
+        }
+    }
+}
+
+/// Creates the channel between the capture callback and the analysis loop.
+/// It holds at most `capacity_frames` frames of audio: when the consumer stalls, the
+/// oldest sample packets are dropped (control messages never are) so the audio thread
+/// never blocks and memory stays bounded. The receiver then gets an
+/// `AudioMessage::Overrun` with the number of frames lost.
+pub fn audio_channel(capacity_frames: usize) -> (AudioSender, AudioReceiver) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(Queue {
+            messages: VecDeque::new(),
+            queued_samples: 0,
+            channels: 1,
+            senders: 1,
+            receiver_alive: true,
+        }),
+        available: Condvar::new(),
+        capacity_frames,
+        spare: Mutex::new(Vec::new()),
+    });
+    (
+        AudioSender {
+            shared: shared.clone(),
+        },
+        AudioReceiver { shared },
+    )
+}
+
+pub struct AudioSender {
+    shared: Arc<Shared>,
+}
+
+impl AudioSender {
+    pub fn send(&self, message: AudioMessage) -> Result<(), SendError> {
+        let Ok(mut queue) = self.shared.queue.lock() else {
+            return Err(SendError(message));
+        };
+        if !queue.receiver_alive {
+            return Err(SendError(message));
+        }
+        if let AudioMessage::Samples(samples) = &message {
+            queue.queued_samples += samples.len();
+            let capacity = self.shared.capacity_frames * queue.channels;
+            while queue.queued_samples > capacity {
+                // Oldest sample packet, the new one is always kept
+                let oldest = queue
+                    .messages
+                    .iter()
+                    .position(|m| matches!(m, AudioMessage::Samples(_)));
+                let Some(index) = oldest else {
+                    break;
+                };
+                let frames = match &queue.messages[index] {
+                    AudioMessage::Samples(dropped) => dropped.len(),
+                    _ => 0,
+                };
+                queue.queued_samples -= frames;
+                let dropped_frames = (frames / queue.channels) as u64;
+                // The gap takes the place of the packet, next to a gap just before it
+                let previous = index.checked_sub(1).map(|i| &mut queue.messages[i]);
+                let dropped = if let Some(AudioMessage::Overrun {
+                    dropped_frames: gap,
+                }) = previous
+                {
+                    *gap += dropped_frames;
+                    queue.messages.remove(index)
+                } else {
+                    Some(std::mem::replace(
+                        &mut queue.messages[index],
+                        AudioMessage::Overrun { dropped_frames },
+                    ))
+                };
+                if let Some(AudioMessage::Samples(dropped)) = dropped {
+                    self.shared.recycle(dropped);
+                }
+            }
+        }
+        queue.messages.push_back(message);
+        drop(queue);
+        self.shared.available.notify_one();
+        Ok(())
+    }
+
+    /// Empty buffer for the next sample packet, taken from the ones the consumer
+    /// gave back (see `AudioReceiver::recycle`) so that steady-state capture does
+    /// not allocate.
+    pub fn buffer(&self) -> Vec<f32> {
+        self.shared
+            .spare
+            .lock()
+            .ok()
+            .and_then(|mut spare| spare.pop())
+            .unwrap_or_default()
+    }
+
+    /// Sets the interleaved channel count of the following packets (per stream).
+    pub fn set_channels(&self, channels: u16) {
+        if let Ok(mut queue) = self.shared.queue.lock() {
+            queue.channels = channels.max(1) as usize;
+        }
+    }
+}
+
+impl Clone for AudioSender {
+    fn clone(&self) -> Self {
+        if let Ok(mut queue) = self.shared.queue.lock() {
+            queue.senders += 1;
+        }
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for AudioSender {
+    fn drop(&mut self) {
+        if let Ok(mut queue) = self.shared.queue.lock() {
+            queue.senders -= 1;
+        }
+        self.shared.available.notify_all();
+    }
+}
+
+pub struct AudioReceiver {
+    shared: Arc<Shared>,
+}
+
+/// Gives consumed packet buffers back to the capture callback, for consumers that
+/// handle the packets away from the receiver (see `AudioReceiver::recycler`).
+#[derive(Clone)]
+pub struct AudioRecycler {
+    shared: Arc<Shared>,
+}
+
+impl AudioRecycler {
+    pub fn recycle(&self, buffer: Vec<f32>) {
+        self.shared.recycle(buffer);
+    }
+}
+
+impl AudioReceiver {
+    /// Gives the buffer of a consumed `AudioMessage::Samples` back to the sender.
+    /// Optional: a packet that is simply dropped is replaced by a new allocation.
+    pub fn recycle(&self, buffer: Vec<f32>) {
+        self.shared.recycle(buffer);
+    }
+
+    pub fn recycler(&self) -> AudioRecycler {
+        AudioRecycler {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Blocks until a message arrives. Errs once every sender is gone and the queue is empty.
+    pub fn recv(&self) -> Result<AudioMessage, RecvError> {
+        self.recv_until(None).map_err(|_| RecvError)
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<AudioMessage, RecvTimeoutError> {
+        self.recv_until(Some(Instant::now() + timeout))
+    }
+
+    pub fn try_recv(&self) -> Result<AudioMessage, TryRecvError> {
+        let mut queue = self
+            .shared
+            .queue
+            .lock()
+            .map_err(|_| TryRecvError::Disconnected)?;
+        match Self::pop(&mut queue) {
+            Some(message) => Ok(message),
+            None if queue.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn recv_until(&self, deadline: Option<Instant>) -> Result<AudioMessage, RecvTimeoutError> {
+        let mut queue = self
+            .shared
+            .queue
+            .lock()
+            .map_err(|_| RecvTimeoutError::Disconnected)?;
+        loop {
+            if let Some(message) = Self::pop(&mut queue) {
+                return Ok(message);
+            }
+            if queue.senders == 0 {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            queue = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    self.shared
+                        .available
+                        .wait_timeout(queue, deadline - now)
+                        .map_err(|_| RecvTimeoutError::Disconnected)?
+                        .0
+                }
+                None => self
+                    .shared
+                    .available
+                    .wait(queue)
+                    .map_err(|_| RecvTimeoutError::Disconnected)?,
+            };
+        }
+    }
+
+    fn pop(queue: &mut Queue) -> Option<AudioMessage> {
+        let message = queue.messages.pop_front()?;
+        if let AudioMessage::Samples(samples) = &message {
+            queue.queued_samples -= samples.len();
+        }
+        Some(message)
+    }
+}
+
+impl Drop for AudioReceiver {
+    fn drop(&mut self) {
+        if let Ok(mut queue) = self.shared.queue.lock() {
+            queue.receiver_alive = false;
+            queue.messages.clear();
+            queue.queued_samples = 0;
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use super::analyzer::{AudioFilter, FilterOrder, FilterType};
+use serde::Serialize;
+use std::time::Duration;
+
+// Per-beat decay of the kick/snare reference peaks (auto-scaling)
+const PEAK_DECAY: f32 = 0.95;
+
+/// Band levels measured over one beat, all in 0..1.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct BeatFeatures {
+    /// Low band (< 120 Hz) relative to recent beats
+    pub kick: f32,
+    /// Snare band (1-4 kHz) relative to recent beats
+    pub snare: f32,
+    /// Share of the energy above 4 kHz
+    pub brightness: f32,
+}
+
+/// Accumulates band energies between two beats of the detected grid and emits one
+/// `BeatFeatures` per beat, so lights can follow the music beat by beat instead of
+/// reacting to the continuous (noisy) level.
+pub struct BeatFeatureSampler {
+    sample_rate: f32,
+    kick_filter: AudioFilter,
+    snare_filter: AudioFilter,
+    bright_filter: AudioFilter,
+    // Energies since the last beat
+    kick_energy: f32,
+    snare_energy: f32,
+    bright_energy: f32,
+    total_energy: f32,
+    count: usize,
+    // Beat grid, in samples
+    period: Option<f32>,
+    to_next_beat: f32,
+    kick_peak: f32,
+    snare_peak: f32,
+}
+
+impl BeatFeatureSampler {
+    pub fn new(sample_rate: u32) -> Result<Self, String> {
+        let rate = sample_rate as f32;
+        Ok(Self {
+            sample_rate: rate,
+            kick_filter: AudioFilter::new(FilterType::LowPass(120.0), rate, FilterOrder::Order4)?,
+            snare_filter: AudioFilter::new(
+                FilterType::BandPass(1000.0, 4000.0),
+                rate,
+                FilterOrder::Order2,
+            )?,
+            bright_filter: AudioFilter::new(
+                FilterType::HighPass(4000.0),
+                rate,
+                FilterOrder::Order2,
+            )?,
+            kick_energy: 0.0,
+            snare_energy: 0.0,
+            bright_energy: 0.0,
+            total_energy: 0.0,
+            count: 0,
+            period: None,
+            to_next_beat: 0.0,
+            kick_peak: 0.0,
+            snare_peak: 0.0,
+        })
+    }
+
+    /// Aligns the grid on the analyzer result. `beat_age` is the time since the last
+    /// beat, measured at the newest sample already passed to `push`.
+    pub fn set_grid(&mut self, bpm: f32, beat_age: Option<Duration>) {
+        if bpm <= 0.0 {
+            self.period = None;
+            return;
+        }
+        let period = self.sample_rate * 60.0 / bpm;
+        self.period = Some(period);
+        if let Some(age) = beat_age {
+            let since_beat = (age.as_secs_f32() * self.sample_rate) % period;
+            self.to_next_beat = period - since_beat;
+        }
+    }
+
+    /// Filters `samples` and appends the features of every beat boundary crossed.
+    pub fn push(&mut self, samples: &[f32], out: &mut Vec<BeatFeatures>) {
+        for &x in samples {
+            let kick = self.kick_filter.process(x);
+            let snare = self.snare_filter.process(x);
+            let bright = self.bright_filter.process(x);
+            self.kick_energy += kick * kick;
+            self.snare_energy += snare * snare;
+            self.bright_energy += bright * bright;
+            self.total_energy += x * x;
+            self.count += 1;
+
+            let Some(period) = self.period else {
+                continue;
+            };
+            self.to_next_beat -= 1.0;
+            if self.to_next_beat <= 0.0 {
+                self.to_next_beat += period;
+                out.push(self.close_beat());
+            }
+        }
+    }
+
+    fn close_beat(&mut self) -> BeatFeatures {
+        let n = self.count.max(1) as f32;
+        let kick = (self.kick_energy / n).sqrt();
+        let snare = (self.snare_energy / n).sqrt();
+        self.kick_peak = kick.max(self.kick_peak * PEAK_DECAY);
+        self.snare_peak = snare.max(self.snare_peak * PEAK_DECAY);
+
+        let features = BeatFeatures {
+            kick: if self.kick_peak > 0.0 {
+                kick / self.kick_peak
+            } else {
+                0.0
+            },
+            snare: if self.snare_peak > 0.0 {
+                snare / self.snare_peak
+            } else {
+                0.0
+            },
+            brightness: if self.total_energy > 0.0 {
+                (self.bright_energy / self.total_energy).sqrt().min(1.0)
+            } else {
+                0.0
+            },
+        };
+
+        self.kick_energy = 0.0;
+        self.snare_energy = 0.0;
+        self.bright_energy = 0.0;
+        self.total_energy = 0.0;
+        self.count = 0;
+        features
+    }
+}
@@ -0,0 +1,242 @@
+use super::offline::{FileAnalysis, same_tempo};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Without beat for this long (silence, beatless break) the grid starts over
+const MAX_GAP_S: f64 = 4.0;
+
+/// `[beat_grid]` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BeatGridConfig {
+    /// Write the beats of the live input to `set-<unix time>.beats.<format>`
+    pub enabled: bool,
+    pub directory: PathBuf,
+    pub format: BeatGridFormat,
+    /// The file is rewritten at this period while analyzing, so that a set ended
+    /// with Ctrl+C or a power cut keeps its grid
+    pub save_interval_s: u64,
+}
+
+impl Default for BeatGridConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: PathBuf::from("."),
+            format: BeatGridFormat::Csv,
+            save_interval_s: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BeatGridFormat {
+    /// One `time_s,bpm` line per beat, imported as markers by most DAWs
+    Csv,
+    /// Tempo markers (Rekordbox `TEMPO` entries) and beat times
+    Json,
+}
+
+impl BeatGridFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// Tempo in force from `time_s` on, `time_s` being a beat.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct GridMarker {
+    pub time_s: f64,
+    pub bpm: f32,
+}
+
+/// Beat grid of a recording, times in seconds from the start of the audio.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BeatGridExport {
+    /// Analyzed file (file mode)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio: Option<PathBuf>,
+    /// Unix time the live analysis started, to line the grid up with a recording
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+    pub markers: Vec<GridMarker>,
+    pub beats: Vec<f64>,
+}
+
+impl BeatGridExport {
+    /// Beats of the grids of an offline analysis, from the first downbeat of each
+    /// tempo segment to its end.
+    pub fn from_analysis(analysis: &FileAnalysis) -> Self {
+        let mut export = Self {
+            audio: Some(analysis.path.clone()),
+            ..Self::default()
+        };
+        for grid in &analysis.grids {
+            let period = 60.0 / grid.bpm as f64;
+            let first = grid.first_downbeat_s as f64;
+            export.markers.push(GridMarker {
+                time_s: first,
+                bpm: grid.bpm,
+            });
+            export.beats.extend(
+                (0..)
+                    .map(|k| first + k as f64 * period)
+                    .take_while(|&t| t < grid.end_s as f64),
+            );
+        }
+        export
+    }
+
+    /// Tempo of the marker in force at `time_s`.
+    pub fn bpm_at(&self, time_s: f64) -> Option<f32> {
+        self.markers
+            .iter()
+            .rev()
+            .find(|marker| marker.time_s <= time_s)
+            .map(|marker| marker.bpm)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("time_s,bpm\n");
+        for &time_s in &self.beats {
+            let bpm = self.bpm_at(time_s).unwrap_or(0.0);
+            let _ = writeln!(csv, "{:.3},{:.2}", time_s, bpm);
+        }
+        csv
+    }
+
+    pub fn write(&self, path: &Path, format: BeatGridFormat) -> Result<(), Box<dyn Error>> {
+        let content = match format {
+            BeatGridFormat::Csv => self.to_csv(),
+            BeatGridFormat::Json => serde_json::to_string_pretty(self)?,
+        };
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// Beat grid of the live input, built hop by hop from the beats placed by aubio.
+/// Times count the analyzed audio since the start, so that the grid lines up with
+/// a recording of the set started at the same time.
+pub struct BeatGridRecorder {
+    config: BeatGridConfig,
+    path: PathBuf,
+    export: BeatGridExport,
+    position_s: f64,
+    last_save: Instant,
+    // Beats added since the last save
+    unsaved: bool,
+}
+
+impl BeatGridRecorder {
+    pub fn new(config: BeatGridConfig) -> Self {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let path = config.directory.join(format!(
+            "set-{}.beats.{}",
+            started_at,
+            config.format.extension()
+        ));
+        Self {
+            config,
+            path,
+            export: BeatGridExport {
+                started_at: Some(started_at),
+                ..BeatGridExport::default()
+            },
+            position_s: 0.0,
+            last_save: Instant::now(),
+            unsaved: false,
+        }
+    }
+
+    /// Moves the clock past a hop of analyzed audio.
+    pub fn advance(&mut self, hop: Duration) {
+        self.position_s += hop.as_secs_f64();
+    }
+
+    /// Adds the beats up to the last one placed by aubio, `beat_age` before the end
+    /// of the hop. The beats in between are spread evenly, which absorbs the jitter
+    /// of the aubio beats, and a change of tempo adds a marker.
+    pub fn record(&mut self, bpm: f32, beat_age: Option<Duration>) {
+        let Some(age) = beat_age.filter(|_| bpm > 0.0) else {
+            return;
+        };
+        let beat_s = self.position_s - age.as_secs_f64();
+        let period = 60.0 / bpm as f64;
+        let first_new = self.export.beats.len();
+        match self.export.beats.last().copied() {
+            // Same beat as the previous hop
+            Some(last) if beat_s - last < period / 2.0 => return,
+            Some(last) if beat_s - last <= MAX_GAP_S => {
+                let count = ((beat_s - last) / period).round().max(1.0) as usize;
+                let step = (beat_s - last) / count as f64;
+                self.export
+                    .beats
+                    .extend((1..=count).map(|k| last + k as f64 * step));
+            }
+            _ => {
+                self.export.beats.push(beat_s);
+                self.export.markers.push(GridMarker {
+                    time_s: beat_s,
+                    bpm,
+                });
+                self.unsaved = true;
+                return;
+            }
+        }
+        if self
+            .export
+            .markers
+            .last()
+            .is_none_or(|marker| !same_tempo(bpm, marker.bpm))
+        {
+            self.export.markers.push(GridMarker {
+                time_s: self.export.beats[first_new],
+                bpm,
+            });
+        }
+        self.unsaved = true;
+    }
+
+    pub fn export(&self) -> &BeatGridExport {
+        &self.export
+    }
+
+    /// File the grid is written to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether new beats wait for a save and `save_interval_s` has elapsed.
+    pub fn save_due(&self) -> bool {
+        self.unsaved && self.last_save.elapsed() >= Duration::from_secs(self.config.save_interval_s)
+    }
+
+    /// Writes the whole grid so far to `path()`.
+    pub fn save(&mut self) -> Result<&Path, Box<dyn Error>> {
+        self.last_save = Instant::now();
+        fs::create_dir_all(&self.config.directory)?;
+        self.export.write(&self.path, self.config.format)?;
+        self.unsaved = false;
+        Ok(&self.path)
+    }
+}
@@ -0,0 +1,139 @@
+use serde::Serialize;
+
+// Share of the phase error corrected at each beat observation
+const PHASE_GAIN: f32 = 0.3;
+// Share of the phase error folded into the period (drift correction)
+const PERIOD_GAIN: f32 = 0.05;
+// Period correction ceiling, relative to the analyzer's period
+const MAX_DRIFT: f32 = 0.02;
+// Tempo move (relative) treated as a new tempo: the loop re-locks from scratch
+const RELOCK_RATIO: f32 = 0.02;
+// Weight of the latest error in the jitter average
+const JITTER_SMOOTHING: f32 = 0.1;
+// Observations before the loop can report a lock
+const LOCK_OBSERVATIONS: u32 = 4;
+// Jitter (share of the period) under which the loop is locked
+const LOCK_JITTER: f32 = 0.1;
+
+/// Beat phase loop statistics reported with each `AnalysisResult`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PhaseLock {
+    pub locked: bool,
+    /// RMS gap between the detected beats and the loop grid (ms)
+    pub jitter_ms: f32,
+    /// Correction of the analyzer tempo applied by the loop (BPM)
+    pub drift_bpm: f32,
+}
+
+/// Second-order phase-locked loop on the beat grid. It runs on the audio clock
+/// (`advance` with every processed hop) and pulls its grid towards each beat
+/// onset found by aubio: the phase takes a share of the error at once, the
+/// period a smaller one, which absorbs the 0.1 BPM rounding of the analyzer
+/// tempo. Unlike the last onset alone, the grid ignores a single early or late
+/// hit and keeps going through breaks.
+#[derive(Debug, Clone, Default)]
+pub struct BeatPll {
+    // Analyzer period and loop correction (s)
+    nominal: Option<f32>,
+    drift: f32,
+    // Time from the last grid beat to the newest sample (s)
+    beat_age: f32,
+    observations: u32,
+    mean_square_error: f32,
+}
+
+impl BeatPll {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets the phase (stream restart), the next onset re-anchors the grid.
+    pub fn reset(&mut self) {
+        *self = Self {
+            nominal: self.nominal,
+            ..Self::default()
+        };
+    }
+
+    fn period(&self) -> Option<f32> {
+        self.nominal.map(|nominal| nominal + self.drift)
+    }
+
+    /// Tempo detected by the analyzer. The loop keeps its phase through small
+    /// moves and re-locks on a new tempo (next track, octave change).
+    pub fn set_tempo(&mut self, bpm: f32) {
+        if bpm <= 0.0 {
+            return;
+        }
+        let nominal = 60.0 / bpm;
+        match self.nominal {
+            Some(current) if ((nominal - current) / current).abs() <= RELOCK_RATIO => {
+                self.nominal = Some(nominal);
+            }
+            _ => {
+                *self = Self {
+                    nominal: Some(nominal),
+                    ..Self::default()
+                };
+            }
+        }
+    }
+
+    /// Moves the grid forward by `seconds` of audio.
+    pub fn advance(&mut self, seconds: f32) {
+        if let Some(period) = self.period() {
+            self.beat_age = (self.beat_age + seconds).rem_euclid(period);
+        }
+    }
+
+    /// Onset `age` seconds before the newest sample.
+    pub fn observe(&mut self, age: f32) {
+        let (Some(nominal), Some(period)) = (self.nominal, self.period()) else {
+            return;
+        };
+        if self.observations == 0 {
+            self.beat_age = age.rem_euclid(period);
+            self.observations = 1;
+            return;
+        }
+        // Positive when the onset comes before the grid beat
+        let mut error = (age - self.beat_age).rem_euclid(period);
+        if error >= period / 2.0 {
+            error -= period;
+        }
+        self.beat_age = (self.beat_age + PHASE_GAIN * error).rem_euclid(period);
+        // Onsets early beat after beat: the grid period is too long
+        let max_drift = MAX_DRIFT * nominal;
+        self.drift = (self.drift - PERIOD_GAIN * error).clamp(-max_drift, max_drift);
+        self.mean_square_error += JITTER_SMOOTHING * (error * error - self.mean_square_error);
+        self.observations = self.observations.saturating_add(1);
+    }
+
+    pub fn is_locked(&self) -> bool {
+        match self.period() {
+            Some(period) => {
+                self.observations >= LOCK_OBSERVATIONS
+                    && self.mean_square_error.sqrt() < LOCK_JITTER * period
+            }
+            None => false,
+        }
+    }
+
+    /// Time from the last grid beat to the newest sample, once locked.
+    pub fn beat_age(&self) -> Option<f32> {
+        self.is_locked().then_some(self.beat_age)
+    }
+
+    /// Loop state, `None` before the first onset at a known tempo.
+    pub fn stats(&self) -> Option<PhaseLock> {
+        let (nominal, period) = (self.nominal?, self.period()?);
+        if self.observations == 0 {
+            return None;
+        }
+        Some(PhaseLock {
+            locked: self.is_locked(),
+            jitter_ms: self.mean_square_error.sqrt() * 1000.0,
+            drift_bpm: 60.0 / period - 60.0 / nominal,
+        })
+    }
+}
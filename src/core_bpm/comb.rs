@@ -0,0 +1,91 @@
+//! Comb-filter resonator bank (Scheirer, 1998), the `CombFilter` tempo estimator.
+//! One resonator per lag is fed with the onsets of the envelope: the resonator
+//! whose delay matches the beat period adds every beat onto the echo of the
+//! previous one, the others spread them out. The score only depends on the
+//! onsets, not on the energy between them, which suits sparse percussion better
+//! than the autocorrelation.
+
+// Time for the echoes of every resonator to lose half their level. The same for
+// all of them, so that no lag is favored by a longer memory.
+const HALF_LIFE_S: f32 = 1.0;
+// Spread of the onsets, about the attack of a kick
+const ONSET_SMOOTHING_S: f32 = 0.01;
+
+/// Onset strength of an envelope sampled at `rate`: its half-wave rectified
+/// first difference, averaged over `ONSET_SMOOTHING_S` so that a beat period
+/// falling between two lags still lines up with its echoes.
+pub fn onsets(envelope: &[f32], rate: f32, out: &mut Vec<f32>) {
+    let width = ((rate * ONSET_SMOOTHING_S) as usize).max(1);
+    let rise = |i: usize| {
+        if i == 0 {
+            0.0
+        } else {
+            (envelope[i] - envelope[i - 1]).max(0.0)
+        }
+    };
+    out.clear();
+    let mut sum = 0.0;
+    for i in 0..envelope.len() {
+        sum += rise(i);
+        if i >= width {
+            sum -= rise(i - width);
+        }
+        out.push(sum / width as f32);
+    }
+}
+
+/// Fills `out` with the energy of the resonators of lags `min_lag..=max_lag`
+/// (`out[0]` is `min_lag`), `rate` being the sampling rate of `onsets`.
+///
+/// Energies are measured on the second half of the window, once the resonators
+/// rang for a few beats, and divided by the onset energy over the same samples:
+/// 1.0 for a resonator locked on a steady pulse train, around
+/// `(1 - a) / (1 + a)` (`a` the echo gain) for noise. Multiples of the beat
+/// period resonate too, the octave is left to the caller.
+pub fn resonator_energies(
+    onsets: &[f32],
+    rate: f32,
+    min_lag: usize,
+    max_lag: usize,
+    output: &mut Vec<f32>,
+    out: &mut Vec<f32>,
+) {
+    out.clear();
+    let start = onsets.len() / 2;
+    let max_lag = max_lag.min(onsets.len().saturating_sub(1));
+    let min_lag = min_lag.max(1);
+    let input_energy: f32 = onsets[start..].iter().map(|x| x * x).sum();
+    if min_lag > max_lag || input_energy <= 0.0 {
+        return;
+    }
+
+    output.resize(onsets.len(), 0.0);
+    for lag in min_lag..=max_lag {
+        let gain = 0.5f32.powf(lag as f32 / (rate * HALF_LIFE_S));
+        let mut energy = 0.0;
+        for i in 0..onsets.len() {
+            let echo = if i >= lag { output[i - lag] } else { 0.0 };
+            let y = gain * echo + (1.0 - gain) * onsets[i];
+            output[i] = y;
+            if i >= start {
+                energy += y * y;
+            }
+        }
+        out.push(energy / input_energy);
+    }
+}
+
+/// Index of the strongest resonator in `energies`, with its contrast over the
+/// bank (`1 - mean / peak`): about 0.2 on noise, 0.5 and more on a steady beat.
+pub fn best_resonator(energies: &[f32]) -> Option<(usize, f32)> {
+    let (index, peak) = energies
+        .iter()
+        .copied()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    if peak <= 0.0 {
+        return None;
+    }
+    let mean = energies.iter().sum::<f32>() / energies.len() as f32;
+    Some((index, 1.0 - mean / peak))
+}
@@ -0,0 +1,203 @@
+use super::analyzer::{AudioFilter, FilterOrder, FilterType};
+use super::offline::{TempoSegment, same_tempo};
+use serde::Serialize;
+
+// Resolution of the kick envelope used to place the grid
+const FRAME_S: f32 = 0.01;
+const BEATS_PER_BAR: usize = 4;
+// Phrase length in bars, every second phrase boundary is a 32-bar section
+const PHRASE_BARS: usize = 16;
+// Bars averaged on each side of a candidate energy transition
+const ENERGY_BARS: usize = 4;
+// Level ratio (after / before) flagged as a transition, i.e. about 6 dB
+const ENERGY_RATIO: f32 = 2.0;
+
+/// Beat grid of one tempo segment.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BeatGrid {
+    pub bpm: f32,
+    /// First beat 1 of a bar inside the segment
+    pub first_downbeat_s: f32,
+    /// Start of the next segment (or end of the file): beatless parts such as
+    /// breakdowns keep the grid of the previous tempo
+    pub end_s: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum CueKind {
+    /// Start of a 16-bar phrase
+    Phrase,
+    /// Start of a 32-bar section (also a phrase boundary)
+    Section,
+    /// Level rising or falling by `ENERGY_RATIO` (drop, breakdown)
+    EnergyUp,
+    EnergyDown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CuePoint {
+    pub time_s: f32,
+    pub kind: CueKind,
+    pub name: String,
+}
+
+/// Low-band (kick) level every `FRAME_S`.
+pub struct KickEnvelope {
+    frames: Vec<f32>,
+}
+
+impl KickEnvelope {
+    pub fn new(samples: &[f32], sample_rate: u32) -> Result<Self, String> {
+        let rate = sample_rate as f32;
+        let mut filter = AudioFilter::new(FilterType::LowPass(150.0), rate, FilterOrder::Order4)?;
+        let frame_len = ((rate * FRAME_S) as usize).max(1);
+        let frames = samples
+            .chunks(frame_len)
+            .map(|chunk| {
+                let sum: f32 = chunk.iter().map(|&x| filter.process(x).abs()).sum();
+                sum / chunk.len() as f32
+            })
+            .collect();
+        Ok(Self { frames })
+    }
+
+    fn at(&self, time_s: f32) -> f32 {
+        let index = (time_s / FRAME_S).round() as usize;
+        self.frames.get(index).copied().unwrap_or(0.0)
+    }
+
+    fn mean(&self, start_s: f32, end_s: f32) -> f32 {
+        let start = ((start_s / FRAME_S) as usize).min(self.frames.len());
+        let end = ((end_s / FRAME_S) as usize).clamp(start, self.frames.len());
+        let frames = &self.frames[start..end];
+        frames.iter().sum::<f32>() / frames.len().max(1) as f32
+    }
+}
+
+/// Places a beat grid on each segment. A segment at the tempo of the previous grid
+/// (after a beatless breakdown) extends that grid instead of starting a new one, so
+/// phrases keep counting through the break.
+pub fn beat_grids(
+    envelope: &KickEnvelope,
+    segments: &[TempoSegment],
+    duration_s: f32,
+) -> Vec<BeatGrid> {
+    let mut grids: Vec<BeatGrid> = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        if segment.bpm <= 0.0 {
+            continue;
+        }
+        let end_s = segments.get(i + 1).map_or(duration_s, |next| next.start_s);
+        match grids.last_mut() {
+            Some(last) if same_tempo(segment.bpm, last.bpm) => last.end_s = end_s,
+            _ => grids.push(BeatGrid {
+                bpm: segment.bpm,
+                first_downbeat_s: first_downbeat(envelope, segment),
+                end_s,
+            }),
+        }
+    }
+    grids
+}
+
+// The beat phase is the one collecting the most kick energy, and the downbeat the
+// beat of the bar with the strongest kicks
+fn first_downbeat(envelope: &KickEnvelope, segment: &TempoSegment) -> f32 {
+    let beat_s = 60.0 / segment.bpm;
+    let beats = ((segment.end_s - segment.start_s) / beat_s) as usize;
+    let energy_at = |offset: f32, first: usize, step: usize| -> f32 {
+        (first..beats)
+            .step_by(step)
+            .map(|k| envelope.at(segment.start_s + offset + k as f32 * beat_s))
+            .sum()
+    };
+
+    let steps = (beat_s / FRAME_S) as usize;
+    let phase = (0..steps.max(1))
+        .map(|i| i as f32 * FRAME_S)
+        .max_by(|a, b| energy_at(*a, 0, 1).total_cmp(&energy_at(*b, 0, 1)))
+        .unwrap_or(0.0);
+    let downbeat = (0..BEATS_PER_BAR)
+        .max_by(|a, b| {
+            energy_at(phase, *a, BEATS_PER_BAR).total_cmp(&energy_at(phase, *b, BEATS_PER_BAR))
+        })
+        .unwrap_or(0);
+    segment.start_s + phase + downbeat as f32 * beat_s
+}
+
+/// Phrase boundaries every `PHRASE_BARS` bars from the first downbeat of each grid,
+/// and bars where the level changes by `ENERGY_RATIO` compared to the previous bars.
+pub fn detect_cues(envelope: &KickEnvelope, grids: &[BeatGrid]) -> Vec<CuePoint> {
+    let mut cues = Vec::new();
+    // (start, length) of every bar of the file, across grids
+    let mut bars: Vec<(f32, f32)> = Vec::new();
+    for grid in grids {
+        let bar_s = 60.0 / grid.bpm * BEATS_PER_BAR as f32;
+        let bar_starts = (0..)
+            .map(|i| grid.first_downbeat_s + i as f32 * bar_s)
+            .take_while(|&t| t + bar_s <= grid.end_s);
+        for (bar, time_s) in bar_starts.enumerate() {
+            bars.push((time_s, bar_s));
+            if bar % PHRASE_BARS != 0 {
+                continue;
+            }
+            let kind = if bar % (2 * PHRASE_BARS) == 0 {
+                CueKind::Section
+            } else {
+                CueKind::Phrase
+            };
+            cues.push(CuePoint {
+                time_s,
+                kind,
+                name: format!("Bar {}", bar + 1),
+            });
+        }
+    }
+
+    // Level ratio between the bars after and before each bar boundary
+    let levels: Vec<f32> = bars
+        .iter()
+        .map(|&(t, len)| envelope.mean(t, t + len))
+        .collect();
+    let ratios: Vec<f32> = (0..levels.len())
+        .map(|bar| {
+            if bar < ENERGY_BARS || bar + ENERGY_BARS > levels.len() {
+                return 1.0;
+            }
+            let before = levels[bar - ENERGY_BARS..bar].iter().sum::<f32>();
+            let after = levels[bar..bar + ENERGY_BARS].iter().sum::<f32>();
+            if before > 0.0 && after > 0.0 {
+                after / before
+            } else {
+                1.0
+            }
+        })
+        .collect();
+    // A transition is marked on the bar where the change is the sharpest
+    let strength = |ratio: f32| ratio.max(1.0 / ratio);
+    for (bar, &ratio) in ratios.iter().enumerate() {
+        if strength(ratio) < ENERGY_RATIO {
+            continue;
+        }
+        let neighbours = bar.saturating_sub(ENERGY_BARS)..(bar + ENERGY_BARS + 1).min(ratios.len());
+        if neighbours
+            .into_iter()
+            .any(|other| other != bar && strength(ratios[other]) > strength(ratio))
+        {
+            continue;
+        }
+        let (kind, label) = if ratio > 1.0 {
+            (CueKind::EnergyUp, "Energy up")
+        } else {
+            (CueKind::EnergyDown, "Energy down")
+        };
+        cues.push(CuePoint {
+            time_s: bars[bar].0,
+            kind,
+            name: label.to_string(),
+        });
+    }
+
+    cues.sort_by(|a, b| a.time_s.total_cmp(&b.time_s));
+    cues
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+// Zero crossings of the sinc kept on each side of the center tap
+const SINC_LOBES: usize = 4;
+
+/// How a sampling stage reduces its rate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Downsampler {
+    /// Mean of each chunk. Cheap, but its sidelobes fold envelope content above the
+    /// new Nyquist frequency back into the tempo range.
+    #[default]
+    Average,
+    /// Windowed-sinc low-pass computed only for the kept samples (polyphase form)
+    Polyphase,
+}
+
+/// FIR decimator by an integer factor: a Blackman-windowed sinc cut at the output
+/// Nyquist frequency, evaluated once every `factor` input samples. Linear phase,
+/// with a delay of `SINC_LOBES` output samples.
+#[derive(Clone, Debug)]
+pub struct PolyphaseDecimator {
+    factor: usize,
+    taps: Vec<f32>,
+    // Input history stored twice so the newest `taps.len()` samples are always contiguous
+    history: Vec<f32>,
+    pos: usize,
+    phase: usize,
+}
+
+impl PolyphaseDecimator {
+    pub fn new(factor: usize) -> Self {
+        let factor = factor.max(1);
+        let taps = if factor == 1 {
+            vec![1.0]
+        } else {
+            design_taps(factor)
+        };
+        Self {
+            factor,
+            history: vec![0.0; taps.len() * 2],
+            taps,
+            pos: 0,
+            phase: 0,
+        }
+    }
+
+    /// Feeds one input sample, returns an output sample every `factor` inputs.
+    pub fn push(&mut self, sample: f32) -> Option<f32> {
+        let len = self.taps.len();
+        self.history[self.pos] = sample;
+        self.history[self.pos + len] = sample;
+        self.pos = (self.pos + 1) % len;
+
+        self.phase += 1;
+        if self.phase < self.factor {
+            return None;
+        }
+        self.phase = 0;
+
+        // Taps are symmetric, no need to reverse them
+        let window = &self.history[self.pos..self.pos + len];
+        Some(window.iter().zip(&self.taps).map(|(x, h)| x * h).sum())
+    }
+
+    pub fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.pos = 0;
+        self.phase = 0;
+    }
+}
+
+fn design_taps(factor: usize) -> Vec<f32> {
+    let len = 2 * SINC_LOBES * factor + 1;
+    let center = (len / 2) as f32;
+    let cutoff = 0.5 / factor as f32;
+    let mut taps: Vec<f32> = (0..len)
+        .map(|i| {
+            let n = i as f32 - center;
+            let sinc = if n == 0.0 {
+                2.0 * cutoff
+            } else {
+                (2.0 * PI * cutoff * n).sin() / (PI * n)
+            };
+            let w = 2.0 * PI * i as f32 / (len - 1) as f32;
+            let blackman = 0.42 - 0.5 * w.cos() + 0.08 * (2.0 * w).cos();
+            sinc * blackman
+        })
+        .collect();
+    // Unity gain at DC, like the average
+    let sum: f32 = taps.iter().sum();
+    taps.iter_mut().for_each(|h| *h /= sum);
+    taps
+}
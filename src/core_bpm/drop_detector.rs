@@ -0,0 +1,136 @@
+//! Drop detection. In a drop the bass comes back after a breakdown where it was
+//! filtered out: the envelope of the analysis window surges (`envelope_surge`),
+//! and so does the sub band against its own recent history (`DropDetector`).
+//! A riser only gets louder in the mids and highs, and fails the second check.
+
+use biquad::*;
+use std::collections::VecDeque;
+
+// Split between the sub band and the mid/high bands
+const SPLIT_HZ: f32 = 120.0;
+// Band energies are averaged over blocks of this length
+const BLOCK_S: f32 = 0.05;
+// Blocks kept, and the latest of them compared to the others: the second half
+// of the default analysis window, as `envelope_surge`
+const HISTORY_BLOCKS: usize = 160;
+const RECENT_BLOCKS: usize = 20;
+// Sub band energy of the recent blocks against the earlier ones (about +5 dB)
+const SUB_SURGE: f32 = 3.0;
+// Rise of the sub band against the mid/high bands, so that a louder mix overall
+// is no drop
+const SUB_RATIO_RISE: f32 = 1.5;
+// Recent sub band energy (mean square) needed, about -40 dB
+const SUB_FLOOR: f32 = 1e-4;
+
+/// Mean square of the second half of `samples` above `threshold` times the
+/// first half, and loud enough.
+pub fn envelope_surge(samples: &[f32], threshold: f32) -> bool {
+    let split_index = samples.len() / 2;
+    let mean_square =
+        |part: &[f32]| part.iter().map(|&val| val * val).sum::<f32>() / part.len().max(1) as f32;
+    let history_energy = mean_square(&samples[..split_index]);
+    let current_energy = mean_square(&samples[split_index..]);
+    current_energy > history_energy * threshold && current_energy > 0.04
+}
+
+/// Energy of the sub band (below `SPLIT_HZ`) and of the mid/high bands of the
+/// input, over the last `HISTORY_BLOCKS` blocks.
+pub struct DropDetector {
+    sub_filter: DirectForm2Transposed<f32>,
+    rest_filter: DirectForm2Transposed<f32>,
+    block_len: usize,
+    // Sums of squares of the block being filled
+    block: (f32, f32, usize),
+    // Mean squares (sub, mid/high) of the complete blocks, oldest first
+    history: VecDeque<(f32, f32)>,
+}
+
+impl DropDetector {
+    pub fn new(sample_rate: u32) -> Result<Self, String> {
+        let fs = Hertz::<f32>::from_hz(sample_rate as f32)
+            .map_err(|_| "Invalid sample rate".to_string())?;
+        let f0 = Hertz::<f32>::from_hz(SPLIT_HZ).map_err(|_| "Invalid cutoff".to_string())?;
+        let coefficients = |filter| {
+            Coefficients::<f32>::from_params(filter, fs, f0, Q_BUTTERWORTH_F32)
+                .map_err(|e| format!("Drop detector filter: {:?}", e))
+        };
+        Ok(Self {
+            sub_filter: DirectForm2Transposed::<f32>::new(coefficients(Type::LowPass)?),
+            rest_filter: DirectForm2Transposed::<f32>::new(coefficients(Type::HighPass)?),
+            block_len: ((sample_rate as f32 * BLOCK_S) as usize).max(1),
+            block: (0.0, 0.0, 0),
+            history: VecDeque::with_capacity(HISTORY_BLOCKS),
+        })
+    }
+
+    /// Feeds input samples (before the analysis filter).
+    pub fn push(&mut self, samples: &[f32]) {
+        for &x in samples {
+            let sub = self.sub_filter.run(x);
+            let rest = self.rest_filter.run(x);
+            self.accumulate(sub * sub, rest * rest, self.block_len);
+        }
+    }
+
+    /// Feeds an onset envelope computed outside of this crate, sampled at `rate`
+    /// (see `BpmAnalyzer::process_envelope`). Without the audio there are no
+    /// bands to split: the envelope stands for the sub band and the mid/high
+    /// bands stay empty, so a drop is the envelope surging against its history.
+    pub fn push_envelope(&mut self, envelope: &[f32], rate: f32) {
+        let block_len = ((rate * BLOCK_S) as usize).max(1);
+        for &x in envelope {
+            self.accumulate(x * x, 0.0, block_len);
+        }
+    }
+
+    fn accumulate(&mut self, sub: f32, rest: f32, block_len: usize) {
+        self.block.0 += sub;
+        self.block.1 += rest;
+        self.block.2 += 1;
+        if self.block.2 >= block_len {
+            if self.history.len() == HISTORY_BLOCKS {
+                self.history.pop_front();
+            }
+            let len = self.block.2 as f32;
+            self.history
+                .push_back((self.block.0 / len, self.block.1 / len));
+            self.block = (0.0, 0.0, 0);
+        }
+    }
+
+    // Mean sub and mid/high energies of the earlier and of the recent blocks
+    fn energies(&self) -> Option<((f32, f32), (f32, f32))> {
+        if self.history.len() < HISTORY_BLOCKS {
+            return None;
+        }
+        let mean = |blocks: &mut dyn Iterator<Item = &(f32, f32)>, len: usize| {
+            let (sub, rest) = blocks.fold((0.0, 0.0), |(sub, rest), b| (sub + b.0, rest + b.1));
+            (sub / len as f32, rest / len as f32)
+        };
+        let split = self.history.len() - RECENT_BLOCKS;
+        Some((
+            mean(&mut self.history.iter().take(split), split),
+            mean(&mut self.history.iter().skip(split), RECENT_BLOCKS),
+        ))
+    }
+
+    /// Recent sub band energy over the earlier one, 0 until the history is full.
+    pub fn sub_surge(&self) -> f32 {
+        self.energies().map_or(0.0, |((sub, _), (recent, _))| {
+            recent / sub.max(f32::EPSILON)
+        })
+    }
+
+    /// Whether the bass just came back: the sub band surged against its recent
+    /// history, more than the mid/high bands did.
+    pub fn is_bass_return(&self) -> bool {
+        let Some(((sub, rest), (recent_sub, recent_rest))) = self.energies() else {
+            return false;
+        };
+        let ratio = sub / rest.max(f32::EPSILON);
+        let recent_ratio = recent_sub / recent_rest.max(f32::EPSILON);
+        recent_sub >= SUB_FLOOR
+            && recent_sub >= sub * SUB_SURGE
+            && recent_ratio >= ratio * SUB_RATIO_RISE
+    }
+}
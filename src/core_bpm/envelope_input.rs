@@ -0,0 +1,105 @@
+use crate::config;
+use std::error::Error;
+use std::io::Read;
+use std::net::UdpSocket;
+use std::sync::mpsc::Sender;
+use std::thread;
+use tracing::{error, info};
+
+/// Source of an onset envelope computed outside of this crate.
+/// Values are little-endian `f32`, sampled at `BpmAnalyzer::fine_rate()`,
+/// and are meant to be fed to `BpmAnalyzer::process_envelope`.
+pub enum EnvelopeSource {
+    /// Datagrams received on the given bind address (e.g. "0.0.0.0:9300")
+    Udp(String),
+    /// Continuous stream on standard input
+    Stdin,
+}
+
+impl EnvelopeSource {
+    /// `udp:<bind address>` or `stdin`.
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn Error>> {
+        match spec.split_once(':') {
+            Some(("udp", addr)) if !addr.is_empty() => Ok(EnvelopeSource::Udp(addr.to_string())),
+            _ if spec == "stdin" => Ok(EnvelopeSource::Stdin),
+            _ => Err(format!(
+                "Unknown envelope source '{}', expected udp:<addr> or stdin",
+                spec
+            )
+            .into()),
+        }
+    }
+}
+
+/// Value of the `--envelope <udp:addr|stdin>` command line option.
+pub fn cli_source() -> Result<Option<EnvelopeSource>, Box<dyn Error>> {
+    match config::cli_option("--envelope") {
+        Some(Some(spec)) => EnvelopeSource::parse(&spec).map(Some),
+        Some(None) => Err("--envelope needs a source, udp:<addr> or stdin".into()),
+        None => Ok(None),
+    }
+}
+
+/// Starts a reader thread that forwards decoded envelope blocks to `sender`.
+/// The thread stops when the source closes or the receiver is dropped.
+pub fn spawn_envelope_input(
+    source: EnvelopeSource,
+    sender: Sender<Vec<f32>>,
+) -> Result<thread::JoinHandle<()>, Box<dyn Error>> {
+    match source {
+        EnvelopeSource::Udp(addr) => {
+            // Bind before spawning so that address errors reach the caller
+            let socket = UdpSocket::bind(&addr)?;
+            info!("Envelope input listening on UDP {}", addr);
+            Ok(thread::spawn(move || {
+                let mut buf = [0u8; 65536];
+                loop {
+                    match socket.recv(&mut buf) {
+                        Ok(n) => {
+                            if sender.send(decode_f32_le(&buf[..n])).is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            error!("Envelope input UDP error: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }))
+        }
+        EnvelopeSource::Stdin => {
+            info!("Envelope input reading from stdin");
+            Ok(thread::spawn(move || {
+                let mut stdin = std::io::stdin().lock();
+                let mut buf = [0u8; 4096];
+                // Bytes of an incomplete value kept for the next read
+                let mut pending: Vec<u8> = Vec::with_capacity(4);
+                loop {
+                    let n = match stdin.read(&mut buf) {
+                        Ok(0) => break,
+                        Ok(n) => n,
+                        Err(e) => {
+                            error!("Envelope input stdin error: {}", e);
+                            break;
+                        }
+                    };
+                    pending.extend_from_slice(&buf[..n]);
+                    let complete = pending.len() - pending.len() % 4;
+                    let values = decode_f32_le(&pending[..complete]);
+                    pending.drain(..complete);
+                    if sender.send(values).is_err() {
+                        break;
+                    }
+                }
+            }))
+        }
+    }
+}
+
+fn decode_f32_le(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
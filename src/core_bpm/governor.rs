@@ -0,0 +1,109 @@
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::info;
+
+/// `[governor]` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GovernorConfig {
+    pub enabled: bool,
+    /// Fraction of one core the analysis may use (0.3 = 30%)
+    pub cpu_budget: f32,
+}
+
+impl Default for GovernorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cpu_budget: 0.3,
+        }
+    }
+}
+
+/// Analysis parameters chosen by the governor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalysisLevel {
+    pub coarse_step: usize,
+    pub window_duration: Duration,
+}
+
+/// Keeps the per-hop analysis time within a CPU budget by trading resolution.
+pub struct CpuGovernor {
+    config: GovernorConfig,
+    level: usize,
+    avg_load: Option<f32>,
+    hops_since_change: u32,
+}
+
+impl CpuGovernor {
+    /// Analysis resolutions, from the most precise to the cheapest.
+    /// (coarse_step, window_duration in ms). The fine search radius (50 samples)
+    /// must stay above the coarse step so that the refinement still covers one coarse bin.
+    const LEVELS: [(usize, u64); 4] = [(22, 2000), (28, 1750), (36, 1500), (44, 1250)];
+
+    // Exponential moving average factor for the measured load
+    const LOAD_SMOOTHING: f32 = 0.2;
+    // Hops to wait after a change before adjusting again (buffers need to refill)
+    const COOLDOWN_HOPS: u32 = 10;
+    // Load below budget * ratio allows stepping back to a finer resolution
+    const RELAX_RATIO: f32 = 0.5;
+
+    pub fn new(config: GovernorConfig) -> Self {
+        Self {
+            config,
+            level: 0,
+            avg_load: None,
+            hops_since_change: 0,
+        }
+    }
+
+    /// Records the time spent analyzing one hop of `hop_duration` audio.
+    /// Returns the new parameters when the resolution must change.
+    pub fn record(&mut self, elapsed: Duration, hop_duration: Duration) -> Option<AnalysisLevel> {
+        if !self.config.enabled || hop_duration.is_zero() {
+            return None;
+        }
+
+        let load = elapsed.as_secs_f32() / hop_duration.as_secs_f32();
+        let avg = match self.avg_load {
+            Some(avg) => avg + Self::LOAD_SMOOTHING * (load - avg),
+            None => load,
+        };
+        self.avg_load = Some(avg);
+
+        self.hops_since_change += 1;
+        if self.hops_since_change < Self::COOLDOWN_HOPS {
+            return None;
+        }
+
+        let budget = self.config.cpu_budget;
+        let new_level = if avg > budget && self.level + 1 < Self::LEVELS.len() {
+            self.level + 1
+        } else if avg < budget * Self::RELAX_RATIO && self.level > 0 {
+            self.level - 1
+        } else {
+            return None;
+        };
+
+        info!(
+            "[Governor] Load {:.0}% (budget {:.0}%): level {} -> {}",
+            avg * 100.0,
+            budget * 100.0,
+            self.level,
+            new_level
+        );
+        self.level = new_level;
+        self.hops_since_change = 0;
+        // Start over from the next measurement, the cost has changed
+        self.avg_load = None;
+        Some(Self::level_params(new_level))
+    }
+
+    fn level_params(level: usize) -> AnalysisLevel {
+        let (coarse_step, window_ms) = Self::LEVELS[level];
+        AnalysisLevel {
+            coarse_step,
+            window_duration: Duration::from_millis(window_ms),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Swing of the off-beat 8th notes, measured on the broadband energy of the
+//! analysis window once the tempo is known.
+
+// Energy frames the onsets are taken from
+const FRAME_S: f32 = 0.005;
+// Positions within a beat the onsets are folded into
+const PHASE_BINS: usize = 48;
+// Off-beat positions searched, in share of a beat after the beat: straight 8ths
+// (0.5) up to a little past the triplet shuffle (2/3). 16th hats at 0.75 stay out.
+const OFF_BEAT_RANGE: (f32, f32) = (0.46, 0.71);
+// Off-beat onsets weaker than this share of the beat onsets give no estimate
+const MIN_OFF_BEAT_RATIO: f32 = 0.1;
+// Beats needed in the window for a meaningful fold
+const MIN_BEATS: f32 = 3.0;
+
+/// Position of the off-beat 8th in percent of a beat: 50 is straight, 66.7 a
+/// triplet shuffle. The odd/even inter-onset ratio is `swing / (100 - swing)`.
+/// `energy` is the mean-square input level at `rate` values per second, `bpm`
+/// the tempo of the window. `None` without clear off-beat onsets.
+pub fn estimate_swing(energy: &[f32], rate: f32, bpm: f32) -> Option<f32> {
+    if bpm <= 0.0 || rate <= 0.0 {
+        return None;
+    }
+    let frame_len = ((rate * FRAME_S) as usize).max(1);
+    let frames: Vec<f32> = energy
+        .chunks(frame_len)
+        .map(|chunk| (chunk.iter().sum::<f32>() / chunk.len() as f32).sqrt())
+        .collect();
+    let frame_s = frame_len as f32 / rate;
+    let beat_frames = 60.0 / bpm / frame_s;
+    if (frames.len() as f32) < beat_frames * MIN_BEATS {
+        return None;
+    }
+
+    // Rising level only (RMS, so that quieter hats still count), folded on the beat period
+    let mut phases = [0.0f32; PHASE_BINS];
+    for (i, pair) in frames.windows(2).enumerate() {
+        let onset = (pair[1] - pair[0]).max(0.0);
+        let phase = ((i + 1) as f32 / beat_frames).fract();
+        phases[(phase * PHASE_BINS as f32) as usize % PHASE_BINS] += onset;
+    }
+
+    let (beat_bin, &beat_strength) = phases
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))?;
+    if beat_strength <= 0.0 {
+        return None;
+    }
+    let first = (OFF_BEAT_RANGE.0 * PHASE_BINS as f32).ceil() as usize;
+    let last = (OFF_BEAT_RANGE.1 * PHASE_BINS as f32).floor() as usize;
+    let bin = |offset: usize| phases[(beat_bin + offset) % PHASE_BINS];
+    let offset = (first..=last).max_by(|&a, &b| bin(a).total_cmp(&bin(b)))?;
+    if bin(offset) < beat_strength * MIN_OFF_BEAT_RATIO {
+        return None;
+    }
+
+    let position = (offset as f32 + peak_shift(&phases, beat_bin + offset)
+        - peak_shift(&phases, beat_bin))
+        / PHASE_BINS as f32;
+    Some((position * 100.0).clamp(50.0, 75.0))
+}
+
+// Sub-bin position of the peak at `bin`, from a parabola through its neighbours
+fn peak_shift(phases: &[f32; PHASE_BINS], bin: usize) -> f32 {
+    let at = |i: usize| phases[i % PHASE_BINS];
+    let (left, center, right) = (at(bin + PHASE_BINS - 1), at(bin), at(bin + 1));
+    let curvature = left - 2.0 * center + right;
+    if curvature < 0.0 {
+        (0.5 * (left - right) / curvature).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,85 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Distribution of the tempos detected over a sliding period.
+/// Makes octave flip-flopping visible (two peaks at x and 2x) and gives a
+/// stable "set tempo" readout, e.g. for labelling a recorded DJ set.
+pub struct TempoHistogram {
+    period: Duration,
+    bin_width: f32,
+    entries: VecDeque<(Instant, f32)>,
+}
+
+/// Snapshot for display or export.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HistogramSnapshot {
+    pub period_s: u64,
+    pub bin_width: f32,
+    /// (bin center in BPM, detections), sorted by BPM
+    pub bins: Vec<(f32, u32)>,
+    /// Mean of the detections falling in the most populated bin
+    pub set_tempo: Option<f32>,
+    pub total: u32,
+}
+
+impl TempoHistogram {
+    pub fn new(period: Duration, bin_width: f32) -> Self {
+        Self {
+            period,
+            bin_width: bin_width.max(0.1),
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, bpm: f32) {
+        self.push_at(bpm, Instant::now());
+    }
+
+    /// `push` with an explicit clock, for replaying recordings in accelerated time.
+    pub fn push_at(&mut self, bpm: f32, now: Instant) {
+        self.entries.push_back((now, bpm));
+        while let Some(&(t, _)) = self.entries.front() {
+            if now.duration_since(t) > self.period {
+                self.entries.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        // Bin index -> (count, sum of BPMs)
+        let mut bins: BTreeMap<i64, (u32, f32)> = BTreeMap::new();
+        for &(t, bpm) in &self.entries {
+            if t.elapsed() > self.period {
+                continue;
+            }
+            let bin = bins
+                .entry((bpm / self.bin_width).round() as i64)
+                .or_default();
+            bin.0 += 1;
+            bin.1 += bpm;
+        }
+
+        let set_tempo = bins
+            .values()
+            .max_by_key(|(count, _)| *count)
+            .map(|&(count, sum)| sum / count as f32);
+
+        HistogramSnapshot {
+            period_s: self.period.as_secs(),
+            bin_width: self.bin_width,
+            total: bins.values().map(|(count, _)| count).sum(),
+            bins: bins
+                .into_iter()
+                .map(|(index, (count, _))| (index as f32 * self.bin_width, count))
+                .collect(),
+            set_tempo,
+        }
+    }
+}
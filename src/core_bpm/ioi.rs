@@ -0,0 +1,159 @@
+//! Tempo induction from inter-onset intervals (after Dixon, 2001), the IOI half
+//! of the `IoiFusion` estimator. Every pair of onsets of the window gives an
+//! interval, the intervals are clustered, and each cluster is scored with the
+//! clusters at its integer multiples: the beat collects the bars and the
+//! half-bars, while a dotted figure (3:2 of the beat) finds few multiples.
+
+use serde::Serialize;
+
+// Intervals closer than this share a cluster
+const CLUSTER_WIDTH_S: f32 = 0.025;
+// Longest interval clustered, a bar at 100 BPM
+const MAX_IOI_S: f32 = 2.5;
+// Onsets closer than this are one hit
+const MIN_ONSET_GAP_S: f32 = 0.06;
+// Onset strength threshold, in standard deviations above the mean
+const ONSET_THRESHOLD: f32 = 1.5;
+// Multiples of a cluster's interval that support it, and their weights: the
+// closest multiples count most (Dixon's f(n))
+const MULTIPLES: usize = 8;
+
+fn multiple_weight(n: usize) -> f32 {
+    if n <= 4 { (6 - n) as f32 } else { 1.0 }
+}
+
+/// A tempo the onsets of the window could follow.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TempoHypothesis {
+    pub bpm: f32,
+    /// Score relative to the best hypothesis (1.0)
+    pub weight: f32,
+}
+
+/// Peak of the onset strength curve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onset {
+    /// Position in samples
+    pub at: usize,
+    pub strength: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Cluster {
+    interval: f32,
+    count: usize,
+    /// Salience of the onset pairs, the weaker onset of each pair
+    salience: f32,
+}
+
+/// Peaks of an onset strength curve: local maxima above `ONSET_THRESHOLD`
+/// deviations, at least `MIN_ONSET_GAP_S` apart.
+pub fn pick_onsets(strength: &[f32], rate: f32, out: &mut Vec<Onset>) {
+    out.clear();
+    if strength.len() < 3 {
+        return;
+    }
+    let mean = strength.iter().sum::<f32>() / strength.len() as f32;
+    let variance = strength
+        .iter()
+        .map(|x| (x - mean) * (x - mean))
+        .sum::<f32>()
+        / strength.len() as f32;
+    let threshold = mean + ONSET_THRESHOLD * variance.sqrt();
+    let min_gap = (rate * MIN_ONSET_GAP_S) as usize;
+
+    for i in 1..strength.len() - 1 {
+        let x = strength[i];
+        if x <= threshold || x < strength[i - 1] || x <= strength[i + 1] {
+            continue;
+        }
+        let onset = Onset { at: i, strength: x };
+        match out.last_mut() {
+            Some(last) if i - last.at < min_gap => {
+                if x > last.strength {
+                    *last = onset;
+                }
+            }
+            _ => out.push(onset),
+        }
+    }
+}
+
+/// Tempo hypotheses of `onsets` (positions at `rate`) within
+/// `min_bpm..=max_bpm`, strongest first. Intervals count with the salience of
+/// their onsets, so that a steady kick outweighs lighter syncopated hits.
+/// Empty with fewer than three onsets.
+pub fn induce(onsets: &[Onset], rate: f32, min_bpm: f32, max_bpm: f32) -> Vec<TempoHypothesis> {
+    if onsets.len() < 3 || rate <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut clusters: Vec<Cluster> = Vec::new();
+    for (i, first) in onsets.iter().enumerate() {
+        for second in &onsets[i + 1..] {
+            let interval = (second.at - first.at) as f32 / rate;
+            let salience = first.strength.min(second.strength);
+            if interval > MAX_IOI_S {
+                break;
+            }
+            match clusters
+                .iter_mut()
+                .find(|c| (c.interval - interval).abs() < CLUSTER_WIDTH_S)
+            {
+                Some(cluster) => {
+                    cluster.interval = (cluster.interval * cluster.count as f32 + interval)
+                        / (cluster.count + 1) as f32;
+                    cluster.count += 1;
+                    cluster.salience += salience;
+                }
+                None => clusters.push(Cluster {
+                    interval,
+                    count: 1,
+                    salience,
+                }),
+            }
+        }
+    }
+
+    let mut hypotheses: Vec<TempoHypothesis> = clusters
+        .iter()
+        .filter_map(|cluster| {
+            let bpm = 60.0 / cluster.interval;
+            if !(min_bpm..=max_bpm).contains(&bpm) {
+                return None;
+            }
+            let mut weight = 0.0;
+            for other in &clusters {
+                let n = (other.interval / cluster.interval).round() as usize;
+                let error = (other.interval - n as f32 * cluster.interval).abs();
+                if (1..=MULTIPLES).contains(&n) && error < CLUSTER_WIDTH_S {
+                    weight += multiple_weight(n) * other.salience;
+                }
+            }
+            Some(TempoHypothesis { bpm, weight })
+        })
+        .collect();
+
+    hypotheses.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+    if let Some(best) = hypotheses.first().map(|h| h.weight).filter(|&w| w > 0.0) {
+        for hypothesis in &mut hypotheses {
+            hypothesis.weight /= best;
+        }
+    }
+    hypotheses
+}
+
+/// Weight of the hypotheses within `tolerance` (share of the tempo) of `bpm`,
+/// of its half or of its double: subdivisions and groupings by two lie on the
+/// same grid, a tempo a third away does not.
+pub fn support(hypotheses: &[TempoHypothesis], bpm: f32, tolerance: f32) -> f32 {
+    hypotheses
+        .iter()
+        .filter(|h| {
+            [0.5, 1.0, 2.0]
+                .iter()
+                .any(|octave| (h.bpm - bpm * octave).abs() <= bpm * octave * tolerance)
+        })
+        .map(|h| h.weight)
+        .sum()
+}
@@ -0,0 +1,235 @@
+use super::analyzer::{AudioFilter, FilterOrder, FilterType};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::fmt;
+
+/// `[key]` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct KeyConfig {
+    /// Estimate the musical key next to the tempo (more CPU, off on the devices by default)
+    pub enabled: bool,
+    /// Seconds of audio the key is estimated over
+    pub window_s: u64,
+}
+
+impl Default for KeyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_s: 30,
+        }
+    }
+}
+
+// Rate the input is decimated to before the pitch analysis
+const ANALYSIS_RATE: f32 = 11025.0;
+// Samples per chroma frame at the analysis rate (about 0.37 s)
+const FRAME_LEN: usize = 4096;
+// Pitches measured: four octaves from C3, below the kicks are too loud
+const LOWEST_NOTE: u32 = 48;
+const OCTAVES: u32 = 4;
+// Audio needed before a key is reported
+const MIN_SECONDS: f32 = 8.0;
+// Correlation with the best key profile below which no key is reported
+const MIN_CORRELATION: f32 = 0.5;
+// Frames quieter than this (mean square) are left out
+const SILENCE: f32 = 1e-6;
+
+// Krumhansl-Kessler probe-tone profiles, from the tonic up
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+const PITCH_NAMES: [&str; 12] = [
+    "C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
+];
+
+/// Musical key: tonic pitch class (0 = C) and mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub tonic: u8,
+    pub minor: bool,
+}
+
+impl Key {
+    /// Position on the Camelot wheel ("8B" C major, "8A" A minor). Tracks one step
+    /// apart, or with the same number, mix harmonically.
+    pub fn camelot(&self) -> String {
+        // Relative major of a minor key shares its number
+        let major_tonic = if self.minor {
+            (self.tonic + 3) % 12
+        } else {
+            self.tonic
+        };
+        // Each step on the wheel is a fifth up, C major is 8
+        let number = (7 * major_tonic as u32 + 7) % 12 + 1;
+        format!("{}{}", number, if self.minor { 'A' } else { 'B' })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode = if self.minor { "minor" } else { "major" };
+        write!(f, "{} {}", PITCH_NAMES[self.tonic as usize % 12], mode)
+    }
+}
+
+// Reported in Camelot notation by the HTTP API
+impl Serialize for Key {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.camelot())
+    }
+}
+
+/// Chroma (energy per pitch class) of the recent input, matched against the
+/// major and minor key profiles. The input is band-limited, decimated, and cut
+/// into frames whose pitch energies are measured with Goertzel filters.
+pub struct KeyDetector {
+    filter: AudioFilter,
+    step: usize,
+    chunk_sum: f32,
+    chunk_len: usize,
+    frame: Vec<f32>,
+    window: Vec<f32>,
+    // Goertzel coefficient of each measured pitch, with its pitch class
+    pitches: Vec<(usize, f32)>,
+    chroma: VecDeque<[f32; 12]>,
+    max_frames: usize,
+    frame_s: f32,
+}
+
+impl KeyDetector {
+    pub fn new(sample_rate: u32, config: &KeyConfig) -> Result<Self, String> {
+        let rate = sample_rate as f32;
+        let step = ((rate / ANALYSIS_RATE).round() as usize).max(1);
+        let frame_rate = rate / step as f32;
+        let pitches = (LOWEST_NOTE..LOWEST_NOTE + 12 * OCTAVES)
+            .map(|note| {
+                let freq = 440.0 * 2f32.powf((note as f32 - 69.0) / 12.0);
+                (
+                    (note % 12) as usize,
+                    2.0 * (2.0 * PI * freq / frame_rate).cos(),
+                )
+            })
+            .collect();
+        let window = (0..FRAME_LEN)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FRAME_LEN as f32).cos())
+            .collect();
+        let frame_s = FRAME_LEN as f32 / frame_rate;
+        Ok(Self {
+            filter: AudioFilter::new(
+                FilterType::BandPass(100.0, 2100.0),
+                rate,
+                FilterOrder::Order4,
+            )?,
+            step,
+            chunk_sum: 0.0,
+            chunk_len: 0,
+            frame: Vec::with_capacity(FRAME_LEN),
+            window,
+            pitches,
+            chroma: VecDeque::new(),
+            max_frames: ((config.window_s as f32 / frame_s) as usize).max(1),
+            frame_s,
+        })
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        for &x in samples {
+            self.chunk_sum += self.filter.process(x);
+            self.chunk_len += 1;
+            if self.chunk_len < self.step {
+                continue;
+            }
+            self.frame.push(self.chunk_sum / self.step as f32);
+            self.chunk_sum = 0.0;
+            self.chunk_len = 0;
+            if self.frame.len() == FRAME_LEN {
+                self.analyze_frame();
+                self.frame.clear();
+            }
+        }
+    }
+
+    fn analyze_frame(&mut self) {
+        let energy = self.frame.iter().map(|x| x * x).sum::<f32>() / FRAME_LEN as f32;
+        if energy < SILENCE {
+            return;
+        }
+        let mut chroma = [0.0f32; 12];
+        for &(pitch_class, coefficient) in &self.pitches {
+            let (mut s1, mut s2) = (0.0f32, 0.0f32);
+            for (&x, &w) in self.frame.iter().zip(&self.window) {
+                let s0 = x * w + coefficient * s1 - s2;
+                s2 = s1;
+                s1 = s0;
+            }
+            let power = s1 * s1 + s2 * s2 - coefficient * s1 * s2;
+            chroma[pitch_class] += power.max(0.0).sqrt();
+        }
+        // Every frame weighs the same, loud passages do not take over
+        let peak = chroma.iter().fold(0.0f32, |a, &b| a.max(b));
+        if peak <= 0.0 {
+            return;
+        }
+        for value in &mut chroma {
+            *value /= peak;
+        }
+        if self.chroma.len() >= self.max_frames {
+            self.chroma.pop_front();
+        }
+        self.chroma.push_back(chroma);
+    }
+
+    /// Key of the recent input, `None` until `MIN_SECONDS` of audio were heard or
+    /// when no key profile fits.
+    pub fn key(&self) -> Option<Key> {
+        if (self.chroma.len() as f32) * self.frame_s < MIN_SECONDS {
+            return None;
+        }
+        let mut total = [0.0f32; 12];
+        for frame in &self.chroma {
+            for (sum, value) in total.iter_mut().zip(frame) {
+                *sum += value;
+            }
+        }
+        let mut best: Option<(f32, Key)> = None;
+        for tonic in 0..12u8 {
+            let rotated: [f32; 12] = std::array::from_fn(|i| total[(tonic as usize + i) % 12]);
+            for (profile, minor) in [(&MAJOR_PROFILE, false), (&MINOR_PROFILE, true)] {
+                let r = correlation(&rotated, profile);
+                if best.is_none_or(|(best_r, _)| r > best_r) {
+                    best = Some((r, Key { tonic, minor }));
+                }
+            }
+        }
+        best.filter(|(r, _)| *r >= MIN_CORRELATION)
+            .map(|(_, key)| key)
+    }
+
+    /// Forgets the chroma (next track).
+    pub fn reset(&mut self) {
+        self.chroma.clear();
+    }
+}
+
+// Pearson correlation of two pitch-class vectors
+fn correlation(a: &[f32; 12], b: &[f32; 12]) -> f32 {
+    let mean_a = a.iter().sum::<f32>() / 12.0;
+    let mean_b = b.iter().sum::<f32>() / 12.0;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+    if var_a <= 0.0 || var_b <= 0.0 {
+        return 0.0;
+    }
+    cov / (var_a * var_b).sqrt()
+}
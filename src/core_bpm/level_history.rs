@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Input level over the last minutes, one mean RMS point per interval.
+/// Shows whether detection dropouts come with level problems (silence, unplugged
+/// source, gain running away). Kept by the devices, shown by the desktop window.
+pub struct LevelHistory {
+    interval: Duration,
+    capacity: usize,
+    levels: VecDeque<f32>,
+    // Interval being averaged
+    started: Option<Instant>,
+    sum: f32,
+    count: u32,
+}
+
+/// Snapshot for display or export.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelSnapshot {
+    pub interval_s: f32,
+    /// Mean RMS of each interval (0..1 full scale), oldest first
+    pub levels: Vec<f32>,
+    /// End of the last interval on the device clock (µs, `GET /clock`), to place
+    /// the histories of several devices on one timeline
+    #[serde(default)]
+    pub end_us: Option<u64>,
+}
+
+impl LevelSnapshot {
+    /// Age (s) at `now` and level of each point, oldest first, given the local
+    /// instant of the end of the last interval.
+    pub fn timeline(&self, end: Instant, now: Instant) -> Vec<(f32, f32)> {
+        let end_age = now.saturating_duration_since(end).as_secs_f32();
+        let last = self.levels.len().saturating_sub(1);
+        self.levels
+            .iter()
+            .enumerate()
+            .map(|(i, &level)| (end_age + (last - i) as f32 * self.interval_s, level))
+            .collect()
+    }
+}
+
+impl LevelHistory {
+    pub fn new(interval: Duration, period: Duration) -> Self {
+        let capacity = (period.as_secs_f32() / interval.as_secs_f32().max(0.01)) as usize;
+        Self {
+            interval,
+            capacity: capacity.max(1),
+            levels: VecDeque::with_capacity(capacity.max(1)),
+            started: None,
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Adds the RMS of a capture packet. Returns true when an interval was completed.
+    pub fn push(&mut self, rms: f32) -> bool {
+        self.push_at(rms, Instant::now())
+    }
+
+    /// `push` with an explicit clock, for replaying recordings in accelerated time.
+    pub fn push_at(&mut self, rms: f32, now: Instant) -> bool {
+        let started = *self.started.get_or_insert(now);
+        self.sum += rms;
+        self.count += 1;
+        if now.duration_since(started) < self.interval {
+            return false;
+        }
+        if self.levels.len() >= self.capacity {
+            self.levels.pop_front();
+        }
+        self.levels.push_back(self.sum / self.count as f32);
+        self.started = Some(now);
+        self.sum = 0.0;
+        self.count = 0;
+        true
+    }
+
+    /// End of the last completed interval.
+    pub fn last_point(&self) -> Option<Instant> {
+        self.started.filter(|_| !self.levels.is_empty())
+    }
+
+    /// Snapshot without `end_us`, which the device API sets on its own clock.
+    pub fn snapshot(&self) -> LevelSnapshot {
+        LevelSnapshot {
+            interval_s: self.interval.as_secs_f32(),
+            levels: self.levels.iter().copied().collect(),
+            end_us: None,
+        }
+    }
+}
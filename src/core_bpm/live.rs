@@ -0,0 +1,356 @@
+use super::analyzer::{AnalysisResult, BpmAnalyzer, BpmAnalyzerConfig, ConfidenceThreshold};
+use super::beat_features::{BeatFeatureSampler, BeatFeatures};
+use super::beat_grid::{BeatGridConfig, BeatGridRecorder};
+use super::governor::{CpuGovernor, GovernorConfig};
+use super::key::{Key, KeyConfig, KeyDetector};
+use super::recorder::{InputRecorder, LowConfidenceWatch, RecordingConfig};
+use super::set_stats::{SetReport, SetStats, StatsConfig};
+use super::verification::{TempoVerifier, VerificationConfig};
+use crate::metrics::METRICS;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Live analysis steps shared by the GUI, embedded and headless loops: hops of
+/// `BpmAnalyzerConfig::hop_duration` scheduled by the analyzer, CPU governor,
+/// per-beat band levels and re-initialization when the capture rate or the
+/// analyzer settings change. The loops only add their own outputs (display,
+/// Link, Art-Net, HTTP status) around it.
+pub struct LiveAnalysis {
+    analyzer: BpmAnalyzer,
+    config: BpmAnalyzerConfig,
+    governor: CpuGovernor,
+    governor_config: GovernorConfig,
+    sample_rate: u32,
+    // Only when an output uses the band levels (Art-Net chase channels)
+    beat_sampler: Option<BeatFeatureSampler>,
+    beat_features: Vec<BeatFeatures>,
+    // Last seconds of input for `save_recording` (`[recording]`)
+    recorder: Option<InputRecorder>,
+    recording: RecordingConfig,
+    low_confidence: Option<LowConfidenceWatch>,
+    // Musical key of the input (`[key]`)
+    key: Option<KeyDetector>,
+    key_config: KeyConfig,
+    // Beats of the set written to a file (`[beat_grid]`)
+    beat_grid: Option<BeatGridRecorder>,
+    // Long-window confirmation of the tempo sent to the outputs (`[verification]`)
+    verifier: Option<TempoVerifier>,
+    // Figures of the set, also written to a file with `[stats] enabled`
+    stats: SetStats,
+    stats_config: StatsConfig,
+    stats_saved: Instant,
+}
+
+impl LiveAnalysis {
+    pub fn new(
+        sample_rate: u32,
+        config: BpmAnalyzerConfig,
+        governor_config: GovernorConfig,
+        beat_features: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        let beat_sampler = if beat_features {
+            Some(BeatFeatureSampler::new(sample_rate)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            analyzer: BpmAnalyzer::new(sample_rate, Some(config))?,
+            config,
+            governor: CpuGovernor::new(governor_config.clone()),
+            governor_config,
+            sample_rate,
+            beat_sampler,
+            beat_features: Vec::new(),
+            recorder: None,
+            recording: RecordingConfig::default(),
+            low_confidence: None,
+            key: None,
+            key_config: KeyConfig::default(),
+            beat_grid: None,
+            verifier: None,
+            stats: SetStats::new(),
+            stats_config: StatsConfig::default(),
+            stats_saved: Instant::now(),
+        })
+    }
+
+    pub fn analyzer(&self) -> &BpmAnalyzer {
+        &self.analyzer
+    }
+
+    pub fn analyzer_mut(&mut self) -> &mut BpmAnalyzer {
+        &mut self.analyzer
+    }
+
+    pub fn config(&self) -> BpmAnalyzerConfig {
+        self.config
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Queues a capture packet for the next hop. Returns the band levels of the last
+    /// beat completed by this packet, if any.
+    pub fn push(&mut self, packet: &[f32]) -> Option<BeatFeatures> {
+        self.analyzer.push_samples(packet);
+        if let Some(recorder) = &mut self.recorder {
+            recorder.push(packet);
+        }
+        if let Some(key) = &mut self.key {
+            key.push(packet);
+        }
+        if let Some(verifier) = &mut self.verifier {
+            verifier.push(packet);
+        }
+        let sampler = self.beat_sampler.as_mut()?;
+        sampler.push(packet, &mut self.beat_features);
+        self.beat_features.drain(..).next_back()
+    }
+
+    /// Whether enough audio is queued for `analyze`.
+    pub fn hop_ready(&self) -> bool {
+        self.analyzer.hop_ready()
+    }
+
+    /// Analyzes the queued audio, then lets the governor adapt the resolution to the
+    /// time it took.
+    pub fn analyze(&mut self) -> Result<Option<AnalysisResult>, Box<dyn Error>> {
+        let samples = self.analyzer.pending_samples();
+        let started = Instant::now();
+        let mut processed = self.analyzer.process_pending();
+        let elapsed = started.elapsed();
+        let hop_duration = Duration::from_secs_f32(samples as f32 / self.sample_rate as f32);
+        METRICS.frames_processed(samples);
+        METRICS.analysis(
+            elapsed,
+            match &processed {
+                Ok(Some(result)) => Some((result.bpm, result.confidence)),
+                _ => None,
+            },
+        );
+        self.stats.record(
+            hop_duration,
+            match &processed {
+                Ok(Some(result)) => Some((result.bpm, result.is_drop)),
+                _ => None,
+            },
+        );
+        let interval = Duration::from_secs(self.stats_config.save_interval_s);
+        if self.stats_config.enabled && self.stats_saved.elapsed() >= interval {
+            self.stats_saved = Instant::now();
+            if let Some(Err(e)) = self.save_statistics() {
+                warn!("Failed to save the set statistics: {}", e);
+            }
+        }
+        if let Some(grid) = &mut self.beat_grid {
+            grid.advance(hop_duration);
+            if let Ok(Some(result)) = &processed {
+                grid.record(result.bpm, self.analyzer.last_beat_age());
+            }
+            // Rewritten as it grows, the process may be killed without warning
+            let saved = grid.save_due().then(|| grid.save().map(|_| ()));
+            if let Some(Err(e)) = saved {
+                warn!("Failed to save the beat grid: {}", e);
+            }
+        }
+        if let Some(level) = self.governor.record(elapsed, hop_duration) {
+            self.analyzer
+                .set_resolution(level.coarse_step, level.window_duration);
+        }
+        if let (Ok(Some(result)), Some(sampler)) = (&processed, &mut self.beat_sampler) {
+            sampler.set_grid(result.bpm, self.analyzer.last_beat_age());
+        }
+        if let Ok(Some(AnalysisResult {
+            tempo_change: Some(change),
+            ..
+        })) = &processed
+        {
+            info!("Tempo change: {:.1} -> {:.1} BPM", change.from, change.to);
+            // Next track, possibly in another key
+            if let Some(key) = &mut self.key {
+                key.reset();
+            }
+        }
+        if let (Ok(Some(result)), Some(key)) = (&mut processed, &self.key) {
+            result.key = key.key();
+        }
+        if let Some(verifier) = &mut self.verifier {
+            let fast = match &processed {
+                Ok(Some(result)) => Some(result.bpm),
+                _ => None,
+            };
+            match verifier.update(fast) {
+                Ok(verified) => {
+                    if let Ok(Some(result)) = &mut processed {
+                        result.verified_bpm = verified;
+                    }
+                }
+                Err(e) => warn!("Verification pass failed: {}", e),
+            }
+        }
+        if let Some(watch) = &mut self.low_confidence {
+            let confidence = match &processed {
+                Ok(Some(result)) => Some(result.confidence),
+                _ => None,
+            };
+            if watch.update(confidence, Instant::now()) {
+                match self.save_recording() {
+                    Ok(path) => info!("Low confidence for a while, audio saved to {:?}", path),
+                    Err(e) => warn!("Failed to save the low-confidence audio: {}", e),
+                }
+            }
+        }
+        processed
+    }
+
+    /// Keeps the last `[recording] seconds` of input for `save_recording`, and
+    /// saves them on its own after a low-confidence stretch with `auto`.
+    pub fn set_recording(&mut self, config: RecordingConfig) {
+        self.recorder = config
+            .enabled
+            .then(|| InputRecorder::new(config.seconds, self.sample_rate));
+        self.low_confidence =
+            (config.enabled && config.auto).then(|| LowConfidenceWatch::new(&config));
+        self.recording = config;
+    }
+
+    /// Estimates the musical key of the input into `AnalysisResult::key` with
+    /// `[key] enabled`.
+    pub fn set_key_detection(&mut self, config: KeyConfig) -> Result<(), Box<dyn Error>> {
+        self.key = if config.enabled {
+            Some(KeyDetector::new(self.sample_rate, &config)?)
+        } else {
+            None
+        };
+        self.key_config = config;
+        Ok(())
+    }
+
+    /// Key of the input, `None` until detected or without `[key] enabled`.
+    pub fn key(&self) -> Option<Key> {
+        self.key.as_ref().and_then(|key| key.key())
+    }
+
+    /// Confirms the tempo over a long window before it is passed on as
+    /// `AnalysisResult::verified_bpm` with `[verification] enabled`.
+    pub fn set_verification(&mut self, config: VerificationConfig) -> Result<(), Box<dyn Error>> {
+        self.verifier = if config.enabled {
+            Some(TempoVerifier::new(self.sample_rate, self.config, config)?)
+        } else {
+            None
+        };
+        Ok(())
+    }
+
+    /// Writes the beats of the input to `[beat_grid] directory` with `enabled`, a
+    /// new file for each run.
+    pub fn set_beat_grid(&mut self, config: BeatGridConfig) {
+        self.beat_grid = config.enabled.then(|| BeatGridRecorder::new(config));
+    }
+
+    /// Writes the beat grid so far, returns `None` with `[beat_grid]` disabled.
+    pub fn save_beat_grid(&mut self) -> Option<Result<PathBuf, Box<dyn Error>>> {
+        let grid = self.beat_grid.as_mut()?;
+        Some(grid.save().map(Path::to_path_buf))
+    }
+
+    /// Rewrites the statistics of the set to `[stats] directory` every
+    /// `save_interval_s` with `enabled`.
+    pub fn set_statistics(&mut self, config: StatsConfig) {
+        self.stats_config = config;
+    }
+
+    /// Drops, tempo range and time locked since the start of the set.
+    pub fn statistics(&self) -> SetReport {
+        self.stats.report()
+    }
+
+    /// Starts a new set (and a new statistics file).
+    pub fn reset_statistics(&mut self) {
+        self.stats = SetStats::new();
+    }
+
+    /// Writes the statistics so far, returns `None` with `[stats]` disabled.
+    pub fn save_statistics(&self) -> Option<Result<PathBuf, Box<dyn Error>>> {
+        self.stats_config
+            .enabled
+            .then(|| self.stats.report().save(&self.stats_config.directory))
+    }
+
+    /// Writes the recorded input to a WAV file in `[recording] directory`.
+    pub fn save_recording(&self) -> Result<PathBuf, Box<dyn Error>> {
+        let recorder = self
+            .recorder
+            .as_ref()
+            .ok_or("Recording disabled ([recording] enabled = false)")?;
+        recorder.save(&self.recording.directory)
+    }
+
+    /// Drops the queued audio (analysis paused or gated).
+    pub fn clear(&mut self) {
+        self.analyzer.clear_pending();
+    }
+
+    /// Audio stream restarted: drops the queued audio, the analyzer warm-starts
+    /// (see `BpmAnalyzer::reset_soft`) and keeps reporting the tempo.
+    pub fn reset(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(verifier) = &mut self.verifier {
+            verifier.reset(self.sample_rate)?;
+        }
+        self.analyzer.reset_soft(self.sample_rate)
+    }
+
+    /// Follows a capture rate change without losing the tempo, the octave
+    /// preference or the governor resolution (see `BpmAnalyzer::reset_soft`).
+    pub fn set_sample_rate(&mut self, sample_rate: u32) -> Result<(), Box<dyn Error>> {
+        self.analyzer.reset_soft(sample_rate)?;
+        self.sample_rate = sample_rate;
+        if self.beat_sampler.is_some() {
+            self.beat_sampler = Some(BeatFeatureSampler::new(sample_rate)?);
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.set_sample_rate(sample_rate);
+        }
+        if self.key.is_some() {
+            self.key = Some(KeyDetector::new(sample_rate, &self.key_config)?);
+        }
+        if let Some(verifier) = &mut self.verifier {
+            verifier.reset(sample_rate)?;
+        }
+        Ok(())
+    }
+
+    /// Tunes the confidence thresholds live, kept across restarts and rebuilds.
+    pub fn set_thresholds(&mut self, thresholds: ConfidenceThreshold) {
+        self.config.thresholds = thresholds;
+        self.analyzer.set_thresholds(thresholds);
+    }
+
+    /// Restarts the analysis with new settings, keeping the tracked tempo and the
+    /// octave preference. The current settings stay in place when the new ones
+    /// are rejected.
+    pub fn set_config(&mut self, config: BpmAnalyzerConfig) -> Result<(), Box<dyn Error>> {
+        self.rebuild(self.sample_rate, config)
+    }
+
+    fn rebuild(
+        &mut self,
+        sample_rate: u32,
+        config: BpmAnalyzerConfig,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut analyzer = BpmAnalyzer::new(sample_rate, Some(config))?;
+        analyzer.restore(&self.analyzer.snapshot());
+        if let Some(verifier) = &mut self.verifier {
+            verifier.set_analyzer_config(sample_rate, config)?;
+        }
+        self.analyzer = analyzer;
+        self.config = config;
+        self.sample_rate = sample_rate;
+        // The new analyzer starts at full resolution
+        self.governor = CpuGovernor::new(self.governor_config.clone());
+        Ok(())
+    }
+}
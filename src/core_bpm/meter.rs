@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+/// Peaks at or above this (about -0.1 dBFS) count as clipping.
+pub const CLIP_LEVEL: f32 = 0.99;
+
+/// Peak and RMS of the captured audio, measured in the capture callback and
+/// published every `period` as `AudioMessage::Level`. The GUI meter, the OLED
+/// audio bar and the Art-Net energy channel all read it.
+pub struct LevelMeter {
+    period_samples: usize,
+    peak: f32,
+    sum_sq: f32,
+    count: usize,
+}
+
+impl LevelMeter {
+    /// `channels` interleaved channels at `sample_rate`.
+    pub fn new(sample_rate: u32, channels: u16, period: Duration) -> Self {
+        let frames = (sample_rate as f64 * period.as_secs_f64()) as usize;
+        Self {
+            period_samples: (frames * channels.max(1) as usize).max(1),
+            peak: 0.0,
+            sum_sq: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Measures a packet. Returns `(peak, rms)` once a period of audio was measured.
+    pub fn push(&mut self, samples: &[f32]) -> Option<(f32, f32)> {
+        for &x in samples {
+            self.peak = self.peak.max(x.abs());
+            self.sum_sq += x * x;
+        }
+        self.count += samples.len();
+        if self.count < self.period_samples {
+            return None;
+        }
+        let level = (self.peak, (self.sum_sq / self.count as f32).sqrt());
+        self.peak = 0.0;
+        self.sum_sq = 0.0;
+        self.count = 0;
+        Some(level)
+    }
+}
@@ -1,10 +1,48 @@
 pub mod analyzer;
 pub mod audio;
+pub mod audio_channel;
+pub mod beat_features;
+pub mod beat_grid;
+pub mod beat_pll;
+pub mod comb;
+pub mod correlation;
+pub mod cues;
+pub mod decimator;
+pub mod drop_detector;
+pub mod envelope_input;
+pub mod governor;
+pub mod groove;
+pub mod histogram;
+pub mod ioi;
+pub mod key;
+pub mod level_history;
+pub mod live;
+pub mod meter;
+pub mod monitor;
+pub mod offline;
 pub mod pid_audio;
+pub mod pipeline;
+pub mod preset;
+pub mod recorder;
+pub mod rekordbox;
+pub mod sections;
+pub mod set_stats;
+pub mod simulated;
+pub mod software_agc;
+pub mod tempo_tracker;
+pub mod testsig;
+pub mod verification;
 
-pub use analyzer::BpmAnalyzer;
+pub use analyzer::AnalysisDebug;
+pub use analyzer::EnvelopeFrame;
 pub use audio::AudioCapture;
 pub use audio::AudioMessage;
+pub use audio_channel::audio_channel;
+pub use live::LiveAnalysis;
 
-#[cfg(all(any(target_arch = "aarch64", target_arch = "arm"), target_os = "linux"))]
-pub use pid_audio::pid_audio::AudioPID;
+#[cfg(all(
+    feature = "embedded-hw",
+    any(target_arch = "aarch64", target_arch = "arm"),
+    target_os = "linux"
+))]
+pub use pid_audio::pid_audio::{AudioPID, open_mixer};
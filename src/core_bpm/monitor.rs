@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Input queued for the output at most, beyond that the oldest audio is dropped.
+// Capture packets can be half a second long (see `AudioCapture::new`).
+const MAX_QUEUE: Duration = Duration::from_secs(1);
+// Beat click: a short decaying sine burst
+pub const CLICK_HZ: f32 = 1000.0;
+const CLICK_LENGTH_S: f32 = 0.03;
+const CLICK_DECAY_S: f32 = 0.008;
+
+/// What the monitor output plays.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MonitorSource {
+    /// The audio the analyzer gets
+    #[default]
+    Input,
+    /// A click on each beat of the tracker
+    Click,
+    /// The input with the click on top, to hear how the beats line up
+    Both,
+}
+
+/// `[monitor]` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MonitorConfig {
+    pub enabled: bool,
+    /// Output device, the default one when unset
+    pub device: Option<String>,
+    pub source: MonitorSource,
+    /// 0.0 - 1.0
+    pub volume: f32,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            device: None,
+            source: MonitorSource::Input,
+            volume: 0.5,
+        }
+    }
+}
+
+/// Mix of the monitor output, rendered by the output stream (see
+/// `AudioMonitor`). The input is resampled to the output rate and queued; the
+/// click follows the beat grid of the analyzer.
+pub struct MonitorMix {
+    source: MonitorSource,
+    volume: f32,
+    output_rate: u32,
+    input_rate: u32,
+    queue: VecDeque<f32>,
+    // Position of the next output sample after the last input sample, and that
+    // sample, for the linear interpolation across packets
+    position: f64,
+    last_input: f32,
+    // Arrival of the newest queued sample
+    last_push: Option<Instant>,
+    // (bpm, time of a beat)
+    beat: Option<(f32, Instant)>,
+}
+
+impl MonitorMix {
+    pub fn new(output_rate: u32, source: MonitorSource, volume: f32) -> Self {
+        Self {
+            source,
+            volume: volume.clamp(0.0, 1.0),
+            output_rate: output_rate.max(1),
+            input_rate: output_rate.max(1),
+            queue: VecDeque::new(),
+            position: 0.0,
+            last_input: 0.0,
+            last_push: None,
+            beat: None,
+        }
+    }
+
+    pub fn source(&self) -> MonitorSource {
+        self.source
+    }
+
+    pub fn set_source(&mut self, source: MonitorSource) {
+        self.source = source;
+        self.queue.clear();
+    }
+
+    /// Sample rate of the captured audio, dropping the queue.
+    pub fn set_input_rate(&mut self, rate: u32) {
+        if rate != self.input_rate {
+            self.input_rate = rate.max(1);
+            self.queue.clear();
+            self.position = 0.0;
+        }
+    }
+
+    /// Queues captured audio, as fed to the analyzer, received at `now`.
+    pub fn push_input(&mut self, samples: &[f32], now: Instant) {
+        if self.source == MonitorSource::Click || samples.is_empty() {
+            return;
+        }
+        // Linear interpolation, `step` input samples per output sample
+        let step = self.input_rate as f64 / self.output_rate as f64;
+        let mut previous = self.last_input;
+        let mut position = self.position;
+        for &sample in samples {
+            while position < 1.0 {
+                self.queue
+                    .push_back(previous + (sample - previous) * position as f32);
+                position += step;
+            }
+            position -= 1.0;
+            previous = sample;
+        }
+        self.position = position;
+        self.last_input = previous;
+        self.last_push = Some(now);
+
+        let max = (self.output_rate as f64 * MAX_QUEUE.as_secs_f64()) as usize;
+        if self.queue.len() > max {
+            let excess = self.queue.len() - max;
+            self.queue.drain(..excess);
+        }
+    }
+
+    /// Beat grid of the analyzer: `bpm`, last beat `beat_age` before `now`.
+    pub fn set_beat(&mut self, bpm: f32, beat_age: Duration, now: Instant) {
+        self.beat = (bpm > 0.0)
+            .then(|| now.checked_sub(beat_age).map(|beat| (bpm, beat)))
+            .flatten();
+    }
+
+    pub fn clear_beat(&mut self) {
+        self.beat = None;
+    }
+
+    /// Input samples waiting for the output.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Fills `out` (mono) with the next samples, the first one played at
+    /// `playback`. Mixed with the input, the clicks are placed on the capture
+    /// time of the input they play over, so that they line up as the analyzer
+    /// heard it.
+    pub fn render(&mut self, out: &mut [f32], playback: Instant) {
+        let input = self.source != MonitorSource::Click;
+        let click = self.source != MonitorSource::Input;
+        let rate = self.output_rate as f32;
+        let start = match self.last_push.filter(|_| input && !self.queue.is_empty()) {
+            Some(pushed) => {
+                let queued = Duration::from_secs_f32(self.queue.len() as f32 / rate);
+                pushed.checked_sub(queued).unwrap_or(pushed)
+            }
+            None => playback,
+        };
+        // Seconds from the last beat before `start`
+        let beat = self.beat.filter(|_| click).map(|(bpm, at)| {
+            let period = 60.0 / bpm;
+            let since = if start >= at {
+                start.duration_since(at).as_secs_f32()
+            } else {
+                -at.duration_since(start).as_secs_f32()
+            };
+            (period, since.rem_euclid(period))
+        });
+
+        for (i, sample) in out.iter_mut().enumerate() {
+            let mut value = if input {
+                self.queue.pop_front().unwrap_or(0.0)
+            } else {
+                0.0
+            };
+            if let Some((period, offset)) = beat {
+                value += click_sample((offset + i as f32 / rate) % period, CLICK_HZ);
+            }
+            *sample = (value * self.volume).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+/// Click level `since_beat_s` seconds after a beat, pitched at `hz`, silent
+/// after `CLICK_LENGTH_S`. The metronome pitches its accents up (see
+/// `ClickKind::frequency`).
+pub fn click_sample(since_beat_s: f32, hz: f32) -> f32 {
+    if !(0.0..CLICK_LENGTH_S).contains(&since_beat_s) {
+        return 0.0;
+    }
+    let phase = 2.0 * std::f32::consts::PI * hz * since_beat_s;
+    phase.sin() * (-since_beat_s / CLICK_DECAY_S).exp()
+}
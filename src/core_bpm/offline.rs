@@ -0,0 +1,285 @@
+use super::analyzer::{BpmAnalyzer, BpmAnalyzerConfig};
+use super::beat_grid::{BeatGridExport, BeatGridFormat};
+use super::cues::{self, BeatGrid, CuePoint, KickEnvelope};
+use super::rekordbox;
+use crate::config;
+use serde::Serialize;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+// Hop between two analyses, same as the live loops
+const HOP_S: f32 = 0.5;
+// Relative deviation still counted as the same tempo
+const SAME_TEMPO: f32 = 0.02;
+// Consecutive estimates a new tempo needs before it splits the current segment
+const CONFIRM_HOPS: usize = 4;
+// Without estimate for this long (silence, beatless break) the segment is closed
+const MAX_GAP_S: f32 = 4.0;
+
+/// Region of a file with a stable tempo.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TempoSegment {
+    pub start_s: f32,
+    pub end_s: f32,
+    /// Median of the estimates in the segment
+    pub bpm: f32,
+}
+
+impl TempoSegment {
+    pub fn duration_s(&self) -> f32 {
+        self.end_s - self.start_s
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileAnalysis {
+    pub path: PathBuf,
+    pub duration_s: f32,
+    pub segments: Vec<TempoSegment>,
+    /// Beat grid of each segment
+    pub grids: Vec<BeatGrid>,
+    /// Phrase boundaries and energy transitions
+    pub cues: Vec<CuePoint>,
+}
+
+impl FileAnalysis {
+    /// Tempo of the longest segment.
+    pub fn main_bpm(&self) -> Option<f32> {
+        self.segments
+            .iter()
+            .max_by(|a, b| a.duration_s().total_cmp(&b.duration_s()))
+            .map(|segment| segment.bpm)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Estimate {
+    // End of the analysis window
+    time_s: f32,
+    bpm: f32,
+}
+
+/// Decodes a WAV file, splits it into tempo-stable segments and marks its cue points.
+pub fn analyze_file(
+    path: &Path,
+    config: BpmAnalyzerConfig,
+) -> Result<FileAnalysis, Box<dyn Error>> {
+    let (samples, sample_rate) = read_wav(path)?;
+    let segments = analyze_samples(&samples, sample_rate, config)?;
+    let envelope = KickEnvelope::new(&samples, sample_rate)?;
+    let duration_s = samples.len() as f32 / sample_rate as f32;
+    let grids = cues::beat_grids(&envelope, &segments, duration_s);
+    let cues = cues::detect_cues(&envelope, &grids);
+    Ok(FileAnalysis {
+        path: path.to_path_buf(),
+        duration_s,
+        segments,
+        grids,
+        cues,
+    })
+}
+
+/// Runs the analyzer over mono `samples` hop by hop and groups the results into segments.
+pub fn analyze_samples(
+    samples: &[f32],
+    sample_rate: u32,
+    config: BpmAnalyzerConfig,
+) -> Result<Vec<TempoSegment>, Box<dyn Error>> {
+    let mut analyzer = BpmAnalyzer::new(sample_rate, Some(config))?;
+    let hop = ((sample_rate as f32 * HOP_S) as usize).max(1);
+    let mut estimates = Vec::new();
+    let mut position = 0;
+    for chunk in samples.chunks(hop) {
+        position += chunk.len();
+        if let Some(result) = analyzer.process(chunk)? {
+            estimates.push(Estimate {
+                time_s: position as f32 / sample_rate as f32,
+                bpm: result.bpm,
+            });
+        }
+    }
+    let duration_s = samples.len() as f32 / sample_rate as f32;
+    Ok(segment(
+        &estimates,
+        config.window_duration.as_secs_f32(),
+        duration_s,
+    ))
+}
+
+/// Groups consecutive estimates of the same tempo. A different tempo has to hold for
+/// `CONFIRM_HOPS` estimates before it opens a new segment, shorter deviations (octave
+/// slips, breaks) are dropped. An estimate describes the window ending at its time,
+/// so a tempo change is placed between the window centers of the two tempos.
+fn segment(estimates: &[Estimate], window_s: f32, duration_s: f32) -> Vec<TempoSegment> {
+    let mut segments = Vec::new();
+    let mut current: Vec<Estimate> = Vec::new();
+    let mut pending: Vec<Estimate> = Vec::new();
+    let mut start_s = 0.0;
+
+    for &estimate in estimates {
+        let last = pending.last().or(current.last()).map(|e| e.time_s);
+        if last.is_some_and(|t| estimate.time_s - t > MAX_GAP_S) {
+            if let Some(end) = current.last() {
+                segments.push(close(start_s, end.time_s, &current));
+            }
+            current.clear();
+            pending.clear();
+        }
+        if current.is_empty() {
+            start_s = (estimate.time_s - window_s).max(0.0);
+            current.push(estimate);
+            continue;
+        }
+
+        if same_tempo(estimate.bpm, median(&current)) {
+            current.push(estimate);
+            pending.clear();
+            continue;
+        }
+        if !pending.is_empty() && !same_tempo(estimate.bpm, median(&pending)) {
+            pending.clear();
+        }
+        pending.push(estimate);
+
+        if pending.len() >= CONFIRM_HOPS {
+            let old_end = current[current.len() - 1].time_s;
+            let boundary = (old_end + pending[0].time_s) / 2.0 - window_s / 2.0;
+            let boundary = boundary.max(start_s);
+            segments.push(close(start_s, boundary, &current));
+            start_s = boundary;
+            current = std::mem::take(&mut pending);
+        }
+    }
+    if let Some(end) = current.last() {
+        segments.push(close(start_s, end.time_s.min(duration_s), &current));
+    }
+    segments
+}
+
+fn close(start_s: f32, end_s: f32, estimates: &[Estimate]) -> TempoSegment {
+    TempoSegment {
+        start_s,
+        end_s,
+        bpm: median(estimates),
+    }
+}
+
+/// Whether `a` is within `SAME_TEMPO` of `b`.
+pub fn same_tempo(a: f32, b: f32) -> bool {
+    (a - b).abs() <= b * SAME_TEMPO
+}
+
+fn median(estimates: &[Estimate]) -> f32 {
+    let mut bpms: Vec<f32> = estimates.iter().map(|e| e.bpm).collect();
+    bpms.sort_by(|a, b| a.total_cmp(b));
+    bpms.get(bpms.len() / 2).copied().unwrap_or(0.0)
+}
+
+/// Mono samples in -1..1 and the sample rate of a WAV file (integer or float PCM).
+pub fn read_wav(path: &Path) -> Result<(Vec<f32>, u32), Box<dyn Error>> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 * scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    let channels = spec.channels.max(1) as usize;
+    let mono = interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    Ok((mono, spec.sample_rate))
+}
+
+/// Files given with `--analyze <file>...` (every argument up to the next flag).
+pub fn cli_files() -> Vec<PathBuf> {
+    config::cli_values("--analyze")
+        .into_iter()
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Value of the `<flag> <file>` command line option.
+fn cli_path(flag: &str) -> Option<PathBuf> {
+    config::cli_option(flag).flatten().map(PathBuf::from)
+}
+
+/// Analyzes `files` and prints their segments and cues. The report is also written as
+/// JSON with `--json <file>`, as a Rekordbox collection with `--rekordbox <file>`, and
+/// the beats of each file next to it with `--beat-grid <csv|json>`.
+pub fn run_cli(files: &[PathBuf], config: BpmAnalyzerConfig) -> Result<(), Box<dyn Error>> {
+    let beat_grid =
+        match cli_path("--beat-grid") {
+            Some(format) => {
+                let format = format.to_string_lossy();
+                Some(BeatGridFormat::parse(&format).ok_or_else(|| {
+                    format!("Unknown beat grid format '{}' (csv or json)", format)
+                })?)
+            }
+            None => None,
+        };
+    let mut analyses = Vec::new();
+    for path in files {
+        match analyze_file(path, config) {
+            Ok(analysis) => analyses.push(analysis),
+            Err(e) => eprintln!("Failed to analyze {:?}: {}", path, e),
+        }
+    }
+
+    for analysis in &analyses {
+        let main_bpm = analysis
+            .main_bpm()
+            .map(|bpm| format!(": {:.1} BPM", bpm))
+            .unwrap_or_default();
+        println!(
+            "{} ({}){}",
+            analysis.path.display(),
+            format_time(analysis.duration_s),
+            main_bpm
+        );
+        if analysis.segments.is_empty() {
+            println!("  no tempo detected");
+        }
+        for segment in &analysis.segments {
+            println!(
+                "  {} - {}  {:.1} BPM",
+                format_time(segment.start_s),
+                format_time(segment.end_s),
+                segment.bpm
+            );
+        }
+        for cue in &analysis.cues {
+            println!("    {}  {}", format_time(cue.time_s), cue.name);
+        }
+    }
+    if let Some(path) = cli_path("--json") {
+        std::fs::write(&path, serde_json::to_string_pretty(&analyses)?)?;
+        println!("Report written to {:?}", path);
+    }
+    if let Some(path) = cli_path("--rekordbox") {
+        rekordbox::write_xml(&path, &analyses)?;
+        println!("Rekordbox collection written to {:?}", path);
+    }
+    if let Some(format) = beat_grid {
+        for analysis in &analyses {
+            // `set.wav` -> `set.beats.csv`
+            let path = analysis
+                .path
+                .with_extension(format!("beats.{}", format.extension()));
+            BeatGridExport::from_analysis(analysis).write(&path, format)?;
+            println!("Beat grid written to {:?}", path);
+        }
+    }
+    Ok(())
+}
+
+fn format_time(seconds: f32) -> String {
+    let seconds = seconds.max(0.0) as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
@@ -1,7 +1,13 @@
-#[cfg(all(any(target_arch = "aarch64", target_arch = "arm"), target_os = "linux"))]
+#[cfg(all(
+    feature = "embedded-hw",
+    any(target_arch = "aarch64", target_arch = "arm"),
+    target_os = "linux"
+))]
 pub mod pid_audio {
+    use alsa::PollDescriptors;
     use alsa::mixer::{Selem, SelemChannelId, SelemId};
     use std::time::Instant;
+    use tracing::{error, info};
     pub struct AudioPID {
         kp: f32,
         ki: f32,
@@ -14,6 +20,8 @@ pub mod pid_audio {
         selem_id: SelemId,
         rms_window: usize,
         rms_history: Vec<f32>,
+        // Dernier volume écrit dans ALSA, pour détecter les changements externes
+        last_gain: i64,
     }
 
     impl AudioPID {
@@ -45,30 +53,114 @@ pub mod pid_audio {
             selem
                 .set_capture_volume(SelemChannelId::FrontLeft, gain)
                 .map_err(|e| format!("set_capture_volume Error: {}", e))?;
+            self.last_gain = gain;
             Ok((gain, rms))
         }
 
+        /// Traite les événements du mixer et détecte un volume de capture modifié
+        /// hors du programme (alsamixer, amixer...). Le PID repart alors de ce volume
+        /// (intégrale recalculée) au lieu de l'écraser brutalement.
+        /// Retourne le nouveau volume en cas de changement externe.
+        pub fn sync_external_change(&mut self, mixer: &alsa::Mixer) -> Option<i64> {
+            // Mixer ouvert en mode bloquant: on ne lit les événements que s'il y en a
+            let pending = mixer
+                .get()
+                .and_then(|mut fds| alsa::poll::poll(&mut fds, 0))
+                .unwrap_or(0);
+            if pending > 0 {
+                if let Err(e) = mixer.handle_events() {
+                    error!("Mixer events error: {}", e);
+                    return None;
+                }
+            }
+            let current = mixer
+                .find_selem(&self.selem_id)?
+                .get_capture_volume(SelemChannelId::FrontLeft)
+                .ok()?;
+            if current == self.last_gain {
+                return None;
+            }
+            self.last_gain = current;
+
+            // Sortie PID = kp * e + ki * I (+ kd * d): on choisit I pour retrouver `current`
+            let target = current.clamp(self.output_min, self.output_max);
+            if self.ki != 0.0 {
+                self.integral = (target as f32 - self.kp * self.prev_error) / self.ki;
+            }
+            Some(current)
+        }
+
+        /// Change les gains et la fenêtre RMS à chaud, sans toucher au volume courant
+        pub fn set_params(&mut self, kp: f32, ki: f32, kd: f32, rms_window: usize) {
+            // Intégrale recalculée pour que la sortie ne saute pas avec le nouveau ki
+            if ki != 0.0 {
+                self.integral = (self.last_gain as f32 - kp * self.prev_error) / ki;
+            }
+            self.kp = kp;
+            self.ki = ki;
+            self.kd = kd;
+            self.rms_window = rms_window.max(1);
+            while self.rms_history.len() > self.rms_window {
+                self.rms_history.remove(0);
+            }
+        }
+
+        /// Repart d'un volume sauvegardé (redémarrage en cours de set) au lieu du
+        /// milieu de la plage. Retourne le volume appliqué, borné à la plage.
+        pub fn restore_gain(&mut self, gain: i64, mixer: &alsa::Mixer) -> Result<i64, String> {
+            let gain = gain.clamp(self.output_min, self.output_max);
+            mixer
+                .find_selem(&self.selem_id)
+                .ok_or_else(|| "Impossible de retrouver le contrôle audio".to_string())?
+                .set_capture_volume(SelemChannelId::FrontLeft, gain)
+                .map_err(|e| format!("set_capture_volume Error: {}", e))?;
+            self.last_gain = gain;
+            // Intégrale choisie pour que la sortie du PID reparte de ce volume
+            if self.ki != 0.0 {
+                self.integral = (gain as f32 - self.kp * self.prev_error) / self.ki;
+            }
+            Ok(gain)
+        }
+
+        /// Dernier volume de capture connu
+        pub fn gain(&self) -> i64 {
+            self.last_gain
+        }
+
+        /// `control` = nom du contrôle de volume de capture, le premier de la carte si `None`
         pub fn new(
             kp: f32,
             ki: f32,
             kd: f32,
             rms_window: usize,
             mixer: &alsa::Mixer,
+            control: Option<&str>,
         ) -> Result<Self, String> {
             let mut found = None;
             for elem in mixer.iter() {
                 // On tente de créer un Selem à partir de l'élément
-                if let Some(selem) = Selem::new(elem) {
-                    if selem.has_capture_volume() {
-                        let (min, max) = selem.get_capture_volume_range();
-                        let id = selem.get_id();
-                        found = Some((id, min, max));
-                        break; // On a trouvé notre bonheur
-                    }
+                let Some(selem) = Selem::new(elem) else {
+                    continue;
+                };
+                let id = selem.get_id();
+                let wanted = control.is_none_or(|name| id.get_name().is_ok_and(|n| n == name));
+                if wanted && selem.has_capture_volume() {
+                    let (min, max) = selem.get_capture_volume_range();
+                    found = Some((id, min, max));
+                    break; // On a trouvé notre bonheur
                 }
             }
-            let (selem_id, output_min, mut output_max) =
-                found.ok_or_else(|| "No capture Selem found in mixer".to_string())?;
+            let (selem_id, output_min, mut output_max) = found.ok_or_else(|| {
+                let available = capture_controls(mixer);
+                match control {
+                    Some(name) => format!(
+                        "Contrôle de capture '{}' introuvable (disponibles: {})",
+                        name,
+                        available.join(", ")
+                    ),
+                    None => "No capture Selem found in mixer".to_string(),
+                }
+            })?;
 
             output_max -= 4; // Ajustement pour éviter les dépassements
             // Configure le volume au milieu de la plage
@@ -77,7 +169,7 @@ pub mod pid_audio {
                 let _ = selem.set_capture_volume(SelemChannelId::FrontLeft, mid);
             }
 
-            println!(
+            info!(
                 "AudioPID initialized | Capture Volume Range: {} - {} | Volume set to middle: {}",
                 output_min, output_max, mid
             );
@@ -93,10 +185,10 @@ pub mod pid_audio {
                 selem_id,
                 rms_window,
                 rms_history: Vec::with_capacity(rms_window),
+                last_gain: mid,
             })
         }
 
-        #[allow(dead_code)]
         pub fn reset(&mut self) {
             self.prev_error = 0.0;
             self.integral = 0.0;
@@ -129,4 +221,38 @@ pub mod pid_audio {
             Ok(output.round() as i64)
         }
     }
+
+    /// Ouvre le mixer de `card` ("hw:0"). En cas d'échec, l'erreur liste les cartes
+    /// présentes pour corriger la section `[alsa]`.
+    pub fn open_mixer(card: &str) -> Result<alsa::Mixer, String> {
+        alsa::Mixer::new(card, false).map_err(|e| {
+            format!(
+                "Carte ALSA '{}' inaccessible: {} (cartes: {})",
+                card,
+                e,
+                list_cards().join(", ")
+            )
+        })
+    }
+
+    /// Cartes son présentes, sous la forme "hw:<index> (<nom>)"
+    pub fn list_cards() -> Vec<String> {
+        alsa::card::Iter::new()
+            .filter_map(|card| card.ok())
+            .map(|card| {
+                let name = card.get_name().unwrap_or_default();
+                format!("hw:{} ({})", card.get_index(), name)
+            })
+            .collect()
+    }
+
+    // Noms des contrôles du mixer ayant un volume de capture
+    fn capture_controls(mixer: &alsa::Mixer) -> Vec<String> {
+        mixer
+            .iter()
+            .filter_map(Selem::new)
+            .filter(|selem| selem.has_capture_volume())
+            .filter_map(|selem| selem.get_id().get_name().ok().map(str::to_string))
+            .collect()
+    }
 }
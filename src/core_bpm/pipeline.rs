@@ -0,0 +1,123 @@
+use super::analyzer::{
+    AnalysisDebug, BpmAnalyzer, Rejection, SearchParams, SearchScratch, WindowEstimate,
+};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+
+/// Envelope windows of one hop, with the settings they were captured with.
+pub struct AnalysisWindow {
+    pub coarse: VecDeque<f32>,
+    pub fine: VecDeque<f32>,
+    pub params: SearchParams,
+    /// Analyzer state the window belongs to, results of older states are dropped
+    pub generation: u64,
+}
+
+/// Outcome of a window once both searches ran (or one of them rejected it).
+pub struct WindowResult {
+    pub estimate: Result<WindowEstimate, Rejection>,
+    pub debug: AnalysisDebug,
+    pub generation: u64,
+}
+
+// Coarse lag of a window, waiting for the fine search
+struct CoarseLag {
+    window: AnalysisWindow,
+    lag: usize,
+    confidence: f32,
+    debug: AnalysisDebug,
+}
+
+/// Runs the coarse search of a window on one thread while the fine search refines the
+/// previous window on another, so the audio loop never waits on the correlations.
+/// On a single core it still moves the analysis out of the capture path.
+pub struct AnalysisPipeline {
+    windows: SyncSender<AnalysisWindow>,
+    results: Receiver<WindowResult>,
+}
+
+impl AnalysisPipeline {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        // One window queued per stage: a stalled stage makes `submit` skip windows
+        // instead of piling up copies of the buffers
+        let (window_tx, window_rx) = mpsc::sync_channel::<AnalysisWindow>(1);
+        let (lag_tx, lag_rx) = mpsc::sync_channel::<CoarseLag>(1);
+        let (result_tx, result_rx) = mpsc::channel();
+        let fine_result_tx = result_tx.clone();
+
+        thread::Builder::new()
+            .name("bpm-coarse".into())
+            .spawn(move || {
+                let mut scratch = SearchScratch::default();
+                while let Ok(window) = window_rx.recv() {
+                    let mut debug = AnalysisDebug::default();
+                    let sent = match BpmAnalyzer::coarse_search(
+                        &window.coarse,
+                        &window.params,
+                        &mut scratch,
+                        &mut debug,
+                    ) {
+                        Ok((lag, confidence)) => lag_tx
+                            .send(CoarseLag {
+                                window,
+                                lag,
+                                confidence,
+                                debug,
+                            })
+                            .is_ok(),
+                        Err(reason) => result_tx
+                            .send(WindowResult {
+                                estimate: Err(reason),
+                                debug,
+                                generation: window.generation,
+                            })
+                            .is_ok(),
+                    };
+                    if !sent {
+                        break;
+                    }
+                }
+            })?;
+
+        thread::Builder::new()
+            .name("bpm-fine".into())
+            .spawn(move || {
+                let mut scratch = SearchScratch::default();
+                while let Ok(coarse) = lag_rx.recv() {
+                    let estimate = BpmAnalyzer::fine_search(
+                        &coarse.window.fine,
+                        coarse.lag,
+                        coarse.confidence,
+                        &coarse.window.params,
+                        &mut scratch,
+                    );
+                    let result = WindowResult {
+                        estimate,
+                        debug: coarse.debug,
+                        generation: coarse.window.generation,
+                    };
+                    if fine_result_tx.send(result).is_err() {
+                        break;
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            windows: window_tx,
+            results: result_rx,
+        })
+    }
+
+    /// Queues a window. Returns false (and drops the window) while the threads are
+    /// still behind on earlier ones.
+    pub fn submit(&self, window: AnalysisWindow) -> bool {
+        self.windows.try_send(window).is_ok()
+    }
+
+    /// Next finished window, if any.
+    pub fn try_recv(&self) -> Option<WindowResult> {
+        self.results.try_recv().ok()
+    }
+}
@@ -0,0 +1,40 @@
+use crate::core_bpm::analyzer::BpmAnalyzerConfig;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Named analyzer settings (window, BPM range, thresholds, input filter) that can
+/// be shared between users. Stored as TOML, or JSON when the file ends in `.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyzerPreset {
+    pub name: String,
+    #[serde(default)]
+    pub analyzer: BpmAnalyzerConfig,
+}
+
+impl AnalyzerPreset {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+        let preset: Self = if is_json(path) {
+            serde_json::from_str(&content)?
+        } else {
+            toml::from_str(&content)?
+        };
+        preset.analyzer.validate()?;
+        Ok(preset)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let content = if is_json(path) {
+            serde_json::to_string_pretty(self)?
+        } else {
+            toml::to_string_pretty(self)?
+        };
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
@@ -0,0 +1,147 @@
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// `[recording]` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    /// Keep the last `seconds` of input in memory (about 100 kB per second at 48 kHz)
+    pub enabled: bool,
+    pub seconds: u64,
+    /// Directory of the `capture-<unix time>.wav` files
+    pub directory: PathBuf,
+    /// Save a capture when the confidence stays below `low_confidence`
+    pub auto: bool,
+    pub low_confidence: f32,
+    /// Consecutive low-confidence hops (`[analysis] hop_ms` each) before an automatic capture
+    pub low_confidence_hops: usize,
+    /// Minimum time between two automatic captures
+    pub cooldown_s: u64,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            seconds: 30,
+            directory: PathBuf::from("."),
+            auto: false,
+            low_confidence: 0.3,
+            low_confidence_hops: 20,
+            cooldown_s: 600,
+        }
+    }
+}
+
+/// Ring buffer of the last seconds of raw input, saved to a WAV file on demand
+/// so that the audio that confused the analyzer can be replayed offline
+/// (`--analyze <file>`). Samples are kept as 16-bit integers, half the memory
+/// of the capture format.
+pub struct InputRecorder {
+    samples: VecDeque<i16>,
+    seconds: u64,
+    sample_rate: u32,
+}
+
+impl InputRecorder {
+    pub fn new(seconds: u64, sample_rate: u32) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(Self::capacity(seconds, sample_rate)),
+            seconds,
+            sample_rate,
+        }
+    }
+
+    fn capacity(seconds: u64, sample_rate: u32) -> usize {
+        (seconds * sample_rate as u64) as usize
+    }
+
+    pub fn push(&mut self, packet: &[f32]) {
+        let capacity = Self::capacity(self.seconds, self.sample_rate);
+        let packet = &packet[packet.len().saturating_sub(capacity)..];
+        let overflow = (self.samples.len() + packet.len()).saturating_sub(capacity);
+        self.samples.drain(..overflow);
+        self.samples.extend(
+            packet
+                .iter()
+                .map(|&x| (x.clamp(-1.0, 1.0) * i16::MAX as f32) as i16),
+        );
+    }
+
+    /// Audio recorded at another rate cannot go in the same file: starts over.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.samples.clear();
+        self.sample_rate = sample_rate;
+    }
+
+    /// Length of the audio currently kept.
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.samples.len() as f64 / self.sample_rate as f64)
+    }
+
+    /// Writes `capture-<unix time>.wav` (mono, 16 bits) in `directory` and returns its path.
+    pub fn save(&self, directory: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        if self.samples.is_empty() {
+            return Err("No audio recorded yet".into());
+        }
+        fs::create_dir_all(directory)?;
+        let stamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let path = directory.join(format!("capture-{}.wav", stamp));
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: self.sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec)?;
+        for &sample in &self.samples {
+            writer.write_sample(sample)?;
+        }
+        writer.finalize()?;
+        Ok(path)
+    }
+}
+
+/// Fires once the confidence stayed low for `low_confidence_hops` analyzed hops,
+/// then waits `cooldown_s` before firing again. Hops without tempo (silence,
+/// buffers filling up) restart the count.
+pub struct LowConfidenceWatch {
+    threshold: f32,
+    hops: usize,
+    cooldown: Duration,
+    low_hops: usize,
+    last_fired: Option<Instant>,
+}
+
+impl LowConfidenceWatch {
+    pub fn new(config: &RecordingConfig) -> Self {
+        Self {
+            threshold: config.low_confidence,
+            hops: config.low_confidence_hops.max(1),
+            cooldown: Duration::from_secs(config.cooldown_s),
+            low_hops: 0,
+            last_fired: None,
+        }
+    }
+
+    /// `confidence` of the last hop, `None` without tempo.
+    pub fn update(&mut self, confidence: Option<f32>, now: Instant) -> bool {
+        match confidence {
+            Some(confidence) if confidence < self.threshold => self.low_hops += 1,
+            _ => self.low_hops = 0,
+        }
+        let cooled_down = self
+            .last_fired
+            .is_none_or(|at| now.duration_since(at) >= self.cooldown);
+        if self.low_hops >= self.hops && cooled_down {
+            self.low_hops = 0;
+            self.last_fired = Some(now);
+            return true;
+        }
+        false
+    }
+}
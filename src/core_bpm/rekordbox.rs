@@ -0,0 +1,84 @@
+use super::offline::FileAnalysis;
+use std::error::Error;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Writes the analyses as a Rekordbox collection (`File > Import > rekordbox xml`):
+/// one `TEMPO` entry per beat grid and the cues as memory cues.
+pub fn write_xml(path: &Path, analyses: &[FileAnalysis]) -> Result<(), Box<dyn Error>> {
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(xml, r#"<DJ_PLAYLISTS Version="1.0.0">"#)?;
+    writeln!(
+        xml,
+        r#"  <PRODUCT Name="{}" Version="{}" Company=""/>"#,
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(xml, r#"  <COLLECTION Entries="{}">"#, analyses.len())?;
+    for (id, analysis) in analyses.iter().enumerate() {
+        let name = analysis
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        writeln!(
+            xml,
+            r#"    <TRACK TrackID="{}" Name="{}" Location="{}" TotalTime="{}" AverageBpm="{:.2}">"#,
+            id + 1,
+            escape(&name),
+            escape(&location(&analysis.path)),
+            analysis.duration_s as u32,
+            analysis.main_bpm().unwrap_or(0.0)
+        )?;
+        for grid in &analysis.grids {
+            writeln!(
+                xml,
+                r#"      <TEMPO Inizio="{:.3}" Bpm="{:.2}" Metro="4/4" Battito="1"/>"#,
+                grid.first_downbeat_s, grid.bpm
+            )?;
+        }
+        for cue in &analysis.cues {
+            // Num -1: memory cue
+            writeln!(
+                xml,
+                r#"      <POSITION_MARK Name="{}" Type="0" Start="{:.3}" Num="-1"/>"#,
+                escape(&cue.name),
+                cue.time_s
+            )?;
+        }
+        writeln!(xml, "    </TRACK>")?;
+    }
+    writeln!(xml, "  </COLLECTION>")?;
+    writeln!(xml, "</DJ_PLAYLISTS>")?;
+    std::fs::write(path, xml)?;
+    Ok(())
+}
+
+// `file://localhost/` URL of the absolute path, percent-encoded
+fn location(path: &Path) -> String {
+    let absolute = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let path = absolute.to_string_lossy().replace('\\', "/");
+    // Windows: drop the verbatim prefix added by canonicalize
+    let path = path.strip_prefix("//?/").unwrap_or(&path);
+    let mut url = String::from("file://localhost");
+    if !path.starts_with('/') {
+        url.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' | b':' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
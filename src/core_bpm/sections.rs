@@ -0,0 +1,309 @@
+//! Section of the track being played, for lighting rigs that want to see a drop
+//! coming. Each hop, `FeatureTracker` measures the input in three bands (sub,
+//! mid, high) and counts its onsets; `SectionClassifier` compares the last
+//! seconds with the level of the track over the last half minute:
+//!
+//! - a breakdown has the sub band far below the level of the track,
+//! - a build-up is a breakdown getting denser (snare rolls) or brighter (the
+//!   high-pass sweep of a riser),
+//! - a drop starts on a detected drop and lasts while the bass holds,
+//! - the intro runs from the start of the analysis to the first drop.
+
+use biquad::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// Band edges (Hz): sub below, high above
+const SUB_HZ: f32 = 120.0;
+const HIGH_HZ: f32 = 2000.0;
+// Onsets are picked on blocks of this length
+const ONSET_BLOCK_S: f32 = 0.01;
+// Blocks an onset is compared to, and its rise over their mean energy
+const ONSET_CONTEXT: usize = 10;
+const ONSET_RISE: f32 = 2.0;
+// Quietest onset (mean square of the mid and high bands), about -50 dB
+const ONSET_FLOOR: f32 = 1e-5;
+
+// Seconds of features kept, the last ones compared to the earlier ones
+const HISTORY_S: f32 = 32.0;
+const RECENT_S: f32 = 2.0;
+const TREND_S: f32 = 4.0;
+// Features needed before telling a breakdown apart
+const MIN_HISTORY_S: f32 = 8.0;
+// Recent sub band energy below this fraction of the track level: breakdown
+const BREAKDOWN_SUB: f32 = 0.2;
+// Rise of the onset rate or of the spectral tilt that makes a build-up
+const BUILD_UP_RISE: f32 = 1.25;
+// Longest drop, and longest intro without a drop
+const DROP_S: f32 = 16.0;
+const INTRO_S: f32 = 90.0;
+// Time a new section must hold before it is reported (drops are immediate)
+const CHANGE_S: f32 = 1.5;
+
+/// Part of the track, see `SectionClassifier`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Section {
+    #[default]
+    Intro,
+    Breakdown,
+    BuildUp,
+    Drop,
+    Steady,
+}
+
+impl Section {
+    /// Name in the OSC and JSON messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            Section::Intro => "intro",
+            Section::Breakdown => "breakdown",
+            Section::BuildUp => "build_up",
+            Section::Drop => "drop",
+            Section::Steady => "steady",
+        }
+    }
+}
+
+/// Input over a stretch of time: mean square of each band and onsets per second.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SectionFeatures {
+    pub sub: f32,
+    pub mid: f32,
+    pub high: f32,
+    pub onset_rate: f32,
+    pub seconds: f32,
+}
+
+impl SectionFeatures {
+    /// High band against the mid band, rises with a high-pass sweep (and not
+    /// when the bass drops out).
+    pub fn tilt(&self) -> f32 {
+        self.high / self.mid.max(f32::EPSILON)
+    }
+
+    // Time-weighted mean
+    fn mean<'a>(features: impl Iterator<Item = &'a SectionFeatures>) -> SectionFeatures {
+        let mut total = SectionFeatures::default();
+        for f in features {
+            total.sub += f.sub * f.seconds;
+            total.mid += f.mid * f.seconds;
+            total.high += f.high * f.seconds;
+            total.onset_rate += f.onset_rate * f.seconds;
+            total.seconds += f.seconds;
+        }
+        let seconds = total.seconds.max(f32::EPSILON);
+        SectionFeatures {
+            sub: total.sub / seconds,
+            mid: total.mid / seconds,
+            high: total.high / seconds,
+            onset_rate: total.onset_rate / seconds,
+            seconds: total.seconds,
+        }
+    }
+}
+
+/// Band energies and onsets of the input between two `take` calls.
+pub struct FeatureTracker {
+    sub_filter: DirectForm2Transposed<f32>,
+    mid_filters: [DirectForm2Transposed<f32>; 2],
+    high_filter: DirectForm2Transposed<f32>,
+    sample_rate: u32,
+    block_len: usize,
+    // Sums of squares (sub, mid, high) of the block being filled
+    block: [f32; 3],
+    block_fill: usize,
+    // Mid and high energies of the latest blocks, for the onsets
+    context: VecDeque<f32>,
+    previous_onset: bool,
+    // Since the last `take`
+    sums: [f32; 3],
+    samples: usize,
+    onsets: usize,
+}
+
+impl FeatureTracker {
+    pub fn new(sample_rate: u32) -> Result<Self, String> {
+        let fs = Hertz::<f32>::from_hz(sample_rate as f32)
+            .map_err(|_| "Invalid sample rate".to_string())?;
+        let filter = |kind, cutoff: f32| {
+            let f0 = Hertz::<f32>::from_hz(cutoff).map_err(|_| "Invalid cutoff".to_string())?;
+            Coefficients::<f32>::from_params(kind, fs, f0, Q_BUTTERWORTH_F32)
+                .map(DirectForm2Transposed::<f32>::new)
+                .map_err(|e| format!("Section filter: {:?}", e))
+        };
+        Ok(Self {
+            sub_filter: filter(Type::LowPass, SUB_HZ)?,
+            mid_filters: [
+                filter(Type::HighPass, SUB_HZ)?,
+                filter(Type::LowPass, HIGH_HZ)?,
+            ],
+            high_filter: filter(Type::HighPass, HIGH_HZ)?,
+            sample_rate,
+            block_len: ((sample_rate as f32 * ONSET_BLOCK_S) as usize).max(1),
+            block: [0.0; 3],
+            block_fill: 0,
+            context: VecDeque::with_capacity(ONSET_CONTEXT),
+            previous_onset: false,
+            sums: [0.0; 3],
+            samples: 0,
+            onsets: 0,
+        })
+    }
+
+    /// Feeds input samples (before the analysis filter).
+    pub fn push(&mut self, samples: &[f32]) {
+        for &x in samples {
+            let sub = self.sub_filter.run(x);
+            let [high_pass, low_pass] = &mut self.mid_filters;
+            let mid = low_pass.run(high_pass.run(x));
+            let high = self.high_filter.run(x);
+            for (sum, band) in self.block.iter_mut().zip([sub, mid, high]) {
+                *sum += band * band;
+            }
+            self.block_fill += 1;
+            if self.block_fill == self.block_len {
+                self.end_block();
+            }
+        }
+    }
+
+    fn end_block(&mut self) {
+        for (sum, block) in self.sums.iter_mut().zip(&self.block) {
+            *sum += block;
+        }
+        self.samples += self.block_fill;
+
+        // An onset is a block well above the ones before it, counted once
+        let energy = (self.block[1] + self.block[2]) / self.block_fill as f32;
+        let context = self.context.iter().sum::<f32>() / self.context.len().max(1) as f32;
+        let onset = self.context.len() == ONSET_CONTEXT
+            && energy > ONSET_FLOOR
+            && energy > context * ONSET_RISE;
+        if onset && !self.previous_onset {
+            self.onsets += 1;
+        }
+        self.previous_onset = onset;
+        if self.context.len() == ONSET_CONTEXT {
+            self.context.pop_front();
+        }
+        self.context.push_back(energy);
+        self.block = [0.0; 3];
+        self.block_fill = 0;
+    }
+
+    /// Features of the input pushed since the last call.
+    pub fn take(&mut self) -> SectionFeatures {
+        let samples = self.samples.max(1) as f32;
+        let seconds = self.samples as f32 / self.sample_rate as f32;
+        let features = SectionFeatures {
+            sub: self.sums[0] / samples,
+            mid: self.sums[1] / samples,
+            high: self.sums[2] / samples,
+            onset_rate: self.onsets as f32 / seconds.max(f32::EPSILON),
+            seconds,
+        };
+        self.sums = [0.0; 3];
+        self.samples = 0;
+        self.onsets = 0;
+        features
+    }
+}
+
+/// Labels the section being played from the features of each hop.
+#[derive(Debug, Clone, Default)]
+pub struct SectionClassifier {
+    history: VecDeque<SectionFeatures>,
+    history_s: f32,
+    elapsed_s: f32,
+    section: Section,
+    // Section waiting to be reported, and how long it has held
+    candidate: Option<(Section, f32)>,
+    drop_at: Option<f32>,
+}
+
+impl SectionClassifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn section(&self) -> Section {
+        self.section
+    }
+
+    /// Adds the features of a hop, and whether a drop was detected in it.
+    pub fn update(&mut self, features: SectionFeatures, is_drop: bool) -> Section {
+        self.elapsed_s += features.seconds;
+        self.history.push_back(features);
+        self.history_s += features.seconds;
+        while self.history_s - self.history.front().map_or(0.0, |f| f.seconds) >= HISTORY_S {
+            let oldest = self.history.pop_front().unwrap();
+            self.history_s -= oldest.seconds;
+        }
+
+        if is_drop {
+            self.drop_at = Some(self.elapsed_s);
+            self.candidate = None;
+            self.section = Section::Drop;
+            return self.section;
+        }
+
+        let observed = self.observe();
+        if observed == self.section {
+            self.candidate = None;
+        } else {
+            let held = match self.candidate {
+                Some((candidate, held)) if candidate == observed => held + features.seconds,
+                _ => features.seconds,
+            };
+            if held >= CHANGE_S {
+                self.section = observed;
+                self.candidate = None;
+            } else {
+                self.candidate = Some((observed, held));
+            }
+        }
+        self.section
+    }
+
+    // Section the latest features point to, before the hold
+    fn observe(&self) -> Section {
+        let recent = self.window(0.0, RECENT_S);
+        let earlier = self.window(RECENT_S, RECENT_S + TREND_S);
+        // Level of the track: loud parts, so a long breakdown does not become it
+        let mut subs: Vec<f32> = self.history.iter().map(|f| f.sub).collect();
+        subs.sort_by(|a, b| a.total_cmp(b));
+        let track_sub = subs.get(subs.len() * 3 / 4).copied().unwrap_or(0.0);
+
+        let breakdown = self.history_s >= MIN_HISTORY_S && recent.sub < track_sub * BREAKDOWN_SUB;
+        if breakdown {
+            let denser =
+                recent.onset_rate > earlier.onset_rate * BUILD_UP_RISE && earlier.onset_rate > 0.0;
+            let brighter = recent.tilt() > earlier.tilt() * BUILD_UP_RISE;
+            let building = earlier.seconds > 0.0 && (denser || brighter);
+            // A build-up stays one until the drop, even when it levels off
+            return if building || self.section == Section::BuildUp {
+                Section::BuildUp
+            } else {
+                Section::Breakdown
+            };
+        }
+        match self.drop_at {
+            Some(at) if self.elapsed_s - at < DROP_S => Section::Drop,
+            Some(_) => Section::Steady,
+            None if self.elapsed_s < INTRO_S => Section::Intro,
+            None => Section::Steady,
+        }
+    }
+
+    // Mean features from `from_s` to `to_s` seconds ago
+    fn window(&self, from_s: f32, to_s: f32) -> SectionFeatures {
+        let mut age = 0.0;
+        let features = self.history.iter().rev().filter(|f| {
+            let start = age;
+            age += f.seconds;
+            start >= from_s && start < to_s
+        });
+        SectionFeatures::mean(features)
+    }
+}
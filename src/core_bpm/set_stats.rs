@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// `[stats]` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StatsConfig {
+    /// Write the statistics of the set to `set-<unix time>.stats.json`
+    pub enabled: bool,
+    pub directory: PathBuf,
+    /// The file is rewritten at this period while analyzing, so that a set ended
+    /// with a power cut keeps its report
+    pub save_interval_s: u64,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: PathBuf::from("."),
+            save_interval_s: 60,
+        }
+    }
+}
+
+/// Figures of the set so far, for post-event reporting.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct SetReport {
+    /// Unix time the set started
+    pub started_at: u64,
+    /// Wall time since the start (s), pauses included
+    pub uptime_s: f64,
+    /// Audio analyzed (s)
+    pub analyzed_s: f64,
+    /// Share of the analyzed audio with an accepted tempo
+    pub locked_percent: f32,
+    pub drops: u32,
+    /// Mean tempo weighted by the time it was held
+    pub average_bpm: Option<f32>,
+    pub min_bpm: Option<f32>,
+    pub max_bpm: Option<f32>,
+}
+
+impl SetReport {
+    /// Writes the report to `set-<started_at>.stats.json` in `directory`.
+    pub fn save(&self, directory: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        fs::create_dir_all(directory)?;
+        let path = directory.join(format!("set-{}.stats.json", self.started_at));
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+}
+
+/// Accumulates the statistics of a set hop by hop: drops, tempo range and
+/// average, time locked.
+#[derive(Debug, Clone)]
+pub struct SetStats {
+    started: Instant,
+    started_at: u64,
+    analyzed: f64,
+    locked: f64,
+    // Sum of bpm * seconds held, over `locked`
+    bpm_seconds: f64,
+    min_bpm: Option<f32>,
+    max_bpm: Option<f32>,
+    drops: u32,
+    // A drop flagged on several hops in a row is counted once
+    in_drop: bool,
+}
+
+impl Default for SetStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SetStats {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            analyzed: 0.0,
+            locked: 0.0,
+            bpm_seconds: 0.0,
+            min_bpm: None,
+            max_bpm: None,
+            drops: 0,
+            in_drop: false,
+        }
+    }
+
+    /// One analysis hop of `hop` audio, with its tempo and drop flag when the
+    /// analyzer accepted one.
+    pub fn record(&mut self, hop: Duration, detection: Option<(f32, bool)>) {
+        let seconds = hop.as_secs_f64();
+        self.analyzed += seconds;
+        let Some((bpm, is_drop)) = detection else {
+            self.in_drop = false;
+            return;
+        };
+        self.locked += seconds;
+        self.bpm_seconds += bpm as f64 * seconds;
+        self.min_bpm = Some(self.min_bpm.map_or(bpm, |min| min.min(bpm)));
+        self.max_bpm = Some(self.max_bpm.map_or(bpm, |max| max.max(bpm)));
+        if is_drop && !self.in_drop {
+            self.drops += 1;
+        }
+        self.in_drop = is_drop;
+    }
+
+    pub fn report(&self) -> SetReport {
+        SetReport {
+            started_at: self.started_at,
+            uptime_s: self.started.elapsed().as_secs_f64(),
+            analyzed_s: self.analyzed,
+            locked_percent: if self.analyzed > 0.0 {
+                (self.locked / self.analyzed * 100.0) as f32
+            } else {
+                0.0
+            },
+            drops: self.drops,
+            average_bpm: (self.locked > 0.0).then(|| (self.bpm_seconds / self.locked) as f32),
+            min_bpm: self.min_bpm,
+            max_bpm: self.max_bpm,
+        }
+    }
+}
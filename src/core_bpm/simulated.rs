@@ -0,0 +1,261 @@
+use super::offline;
+use super::testsig::XorShift;
+use crate::config;
+use std::error::Error;
+use std::f32::consts::PI;
+use std::path::Path;
+use std::time::Duration;
+
+/// Pseudo device generating audio instead of capturing it: a kick pattern whose
+/// tempo ramps between `RAMP_BPM` bounds. `"<SIMULATED_DEVICE>: <file.wav>"` loops
+/// a WAV file instead. Lets the GUI, network and Link features run without any
+/// sound card (`--sim`, or the last entry of the device list).
+pub const SIMULATED_DEVICE: &str = "Simulated Input";
+
+// Tempo bounds of the generated kicks, crossed in `RAMP_DURATION` each way
+const RAMP_BPM: (f32, f32) = (118.0, 132.0);
+const RAMP_DURATION: Duration = Duration::from_secs(60);
+// Kick: pitch drop 150 -> 50 Hz over 120 ms
+const KICK_DURATION_S: f32 = 0.12;
+const KICK_FREQ: (f32, f32) = (150.0, 50.0);
+// Hiss under the kicks, so the input never is digital silence
+const NOISE_LEVEL: f32 = 0.01;
+
+enum Signal {
+    Kicks {
+        // Position in beats, the integer part counts the kicks
+        beat: f64,
+        // Samples since the last kick onset
+        since_kick: usize,
+        kick_phase: f32,
+        noise: XorShift,
+    },
+    Wav {
+        samples: Vec<f32>,
+        position: usize,
+    },
+    Program {
+        rng: XorShift,
+        section: Section,
+        // Samples left in the section
+        remaining: usize,
+        beat: f64,
+        since_kick: usize,
+        kick_phase: f32,
+        noise: XorShift,
+    },
+}
+
+// Section of the soak program
+#[derive(Clone, Copy)]
+enum Section {
+    Track { bpm: f32, gain: f32, hiss: f32 },
+    Noise(f32),
+}
+
+impl Section {
+    // A track of 1-6 minutes between 90 and 180 BPM (70 %), near silence of up
+    // to a minute (20 %) or a loud noise burst (10 %). Returns its length.
+    fn next(rng: &mut XorShift, rate: f32) -> (Self, usize) {
+        let seconds = |s: f32| (rate * s) as usize;
+        match rng.next_u32() % 10 {
+            0..=6 => (
+                Section::Track {
+                    bpm: rng.range(90.0, 180.0),
+                    gain: rng.range(0.1, 1.0),
+                    hiss: rng.range(0.0, 0.1),
+                },
+                seconds(rng.range(60.0, 360.0)),
+            ),
+            7 | 8 => (Section::Noise(0.001), seconds(rng.range(1.0, 60.0))),
+            _ => (Section::Noise(0.9), seconds(rng.range(1.0, 10.0))),
+        }
+    }
+}
+
+/// Endless audio for the simulated device, produced in capture-sized packets.
+pub struct SimulatedSource {
+    signal: Signal,
+    sample_rate: u32,
+    // Samples produced so far, drives the tempo ramp
+    elapsed: u64,
+}
+
+impl SimulatedSource {
+    /// Kick pattern at a tempo ramping up and down between `RAMP_BPM`.
+    pub fn kicks(sample_rate: u32) -> Self {
+        Self {
+            signal: Signal::Kicks {
+                beat: 0.0,
+                since_kick: 0,
+                kick_phase: 0.0,
+                noise: XorShift::new(0x5eed),
+            },
+            sample_rate,
+            elapsed: 0,
+        }
+    }
+
+    /// Loops the mono mix of a WAV file, at the rate of the file.
+    pub fn wav(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let (samples, sample_rate) = offline::read_wav(path)?;
+        if samples.is_empty() {
+            return Err(format!("{:?} holds no audio", path).into());
+        }
+        Ok(Self {
+            signal: Signal::Wav {
+                samples,
+                position: 0,
+            },
+            sample_rate,
+            elapsed: 0,
+        })
+    }
+
+    /// Endless program for soak tests: tracks at random tempos and levels,
+    /// silences and noise bursts. Generated on the fly, hours of it hold no memory.
+    pub fn program(sample_rate: u32, seed: u32) -> Self {
+        Self {
+            signal: Signal::Program {
+                rng: XorShift::new(seed),
+                section: Section::Noise(0.0),
+                remaining: 0,
+                beat: 0.0,
+                since_kick: usize::MAX,
+                kick_phase: 0.0,
+                noise: XorShift::new(seed ^ 0x5eed),
+            },
+            sample_rate,
+            elapsed: 0,
+        }
+    }
+
+    /// Source of a device name: `None` for real devices.
+    pub fn open(device_name: &str, sample_rate: u32) -> Option<Result<Self, Box<dyn Error>>> {
+        let rest = device_name.strip_prefix(SIMULATED_DEVICE)?;
+        if rest.is_empty() {
+            return Some(Ok(Self::kicks(sample_rate)));
+        }
+        let path = rest.strip_prefix(": ")?;
+        Some(Self::wav(Path::new(path)))
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Tempo of the generated kicks at this point of the ramp or of the program
+    /// (`None` for a WAV file or between the program tracks).
+    pub fn bpm(&self) -> Option<f32> {
+        match self.signal {
+            Signal::Kicks { .. } => Some(ramp_bpm(self.elapsed as f32 / self.sample_rate as f32)),
+            Signal::Program {
+                section: Section::Track { bpm, .. },
+                ..
+            } => Some(bpm),
+            Signal::Wav { .. } | Signal::Program { .. } => None,
+        }
+    }
+
+    /// Next `len` mono samples.
+    pub fn next_packet(&mut self, len: usize) -> Vec<f32> {
+        let rate = self.sample_rate as f32;
+        let mut packet = Vec::with_capacity(len);
+        match &mut self.signal {
+            Signal::Kicks {
+                beat,
+                since_kick,
+                kick_phase,
+                noise,
+            } => {
+                for i in 0..len {
+                    let time_s = (self.elapsed + i as u64) as f32 / rate;
+                    let previous = beat.floor();
+                    *beat += ramp_bpm(time_s) as f64 / 60.0 / rate as f64;
+                    if beat.floor() > previous {
+                        *since_kick = 0;
+                        *kick_phase = 0.0;
+                    }
+                    let sample =
+                        NOISE_LEVEL * noise.next_uniform() + kick(since_kick, kick_phase, rate);
+                    packet.push(sample);
+                }
+            }
+            Signal::Program {
+                rng,
+                section,
+                remaining,
+                beat,
+                since_kick,
+                kick_phase,
+                noise,
+            } => {
+                for _ in 0..len {
+                    if *remaining == 0 {
+                        (*section, *remaining) = Section::next(rng, rate);
+                        *beat = 0.0;
+                    }
+                    *remaining -= 1;
+                    let sample = match *section {
+                        Section::Track { bpm, gain, hiss } => {
+                            let previous = beat.floor();
+                            *beat += bpm as f64 / 60.0 / rate as f64;
+                            if beat.floor() > previous {
+                                *since_kick = 0;
+                                *kick_phase = 0.0;
+                            }
+                            gain * (hiss * noise.next_uniform()
+                                + kick(since_kick, kick_phase, rate))
+                        }
+                        Section::Noise(amplitude) => amplitude * noise.next_uniform(),
+                    };
+                    packet.push(sample);
+                }
+            }
+            Signal::Wav { samples, position } => {
+                while packet.len() < len {
+                    let end = (*position + len - packet.len()).min(samples.len());
+                    packet.extend_from_slice(&samples[*position..end]);
+                    *position = end % samples.len();
+                }
+            }
+        }
+        self.elapsed += len as u64;
+        packet
+    }
+}
+
+// Sample of the kick started `since_kick` samples ago, silent after `KICK_DURATION_S`
+fn kick(since_kick: &mut usize, kick_phase: &mut f32, rate: f32) -> f32 {
+    let kick_len = (rate * KICK_DURATION_S) as usize;
+    let mut sample = 0.0;
+    if *since_kick < kick_len {
+        let progress = *since_kick as f32 / kick_len as f32;
+        let freq = KICK_FREQ.0 + (KICK_FREQ.1 - KICK_FREQ.0) * progress;
+        *kick_phase += 2.0 * PI * freq / rate;
+        sample = 0.9 * (1.0 - progress).powi(2) * kick_phase.sin();
+    }
+    *since_kick = since_kick.saturating_add(1);
+    sample
+}
+
+// Triangle between the `RAMP_BPM` bounds
+fn ramp_bpm(time_s: f32) -> f32 {
+    let (low, high) = RAMP_BPM;
+    let ramp_s = RAMP_DURATION.as_secs_f32();
+    let position = (time_s / ramp_s) % 2.0;
+    let progress = if position < 1.0 {
+        position
+    } else {
+        2.0 - position
+    };
+    low + (high - low) * progress
+}
+
+/// Device selected by `--sim` (generated kicks) or `--sim <file.wav>` (looped file).
+pub fn cli_device() -> Option<String> {
+    Some(match config::cli_option("--sim")? {
+        Some(path) => format!("{}: {}", SIMULATED_DEVICE, path),
+        None => SIMULATED_DEVICE.to_string(),
+    })
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+/// `[software_agc]` section of the config file (also kept in analyzer presets).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoftwareAgcConfig {
+    /// Normalize the onset envelope before the tempo search, for inputs without
+    /// hardware capture gain (desktop sound cards, USB interfaces)
+    pub enabled: bool,
+    /// Envelope level aimed for (mean of the filtered, rectified audio)
+    pub target: f32,
+    /// Time constants of the level follower. Both stay well above a beat period
+    /// so that the gain follows the track level, not the kicks.
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    /// Gain ceiling, keeps quiet passages and hiss from being blown up
+    pub max_gain: f32,
+}
+
+impl Default for SoftwareAgcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: 0.2,
+            attack_ms: 2000.0,
+            release_ms: 10000.0,
+            max_gain: 100.0,
+        }
+    }
+}
+
+impl SoftwareAgcConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.target > 0.0 && self.target <= 1.0) {
+            return Err("target must be in ]0, 1]".to_string());
+        }
+        if !(self.attack_ms > 0.0 && self.release_ms > 0.0) {
+            return Err("attack_ms and release_ms must be positive".to_string());
+        }
+        if self.max_gain.is_nan() || self.max_gain < 1.0 {
+            return Err("max_gain must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Gain applied to the onset envelope so that its level stays around `target`
+/// whatever the input level. The follower rises with the attack time constant
+/// and falls with the release one.
+pub struct SoftwareAgc {
+    target: f32,
+    max_gain: f32,
+    attack: f32,
+    release: f32,
+    level: f32,
+}
+
+impl SoftwareAgc {
+    /// `sample_rate` of the envelope samples passed to `process`.
+    pub fn new(config: &SoftwareAgcConfig, sample_rate: f32) -> Self {
+        let coefficient = |ms: f32| 1.0 - (-1000.0 / (ms.max(1.0) * sample_rate)).exp();
+        Self {
+            target: config.target,
+            max_gain: config.max_gain.max(1.0),
+            attack: coefficient(config.attack_ms),
+            release: coefficient(config.release_ms),
+            // Unity gain until the follower has measured the input
+            level: config.target,
+        }
+    }
+
+    pub fn process(&mut self, x: f32) -> f32 {
+        let coefficient = if x > self.level {
+            self.attack
+        } else {
+            self.release
+        };
+        self.level += coefficient * (x - self.level);
+        x * self.gain()
+    }
+
+    /// Carries the measured level over from the follower of a previous analyzer
+    /// (capture rate change), so the gain does not start from unity again.
+    pub fn resume_from(&mut self, previous: &SoftwareAgc) {
+        self.level = previous.level;
+    }
+
+    /// Gain currently applied.
+    pub fn gain(&self) -> f32 {
+        (self.target / self.level.max(f32::MIN_POSITIVE)).min(self.max_gain)
+    }
+}
@@ -0,0 +1,299 @@
+//! Tempo tracking with a hidden Markov model over the detections of the hops.
+//! The hidden state is the tempo, on a 1 BPM grid over the searched range. A
+//! detection is likely near its state or on its half or double. Between two
+//! hops the tempo drifts by about a BPM, slips to its half or double now and
+//! then, and jumps anywhere rarely. The reported tempo is the end of the
+//! Viterbi path over the last `OBSERVATIONS` detections. Its confidence is the
+//! posterior mass of the forward pass around that tempo.
+//!
+//! The jump weight is set so that it takes `change_hops` detections of another
+//! tempo to outweigh it: a stray detection is absorbed, a new track is not.
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Detections decoded on each hop
+pub const OBSERVATIONS: usize = 8;
+// Detections behind the reported tempo, see `reported_bpm`
+const REPORTED: usize = 3;
+const STEP_BPM: f32 = 1.0;
+// Likelihood of a detection unrelated to the tempo (a misdetection)
+const EMISSION_FLOOR: f32 = 0.01;
+// Likelihood of a detection on the half or double of the tempo
+const OCTAVE_EMISSION: f32 = 0.5;
+// Tempo drift between two hops (standard deviation, BPM), and the drifts
+// considered: 4 deviations
+const DRIFT_BPM: f32 = 1.0;
+const DRIFT_STATES: usize = 4;
+// Weight of a move to the half or double of the tempo
+const OCTAVE_JUMP: f32 = 0.05;
+
+/// Detection fed to the tracker.
+#[derive(Debug, Clone, Copy)]
+pub struct Observation {
+    pub bpm: f32,
+    pub at: Instant,
+}
+
+/// Outcome of `TempoTracker::update`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tracked {
+    pub bpm: f32,
+    /// Posterior probability of the tempo, within the tolerance (0.0 - 1.0)
+    pub confidence: f32,
+    /// Previous tempo, when the tracker moved away from it by more than the
+    /// tolerance and not by an octave
+    pub changed_from: Option<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TempoTracker {
+    min_bpm: f32,
+    states: usize,
+    tolerance: f32,
+    change_hops: usize,
+    // Log weight of a jump to any tempo
+    log_jump: f32,
+    // Log weight of a drift by 0..=DRIFT_STATES states
+    log_drift: [f32; DRIFT_STATES + 1],
+    observations: VecDeque<Observation>,
+    tempo: Option<f32>,
+    confidence: f32,
+    // Viterbi scores and forward probabilities, current and next observation
+    scores: Vec<f32>,
+    forward: Vec<f32>,
+    next: Vec<f32>,
+}
+
+impl TempoTracker {
+    /// Tracker over `min_bpm..=max_bpm`. `tolerance` (BPM) is the spread of the
+    /// detections around the tempo, and `change_hops` the detections of another
+    /// tempo it takes to move to it.
+    pub fn new(min_bpm: f32, max_bpm: f32, tolerance: f32, change_hops: usize) -> Self {
+        let states = ((max_bpm - min_bpm).max(0.0) / STEP_BPM) as usize + 1;
+        let mut log_drift = [0.0; DRIFT_STATES + 1];
+        for (states, weight) in log_drift.iter_mut().enumerate() {
+            let drift = states as f32 * STEP_BPM / DRIFT_BPM;
+            *weight = -0.5 * drift * drift;
+        }
+        Self {
+            min_bpm,
+            states,
+            tolerance: tolerance.max(STEP_BPM),
+            change_hops: change_hops.max(1),
+            // A detection away from the tempo costs about the floor: staying
+            // is cheaper for fewer than `change_hops` of them
+            log_jump: EMISSION_FLOOR.ln() * (change_hops.max(1) as f32 - 0.5),
+            log_drift,
+            observations: VecDeque::with_capacity(OBSERVATIONS),
+            tempo: None,
+            confidence: 0.0,
+            scores: vec![0.0; states],
+            forward: vec![0.0; states],
+            next: vec![0.0; states],
+        }
+    }
+
+    /// Adds a detection and decodes the tempo.
+    pub fn update(&mut self, bpm: f32, at: Instant) -> Tracked {
+        if self.observations.len() >= OBSERVATIONS {
+            self.observations.pop_front();
+        }
+        self.observations.push_back(Observation { bpm, at });
+
+        let state = self.viterbi();
+        self.confidence = self.posterior(state);
+        let tempo = self.reported_bpm(self.state_bpm(state));
+        let changed_from = self.tempo.filter(|&previous| !self.near(previous, tempo));
+        if changed_from.is_some() {
+            // Re-converge from the new tempo only, the detections of the
+            // previous one would pull the path back
+            let keep = self.observations.len().min(self.change_hops);
+            self.observations.drain(..self.observations.len() - keep);
+        }
+        self.tempo = Some(tempo);
+        Tracked {
+            bpm: tempo,
+            confidence: self.confidence,
+            changed_from,
+        }
+    }
+
+    /// Starts from a known tempo, e.g. saved before a restart: two detections
+    /// of it, so that a single stray detection does not move the tracker.
+    pub fn seed(&mut self, bpm: f32, at: Instant) {
+        self.clear();
+        for _ in 0..2 {
+            self.observations.push_back(Observation { bpm, at });
+        }
+        self.tempo = Some(bpm);
+        self.confidence = 1.0;
+    }
+
+    /// Resumes from earlier detections, oldest first, and the tempo and
+    /// confidence they gave, e.g. from the snapshot of another analyzer.
+    pub fn restore(
+        &mut self,
+        observations: impl IntoIterator<Item = Observation>,
+        tempo: Option<f32>,
+        confidence: f32,
+    ) {
+        self.clear();
+        self.observations.extend(observations);
+        let excess = self.observations.len().saturating_sub(OBSERVATIONS);
+        self.observations.drain(..excess);
+        self.tempo = tempo;
+        self.confidence = confidence;
+    }
+
+    pub fn clear(&mut self) {
+        self.observations.clear();
+        self.tempo = None;
+        self.confidence = 0.0;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.observations.is_empty()
+    }
+
+    /// Tempo reported by the last update.
+    pub fn tempo(&self) -> Option<f32> {
+        self.tempo
+    }
+
+    pub fn confidence(&self) -> f32 {
+        self.confidence
+    }
+
+    /// Decoded detections, oldest first.
+    pub fn observations(&self) -> impl Iterator<Item = &Observation> {
+        self.observations.iter()
+    }
+
+    fn state_bpm(&self, state: usize) -> f32 {
+        self.min_bpm + state as f32 * STEP_BPM
+    }
+
+    fn state_of(&self, bpm: f32) -> Option<usize> {
+        let state = ((bpm - self.min_bpm) / STEP_BPM).round();
+        (state >= 0.0 && (state as usize) < self.states).then_some(state as usize)
+    }
+
+    // Within the tolerance of `b`, of its half or of its double
+    fn near(&self, a: f32, b: f32) -> bool {
+        [0.5, 1.0, 2.0]
+            .iter()
+            .any(|octave| (a - b * octave).abs() <= self.tolerance)
+    }
+
+    fn log_emission(&self, bpm: f32, state: usize) -> f32 {
+        let tempo = self.state_bpm(state);
+        let sigma = self.tolerance / 2.0;
+        let fit = |target: f32| (-0.5 * ((bpm - target) / sigma).powi(2)).exp();
+        let likelihood = fit(tempo).max(OCTAVE_EMISSION * fit(2.0 * tempo).max(fit(0.5 * tempo)));
+        (EMISSION_FLOOR + (1.0 - EMISSION_FLOOR) * likelihood).ln()
+    }
+
+    // Previous states that move to `state` other than by a jump, with the log
+    // weight of the move: drifts, and slips from the half or double tempo
+    fn sources(&self, state: usize) -> impl Iterator<Item = (usize, f32)> + '_ {
+        let first = state.saturating_sub(DRIFT_STATES);
+        let last = (state + DRIFT_STATES).min(self.states - 1);
+        let tempo = self.state_bpm(state);
+        let drifts = (first..=last).map(move |from| (from, self.log_drift[from.abs_diff(state)]));
+        let octaves = [0.5, 2.0]
+            .into_iter()
+            .filter_map(move |octave| self.state_of(tempo * octave))
+            .map(|from| (from, OCTAVE_JUMP.ln()));
+        drifts.chain(octaves)
+    }
+
+    // End state of the most likely tempo path over the observations. Scores
+    // are log weights, up to a constant: the transitions are not normalized
+    fn viterbi(&mut self) -> usize {
+        let mut scores = std::mem::take(&mut self.scores);
+        let mut next = std::mem::take(&mut self.next);
+        for (state, score) in scores.iter_mut().enumerate() {
+            *score = self.log_emission(self.observations[0].bpm, state);
+        }
+        for observation in self.observations.iter().skip(1) {
+            let jump = scores.iter().copied().fold(f32::MIN, f32::max) + self.log_jump;
+            for (state, score) in next.iter_mut().enumerate() {
+                let moved = self
+                    .sources(state)
+                    .map(|(from, weight)| scores[from] + weight)
+                    .fold(jump, f32::max);
+                *score = moved + self.log_emission(observation.bpm, state);
+            }
+            std::mem::swap(&mut scores, &mut next);
+        }
+        let best = scores
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map_or(0, |(state, _)| state);
+        self.scores = scores;
+        self.next = next;
+        best
+    }
+
+    // Forward pass over the observations: probability of the tempos within the
+    // tolerance of `state` given all of them
+    fn posterior(&mut self, state: usize) -> f32 {
+        let mut forward = std::mem::take(&mut self.forward);
+        let mut next = std::mem::take(&mut self.next);
+        let jump = self.log_jump.exp();
+        for (index, observation) in self.observations.iter().enumerate() {
+            let total: f32 = forward.iter().sum();
+            for (to, p) in next.iter_mut().enumerate() {
+                let prior = if index == 0 {
+                    1.0
+                } else {
+                    self.sources(to)
+                        .map(|(from, weight)| forward[from] * weight.exp())
+                        .sum::<f32>()
+                        + jump * total
+                };
+                *p = prior * self.log_emission(observation.bpm, to).exp();
+            }
+            let sum: f32 = next.iter().sum();
+            if sum > 0.0 {
+                next.iter_mut().for_each(|p| *p /= sum);
+            }
+            std::mem::swap(&mut forward, &mut next);
+        }
+        let tempo = self.state_bpm(state);
+        let confidence = forward
+            .iter()
+            .enumerate()
+            .filter(|&(to, _)| (self.state_bpm(to) - tempo).abs() <= self.tolerance)
+            .map(|(_, p)| p)
+            .sum::<f32>()
+            .min(1.0);
+        self.forward = forward;
+        self.next = next;
+        confidence
+    }
+
+    // Median of the latest detections of the decoded tempo, brought back to its
+    // octave: finer than the 1 BPM grid. The grid tempo without any.
+    fn reported_bpm(&self, tempo: f32) -> f32 {
+        let mut matching: Vec<f32> = self
+            .observations
+            .iter()
+            .rev()
+            .filter_map(|o| {
+                [1.0, 0.5, 2.0]
+                    .into_iter()
+                    .map(|octave| o.bpm * octave)
+                    .find(|bpm| (bpm - tempo).abs() <= self.tolerance)
+            })
+            .take(REPORTED)
+            .collect();
+        if matching.is_empty() {
+            return tempo;
+        }
+        matching.sort_by(|a, b| a.total_cmp(b));
+        matching[matching.len() / 2]
+    }
+}
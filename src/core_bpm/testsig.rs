@@ -0,0 +1,126 @@
+//! Synthetic signals at known tempos, for the analyzer self-check and the `tests/` suite.
+
+use std::f32::consts::PI;
+
+/// Click track made of short 150 Hz bursts (inside the analysis band) at the given tempo.
+pub fn click_track(sample_rate: u32, bpm: f32, len: usize) -> Vec<f32> {
+    let period = (sample_rate as f32 * 60.0 / bpm) as usize;
+    let click_len = (sample_rate as f32 * 0.03) as usize;
+    let mut signal = vec![0.0; len];
+    for start in (0..len).step_by(period.max(1)) {
+        for i in 0..click_len.min(len - start) {
+            let t = i as f32 / sample_rate as f32;
+            let decay = 1.0 - i as f32 / click_len as f32;
+            signal[start + i] = 0.8 * decay * (2.0 * PI * 150.0 * t).sin();
+        }
+    }
+    signal
+}
+
+/// Four-on-the-floor kick (pitch drop 150 -> 50 Hz) with a quieter percussion hit on
+/// every off-beat 8th. `swing` (0..0.5) delays the off-beats by that share of a beat,
+/// 0 being straight 8ths.
+pub fn kick_pattern(sample_rate: u32, bpm: f32, swing: f32, len: usize) -> Vec<f32> {
+    let rate = sample_rate as f32;
+    let beat = rate * 60.0 / bpm;
+    let mut signal = vec![0.0; len];
+    let mut index = 0;
+    loop {
+        let start = (index as f32 * beat) as usize;
+        if start >= len {
+            break;
+        }
+        add_hit(&mut signal, rate, start, 0.9, 150.0, 50.0, 0.12);
+        let off_beat = start + (beat * (0.5 + swing.clamp(0.0, 0.5))) as usize;
+        add_hit(&mut signal, rate, off_beat, 0.3, 200.0, 180.0, 0.05);
+        index += 1;
+    }
+    signal
+}
+
+/// Kick on every beat under stabs almost as loud on every dotted 8th (four
+/// stabs per three beats): the stabs repeat at 4:3 of the tempo, a trap for
+/// the autocorrelation.
+pub fn dotted_stabs(sample_rate: u32, bpm: f32, len: usize) -> Vec<f32> {
+    let rate = sample_rate as f32;
+    let beat = rate * 60.0 / bpm;
+    let mut signal = vec![0.0; len];
+    for index in 0.. {
+        let start = (index as f32 * beat) as usize;
+        if start >= len {
+            break;
+        }
+        add_hit(&mut signal, rate, start, 0.6, 150.0, 150.0, 0.03);
+        if index % 3 == 0 {
+            for stab in 0..4 {
+                let at = start + (stab as f32 * 0.75 * beat) as usize;
+                add_hit(&mut signal, rate, at, 0.5, 150.0, 150.0, 0.03);
+            }
+        }
+    }
+    signal
+}
+
+/// Adds white noise at `snr_db` relative to the signal RMS. The generator is seeded
+/// so that tests are reproducible.
+pub fn add_noise(signal: &mut [f32], snr_db: f32, seed: u32) {
+    let rms = (signal.iter().map(|x| x * x).sum::<f32>() / signal.len().max(1) as f32).sqrt();
+    // Uniform noise in [-a, a] has an RMS of a / sqrt(3)
+    let amplitude = rms / 10f32.powf(snr_db / 20.0) * 3f32.sqrt();
+    let mut rng = XorShift::new(seed);
+    for x in signal.iter_mut() {
+        *x += amplitude * rng.next_uniform();
+    }
+}
+
+/// Uniform white noise in [-amplitude, amplitude] (noise bursts, crowd, hiss).
+pub fn white_noise(len: usize, amplitude: f32, seed: u32) -> Vec<f32> {
+    let mut rng = XorShift::new(seed);
+    (0..len).map(|_| amplitude * rng.next_uniform()).collect()
+}
+
+/// xorshift32, enough for reproducible test material without a rand dependency.
+pub struct XorShift(u32);
+
+impl XorShift {
+    pub fn new(seed: u32) -> Self {
+        Self(seed.max(1))
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// Uniform in [-1, 1]
+    pub fn next_uniform(&mut self) -> f32 {
+        self.next_u32() as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+
+    /// Uniform in [low, high)
+    pub fn range(&mut self, low: f32, high: f32) -> f32 {
+        low + (self.next_uniform() + 1.0) / 2.0 * (high - low)
+    }
+}
+
+// Decaying sine sweeping from `f_start` to `f_end` over `duration` seconds
+fn add_hit(
+    signal: &mut [f32],
+    rate: f32,
+    start: usize,
+    gain: f32,
+    f_start: f32,
+    f_end: f32,
+    duration: f32,
+) {
+    let hit_len = (rate * duration) as usize;
+    let mut phase = 0.0;
+    for i in 0..hit_len.min(signal.len().saturating_sub(start)) {
+        let progress = i as f32 / hit_len as f32;
+        let freq = f_start + (f_end - f_start) * progress;
+        phase += 2.0 * PI * freq / rate;
+        signal[start + i] += gain * (1.0 - progress).powi(2) * phase.sin();
+    }
+}
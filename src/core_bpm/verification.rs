@@ -0,0 +1,143 @@
+use super::analyzer::{BpmAnalyzer, BpmAnalyzerConfig};
+use serde::Deserialize;
+use std::error::Error;
+use std::time::Duration;
+
+/// `[verification]` section: slower analysis over a long window that has to
+/// confirm the tempo before it reaches Link, Art-Net and MIDI.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct VerificationConfig {
+    pub enabled: bool,
+    /// Seconds of audio the verification track correlates over
+    pub window_s: u64,
+    /// Largest gap (BPM) between the two tracks still counted as agreement
+    pub tolerance: f32,
+    /// Live hops between two verification passes (CPU)
+    pub interval_hops: usize,
+}
+
+impl Default for VerificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_s: 12,
+            tolerance: 1.5,
+            interval_hops: 4,
+        }
+    }
+}
+
+impl VerificationConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(4..=30).contains(&self.window_s) {
+            return Err("window_s must be in [4, 30]".to_string());
+        }
+        if !(self.tolerance.is_finite() && self.tolerance > 0.0) {
+            return Err("tolerance must be positive".to_string());
+        }
+        if self.interval_hops == 0 {
+            return Err("interval_hops must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Second analyzer on the same input with a long window, run every few hops.
+/// Its tempo moves slowly but does not flap: the fast tempo is only passed on
+/// as verified while both agree (up to an octave), and the last verified one is
+/// held otherwise.
+pub struct TempoVerifier {
+    analyzer: BpmAnalyzer,
+    config: VerificationConfig,
+    accumulator: Vec<f32>,
+    hops: usize,
+    slow_bpm: Option<f32>,
+    verified: Option<f32>,
+}
+
+impl TempoVerifier {
+    pub fn new(
+        sample_rate: u32,
+        analyzer_config: BpmAnalyzerConfig,
+        config: VerificationConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        config.validate()?;
+        let slow_config = Self::slow_config(analyzer_config, &config);
+        Ok(Self {
+            analyzer: BpmAnalyzer::new(sample_rate, Some(slow_config))?,
+            config,
+            accumulator: Vec::new(),
+            hops: 0,
+            slow_bpm: None,
+            verified: None,
+        })
+    }
+
+    // Same search as the live analyzer over the long window, results right away
+    fn slow_config(
+        analyzer_config: BpmAnalyzerConfig,
+        config: &VerificationConfig,
+    ) -> BpmAnalyzerConfig {
+        BpmAnalyzerConfig {
+            window_duration: Duration::from_secs(config.window_s),
+            pipelined: false,
+            ..analyzer_config
+        }
+    }
+
+    pub fn push(&mut self, packet: &[f32]) {
+        self.accumulator.extend_from_slice(packet);
+    }
+
+    /// Called once per live hop with the fast tempo. Runs a verification pass
+    /// every `interval_hops`, then returns the verified tempo.
+    pub fn update(&mut self, fast_bpm: Option<f32>) -> Result<Option<f32>, Box<dyn Error>> {
+        self.hops += 1;
+        if self.hops >= self.config.interval_hops {
+            self.hops = 0;
+            let processed = self.analyzer.process(&self.accumulator);
+            self.accumulator.clear();
+            if let Some(result) = processed? {
+                self.slow_bpm = Some(result.bpm);
+            }
+        }
+        // The octave stays the live analyzer's call (preference, corrections)
+        if let (Some(fast), Some(slow)) = (fast_bpm, self.slow_bpm) {
+            let agrees = [slow / 2.0, slow, slow * 2.0]
+                .iter()
+                .any(|candidate| (fast - candidate).abs() <= self.config.tolerance);
+            if agrees {
+                self.verified = Some(fast);
+            }
+        }
+        Ok(self.verified)
+    }
+
+    /// Tempo of the long window, `None` until it has filled up.
+    pub fn slow_bpm(&self) -> Option<f32> {
+        self.slow_bpm
+    }
+
+    /// Stream restarted or new capture rate: the long window warm-starts like
+    /// the live analyzer, the verified tempo is kept.
+    pub fn reset(&mut self, sample_rate: u32) -> Result<(), Box<dyn Error>> {
+        self.accumulator.clear();
+        self.hops = 0;
+        self.analyzer.reset_soft(sample_rate)
+    }
+
+    /// New analyzer settings: the long window starts over.
+    pub fn set_analyzer_config(
+        &mut self,
+        sample_rate: u32,
+        analyzer_config: BpmAnalyzerConfig,
+    ) -> Result<(), Box<dyn Error>> {
+        let slow_config = Self::slow_config(analyzer_config, &self.config);
+        self.analyzer = BpmAnalyzer::new(sample_rate, Some(slow_config))?;
+        self.accumulator.clear();
+        self.hops = 0;
+        self.slow_bpm = None;
+        Ok(())
+    }
+}
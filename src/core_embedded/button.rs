@@ -5,6 +5,7 @@ pub mod button {
     use std::time::Duration;
     use tokio::sync::mpsc::Sender;
     use tokio::time::{Instant, sleep_until};
+    use tracing::{error, info};
 
     /// Les différents types d'actions détectées
     #[derive(Debug, Clone, Copy, PartialEq)]
@@ -78,7 +79,7 @@ pub mod button {
             tokio::pin!(double_click_timer);
             tokio::pin!(long_press_timer);
 
-            println!(
+            info!(
                 "Button Listener started on {} line {}",
                 self.chip_path, self.line_offset
             );
@@ -115,7 +116,7 @@ pub mod button {
                                 }
                             },
                             Err(e) => {
-                                eprintln!("Erreur GPIO Stream: {}", e);
+                                error!("Erreur GPIO Stream: {}", e);
                                 // On peut décider de continuer ou break
                             }
                         }
@@ -1,18 +1,28 @@
 #[cfg(all(target_arch = "aarch64", target_os = "linux"))]
 pub mod display {
+    // `DisplayConfig` est aussi un trait du prélude ssd1306 (init)
+    use crate::core_embedded::led::led::BeatInfo;
+    use crate::core_embedded::pages::pages::{self, Layout, Page, PageId, Pages};
+    use crate::core_embedded::tft::tft::TftDisplay;
     use embedded_graphics::image::Image;
-    use embedded_graphics::mono_font::{MonoTextStyle, ascii::FONT_10X20};
+    use embedded_graphics::mono_font::ascii::{FONT_5X8, FONT_6X10, FONT_10X20};
     use embedded_graphics::pixelcolor::BinaryColor;
     use embedded_graphics::prelude::*;
-    use embedded_graphics::text::Text;
+    use embedded_graphics::primitives::Rectangle;
+    use embedded_graphics::text::Alignment;
     use linux_embedded_hal::I2cdev;
+    use rust_bpm_analyzer::config::{DisplayConfig as ScreenConfig, DisplayKind};
+    use rust_bpm_analyzer::outputs::{OutputSink, TempoUpdate};
     use ssd1306::mode::BufferedGraphicsMode;
     use ssd1306::{I2CDisplayInterface, Ssd1306, prelude::*};
+    use std::net::Ipv4Addr;
     use std::sync::Arc;
     use std::sync::Mutex;
     use std::sync::atomic::{AtomicBool, Ordering};
     use tinybmp::Bmp;
-    use tokio::time::{Duration, sleep};
+    use tokio::sync::watch;
+    use tokio::time::{Duration, interval, sleep};
+    use tracing::{info, warn};
 
     mod assets {
         pub const ICON_USB: &[u8] = include_bytes!("../../assets/display_asset/USB-tiny.bmp");
@@ -34,6 +44,18 @@ pub mod display {
         Update,
     }
 
+    impl StatusBarIcon {
+        /// Emplacement de l'icône dans la barre de statut `area`
+        fn position(&self, area: Rectangle) -> Point {
+            let x = match self {
+                StatusBarIcon::Usb => 16,
+                StatusBarIcon::Ethernet | StatusBarIcon::Internet => 48,
+                StatusBarIcon::Update => 112,
+            };
+            area.top_left + Point::new(x, 0)
+        }
+    }
+
     #[derive(Clone, Debug, Default)]
     pub struct AppState {
         pub usb_connected: bool,
@@ -41,8 +63,118 @@ pub mod display {
         pub internet_connected: bool,
         pub update_available: bool,
         pub update_in_progress: bool,
+        /// Session Link affichée en haut de l'écran: (pairs, tempo x10)
+        pub link_session: Option<(usize, i64)>,
+    }
+
+    impl AppState {
+        /// Retient l'état affiché par une icône de la barre de statut
+        pub fn set_icon(&mut self, icon: &StatusBarIcon, on: bool) {
+            match icon {
+                StatusBarIcon::Usb => self.usb_connected = on,
+                StatusBarIcon::Ethernet => self.ethernet_connected = on,
+                StatusBarIcon::Internet => {
+                    self.internet_connected = on;
+                    // Usually internet implies ethernet too
+                    if on {
+                        self.ethernet_connected = true;
+                    }
+                }
+                StatusBarIcon::Update => self.update_available = on,
+            }
+        }
+
+        /// Retient la session Link affichée, `false` si rien n'a changé au dixième de BPM près
+        pub fn set_link_session(&mut self, peers: usize, tempo: f64) -> bool {
+            let session = (peers, (tempo * 10.0).round() as i64);
+            if self.link_session == Some(session) {
+                return false;
+            }
+            self.link_session = Some(session);
+            true
+        }
+
+        /// Passe en mise à jour si une nouvelle version est joignable
+        pub fn begin_update(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            if !self.update_in_progress && self.update_available && self.internet_connected {
+                self.update_in_progress = true;
+                return Ok(());
+            }
+            Err("Impossible to Update".into())
+        }
     }
 
+    /// Écran de l'appareil, OLED monochrome ou TFT couleur (`[display] kind`).
+    /// Les erreurs de dessin sont rendues à l'appelant, qui les ignore en général.
+    pub trait Display: Send {
+        /// Met à jour (flush) l'affichage
+        fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+
+        /// Affiche une icône spécifique de la barre de statut
+        fn draw_status_icon(
+            &mut self,
+            icon: StatusBarIcon,
+        ) -> Result<(), Box<dyn std::error::Error>>;
+
+        /// Efface une icône de la barre de statut
+        fn clear_status_icon(
+            &mut self,
+            icon: StatusBarIcon,
+        ) -> Result<(), Box<dyn std::error::Error>>;
+
+        /// Passe à la page suivante (appui court sur le bouton)
+        fn next_page(&mut self) -> Result<PageId, Box<dyn std::error::Error>>;
+
+        fn show_bpm(&mut self, bpm: f32) -> Result<(), Box<dyn std::error::Error>>;
+
+        /// Flash du beat (`on` pendant le début de chaque temps), ignoré par l'OLED
+        fn show_beat(&mut self, _on: bool) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+
+        /// Ligne de statut Link: nombre de pairs et tempo de la session, pour
+        /// vérifier que l'appareil a rejoint la session
+        fn show_link_state(
+            &mut self,
+            peers: usize,
+            tempo: f64,
+        ) -> Result<(), Box<dyn std::error::Error>>;
+
+        /// Nom d'hôte affiché avec les adresses réseau
+        fn set_hostname(&mut self, hostname: &str) -> Result<(), Box<dyn std::error::Error>>;
+
+        /// Adresse IPv4 d'une interface suivie (eth0 ou usb0), `None` quand elle est retirée
+        fn set_interface_address(
+            &mut self,
+            interface: &str,
+            address: Option<Ipv4Addr>,
+        ) -> Result<(), Box<dyn std::error::Error>>;
+
+        /// Niveau RMS de la capture (valeur entre 0.0 et 0.6 en pleine échelle)
+        fn update_audio_bar(&mut self, value: f32) -> Result<(), Box<dyn std::error::Error>>;
+
+        /// Gain ALSA appliqué par le PID
+        fn update_gain(&mut self, gain: i64) -> Result<(), Box<dyn std::error::Error>>;
+
+        /// Écran d'au revoir avant l'arrêt: plus rien n'est dessiné ensuite
+        fn show_goodbye(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+
+        /// Passe sur l'affichage de mise à jour, erreur si aucune n'est possible
+        fn update_in_progress(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+
+        /// Image suivante de l'animation de mise à jour
+        fn next_update_frame(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+
+        /// Fin de la mise à jour: retour à l'affichage du tempo
+        fn end_update(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+    }
+
+    /// Écran partagé entre la boucle principale et les tâches réseau/mise à jour
+    pub type SharedDisplay = Arc<Mutex<Box<dyn Display>>>;
+
+    // Durée du flash de beat des écrans couleur
+    const BEAT_FLASH: Duration = Duration::from_millis(80);
+
     pub struct Icons {
         pub usb: Bmp<'static, BinaryColor>,
         pub ethernet: Bmp<'static, BinaryColor>,
@@ -51,6 +183,9 @@ pub mod display {
         pub update_pivot: Bmp<'static, BinaryColor>,
     }
 
+    // Icônes de la barre de statut: 16x16
+    const ICON_SIZE: Size = Size::new(16, 16);
+
     impl Icons {
         pub fn new() -> Result<Self, String> {
             Ok(Self {
@@ -65,284 +200,424 @@ pub mod display {
         }
     }
 
+    // Adresses I2C essayées dans l'ordre quand `[display] address` n'est pas fixée
+    const PROBED_ADDRESSES: [u8; 2] = [0x3C, 0x3D];
+
+    type Screen<SIZE> = Ssd1306<I2CInterface<I2cdev>, SIZE, BufferedGraphicsMode<SIZE>>;
+
+    /// Écran 128x64 ou 128x32, le type du pilote dépendant de la taille
+    enum Panel {
+        Tall(Screen<DisplaySize128x64>),
+        Short(Screen<DisplaySize128x32>),
+    }
+
+    impl Panel {
+        fn new(i2c: I2cdev, address: u8, height: u32, rotation: DisplayRotation) -> Self {
+            let interface = I2CDisplayInterface::new_custom_address(i2c, address);
+            if height == 32 {
+                Panel::Short(
+                    Ssd1306::new(interface, DisplaySize128x32, rotation)
+                        .into_buffered_graphics_mode(),
+                )
+            } else {
+                Panel::Tall(
+                    Ssd1306::new(interface, DisplaySize128x64, rotation)
+                        .into_buffered_graphics_mode(),
+                )
+            }
+        }
+
+        fn init(&mut self) -> Result<(), String> {
+            match self {
+                Panel::Tall(screen) => screen.init(),
+                Panel::Short(screen) => screen.init(),
+            }
+            .map_err(|e| format!("Init error: {:?}", e))
+        }
+
+        fn flush(&mut self) -> Result<(), String> {
+            match self {
+                Panel::Tall(screen) => screen.flush(),
+                Panel::Short(screen) => screen.flush(),
+            }
+            .map_err(|e| format!("Flush error: {:?}", e))
+        }
+    }
+
+    impl OriginDimensions for Panel {
+        fn size(&self) -> Size {
+            match self {
+                Panel::Tall(screen) => screen.size(),
+                Panel::Short(screen) => screen.size(),
+            }
+        }
+    }
+
+    impl DrawTarget for Panel {
+        type Color = BinaryColor;
+        type Error = String;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            match self {
+                Panel::Tall(screen) => screen.draw_iter(pixels),
+                Panel::Short(screen) => screen.draw_iter(pixels),
+            }
+            .map_err(|e| format!("{:?}", e))
+        }
+    }
+
+    /// Écran OLED monochrome: barre de statut et pages, une à la fois
     pub struct BpmDisplay {
-        display: Ssd1306<
-            I2CInterface<I2cdev>,
-            DisplaySize128x64,
-            BufferedGraphicsMode<DisplaySize128x64>,
-        >,
+        display: Panel,
         icons: Icons,
+        pages: Pages,
         pub state: AppState,
     }
 
     impl BpmDisplay {
-        pub fn new(i2c_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-            // Tentative adresse par défaut (0x3C)
-            eprintln!(
-                "Tentative connexion OLED sur {} à l'adresse 0x3C...",
-                i2c_path
-            );
-            match Self::try_init(i2c_path, 0x3C) {
-                Ok(display) => return Ok(display),
-                Err(e) => eprintln!("-> Échec 0x3C: {:?}", e),
+        /// Ouvre l'écran de la section `[display]`: adresse fixée, ou 0x3C puis 0x3D
+        pub fn new(config: &ScreenConfig) -> Result<Self, Box<dyn std::error::Error>> {
+            let rotation = match config.rotation {
+                180 => DisplayRotation::Rotate180,
+                _ => DisplayRotation::Rotate0,
+            };
+            let addresses = match config.address {
+                Some(address) => vec![address],
+                None => PROBED_ADDRESSES.to_vec(),
+            };
+            for address in addresses {
+                info!(
+                    "Tentative connexion OLED 128x{} sur {} à l'adresse 0x{:02X}...",
+                    config.height, config.i2c_bus, address
+                );
+                match Self::try_init(&config.i2c_bus, address, config.height, rotation) {
+                    Ok(display) => return Ok(display),
+                    Err(e) => warn!("-> Échec 0x{:02X}: {:?}", address, e),
+                }
             }
             Err("Échec de l'initialisation de l'écran OLED".into())
         }
 
-        /// Met à jour (flush) l'affichage
-        pub fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-            self.display
-                .flush()
-                .map_err(|e| format!("Flush error: {:?}", e))?;
+        fn try_init(
+            i2c_path: &str,
+            address: u8,
+            height: u32,
+            rotation: DisplayRotation,
+        ) -> Result<Self, Box<dyn std::error::Error>> {
+            let i2c = I2cdev::new(i2c_path)?;
+            let mut display = Panel::new(i2c, address, height, rotation);
+
+            display.init()?;
+            display
+                .clear(BinaryColor::Off)
+                .map_err(|e| format!("Clear error: {:?}", e))?;
+
+            let icons = Icons::new().map_err(|e| format!("Icon load error: {}", e))?;
+            let layout = Layout::new(display.size());
+            let pages = Pages::new([icons.update, icons.update_pivot], layout);
+            let mut bpm_display = BpmDisplay {
+                display,
+                icons,
+                pages,
+                state: AppState::default(),
+            };
+            // Affichage de démarrage: page BPM sans tempo ("***.**")
+            bpm_display.redraw()?;
+            info!("OLED initialized at I2C address 0x{:02X}", address);
+            Ok(bpm_display)
+        }
+
+        pub fn current_page(&self) -> PageId {
+            self.pages.current()
+        }
+
+        pub fn show_page(&mut self, page: PageId) -> Result<(), Box<dyn std::error::Error>> {
+            self.pages.show(page);
+            self.redraw()
+        }
+
+        /// Redessine la page courante (la barre de statut n'est pas touchée)
+        pub fn redraw(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.pages
+                .draw(&mut self.display)
+                .map_err(|e| format!("Draw page error: {:?}", e))?;
+            self.flush()
+        }
+
+        fn redraw_network(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            if self.pages.current() == PageId::Network {
+                return self.redraw();
+            }
             Ok(())
         }
+    }
 
-        /// Affiche une icône spécifique de la barre de statut
-        pub fn draw_status_icon(
+    impl Display for BpmDisplay {
+        fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.display.flush()?;
+            Ok(())
+        }
+
+        fn draw_status_icon(
             &mut self,
             icon: StatusBarIcon,
         ) -> Result<(), Box<dyn std::error::Error>> {
-            match icon {
-                StatusBarIcon::Usb => {
-                    self.state.usb_connected = true;
-                    Image::new(&self.icons.usb, Point::new(16, 8))
-                        .draw(&mut self.display)
-                        .map_err(|e| format!("{:?}", e))?;
-                }
-                StatusBarIcon::Ethernet => {
-                    self.state.ethernet_connected = true;
-                    Image::new(&self.icons.ethernet, Point::new(48, 8))
-                        .draw(&mut self.display)
-                        .map_err(|e| format!("{:?}", e))?;
-                }
-                StatusBarIcon::Internet => {
-                    self.state.internet_connected = true;
-                    // Usually internet implies ethernet too
-                    self.state.ethernet_connected = true;
-                    Image::new(&self.icons.ethernet_internet, Point::new(48, 8))
-                        .draw(&mut self.display)
-                        .map_err(|e| format!("{:?}", e))?;
-                }
-                StatusBarIcon::Update => {
-                    self.state.update_available = true;
-                    Image::new(&self.icons.update, Point::new(112, 8))
-                        .draw(&mut self.display)
-                        .map_err(|e| format!("{:?}", e))?;
-                }
-            }
+            let bmp = match icon {
+                StatusBarIcon::Usb => &self.icons.usb,
+                StatusBarIcon::Ethernet => &self.icons.ethernet,
+                StatusBarIcon::Internet => &self.icons.ethernet_internet,
+                StatusBarIcon::Update => &self.icons.update,
+            };
+            self.state.set_icon(&icon, true);
+            // Pas de rangée d'icônes sur les écrans 128x32: seul l'état est retenu
+            let Some(area) = self.pages.layout().status_icons else {
+                return Ok(());
+            };
+            Image::new(bmp, icon.position(area))
+                .draw(&mut self.display)
+                .map_err(|e| format!("{:?}", e))?;
             Ok(())
         }
 
-        /// Efface une zone correspondant à une icône de la barre de statut
-        pub fn clear_status_icon(
+        fn clear_status_icon(
             &mut self,
             icon: StatusBarIcon,
         ) -> Result<(), Box<dyn std::error::Error>> {
-            // Taille standard des icônes à effacer (ex: 16x16 par sécurité, ou la taille réelle des BMP)
-            // Adaptez les dimensions (Size::new(w, h)) selon vos BMPs
-            let size = Size::new(16, 16);
-
-            let point = match icon {
-                StatusBarIcon::Usb => {
-                    self.state.usb_connected = false;
-                    Point::new(16, 8)
-                }
-                StatusBarIcon::Ethernet => {
-                    self.state.ethernet_connected = false;
-                    Point::new(48, 8)
-                }
-                StatusBarIcon::Internet => {
-                    self.state.internet_connected = false;
-                    Point::new(48, 8)
-                }
-                StatusBarIcon::Update => {
-                    self.state.update_available = false;
-                    Point::new(112, 8)
-                }
+            self.state.set_icon(&icon, false);
+            let Some(area) = self.pages.layout().status_icons else {
+                return Ok(());
             };
+            let area = Rectangle::new(icon.position(area), ICON_SIZE);
+            pages::clear(&mut self.display, area).map_err(|e| format!("{:?}", e))?;
+            Ok(())
+        }
 
-            // Dessine un rectangle noir (Off) par dessus
-            embedded_graphics::primitives::Rectangle::new(point, size)
-                .into_styled(embedded_graphics::primitives::PrimitiveStyle::with_fill(
-                    BinaryColor::Off,
-                ))
-                .draw(&mut self.display)
-                .map_err(|e| format!("{:?}", e))?;
+        fn next_page(&mut self) -> Result<PageId, Box<dyn std::error::Error>> {
+            let page = self.pages.next();
+            self.redraw()?;
+            Ok(page)
+        }
 
-            Ok(())
+        fn show_bpm(&mut self, bpm: f32) -> Result<(), Box<dyn std::error::Error>> {
+            self.pages.bpm.bpm = Some(bpm);
+            if self.pages.current() != PageId::Bpm {
+                return Ok(());
+            }
+            // Seule la zone du tempo est redessinée
+            let content = self.pages.layout().content;
+            self.pages
+                .bpm
+                .draw_bpm(&mut self.display, content)
+                .map_err(|e| format!("Draw error: {:?}", e))?;
+            self.flush()
         }
 
-        fn try_init(i2c_path: &str, address: u8) -> Result<Self, Box<dyn std::error::Error>> {
-            let i2c = I2cdev::new(i2c_path)?;
-            let interface = I2CDisplayInterface::new_custom_address(i2c, address);
-            let mut display = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0)
-                .into_buffered_graphics_mode();
+        fn show_link_state(
+            &mut self,
+            peers: usize,
+            tempo: f64,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            // Pas de rafraîchissement I2C si rien n'a changé au dixième de BPM près
+            if !self.state.set_link_session(peers, tempo) {
+                return Ok(());
+            }
+            let status_line = self.pages.layout().status_line;
+            pages::clear(&mut self.display, status_line)
+                .and_then(|_| {
+                    pages::text(
+                        &mut self.display,
+                        status_line.top_left + Point::new(2, 0),
+                        &link_text(peers, tempo),
+                        &FONT_5X8,
+                        Alignment::Left,
+                    )
+                })
+                .map_err(|e| format!("Draw link line error: {:?}", e))?;
+            self.flush()
+        }
 
-            display.init().map_err(|e| format!("Init error: {:?}", e))?;
-            display
-                .clear(BinaryColor::Off)
-                .map_err(|e| format!("Clear error: {:?}", e))?;
+        fn set_hostname(&mut self, hostname: &str) -> Result<(), Box<dyn std::error::Error>> {
+            self.pages.network.hostname = hostname.to_string();
+            self.redraw_network()
+        }
 
-            // Affichage de démarrage
-            let style = MonoTextStyle::new(&FONT_10X20, BinaryColor::On);
-            Text::new("***.**", Point::new(35, 45), style)
-                .draw(&mut display)
-                .map_err(|e| format!("Draw Hello error: {:?}", e))?;
-
-            embedded_graphics::primitives::Rectangle::new(Point::new(1, 54), Size::new(127, 10))
-                .into_styled(embedded_graphics::primitives::PrimitiveStyle::with_stroke(
-                    BinaryColor::On,
-                    1,
-                ))
-                .draw(&mut display)
-                .map_err(|e| format!("Rect audio error: {:?}", e))?;
-            println!("OLED initialized at I2C address 0x{:02X}", address);
+        fn set_interface_address(
+            &mut self,
+            interface: &str,
+            address: Option<Ipv4Addr>,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            let slot = match interface {
+                "eth0" => &mut self.pages.network.eth0,
+                "usb0" => &mut self.pages.network.usb0,
+                _ => return Ok(()),
+            };
+            if *slot == address {
+                return Ok(());
+            }
+            *slot = address;
+            self.redraw_network()
+        }
 
-            display
-                .flush()
-                .map_err(|e| format!("Flush error: {:?}", e))?;
+        fn update_audio_bar(&mut self, value: f32) -> Result<(), Box<dyn std::error::Error>> {
+            self.pages.bpm.level = value;
+            self.pages.audio.level = value;
+            // Seule la barre de la page affichée est redessinée à chaque paquet
+            let content = self.pages.layout().content;
+            let drawn = match self.pages.current() {
+                PageId::Bpm => self.pages.bpm.draw_level(&mut self.display, content),
+                PageId::Audio => self.pages.audio.draw(&mut self.display, content),
+                _ => return Ok(()),
+            };
+            drawn.map_err(|e| format!("Draw audio bar error: {:?}", e))?;
+            self.flush()
+        }
 
-            let icons = Icons::new().map_err(|e| format!("Icon load error: {}", e))?;
-            let state = AppState::default();
-            Ok(BpmDisplay {
-                display,
-                icons,
-                state,
-            })
+        fn update_gain(&mut self, gain: i64) -> Result<(), Box<dyn std::error::Error>> {
+            if self.pages.audio.gain == Some(gain) {
+                return Ok(());
+            }
+            self.pages.audio.gain = Some(gain);
+            if self.pages.current() == PageId::Audio {
+                return self.redraw();
+            }
+            Ok(())
         }
 
-        pub fn show_bpm(&mut self, bpm: f32) -> Result<(), Box<dyn std::error::Error>> {
-            // On efface la zone où le BPM est affiché pour éviter la superposition
-            // Position (35, 45), Font 10x20. approx 60px de large pour "XXX.XX"
-            embedded_graphics::primitives::Rectangle::new(Point::new(0, 25), Size::new(128, 25))
-                .into_styled(embedded_graphics::primitives::PrimitiveStyle::with_fill(
-                    BinaryColor::Off,
-                ))
-                .draw(&mut self.display)
-                .map_err(|e| format!("Clear rect error: {:?}", e))?;
+        fn show_goodbye(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            let screen = self.display.bounding_box();
+            let center = screen.center();
+            pages::clear(&mut self.display, screen)
+                .and_then(|_| {
+                    pages::text(
+                        &mut self.display,
+                        Point::new(center.x, center.y - 16),
+                        "Bye!",
+                        &FONT_10X20,
+                        Alignment::Center,
+                    )
+                })
+                .and_then(|_| {
+                    pages::text(
+                        &mut self.display,
+                        Point::new(center.x, center.y + 8),
+                        "Powering off...",
+                        &FONT_6X10,
+                        Alignment::Center,
+                    )
+                })
+                .map_err(|e| format!("Draw goodbye error: {:?}", e))?;
+            self.flush()
+        }
 
-            let style = MonoTextStyle::new(&FONT_10X20, BinaryColor::On);
-            let text = format!("{:.2}", bpm);
+        fn update_in_progress(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.state.begin_update()?;
+            // Affichage de mise à jour en cours
+            self.show_page(PageId::Update)
+        }
 
-            Text::new(&text, Point::new(35, 45), style)
-                .draw(&mut self.display)
-                .map_err(|e| format!("Draw error: {:?}", e))?;
-            self.display
-                .flush()
-                .map_err(|e| format!("Flush error: {:?}", e))?;
-            Ok(())
+        fn next_update_frame(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.pages.update.frame += 1;
+            self.redraw()
         }
 
-        pub fn update_audio_bar(&mut self, value: f32) -> Result<(), Box<dyn std::error::Error>> {
-            // Valeur entre 0.0 et 0.6
-            let clamped = if value < 0.0 {
-                0.0
-            } else if value > 0.6 {
-                0.6
-            } else {
-                value
-            };
-            let bar_width = (clamped * 125.0 / 0.6).round() as u32; // Largeur max 125px
+        fn end_update(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.state.update_in_progress = false;
+            self.show_page(PageId::Bpm)
+        }
+    }
 
-            // On efface la zone de la barre audio
-            embedded_graphics::primitives::Rectangle::new(Point::new(2, 55), Size::new(125, 8))
-                .into_styled(embedded_graphics::primitives::PrimitiveStyle::with_fill(
-                    BinaryColor::Off,
-                ))
-                .draw(&mut self.display)
-                .map_err(|e| format!("Clear audio bar error: {:?}", e))?;
-
-            // On dessine la nouvelle barre audio
-            embedded_graphics::primitives::Rectangle::new(
-                Point::new(2, 55),
-                Size::new(bar_width, 8),
-            )
-            .into_styled(embedded_graphics::primitives::PrimitiveStyle::with_fill(
-                BinaryColor::On,
-            ))
-            .draw(&mut self.display)
-            .map_err(|e| format!("Draw audio bar error: {:?}", e))?;
-
-            self.display
-                .flush()
-                .map_err(|e| format!("Flush error: {:?}", e))?;
-            Ok(())
+    /// L'écran vu comme une sortie du tempo: BPM affiché et barre audio. Sans
+    /// attente: une image manquée est rattrapée au passage suivant.
+    pub struct OledSink {
+        display: SharedDisplay,
+    }
+
+    impl OledSink {
+        pub fn new(display: SharedDisplay) -> Self {
+            Self { display }
         }
+    }
 
-        pub fn update_in_progress(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-            if !self.state.update_in_progress
-                && self.state.update_available
-                && self.state.internet_connected
-            {
-                self.state.update_in_progress = true;
-                self.display
-                    .clear(BinaryColor::Off)
-                    .map_err(|e| format!("Clear error: {:?}", e))?;
-                // Affichage de mise à jour en cours
-                let style = MonoTextStyle::new(&FONT_10X20, BinaryColor::On);
-                Text::new("Update in Progress", Point::new(10, 30), style)
-                    .draw(&mut self.display)
-                    .map_err(|e| format!("Draw update error: {:?}", e))?;
-                self.display
-                    .flush()
-                    .map_err(|e| format!("Flush error: {:?}", e))?;
+    impl OutputSink for OledSink {
+        fn name(&self) -> &'static str {
+            "Display"
+        }
 
-                return Ok(());
+        fn on_result(&mut self, update: &TempoUpdate) {
+            if let Ok(mut guard) = self.display.try_lock() {
+                let _ = guard.show_bpm(update.shown_bpm);
             }
-            Err("Impossible to Update".into())
         }
 
-        // Note: Cette fonction prend un Arc<Mutex<Self>> pour pouvoir être spawnée
-        // et acquérir le lock uniquement quand nécessaire.
-        pub async fn run_update_animation(
-            display_arc: Arc<Mutex<Self>>,
-            is_running: Arc<AtomicBool>,
-        ) {
-            // Boucle tant que `is_running` est vrai
-            while is_running.load(Ordering::Relaxed) {
-                // Scope pour le lock (Frame 1)
-                {
-                    if let Ok(mut guard) = display_arc.try_lock() {
-                        // Hack: déstructurer.
-                        let BpmDisplay { display, icons, .. } = &mut *guard;
-                        let _ = Image::new(&icons.update, Point::new(56, 10)).draw(display);
-                        let _ = display.flush();
-                    }
-                }
+        fn on_level(&mut self, rms: f32) {
+            if let Ok(mut guard) = self.display.try_lock() {
+                let _ = guard.update_audio_bar(rms);
+            }
+        }
+    }
 
-                // Pause 500 ms (en vérifiant si on doit quitter)
-                for _ in 0..5 {
-                    if !is_running.load(Ordering::Relaxed) {
-                        break;
-                    }
-                    sleep(Duration::from_millis(100)).await;
-                }
+    /// Ouvre l'écran choisi par `[display] kind`
+    pub fn open(config: &ScreenConfig) -> Result<Box<dyn Display>, Box<dyn std::error::Error>> {
+        Ok(match config.kind {
+            DisplayKind::Ssd1306 => Box::new(BpmDisplay::new(config)?),
+            DisplayKind::St7789 | DisplayKind::Ili9341 => Box::new(TftDisplay::new(config)?),
+        })
+    }
+
+    /// Texte de la ligne d'état Link
+    pub fn link_text(peers: usize, tempo: f64) -> String {
+        if peers == 0 {
+            "Link: no peers".to_string()
+        } else {
+            format!("Link: {} peers @ {:.1}", peers, tempo)
+        }
+    }
+
+    // Note: Cette fonction prend un Arc<Mutex<..>> pour pouvoir être spawnée
+    // et acquérir le lock uniquement quand nécessaire.
+    pub async fn run_update_animation(display_arc: SharedDisplay, is_running: Arc<AtomicBool>) {
+        // Boucle tant que `is_running` est vrai: une image toutes les 500 ms
+        while is_running.load(Ordering::Relaxed) {
+            if let Ok(mut guard) = display_arc.try_lock() {
+                let _ = guard.next_update_frame();
+            }
+
+            // Pause 500 ms (en vérifiant si on doit quitter)
+            for _ in 0..5 {
                 if !is_running.load(Ordering::Relaxed) {
                     break;
                 }
+                sleep(Duration::from_millis(100)).await;
+            }
+        }
 
-                // Scope pour le lock (Frame 2)
-                {
-                    if let Ok(mut guard) = display_arc.try_lock() {
-                        let BpmDisplay { display, icons, .. } = &mut *guard;
-                        let _ = Image::new(&icons.update_pivot, Point::new(56, 10)).draw(display);
-                        let _ = display.flush();
-                    }
-                }
+        // Cleanup: retour à la page BPM
+        if let Ok(mut guard) = display_arc.try_lock() {
+            let _ = guard.end_update();
+        }
+    }
 
-                // Pause 500 ms
-                for _ in 0..5 {
-                    if !is_running.load(Ordering::Relaxed) {
-                        break;
-                    }
-                    sleep(Duration::from_millis(100)).await;
-                }
+    /// Flash de l'écran sur chaque beat (écrans couleur), comme la LED de statut
+    pub async fn run_beat_flash(display_arc: SharedDisplay, rx: watch::Receiver<Option<BeatInfo>>) {
+        let mut frame_timer = interval(Duration::from_millis(20));
+        let mut shown = false;
+        loop {
+            frame_timer.tick().await;
+            // Allumé pendant les premières `BEAT_FLASH` de chaque beat
+            let beat = *rx.borrow();
+            let on = beat.filter(|b| b.bpm > 0.0).is_some_and(|b| {
+                let period = 60.0 / b.bpm;
+                let elapsed = b.last_beat.elapsed().as_secs_f32();
+                elapsed % period < BEAT_FLASH.as_secs_f32()
+            });
+            if on == shown {
+                continue;
             }
-
-            // Cleanup: Effacer l'icône à la fin
             if let Ok(mut guard) = display_arc.try_lock() {
-                let _ = guard.display.clear(BinaryColor::Off);
-                let _ = guard.display.flush();
+                shown = guard.show_beat(on).map_or(shown, |_| on);
             }
         }
     }
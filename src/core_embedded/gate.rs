@@ -0,0 +1,61 @@
+#[cfg(all(any(target_arch = "aarch64", target_arch = "arm"), target_os = "linux"))]
+pub mod gate {
+    use futures::stream::StreamExt;
+    use gpio_cdev::{AsyncLineEventHandle, Chip, EventRequestFlags, EventType, LineRequestFlags};
+    use rust_bpm_analyzer::config::GateConfig;
+    use tokio::sync::mpsc::Sender;
+    use tracing::info;
+
+    /// Entrée GPIO de gating : l'analyse ne tourne que tant que la ligne est active.
+    pub struct GateInput {
+        chip_path: String,
+        line_offset: u32,
+        active_low: bool,
+    }
+
+    impl GateInput {
+        /// `None` si aucune ligne n'est configurée.
+        pub fn new(config: &GateConfig) -> Option<Self> {
+            Some(Self {
+                chip_path: config.gpio_chip.clone(),
+                line_offset: config.gpio_line?,
+                active_low: config.active_low,
+            })
+        }
+
+        /// Envoie l'état initial de la porte puis chacun de ses changements.
+        pub async fn run(self, sender: Sender<bool>) -> Result<(), Box<dyn std::error::Error>> {
+            let mut chip = Chip::new(&self.chip_path)?;
+            let line = chip.get_line(self.line_offset)?;
+            let handle = line.events(
+                LineRequestFlags::INPUT,
+                EventRequestFlags::BOTH_EDGES,
+                "rust-bpm-gate",
+            )?;
+
+            let mut open = (handle.get_value()? != 0) != self.active_low;
+            info!(
+                "Gate GPIO sur {} ligne {} ({})",
+                self.chip_path,
+                self.line_offset,
+                if open { "ouverte" } else { "fermée" }
+            );
+            if sender.send(open).await.is_err() {
+                return Ok(());
+            }
+
+            let mut events = AsyncLineEventHandle::new(handle)?;
+            while let Some(event) = events.next().await {
+                let high = event?.event_type() == EventType::RisingEdge;
+                let now_open = high != self.active_low;
+                if now_open != open {
+                    open = now_open;
+                    if sender.send(open).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
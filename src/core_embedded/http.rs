@@ -0,0 +1,430 @@
+#[cfg(all(any(target_arch = "aarch64", target_arch = "arm"), target_os = "linux"))]
+pub mod http {
+    use crate::core_embedded::update::update::UpdateStatus;
+    use axum::body::{Body, to_bytes};
+    use axum::extract::{Request, State};
+    use axum::http::header::CONTENT_TYPE;
+    use axum::http::{HeaderName, Method, StatusCode};
+    use axum::middleware::{self, Next};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use rust_bpm_analyzer::config::AgcConfig;
+    use rust_bpm_analyzer::core_bpm::analyzer::{AnalyzerState, OctavePreference};
+    use rust_bpm_analyzer::core_bpm::histogram::HistogramSnapshot;
+    use rust_bpm_analyzer::core_bpm::key::Key;
+    use rust_bpm_analyzer::core_bpm::level_history::LevelSnapshot;
+    use rust_bpm_analyzer::logging::{self, LogEvent};
+    use rust_bpm_analyzer::metrics::METRICS;
+    use rust_bpm_analyzer::network_sync::api_auth::{self, CommandVerifier};
+    use rust_bpm_analyzer::network_sync::discovery;
+    use rust_bpm_analyzer::network_sync::follow::LinkRole;
+    use serde::{Deserialize, Serialize};
+    use std::net::UdpSocket;
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+    use tokio::sync::mpsc::Sender;
+    use tracing::{info, warn};
+
+    /// Commandes reçues par l'API, traitées dans la boucle principale
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum HttpCommand {
+        SetAnalysis(bool),
+        SetOctave(OctavePreference),
+        SetAgcParams(AgcConfig),
+        SetGate(bool),
+        AutoGain,
+        SaveRecording,
+        CheckUpdate,
+        Update,
+        Shutdown,
+    }
+
+    /// Session Ableton Link rejointe par l'appareil
+    #[derive(Debug, Clone, Copy, Default, Serialize)]
+    pub struct LinkState {
+        pub peers: usize,
+        pub tempo: f64,
+    }
+
+    /// État partagé, mis à jour par la boucle principale
+    #[derive(Debug, Clone, Default)]
+    pub struct DeviceStatus {
+        /// Nom d'hôte annoncé en mDNS, repris par GET /status pour les pairs statiques
+        pub name: String,
+        pub bpm: Option<f32>,
+        pub confidence: f32,
+        /// Position du contretemps en % du temps (50 droit, 66,7 shuffle)
+        pub swing: Option<f32>,
+        /// Tonalité en notation Camelot, avec `[key] enabled`
+        pub key: Option<Key>,
+        pub analysis_enabled: bool,
+        pub octave: OctavePreference,
+        /// Volume de capture ALSA appliqué par le PID
+        pub gain: Option<i64>,
+        pub agc: AgcConfig,
+        pub histogram: HistogramSnapshot,
+        /// Niveau d'entrée des dernières minutes
+        pub levels: LevelSnapshot,
+        /// Fin du dernier point de `levels`, publiée par GET /levels sur l'horloge de GET /clock
+        pub levels_at: Option<Instant>,
+        /// Niveau RMS actuel de l'entrée (0..1), pour le tableau de bord du bureau
+        pub energy: f32,
+        /// Instant du dernier temps détecté, publié par GET /status sur l'horloge de GET /clock
+        pub last_beat: Option<Instant>,
+        /// État de la porte d'analyse, `None` sans gating
+        pub gate: Option<bool>,
+        /// Internes de l'analyseur, rafraîchis au plus une fois par seconde
+        pub state: Option<AnalyzerState>,
+        /// Trames audio perdues depuis le démarrage (boucle d'analyse en retard)
+        pub dropped_frames: u64,
+        /// Meneur ou suiveur du tempo de la session Link
+        pub link_role: LinkRole,
+        /// Maître de l'élection ([election]), `None` sans élection
+        pub elected: Option<bool>,
+        /// Pairs et tempo de la session Link
+        pub link: LinkState,
+        /// Version disponible et avancement de la mise à jour
+        pub update: UpdateStatus,
+    }
+
+    #[derive(Serialize)]
+    struct StatusResponse {
+        bpm: Option<f32>,
+        confidence: f32,
+        swing: Option<f32>,
+        key: Option<Key>,
+        analysis_enabled: bool,
+        octave: OctavePreference,
+        gain: Option<i64>,
+        gate: Option<bool>,
+        energy: f32,
+        /// Dernier temps en µs sur l'horloge de GET /clock
+        beat_us: Option<u64>,
+        dropped_frames: u64,
+        link_role: LinkRole,
+        elected: Option<bool>,
+        link: LinkState,
+        update: UpdateStatus,
+        uptime_s: u64,
+        // Mêmes champs que l'annonce mDNS, pour la découverte en unicast
+        name: String,
+        version: &'static str,
+        platform: String,
+        api: u32,
+        ip: Option<String>,
+    }
+
+    /// Horloge monotone de l'appareil (µs depuis le démarrage de l'API), que le
+    /// bureau échange en NTP allégé pour aligner les temps des appareils
+    #[derive(Serialize)]
+    struct ClockResponse {
+        clock_us: u64,
+    }
+
+    #[derive(Serialize)]
+    struct UpdateResponse {
+        version: &'static str,
+        #[serde(flatten)]
+        status: UpdateStatus,
+    }
+
+    #[derive(Deserialize)]
+    struct AnalysisRequest {
+        enabled: bool,
+    }
+
+    /// Champs absents = valeur actuelle conservée
+    #[derive(Deserialize)]
+    struct AgcRequest {
+        setpoint: Option<f32>,
+        kp: Option<f32>,
+        ki: Option<f32>,
+        kd: Option<f32>,
+        window: Option<usize>,
+    }
+
+    #[derive(Deserialize)]
+    struct GateRequest {
+        open: bool,
+    }
+
+    #[derive(Deserialize)]
+    struct OctaveRequest {
+        preference: OctavePreference,
+    }
+
+    // Corps des commandes POST, de petits documents JSON
+    const MAX_COMMAND_BODY: usize = 16 * 1024;
+
+    #[derive(Clone)]
+    struct ApiState {
+        status: Arc<Mutex<DeviceStatus>>,
+        commands: Sender<HttpCommand>,
+        started_at: Instant,
+        verifier: Option<Arc<Mutex<CommandVerifier>>>,
+        metrics: bool,
+    }
+
+    /// Serveur REST (GET /status, /clock, /histogram, /levels, /agc, /state, /update, /logs, /metrics, POST /analysis,
+    /// /octave, /agc, /gate, /autogain, /recording, /update/check, /update, /shutdown).
+    /// Avec `api_key`, les commandes POST doivent être signées (HMAC-SHA256 de
+    /// l'horodatage, de la méthode, du chemin et du corps, voir `api_auth`);
+    /// les GET restent ouverts pour la découverte.
+    /// GET /metrics (format Prometheus) répond 404 sans `[metrics] enabled`.
+    pub async fn serve(
+        port: u16,
+        status: Arc<Mutex<DeviceStatus>>,
+        commands: Sender<HttpCommand>,
+        api_key: Option<String>,
+        metrics: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let state = ApiState {
+            status,
+            commands,
+            started_at: Instant::now(),
+            verifier: api_key
+                .filter(|k| !k.is_empty())
+                .map(|key| Arc::new(Mutex::new(CommandVerifier::new(key)))),
+            metrics,
+        };
+        let app = Router::new()
+            .route("/status", get(get_status))
+            .route("/clock", get(get_clock))
+            .route("/histogram", get(get_histogram))
+            .route("/levels", get(get_levels))
+            .route("/state", get(get_state))
+            .route("/analysis", post(post_analysis))
+            .route("/octave", post(post_octave))
+            .route("/agc", get(get_agc).post(post_agc))
+            .route("/gate", post(post_gate))
+            .route("/autogain", post(post_autogain))
+            .route("/recording", post(post_recording))
+            .route("/update", get(get_update).post(post_update))
+            .route("/update/check", post(post_update_check))
+            .route("/logs", get(get_logs))
+            .route("/metrics", get(get_metrics))
+            .route("/shutdown", post(post_shutdown))
+            .route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                verify_command,
+            ))
+            .with_state(state);
+
+        let listener = tokio::net::TcpListener::bind(("0.0.0.0", port)).await?;
+        info!("API HTTP disponible sur le port {}", port);
+        axum::serve(listener, app).await?;
+        Ok(())
+    }
+
+    async fn get_status(State(state): State<ApiState>) -> Json<StatusResponse> {
+        let status = state.status.lock().map(|s| s.clone()).unwrap_or_default();
+        Json(StatusResponse {
+            bpm: status.bpm,
+            confidence: status.confidence,
+            swing: status.swing,
+            key: status.key,
+            analysis_enabled: status.analysis_enabled,
+            octave: status.octave,
+            gain: status.gain,
+            gate: status.gate,
+            energy: status.energy,
+            beat_us: status
+                .last_beat
+                .map(|beat| clock_us(state.started_at, beat)),
+            dropped_frames: status.dropped_frames,
+            link_role: status.link_role,
+            elected: status.elected,
+            link: status.link,
+            update: status.update,
+            uptime_s: state.started_at.elapsed().as_secs(),
+            name: status.name,
+            version: env!("CARGO_PKG_VERSION"),
+            platform: discovery::platform(),
+            api: discovery::API_VERSION,
+            ip: local_ip(),
+        })
+    }
+
+    async fn get_clock(State(state): State<ApiState>) -> Json<ClockResponse> {
+        Json(ClockResponse {
+            clock_us: clock_us(state.started_at, Instant::now()),
+        })
+    }
+
+    fn clock_us(started_at: Instant, at: Instant) -> u64 {
+        at.saturating_duration_since(started_at).as_micros() as u64
+    }
+
+    /// Histogramme des tempos sur la période glissante (tempo du set)
+    async fn get_histogram(State(state): State<ApiState>) -> Json<HistogramSnapshot> {
+        let status = state.status.lock().map(|s| s.clone()).unwrap_or_default();
+        Json(status.histogram)
+    }
+
+    /// Niveau RMS moyen par seconde sur les dernières minutes, daté sur l'horloge
+    /// de GET /clock pour la frise des appareils du bureau
+    async fn get_levels(State(state): State<ApiState>) -> Json<LevelSnapshot> {
+        let status = state.status.lock().map(|s| s.clone()).unwrap_or_default();
+        Json(LevelSnapshot {
+            end_us: status.levels_at.map(|end| clock_us(state.started_at, end)),
+            ..status.levels
+        })
+    }
+
+    /// Dump des internes de l'analyseur (historique, seuils, buffers, rejets)
+    /// pour diagnostiquer un tempo bloqué
+    async fn get_state(State(state): State<ApiState>) -> Json<Option<AnalyzerState>> {
+        let status = state.status.lock().map(|s| s.clone()).unwrap_or_default();
+        Json(status.state)
+    }
+
+    async fn post_analysis(
+        State(state): State<ApiState>,
+        Json(request): Json<AnalysisRequest>,
+    ) -> StatusCode {
+        send_command(&state, HttpCommand::SetAnalysis(request.enabled)).await
+    }
+
+    async fn post_octave(
+        State(state): State<ApiState>,
+        Json(request): Json<OctaveRequest>,
+    ) -> StatusCode {
+        send_command(&state, HttpCommand::SetOctave(request.preference)).await
+    }
+
+    /// Réglages actuels de l'AGC, à lire pendant le réglage en direct
+    async fn get_agc(State(state): State<ApiState>) -> Json<AgcConfig> {
+        let status = state.status.lock().map(|s| s.clone()).unwrap_or_default();
+        Json(status.agc)
+    }
+
+    async fn post_agc(
+        State(state): State<ApiState>,
+        Json(request): Json<AgcRequest>,
+    ) -> (StatusCode, String) {
+        let current = state.status.lock().map(|s| s.agc).unwrap_or_default();
+        let params = AgcConfig {
+            setpoint: request.setpoint.unwrap_or(current.setpoint),
+            kp: request.kp.unwrap_or(current.kp),
+            ki: request.ki.unwrap_or(current.ki),
+            kd: request.kd.unwrap_or(current.kd),
+            window: request.window.unwrap_or(current.window),
+        };
+        if let Err(e) = params.validate() {
+            return (StatusCode::BAD_REQUEST, e);
+        }
+        let code = send_command(&state, HttpCommand::SetAgcParams(params)).await;
+        (code, String::new())
+    }
+
+    /// Porte d'analyse pilotée par le réseau (section `[gate]`)
+    async fn post_gate(
+        State(state): State<ApiState>,
+        Json(request): Json<GateRequest>,
+    ) -> StatusCode {
+        send_command(&state, HttpCommand::SetGate(request.open)).await
+    }
+
+    async fn post_autogain(State(state): State<ApiState>) -> StatusCode {
+        send_command(&state, HttpCommand::AutoGain).await
+    }
+
+    /// Sauvegarde des dernières secondes d'entrée en WAV (section `[recording]`)
+    async fn post_recording(State(state): State<ApiState>) -> StatusCode {
+        send_command(&state, HttpCommand::SaveRecording).await
+    }
+
+    /// Derniers avertissements et erreurs, pour le diagnostic à distance
+    async fn get_logs() -> Json<Vec<LogEvent>> {
+        Json(logging::recent_events())
+    }
+
+    /// Compteurs pour Prometheus/Grafana (installations permanentes)
+    async fn get_metrics(
+        State(state): State<ApiState>,
+    ) -> Result<([(HeaderName, &'static str); 1], String), StatusCode> {
+        if !state.metrics {
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Ok((
+            [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+            METRICS.render(),
+        ))
+    }
+
+    /// Version installée, version disponible et avancement d'une mise à jour
+    async fn get_update(State(state): State<ApiState>) -> Json<UpdateResponse> {
+        let status = state.status.lock().map(|s| s.clone()).unwrap_or_default();
+        Json(UpdateResponse {
+            version: env!("CARGO_PKG_VERSION"),
+            status: status.update,
+        })
+    }
+
+    /// Recherche d'une nouvelle version, résultat dans GET /update
+    async fn post_update_check(State(state): State<ApiState>) -> StatusCode {
+        send_command(&state, HttpCommand::CheckUpdate).await
+    }
+
+    async fn post_update(State(state): State<ApiState>) -> StatusCode {
+        send_command(&state, HttpCommand::Update).await
+    }
+
+    /// Arrêt propre de l'appareil (évite de corrompre la carte SD)
+    async fn post_shutdown(State(state): State<ApiState>) -> StatusCode {
+        send_command(&state, HttpCommand::Shutdown).await
+    }
+
+    async fn send_command(state: &ApiState, command: HttpCommand) -> StatusCode {
+        METRICS.message_received();
+        match state.commands.send(command).await {
+            Ok(_) => StatusCode::ACCEPTED,
+            Err(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// Vérifie la signature des commandes POST avant leur traitement. La clé ne
+    /// circule pas: une commande capturée sur le réseau est périmée au-delà de
+    /// `api_auth::MAX_SKEW_MS` et ne peut pas être rejouée avant.
+    async fn verify_command(
+        State(state): State<ApiState>,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        let Some(verifier) = &state.verifier else {
+            return next.run(request).await;
+        };
+        if request.method() != Method::POST {
+            return next.run(request).await;
+        }
+        let (parts, body) = request.into_parts();
+        let Ok(body) = to_bytes(body, MAX_COMMAND_BODY).await else {
+            return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+        };
+        let header = |name: &str| parts.headers.get(name).and_then(|v| v.to_str().ok());
+        let checked = match verifier.lock() {
+            Ok(mut verifier) => verifier.verify(
+                header(api_auth::TIMESTAMP_HEADER),
+                header(api_auth::SIGNATURE_HEADER),
+                parts.method.as_str(),
+                parts.uri.path(),
+                &body,
+                api_auth::now_ms(),
+            ),
+            Err(_) => Err("verifier unavailable".to_string()),
+        };
+        if let Err(e) = checked {
+            warn!("Commande HTTP refusée ({}): POST {}", e, parts.uri.path());
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+        next.run(Request::from_parts(parts, Body::from(body))).await
+    }
+
+    /// IP de l'interface de sortie par défaut (aucun paquet n'est envoyé)
+    fn local_ip() -> Option<String> {
+        let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+        socket.connect("8.8.8.8:80").ok()?;
+        socket.local_addr().ok().map(|addr| addr.ip().to_string())
+    }
+}
@@ -1,8 +1,9 @@
 #[cfg(all(any(target_arch = "aarch64", target_arch = "arm"), target_os = "linux"))]
 pub mod led {
     use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+    use tokio::sync::watch;
     use tokio::task;
-    use tokio::time::{Duration, sleep};
+    use tokio::time::{Duration, Instant, sleep, sleep_until};
 
     use std::sync::Arc;
 
@@ -26,7 +27,6 @@ pub mod led {
             Ok(())
         }
 
-        #[allow(dead_code)]
         /// Éteint la LED
         pub fn off(&self) -> Result<(), Box<dyn std::error::Error>> {
             self.handle.set_value(0)?;
@@ -46,4 +46,99 @@ pub mod led {
             });
         }
     }
+
+    /// Tempo courant transmis à la tâche `BeatLed`
+    #[derive(Debug, Clone, Copy)]
+    pub struct BeatInfo {
+        pub bpm: f32,
+        /// Instant du dernier beat (latence déjà compensée)
+        pub last_beat: std::time::Instant,
+        /// Instant du dernier drop détecté
+        pub last_drop: Option<std::time::Instant>,
+    }
+
+    /// Fait flasher une LED sur chaque beat : impulsion longue sur le premier temps
+    /// de la mesure, triple flash pendant les 2 mesures qui suivent un drop.
+    pub struct BeatLed {
+        led: Led,
+        beats_per_bar: u32,
+        beat_pulse_ms: u64,
+        downbeat_pulse_ms: u64,
+    }
+
+    impl BeatLed {
+        pub fn new(led: Led) -> Self {
+            Self {
+                led,
+                beats_per_bar: 4,
+                beat_pulse_ms: 40,
+                downbeat_pulse_ms: 150,
+            }
+        }
+
+        /// Boucle de la tâche. La LED reste allumée (statut) tant qu'aucun tempo n'est connu.
+        pub async fn run(self, mut rx: watch::Receiver<Option<BeatInfo>>) {
+            let mut beat_index: u64 = 0;
+            let mut current_drop = None;
+
+            loop {
+                let info = *rx.borrow_and_update();
+                let Some(info) = info.filter(|i| i.bpm > 0.0) else {
+                    let _ = self.led.on();
+                    if rx.changed().await.is_err() {
+                        return;
+                    }
+                    continue;
+                };
+
+                // Le premier temps de la mesure est recalé sur le dernier drop
+                if info.last_drop != current_drop {
+                    current_drop = info.last_drop;
+                    beat_index = 0;
+                }
+
+                // Prochain beat sur la grille ancrée à `last_beat`
+                let period = Duration::from_secs_f32(60.0 / info.bpm);
+                let anchor = Instant::from_std(info.last_beat);
+                let now = Instant::now();
+                let elapsed = now.saturating_duration_since(anchor);
+                let beats = (elapsed.as_secs_f32() / period.as_secs_f32()).floor() as u32 + 1;
+                let next_beat = anchor + period * beats;
+
+                tokio::select! {
+                    _ = sleep_until(next_beat) => {
+                        let in_drop = info.last_drop.is_some_and(|t| {
+                            t.elapsed() < period * (2 * self.beats_per_bar)
+                        });
+                        let is_downbeat = beat_index.is_multiple_of(self.beats_per_bar as u64);
+                        beat_index += 1;
+
+                        if in_drop {
+                            self.pulse_pattern(3, 20).await;
+                        } else if is_downbeat {
+                            self.pulse_pattern(1, self.downbeat_pulse_ms).await;
+                        } else {
+                            self.pulse_pattern(1, self.beat_pulse_ms).await;
+                        }
+                    }
+                    changed = rx.changed() => {
+                        if changed.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+
+        async fn pulse_pattern(&self, count: u32, pulse_ms: u64) {
+            for _ in 0..count {
+                let _ = self.led.on();
+                sleep(Duration::from_millis(pulse_ms)).await;
+                let _ = self.led.off();
+                if count > 1 {
+                    sleep(Duration::from_millis(pulse_ms)).await;
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,156 @@
+#[cfg(all(any(target_arch = "aarch64", target_arch = "arm"), target_os = "linux"))]
+pub mod ledstrip {
+    use crate::core_embedded::led::led::BeatInfo;
+    use spidev::{SpiModeFlags, Spidev, SpidevOptions};
+    use std::io::Write;
+    use tokio::sync::watch;
+    use tokio::time::{Duration, Instant, interval};
+    use tracing::error;
+
+    // Chaque bit WS2812 est codé sur 3 bits SPI à 2.4 MHz : 1 -> 110, 0 -> 100
+    const SPI_SPEED_HZ: u32 = 2_400_000;
+    // Maintien bas > 50 µs pour latcher les couleurs
+    const RESET_BYTES: usize = 24;
+
+    /// Motifs disponibles pour le ruban
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum StripPattern {
+        /// Tout le ruban flashe sur le beat puis décroît
+        Pulse,
+        /// Un point fait le tour du ruban en une mesure
+        Chase,
+        /// Barre de niveau suivant l'énergie audio
+        VuMeter,
+    }
+
+    /// Driver WS2812/NeoPixel sur bus SPI (MOSI uniquement)
+    pub struct LedStrip {
+        spi: Spidev,
+        length: usize,
+        tx_buffer: Vec<u8>,
+    }
+
+    impl LedStrip {
+        /// Ouvre le bus SPI (ex: "/dev/spidev0.0") pour un ruban de `length` LEDs
+        pub fn new(spi_path: &str, length: usize) -> Result<Self, Box<dyn std::error::Error>> {
+            let mut spi = Spidev::open(spi_path)?;
+            let options = SpidevOptions::new()
+                .bits_per_word(8)
+                .max_speed_hz(SPI_SPEED_HZ)
+                .mode(SpiModeFlags::SPI_MODE_0)
+                .build();
+            spi.configure(&options)?;
+            Ok(Self {
+                spi,
+                length,
+                tx_buffer: Vec::with_capacity(length * 9 + RESET_BYTES),
+            })
+        }
+
+        /// Envoie les couleurs (R, G, B) au ruban
+        pub fn write(&mut self, colors: &[(u8, u8, u8)]) -> Result<(), Box<dyn std::error::Error>> {
+            self.tx_buffer.clear();
+            for &(r, g, b) in colors.iter().take(self.length) {
+                // Ordre GRB attendu par les WS2812
+                for byte in [g, r, b] {
+                    let mut bits: u32 = 0;
+                    for i in (0..8).rev() {
+                        let pattern = if byte & (1 << i) != 0 { 0b110 } else { 0b100 };
+                        bits = (bits << 3) | pattern;
+                    }
+                    self.tx_buffer.extend_from_slice(&bits.to_be_bytes()[1..]);
+                }
+            }
+            self.tx_buffer.extend(std::iter::repeat_n(0u8, RESET_BYTES));
+            self.spi.write_all(&self.tx_buffer)?;
+            Ok(())
+        }
+
+        /// Boucle de rendu (~50 fps) pilotée par la phase du beat et l'énergie audio.
+        /// `energy` suit la même échelle que la barre audio de l'OLED (0.0 - 0.6).
+        pub async fn run(
+            mut self,
+            pattern: StripPattern,
+            color: (u8, u8, u8),
+            beat_rx: watch::Receiver<Option<BeatInfo>>,
+            energy_rx: watch::Receiver<f32>,
+        ) {
+            let mut frame_timer = interval(Duration::from_millis(20));
+            let mut colors = vec![(0u8, 0u8, 0u8); self.length];
+
+            loop {
+                frame_timer.tick().await;
+                let beat = *beat_rx.borrow();
+                let energy = *energy_rx.borrow();
+
+                // Position dans la mesure (en beats), à partir du dernier beat connu
+                let beat_pos = beat.filter(|b| b.bpm > 0.0).map(|b| {
+                    let period = 60.0 / b.bpm;
+                    let elapsed = Instant::now()
+                        .saturating_duration_since(Instant::from_std(b.last_beat))
+                        .as_secs_f32();
+                    (elapsed / period) % 4.0
+                });
+
+                self.render(pattern, color, beat_pos, energy, &mut colors);
+                if let Err(e) = self.write(&colors) {
+                    error!("Erreur écriture ruban LED: {}", e);
+                    return;
+                }
+            }
+        }
+
+        fn render(
+            &self,
+            pattern: StripPattern,
+            color: (u8, u8, u8),
+            beat_pos: Option<f32>,
+            energy: f32,
+            colors: &mut [(u8, u8, u8)],
+        ) {
+            let scale = |c: (u8, u8, u8), k: f32| {
+                let k = k.clamp(0.0, 1.0);
+                (
+                    (c.0 as f32 * k) as u8,
+                    (c.1 as f32 * k) as u8,
+                    (c.2 as f32 * k) as u8,
+                )
+            };
+            let len = colors.len();
+            if len == 0 {
+                return;
+            }
+
+            match pattern {
+                StripPattern::Pulse => {
+                    let phase = beat_pos.map(|p| p.fract()).unwrap_or(1.0);
+                    let level = (1.0 - phase) * (1.0 - phase);
+                    colors.fill(scale(color, level));
+                }
+                StripPattern::Chase => {
+                    colors.fill((0, 0, 0));
+                    if let Some(pos) = beat_pos {
+                        // Un tour par mesure, avec une traînée de 4 LEDs
+                        let head = (pos / 4.0 * len as f32) as usize;
+                        for tail in 0..4 {
+                            let idx = (head + 4 * len - tail) % len;
+                            colors[idx] = scale(color, 1.0 - tail as f32 * 0.25);
+                        }
+                    }
+                }
+                StripPattern::VuMeter => {
+                    let lit = ((energy / 0.6).clamp(0.0, 1.0) * len as f32).round() as usize;
+                    for (i, c) in colors.iter_mut().enumerate() {
+                        *c = if i < lit {
+                            // Vert -> rouge le long du ruban
+                            let k = i as f32 / len.max(1) as f32;
+                            ((255.0 * k) as u8, (255.0 * (1.0 - k)) as u8, 0)
+                        } else {
+                            (0, 0, 0)
+                        };
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,14 @@
 pub mod button;
 pub mod display;
+pub mod gate;
+pub mod http;
 pub mod led;
+pub mod ledstrip;
 pub mod network;
+pub mod pages;
+pub mod session;
+pub mod tft;
+pub mod trigger;
 pub mod update;
 pub mod usb;
+pub mod watchdog;
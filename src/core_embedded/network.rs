@@ -1,33 +1,35 @@
 #[cfg(all(any(target_arch = "aarch64", target_arch = "arm"), target_os = "linux"))]
 pub mod network {
-    use crate::core_embedded::display::display::{BpmDisplay, StatusBarIcon};
+    use crate::core_embedded::display::display::{SharedDisplay, StatusBarIcon};
     use crate::core_embedded::update::update::Updater;
     use futures::StreamExt;
     use netlink_packet_core::NetlinkPayload;
     use netlink_packet_route::RouteNetlinkMessage;
+    use netlink_packet_route::address::{AddressAttribute, AddressMessage};
     use netlink_packet_route::link::LinkAttribute;
     use rtnetlink::new_connection;
     use rtnetlink::sys::AsyncSocket;
     use std::collections::HashMap;
+    use std::net::{IpAddr, Ipv4Addr};
     use std::sync::atomic::{AtomicBool, Ordering};
-    use std::sync::{Arc, Mutex};
     use tokio::process::Command;
     use tokio::time::Duration;
+    use tracing::{error, info};
 
     // Flag statique pour empêcher l'exécution simultanée multiple
     static IS_CHECKING_UPDATE: AtomicBool = AtomicBool::new(false);
 
-    async fn check_internet_and_update(display: Option<Arc<Mutex<BpmDisplay>>>, updater: Updater) {
+    async fn check_internet_and_update(display: Option<SharedDisplay>, updater: Updater) {
         // Si une vérification est déjà en cours, on annule celle-ci
         if IS_CHECKING_UPDATE
             .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
             .is_err()
         {
-            println!("Une vérification Internet/Update est déjà en cours. Ignoré.");
+            info!("Une vérification Internet/Update est déjà en cours. Ignoré.");
             return;
         }
 
-        println!("Vérification accès Internet (tentatives pdt 10s)...");
+        info!("Vérification accès Internet (tentatives pdt 10s)...");
         let start = std::time::Instant::now();
         // On augmente à 10s comme demandé
         let max_duration = Duration::from_secs(10);
@@ -51,7 +53,7 @@ pub mod network {
         }
 
         if success {
-            println!("Ping Internet: SUCCÈS");
+            info!("Ping Internet: SUCCÈS");
             if let Some(disp_arc) = &display {
                 if let Ok(mut disp) = disp_arc.lock() {
                     let _ = disp.draw_status_icon(StatusBarIcon::Internet);
@@ -59,29 +61,45 @@ pub mod network {
                 }
             }
 
-            println!("Vérification des mises à jour...");
-            match updater.check() {
-                Ok(Some(new_version)) => {
-                    println!("Mise à jour disponible : {}", new_version);
-                    if let Some(disp_arc) = &display {
-                        if let Ok(mut disp) = disp_arc.lock() {
-                            let _ = disp.draw_status_icon(StatusBarIcon::Update);
-                            let _ = disp.flush();
-                        }
-                    }
-                }
-                Ok(None) => println!("Pas de mise à jour."),
-                Err(e) => eprintln!("Erreur check update: {}", e),
-            }
+            check_update(display, updater).await;
         } else {
-            println!("Ping Internet: ÉCHEC (Timeout 10s)");
+            info!("Ping Internet: ÉCHEC (Timeout 10s)");
         }
 
         // On libère le flag à la fin
         IS_CHECKING_UPDATE.store(false, Ordering::SeqCst);
     }
 
-    fn update_link_status(display: &Option<Arc<Mutex<BpmDisplay>>>, name: &str, is_up: bool) {
+    /// Cherche une nouvelle version (aussi sur POST /update/check) et affiche
+    /// l'icône de mise à jour si elle existe
+    pub async fn check_update(display: Option<SharedDisplay>, updater: Updater) {
+        if updater.status().busy() {
+            info!("Vérification ou mise à jour déjà en cours. Ignoré.");
+            return;
+        }
+        info!("Vérification des mises à jour...");
+        // Requêtes HTTP bloquantes hors des threads de tokio
+        let found =
+            tokio::task::spawn_blocking(move || updater.check().map_err(|e| e.to_string())).await;
+        match found {
+            Ok(Ok(Some(new_version))) => {
+                info!("Mise à jour disponible : {}", new_version);
+                if let Some(disp_arc) = &display {
+                    if let Ok(mut disp) = disp_arc.lock() {
+                        // GitHub a répondu: Internet est joignable
+                        let _ = disp.draw_status_icon(StatusBarIcon::Internet);
+                        let _ = disp.draw_status_icon(StatusBarIcon::Update);
+                        let _ = disp.flush();
+                    }
+                }
+            }
+            Ok(Ok(None)) => info!("Pas de mise à jour."),
+            Ok(Err(e)) => error!("Erreur check update: {}", e),
+            Err(e) => error!("Erreur check update: {}", e),
+        }
+    }
+
+    fn update_link_status(display: &Option<SharedDisplay>, name: &str, is_up: bool) {
         if name != "eth0" && name != "usb0" {
             // On ne gère que eth0 et usb0
             return;
@@ -130,9 +148,53 @@ pub mod network {
         (name, is_up && is_lower_up)
     }
 
-    /// Écoute les changements d'état des interfaces réseau et affiche UP/DOWN
+    /// Adresse IPv4 portée par un message d'adresse, avec le nom de l'interface
+    fn extract_address_info(
+        addr_msg: &AddressMessage,
+        iface_map: &HashMap<u32, String>,
+    ) -> (Option<String>, Option<Ipv4Addr>) {
+        let name = addr_msg
+            .attributes
+            .iter()
+            .find_map(|attr| match attr {
+                AddressAttribute::Label(label) => Some(label.clone()),
+                _ => None,
+            })
+            .or_else(|| iface_map.get(&addr_msg.header.index).cloned());
+
+        // Local = adresse de l'interface (Address est celle du pair en point-à-point)
+        let local = addr_msg.attributes.iter().find_map(|attr| match attr {
+            AddressAttribute::Local(IpAddr::V4(ip)) => Some(*ip),
+            _ => None,
+        });
+        let address = local.or_else(|| {
+            addr_msg.attributes.iter().find_map(|attr| match attr {
+                AddressAttribute::Address(IpAddr::V4(ip)) => Some(*ip),
+                _ => None,
+            })
+        });
+        (name, address)
+    }
+
+    /// Affiche (ou retire) l'adresse IPv4 de eth0/usb0 sur la page réseau
+    fn update_address(display: &Option<SharedDisplay>, name: &str, address: Option<Ipv4Addr>) {
+        if name != "eth0" && name != "usb0" {
+            return;
+        }
+        if let Some(disp_arc) = display {
+            if let Ok(mut disp) = disp_arc.lock() {
+                let _ = disp.set_interface_address(name, address);
+            }
+        }
+    }
+
+    /// Écoute les changements d'état des interfaces réseau et affiche UP/DOWN.
+    /// Les mises à jour sont vérifiées à chaque connexion Ethernet, et toutes les
+    /// `check_interval` si elle est donnée.
     pub async fn listen_interface_events(
-        display: Option<Arc<Mutex<BpmDisplay>>>,
+        display: Option<SharedDisplay>,
+        updater: Updater,
+        check_interval: Option<Duration>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let (mut connection, handle, mut messages) = new_connection()?;
 
@@ -143,14 +205,32 @@ pub mod network {
             .socket_mut()
             .add_membership(1)
             .map_err(|e| format!("Add membership error: {}", e))?;
+        // Et RTNLGRP_IPV4_IFADDR (5) pour suivre les adresses (DHCP, IP fixe)
+        connection
+            .socket_mut()
+            .socket_mut()
+            .add_membership(5)
+            .map_err(|e| format!("Add membership error: {}", e))?;
 
         tokio::spawn(connection);
 
-        let updater = Updater::new("kiki442002", "rust-bpm-analyzer", "rust-bpm-analyzer");
+        // Vérification périodique (le premier tick est immédiat, déjà couvert par le scan)
+        if let Some(period) = check_interval {
+            let display = display.clone();
+            let updater = updater.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    check_internet_and_update(display.clone(), updater.clone()).await;
+                }
+            });
+        }
 
         let mut iface_map: HashMap<u32, String> = HashMap::new();
         // 1. Scan initial des interfaces existantes
-        println!("Scan initial des interfaces réseau...");
+        info!("Scan initial des interfaces réseau...");
         let mut links = handle.link().get().execute();
         while let Some(msg_result) = links.next().await {
             match msg_result {
@@ -158,7 +238,7 @@ pub mod network {
                     let (name_opt, is_up) = extract_link_info(&link_msg);
                     if let Some(name) = name_opt {
                         iface_map.insert(link_msg.header.index, name.clone());
-                        println!(
+                        info!(
                             "Initial: Interface {} is {}",
                             name,
                             if is_up { "UP" } else { "DOWN" }
@@ -172,12 +252,27 @@ pub mod network {
                         update_link_status(&display, &name, is_up);
                     }
                 }
-                Err(e) => eprintln!("Erreur lors du scan initial: {}", e),
+                Err(e) => error!("Erreur lors du scan initial: {}", e),
+            }
+        }
+
+        // Adresses IPv4 déjà attribuées
+        let mut addresses = handle.address().get().execute();
+        while let Some(msg_result) = addresses.next().await {
+            match msg_result {
+                Ok(addr_msg) => {
+                    if let (Some(name), Some(address)) = extract_address_info(&addr_msg, &iface_map)
+                    {
+                        info!("Initial: Interface {} a l'adresse {}", name, address);
+                        update_address(&display, &name, Some(address));
+                    }
+                }
+                Err(e) => error!("Erreur lors du scan des adresses: {}", e),
             }
         }
 
         // 2. Boucle d'événements (changements dynamiques)
-        println!("En attente d'événements Netlink...");
+        info!("En attente d'événements Netlink...");
         while let Some((message, _)) = messages.next().await {
             // Dans les versions récentes avec netlink-packet-route, le payload est du type RouteNetlinkMessage
             // encapsulé dans NetlinkPayload::InnerMessage
@@ -194,7 +289,7 @@ pub mod network {
                     };
 
                     if let Some(name) = name_final {
-                        println!(
+                        info!(
                             "Event: Interface {} is {}",
                             name,
                             if is_up { "UP" } else { "DOWN" }
@@ -207,7 +302,21 @@ pub mod network {
                         }
                         update_link_status(&display, &name, is_up);
                     } else {
-                        // println!("DEBUG: Interface index {} changed but name unknown", link_msg.header.index);
+                        // info!("DEBUG: Interface index {} changed but name unknown", link_msg.header.index);
+                    }
+                }
+                NetlinkPayload::InnerMessage(RouteNetlinkMessage::NewAddress(addr_msg)) => {
+                    if let (Some(name), Some(address)) = extract_address_info(&addr_msg, &iface_map)
+                    {
+                        info!("Event: Interface {} a l'adresse {}", name, address);
+                        update_address(&display, &name, Some(address));
+                    }
+                }
+                NetlinkPayload::InnerMessage(RouteNetlinkMessage::DelAddress(addr_msg)) => {
+                    if let (Some(name), Some(address)) = extract_address_info(&addr_msg, &iface_map)
+                    {
+                        info!("Event: Interface {} perd l'adresse {}", name, address);
+                        update_address(&display, &name, None);
                     }
                 }
                 _ => {}
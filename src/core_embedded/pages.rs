@@ -0,0 +1,362 @@
+#[cfg(all(target_arch = "aarch64", target_os = "linux"))]
+pub mod pages {
+    use embedded_graphics::image::Image;
+    use embedded_graphics::mono_font::{
+        MonoFont, MonoTextStyle,
+        ascii::{FONT_6X10, FONT_10X20},
+    };
+    use embedded_graphics::pixelcolor::BinaryColor;
+    use embedded_graphics::prelude::*;
+    use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+    use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+    use std::net::Ipv4Addr;
+    use tinybmp::Bmp;
+
+    // Hauteur des écrans 128x64, les 128x32 n'ont pas la rangée d'icônes
+    const TALL_PANEL: u32 = 64;
+    const STATUS_LINE_HEIGHT: u32 = 8;
+    const STATUS_ICONS_HEIGHT: u32 = 16;
+
+    // Niveau RMS affiché en pleine échelle par les barres de niveau
+    const LEVEL_FULL_SCALE: f32 = 0.6;
+    // Hauteur d'une ligne de texte en FONT_6X10
+    const LINE_HEIGHT: i32 = 10;
+    // Hauteur des barres de niveau sur un écran 128x64
+    const LEVEL_BAR_HEIGHT: u32 = 10;
+    // Caractères FONT_6X10 sur la largeur de l'écran
+    const LINE_CHARS: usize = 21;
+
+    /// Découpage de l'écran: ligne d'état Link, icônes, puis la page courante
+    #[derive(Clone, Copy, Debug)]
+    pub struct Layout {
+        pub status_line: Rectangle,
+        /// Absente sur les écrans 128x32
+        pub status_icons: Option<Rectangle>,
+        pub content: Rectangle,
+    }
+
+    impl Layout {
+        pub fn new(size: Size) -> Self {
+            let status_line =
+                Rectangle::new(Point::zero(), Size::new(size.width, STATUS_LINE_HEIGHT));
+            let mut top = STATUS_LINE_HEIGHT;
+            let status_icons = (size.height >= TALL_PANEL).then(|| {
+                let area = Rectangle::new(
+                    Point::new(0, top as i32),
+                    Size::new(size.width, STATUS_ICONS_HEIGHT),
+                );
+                top += STATUS_ICONS_HEIGHT;
+                area
+            });
+            Self {
+                status_line,
+                status_icons,
+                content: Rectangle::new(
+                    Point::new(0, top as i32),
+                    Size::new(size.width, size.height.saturating_sub(top)),
+                ),
+            }
+        }
+    }
+
+    /// Pages affichées sous la barre de statut
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum PageId {
+        Bpm,
+        Network,
+        Audio,
+        /// Uniquement pendant une mise à jour, hors du cycle du bouton
+        Update,
+    }
+
+    /// Une page se dessine dans la zone qu'on lui donne, sans coordonnées absolues
+    pub trait Page {
+        fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+        where
+            D: DrawTarget<Color = BinaryColor>;
+    }
+
+    // Widgets communs aux pages
+
+    /// Efface une zone de l'écran
+    pub fn clear<D>(target: &mut D, area: Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        area.into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+            .draw(target)
+    }
+
+    /// Texte dont `position` est le coin haut (gauche, centre ou droit selon `alignment`)
+    pub fn text<D>(
+        target: &mut D,
+        position: Point,
+        content: &str,
+        font: &MonoFont<'_>,
+        alignment: Alignment,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let style = MonoTextStyle::new(font, BinaryColor::On);
+        let layout = TextStyleBuilder::new()
+            .alignment(alignment)
+            .baseline(Baseline::Top)
+            .build();
+        Text::with_text_style(content, position, style, layout).draw(target)?;
+        Ok(())
+    }
+
+    /// `index`-ième ligne de texte (petite police) en partant du haut de `area`
+    fn line<D>(target: &mut D, area: Rectangle, index: i32, content: &str) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        // Les dernières lignes sont omises sur les petits écrans
+        if (index + 1) * LINE_HEIGHT > area.size.height as i32 {
+            return Ok(());
+        }
+        let position = area.top_left + Point::new(2, index * LINE_HEIGHT);
+        text(target, position, content, &FONT_6X10, Alignment::Left)
+    }
+
+    /// Barre de niveau encadrée occupant toute `area`
+    pub fn level_bar<D>(target: &mut D, area: Rectangle, level: f32) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        clear(target, area)?;
+        area.into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(target)?;
+        let inner = area.size.width.saturating_sub(2);
+        let fraction = level.clamp(0.0, LEVEL_FULL_SCALE) / LEVEL_FULL_SCALE;
+        let width = (fraction * inner as f32).round() as u32;
+        Rectangle::new(
+            area.top_left + Point::new(1, 1),
+            Size::new(width, area.size.height.saturating_sub(2)),
+        )
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(target)
+    }
+
+    /// Barre de niveau en bas de `area`, plus fine sur les écrans 128x32
+    fn level_area(area: Rectangle) -> Rectangle {
+        let height = if area.size.height >= 2 * LEVEL_BAR_HEIGHT + 20 {
+            LEVEL_BAR_HEIGHT
+        } else {
+            LEVEL_BAR_HEIGHT / 2
+        };
+        bottom(area, height)
+    }
+
+    /// Bas de `area` sur `height` pixels
+    fn bottom(area: Rectangle, height: u32) -> Rectangle {
+        let height = height.min(area.size.height);
+        Rectangle::new(
+            area.top_left + Point::new(0, (area.size.height - height) as i32),
+            Size::new(area.size.width, height),
+        )
+    }
+
+    /// Tempo en grand et niveau audio
+    #[derive(Clone, Debug, Default)]
+    pub struct BpmPage {
+        pub bpm: Option<f32>,
+        pub level: f32,
+    }
+
+    impl BpmPage {
+        pub fn draw_bpm<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+        where
+            D: DrawTarget<Color = BinaryColor>,
+        {
+            let level = level_area(area);
+            let zone = Rectangle::new(
+                area.top_left,
+                Size::new(
+                    area.size.width,
+                    area.size.height.saturating_sub(level.size.height),
+                ),
+            );
+            clear(target, zone)?;
+            let content = match self.bpm {
+                Some(bpm) => format!("{:.2}", bpm),
+                None => "***.**".to_string(),
+            };
+            // FONT_10X20: 20 pixels de haut, marge réduite si la zone est basse
+            let margin = zone.size.height.saturating_sub(20).min(4) as i32;
+            let position = Point::new(zone.center().x, zone.top_left.y + margin);
+            text(target, position, &content, &FONT_10X20, Alignment::Center)
+        }
+
+        pub fn draw_level<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+        where
+            D: DrawTarget<Color = BinaryColor>,
+        {
+            level_bar(target, level_area(area), self.level)
+        }
+    }
+
+    impl Page for BpmPage {
+        fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+        where
+            D: DrawTarget<Color = BinaryColor>,
+        {
+            self.draw_bpm(target, area)?;
+            self.draw_level(target, area)
+        }
+    }
+
+    /// Nom d'hôte et adresses IPv4, pour joindre l'appareil en SSH/HTTP sans
+    /// console série
+    #[derive(Clone, Debug, Default)]
+    pub struct NetworkPage {
+        pub hostname: String,
+        pub eth0: Option<Ipv4Addr>,
+        pub usb0: Option<Ipv4Addr>,
+    }
+
+    impl Page for NetworkPage {
+        fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+        where
+            D: DrawTarget<Color = BinaryColor>,
+        {
+            let hostname: String = self.hostname.chars().take(LINE_CHARS).collect();
+            line(target, area, 0, &hostname)?;
+            for (index, (name, address)) in [("eth0", self.eth0), ("usb0", self.usb0)]
+                .into_iter()
+                .enumerate()
+            {
+                let address = match address {
+                    Some(address) => address.to_string(),
+                    None => "-".to_string(),
+                };
+                line(
+                    target,
+                    area,
+                    index as i32 + 1,
+                    &format!("{} {}", name, address),
+                )?;
+            }
+            line(
+                target,
+                area,
+                3,
+                &format!("Version {}", env!("CARGO_PKG_VERSION")),
+            )
+        }
+    }
+
+    /// Niveau de capture en dBFS et gain ALSA appliqué par le PID
+    #[derive(Clone, Debug, Default)]
+    pub struct AudioPage {
+        pub level: f32,
+        pub gain: Option<i64>,
+    }
+
+    impl Page for AudioPage {
+        fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+        where
+            D: DrawTarget<Color = BinaryColor>,
+        {
+            let db = 20.0 * self.level.max(1e-5).log10();
+            line(target, area, 0, &format!("Level: {:.1} dBFS", db))?;
+            let gain = match self.gain {
+                Some(gain) => format!("Gain: {}", gain),
+                None => "Gain: -".to_string(),
+            };
+            line(target, area, 1, &gain)?;
+            level_bar(target, level_area(area), self.level)
+        }
+    }
+
+    /// Animation pendant la mise à jour (deux images alternées)
+    pub struct UpdatePage {
+        pub frames: [Bmp<'static, BinaryColor>; 2],
+        pub frame: usize,
+    }
+
+    impl Page for UpdatePage {
+        fn draw<D>(&self, target: &mut D, area: Rectangle) -> Result<(), D::Error>
+        where
+            D: DrawTarget<Color = BinaryColor>,
+        {
+            let icon = &self.frames[self.frame % 2];
+            let size = icon.bounding_box().size;
+            let mut icon_top = area.top_left.y + 2;
+            // Texte au-dessus de l'icône quand la place le permet
+            if area.size.height as i32 >= 2 + LINE_HEIGHT + 4 + size.height as i32 {
+                let top = Point::new(area.center().x, area.top_left.y + 2);
+                text(target, top, "Updating...", &FONT_6X10, Alignment::Center)?;
+                icon_top += LINE_HEIGHT + 4;
+            }
+            let position = Point::new(area.center().x - size.width as i32 / 2, icon_top);
+            Image::new(icon, position).draw(target)
+        }
+    }
+
+    /// Pages de l'écran et page affichée
+    pub struct Pages {
+        pub bpm: BpmPage,
+        pub network: NetworkPage,
+        pub audio: AudioPage,
+        pub update: UpdatePage,
+        current: PageId,
+        layout: Layout,
+    }
+
+    impl Pages {
+        pub fn new(update_frames: [Bmp<'static, BinaryColor>; 2], layout: Layout) -> Self {
+            Self {
+                bpm: BpmPage::default(),
+                network: NetworkPage::default(),
+                audio: AudioPage::default(),
+                update: UpdatePage {
+                    frames: update_frames,
+                    frame: 0,
+                },
+                current: PageId::Bpm,
+                layout,
+            }
+        }
+
+        pub fn layout(&self) -> &Layout {
+            &self.layout
+        }
+
+        pub fn current(&self) -> PageId {
+            self.current
+        }
+
+        /// Page suivante du cycle BPM -> Réseau -> Audio.
+        /// La page de mise à jour reste affichée jusqu'à la fin de celle-ci.
+        pub fn next(&mut self) -> PageId {
+            self.current = match self.current {
+                PageId::Bpm => PageId::Network,
+                PageId::Network => PageId::Audio,
+                PageId::Audio => PageId::Bpm,
+                PageId::Update => PageId::Update,
+            };
+            self.current
+        }
+
+        pub fn show(&mut self, page: PageId) {
+            self.current = page;
+        }
+
+        /// Redessine toute la zone de contenu avec la page courante
+        pub fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+        where
+            D: DrawTarget<Color = BinaryColor>,
+        {
+            let content = self.layout.content;
+            clear(target, content)?;
+            match self.current {
+                PageId::Bpm => self.bpm.draw(target, content),
+                PageId::Network => self.network.draw(target, content),
+                PageId::Audio => self.audio.draw(target, content),
+                PageId::Update => self.update.draw(target, content),
+            }
+        }
+    }
+}
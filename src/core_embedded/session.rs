@@ -0,0 +1,154 @@
+#[cfg(all(any(target_arch = "aarch64", target_arch = "arm"), target_os = "linux"))]
+pub mod session {
+    use rust_bpm_analyzer::config::SessionConfig;
+    use rust_bpm_analyzer::core_bpm::analyzer::OctavePreference;
+    use serde::{Deserialize, Serialize};
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+    use tracing::{info, warn};
+
+    // Écarts en dessous desquels l'état n'est pas réécrit (le PID bouge sans cesse)
+    const BPM_TOLERANCE: f32 = 0.1;
+    const GAIN_TOLERANCE: i64 = 2;
+
+    /// État repris au démarrage suivant
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct SavedSession {
+        /// Tempo de référence verrouillé par l'analyseur
+        pub reference_bpm: Option<f32>,
+        pub octave: OctavePreference,
+        /// Volume de capture ALSA atteint par le PID
+        pub gain: Option<i64>,
+    }
+
+    impl SavedSession {
+        fn differs(&self, other: &SavedSession) -> bool {
+            let bpm_moved = match (self.reference_bpm, other.reference_bpm) {
+                (Some(a), Some(b)) => (a - b).abs() > BPM_TOLERANCE,
+                (a, b) => a.is_some() != b.is_some(),
+            };
+            let gain_moved = match (self.gain, other.gain) {
+                (Some(a), Some(b)) => (a - b).abs() > GAIN_TOLERANCE,
+                (a, b) => a.is_some() != b.is_some(),
+            };
+            bpm_moved || gain_moved || self.octave != other.octave
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct SessionFile {
+        /// Secondes depuis l'epoch Unix
+        saved_at: u64,
+        reference_bpm: Option<f32>,
+        octave: OctavePreference,
+        gain: Option<i64>,
+    }
+
+    /// Sauvegarde de la session (`[session]`): une coupure de courant en plein set
+    /// reprend sur le tempo verrouillé au lieu d'attendre le prochain drop.
+    pub struct SessionStore {
+        path: PathBuf,
+        save_interval: Duration,
+        max_age: Duration,
+        // Dernier état écrit et dernier état connu
+        saved: Option<SavedSession>,
+        pending: Option<SavedSession>,
+        last_save: Option<Instant>,
+    }
+
+    impl SessionStore {
+        pub fn new(config: &SessionConfig) -> Self {
+            Self {
+                path: config.path.clone(),
+                save_interval: Duration::from_secs(config.save_interval_s),
+                max_age: Duration::from_secs(config.max_age_s),
+                saved: None,
+                pending: None,
+                last_save: None,
+            }
+        }
+
+        /// Session enregistrée, `None` si absente, illisible ou trop ancienne
+        pub fn load(&mut self) -> Option<SavedSession> {
+            let content = std::fs::read_to_string(&self.path).ok()?;
+            let file: SessionFile = match toml::from_str(&content) {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!("Session {:?} illisible: {}", self.path, e);
+                    return None;
+                }
+            };
+            // Sans RTC l'horloge peut repartir en arrière au démarrage: la session
+            // est alors considérée comme récente
+            let age = now_s().saturating_sub(file.saved_at);
+            if age > self.max_age.as_secs() {
+                info!("Session sauvegardée il y a {} s ignorée (autre set)", age);
+                return None;
+            }
+            let session = SavedSession {
+                reference_bpm: file.reference_bpm,
+                octave: file.octave,
+                gain: file.gain,
+            };
+            self.saved = Some(session);
+            Some(session)
+        }
+
+        /// Enregistre `session` si elle a changé, au plus une fois par `save_interval`
+        pub fn update(&mut self, session: SavedSession) {
+            self.pending = Some(session);
+            if self
+                .last_save
+                .is_none_or(|at| at.elapsed() >= self.save_interval)
+            {
+                self.flush();
+            }
+        }
+
+        /// Écrit tout de suite le dernier état connu s'il a changé (arrêt propre).
+        /// Un état inchangé est réécrit toutes les `max_age / 2` pour dater la
+        /// session: un set stable ne doit pas vieillir au-delà de `max_age`.
+        pub fn flush(&mut self) {
+            let stale = self
+                .last_save
+                .is_none_or(|at| at.elapsed() >= self.max_age / 2);
+            let Some(session) = self
+                .pending
+                .filter(|pending| stale || self.saved.is_none_or(|saved| saved.differs(pending)))
+            else {
+                return;
+            };
+            self.last_save = Some(Instant::now());
+            match write(&self.path, session) {
+                Ok(()) => self.saved = Some(session),
+                Err(e) => warn!("Échec de la sauvegarde de session {:?}: {}", self.path, e),
+            }
+        }
+    }
+
+    // Fichier temporaire synchronisé puis renommé: une coupure pendant l'écriture
+    // laisse l'ancienne session intacte
+    fn write(path: &Path, session: SavedSession) -> Result<(), Box<dyn std::error::Error>> {
+        let content = toml::to_string(&SessionFile {
+            saved_at: now_s(),
+            reference_bpm: session.reference_bpm,
+            octave: session.octave,
+            gain: session.gain,
+        })?;
+        let tmp = path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    fn now_s() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,707 @@
+#[cfg(all(target_arch = "aarch64", target_os = "linux"))]
+pub mod tft {
+    use crate::core_embedded::display::display::{AppState, Display, StatusBarIcon, link_text};
+    use crate::core_embedded::pages::pages::PageId;
+    use embedded_graphics::mono_font::{
+        MonoFont, MonoTextStyle,
+        ascii::{FONT_6X10, FONT_10X20},
+    };
+    use embedded_graphics::pixelcolor::{Rgb565, raw::RawU16};
+    use embedded_graphics::prelude::*;
+    use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+    use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+    use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+    use rust_bpm_analyzer::config::{DisplayConfig, DisplayKind};
+    use spidev::{SpiModeFlags, Spidev, SpidevOptions};
+    use std::convert::Infallible;
+    use std::io::Write;
+    use std::net::Ipv4Addr;
+    use std::thread::sleep;
+    use std::time::Duration;
+    use tracing::info;
+
+    // SPI à 32 MHz: un écran 240x240 complet (115 ko) en ~30 ms
+    const SPI_SPEED_HZ: u32 = 32_000_000;
+    // Taille maximale d'un transfert spidev (bufsiz par défaut du noyau)
+    const SPI_CHUNK: usize = 4096;
+
+    // Commandes MIPI DCS communes aux ST7789 et ILI9341
+    const SWRESET: u8 = 0x01;
+    const SLPOUT: u8 = 0x11;
+    const NORON: u8 = 0x13;
+    const INVON: u8 = 0x21;
+    const DISPON: u8 = 0x29;
+    const CASET: u8 = 0x2A;
+    const RASET: u8 = 0x2B;
+    const RAMWR: u8 = 0x2C;
+    const MADCTL: u8 = 0x36;
+    const COLMOD: u8 = 0x3A;
+    // 16 bits par pixel (RGB565)
+    const COLMOD_RGB565: u8 = 0x55;
+
+    /// Contrôleur: taille de la dalle en portrait et orientation de chaque rotation
+    struct Controller {
+        size: (u32, u32),
+        /// MADCTL pour 0, 90, 180 et 270 degrés
+        madctl: [u8; 4],
+        /// Décalage de la dalle dans la RAM du contrôleur pour chaque rotation
+        offsets: [(u16, u16); 4],
+        /// Les dalles IPS des ST7789 affichent les couleurs inversées
+        invert: bool,
+    }
+
+    const ST7789: Controller = Controller {
+        // Dalle 240x240 sur un contrôleur 240x320: 80 lignes hors écran
+        size: (240, 240),
+        madctl: [0x00, 0x60, 0xC0, 0xA0],
+        offsets: [(0, 0), (0, 0), (0, 80), (80, 0)],
+        invert: true,
+    };
+
+    const ILI9341: Controller = Controller {
+        size: (240, 320),
+        // Ordre BGR sur ces dalles
+        madctl: [0x48, 0x28, 0x88, 0xE8],
+        offsets: [(0, 0); 4],
+        invert: false,
+    };
+
+    // Couleurs de l'écran
+    const BACKGROUND: Rgb565 = Rgb565::BLACK;
+    const FOREGROUND: Rgb565 = Rgb565::WHITE;
+    const DIM: Rgb565 = Rgb565::new(10, 20, 10);
+    // Orange du ruban LED
+    const ACCENT: Rgb565 = Rgb565::new(31, 10, 0);
+    const ACTIVE: Rgb565 = Rgb565::new(0, 50, 8);
+    const BAR_OFF: Rgb565 = Rgb565::new(3, 6, 3);
+
+    // Niveau RMS affiché en pleine échelle, comme la barre de l'OLED
+    const LEVEL_FULL_SCALE: f32 = 0.6;
+    // Hauteur des lignes en FONT_10X20
+    const LINE_HEIGHT: u32 = 20;
+    const MARGIN: u32 = 8;
+    // Épaisseur du cadre qui flashe sur le beat
+    const BEAT_FRAME: u32 = 3;
+
+    /// Pilote SPI minimal: image complète en RAM (RGB565), seule la zone modifiée
+    /// depuis le dernier `flush` est envoyée
+    pub struct Tft {
+        spi: Spidev,
+        dc: LineHandle,
+        // Gardées pour que les lignes restent réservées
+        _reset: Option<LineHandle>,
+        _backlight: Option<LineHandle>,
+        size: Size,
+        offset: (u16, u16),
+        frame: Vec<u16>,
+        // Zone modifiée (x min, y min, x max, y max), bornes incluses
+        dirty: Option<(u32, u32, u32, u32)>,
+        tx: Vec<u8>,
+    }
+
+    impl Tft {
+        pub fn new(config: &DisplayConfig) -> Result<Self, Box<dyn std::error::Error>> {
+            let controller = match config.kind {
+                DisplayKind::Ili9341 => &ILI9341,
+                _ => &ST7789,
+            };
+            let mut spi = Spidev::open(&config.spi_device)?;
+            let options = SpidevOptions::new()
+                .bits_per_word(8)
+                .max_speed_hz(SPI_SPEED_HZ)
+                .mode(SpiModeFlags::SPI_MODE_0)
+                .build();
+            spi.configure(&options)?;
+
+            let mut chip = Chip::new(&config.gpio_chip)?;
+            let mut output = |line: u32, value: u8| {
+                chip.get_line(line)?
+                    .request(LineRequestFlags::OUTPUT, value, "bpm_display")
+            };
+            let dc = output(config.dc_line, 0)?;
+            let reset = config.reset_line.map(|line| output(line, 1)).transpose()?;
+            let backlight = config
+                .backlight_line
+                .map(|line| output(line, 1))
+                .transpose()?;
+            if let Some(reset) = &reset {
+                reset.set_value(0)?;
+                sleep(Duration::from_millis(10));
+                reset.set_value(1)?;
+                sleep(Duration::from_millis(120));
+            }
+
+            let index = (config.rotation / 90 % 4) as usize;
+            let (width, height) = if index.is_multiple_of(2) {
+                controller.size
+            } else {
+                (controller.size.1, controller.size.0)
+            };
+            let mut tft = Self {
+                spi,
+                dc,
+                _reset: reset,
+                _backlight: backlight,
+                size: Size::new(width, height),
+                offset: controller.offsets[index],
+                frame: vec![0; (width * height) as usize],
+                dirty: None,
+                tx: Vec::with_capacity((width * height * 2) as usize),
+            };
+            tft.command(SWRESET, &[])?;
+            sleep(Duration::from_millis(150));
+            tft.command(SLPOUT, &[])?;
+            sleep(Duration::from_millis(120));
+            tft.command(COLMOD, &[COLMOD_RGB565])?;
+            tft.command(MADCTL, &[controller.madctl[index]])?;
+            if controller.invert {
+                tft.command(INVON, &[])?;
+            }
+            tft.command(NORON, &[])?;
+            tft.command(DISPON, &[])?;
+            sleep(Duration::from_millis(20));
+            Ok(tft)
+        }
+
+        fn command(&mut self, command: u8, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+            self.dc.set_value(0)?;
+            self.spi.write_all(&[command])?;
+            if !data.is_empty() {
+                self.dc.set_value(1)?;
+                for chunk in data.chunks(SPI_CHUNK) {
+                    self.spi.write_all(chunk)?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Envoie la zone modifiée depuis le dernier appel
+        pub fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            let Some((x0, y0, x1, y1)) = self.dirty.take() else {
+                return Ok(());
+            };
+            let (offset_x, offset_y) = self.offset;
+            self.command(CASET, &window(x0, x1, offset_x))?;
+            self.command(RASET, &window(y0, y1, offset_y))?;
+            let mut pixels = std::mem::take(&mut self.tx);
+            pixels.clear();
+            let width = self.size.width;
+            for y in y0..=y1 {
+                let row = (y * width) as usize;
+                for pixel in &self.frame[row + x0 as usize..=row + x1 as usize] {
+                    pixels.extend_from_slice(&pixel.to_be_bytes());
+                }
+            }
+            let sent = self.command(RAMWR, &pixels);
+            self.tx = pixels;
+            sent
+        }
+    }
+
+    // Paramètres de CASET/RASET: première et dernière colonne (ou ligne)
+    fn window(start: u32, end: u32, offset: u16) -> [u8; 4] {
+        let start = (start as u16 + offset).to_be_bytes();
+        let end = (end as u16 + offset).to_be_bytes();
+        [start[0], start[1], end[0], end[1]]
+    }
+
+    impl OriginDimensions for Tft {
+        fn size(&self) -> Size {
+            self.size
+        }
+    }
+
+    impl DrawTarget for Tft {
+        type Color = Rgb565;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            let (width, height) = (self.size.width, self.size.height);
+            for Pixel(point, color) in pixels {
+                if point.x < 0 || point.y < 0 {
+                    continue;
+                }
+                let (x, y) = (point.x as u32, point.y as u32);
+                if x >= width || y >= height {
+                    continue;
+                }
+                self.frame[(y * width + x) as usize] = RawU16::from(color).into_inner();
+                self.dirty = Some(match self.dirty {
+                    Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                    None => (x, y, x, y),
+                });
+            }
+            Ok(())
+        }
+    }
+
+    /// Cible agrandie `scale` fois: les polices bitmap deviennent lisibles de loin
+    struct Scaled<'a> {
+        target: &'a mut Tft,
+        scale: u32,
+    }
+
+    impl OriginDimensions for Scaled<'_> {
+        fn size(&self) -> Size {
+            self.target.size / self.scale
+        }
+    }
+
+    impl DrawTarget for Scaled<'_> {
+        type Color = Rgb565;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            let size = Size::new(self.scale, self.scale);
+            for Pixel(point, color) in pixels {
+                let area = Rectangle::new(point * self.scale as i32, size);
+                self.target.fill_solid(&area, color)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Texte dont `position` est le coin haut (gauche, centre ou droit selon `alignment`)
+    fn text<D>(
+        target: &mut D,
+        position: Point,
+        content: &str,
+        font: &MonoFont<'_>,
+        color: Rgb565,
+        alignment: Alignment,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let style = MonoTextStyle::new(font, color);
+        let layout = TextStyleBuilder::new()
+            .alignment(alignment)
+            .baseline(Baseline::Top)
+            .build();
+        Text::with_text_style(content, position, style, layout).draw(target)?;
+        Ok(())
+    }
+
+    /// Zones de l'écran: ligne Link, tempo, barre de niveau, informations
+    struct TftLayout {
+        header: Rectangle,
+        bpm: Rectangle,
+        level: Rectangle,
+        info: Rectangle,
+    }
+
+    impl TftLayout {
+        fn new(size: Size) -> Self {
+            let header_height = LINE_HEIGHT + 4;
+            let level_height = 16;
+            let info_height = 3 * LINE_HEIGHT;
+            // Le tempo prend toute la place restante
+            let bpm_height = size
+                .height
+                .saturating_sub(header_height + level_height + 2 * MARGIN + info_height)
+                .max(LINE_HEIGHT * 2);
+            let mut top = 0;
+            let mut next = |height: u32, margin: u32| {
+                let area = Rectangle::new(
+                    Point::new(margin as i32, top as i32),
+                    Size::new(size.width - 2 * margin, height),
+                );
+                top += height + MARGIN;
+                area
+            };
+            Self {
+                header: next(header_height, 0),
+                bpm: next(bpm_height - MARGIN, 0),
+                level: next(level_height, MARGIN),
+                info: next(info_height, MARGIN),
+            }
+        }
+    }
+
+    /// Écran couleur SPI (ST7789, ILI9341): un seul écran avec le tempo en grand,
+    /// le niveau en couleur et un cadre qui flashe sur le beat
+    pub struct TftDisplay {
+        tft: Tft,
+        layout: TftLayout,
+        pub state: AppState,
+        bpm: Option<f32>,
+        beat: bool,
+        // Largeur allumée de la barre de niveau, pour ne renvoyer que les changements
+        level_width: Option<u32>,
+        gain: Option<i64>,
+        hostname: String,
+        eth0: Option<Ipv4Addr>,
+        usb0: Option<Ipv4Addr>,
+        update_frame: usize,
+    }
+
+    impl TftDisplay {
+        pub fn new(config: &DisplayConfig) -> Result<Self, Box<dyn std::error::Error>> {
+            let tft = Tft::new(config)?;
+            let size = tft.size();
+            let mut display = Self {
+                layout: TftLayout::new(size),
+                tft,
+                state: AppState::default(),
+                bpm: None,
+                beat: false,
+                level_width: None,
+                gain: None,
+                hostname: String::new(),
+                eth0: None,
+                usb0: None,
+                update_frame: 0,
+            };
+            display.redraw()?;
+            info!(
+                "Écran {:?} {}x{} initialisé sur {}",
+                config.kind, size.width, size.height, config.spi_device
+            );
+            Ok(display)
+        }
+
+        // Rien n'est dessiné par-dessus l'animation de mise à jour
+        fn visible(&self) -> bool {
+            !self.state.update_in_progress
+        }
+
+        /// Redessine tout l'écran
+        fn redraw(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.tft.clear(BACKGROUND)?;
+            self.level_width = None;
+            self.draw_header()?;
+            self.draw_bpm()?;
+            self.draw_beat()?;
+            self.draw_level(0.0)?;
+            self.draw_info()?;
+            self.tft.flush()
+        }
+
+        fn draw_header(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            let area = self.layout.header;
+            self.tft.fill_solid(&area, BACKGROUND)?;
+            let content = match self.state.link_session {
+                Some((peers, tempo)) => link_text(peers, tempo as f64 / 10.0),
+                None => "Link".to_string(),
+            };
+            let position = area.top_left + Point::new(MARGIN as i32, 2);
+            text(
+                &mut self.tft,
+                position,
+                &content,
+                &FONT_10X20,
+                DIM,
+                Alignment::Left,
+            )?;
+            Ok(())
+        }
+
+        fn draw_bpm(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            let area = self.layout.bpm;
+            let inner = Rectangle::new(
+                area.top_left + Point::new(BEAT_FRAME as i32, BEAT_FRAME as i32),
+                area.size - Size::new(2 * BEAT_FRAME, 2 * BEAT_FRAME),
+            );
+            self.tft.fill_solid(&inner, BACKGROUND)?;
+            let content = match self.bpm {
+                Some(bpm) => format!("{:.2}", bpm),
+                None => "***.**".to_string(),
+            };
+            // FONT_10X20 agrandie au maximum: 6 caractères sur la largeur
+            let font = &FONT_10X20;
+            let glyph = font.character_size;
+            let scale = ((inner.size.width - 2 * MARGIN) / (6 * glyph.width))
+                .min(inner.size.height / glyph.height)
+                .max(1);
+            let center = inner.center();
+            let top = center.y - (glyph.height * scale / 2) as i32;
+            let position = Point::new(center.x, top) / scale as i32;
+            let mut scaled = Scaled {
+                target: &mut self.tft,
+                scale,
+            };
+            text(
+                &mut scaled,
+                position,
+                &content,
+                font,
+                FOREGROUND,
+                Alignment::Center,
+            )?;
+            Ok(())
+        }
+
+        fn draw_beat(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            let color = if self.beat { ACCENT } else { BACKGROUND };
+            self.layout
+                .bpm
+                .into_styled(PrimitiveStyle::with_stroke(color, BEAT_FRAME))
+                .draw(&mut self.tft)?;
+            Ok(())
+        }
+
+        fn draw_level(&mut self, level: f32) -> Result<(), Box<dyn std::error::Error>> {
+            let area = self.layout.level;
+            let fraction = level.clamp(0.0, LEVEL_FULL_SCALE) / LEVEL_FULL_SCALE;
+            let lit = (fraction * area.size.width as f32).round() as u32;
+            if self.level_width == Some(lit) {
+                return Ok(());
+            }
+            self.level_width = Some(lit);
+            // Vert -> jaune -> rouge le long de la barre, comme le VU-mètre du ruban
+            for x in 0..area.size.width {
+                let color = if x < lit {
+                    let k = x as f32 / area.size.width as f32;
+                    Rgb565::new(
+                        (62.0 * k).min(31.0) as u8,
+                        (126.0 * (1.0 - k)).min(63.0) as u8,
+                        0,
+                    )
+                } else {
+                    BAR_OFF
+                };
+                let column = Rectangle::new(
+                    area.top_left + Point::new(x as i32, 0),
+                    Size::new(1, area.size.height),
+                );
+                self.tft.fill_solid(&column, color)?;
+            }
+            Ok(())
+        }
+
+        fn draw_info(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            let area = self.layout.info;
+            self.tft.fill_solid(&area, BACKGROUND)?;
+            let line = |index: u32| area.top_left + Point::new(0, (index * LINE_HEIGHT) as i32);
+
+            let address = match (self.eth0, self.usb0) {
+                (Some(address), _) => format!("eth0 {}", address),
+                (None, Some(address)) => format!("usb0 {}", address),
+                (None, None) => "eth0 -".to_string(),
+            };
+            for (index, content) in [(0, &self.hostname), (1, &address)] {
+                text(
+                    &mut self.tft,
+                    line(index),
+                    content,
+                    &FONT_10X20,
+                    FOREGROUND,
+                    Alignment::Left,
+                )?;
+            }
+
+            // Équivalent des icônes de l'OLED
+            let tags = [
+                ("USB", self.state.usb_connected, ACTIVE),
+                ("ETH", self.state.ethernet_connected, ACTIVE),
+                ("NET", self.state.internet_connected, ACTIVE),
+                ("UPD", self.state.update_available, ACCENT),
+            ];
+            let mut position = line(2);
+            for (label, on, color) in tags {
+                let color = if on { color } else { DIM };
+                text(
+                    &mut self.tft,
+                    position,
+                    label,
+                    &FONT_10X20,
+                    color,
+                    Alignment::Left,
+                )?;
+                position.x += 4 * FONT_10X20.character_size.width as i32;
+            }
+            let gain = match self.gain {
+                Some(gain) => format!("Gain {}", gain),
+                None => "Gain -".to_string(),
+            };
+            let right = Point::new(area.top_left.x + area.size.width as i32, line(2).y);
+            text(
+                &mut self.tft,
+                right,
+                &gain,
+                &FONT_6X10,
+                DIM,
+                Alignment::Right,
+            )?;
+            Ok(())
+        }
+
+        fn draw_update(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.tft.clear(BACKGROUND)?;
+            let dots = ".".repeat(self.update_frame % 4);
+            let center = self.tft.bounding_box().center();
+            text(
+                &mut self.tft,
+                center - Point::new(0, LINE_HEIGHT as i32),
+                &format!("Updating{:<3}", dots),
+                &FONT_10X20,
+                ACCENT,
+                Alignment::Center,
+            )?;
+            Ok(())
+        }
+    }
+
+    impl Display for TftDisplay {
+        fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.tft.flush()
+        }
+
+        fn draw_status_icon(
+            &mut self,
+            icon: StatusBarIcon,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            self.state.set_icon(&icon, true);
+            if self.visible() {
+                self.draw_info()?;
+            }
+            Ok(())
+        }
+
+        fn clear_status_icon(
+            &mut self,
+            icon: StatusBarIcon,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            self.state.set_icon(&icon, false);
+            if self.visible() {
+                self.draw_info()?;
+            }
+            Ok(())
+        }
+
+        /// Un seul écran sur les TFT: tout y est affiché
+        fn next_page(&mut self) -> Result<PageId, Box<dyn std::error::Error>> {
+            Ok(PageId::Bpm)
+        }
+
+        fn show_bpm(&mut self, bpm: f32) -> Result<(), Box<dyn std::error::Error>> {
+            self.bpm = Some(bpm);
+            if !self.visible() {
+                return Ok(());
+            }
+            self.draw_bpm()?;
+            self.tft.flush()
+        }
+
+        fn show_beat(&mut self, on: bool) -> Result<(), Box<dyn std::error::Error>> {
+            self.beat = on;
+            if !self.visible() {
+                return Ok(());
+            }
+            self.draw_beat()?;
+            self.tft.flush()
+        }
+
+        fn show_link_state(
+            &mut self,
+            peers: usize,
+            tempo: f64,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            if !self.state.set_link_session(peers, tempo) || !self.visible() {
+                return Ok(());
+            }
+            self.draw_header()?;
+            self.tft.flush()
+        }
+
+        fn set_hostname(&mut self, hostname: &str) -> Result<(), Box<dyn std::error::Error>> {
+            self.hostname = hostname.to_string();
+            if !self.visible() {
+                return Ok(());
+            }
+            self.draw_info()?;
+            self.tft.flush()
+        }
+
+        fn set_interface_address(
+            &mut self,
+            interface: &str,
+            address: Option<Ipv4Addr>,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            let slot = match interface {
+                "eth0" => &mut self.eth0,
+                "usb0" => &mut self.usb0,
+                _ => return Ok(()),
+            };
+            if *slot == address {
+                return Ok(());
+            }
+            *slot = address;
+            if !self.visible() {
+                return Ok(());
+            }
+            self.draw_info()?;
+            self.tft.flush()
+        }
+
+        fn update_audio_bar(&mut self, value: f32) -> Result<(), Box<dyn std::error::Error>> {
+            if !self.visible() {
+                return Ok(());
+            }
+            self.draw_level(value)?;
+            self.tft.flush()
+        }
+
+        fn update_gain(&mut self, gain: i64) -> Result<(), Box<dyn std::error::Error>> {
+            if self.gain == Some(gain) {
+                return Ok(());
+            }
+            self.gain = Some(gain);
+            if !self.visible() {
+                return Ok(());
+            }
+            self.draw_info()?;
+            self.tft.flush()
+        }
+
+        fn show_goodbye(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.tft.clear(BACKGROUND)?;
+            let center = self.tft.bounding_box().center();
+            let mut scaled = Scaled {
+                target: &mut self.tft,
+                scale: 2,
+            };
+            text(
+                &mut scaled,
+                Point::new(center.x, center.y - 2 * LINE_HEIGHT as i32) / 2,
+                "Bye!",
+                &FONT_10X20,
+                FOREGROUND,
+                Alignment::Center,
+            )?;
+            text(
+                &mut self.tft,
+                Point::new(center.x, center.y + LINE_HEIGHT as i32),
+                "Powering off...",
+                &FONT_10X20,
+                DIM,
+                Alignment::Center,
+            )?;
+            self.tft.flush()
+        }
+
+        fn update_in_progress(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.state.begin_update()?;
+            self.update_frame = 0;
+            self.draw_update()?;
+            self.tft.flush()
+        }
+
+        fn next_update_frame(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.update_frame += 1;
+            self.draw_update()?;
+            self.tft.flush()
+        }
+
+        fn end_update(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            self.state.update_in_progress = false;
+            self.redraw()
+        }
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(all(any(target_arch = "aarch64", target_arch = "arm"), target_os = "linux"))]
+pub mod trigger {
+    use crate::core_embedded::led::led::{BeatInfo, Led};
+    use rust_bpm_analyzer::config::PhraseTriggerConfig;
+    use tokio::sync::watch;
+    use tokio::time::{Duration, Instant, sleep, sleep_until};
+
+    /// Impulsion GPIO toutes les N mesures (début de phrase), pour déclencher
+    /// un changement de caméra ou un enregistrement de clip.
+    pub struct PhraseTrigger {
+        output: Led,
+        beats_per_phrase: u64,
+        pulse_ms: u64,
+    }
+
+    impl PhraseTrigger {
+        pub fn new(config: &PhraseTriggerConfig) -> Result<Self, Box<dyn std::error::Error>> {
+            let output = Led::new(&config.gpio_chip, config.gpio_line)?;
+            Ok(Self {
+                output,
+                beats_per_phrase: (config.bars.max(1) * config.beats_per_bar.max(1)) as u64,
+                pulse_ms: config.pulse_ms,
+            })
+        }
+
+        /// Même grille que `BeatLed` : la phrase redémarre sur chaque drop.
+        pub async fn run(self, mut rx: watch::Receiver<Option<BeatInfo>>) {
+            let _ = self.output.off();
+            let mut beat_index: u64 = 0;
+            let mut current_drop = None;
+
+            loop {
+                let info = *rx.borrow_and_update();
+                let Some(info) = info.filter(|i| i.bpm > 0.0) else {
+                    if rx.changed().await.is_err() {
+                        return;
+                    }
+                    continue;
+                };
+
+                if info.last_drop != current_drop {
+                    current_drop = info.last_drop;
+                    beat_index = 0;
+                }
+
+                let period = Duration::from_secs_f32(60.0 / info.bpm);
+                let anchor = Instant::from_std(info.last_beat);
+                let elapsed = Instant::now().saturating_duration_since(anchor);
+                let beats = (elapsed.as_secs_f32() / period.as_secs_f32()).floor() as u32 + 1;
+                let next_beat = anchor + period * beats;
+
+                tokio::select! {
+                    _ = sleep_until(next_beat) => {
+                        let is_phrase_start = beat_index.is_multiple_of(self.beats_per_phrase);
+                        beat_index += 1;
+                        if is_phrase_start {
+                            let _ = self.output.on();
+                            sleep(Duration::from_millis(self.pulse_ms)).await;
+                            let _ = self.output.off();
+                        }
+                    }
+                    changed = rx.changed() => {
+                        if changed.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
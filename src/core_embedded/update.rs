@@ -1,74 +1,368 @@
 #[cfg(all(any(target_arch = "aarch64", target_arch = "arm"), target_os = "linux"))]
 pub mod update {
+    use rust_bpm_analyzer::config::UpdateChannel;
     use self_update::cargo_crate_version;
+    use self_update::update::{Release, ReleaseAsset};
+    use serde::Serialize;
+    use sha2::{Digest, Sha256};
+    use std::io::{Read, Write};
     use std::os::unix::process::CommandExt;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tracing::{error, info, warn};
+
+    // Marqueur écrit avant de redémarrer sur une nouvelle version, effacé une fois
+    // celle-ci jugée saine: "<version> <démarrages>"
+    const PENDING_FILE: &str = "update-pending";
+    // Suffixe de la copie de la version précédente, gardée pour le retour arrière
+    const BACKUP_SUFFIX: &str = ".previous";
+    // Démarrages sans confirmation tolérés (plantage avant le contrôle de santé)
+    const MAX_UNCONFIRMED_STARTS: u32 = 2;
+
+    /// Étape de la mise à jour, publiée dans GET /status et GET /update
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+    pub enum UpdatePhase {
+        #[default]
+        Idle,
+        Checking,
+        Downloading,
+        Installing,
+        Failed,
+    }
+
+    /// État de la mise à jour, partagé par les clones de `Updater`
+    #[derive(Debug, Clone, Default, Serialize)]
+    pub struct UpdateStatus {
+        pub phase: UpdatePhase,
+        /// Version trouvée par la dernière vérification
+        pub available: Option<String>,
+        /// Avancement du téléchargement (%)
+        pub progress: Option<u8>,
+        pub error: Option<String>,
+    }
+
+    impl UpdateStatus {
+        /// Vérification ou installation en cours
+        pub fn busy(&self) -> bool {
+            matches!(
+                self.phase,
+                UpdatePhase::Checking | UpdatePhase::Downloading | UpdatePhase::Installing
+            )
+        }
+    }
 
     #[derive(Clone)]
     pub struct Updater {
         repo_owner: String,
         repo_name: String,
         bin_name: String,
+        channel: UpdateChannel,
+        status: Arc<Mutex<UpdateStatus>>,
     }
 
     impl Updater {
         /// Vérifie s'il existe une mise à jour disponible sur GitHub via ReleaseList
         pub fn check(&self) -> Result<Option<String>, Box<dyn std::error::Error>> {
-            // Configuration inspirée de l'exemple self_update
-            let releases = self_update::backends::github::ReleaseList::configure()
-                .repo_owner(&self.repo_owner)
-                .repo_name(&self.repo_name)
-                .build()?
-                .fetch()?;
-
-            // println!("found releases:");
-            // println!("{:#?}\n", releases);
-
-            if let Some(release) = releases.first() {
-                if release.version != cargo_crate_version!() {
-                    return Ok(Some(release.version.clone()));
+            self.set_phase(UpdatePhase::Checking, None);
+            match self.latest_release() {
+                Ok(release) => {
+                    let version = release.map(|release| release.version);
+                    if let Ok(mut status) = self.status.lock() {
+                        status.available = version.clone();
+                    }
+                    self.set_phase(UpdatePhase::Idle, None);
+                    Ok(version)
+                }
+                Err(e) => {
+                    self.fail(e.as_ref());
+                    Err(e)
                 }
             }
-            Ok(None)
         }
+
         pub fn new(repo_owner: &str, repo_name: &str, bin_name: &str) -> Self {
             Updater {
                 repo_owner: repo_owner.to_string(),
                 repo_name: repo_name.to_string(),
                 bin_name: bin_name.to_string(),
+                channel: UpdateChannel::Stable,
+                status: Arc::new(Mutex::new(UpdateStatus::default())),
             }
         }
 
-        pub fn check_and_update(&self) -> Result<(), Box<dyn std::error::Error>> {
-            // Configuration de l'update selon l'exemple github
-            let status = self_update::backends::github::Update::configure()
+        pub fn status(&self) -> UpdateStatus {
+            self.status.lock().map(|s| s.clone()).unwrap_or_default()
+        }
+
+        fn set_phase(&self, phase: UpdatePhase, progress: Option<u8>) {
+            if let Ok(mut status) = self.status.lock() {
+                status.phase = phase;
+                status.progress = progress;
+                if phase != UpdatePhase::Failed {
+                    status.error = None;
+                }
+            }
+        }
+
+        fn fail(&self, error: &dyn std::error::Error) {
+            self.set_phase(UpdatePhase::Failed, None);
+            if let Ok(mut status) = self.status.lock() {
+                status.error = Some(error.to_string());
+            }
+        }
+
+        /// Canal suivi: versions stables seulement, ou aussi les pré-versions (beta)
+        pub fn with_channel(mut self, channel: UpdateChannel) -> Self {
+            self.channel = channel;
+            self
+        }
+
+        /// Version la plus récente du canal, si elle est plus récente que la version courante
+        fn latest_release(&self) -> Result<Option<Release>, Box<dyn std::error::Error>> {
+            let releases = self_update::backends::github::ReleaseList::configure()
                 .repo_owner(&self.repo_owner)
                 .repo_name(&self.repo_name)
-                .bin_name(&self.bin_name)
-                .show_download_progress(true)
-                .no_confirm(true)
-                .current_version(cargo_crate_version!())
                 .build()?
-                .update()?;
+                .fetch()?;
+
+            // Les releases arrivent de la plus récente à la plus ancienne
+            Ok(releases.into_iter().find(|release| {
+                self.channel.accepts(&release.version)
+                    && self_update::version::bump_is_greater(
+                        cargo_crate_version!(),
+                        &release.version,
+                    )
+                    .unwrap_or(false)
+            }))
+        }
+
+        pub fn check_and_update(&self) -> Result<(), Box<dyn std::error::Error>> {
+            let result = self.install();
+            if let Err(e) = &result {
+                self.fail(e.as_ref());
+            }
+            result
+        }
+
+        fn install(&self) -> Result<(), Box<dyn std::error::Error>> {
+            self.set_phase(UpdatePhase::Checking, None);
+            let Some(release) = self.latest_release()? else {
+                info!("Déjà à jour.");
+                self.set_phase(UpdatePhase::Idle, None);
+                return Ok(());
+            };
+            info!(
+                "Mise à jour vers {} (canal {:?})...",
+                release.version, self.channel
+            );
+
+            // Archive du binaire et son empreinte SHA-256 publiées par la CI
+            let archive_name = format!("{}-{}.tar.gz", self.bin_name, self_update::get_target());
+            let archive = find_asset(&release, &archive_name)?;
+            let checksum = find_asset(&release, &format!("{}.sha256", archive_name))?;
+
+            let tmp_dir = self_update::TempDir::new()?;
+            let archive_path = tmp_dir.path().join(&archive_name);
+            self.set_phase(UpdatePhase::Downloading, Some(0));
+            download(&archive, std::fs::File::create(&archive_path)?, |pct| {
+                self.set_phase(UpdatePhase::Downloading, Some(pct))
+            })?;
+            let mut expected = Vec::new();
+            download(&checksum, &mut expected, |_| {})?;
+            self.set_phase(UpdatePhase::Installing, None);
+            verify_sha256(&std::fs::read(&archive_path)?, &expected)?;
+            info!("Empreinte SHA-256 vérifiée");
 
-            println!("Update status: `{}`!", status.version());
+            self_update::Extract::from_source(&archive_path)
+                .extract_file(tmp_dir.path(), &self.bin_name)?;
 
-            if status.updated() {
-                println!("Mise à jour réussie ! Redémarrage...");
-                self.restart()?;
-            } else {
-                println!("Déjà à jour.");
+            // Copie de la version courante pour revenir en arrière si la nouvelle échoue
+            let exe = self.exe_path()?;
+            std::fs::copy(&exe, self.backup_path()?)?;
+            self_update::self_replace::self_replace(tmp_dir.path().join(&self.bin_name))?;
+            write_pending(&self.pending_path()?, &release.version, 0)?;
+
+            info!("Mise à jour réussie ! Redémarrage...");
+            self.restart()
+        }
+
+        // Binaire, sauvegarde et marqueur dans le dossier de travail (voir `restart`)
+        fn exe_path(&self) -> std::io::Result<PathBuf> {
+            Ok(std::env::current_dir()?.join(&self.bin_name))
+        }
+
+        fn backup_path(&self) -> std::io::Result<PathBuf> {
+            Ok(std::env::current_dir()?.join(format!("{}{}", self.bin_name, BACKUP_SUFFIX)))
+        }
+
+        fn pending_path(&self) -> std::io::Result<PathBuf> {
+            Ok(std::env::current_dir()?.join(PENDING_FILE))
+        }
+
+        /// Réinstalle la version précédente et redémarre dessus
+        fn rollback(&self) -> Result<(), Box<dyn std::error::Error>> {
+            let backup = self.backup_path()?;
+            if !backup.exists() {
+                let _ = std::fs::remove_file(self.pending_path()?);
+                return Err("pas de version précédente sauvegardée".into());
             }
-            Ok(())
+            warn!("Retour à la version précédente...");
+            self_update::self_replace::self_replace(&backup)?;
+            let _ = std::fs::remove_file(&backup);
+            let _ = std::fs::remove_file(self.pending_path()?);
+            self.restart()
         }
 
         fn restart(&self) -> Result<(), Box<dyn std::error::Error>> {
-            let cur_dir = std::env::current_dir()?;
             // On utilise ./bin_name car current_exe() peut être invalide après update
-            let exe = cur_dir.join(&self.bin_name);
+            let exe = self.exe_path()?;
 
-            println!("Redémarrage de : {:?}", exe);
+            info!("Redémarrage de : {:?}", exe);
             let err = std::process::Command::new(&exe).exec();
             Err(Box::new(err))
         }
     }
+
+    fn find_asset(release: &Release, name: &str) -> Result<ReleaseAsset, String> {
+        release
+            .assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .cloned()
+            .ok_or_else(|| format!("fichier {} absent de la release {}", name, release.version))
+    }
+
+    /// Télécharge `asset` dans `dest` en signalant l'avancement (%) quand la
+    /// taille est connue
+    fn download<W: Write>(
+        asset: &ReleaseAsset,
+        mut dest: W,
+        mut progress: impl FnMut(u8),
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // L'URL de l'API GitHub renvoie le fichier lui-même avec cet en-tête
+        let mut response = reqwest::blocking::Client::new()
+            .get(&asset.download_url)
+            .header(reqwest::header::ACCEPT, "application/octet-stream")
+            .header(
+                reqwest::header::USER_AGENT,
+                concat!("rust-bpm-analyzer/", env!("CARGO_PKG_VERSION")),
+            )
+            .send()?
+            .error_for_status()?;
+        let total = response.content_length().filter(|&len| len > 0);
+
+        let mut buffer = [0u8; 16 * 1024];
+        let mut received: u64 = 0;
+        let mut last_pct = None;
+        loop {
+            let n = response.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            dest.write_all(&buffer[..n])?;
+            received += n as u64;
+            if let Some(total) = total {
+                let pct = (received * 100 / total).min(100) as u8;
+                if last_pct != Some(pct) {
+                    progress(pct);
+                    last_pct = Some(pct);
+                }
+            }
+        }
+        dest.flush()?;
+        Ok(())
+    }
+
+    /// Compare l'archive à la sortie de `sha256sum` ("<hex>  <fichier>")
+    fn verify_sha256(data: &[u8], expected: &[u8]) -> Result<(), String> {
+        let expected = String::from_utf8_lossy(expected);
+        let expected = expected
+            .split_whitespace()
+            .next()
+            .ok_or("empreinte SHA-256 vide")?
+            .to_ascii_lowercase();
+        let actual = format!("{:x}", Sha256::digest(data));
+        if actual != expected {
+            return Err(format!(
+                "empreinte SHA-256 invalide (attendue {}, obtenue {})",
+                expected, actual
+            ));
+        }
+        Ok(())
+    }
+
+    fn write_pending(path: &Path, version: &str, starts: u32) -> std::io::Result<()> {
+        std::fs::write(path, format!("{} {}", version, starts))
+    }
+
+    fn read_pending(path: &Path) -> Option<(String, u32)> {
+        let content = std::fs::read_to_string(path).ok()?;
+        let mut fields = content.split_whitespace();
+        let version = fields.next()?.to_string();
+        let starts = fields.next()?.parse().ok()?;
+        Some((version, starts))
+    }
+
+    /// Contrôle de santé après une mise à jour: la nouvelle version doit lancer
+    /// l'analyse dans le délai imparti, sinon l'ancienne est réinstallée.
+    pub struct HealthCheck {
+        updater: Updater,
+        healthy: Arc<AtomicBool>,
+    }
+
+    impl HealthCheck {
+        /// `None` hors redémarrage après mise à jour. Un binaire qui plante avant
+        /// le contrôle est remplacé dès le démarrage suivant.
+        pub fn start(updater: Updater, timeout: Duration) -> Option<Self> {
+            let path = updater.pending_path().ok()?;
+            let (version, starts) = read_pending(&path)?;
+            if starts >= MAX_UNCONFIRMED_STARTS {
+                warn!(
+                    "Version {} jamais confirmée après {} démarrages",
+                    version, starts
+                );
+                if let Err(e) = updater.rollback() {
+                    error!("Retour arrière impossible: {}", e);
+                }
+                return None;
+            }
+            if let Err(e) = write_pending(&path, &version, starts + 1) {
+                error!("Erreur marqueur de mise à jour: {}", e);
+            }
+            info!(
+                "Version {} en observation: analyse attendue sous {:?}",
+                version, timeout
+            );
+
+            let healthy = Arc::new(AtomicBool::new(false));
+            let check_healthy = healthy.clone();
+            let check_updater = updater.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(timeout).await;
+                if !check_healthy.load(Ordering::SeqCst) {
+                    warn!("Version {} non saine après {:?}", version, timeout);
+                    if let Err(e) = check_updater.rollback() {
+                        error!("Retour arrière impossible: {}", e);
+                    }
+                }
+            });
+            Some(Self { updater, healthy })
+        }
+
+        /// L'analyse tourne: la mise à jour est validée (sans effet après le premier appel)
+        pub fn confirm(&self) {
+            if self.healthy.swap(true, Ordering::SeqCst) {
+                return;
+            }
+            info!("Mise à jour validée");
+            if let Ok(path) = self.updater.pending_path() {
+                let _ = std::fs::remove_file(path);
+            }
+            if let Ok(path) = self.updater.backup_path() {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
 }
@@ -4,6 +4,7 @@ pub mod usb {
     use std::os::unix::io::RawFd;
     use tokio::io::unix::AsyncFd;
     use tokio::process::Command;
+    use tracing::{error, info};
 
     // Constantes Netlink pour KOBJECT_UEVENT
     const NETLINK_KOBJECT_UEVENT: i32 = 15; // La valeur est 15 (NETLINK_KOBJECT_UEVENT) dans la plupart des headers kernel, parfois 31
@@ -93,7 +94,7 @@ pub mod usb {
     }
 
     async fn run_usb_script(action: &str, devpath: &str) {
-        println!("USB Event detected: Action={} DevPath={}", action, devpath);
+        info!("USB Event detected: Action={} DevPath={}", action, devpath);
 
         let script = "/mnt/system/usb.sh";
 
@@ -101,10 +102,10 @@ pub mod usb {
 
         match child {
             Ok(mut c) => match c.wait().await {
-                Ok(status) => println!("USB plug script finished: {}", status),
-                Err(e) => eprintln!("Error waiting for USB plug script: {}", e),
+                Ok(status) => info!("USB plug script finished: {}", status),
+                Err(e) => error!("Error waiting for USB plug script: {}", e),
             },
-            Err(e) => eprintln!("Failed to spawn USB plug script '{}': {}", script, e),
+            Err(e) => error!("Failed to spawn USB plug script '{}': {}", script, e),
         }
     }
 
@@ -127,12 +128,12 @@ pub mod usb {
         let mut listener = match UeventListener::new() {
             Ok(l) => l,
             Err(e) => {
-                eprintln!("Impossible d'ouvrir le socket Netlink Uevent: {}", e);
+                error!("Impossible d'ouvrir le socket Netlink Uevent: {}", e);
                 return Ok(());
             }
         };
 
-        println!("Écoute des événements USB matériels (Netlink KOBJECT_UEVENT)...");
+        info!("Écoute des événements USB matériels (Netlink KOBJECT_UEVENT)...");
 
         loop {
             match listener.next_event().await {
@@ -144,7 +145,7 @@ pub mod usb {
                     let action = parse_env(&event_str, "ACTION");
                     let devpath = parse_env(&event_str, "DEVPATH");
 
-                    // println!("DEBUG UEVENT: {:?}", event_str); // Très verbeux
+                    // info!("DEBUG UEVENT: {:?}", event_str); // Très verbeux
 
                     if let (Some(sub), Some(dtype), Some(act)) = (subsystem, devtype, action) {
                         if sub == "usb" && dtype == "usb_device" && act == "add" {
@@ -156,7 +157,7 @@ pub mod usb {
                     }
                 }
                 Err(e) => {
-                    eprintln!("Erreur lecture Uevent: {}", e);
+                    error!("Erreur lecture Uevent: {}", e);
                     // Petit délai pour éviter boucle infinie en cas d'erreur persistante
                     tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
                 }
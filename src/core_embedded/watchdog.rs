@@ -0,0 +1,158 @@
+#[cfg(all(any(target_arch = "aarch64", target_arch = "arm"), target_os = "linux"))]
+pub mod watchdog {
+    use std::fs::{File, OpenOptions};
+    use std::io::Write;
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{Duration, Instant};
+    use tokio::time::sleep;
+    use tracing::{error, info};
+
+    const WATCHDOG_DEVICE: &str = "/dev/watchdog";
+    // Période de rafraîchissement de /dev/watchdog (timeout matériel typique: 15 à 60 s)
+    const DEVICE_FEED_PERIOD: Duration = Duration::from_secs(2);
+
+    /// Progression de la boucle principale, marquée à chaque paquet audio traité
+    #[derive(Clone)]
+    pub struct Progress {
+        started: Instant,
+        last_ms: Arc<AtomicU64>,
+    }
+
+    impl Progress {
+        pub fn new() -> Self {
+            Self {
+                started: Instant::now(),
+                last_ms: Arc::new(AtomicU64::new(0)),
+            }
+        }
+
+        pub fn mark(&self) {
+            let now = self.started.elapsed().as_millis() as u64;
+            self.last_ms.store(now, Ordering::Relaxed);
+        }
+
+        /// Temps écoulé depuis la dernière progression
+        fn age(&self) -> Duration {
+            let last = Duration::from_millis(self.last_ms.load(Ordering::Relaxed));
+            self.started.elapsed().saturating_sub(last)
+        }
+    }
+
+    enum Backend {
+        /// sd_notify(WATCHDOG=1) quand le service a `WatchdogSec=`
+        Systemd {
+            socket: UnixDatagram,
+            address: SocketAddr,
+            period: Duration,
+        },
+        /// Watchdog matériel du SoC
+        Device(File),
+    }
+
+    /// Nourrit le watchdog tant que la boucle audio progresse: un flux ALSA bloqué ou
+    /// un mutex en interblocage provoque un redémarrage au lieu d'un appareil muet
+    pub struct Watchdog {
+        backend: Backend,
+        progress: Progress,
+        stall_timeout: Duration,
+    }
+
+    impl Watchdog {
+        /// Watchdog systemd si le service en a un, sinon /dev/watchdog.
+        /// `None` sans l'un ni l'autre.
+        pub fn new(progress: Progress, stall_timeout: Duration) -> Option<Self> {
+            let backend = match Self::systemd() {
+                Some(backend) => backend,
+                None => match OpenOptions::new().write(true).open(WATCHDOG_DEVICE) {
+                    Ok(file) => Backend::Device(file),
+                    Err(e) => {
+                        info!("Pas de watchdog ({}: {})", WATCHDOG_DEVICE, e);
+                        return None;
+                    }
+                },
+            };
+            Some(Self {
+                backend,
+                progress,
+                stall_timeout,
+            })
+        }
+
+        // NOTIFY_SOCKET et WATCHDOG_USEC sont fournis par systemd (WatchdogSec=)
+        fn systemd() -> Option<Backend> {
+            let path = std::env::var("NOTIFY_SOCKET").ok()?;
+            let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+            let address = match path.strip_prefix('@') {
+                Some(name) => SocketAddr::from_abstract_name(name.as_bytes()).ok()?,
+                None => SocketAddr::from_pathname(&path).ok()?,
+            };
+            let socket = UnixDatagram::unbound().ok()?;
+            let _ = socket.send_to_addr(b"READY=1", &address);
+            Some(Backend::Systemd {
+                socket,
+                address,
+                // Deux notifications par période de systemd
+                period: Duration::from_micros(usec) / 2,
+            })
+        }
+
+        fn period(&self) -> Duration {
+            match &self.backend {
+                Backend::Systemd { period, .. } => *period,
+                Backend::Device(_) => DEVICE_FEED_PERIOD,
+            }
+        }
+
+        fn feed(&mut self) -> std::io::Result<()> {
+            match &mut self.backend {
+                Backend::Systemd {
+                    socket, address, ..
+                } => socket.send_to_addr(b"WATCHDOG=1", address).map(|_| ()),
+                Backend::Device(file) => file.write_all(b"\0"),
+            }
+        }
+
+        pub async fn run(mut self) {
+            info!(
+                "Watchdog actif ({}), boucle audio bloquée après {:?}",
+                match self.backend {
+                    Backend::Systemd { .. } => "systemd",
+                    Backend::Device(_) => WATCHDOG_DEVICE,
+                },
+                self.stall_timeout
+            );
+            let mut stalled = false;
+            loop {
+                sleep(self.period()).await;
+                if self.progress.age() > self.stall_timeout {
+                    // Plus de rafraîchissement: redémarrage à l'expiration du watchdog
+                    if !stalled {
+                        error!(
+                            "Watchdog: boucle audio bloquée depuis {:?}, redémarrage imminent",
+                            self.progress.age()
+                        );
+                        stalled = true;
+                    }
+                    continue;
+                }
+                stalled = false;
+                if let Err(e) = self.feed() {
+                    error!("Erreur watchdog: {}", e);
+                }
+            }
+        }
+    }
+
+    impl Drop for Watchdog {
+        // Arrêt volontaire (Ctrl+C, arrêt propre): le caractère magique 'V' désarme
+        // /dev/watchdog avant sa fermeture
+        fn drop(&mut self) {
+            if let Backend::Device(file) = &mut self.backend {
+                let _ = file.write_all(b"V");
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use serde::Serialize;
+use std::error::Error;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rust_bpm_analyzer::config::{self, AppConfig};
+use rust_bpm_analyzer::core_bpm::analyzer::{AnalyzerState, BpmAnalyzerConfig};
+use rust_bpm_analyzer::core_bpm::histogram::HistogramSnapshot;
+use rust_bpm_analyzer::logging;
+
+// Port of the headless HTTP API
+const DEVICE_HTTP_PORT: u16 = 8080;
+
+/// State dumped as `stats.json`. Holds no audio.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsReport {
+    pub version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub config_path: PathBuf,
+    pub config_found: bool,
+    pub input_device: Option<String>,
+    pub live_device: Option<String>,
+    pub available_devices: Vec<String>,
+    pub detection_enabled: bool,
+    pub bpm: Option<f32>,
+    pub link_peers: usize,
+    pub network_devices: Vec<String>,
+    pub histogram: HistogramSnapshot,
+    // Filled by the analysis thread
+    pub sample_rate: Option<u32>,
+    pub analyzer: Option<BpmAnalyzerConfig>,
+    pub analyzer_state: Option<AnalyzerState>,
+}
+
+impl DiagnosticsReport {
+    pub fn new() -> Self {
+        let config_path = AppConfig::path();
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            config_found: config_path.is_file(),
+            config_path,
+            input_device: None,
+            live_device: None,
+            available_devices: Vec::new(),
+            detection_enabled: false,
+            bpm: None,
+            link_peers: 0,
+            network_devices: Vec::new(),
+            histogram: HistogramSnapshot::default(),
+            sample_rate: None,
+            analyzer: None,
+            analyzer_state: None,
+        }
+    }
+
+    /// Writes `diagnostics-<unix time>/` in `parent` with `stats.json`, `log.txt`
+    /// and a copy of the config file (API key redacted). Returns the bundle directory.
+    pub fn export(&self, parent: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        let stamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let dir = parent.join(format!("diagnostics-{}", stamp));
+        fs::create_dir_all(&dir)?;
+
+        fs::write(dir.join("stats.json"), serde_json::to_string_pretty(self)?)?;
+
+        fs::write(dir.join("log.txt"), logging::recent_lines().join("\n"))?;
+
+        if self.config_found {
+            let config = fs::read_to_string(&self.config_path)?;
+            fs::write(dir.join("config.toml"), redact_keys(&config))?;
+        }
+        Ok(dir)
+    }
+}
+
+/// Writes `analyzer-state-<unix time>.json` in `parent` and returns its path.
+pub fn dump_state(state: &AnalyzerState, parent: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let path = parent.join(format!("analyzer-state-{}.json", stamp));
+    fs::write(&path, serde_json::to_string_pretty(state)?)?;
+    Ok(path)
+}
+
+/// Value of the `--dump-state <host[:port]>` command line flag.
+pub fn cli_dump_state() -> Option<String> {
+    config::cli_option("--dump-state").flatten()
+}
+
+/// Prints the analyzer state of a headless device (`GET /state` on its HTTP API).
+pub fn print_remote_state(target: &str) -> Result<(), Box<dyn Error>> {
+    let address = if target.contains(':') {
+        target.to_string()
+    } else {
+        format!("{}:{}", target, DEVICE_HTTP_PORT)
+    };
+    let mut stream = TcpStream::connect(&address)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    // HTTP/1.0: the device closes the connection after the response
+    write!(stream, "GET /state HTTP/1.0\r\nHost: {}\r\n\r\n", address)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or("Invalid HTTP response")?;
+    let status = head.lines().next().unwrap_or_default();
+    if !status.contains(" 200 ") {
+        return Err(format!("{} answered: {}", address, status).into());
+    }
+    println!("{}", body);
+    Ok(())
+}
+
+// Masks `key = ...` entries so the bundle can be attached to a public issue
+fn redact_keys(config: &str) -> String {
+    config
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            match trimmed.split_once('=') {
+                Some((name, _)) if name.trim() == "key" => "key = \"<redacted>\"".to_string(),
+                _ => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
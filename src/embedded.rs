@@ -1,47 +1,157 @@
-use crate::core_bpm::{AudioCapture, AudioMessage, AudioPID, BpmAnalyzer};
 use crate::core_embedded::button::button::{ButtonAction, ButtonListener};
-use crate::core_embedded::display::display::BpmDisplay;
-use crate::core_embedded::led::led::Led;
+use crate::core_embedded::display::display::{self, OledSink, SharedDisplay};
+use crate::core_embedded::gate::gate::GateInput;
+use crate::core_embedded::http::http::{self, DeviceStatus, HttpCommand, LinkState};
+use crate::core_embedded::led::led::{BeatInfo, BeatLed, Led};
+use crate::core_embedded::ledstrip::ledstrip::{LedStrip, StripPattern};
 use crate::core_embedded::network::network;
-use crate::network_sync::LinkManager;
+use crate::core_embedded::session::session::{SavedSession, SessionStore};
+use crate::core_embedded::trigger::trigger::PhraseTrigger;
+use crate::core_embedded::update::update::{HealthCheck, Updater};
+use crate::core_embedded::watchdog::watchdog::{Progress, Watchdog};
 use crate::platform::TARGET_SAMPLE_RATE;
-use alsa::Mixer;
-use std::sync::mpsc;
+use rust_bpm_analyzer::config::{AppConfig, ButtonCommand, DisplayKind, LedStripPattern};
+use rust_bpm_analyzer::core_bpm::analyzer::AnalyzerState;
+use rust_bpm_analyzer::core_bpm::level_history::LevelHistory;
+use rust_bpm_analyzer::core_bpm::meter::CLIP_LEVEL;
+use rust_bpm_analyzer::core_bpm::simulated;
+use rust_bpm_analyzer::core_bpm::{
+    AudioCapture, AudioMessage, AudioPID, LiveAnalysis, audio_channel, open_mixer,
+};
+use rust_bpm_analyzer::metrics::METRICS;
+use rust_bpm_analyzer::network_sync::LinkManager;
+use rust_bpm_analyzer::network_sync::discovery::Discovery;
+use rust_bpm_analyzer::network_sync::election::{Candidate, Election};
+use rust_bpm_analyzer::network_sync::follow::{LinkRole, TempoFollow};
+use rust_bpm_analyzer::outputs::{SinkRegistry, TempoUpdate};
 use std::sync::{
     Arc, Mutex,
     atomic::{AtomicBool, Ordering},
 };
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::signal;
+use tracing::{error, info, warn};
+
+// Auto-test du chemin audio pendant les silences
+const SELF_CHECK_PERIOD: Duration = Duration::from_secs(60);
+const SELF_CHECK_IDLE: Duration = Duration::from_secs(300);
+const SELF_CHECK_BPM: f32 = 128.0;
+// Au-delà, on considère que le flux audio est bloqué
+const AUDIO_STALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Rafraîchissement de l'état de la session Link (écran et /status)
+const LINK_STATE_PERIOD: Duration = Duration::from_secs(1);
+// Copie des internes de l'analyseur servie par GET /state, pas à chaque analyse
+const ANALYZER_STATE_PERIOD: Duration = Duration::from_secs(1);
+
+// Historique du niveau d'entrée (GET /levels): un point par seconde sur 5 minutes
+const LEVEL_INTERVAL: Duration = Duration::from_secs(1);
+const LEVEL_PERIOD: Duration = Duration::from_secs(300);
+// Une entrée qui sature n'est signalée qu'une fois par période
+const CLIP_WARNING_PERIOD: Duration = Duration::from_secs(10);
+
+// État de l'analyseur écrit avant une mise à jour et repris par la nouvelle
+// version. Plus ancien, il vient d'une mise à jour interrompue: autre set
+const CHECKPOINT_FILE: &str = "analyzer-checkpoint.json";
+const CHECKPOINT_MAX_AGE: Duration = Duration::from_secs(300);
+
+// API REST de gestion (status / contrôle depuis un navigateur)
+const HTTP_PORT: u16 = 8080;
 
 enum AppEvent {
     Audio(AudioMessage),
     Button(ButtonAction),
+    SelfCheckTick,
+    Http(HttpCommand),
+    Gate(bool),
 }
 
 pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialisation de la LED de statut
-    if let Err(e) = Led::new("/dev/gpiochip4", 2).and_then(|l| l.on()) {
-        eprintln!("Erreur init LED statut: {}", e);
+    // Fichier de configuration (optionnel)
+    let config = AppConfig::load();
+
+    // Initialisation de la LED de statut (flashe ensuite sur les beats)
+    let (beat_tx, beat_rx) = tokio::sync::watch::channel::<Option<BeatInfo>>(None);
+    match Led::new("/dev/gpiochip4", 2) {
+        Ok(led) => {
+            tokio::spawn(BeatLed::new(led).run(beat_rx));
+        }
+        Err(e) => error!("Erreur init LED statut: {}", e),
+    }
+
+    // Ruban LED réactif au BPM ([ledstrip], désactivé par défaut)
+    let (energy_tx, energy_rx) = tokio::sync::watch::channel(0.0f32);
+    let strip_config = &config.ledstrip;
+    if strip_config.enabled {
+        match strip_config
+            .validate()
+            .map_err(Into::into)
+            .and_then(|()| LedStrip::new(&strip_config.spi_device, strip_config.length))
+        {
+            Ok(strip) => {
+                let pattern = match strip_config.pattern {
+                    LedStripPattern::Pulse => StripPattern::Pulse,
+                    LedStripPattern::Chase => StripPattern::Chase,
+                    LedStripPattern::VuMeter => StripPattern::VuMeter,
+                };
+                let [r, g, b] = strip_config.color;
+                tokio::spawn(strip.run(pattern, (r, g, b), beat_tx.subscribe(), energy_rx));
+            }
+            Err(e) => error!(
+                "Erreur init ruban LED sur {}: {}",
+                strip_config.spi_device, e
+            ),
+        }
     }
+    let mut last_drop: Option<Instant> = None;
 
-    // Initialisation de l'écran OLED
-    let bpm_display: Option<_> = match BpmDisplay::new("/dev/i2c-2") {
+    // Initialisation de l'écran, OLED ou TFT couleur ([display])
+    let display_config = match config.display.validate() {
+        Ok(()) => config.display.clone(),
+        Err(e) => {
+            warn!("Section [display] invalide ({}), valeurs par défaut", e);
+            Default::default()
+        }
+    };
+    let bpm_display: Option<SharedDisplay> = match display::open(&display_config) {
         Ok(d) => Some(Arc::new(Mutex::new(d))),
         Err(e) => {
-            eprintln!("Erreur init écran OLED: {}", e);
+            error!("Erreur init écran: {}", e);
             None
         }
     };
+    // Flash du beat sur les écrans couleur
+    if let Some(display_mutex) = bpm_display
+        .as_ref()
+        .filter(|_| display_config.kind != DisplayKind::Ssd1306)
+    {
+        tokio::spawn(display::run_beat_flash(
+            display_mutex.clone(),
+            beat_tx.subscribe(),
+        ));
+    }
 
     // Canal principal unique (MPSC Async)
     let (tx_main, mut rx_main) = tokio::sync::mpsc::channel::<AppEvent>(100);
 
+    // Mises à jour GitHub sur le canal choisi ([update])
+    let updater = Updater::new("kiki442002", "rust-bpm-analyzer", "rust-bpm-analyzer")
+        .with_channel(config.update.channel);
+    // Après une mise à jour: retour à l'ancienne version si l'analyse ne démarre pas
+    let health_check = HealthCheck::start(
+        updater.clone(),
+        Duration::from_secs(config.update.health_check_s),
+    );
+
     // Lancement des tâches spécifiques à l'embarqué
     #[cfg(all(any(target_arch = "aarch64", target_arch = "arm"), target_os = "linux"))]
     {
         /////////////Tache pour événements réseau////////////////
-        tokio::spawn(network::listen_interface_events(bpm_display.clone()));
+        tokio::spawn(network::listen_interface_events(
+            bpm_display.clone(),
+            updater.clone(),
+            config.update.check_interval(),
+        ));
         /////////////////////////////////////////////////////////
 
         /////////////Tache pour événements USB////////////////
@@ -58,7 +168,7 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
             // Lance le listener
             tokio::spawn(async move {
                 if let Err(e) = button_listener.run(tx_internal).await {
-                    eprintln!("Button listener error: {}", e);
+                    error!("Button listener error: {}", e);
                 }
             });
 
@@ -70,33 +180,254 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
         ////////////////////////////////////////////////////////
     }
 
+    /////////////Tache pour l'API HTTP////////////////
+    let api_key = config.api.key.clone();
+    let metrics_enabled = config.metrics.enabled;
+    let device_status = Arc::new(Mutex::new(DeviceStatus {
+        analysis_enabled: true,
+        gate: config.gate.enabled.then_some(false),
+        ..Default::default()
+    }));
+    let tx_http = tx_main.clone();
+    let http_status = device_status.clone();
+    tokio::spawn(async move {
+        let (tx_internal, mut rx_internal) = tokio::sync::mpsc::channel(8);
+
+        tokio::spawn(async move {
+            if let Err(e) = http::serve(
+                HTTP_PORT,
+                http_status,
+                tx_internal,
+                api_key,
+                metrics_enabled,
+            )
+            .await
+            {
+                error!("Erreur serveur HTTP: {}", e);
+            }
+        });
+
+        // Redirige vers la boucle principale
+        while let Some(command) = rx_internal.recv().await {
+            let _ = tx_http.send(AppEvent::Http(command)).await;
+        }
+    });
+    //////////////////////////////////////////////////
+
+    // Nom d'hôte: page réseau de l'écran et annonce mDNS
+    let hostname = std::fs::read_to_string("/etc/hostname")
+        .map(|h| h.trim().to_string())
+        .ok()
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "bpm-analyzer".to_string());
+    if let Some(display_mutex) = &bpm_display {
+        if let Ok(mut guard) = display_mutex.lock() {
+            let _ = guard.set_hostname(&hostname);
+        }
+    }
+    if let Ok(mut status) = device_status.lock() {
+        status.name = hostname.clone();
+    }
+
+    // Annonce mDNS pour que l'application desktop trouve l'appareil
+    let discovery = match Discovery::new(&config.discovery) {
+        Ok(discovery) => {
+            if let Err(e) = discovery.advertise(&hostname, HTTP_PORT) {
+                error!("Erreur annonce mDNS: {}", e);
+            }
+            // L'élection a besoin des annonces des autres analyseurs
+            if config.election.enabled {
+                if let Err(e) = discovery.browse() {
+                    error!("Erreur recherche mDNS: {}", e);
+                }
+            }
+            Some(discovery)
+        }
+        Err(e) => {
+            error!("Erreur init mDNS: {}", e);
+            None
+        }
+    };
+
     /////////////Tache pour CTRL+C////////////////
     let stop_flag = Arc::new(AtomicBool::new(false));
     let stop_flag_ctrlc = stop_flag.clone();
     tokio::spawn(async move {
         signal::ctrl_c().await.ok();
-        println!("Ctrl+C reçu, arrêt demandé.");
+        info!("Ctrl+C reçu, arrêt demandé.");
         stop_flag_ctrlc.store(true, Ordering::SeqCst);
     });
     ////////////////////////////////////////////////
 
-    println!("Starting BPM Analyzer (Headless)...");
+    /////////////Tache pour l'auto-test audio////////////////
+    let tx_check = tx_main.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SELF_CHECK_PERIOD);
+        interval.tick().await; // Le premier tick est immédiat
+        loop {
+            interval.tick().await;
+            if tx_check.send(AppEvent::SelfCheckTick).await.is_err() {
+                break;
+            }
+        }
+    });
+    ////////////////////////////////////////////////////////
+
+    info!("Starting BPM Analyzer (Headless)...");
+
+    // Mixer de la carte de capture ([alsa])
+    let alsa = match config.alsa.validate() {
+        Ok(()) => config.alsa.clone(),
+        Err(e) => {
+            warn!("Section [alsa] invalide ({}), valeurs par défaut", e);
+            Default::default()
+        }
+    };
+    let mixer = open_mixer(&alsa.card)?;
 
     // Paramètres PID
-    let mixer = Mixer::new("hw:0", false).map_err(|e: alsa::Error| e.to_string())?;
-    let mut pid = AudioPID::new(15.0, 1.5, 0.0, 8, &mixer)?;
-    let setpoint = 0.25; // Niveau cible RMS 
+    let agc = match config.agc.validate() {
+        Ok(()) => config.agc,
+        Err(e) => {
+            warn!("Section [agc] invalide ({}), valeurs par défaut", e);
+            Default::default()
+        }
+    };
+    let mut pid = AudioPID::new(
+        agc.kp,
+        agc.ki,
+        agc.kd,
+        agc.window,
+        &mixer,
+        alsa.control.as_deref(),
+    )?;
+    let mut setpoint = agc.setpoint; // Niveau cible RMS
+    if let Ok(mut status) = device_status.lock() {
+        status.agc = agc;
+    }
 
     // Ableton Link Manager
     let mut link_manager = LinkManager::new();
     link_manager.link_state(true); // Active Link
+    link_manager.set_quantum(config.link.quantum);
+    // Meneur ou suiveur du tempo de la session Link ([link] follow)
+    let mut tempo_follow = TempoFollow::new(config.link.clone());
+    // Élection du maître entre analyseurs sur la même sono ([election])
+    let mut election = config
+        .election
+        .enabled
+        .then(|| Election::new(config.election.clone(), &hostname));
+
+    // Impulsion GPIO en début de phrase (optionnelle)
+    if config.trigger.enabled {
+        match PhraseTrigger::new(&config.trigger) {
+            Ok(trigger) => {
+                tokio::spawn(trigger.run(beat_tx.subscribe()));
+            }
+            Err(e) => error!("Erreur init trigger de phrase: {}", e),
+        }
+    }
+
+    // Porte d'analyse sur entrée GPIO (optionnelle, aussi pilotable par POST /gate)
+    if config.gate.enabled {
+        if let Some(gate_input) = GateInput::new(&config.gate) {
+            let tx_gate = tx_main.clone();
+            tokio::spawn(async move {
+                let (tx_internal, mut rx_internal) = tokio::sync::mpsc::channel(8);
+                tokio::spawn(async move {
+                    if let Err(e) = gate_input.run(tx_internal).await {
+                        error!("Erreur entrée gate: {}", e);
+                    }
+                });
+                while let Some(open) = rx_internal.recv().await {
+                    let _ = tx_gate.send(AppEvent::Gate(open)).await;
+                }
+            });
+        }
+        info!("Gating actif: analyse en attente de l'ouverture de la porte");
+    }
+
+    // Sorties du tempo: Link, Art-Net, notes MIDI de beat/drop, sinks
+    // `[outputs]` (OSC, WebSocket, broadcast) et l'écran
+    let mut outputs = SinkRegistry::from_config(link_manager, &config);
+    if let Some(display_mutex) = &bpm_display {
+        outputs.register(Box::new(OledSink::new(display_mutex.clone())));
+    }
+    info!("Sorties: {}", outputs.names().join(", "));
+    // Analyseur BPM, gouverneur CPU et niveaux par bande à chaque temps
+    // (canaux de chase Art-Net)
+    let mut live = LiveAnalysis::new(
+        TARGET_SAMPLE_RATE,
+        config.analyzer_config(),
+        config.governor.clone(),
+        outputs.wants_beat_features(),
+    )?;
+    // Dernières secondes d'entrée, sauvegardées par POST /recording
+    live.set_recording(config.recording.clone());
+    // Tonalité du morceau (`[key]`), coûteuse sur les petites cartes
+    live.set_key_detection(config.key.clone())?;
+    // Confirmation du tempo sur une longue fenêtre avant Link et les sorties
+    live.set_verification(config.verification.clone())?;
+    // Grille des beats du set (`[beat_grid]`), réécrite pendant l'analyse
+    live.set_beat_grid(config.beat_grid.clone());
+    // Bilan du set (`[stats]`): drops, plage de tempo, temps verrouillé
+    live.set_statistics(config.stats.clone());
+    // Distribution des tempos détectés, exposée par GET /histogram
+    let mut histogram = config.tempo_histogram();
+    // Niveau d'entrée par seconde, exposé par GET /levels
+    let mut level_history = LevelHistory::new(LEVEL_INTERVAL, LEVEL_PERIOD);
+    let mut last_clip_warning: Option<Instant> = None;
 
-    // Analyseur BPM
-    let mut analyzer = BpmAnalyzer::new(TARGET_SAMPLE_RATE, None)?;
+    // Tempo, octave et gain sauvegardés ([session]): après une coupure en plein set,
+    // l'analyse reprend verrouillée au lieu d'attendre le prochain drop
+    let session_config = match config.session.validate() {
+        Ok(()) => config.session.clone(),
+        Err(e) => {
+            warn!("Section [session] invalide ({}), valeurs par défaut", e);
+            Default::default()
+        }
+    };
+    let mut session = session_config
+        .enabled
+        .then(|| SessionStore::new(&session_config));
+    if let Some(saved) = session.as_mut().and_then(|store| store.load()) {
+        info!("Session restaurée: {:?}", saved);
+        live.analyzer_mut().set_octave_preference(saved.octave);
+        if let Some(bpm) = saved.reference_bpm {
+            live.analyzer_mut().restore_reference(bpm);
+        }
+        if let Some(gain) = saved.gain {
+            match pid.restore_gain(gain, &mixer) {
+                Ok(gain) => info!("Volume de capture restauré: {}", gain),
+                Err(e) => error!("Échec de la restauration du volume: {}", e),
+            }
+        }
+        if let Ok(mut status) = device_status.lock() {
+            status.octave = saved.octave;
+            status.bpm = saved.reference_bpm;
+            status.gain = saved.gain.map(|_| pid.gain());
+        }
+        if let (Some(display_mutex), Some(bpm)) = (&bpm_display, saved.reference_bpm) {
+            if let Ok(mut guard) = display_mutex.lock() {
+                let _ = guard.show_bpm(bpm);
+            }
+        }
+    }
+    // Historique du tempo d'avant la mise à jour, plus complet que la session
+    if let Some(state) = take_checkpoint() {
+        info!(
+            "Analyseur repris après la mise à jour: {:?} BPM",
+            state.reference_bpm
+        );
+        live.analyzer_mut().restore(&state);
+    }
 
     // Bridge pour l'Audio (Sync -> Async)
-    let (audio_sender, audio_receiver) = mpsc::channel();
+    // 2 s d'audio au plus en attente: si la boucle décroche, les paquets les plus
+    // anciens sont jetés et signalés par AudioMessage::Overrun
+    let (audio_sender, audio_receiver) = audio_channel(TARGET_SAMPLE_RATE as usize * 2);
     let tx_audio = tx_main.clone();
+    let audio_recycler = audio_receiver.recycler();
 
     // Thread bridge qui convertit les messages Audio (Sync) vers AppEvent (Async)
     std::thread::spawn(move || {
@@ -108,129 +439,387 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
     });
 
     // Audio Capture
-    let mut current_hop_size = TARGET_SAMPLE_RATE as usize / 2;
-    let mut new_samples_accumulator: Vec<f32> = Vec::with_capacity(current_hop_size);
-    let _audio_capture = AudioCapture::new(
+    // Audio généré avec `--sim` (développement sans carte son)
+    let audio_capture = AudioCapture::new(
         audio_sender,
-        None,
+        simulated::cli_device(),
         TARGET_SAMPLE_RATE,
         None,
         Some(Duration::from_millis(500)),
+        config.audio_warmup(),
     )?;
 
-    println!("App initilized, start listening... (Press Ctrl+C to stop)");
+    // Watchdog nourri tant que les paquets audio sont traités
+    let progress = Progress::new();
+    if let Some(watchdog) = Watchdog::new(progress.clone(), AUDIO_STALL_TIMEOUT) {
+        tokio::spawn(watchdog.run());
+    }
+
+    info!("App initilized, start listening... (Press Ctrl+C to stop)");
+
+    let mut last_samples_at = Instant::now();
+    let mut last_result_at = Instant::now();
+    let mut last_self_check = Instant::now();
+    let mut last_link_state: Option<Instant> = None;
+    let mut last_analyzer_state: Option<Instant> = None;
+    let mut analysis_enabled = true;
+    // Fermée jusqu'au premier signal quand le gating est actif
+    let mut gate_open = !config.gate.enabled;
+    let mut shutdown_requested = false;
 
     // Boucle Principale Async (Consomme Audio + Boutons)
     while let Some(event) = rx_main.recv().await {
         if stop_flag.load(Ordering::SeqCst) {
-            println!("Arrêt demandé, sortie de la boucle.");
+            info!("Arrêt demandé, sortie de la boucle.");
             break;
         }
 
+        // POST /gate et l'entrée GPIO suivent le même chemin
+        let event = match event {
+            AppEvent::Http(HttpCommand::SetGate(open)) => AppEvent::Gate(open),
+            event => event,
+        };
+
         match event {
-            AppEvent::Button(action) => {
-                println!(">> Button Action: {:?}", action);
-                match action {
-                    ButtonAction::SinglePress => {
-                        // Action sur simple click (ex: Tap Tempo ?)
+            AppEvent::Gate(open) => {
+                if !config.gate.enabled {
+                    info!(">> Gate ignorée (section [gate] désactivée)");
+                } else if open != gate_open {
+                    info!(">> Gate: {}", if open { "ouverte" } else { "fermée" });
+                    gate_open = open;
+                    live.clear();
+                    if let Ok(mut status) = device_status.lock() {
+                        status.gate = Some(open);
+                        if !open {
+                            status.bpm = None;
+                        }
                     }
-                    ButtonAction::DoublePress => {}
-                    ButtonAction::LongPress => {
+                }
+            }
+            AppEvent::Button(action) => {
+                // Menu local: commande associée à l'appui dans la section [button]
+                let command = match action {
+                    ButtonAction::SinglePress => config.button.single_press,
+                    ButtonAction::DoublePress => config.button.double_press,
+                    ButtonAction::LongPress => config.button.long_press,
+                };
+                info!(">> Button Action: {:?} -> {:?}", action, command);
+                // Les commandes communes avec l'API suivent le même chemin
+                let http_command = match command {
+                    ButtonCommand::None => None,
+                    ButtonCommand::NextPage => {
                         if let Some(display_mutex) = &bpm_display {
-                            let mut update_in_progress = Err("Not init".into());
-                            // On tente de verrouiller le mutex sans bloquer
-                            if let Ok(mut guard) = display_mutex.try_lock() {
-                                update_in_progress = guard.update_in_progress();
-                            }
-                            match update_in_progress {
-                                Ok(_) => {
-                                    use crate::core_embedded::update::update::Updater;
-                                    let updater = Updater::new(
-                                        "kiki442002",
-                                        "rust-bpm-analyzer",
-                                        "rust-bpm-analyzer",
-                                    );
-
-                                    let is_running = Arc::new(AtomicBool::new(true));
-                                    let _ = tokio::spawn(BpmDisplay::run_update_animation(
-                                        display_mutex.clone(),
-                                        is_running.clone(),
-                                    ));
-                                    updater.check_and_update().ok();
+                            if let Ok(mut guard) = display_mutex.lock() {
+                                match guard.next_page() {
+                                    Ok(page) => info!(">> Page écran: {:?}", page),
+                                    Err(e) => error!("Erreur changement de page: {}", e),
                                 }
-                                Err(e) => eprintln!("Erreur lancement mise à jour: {}", e),
                             }
                         }
+                        None
+                    }
+                    ButtonCommand::ToggleAnalysis => {
+                        Some(HttpCommand::SetAnalysis(!analysis_enabled))
+                    }
+                    ButtonCommand::AutoGain => Some(HttpCommand::AutoGain),
+                    ButtonCommand::SaveRecording => Some(HttpCommand::SaveRecording),
+                    ButtonCommand::Update => Some(HttpCommand::Update),
+                    ButtonCommand::Shutdown => Some(HttpCommand::Shutdown),
+                };
+                if let Some(http_command) = http_command {
+                    if let Err(e) = tx_main.try_send(AppEvent::Http(http_command)) {
+                        error!("Commande bouton perdue: {}", e);
                     }
                 }
             }
+            AppEvent::Http(command) => {
+                info!(">> HTTP: {:?}", command);
+                match command {
+                    HttpCommand::SetAnalysis(enabled) => {
+                        analysis_enabled = enabled;
+                        live.clear();
+                        if let Ok(mut status) = device_status.lock() {
+                            status.analysis_enabled = enabled;
+                            if !enabled {
+                                status.bpm = None;
+                            }
+                        }
+                    }
+                    HttpCommand::SetOctave(octave) => {
+                        live.analyzer_mut().set_octave_preference(octave);
+                        if let Ok(mut status) = device_status.lock() {
+                            status.octave = octave;
+                        }
+                    }
+                    HttpCommand::SetAgcParams(params) => {
+                        setpoint = params.setpoint;
+                        pid.set_params(params.kp, params.ki, params.kd, params.window);
+                        if let Ok(mut status) = device_status.lock() {
+                            status.agc = params;
+                        }
+                    }
+                    HttpCommand::SetGate(_) => {} // Converti en AppEvent::Gate plus haut
+                    HttpCommand::AutoGain => pid.reset(),
+                    HttpCommand::SaveRecording => match live.save_recording() {
+                        Ok(path) => info!("Audio enregistré dans {:?}", path),
+                        Err(e) => error!("Échec de l'enregistrement audio: {}", e),
+                    },
+                    HttpCommand::CheckUpdate => {
+                        tokio::spawn(network::check_update(bpm_display.clone(), updater.clone()));
+                    }
+                    HttpCommand::Update => {
+                        start_update(&bpm_display, &updater, &live.analyzer().snapshot())
+                    }
+                    HttpCommand::Shutdown => {
+                        shutdown_requested = true;
+                        break;
+                    }
+                }
+            }
+            AppEvent::SelfCheckTick => {
+                if last_samples_at.elapsed() > AUDIO_STALL_TIMEOUT {
+                    error!(
+                        "[Self-check] DÉFAUT: aucun échantillon audio depuis {:?} (flux bloqué ?)",
+                        last_samples_at.elapsed()
+                    );
+                } else if last_result_at.elapsed() > SELF_CHECK_IDLE
+                    && last_self_check.elapsed() > SELF_CHECK_IDLE
+                {
+                    last_self_check = Instant::now();
+                    // Sur un analyseur à part, hors de la boucle: quelques secondes de calcul
+                    let check = live.analyzer().self_check(SELF_CHECK_BPM);
+                    tokio::spawn(async move {
+                        match tokio::task::spawn_blocking(check).await {
+                            Ok(Ok(bpm)) => info!("[Self-check] OK ({:.1} BPM)", bpm),
+                            Ok(Err(e)) => error!("[Self-check] DÉFAUT: {}", e),
+                            Err(e) => error!("[Self-check] Tâche interrompue: {}", e),
+                        }
+                    });
+                }
+            }
             AppEvent::Audio(msg) => {
                 match msg {
                     AudioMessage::Samples(packet) => {
-                        new_samples_accumulator.extend(&packet);
+                        last_samples_at = Instant::now();
+                        progress.mark();
+                        let features = live.push(&packet);
+                        // Pairs et tempo de la session (et avancement d'une mise à jour),
+                        // même analyse en pause
+                        if last_link_state.is_none_or(|at| at.elapsed() >= LINK_STATE_PERIOD) {
+                            last_link_state = Some(Instant::now());
+                            let link = LinkState {
+                                peers: outputs.link().num_peers(),
+                                tempo: outputs.link_mut().get_tempo(),
+                            };
+                            if let Ok(mut status) = device_status.lock() {
+                                status.link = link;
+                                status.update = updater.status();
+                            }
+                            if let Some(display_mutex) = &bpm_display {
+                                if let Ok(mut guard) = display_mutex.try_lock() {
+                                    let _ = guard.show_link_state(link.peers, link.tempo);
+                                }
+                            }
+                        }
+                        if let Some(gain) = pid.sync_external_change(&mixer) {
+                            info!("Volume de capture modifié hors programme: {}", gain);
+                        }
+                        if let Some(features) = features {
+                            outputs.on_beat_features(features);
+                        }
+                        outputs.tick(Instant::now());
                         match pid.update_alsa_from_slice(setpoint, &packet, &mixer) {
-                            Ok((_, rms)) => {
-                                //println!("PID output gain: {}", gain);
+                            Ok(_) => {
+                                //info!("PID output gain: {}", gain);
+                                if let Ok(mut status) = device_status.lock() {
+                                    status.gain = Some(pid.gain());
+                                }
                                 if let Some(display_mutex) = &bpm_display {
                                     // On tente de verrouiller le mutex sans bloquer
                                     if let Ok(mut guard) = display_mutex.try_lock() {
-                                        let _ = guard.update_audio_bar(rms);
+                                        let _ = guard.update_gain(pid.gain());
                                     }
                                 }
                             }
                             Err(e) => {
-                                eprintln!("PID update error: {}", e);
+                                error!("PID update error: {}", e);
                             }
                         }
 
-                        if new_samples_accumulator.len() >= current_hop_size {
-                            if let Ok(Some(result)) = analyzer.process(&new_samples_accumulator) {
-                                println!(
-                                    "BPM: {:.1} | Drop: {} | Conf: {:.2} | CoarseConf: {:.2}",
+                        if !analysis_enabled || !gate_open {
+                            live.clear();
+                            // Analyse en pause volontaire: la boucle audio tourne
+                            if let Some(check) = &health_check {
+                                check.confirm();
+                            }
+                        } else if live.hop_ready() {
+                            let processed = live.analyze();
+                            if let (Ok(_), Some(check)) = (&processed, &health_check) {
+                                check.confirm();
+                            }
+                            let confidence = match &processed {
+                                Ok(Some(result)) => Some(result.confidence),
+                                _ => None,
+                            };
+                            let mut role = tempo_follow.update(
+                                confidence,
+                                outputs.link().num_peers(),
+                                Instant::now(),
+                            );
+                            // Hors maître: écoute seule, le tempo de la session est suivi
+                            let elected = election.as_mut().map(|election| {
+                                let was_master = election.is_master();
+                                let confidence = confidence.unwrap_or(0.0);
+                                let rivals: Vec<Candidate> = discovery
+                                    .as_ref()
+                                    .map(|d| d.peers())
+                                    .unwrap_or_default()
+                                    .iter()
+                                    .filter(|peer| peer.online)
+                                    .filter_map(|peer| peer.candidate())
+                                    .collect();
+                                let master = election.update(confidence, &rivals);
+                                if let Some(discovery) = &discovery {
+                                    discovery.announce_candidate(
+                                        config.election.priority,
+                                        confidence,
+                                        master,
+                                    );
+                                }
+                                if master != was_master {
+                                    info!("Élection: maître {}", election.master().unwrap_or("-"));
+                                }
+                                master
+                            });
+                            if elected == Some(false) {
+                                role = LinkRole::Following;
+                            }
+                            let state = last_analyzer_state
+                                .is_none_or(|at| at.elapsed() >= ANALYZER_STATE_PERIOD)
+                                .then(|| {
+                                    last_analyzer_state = Some(Instant::now());
+                                    live.analyzer().snapshot()
+                                });
+                            if let Ok(mut status) = device_status.lock() {
+                                if state.is_some() {
+                                    status.state = state;
+                                }
+                                status.link_role = role;
+                                status.elected = elected;
+                            }
+                            if let Ok(Some(result)) = processed {
+                                last_result_at = Instant::now();
+                                histogram.push(result.bpm);
+                                // Tempo poussé vers la session Link, ou celui de la session
+                                // tant que la détection est incertaine (mode suiveur)
+                                // Avec `[verification]`, seul le tempo confirmé par la longue
+                                // fenêtre part vers Link et les sorties, l'écran garde
+                                // l'estimation rapide
+                                let tempo = match role {
+                                    LinkRole::Leading => result.bpm,
+                                    LinkRole::Following => outputs.link_mut().get_tempo() as f32,
+                                };
+                                let output = match role {
+                                    LinkRole::Leading => result.verified_bpm,
+                                    LinkRole::Following => Some(tempo),
+                                };
+                                // Tempo verrouillé par le suivi de tempo, pas la dernière détection
+                                if let Some(store) = &mut session {
+                                    store.update(SavedSession {
+                                        reference_bpm: live.analyzer().reference_bpm(),
+                                        octave: live.analyzer().octave_preference(),
+                                        gain: Some(pid.gain()),
+                                    });
+                                }
+                                if let Ok(mut status) = device_status.lock() {
+                                    status.bpm = Some(tempo);
+                                    status.confidence = result.confidence;
+                                    status.swing = result.swing;
+                                    status.key = result.key;
+                                    status.histogram = histogram.snapshot();
+                                }
+                                info!(
+                                    "BPM: {:.1} | Drop: {} | Conf: {:.2} | CoarseConf: {:.2} | Swing: {:?} | Key: {} | Section: {} | Link: {:?}",
                                     result.bpm,
                                     result.is_drop,
                                     result.confidence,
-                                    result.coarse_confidence
+                                    result.coarse_confidence,
+                                    result.swing,
+                                    result.key.map_or("-".to_string(), |key| key.camelot()),
+                                    result.section.name(),
+                                    role
                                 );
-                                link_manager.update_tempo(
-                                    result.bpm as f64,
-                                    result.is_drop,
-                                    result.beat_offset,
-                                );
-                                #[cfg(all(
-                                    any(target_arch = "aarch64", target_arch = "arm"),
-                                    target_os = "linux"
-                                ))]
-                                if let Some(display_mutex) = &bpm_display {
-                                    if let Ok(mut guard) = display_mutex.try_lock() {
-                                        let _ = guard.show_bpm(result.bpm);
+                                let now = Instant::now();
+                                if result.is_drop {
+                                    last_drop = Some(now);
+                                }
+                                outputs.on_result(&TempoUpdate {
+                                    shown_bpm: tempo,
+                                    bpm: output,
+                                    beat_age: live.analyzer().last_beat_age(),
+                                    leading: role == LinkRole::Leading,
+                                    is_drop: result.is_drop,
+                                    beat_offset: result.beat_offset,
+                                    confidence: result.confidence,
+                                    section: result.section,
+                                    key: result.key,
+                                });
+                                if let Some(age) = live.analyzer().last_beat_age() {
+                                    if let Ok(mut status) = device_status.lock() {
+                                        status.last_beat = Some(now - age);
                                     }
+                                    let _ = beat_tx.send(Some(BeatInfo {
+                                        bpm: tempo,
+                                        last_beat: now - age,
+                                        last_drop,
+                                    }));
                                 }
                             }
-                            new_samples_accumulator.clear();
                         }
+                        // Tampon rendu au callback de capture, sans nouvelle allocation
+                        audio_recycler.recycle(packet);
+                    }
+                    // Niveau mesuré par la capture: barre audio, ruban LED, Art-Net, GET /levels
+                    AudioMessage::Level { peak, rms } => {
+                        if peak >= CLIP_LEVEL
+                            && last_clip_warning
+                                .is_none_or(|at| at.elapsed() >= CLIP_WARNING_PERIOD)
+                        {
+                            last_clip_warning = Some(Instant::now());
+                            warn!("Saturation de l'entrée audio (crête {:.2})", peak);
+                        }
+                        let levels = level_history.push(rms).then(|| level_history.snapshot());
+                        if let Ok(mut status) = device_status.lock() {
+                            status.energy = rms;
+                            if let Some(levels) = levels {
+                                status.levels = levels;
+                                status.levels_at = level_history.last_point();
+                            }
+                        }
+                        let _ = energy_tx.send(rms);
+                        outputs.on_level(rms);
+                    }
+                    AudioMessage::Overrun { dropped_frames } => {
+                        warn!("Débordement audio: {} trames perdues", dropped_frames);
+                        METRICS.frames_dropped(dropped_frames);
+                        if let Ok(mut status) = device_status.lock() {
+                            status.dropped_frames += dropped_frames;
+                        }
+                    }
+                    AudioMessage::DeviceChanged(name) => {
+                        info!("Entrée audio active: {}", name);
                     }
                     AudioMessage::Reset => {
-                        println!("Audio stream reset. Clearing buffers...");
-                        new_samples_accumulator.clear();
+                        info!("Audio stream reset, warm-starting the analyzer");
+                        METRICS.audio_restart();
+                        if let Err(e) = live.reset() {
+                            error!("Failed to warm-start the analyzer: {}", e)
+                        }
                     }
                     AudioMessage::SampleRateChanged(rate) => {
-                        println!("Audio sample rate changed to: {} Hz", rate);
-                        match BpmAnalyzer::new(rate, None) {
-                            Ok(new_analyzer) => {
-                                analyzer = new_analyzer;
-                                current_hop_size = (rate / 2) as usize;
-                                if new_samples_accumulator.capacity() < current_hop_size {
-                                    new_samples_accumulator
-                                        .reserve(current_hop_size - new_samples_accumulator.len());
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!(
-                                    "Failed to re-initialize analyzer with rate {}: {}",
-                                    rate, e
-                                )
-                            }
+                        info!("Audio sample rate changed to: {} Hz", rate);
+                        if let Err(e) = live.set_sample_rate(rate) {
+                            error!("Failed to re-initialize analyzer with rate {}: {}", rate, e)
                         }
                     }
                 }
@@ -238,5 +827,128 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    // Dernier état connu, même si l'intervalle de sauvegarde n'est pas écoulé
+    if let Some(store) = &mut session {
+        store.flush();
+    }
+    if let Some(Err(e)) = live.save_beat_grid() {
+        warn!("Échec de l'écriture de la grille des beats: {}", e);
+    }
+    if let Some(Err(e)) = live.save_statistics() {
+        warn!("Échec de l'écriture des statistiques du set: {}", e);
+    }
+
+    if shutdown_requested {
+        // Quitte la session Link avant de couper
+        outputs.link_mut().link_state(false);
+        safe_shutdown(discovery.as_ref(), &hostname, &bpm_display, audio_capture);
+    }
+
+    Ok(())
+}
+
+// Arrêt propre (bouton ou POST /shutdown): évite de corrompre la carte SD
+// lors d'une coupure d'alimentation franche
+fn safe_shutdown(
+    discovery: Option<&Discovery>,
+    hostname: &str,
+    bpm_display: &Option<SharedDisplay>,
+    audio_capture: AudioCapture,
+) {
+    info!("Arrêt de l'appareil...");
+    // 1. Annonce hors ligne (goodbye mDNS) pour l'application desktop
+    if let Some(discovery) = discovery {
+        if let Err(e) = discovery.withdraw(hostname) {
+            error!("Erreur retrait mDNS: {}", e);
+        }
+    }
+    // 2. Écran d'au revoir
+    if let Some(display_mutex) = bpm_display {
+        if let Ok(mut guard) = display_mutex.lock() {
+            let _ = guard.show_goodbye();
+        }
+    }
+    // 3. Arrêt du flux audio
+    drop(audio_capture);
+    // 4. Écriture des caches sur la carte SD
+    unsafe { libc::sync() };
+    // 5. Extinction
+    match std::process::Command::new("systemctl")
+        .arg("poweroff")
+        .status()
+    {
+        Ok(status) if status.success() => {}
+        Ok(status) => error!("systemctl poweroff a échoué: {}", status),
+        Err(e) => error!("Impossible de lancer systemctl poweroff: {}", e),
+    }
+}
+
+// Lance la mise à jour GitHub (bouton ou POST /update) sans bloquer la boucle audio.
+// L'avancement est publié dans GET /status et GET /update. `checkpoint` est repris
+// au redémarrage sur la nouvelle version.
+fn start_update(
+    bpm_display: &Option<SharedDisplay>,
+    updater: &Updater,
+    checkpoint: &AnalyzerState,
+) {
+    if updater.status().busy() {
+        warn!("Mise à jour déjà en cours");
+        return;
+    }
+    if let Err(e) = write_checkpoint(checkpoint) {
+        warn!("Échec de l'écriture de l'état de l'analyseur: {}", e);
+    }
+    let is_running = Arc::new(AtomicBool::new(true));
+    if let Some(display_mutex) = bpm_display {
+        let mut update_in_progress = Err("Not init".into());
+        // On tente de verrouiller le mutex sans bloquer
+        if let Ok(mut guard) = display_mutex.try_lock() {
+            update_in_progress = guard.update_in_progress();
+        }
+        if let Err(e) = update_in_progress {
+            warn!("Erreur lancement mise à jour: {}", e);
+            return;
+        }
+        let _ = tokio::spawn(display::run_update_animation(
+            display_mutex.clone(),
+            is_running.clone(),
+        ));
+    }
+    let updater = updater.clone();
+    tokio::task::spawn_blocking(move || {
+        // En cas de succès le processus redémarre sur la nouvelle version
+        if let Err(e) = updater.check_and_update() {
+            error!("Échec de la mise à jour: {}", e);
+            // Pas de redémarrage: l'analyse continue sans reprise
+            let _ = std::fs::remove_file(CHECKPOINT_FILE);
+        }
+        is_running.store(false, Ordering::Relaxed);
+    });
+}
+
+fn write_checkpoint(state: &AnalyzerState) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(CHECKPOINT_FILE, serde_json::to_string(state)?)?;
     Ok(())
 }
+
+// État laissé par une mise à jour, effacé à la lecture: il ne sert qu'une fois
+fn take_checkpoint() -> Option<AnalyzerState> {
+    let age = std::fs::metadata(CHECKPOINT_FILE)
+        .and_then(|metadata| metadata.modified())
+        .ok()?
+        .elapsed()
+        .unwrap_or_default();
+    let content = std::fs::read_to_string(CHECKPOINT_FILE);
+    let _ = std::fs::remove_file(CHECKPOINT_FILE);
+    if age > CHECKPOINT_MAX_AGE {
+        info!("État de l'analyseur d'il y a {} s ignoré", age.as_secs());
+        return None;
+    }
+    match serde_json::from_str(&content.ok()?) {
+        Ok(state) => Some(state),
+        Err(e) => {
+            warn!("État de l'analyseur {} illisible: {}", CHECKPOINT_FILE, e);
+            None
+        }
+    }
+}
@@ -1,26 +1,100 @@
 use iced::alignment::Horizontal;
-use iced::widget::{button, column, container, pick_list, row, text};
-use iced::{Color, Element, Length, Subscription, Task, Theme};
+use iced::keyboard::{self, Key, key::Named};
+use iced::theme::Palette;
+use iced::widget::canvas::{self, Canvas, Frame, Geometry, Path, Stroke};
+use iced::widget::{
+    button, checkbox, column, container, pick_list, row, scrollable, slider, text, text_input,
+};
+use iced::{
+    Color, Element, Length, Point, Rectangle, Renderer, Subscription, Task, Theme, mouse, window,
+};
+use std::path::PathBuf;
 use std::sync::mpsc;
 use std::thread;
 use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
 
-use crate::core_bpm::{AudioCapture, AudioMessage, BpmAnalyzer};
-use crate::midi::{MidiEvent, MidiManager};
-use crate::network_sync::LinkManager;
+use crate::diagnostics::{self, DiagnosticsReport};
 use crate::platform::TARGET_SAMPLE_RATE;
+use crate::preferences::{AnalysisPreferences, Preferences};
+#[cfg(feature = "tray")]
+use crate::tray::{self, Tray, TrayAction, TrayState};
+use rust_bpm_analyzer::config::{AppConfig, GateConfig};
+use rust_bpm_analyzer::core_bpm::analyzer::{
+    ConfidenceThreshold, NOISE_GATE, OctavePreference, TempoEstimator,
+};
+use rust_bpm_analyzer::core_bpm::audio::{AudioMonitor, SYSTEM_AUDIO_DEVICE};
+use rust_bpm_analyzer::core_bpm::audio_channel::RecvTimeoutError;
+use rust_bpm_analyzer::core_bpm::histogram::HistogramSnapshot;
+use rust_bpm_analyzer::core_bpm::key::Key as MusicalKey;
+use rust_bpm_analyzer::core_bpm::meter::CLIP_LEVEL;
+use rust_bpm_analyzer::core_bpm::preset::AnalyzerPreset;
+use rust_bpm_analyzer::core_bpm::set_stats::SetReport;
+use rust_bpm_analyzer::core_bpm::simulated;
+use rust_bpm_analyzer::core_bpm::{
+    AnalysisDebug, AudioCapture, AudioMessage, EnvelopeFrame, LiveAnalysis, audio_channel,
+};
+use rust_bpm_analyzer::metrics::{self, METRICS};
+use rust_bpm_analyzer::midi::{MidiEvent, MidiManager, MidiPortEvent};
+use rust_bpm_analyzer::midi_mapping::{MidiAction, MidiMapping};
+use rust_bpm_analyzer::network_sync::LinkManager;
+use rust_bpm_analyzer::network_sync::clock_sync::phase_offset;
+use rust_bpm_analyzer::network_sync::device_api::{
+    DeviceUpdates, StaticPeers, UpdateCommand, UpdatePhase,
+};
+use rust_bpm_analyzer::network_sync::discovery::{DiscoveredPeer, Discovery};
+use rust_bpm_analyzer::network_sync::follow::{LinkRole, TempoFollow};
+use rust_bpm_analyzer::outputs::{SinkRegistry, TempoUpdate};
+
+// Fade-out of the performance display beat flash
+const PERFORMANCE_FLASH: Duration = Duration::from_millis(150);
+// Strength of the background flash, full window in performance mode, subtle otherwise
+const PERFORMANCE_FLASH_STRENGTH: f32 = 0.6;
+const MAIN_FLASH_STRENGTH: f32 = 0.25;
+// Time the clip warning stays on after the last clipped peak
+const CLIP_HOLD: Duration = Duration::from_secs(2);
+// Devices dashboard: tiles per row and tile width
+const DASHBOARD_COLUMNS: usize = 3;
+const DASHBOARD_TILE_WIDTH: f32 = 220.0;
+// Span of the dashboard level timeline, the history kept by the devices
+const DASHBOARD_TIMELINE: Duration = Duration::from_secs(300);
+// Link quantums offered in the settings
+const LINK_QUANTUMS: [u32; 4] = [1, 2, 4, 8];
+// Age after which the confidence badge goes back to "no lock"
+const CONFIDENCE_HOLD: Duration = Duration::from_secs(3);
+// Confidence above the fine threshold shown as a solid lock
+const STRONG_CONFIDENCE_MARGIN: f32 = 0.2;
+// Manual override range, the tempo range of a Link session
+const MANUAL_BPM: std::ops::RangeInclusive<f32> = 20.0..=999.0;
 
 #[derive(Debug, Clone)]
 pub struct GuiUpdate {
     pub bpm: Option<f32>,
     pub num_peers: usize,
-}
-
-#[derive(Debug, Clone)]
-struct MidiMapping {
-    channel: u8,
-    note_or_cc: u8,
-    is_note: bool,
+    pub link_role: LinkRole,
+    // Session (beat, tempo) while Link is on, the GUI moves the phase on from there
+    pub link_beat: Option<(f64, f64)>,
+    pub envelope: Option<EnvelopeFrame>,
+    pub debug: Option<AnalysisDebug>,
+    pub is_drop: bool,
+    // Musical key with `[key] enabled`
+    pub key: Option<MusicalKey>,
+    // Input (peak, rms) since the last update
+    pub level: Option<(f32, f32)>,
+    // Last beat of the detected grid, for the performance display flash
+    pub last_beat: Option<Instant>,
+    pub live_device: Option<String>,
+    pub histogram: Option<HistogramSnapshot>,
+    // Outcome of a command worth showing to the user
+    pub notice: Option<String>,
+    // Analyzer settings in use, sent at start and when they change
+    pub analysis: Option<AnalysisPreferences>,
+    // (fine, coarse) confidence of the detection of this hop
+    pub confidence: Option<(f32, f32)>,
+    // Level of the analysis window, see `BpmAnalyzer::input_level`
+    pub energy: Option<f32>,
+    // Drops, tempo range and time locked since the start of the set
+    pub stats: Option<SetReport>,
 }
 
 #[derive(Debug, Clone)]
@@ -28,16 +102,38 @@ pub enum GuiCommand {
     SetDetection(bool),
     SetDevice(Option<String>),
     SetBpm(f64),
+    // Tempo pinned for Link, Art-Net and MIDI, `None` back to the analyzer
+    SetOverride(Option<f32>),
+    SetOctave(OctavePreference),
+    // Confidence thresholds tuned live, without restarting the analysis
+    SetThresholds(ConfidenceThreshold),
+    SetAnalysis(AnalysisPreferences),
+    SetLink(bool),
+    SetLinkFollow(bool),
+    SetLinkQuantum(u32),
+    SetGate(bool),
+    ImportPreset(PathBuf),
+    ExportPreset(PathBuf),
+    ExportDiagnostics(Box<DiagnosticsReport>),
+    DumpState,
+    SaveRecording,
+    // Set statistics written to `[stats] directory` now
+    SaveStats,
+    // New set: statistics start over (and go to a new file)
+    ResetStats,
 }
 
 pub fn run() -> Result<(), Box<dyn std::error::Error>> {
     let window_settings = iced::window::Settings {
-        size: iced::Size::new(350.0, 400.0),
+        size: iced::Size::new(350.0, 640.0),
+        // Closing hides the window in the tray instead of quitting
+        #[cfg(feature = "tray")]
+        exit_on_close_request: false,
         ..Default::default()
     };
 
     iced::application("Rust BPM Analyzer", BpmApp::update, BpmApp::view)
-        .theme(|_| Theme::Dracula)
+        .theme(BpmApp::theme)
         .subscription(BpmApp::subscription)
         .window(window_settings)
         .run_with(BpmApp::new)?;
@@ -46,10 +142,72 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
 
 struct BpmApp {
     bpm: Option<f32>,
+    key: Option<MusicalKey>,
+    // Input meter: last (peak, rms) and last clipped peak
+    input_level: Option<(f32, f32)>,
+    last_clip: Option<Instant>,
+    // Last detection (fine, coarse confidence, when) and analysis window level
+    confidence: Option<(f32, f32, Instant)>,
+    energy: Option<f32>,
     num_peers: usize,
+    // Whether our tempo leads the Link session or the session tempo is adopted
+    link_role: LinkRole,
+    link_follow: bool,
+    link_quantum: u32,
+    // Last session (beat, tempo) received and when
+    link_beat: Option<(f64, f64, Instant)>,
     is_enabled: bool,
     input_device: Option<String>,
     available_devices: Vec<String>,
+    // Input actually captured (the default one after a failover)
+    live_device: Option<String>,
+
+    // Envelope view
+    envelope: EnvelopeFrame,
+    last_drop: Option<Instant>,
+    last_beat: Option<Instant>,
+
+    // Correlation debug panel
+    show_correlation: bool,
+    correlation: AnalysisDebug,
+    histogram: HistogramSnapshot,
+
+    // Octave bias of the analyzer, kept for the whole session
+    octave: OctavePreference,
+
+    // Analyzer preset file for import/export
+    preset_path: String,
+
+    // Analyzers found over mDNS
+    discovery: Option<Discovery>,
+    // `[discovery] static_peers`, polled over unicast while mDNS finds nothing
+    static_peers: StaticPeers,
+    devices: Vec<DiscoveredPeer>,
+    // Version, self-update, status and input level of the devices, polled while
+    // the About screen or the devices dashboard is open
+    device_updates: DeviceUpdates,
+
+    // About screen
+    show_about: bool,
+    // Tile per device on the network, a venue-wide monitor
+    show_dashboard: bool,
+    // Full-window BPM readout (F11)
+    show_performance: bool,
+    // Set statistics screen, with the last figures of the analysis thread
+    show_stats: bool,
+    set_stats: Option<SetReport>,
+    notice: Option<String>,
+
+    // Settings screen, saved to disk on every change
+    show_settings: bool,
+    preferences: Preferences,
+    analysis: Option<AnalysisPreferences>,
+    min_bpm_input: String,
+    max_bpm_input: String,
+    fine_confidence_input: String,
+    coarse_confidence_input: String,
+    accent_input: String,
+    settings_error: Option<String>,
 
     // Receiver to get updates from the analysis thread
     receiver: std::sync::Arc<std::sync::Mutex<mpsc::Receiver<GuiUpdate>>>,
@@ -58,11 +216,26 @@ struct BpmApp {
 
     // TAP system
     tap_times: Vec<Instant>,
+    // Manual tempo sent to the outputs instead of the detected one, and its entry
+    manual_bpm: Option<f32>,
+    manual_input: String,
 
     // MIDI
     midi_manager: Option<std::sync::Arc<std::sync::Mutex<MidiManager>>>,
-    midi_learn: bool,
-    tap_midi_mapping: Option<MidiMapping>,
+    // Connected MIDI input port, followed across unplugs
+    midi_port: Option<String>,
+    // Action whose control MIDI Learn is waiting for
+    midi_learn: Option<MidiAction>,
+
+    // External gate (MIDI note held), analysis paused while closed
+    gate: GateConfig,
+    gate_open: bool,
+
+    // System tray, created on the first frame (None if unavailable)
+    #[cfg(feature = "tray")]
+    tray: Option<Tray>,
+    #[cfg(feature = "tray")]
+    tray_started: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -71,7 +244,56 @@ enum Message {
     ToggleDetection,
     DeviceSelected(String),
     Tap,
-    ToggleMidiLearn,
+    // Manual override: nudge by a step, entry field, back to the detected tempo
+    NudgeBpm(f32),
+    ManualBpmChanged(String),
+    SetManualBpm(f32),
+    ReturnToAuto,
+    // MIDI Learn of the control of an action (again to cancel)
+    LearnMidi(MidiAction),
+    ToggleCorrelation,
+    CycleOctave,
+    PresetPathChanged(String),
+    ImportPreset,
+    ExportPreset,
+    ToggleAbout,
+    ToggleDashboard,
+    ToggleStats,
+    SaveStats,
+    ResetStats,
+    // Start or stop the detection of one device, or of every online one
+    DeviceAnalysis(String, bool),
+    AllDevicesAnalysis(bool),
+    TogglePerformance,
+    ExitPerformance,
+    SetPerformanceFullscreen(bool),
+    SetAlwaysOnTop(bool),
+    ExportDiagnostics,
+    DumpState,
+    SaveRecording,
+    DeviceUpdate(String, UpdateCommand),
+    ToggleSettings,
+    MinBpmChanged(String),
+    MaxBpmChanged(String),
+    FineConfidenceChanged(String),
+    CoarseConfidenceChanged(String),
+    // Threshold sliders, applied while dragging and saved on release
+    FineThresholdChanged(f32),
+    CoarseThresholdChanged(f32),
+    SaveThresholds,
+    ApplyAnalysis,
+    ToggleLink(bool),
+    ToggleLinkFollow(bool),
+    LinkQuantumSelected(u32),
+    ThemeSelected(Theme),
+    AccentChanged(String),
+    ToggleBackgroundFlash(bool),
+    ClearMidiMapping(MidiAction),
+    CalibrateDevices,
+    #[cfg(feature = "tray")]
+    Tray(TrayAction),
+    #[cfg(feature = "tray")]
+    CloseRequested(iced::window::Id),
 }
 
 impl BpmApp {
@@ -79,36 +301,122 @@ impl BpmApp {
         let (tx_results, rx_results) = mpsc::channel();
         let (tx_commands, rx_commands) = mpsc::channel();
 
+        let mut preferences = Preferences::load();
+        let config = AppConfig::load();
+        let gate = config.gate.clone();
+        let link_follow = preferences.link_follow.unwrap_or(config.link.follow);
+        let link_quantum = preferences.link_quantum.unwrap_or(config.link.quantum);
+        let discovery_config = config.discovery.clone();
+        let static_peers = StaticPeers::new(&config.discovery.static_peers);
+        let device_updates = DeviceUpdates::new(config.api.key.clone());
+        if config.metrics.enabled {
+            metrics::serve(config.metrics.port);
+        }
+
         // Fetch available devices
         let available_devices = AudioCapture::list_devices().unwrap_or_default();
-        let default_device =
-            AudioCapture::default_device_name().or_else(|| available_devices.first().cloned());
+        // The saved device is only kept while it is plugged in
+        if preferences
+            .device
+            .as_ref()
+            .is_some_and(|d| !available_devices.contains(d))
+        {
+            preferences.device = None;
+        }
+        // `--sim` only applies to this run, the saved device is left alone
+        let simulated_device = simulated::cli_device();
+        let default_device = simulated_device
+            .clone()
+            .or_else(|| preferences.device.clone())
+            .or_else(AudioCapture::default_device_name)
+            .or_else(|| available_devices.first().cloned());
 
         // Spawn the analysis thread
+        let mut thread_preferences = preferences.clone();
+        if simulated_device.is_some() {
+            thread_preferences.device = simulated_device;
+        }
         thread::spawn(move || {
-            if let Err(e) = run_analysis_loop(tx_results, rx_commands) {
-                eprintln!("Analysis loop error: {}", e);
+            if let Err(e) = run_analysis_loop(tx_results, rx_commands, config, thread_preferences) {
+                error!("Analysis loop error: {}", e);
             }
         });
 
         // Initialize MIDI Manager
-        let midi_manager = MidiManager::new()
-            .ok()
-            .map(|m| std::sync::Arc::new(std::sync::Mutex::new(m)));
+        let midi_manager = MidiManager::new().ok();
+        let midi_port = midi_manager
+            .as_ref()
+            .and_then(|m| m.input_port().map(str::to_string));
+        let midi_manager = midi_manager.map(|m| std::sync::Arc::new(std::sync::Mutex::new(m)));
+
+        // Browse for analyzers on the network
+        let discovery = match Discovery::new(&discovery_config).and_then(|d| d.browse().map(|_| d))
+        {
+            Ok(d) => Some(d),
+            Err(e) => {
+                error!("mDNS discovery unavailable: {}", e);
+                None
+            }
+        };
 
         (
             Self {
                 bpm: None,
+                key: None,
+                input_level: None,
+                last_clip: None,
+                confidence: None,
+                energy: None,
                 num_peers: 0,
+                link_role: LinkRole::Leading,
+                link_follow,
+                link_quantum,
+                link_beat: None,
                 is_enabled: false,
                 receiver: std::sync::Arc::new(std::sync::Mutex::new(rx_results)),
                 sender: tx_commands,
                 input_device: default_device,
                 available_devices,
+                live_device: None,
+                envelope: EnvelopeFrame::default(),
+                last_drop: None,
+                last_beat: None,
+                show_correlation: false,
+                correlation: AnalysisDebug::default(),
+                histogram: HistogramSnapshot::default(),
+                octave: preferences.octave,
+                preset_path: "preset.toml".to_string(),
+                discovery,
+                static_peers,
+                devices: Vec::new(),
+                device_updates,
+                show_about: false,
+                show_dashboard: false,
+                show_performance: false,
+                show_stats: false,
+                set_stats: None,
+                notice: None,
+                show_settings: false,
+                analysis: None,
+                min_bpm_input: String::new(),
+                max_bpm_input: String::new(),
+                fine_confidence_input: String::new(),
+                coarse_confidence_input: String::new(),
+                accent_input: preferences.accent.clone().unwrap_or_default(),
+                settings_error: None,
                 tap_times: Vec::new(),
+                manual_bpm: None,
+                manual_input: String::new(),
                 midi_manager,
-                midi_learn: false,
-                tap_midi_mapping: None,
+                midi_port,
+                midi_learn: None,
+                preferences,
+                gate_open: !gate.enabled,
+                gate,
+                #[cfg(feature = "tray")]
+                tray: None,
+                #[cfg(feature = "tray")]
+                tray_started: false,
             },
             Task::none(),
         )
@@ -118,99 +426,330 @@ impl BpmApp {
         match message {
             Message::Tick => {
                 // Poll all available messages
+                let mut refresh_form = false;
                 if let Ok(rx) = self.receiver.lock() {
                     while let Ok(result) = rx.try_recv() {
                         self.bpm = result.bpm;
+                        if result.key.is_some() {
+                            self.key = result.key;
+                        }
+                        if let Some((peak, rms)) = result.level {
+                            self.input_level = Some((peak, rms));
+                            if peak >= CLIP_LEVEL {
+                                self.last_clip = Some(Instant::now());
+                            }
+                        }
+                        if let Some((fine, coarse)) = result.confidence {
+                            self.confidence = Some((fine, coarse, Instant::now()));
+                        }
+                        if result.energy.is_some() {
+                            self.energy = result.energy;
+                        }
+                        if result.stats.is_some() {
+                            self.set_stats = result.stats;
+                        }
                         self.num_peers = result.num_peers;
+                        self.link_role = result.link_role;
+                        self.link_beat = result
+                            .link_beat
+                            .map(|(beat, tempo)| (beat, tempo, Instant::now()));
+                        if result.live_device.is_some() {
+                            self.live_device = result.live_device;
+                        }
+                        if let Some(envelope) = result.envelope {
+                            self.envelope = envelope;
+                        }
+                        if let Some(debug) = result.debug {
+                            self.correlation = debug;
+                        }
+                        if let Some(histogram) = result.histogram {
+                            self.histogram = histogram;
+                        }
+                        if result.notice.is_some() {
+                            self.notice = result.notice;
+                        }
+                        if let Some(analysis) = result.analysis {
+                            self.analysis = Some(analysis);
+                            refresh_form = !self.show_settings;
+                        }
+                        if result.is_drop {
+                            self.last_drop = Some(Instant::now());
+                        }
+                        if result.last_beat.is_some() {
+                            self.last_beat = result.last_beat;
+                        }
                     }
                 }
 
-                let mut should_tap = false;
+                if refresh_form {
+                    self.reset_settings_form();
+                }
+
+                let mut devices = self
+                    .discovery
+                    .as_ref()
+                    .map(Discovery::peers)
+                    .unwrap_or_default();
+                // Multicast blocked by the venue network: unicast to the configured devices
+                if !devices.iter().any(|d| d.online) && !self.static_peers.is_empty() {
+                    self.static_peers.poll();
+                    devices = self.static_peers.peers();
+                }
+                self.devices = devices;
+                if self.show_about || self.show_dashboard {
+                    self.device_updates.poll(&self.devices);
+                }
+
+                #[cfg(feature = "tray")]
+                self.update_tray();
 
-                // Poll MIDI events
+                // Poll MIDI events, the actions run once the MIDI lock is released
+                let mut actions = Vec::new();
                 if let Some(midi_mutex) = &self.midi_manager {
                     if let Ok(mut midi) = midi_mutex.lock() {
-                        while let Ok(event) = midi.try_recv() {
-                            if self.midi_learn {
-                                match event {
-                                    MidiEvent::NoteOn {
-                                        channel,
-                                        note,
-                                        velocity: _,
-                                    } => {
-                                        self.tap_midi_mapping = Some(MidiMapping {
-                                            channel,
-                                            note_or_cc: note,
-                                            is_note: true,
-                                        });
-                                        self.midi_learn = false;
-                                        println!(
-                                            "MIDI Learn: Note {} on Channel {}",
-                                            note, channel
-                                        );
-                                        // APC Mini Feedback: Channel 6 (which is index 6 on APC, typically mapped as channel 6 in DAW, here it's 0-indexed in code usually)
-                                        // Actually midi channels in code are 0-15. So channel 1 in MIDI is 0.
-                                        // User asked for "channel 6 brightness 100% and velocity 3 for white".
-                                        // Assuming 0-indexed, channel 6 is 6. If user means MIDI Channel 7 (labelled 1-16), it's 6.
-                                        // For APC Mini Mk2 often Note On Ch 6 with Velocity determines color/brightness.
-                                        midi.send_note_on(6, note, 3);
-                                    }
-                                    MidiEvent::ControlChange {
-                                        channel,
-                                        controller,
-                                        value: _,
-                                    } => {
-                                        self.tap_midi_mapping = Some(MidiMapping {
-                                            channel,
-                                            note_or_cc: controller,
-                                            is_note: false,
-                                        });
-                                        self.midi_learn = false;
-                                        println!(
-                                            "MIDI Learn: CC {} on Channel {}",
-                                            controller, channel
-                                        );
-                                        // APC feedback for CC or buttons mapped via CC:
-                                        // Use channel 6 (index) and value 3
-                                        midi.send_control_change(6, controller, 3);
-                                    }
+                        // Controller unplugged or plugged again mid-set
+                        for port_event in midi.rescan() {
+                            match port_event {
+                                MidiPortEvent::InputConnected(name) => {
+                                    self.notice =
+                                        Some(format!("MIDI controller connected: {}", name));
+                                    self.midi_port = Some(name);
                                 }
-                            } else if let Some(mapping) = &self.tap_midi_mapping {
-                                let is_match = match event {
-                                    MidiEvent::NoteOn {
-                                        channel,
-                                        note,
-                                        velocity: _,
-                                    } => {
-                                        mapping.is_note
-                                            && mapping.channel == channel
-                                            && mapping.note_or_cc == note
-                                    }
-                                    MidiEvent::ControlChange {
-                                        channel,
-                                        controller,
-                                        value: _,
-                                    } => {
-                                        !mapping.is_note
-                                            && mapping.channel == channel
-                                            && mapping.note_or_cc == controller
+                                MidiPortEvent::InputLost(name) => {
+                                    self.notice = Some(format!("MIDI controller lost: {}", name));
+                                    self.midi_port = None;
+                                }
+                                // Pads of a replugged controller are dark again
+                                MidiPortEvent::OutputConnected(_) => {
+                                    for action in MidiAction::ALL {
+                                        if let Some(mapping) = self.preferences.midi.get(action) {
+                                            light_pad(&mut midi, mapping);
+                                        }
                                     }
-                                };
-
-                                if is_match {
-                                    should_tap = true;
                                 }
+                                MidiPortEvent::OutputLost(_) => {}
+                            }
+                        }
+                        while let Ok(event) = midi.try_recv() {
+                            if let Some(open) = self.gate_event(&event) {
+                                if open != self.gate_open {
+                                    self.gate_open = open;
+                                    info!("Gate {}", if open { "open" } else { "closed" });
+                                    let _ = self.sender.send(GuiCommand::SetGate(open));
+                                }
+                            } else if let Some(action) = self.midi_learn {
+                                let Some(mapping) = self.preferences.midi.learn(action, &event)
+                                else {
+                                    continue;
+                                };
+                                self.midi_learn = None;
+                                self.preferences.save();
+                                info!("MIDI Learn: {} -> {}", mapping, action);
+                                light_pad(&mut midi, mapping);
+                            } else if let Some(action) = self.preferences.midi.action(&event) {
+                                actions.push(action);
                             }
                         }
                     }
                 }
 
-                if should_tap {
-                    return self.update(Message::Tap);
+                if !actions.is_empty() {
+                    let tasks: Vec<Task<Message>> = actions
+                        .into_iter()
+                        .map(|action| self.update(midi_message(action)))
+                        .collect();
+                    return Task::batch(tasks);
+                }
+            }
+            Message::LearnMidi(action) => {
+                // A second press cancels
+                self.midi_learn = (self.midi_learn != Some(action)).then_some(action);
+            }
+            Message::ToggleCorrelation => {
+                self.show_correlation = !self.show_correlation;
+            }
+            Message::CycleOctave => {
+                self.octave = match self.octave {
+                    OctavePreference::Normal => OctavePreference::Double,
+                    OctavePreference::Double => OctavePreference::Half,
+                    OctavePreference::Half => OctavePreference::Normal,
+                };
+                let _ = self.sender.send(GuiCommand::SetOctave(self.octave));
+                self.preferences.octave = self.octave;
+                self.preferences.save();
+            }
+            Message::ToggleSettings => {
+                self.show_settings = !self.show_settings;
+                self.settings_error = None;
+                self.reset_settings_form();
+            }
+            Message::MinBpmChanged(value) => self.min_bpm_input = value,
+            Message::MaxBpmChanged(value) => self.max_bpm_input = value,
+            Message::FineConfidenceChanged(value) => self.fine_confidence_input = value,
+            Message::CoarseConfidenceChanged(value) => self.coarse_confidence_input = value,
+            Message::FineThresholdChanged(value) => {
+                self.adjust_thresholds(|analysis| analysis.fine_confidence = value)
+            }
+            Message::CoarseThresholdChanged(value) => {
+                self.adjust_thresholds(|analysis| analysis.coarse_confidence = value)
+            }
+            Message::SaveThresholds => {
+                self.preferences.analysis = self.analysis;
+                self.preferences.save();
+            }
+            Message::ApplyAnalysis => match self.parse_settings_form() {
+                Ok(analysis) => {
+                    self.settings_error = None;
+                    self.analysis = Some(analysis);
+                    self.preferences.analysis = Some(analysis);
+                    self.preferences.save();
+                    let _ = self.sender.send(GuiCommand::SetAnalysis(analysis));
+                }
+                Err(e) => self.settings_error = Some(e),
+            },
+            Message::ToggleLink(enabled) => {
+                self.preferences.link_enabled = enabled;
+                self.preferences.save();
+                let _ = self.sender.send(GuiCommand::SetLink(enabled));
+            }
+            Message::ToggleLinkFollow(follow) => {
+                self.link_follow = follow;
+                self.preferences.link_follow = Some(follow);
+                self.preferences.save();
+                let _ = self.sender.send(GuiCommand::SetLinkFollow(follow));
+            }
+            Message::LinkQuantumSelected(quantum) => {
+                self.link_quantum = quantum;
+                self.preferences.link_quantum = Some(quantum);
+                self.preferences.save();
+                let _ = self.sender.send(GuiCommand::SetLinkQuantum(quantum));
+            }
+            Message::ThemeSelected(theme) => {
+                self.preferences.theme = theme.to_string();
+                self.preferences.save();
+            }
+            Message::AccentChanged(value) => {
+                // Applied as soon as the input is empty (theme default) or a valid color
+                let accent = value.trim();
+                if accent.is_empty() || Color::parse(accent).is_some() {
+                    self.preferences.accent = (!accent.is_empty()).then(|| accent.to_string());
+                    self.preferences.save();
+                }
+                self.accent_input = value;
+            }
+            Message::ToggleBackgroundFlash(enabled) => {
+                self.preferences.background_flash = enabled;
+                self.preferences.save();
+            }
+            Message::ClearMidiMapping(action) => {
+                self.preferences.midi.clear(action);
+                self.preferences.save();
+            }
+            Message::CalibrateDevices => {
+                // Gain calibration runs on the devices, the desktop input has no
+                // capture gain to calibrate
+                let online: Vec<&DiscoveredPeer> =
+                    self.devices.iter().filter(|peer| peer.online).collect();
+                for peer in &online {
+                    self.device_updates.auto_gain(peer);
+                }
+                info!("Gain calibration sent to {} device(s)", online.len());
+                self.notice = Some(format!(
+                    "Gain calibration started on {} device(s)",
+                    online.len()
+                ));
+            }
+            #[cfg(feature = "tray")]
+            Message::Tray(action) => return self.handle_tray_action(action),
+            #[cfg(feature = "tray")]
+            Message::CloseRequested(id) => {
+                if self.tray.is_some() {
+                    return iced::window::change_mode(id, iced::window::Mode::Hidden);
+                }
+                return iced::exit();
+            }
+            Message::ToggleAbout => {
+                self.show_about = !self.show_about;
+            }
+            Message::ToggleDashboard => {
+                self.show_dashboard = !self.show_dashboard;
+            }
+            Message::ToggleStats => {
+                self.show_stats = !self.show_stats;
+            }
+            Message::SaveStats => {
+                let _ = self.sender.send(GuiCommand::SaveStats);
+            }
+            Message::ResetStats => {
+                let _ = self.sender.send(GuiCommand::ResetStats);
+            }
+            Message::DeviceAnalysis(name, enabled) => {
+                if let Some(peer) = self.devices.iter().find(|d| d.name == name) {
+                    self.device_updates.set_analysis(peer, enabled);
+                }
+            }
+            Message::AllDevicesAnalysis(enabled) => {
+                for peer in self.devices.iter().filter(|d| d.online) {
+                    self.device_updates.set_analysis(peer, enabled);
+                }
+            }
+            Message::TogglePerformance => {
+                self.show_performance = !self.show_performance;
+                return self.apply_window_options();
+            }
+            Message::ExitPerformance => {
+                if self.show_performance {
+                    return self.update(Message::TogglePerformance);
+                }
+            }
+            Message::SetPerformanceFullscreen(enabled) => {
+                self.preferences.performance_fullscreen = enabled;
+                self.preferences.save();
+                return self.apply_window_options();
+            }
+            Message::SetAlwaysOnTop(enabled) => {
+                self.preferences.always_on_top = enabled;
+                self.preferences.save();
+                return self.apply_window_options();
+            }
+            Message::ExportDiagnostics => {
+                let report = DiagnosticsReport {
+                    input_device: self.input_device.clone(),
+                    live_device: self.live_device.clone(),
+                    available_devices: self.available_devices.clone(),
+                    detection_enabled: self.is_enabled,
+                    bpm: self.bpm,
+                    link_peers: self.num_peers,
+                    network_devices: self.devices.iter().map(|d| d.name.clone()).collect(),
+                    histogram: self.histogram.clone(),
+                    ..DiagnosticsReport::new()
+                };
+                self.notice = Some("Exporting diagnostics...".to_string());
+                let _ = self
+                    .sender
+                    .send(GuiCommand::ExportDiagnostics(Box::new(report)));
+            }
+            Message::DumpState => {
+                let _ = self.sender.send(GuiCommand::DumpState);
+            }
+            Message::SaveRecording => {
+                let _ = self.sender.send(GuiCommand::SaveRecording);
+            }
+            Message::DeviceUpdate(name, command) => {
+                if let Some(peer) = self.devices.iter().find(|d| d.name == name) {
+                    self.device_updates.send(peer, command);
                 }
             }
-            Message::ToggleMidiLearn => {
-                self.midi_learn = !self.midi_learn;
+            Message::PresetPathChanged(path) => {
+                self.preset_path = path;
+            }
+            Message::ImportPreset => {
+                let path = PathBuf::from(self.preset_path.trim());
+                let _ = self.sender.send(GuiCommand::ImportPreset(path));
+            }
+            Message::ExportPreset => {
+                let path = PathBuf::from(self.preset_path.trim());
+                let _ = self.sender.send(GuiCommand::ExportPreset(path));
             }
             Message::Tap => {
                 let now = Instant::now();
@@ -245,12 +784,26 @@ impl BpmApp {
                     }
                 }
             }
+            Message::NudgeBpm(step) => {
+                // From the override in place, or from the detected tempo
+                if let Some(bpm) = self.manual_bpm.or(self.bpm) {
+                    self.set_manual_bpm(((bpm + step) * 10.0).round() / 10.0);
+                }
+            }
+            Message::ManualBpmChanged(value) => self.manual_input = value,
+            Message::SetManualBpm(bpm) => self.set_manual_bpm(bpm),
+            Message::ReturnToAuto => {
+                self.manual_bpm = None;
+                self.manual_input.clear();
+                let _ = self.sender.send(GuiCommand::SetOverride(None));
+            }
             Message::ToggleDetection => {
                 self.is_enabled = !self.is_enabled;
                 if !self.is_enabled {
                     self.bpm = None;
+                    self.envelope = EnvelopeFrame::default();
                 }
-                println!(
+                info!(
                     "Detection toggled: {}",
                     if self.is_enabled { "ON" } else { "OFF" }
                 );
@@ -258,14 +811,139 @@ impl BpmApp {
             }
             Message::DeviceSelected(device_name) => {
                 self.input_device = Some(device_name.clone());
+                self.preferences.device = Some(device_name.clone());
+                self.preferences.save();
                 let _ = self.sender.send(GuiCommand::SetDevice(Some(device_name)));
             }
         }
         Task::none()
     }
 
+    // Selected built-in theme
+    fn base_theme(&self) -> Theme {
+        Theme::ALL
+            .iter()
+            .find(|theme| theme.to_string() == self.preferences.theme)
+            .cloned()
+            .unwrap_or(Theme::Dracula)
+    }
+
+    // Built-in theme with the accent color as primary
+    fn theme(&self) -> Theme {
+        let base = self.base_theme();
+        match self.preferences.accent.as_deref().and_then(Color::parse) {
+            Some(accent) => Theme::custom(
+                base.to_string(),
+                Palette {
+                    primary: accent,
+                    ..base.palette()
+                },
+            ),
+            None => base,
+        }
+    }
+
+    // New gate state if `event` is the configured gate note
+    fn gate_event(&self, event: &MidiEvent) -> Option<bool> {
+        if !self.gate.enabled {
+            return None;
+        }
+        match *event {
+            MidiEvent::NoteOn { channel, note, .. } if self.gate.matches_midi(channel, note) => {
+                Some(true)
+            }
+            MidiEvent::NoteOff { channel, note } if self.gate.matches_midi(channel, note) => {
+                Some(false)
+            }
+            _ => None,
+        }
+    }
+
+    // Fills the settings inputs with the analyzer values in use
+    // Moves a confidence threshold of the running analyzer (sliders)
+    fn adjust_thresholds(&mut self, change: impl FnOnce(&mut AnalysisPreferences)) {
+        let Some(analysis) = &mut self.analysis else {
+            return;
+        };
+        change(analysis);
+        let thresholds = ConfidenceThreshold {
+            fine_confidence: analysis.fine_confidence,
+            coarse_confidence: analysis.coarse_confidence,
+        };
+        self.reset_settings_form();
+        let _ = self.sender.send(GuiCommand::SetThresholds(thresholds));
+    }
+
+    fn reset_settings_form(&mut self) {
+        if let Some(analysis) = self.analysis {
+            self.min_bpm_input = format!("{:.0}", analysis.min_bpm);
+            self.max_bpm_input = format!("{:.0}", analysis.max_bpm);
+            self.fine_confidence_input = format!("{:.2}", analysis.fine_confidence);
+            self.coarse_confidence_input = format!("{:.2}", analysis.coarse_confidence);
+        }
+    }
+
+    fn parse_settings_form(&self) -> Result<AnalysisPreferences, String> {
+        let parse = |value: &str, name: &str| {
+            value
+                .trim()
+                .parse::<f32>()
+                .map_err(|_| format!("Invalid {}: '{}'", name, value))
+        };
+        let analysis = AnalysisPreferences {
+            min_bpm: parse(&self.min_bpm_input, "min BPM")?,
+            max_bpm: parse(&self.max_bpm_input, "max BPM")?,
+            fine_confidence: parse(&self.fine_confidence_input, "fine confidence")?,
+            coarse_confidence: parse(&self.coarse_confidence_input, "coarse confidence")?,
+        };
+        analysis.validate()?;
+        Ok(analysis)
+    }
+
+    fn set_manual_bpm(&mut self, bpm: f32) {
+        let bpm = bpm.clamp(*MANUAL_BPM.start(), *MANUAL_BPM.end());
+        self.manual_bpm = Some(bpm);
+        self.manual_input = format!("{:.1}", bpm);
+        let _ = self.sender.send(GuiCommand::SetOverride(Some(bpm)));
+    }
+
+    // Tempo typed in the override field, if valid
+    fn manual_input_bpm(&self) -> Option<f32> {
+        self.manual_input
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|bpm| MANUAL_BPM.contains(bpm))
+    }
+
     fn view(&self) -> Element<'_, Message> {
-        let peers_text = if self.is_enabled {
+        if self.show_performance {
+            return self.performance_view();
+        }
+        if self.show_about {
+            return self.about_view();
+        }
+        if self.show_dashboard {
+            return self.dashboard_view();
+        }
+        if self.show_stats {
+            return self.stats_view();
+        }
+        if self.show_settings {
+            return self.settings_view();
+        }
+
+        let peers_text = if self.is_enabled && !self.gate_open {
+            text("Waiting for gate").size(14).color([0.9, 0.6, 0.3])
+        } else if self.is_enabled && self.num_peers > 0 && self.link_follow {
+            let (role, color) = match self.link_role {
+                LinkRole::Leading => ("leading", [0.7, 0.7, 0.7]),
+                LinkRole::Following => ("following", [0.4, 0.7, 0.9]),
+            };
+            text(format!("Link Peers: {} ({})", self.num_peers, role))
+                .size(14)
+                .color(color)
+        } else if self.is_enabled {
             text(format!("Link Peers: {}", self.num_peers))
                 .size(14)
                 .color([0.7, 0.7, 0.7])
@@ -283,6 +961,94 @@ impl BpmApp {
 
         let label_text = text("BPM").size(20).color([0.6, 0.6, 0.6]);
 
+        // Confidence of the last detection, greyed out once it gets old
+        let confidence = self
+            .confidence
+            .filter(|(_, _, at)| self.is_enabled && at.elapsed() < CONFIDENCE_HOLD);
+        let thresholds = self
+            .analysis
+            .map(|analysis| (analysis.fine_confidence, analysis.coarse_confidence));
+        let confidence_badge = match confidence {
+            Some((fine, _, _)) => {
+                let strong = thresholds.is_none_or(|(fine_threshold, _)| {
+                    fine >= fine_threshold + STRONG_CONFIDENCE_MARGIN
+                });
+                text(format!(
+                    "{} {:.2}",
+                    if strong { "LOCKED" } else { "WEAK" },
+                    fine
+                ))
+                .color(if strong {
+                    [0.3, 0.8, 0.4]
+                } else {
+                    [0.9, 0.6, 0.3]
+                })
+            }
+            None if self.is_enabled => text("NO LOCK").color([0.5, 0.5, 0.5]),
+            None => text(""),
+        };
+        let confidence_row = row![
+            confidence_badge.size(12).width(Length::Fixed(80.0)),
+            Canvas::new(ConfidenceView {
+                fine: confidence.map(|(fine, _, _)| fine),
+                coarse: confidence.map(|(_, coarse, _)| coarse),
+                energy: self.energy.filter(|_| self.is_enabled),
+                thresholds,
+            })
+            .width(Length::Fill)
+            .height(Length::Fixed(20.0)),
+        ]
+        .spacing(10)
+        .align_y(iced::alignment::Vertical::Center);
+
+        // The analyzer keeps running under the override, the readout stays its tempo
+        let override_text = match self.manual_bpm {
+            Some(bpm) => text(format!("MANUAL OVERRIDE: {:.1} BPM to Link/MIDI", bpm))
+                .size(12)
+                .color([0.9, 0.6, 0.3]),
+            None => text("").size(12),
+        };
+        let nudge = |label, step| {
+            button(text(label).size(12).align_x(Horizontal::Center))
+                .on_press_maybe(
+                    self.manual_bpm
+                        .or(self.bpm)
+                        .map(|_| Message::NudgeBpm(step)),
+                )
+                .padding(5)
+                .width(Length::Fixed(45.0))
+                .style(button::secondary)
+        };
+        let override_row = row![
+            nudge("-0.1", -0.1),
+            text_input("BPM", &self.manual_input)
+                .on_input(Message::ManualBpmChanged)
+                .on_submit_maybe(self.manual_input_bpm().map(Message::SetManualBpm))
+                .size(12)
+                .width(Length::Fixed(60.0)),
+            nudge("+0.1", 0.1),
+            button(text("Set").size(12))
+                .on_press_maybe(self.manual_input_bpm().map(Message::SetManualBpm))
+                .padding(5)
+                .style(button::secondary),
+            button(text("Auto").size(12))
+                .on_press_maybe(self.manual_bpm.map(|_| Message::ReturnToAuto))
+                .padding(5)
+                .style(if self.manual_bpm.is_some() {
+                    button::primary
+                } else {
+                    button::secondary
+                }),
+        ]
+        .spacing(8)
+        .align_y(iced::alignment::Vertical::Center);
+
+        // Camelot notation first, it is what harmonic mixing goes by
+        let key_text = match self.key.filter(|_| self.is_enabled) {
+            Some(key) => text(format!("{} · {}", key.camelot(), key)).size(20),
+            None => text(""),
+        };
+
         let device_picker = pick_list(
             self.available_devices.clone(),
             self.input_device.clone(),
@@ -291,6 +1057,43 @@ impl BpmApp {
         .placeholder("Select Audio Device")
         .width(Length::Fill);
 
+        // Input meter of the selected device, with a clip warning held for CLIP_HOLD
+        let meter_row = match self.input_level.filter(|_| self.is_enabled) {
+            Some((peak, rms)) => {
+                let clipping = self.last_clip.is_some_and(|at| at.elapsed() < CLIP_HOLD);
+                row![
+                    Canvas::new(MeterView {
+                        peak,
+                        rms,
+                        clipping
+                    })
+                    .width(Length::Fill)
+                    .height(Length::Fixed(8.0)),
+                    text(if clipping { "CLIP" } else { "" })
+                        .size(12)
+                        .color([0.9, 0.2, 0.2])
+                        .width(Length::Fixed(30.0)),
+                ]
+                .spacing(10)
+                .align_y(iced::alignment::Vertical::Center)
+            }
+            None => row![],
+        };
+
+        // Shown when the selected device is unplugged and the default input is used
+        let live_device_text = match &self.live_device {
+            Some(live)
+                if self.is_enabled
+                    && self.input_device.as_deref() != Some(SYSTEM_AUDIO_DEVICE)
+                    && self.input_device.as_ref() != Some(live) =>
+            {
+                text(format!("Live input: {}", live))
+                    .size(12)
+                    .color([0.9, 0.6, 0.3])
+            }
+            _ => text("").size(12),
+        };
+
         let toggle_btn = button(
             text(if self.is_enabled {
                 "Disable Detection"
@@ -359,19 +1162,19 @@ impl BpmApp {
             });
 
         // MIDI Learn Button
-        let learn_btn_text = if self.midi_learn {
+        let learn_btn_text = if self.midi_learn.is_some() {
             "Listening..."
         } else {
             "MIDI Learn"
         };
         let learn_btn = button(text(learn_btn_text).size(12).align_x(Horizontal::Center))
-            .on_press(Message::ToggleMidiLearn)
+            .on_press(Message::LearnMidi(MidiAction::Tap))
             .padding(10)
             .width(iced::Length::Fixed(100.0))
             .style(move |theme: &'_ Theme, status| {
                 let palette = theme.palette();
                 // If learning, use warning/danger color (orange/red), else neutral
-                let base = if self.midi_learn {
+                let base = if self.midi_learn.is_some() {
                     palette.danger
                 } else {
                     Color {
@@ -392,8 +1195,8 @@ impl BpmApp {
                     text_color: Color::WHITE,
                     border: iced::Border {
                         radius: 15.0.into(),
-                        width: if self.midi_learn { 2.0 } else { 1.0 },
-                        color: if self.midi_learn {
+                        width: if self.midi_learn.is_some() { 2.0 } else { 1.0 },
+                        color: if self.midi_learn.is_some() {
                             palette.primary
                         } else {
                             Color::TRANSPARENT
@@ -404,20 +1207,251 @@ impl BpmApp {
                 }
             });
 
-        let tap_row = row![tap_btn, learn_btn]
+        // Half/double-time bias, for genres detected at the wrong octave
+        let octave_btn = button(
+            text(match self.octave {
+                OctavePreference::Half => "Octave: ½x",
+                OctavePreference::Normal => "Octave: 1x",
+                OctavePreference::Double => "Octave: 2x",
+            })
+            .size(12)
+            .align_x(Horizontal::Center),
+        )
+        .on_press(Message::CycleOctave)
+        .padding(10)
+        .width(iced::Length::Fixed(90.0))
+        .style(if self.octave == OctavePreference::Normal {
+            button::secondary
+        } else {
+            button::primary
+        });
+
+        let tap_row = row![tap_btn, learn_btn, octave_btn]
             .spacing(10)
             .align_y(iced::alignment::Vertical::Center);
 
+        let envelope_view = Canvas::new(EnvelopeView {
+            frame: &self.envelope,
+            drop_active: self.drop_active(),
+        })
+        .width(Length::Fill)
+        .height(Length::Fixed(80.0));
+
+        // Collapsible autocorrelation panel
+        let correlation_toggle = button(
+            text(if self.show_correlation {
+                "Hide Correlation"
+            } else {
+                "Show Correlation"
+            })
+            .size(12),
+        )
+        .on_press(Message::ToggleCorrelation)
+        .padding(5)
+        .style(button::text);
+        let mut correlation_panel = column![correlation_toggle].spacing(5);
+        if self.show_correlation {
+            let chosen_bpm = self
+                .correlation
+                .chosen_lag
+                .filter(|&lag| lag > 0)
+                .map(|lag| {
+                    format!(
+                        "{:.1} BPM",
+                        self.correlation.coarse_rate * 60.0 / lag as f32
+                    )
+                })
+                .unwrap_or_else(|| "no lock".to_string());
+            correlation_panel = correlation_panel
+                .push(
+                    Canvas::new(CorrelationView {
+                        debug: &self.correlation,
+                    })
+                    .width(Length::Fill)
+                    .height(Length::Fixed(80.0)),
+                )
+                .push(
+                    text(match self.correlation.estimator {
+                        TempoEstimator::Autocorrelation => format!("Coarse lag: {}", chosen_bpm),
+                        TempoEstimator::CombFilter => {
+                            format!("Coarse lag (comb resonators): {}", chosen_bpm)
+                        }
+                        // Strongest tempos of the onset intervals, next to the lag they settled
+                        TempoEstimator::IoiFusion => format!(
+                            "Coarse lag: {} | IOI: {}",
+                            chosen_bpm,
+                            self.correlation
+                                .tempo_hypotheses
+                                .iter()
+                                .take(3)
+                                .map(|h| format!("{:.1} ({:.0}%)", h.bpm, h.weight * 100.0))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                    })
+                    .size(12)
+                    .color([0.6, 0.6, 0.6]),
+                )
+                .push(
+                    Canvas::new(HistogramView {
+                        histogram: &self.histogram,
+                    })
+                    .width(Length::Fill)
+                    .height(Length::Fixed(60.0)),
+                )
+                .push(
+                    text(match self.histogram.set_tempo {
+                        Some(bpm) => format!(
+                            "Set tempo ({} min): {:.1} BPM",
+                            self.histogram.period_s / 60,
+                            bpm
+                        ),
+                        None => "Set tempo: ---".to_string(),
+                    })
+                    .size(12)
+                    .color([0.6, 0.6, 0.6]),
+                );
+            // Lower thresholds keep a tempo in noisy rooms, at the cost of wrong ones
+            if let Some(analysis) = self.analysis {
+                let threshold_row =
+                    |label: &'static str, value: f32, on_change: fn(f32) -> Message| {
+                        row![
+                            text(label).size(12).width(Length::Fixed(110.0)),
+                            slider(0.0..=1.0, value, on_change)
+                                .step(0.01)
+                                .on_release(Message::SaveThresholds),
+                            text(format!("{:.2}", value))
+                                .size(12)
+                                .width(Length::Fixed(35.0)),
+                        ]
+                        .spacing(10)
+                        .align_y(iced::alignment::Vertical::Center)
+                    };
+                correlation_panel = correlation_panel
+                    .push(threshold_row(
+                        "Fine threshold",
+                        analysis.fine_confidence,
+                        Message::FineThresholdChanged,
+                    ))
+                    .push(threshold_row(
+                        "Coarse threshold",
+                        analysis.coarse_confidence,
+                        Message::CoarseThresholdChanged,
+                    ));
+            }
+        }
+
+        let devices_text = if self.devices.is_empty() {
+            text("").size(12)
+        } else {
+            let names: Vec<String> = self
+                .devices
+                .iter()
+                .map(|d| {
+                    let mut label = match d.address {
+                        // Brackets around IPv6 addresses
+                        Some(ip) => {
+                            format!("{} ({})", d.name, std::net::SocketAddr::new(ip, d.port))
+                        }
+                        None => d.name.clone(),
+                    };
+                    if let Some(version) = &d.version {
+                        label.push_str(&format!(" v{}", version));
+                    }
+                    if let Some(platform) = &d.platform {
+                        label.push_str(&format!(" {}", platform));
+                    }
+                    // Spotted at a glance before the show: old firmware, or an API
+                    // this build cannot drive
+                    if !d.is_compatible() {
+                        label.push_str(" [incompatible, update needed]");
+                    } else if d.is_older_than(env!("CARGO_PKG_VERSION")) {
+                        label.push_str(" [outdated]");
+                    }
+                    if !d.online {
+                        label.push_str(" [offline]");
+                    }
+                    label
+                })
+                .collect();
+            text(format!("Devices: {}", names.join(", ")))
+                .size(12)
+                .color([0.6, 0.6, 0.6])
+        };
+
+        let preset_row = row![
+            text_input("preset.toml", &self.preset_path)
+                .on_input(Message::PresetPathChanged)
+                .size(12),
+            button(text("Import").size(12))
+                .on_press(Message::ImportPreset)
+                .padding(5),
+            button(text("Export").size(12))
+                .on_press(Message::ExportPreset)
+                .padding(5),
+        ]
+        .spacing(5)
+        .align_y(iced::alignment::Vertical::Center);
+
+        // Venue-wide monitor, offered once several devices are on the network
+        let dashboard_btn = (self.devices.len() > 1).then(|| {
+            button(text("Devices").size(12))
+                .on_press(Message::ToggleDashboard)
+                .padding(5)
+                .style(button::text)
+        });
+
+        // Visualizer: background flashing with the beat grid and drops
+        let (flash, drop_flash) = if self.preferences.background_flash {
+            (self.beat_flash(), self.drop_active())
+        } else {
+            (0.0, false)
+        };
+
         container(
             column![
-                row![peers_text]
+                row![peers_text, iced::widget::horizontal_space()]
+                    .push_maybe(dashboard_btn)
+                    .push(
+                        button(text("Stats").size(12))
+                            .on_press(Message::ToggleStats)
+                            .padding(5)
+                            .style(button::text)
+                    )
+                    .push(
+                        button(text("Performance").size(12))
+                            .on_press(Message::TogglePerformance)
+                            .padding(5)
+                            .style(button::text)
+                    )
+                    .push(
+                        button(text("Settings").size(12))
+                            .on_press(Message::ToggleSettings)
+                            .padding(5)
+                            .style(button::text)
+                    )
+                    .push(
+                        button(text("About").size(12))
+                            .on_press(Message::ToggleAbout)
+                            .padding(5)
+                            .style(button::text)
+                    )
                     .width(Length::Fill)
                     .align_y(iced::alignment::Vertical::Top),
-                column![label_text, bpm_display]
+                devices_text,
+                column![label_text, bpm_display, key_text, override_text]
+                    .push_maybe(self.link_phase_view())
                     .align_x(Horizontal::Center)
                     .spacing(5),
+                confidence_row,
                 tap_row,
+                override_row,
+                envelope_view,
+                correlation_panel,
+                preset_row,
                 device_picker,
+                meter_row,
+                live_device_text,
                 toggle_btn
             ]
             .align_x(Horizontal::Center)
@@ -428,161 +1462,1702 @@ impl BpmApp {
         .height(Length::Fill)
         .center_x(Length::Fill)
         .center_y(Length::Fill)
+        .style(move |theme: &Theme| container::Style {
+            background: Some(
+                flash_background(&theme.palette(), flash, drop_flash, MAIN_FLASH_STRENGTH).into(),
+            ),
+            ..container::Style::default()
+        })
         .into()
     }
 
-    fn subscription(&self) -> Subscription<Message> {
-        iced::window::frames().map(|_| Message::Tick)
-    }
-}
-
-// This function runs in a background thread and does the heavy lifting
-fn run_analysis_loop(
-    tx: mpsc::Sender<GuiUpdate>,
-    rx_cmd: mpsc::Receiver<GuiCommand>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let (sender, receiver) = mpsc::channel();
-    let sender_clone = sender.clone(); // Keep a clone to restart audio capture
-    let mut last_ui_update = Instant::now();
-    let mut is_enabled = false;
-    let mut current_device: Option<String> = None;
-    let mut current_hop_size = TARGET_SAMPLE_RATE as usize;
-
-    let mut new_samples_accumulator: Vec<f32> = Vec::with_capacity(TARGET_SAMPLE_RATE as usize);
-    let mut analyzer = BpmAnalyzer::new(TARGET_SAMPLE_RATE, None)?;
-    let mut bpm_history: std::collections::VecDeque<f32> =
-        std::collections::VecDeque::with_capacity(5);
+    // BPM in huge digits for reading across a DJ booth: the background flashes on
+    // each beat and turns red after a drop. F11 or Esc to leave.
+    fn performance_view(&self) -> Element<'_, Message> {
+        let controls = row![
+            checkbox("Full screen", self.preferences.performance_fullscreen)
+                .on_toggle(Message::SetPerformanceFullscreen)
+                .size(14)
+                .text_size(12),
+            checkbox("Always on top", self.preferences.always_on_top)
+                .on_toggle(Message::SetAlwaysOnTop)
+                .size(14)
+                .text_size(12),
+            iced::widget::horizontal_space(),
+            button(text("Exit (F11)").size(12))
+                .on_press(Message::TogglePerformance)
+                .padding(5)
+                .style(button::text),
+        ]
+        .spacing(15)
+        .padding(10)
+        .align_y(iced::alignment::Vertical::Center);
 
-    let mut link_manager = LinkManager::new();
+        let performance = Canvas::new(PerformanceView {
+            bpm: self.bpm.filter(|_| self.is_enabled),
+            flash: self.beat_flash(),
+            drop_active: self.drop_active(),
+        })
+        .width(Length::Fill)
+        .height(Length::Fill);
 
-    let mut audio_capture: Option<AudioCapture> = None;
+        column![controls, performance].into()
+    }
 
-    loop {
-        // Check for GUI commands
-        while let Ok(cmd) = rx_cmd.try_recv() {
-            match cmd {
-                GuiCommand::SetDetection(enabled) => {
-                    link_manager.link_state(enabled);
-                    is_enabled = enabled;
-                    if enabled {
-                        if audio_capture.is_none() {
-                            println!("Starting audio capture...");
-                            // Re-create audio capture
-                            match AudioCapture::new(
-                                sender_clone.clone(),
-                                current_device.clone(),
-                                TARGET_SAMPLE_RATE,
-                                None,
-                                Some(Duration::from_millis(500)),
-                            ) {
-                                Ok(capture) => audio_capture = Some(capture),
-                                Err(e) => eprintln!("Failed to restart audio capture: {}", e),
-                            }
-                        }
-                    } else {
-                        if audio_capture.is_some() {
-                            println!("Stopping audio capture...");
-                            audio_capture = None; // Drops the capture and stops the stream
-                        }
-                        new_samples_accumulator.clear();
-                        bpm_history.clear();
-                    }
-                }
-                GuiCommand::SetDevice(device_name) => {
-                    println!("Switching device to: {:?}", device_name);
-                    current_device = device_name.clone();
-                    if let Some(capture) = &mut audio_capture {
-                        if let Err(e) = capture.set_device(device_name) {
-                            eprintln!("Failed to switch device: {}", e);
-                        }
-                    }
-                }
-                GuiCommand::SetBpm(new_bpm) => {
-                    link_manager.update_tempo(new_bpm, false, None);
-                }
-            }
+    // 1.0 on each beat of the current grid, fading out over PERFORMANCE_FLASH
+    fn beat_flash(&self) -> f32 {
+        let (Some(bpm), Some(last_beat)) = (self.bpm, self.last_beat) else {
+            return 0.0;
+        };
+        if !self.is_enabled || bpm <= 0.0 {
+            return 0.0;
         }
+        let since_beat = last_beat.elapsed().as_secs_f32() % (60.0 / bpm);
+        (1.0 - since_beat / PERFORMANCE_FLASH.as_secs_f32()).max(0.0)
+    }
 
-        // Use recv_timeout to allow checking commands and updating UI even if no audio comes in
-        match receiver.recv_timeout(Duration::from_millis(50)) {
-            Ok(AudioMessage::Samples(packet)) => {
-                if is_enabled {
-                    new_samples_accumulator.extend(packet);
-
-                    if new_samples_accumulator.len() >= current_hop_size {
-                        if let Ok(Some(result)) = analyzer.process(&new_samples_accumulator) {
-                            // Update history for moving average
-                            if bpm_history.len() >= 5 {
-                                bpm_history.pop_front();
-                            }
-                            bpm_history.push_back(result.bpm);
+    // Drop flag stays visible for 2 seconds
+    fn drop_active(&self) -> bool {
+        self.last_drop
+            .map(|t| t.elapsed() < Duration::from_secs(2))
+            .unwrap_or(false)
+    }
 
-                            // Calculate average
+    // Full screen / always on top while in performance mode, normal window otherwise
+    fn apply_window_options(&self) -> Task<Message> {
+        let (mode, level) = if self.show_performance {
+            (
+                if self.preferences.performance_fullscreen {
+                    window::Mode::Fullscreen
+                } else {
+                    window::Mode::Windowed
+                },
+                if self.preferences.always_on_top {
+                    window::Level::AlwaysOnTop
+                } else {
+                    window::Level::Normal
+                },
+            )
+        } else {
+            (window::Mode::Windowed, window::Level::Normal)
+        };
+        window::get_oldest().and_then(move |id| {
+            window::change_mode(id, mode).chain(window::change_level(id, level))
+        })
+    }
+
+    // Analyzer range and thresholds, Link, theme and MIDI mapping, saved as preferences
+    fn settings_view(&self) -> Element<'_, Message> {
+        let field = |label: &'static str, value: &str, on_input: fn(String) -> Message| {
+            row![
+                text(label).size(12).width(Length::Fixed(130.0)),
+                text_input("", value).on_input(on_input).size(12),
+            ]
+            .spacing(10)
+            .align_y(iced::alignment::Vertical::Center)
+        };
+
+        // One row per action: control learnt, Learn and Clear
+        let midi_rows = column(MidiAction::ALL.into_iter().map(|action| {
+            let mapping = self.preferences.midi.get(action);
+            let learning = self.midi_learn == Some(action);
+            let label = match mapping {
+                Some(mapping) => format!("{}: {}", action, mapping),
+                None => format!("{}: not mapped", action),
+            };
+            row![
+                text(label).size(12).width(Length::Fill),
+                button(text(if learning { "Listening..." } else { "Learn" }).size(12))
+                    .on_press(Message::LearnMidi(action))
+                    .padding(5),
+                button(text("Clear").size(12))
+                    .on_press_maybe(mapping.map(|_| Message::ClearMidiMapping(action)))
+                    .padding(5),
+            ]
+            .spacing(10)
+            .align_y(iced::alignment::Vertical::Center)
+            .into()
+        }))
+        .spacing(6);
+
+        let saved_text = Preferences::path()
+            .map(|path| format!("Saved in {}", path.display()))
+            .unwrap_or_default();
+
+        container(
+            column![
+                text("Settings").size(24),
+                field("Min BPM", &self.min_bpm_input, Message::MinBpmChanged),
+                field("Max BPM", &self.max_bpm_input, Message::MaxBpmChanged),
+                field(
+                    "Fine confidence",
+                    &self.fine_confidence_input,
+                    Message::FineConfidenceChanged
+                ),
+                field(
+                    "Coarse confidence",
+                    &self.coarse_confidence_input,
+                    Message::CoarseConfidenceChanged
+                ),
+                button(text("Apply").size(14))
+                    .on_press(Message::ApplyAnalysis)
+                    .padding(8),
+                text(self.settings_error.clone().unwrap_or_default())
+                    .size(12)
+                    .color([0.9, 0.4, 0.4]),
+                checkbox("Ableton Link", self.preferences.link_enabled)
+                    .on_toggle(Message::ToggleLink)
+                    .size(16)
+                    .text_size(12),
+                checkbox("Follow the Link tempo when unsure", self.link_follow)
+                    .on_toggle(Message::ToggleLinkFollow)
+                    .size(16)
+                    .text_size(12),
+                row![
+                    text("Link quantum (beats)")
+                        .size(12)
+                        .width(Length::Fixed(130.0)),
+                    pick_list(
+                        LINK_QUANTUMS,
+                        Some(self.link_quantum),
+                        Message::LinkQuantumSelected
+                    )
+                    .text_size(12)
+                    .width(Length::Fill),
+                ]
+                .spacing(10)
+                .align_y(iced::alignment::Vertical::Center),
+                row![
+                    text("Theme").size(12).width(Length::Fixed(130.0)),
+                    pick_list(Theme::ALL, Some(self.base_theme()), Message::ThemeSelected)
+                        .text_size(12)
+                        .width(Length::Fill),
+                ]
+                .spacing(10)
+                .align_y(iced::alignment::Vertical::Center),
+                row![
+                    text("Accent color").size(12).width(Length::Fixed(130.0)),
+                    text_input("#RRGGBB (theme default)", &self.accent_input)
+                        .on_input(Message::AccentChanged)
+                        .size(12),
+                ]
+                .spacing(10)
+                .align_y(iced::alignment::Vertical::Center),
+                checkbox(
+                    "Flash on beats and drops",
+                    self.preferences.background_flash
+                )
+                .on_toggle(Message::ToggleBackgroundFlash)
+                .size(16)
+                .text_size(12),
+                text(match &self.midi_port {
+                    Some(port) => format!("MIDI controller: {}", port),
+                    None =>
+                        "MIDI controller: none (plug one in, it connects by itself)".to_string(),
+                })
+                .size(12),
+                midi_rows,
+                text(saved_text).size(10).color([0.5, 0.5, 0.5]),
+                button(text("Back").size(14))
+                    .on_press(Message::ToggleSettings)
+                    .padding(10),
+            ]
+            .align_x(Horizontal::Center)
+            .spacing(12)
+            .padding(20),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x(Length::Fill)
+        .center_y(Length::Fill)
+        .into()
+    }
+
+    // Version, active config and devices, with the diagnostics bundle export
+    fn about_view(&self) -> Element<'_, Message> {
+        let config_path = AppConfig::path();
+        let config_text = if config_path.is_file() {
+            format!("Config: {}", config_path.display())
+        } else {
+            format!("Config: {} (not found, defaults)", config_path.display())
+        };
+        let device_text = format!("Input: {}", self.input_device.as_deref().unwrap_or("none"));
+        let live_text = format!(
+            "Live input: {}",
+            self.live_device.as_deref().unwrap_or("---")
+        );
+
+        // Analyzers on the network, with their version and remote update
+        let mut devices = column![].spacing(5).align_x(Horizontal::Center);
+        for device in self.devices.iter().filter(|d| d.online) {
+            let mut version = device.version.clone().unwrap_or_else(|| "?".to_string());
+            let (state, action) = match self.device_updates.get(&device.name) {
+                Some(Ok(update)) => {
+                    // Installed version from the API, newer than the mDNS one after an update
+                    version = update.version.clone();
+                    let action = match (update.phase, &update.available) {
+                        (UpdatePhase::Idle | UpdatePhase::Failed, Some(available)) => {
+                            Some((format!("Update to v{}", available), UpdateCommand::Start))
+                        }
+                        (UpdatePhase::Idle | UpdatePhase::Failed, None) => {
+                            Some(("Check".to_string(), UpdateCommand::Check))
+                        }
+                        _ => None,
+                    };
+                    (update.summary(), action)
+                }
+                Some(Err(e)) => (e, Some(("Check".to_string(), UpdateCommand::Check))),
+                None => ("---".to_string(), None),
+            };
+            let mut line = row![
+                text(format!("{} v{}: {}", device.name, version, state))
+                    .size(12)
+                    .color([0.6, 0.6, 0.6])
+            ]
+            .spacing(10)
+            .align_y(iced::alignment::Vertical::Center);
+            // Level of the last minutes, to tell input problems from detection dropouts
+            if let Some(history) = self.device_updates.levels(&device.name) {
+                line = line.push(
+                    Canvas::new(LevelView {
+                        levels: history.levels,
+                    })
+                    .width(Length::Fixed(120.0))
+                    .height(Length::Fixed(20.0)),
+                );
+            }
+            if let Some((label, command)) = action {
+                line = line.push(
+                    button(text(label).size(12))
+                        .on_press(Message::DeviceUpdate(device.name.clone(), command))
+                        .padding(5)
+                        .style(button::secondary),
+                );
+            }
+            devices = devices.push(line);
+        }
+
+        container(
+            column![
+                text("Rust BPM Analyzer").size(24),
+                text(format!(
+                    "Version {} ({} {})",
+                    env!("CARGO_PKG_VERSION"),
+                    std::env::consts::OS,
+                    std::env::consts::ARCH
+                ))
+                .size(14)
+                .color([0.7, 0.7, 0.7]),
+                text(config_text).size(12).color([0.6, 0.6, 0.6]),
+                text(device_text).size(12).color([0.6, 0.6, 0.6]),
+                text(live_text).size(12).color([0.6, 0.6, 0.6]),
+                devices,
+                text("The diagnostics bundle holds the config, recent logs and stats (no audio).")
+                    .size(12)
+                    .color([0.6, 0.6, 0.6]),
+                button(text("Export diagnostics bundle").size(14))
+                    .on_press(Message::ExportDiagnostics)
+                    .padding(10),
+                row![
+                    button(text("Dump analyzer state").size(12))
+                        .on_press(Message::DumpState)
+                        .padding(5)
+                        .style(button::secondary),
+                    button(text("Save recent audio").size(12))
+                        .on_press(Message::SaveRecording)
+                        .padding(5)
+                        .style(button::secondary),
+                ]
+                .spacing(10),
+                text(self.notice.clone().unwrap_or_default())
+                    .size(12)
+                    .color([0.9, 0.6, 0.3]),
+                button(text("Back").size(14))
+                    .on_press(Message::ToggleAbout)
+                    .padding(10),
+            ]
+            .align_x(Horizontal::Center)
+            .spacing(15)
+            .padding(20),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x(Length::Fill)
+        .center_y(Length::Fill)
+        .into()
+    }
+
+    // Tile per analyzer on the network, with bulk start and stop of the detection
+    fn dashboard_view(&self) -> Element<'_, Message> {
+        let mut tiles = column![].spacing(10).align_x(Horizontal::Center);
+        for devices in self.devices.chunks(DASHBOARD_COLUMNS) {
+            let mut line = row![].spacing(10);
+            for device in devices {
+                line = line.push(self.device_tile(device));
+            }
+            tiles = tiles.push(line);
+        }
+        tiles = tiles.push_maybe(self.level_timeline_view());
+        let online = self.devices.iter().any(|d| d.online);
+
+        container(
+            column![
+                text("Devices").size(24),
+                row![
+                    button(text("Enable all").size(14))
+                        .on_press_maybe(online.then_some(Message::AllDevicesAnalysis(true)))
+                        .padding(10),
+                    button(text("Disable all").size(14))
+                        .on_press_maybe(online.then_some(Message::AllDevicesAnalysis(false)))
+                        .padding(10)
+                        .style(button::secondary),
+                ]
+                .spacing(10),
+                scrollable(tiles).height(Length::Fill),
+                button(text("Back").size(14))
+                    .on_press(Message::ToggleDashboard)
+                    .padding(10),
+            ]
+            .align_x(Horizontal::Center)
+            .spacing(15)
+            .padding(20),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x(Length::Fill)
+        .into()
+    }
+
+    // Input level of the devices stacked on the desktop clock, now on the right,
+    // to see which room started or stopped and when
+    fn level_timeline_view(&self) -> Option<Element<'_, Message>> {
+        let now = Instant::now();
+        let lines: Vec<Element<'_, Message>> = self
+            .devices
+            .iter()
+            .filter(|device| device.online)
+            .filter_map(|device| {
+                let points = self.device_updates.level_timeline(&device.name, now)?;
+                let line = row![
+                    text(device.name.clone())
+                        .size(12)
+                        .color([0.6, 0.6, 0.6])
+                        .width(Length::Fixed(120.0)),
+                    Canvas::new(LevelTimelineView {
+                        points,
+                        span_s: DASHBOARD_TIMELINE.as_secs_f32(),
+                    })
+                    .width(Length::Fill)
+                    .height(Length::Fixed(24.0)),
+                ]
+                .spacing(10)
+                .align_y(iced::alignment::Vertical::Center);
+                Some(line.into())
+            })
+            .collect();
+        if lines.is_empty() {
+            return None;
+        }
+        let width = DASHBOARD_TILE_WIDTH * DASHBOARD_COLUMNS as f32 + 90.0;
+        Some(
+            column![
+                text(format!(
+                    "Input level, last {} min",
+                    DASHBOARD_TIMELINE.as_secs() / 60
+                ))
+                .size(14),
+                column(lines).spacing(4),
+            ]
+            .spacing(8)
+            .width(Length::Fixed(width))
+            .into(),
+        )
+    }
+
+    // Figures of the set so far, for the post-event report
+    fn stats_view(&self) -> Element<'_, Message> {
+        let stats = self.set_stats.unwrap_or_default();
+        let bpm = |bpm: Option<f32>| bpm.map_or("---.-".to_string(), |bpm| format!("{:.1}", bpm));
+        let duration = |seconds: f64| {
+            let seconds = seconds as u64;
+            format!(
+                "{}:{:02}:{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            )
+        };
+        let lines = [
+            ("Drops", stats.drops.to_string()),
+            ("Average BPM", bpm(stats.average_bpm)),
+            (
+                "Tempo range",
+                format!("{} - {}", bpm(stats.min_bpm), bpm(stats.max_bpm)),
+            ),
+            ("Uptime", duration(stats.uptime_s)),
+            ("Analyzed", duration(stats.analyzed_s)),
+            ("Time locked", format!("{:.0} %", stats.locked_percent)),
+        ];
+        let mut table = column![].spacing(8);
+        for (label, value) in lines {
+            table = table.push(
+                row![
+                    text(label).size(14).color([0.6, 0.6, 0.6]),
+                    iced::widget::horizontal_space(),
+                    text(value).size(14),
+                ]
+                .width(Length::Fixed(240.0)),
+            );
+        }
+
+        container(
+            column![
+                text("Set statistics").size(24),
+                table,
+                row![
+                    button(text("Save").size(14))
+                        .on_press(Message::SaveStats)
+                        .padding(10),
+                    button(text("Reset").size(14))
+                        .on_press(Message::ResetStats)
+                        .padding(10)
+                        .style(button::secondary),
+                ]
+                .spacing(10),
+                text(self.notice.clone().unwrap_or_default())
+                    .size(12)
+                    .color([0.9, 0.6, 0.3]),
+                button(text("Back").size(14))
+                    .on_press(Message::ToggleStats)
+                    .padding(10),
+            ]
+            .align_x(Horizontal::Center)
+            .spacing(15)
+            .padding(20),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x(Length::Fill)
+        .into()
+    }
+
+    // Name, BPM, input energy and analysis state of a device
+    fn device_tile(&self, device: &DiscoveredPeer) -> Element<'_, Message> {
+        let idle = "---.-".to_string();
+        let (bpm, state, energy, enabled) = match self.device_updates.status(&device.name) {
+            _ if !device.online => (idle, "offline".to_string(), None, None),
+            Some(Ok(status)) => {
+                let state = if status.analysis_enabled {
+                    format!(
+                        "{}, confidence {:.0} %",
+                        status.state(),
+                        status.confidence * 100.0
+                    )
+                } else {
+                    status.state().to_string()
+                };
+                (
+                    status
+                        .bpm
+                        .filter(|_| status.analysis_enabled)
+                        .map_or(idle, |bpm| format!("{:.1}", bpm)),
+                    state,
+                    status.energy,
+                    Some(status.analysis_enabled),
+                )
+            }
+            Some(Err(e)) => (idle, e, None, None),
+            None => (idle, "connecting".to_string(), None, None),
+        };
+
+        let mut tile = column![
+            text(device.name.clone()).size(16),
+            text(bpm).size(40),
+            text(state).size(12).color([0.6, 0.6, 0.6]),
+        ]
+        .spacing(5)
+        .align_x(Horizontal::Center)
+        .width(Length::Fixed(DASHBOARD_TILE_WIDTH));
+        if let Some(energy) = energy {
+            tile = tile.push(
+                Canvas::new(MeterView {
+                    peak: energy,
+                    rms: energy,
+                    clipping: false,
+                })
+                .width(Length::Fill)
+                .height(Length::Fixed(8.0)),
+            );
+        }
+        // Beat of the device against the local one, both on the desktop clock
+        if let (Some(beat), Some(bpm), Some(local_beat)) = (
+            self.device_updates
+                .last_beat(&device.name)
+                .filter(|_| device.online),
+            self.bpm,
+            self.last_beat,
+        ) {
+            let offset = phase_offset(beat, local_beat, bpm);
+            tile = tile.push(
+                text(format!("beat {:+.0} ms vs this desktop", offset * 1000.0))
+                    .size(12)
+                    .color([0.6, 0.6, 0.6]),
+            );
+        }
+        if let Some(enabled) = enabled {
+            tile = tile.push(
+                button(text(if enabled { "Disable" } else { "Enable" }).size(12))
+                    .on_press(Message::DeviceAnalysis(device.name.clone(), !enabled))
+                    .padding(5)
+                    .style(button::secondary),
+            );
+        }
+        container(tile)
+            .padding(10)
+            .style(container::rounded_box)
+            .into()
+    }
+
+    // One dot per beat of the Link quantum, the current beat lit and the downbeat
+    // in another color. The phase moves on between updates at the session tempo.
+    fn link_phase_view(&self) -> Option<Element<'_, Message>> {
+        let (beat, tempo, at) = self
+            .link_beat
+            .filter(|_| self.is_enabled && self.preferences.link_enabled)?;
+        let beat = beat + at.elapsed().as_secs_f64() * tempo / 60.0;
+        let current = beat.rem_euclid(self.link_quantum as f64) as u32;
+        let dots = (0..self.link_quantum).map(|index| {
+            let color = match (index == current, index == 0) {
+                (true, true) => [0.9, 0.6, 0.3],
+                (true, false) => [0.4, 0.7, 0.9],
+                (false, _) => [0.3, 0.3, 0.3],
+            };
+            text("●").size(14).color(color).into()
+        });
+        Some(row(dots).spacing(8).into())
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let frames = Subscription::batch([
+            window::frames().map(|_| Message::Tick),
+            keyboard::on_key_press(performance_key),
+        ]);
+        #[cfg(feature = "tray")]
+        {
+            Subscription::batch([
+                frames,
+                Subscription::run(tray::actions).map(Message::Tray),
+                iced::window::close_requests().map(Message::CloseRequested),
+            ])
+        }
+        #[cfg(not(feature = "tray"))]
+        {
+            frames
+        }
+    }
+
+    #[cfg(feature = "tray")]
+    fn update_tray(&mut self) {
+        let state = TrayState {
+            bpm: self.bpm,
+            detection: self.is_enabled,
+            link: self.preferences.link_enabled,
+        };
+        if !self.tray_started {
+            // Created here rather than in new(): macOS needs the event loop running
+            self.tray_started = true;
+            match Tray::new(state) {
+                Ok(tray) => self.tray = Some(tray),
+                Err(e) => error!("System tray unavailable: {}", e),
+            }
+        }
+        if let Some(tray) = &mut self.tray {
+            tray.update(state);
+        }
+    }
+
+    #[cfg(feature = "tray")]
+    fn handle_tray_action(&mut self, action: TrayAction) -> Task<Message> {
+        match action {
+            TrayAction::Show => window::get_oldest().and_then(|id| {
+                window::change_mode(id, window::Mode::Windowed).chain(window::gain_focus(id))
+            }),
+            TrayAction::ToggleDetection => self.update(Message::ToggleDetection),
+            TrayAction::ToggleLink => {
+                self.update(Message::ToggleLink(!self.preferences.link_enabled))
+            }
+            TrayAction::Quit => iced::exit(),
+            // Frames (and so Tick) stop while the window is hidden
+            TrayAction::Refresh => self.update(Message::Tick),
+        }
+    }
+}
+
+// Theme background tinted with the accent on each beat (`flash` 0..1) and held
+// on the danger color after a drop
+fn flash_background(palette: &Palette, flash: f32, drop_active: bool, strength: f32) -> Color {
+    let (tint, amount) = if drop_active {
+        (palette.danger, 0.5 + 0.5 * flash)
+    } else {
+        (palette.primary, flash)
+    };
+    let t = (amount * strength).clamp(0.0, 1.0);
+    let background = palette.background;
+    Color::from_rgb(
+        background.r + (tint.r - background.r) * t,
+        background.g + (tint.g - background.g) * t,
+        background.b + (tint.b - background.b) * t,
+    )
+}
+
+// F11 toggles the performance display, Esc leaves it
+// Message run by a mapped MIDI control
+// APC Mini feedback: channel 6 (0-indexed) with velocity or value 3 lights the pad
+// white
+fn light_pad(midi: &mut MidiManager, mapping: MidiMapping) {
+    if mapping.is_note {
+        midi.send_note_on(6, mapping.note_or_cc, 3);
+    } else {
+        midi.send_control_change(6, mapping.note_or_cc, 3);
+    }
+}
+
+fn midi_message(action: MidiAction) -> Message {
+    match action {
+        MidiAction::ToggleDetection => Message::ToggleDetection,
+        MidiAction::Tap => Message::Tap,
+        MidiAction::OctaveShift => Message::CycleOctave,
+        MidiAction::Calibrate => Message::CalibrateDevices,
+    }
+}
+
+fn performance_key(key: Key, _modifiers: keyboard::Modifiers) -> Option<Message> {
+    match key {
+        Key::Named(Named::F11) => Some(Message::TogglePerformance),
+        Key::Named(Named::Escape) => Some(Message::ExitPerformance),
+        _ => None,
+    }
+}
+
+// Full-window BPM of the performance display
+struct PerformanceView {
+    bpm: Option<f32>,
+    // Beat flash intensity, 0..1
+    flash: f32,
+    drop_active: bool,
+}
+
+impl canvas::Program<Message> for PerformanceView {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.palette();
+
+        let background = flash_background(
+            &palette,
+            self.flash,
+            self.drop_active,
+            PERFORMANCE_FLASH_STRENGTH,
+        );
+        frame.fill_rectangle(Point::ORIGIN, bounds.size(), background);
+
+        // Five characters ("128.0") across most of the width
+        let size = (bounds.width / 3.2).min(bounds.height * 0.6);
+        let (content, color) = match self.bpm {
+            Some(bpm) => (format!("{:.1}", bpm), palette.text),
+            None => (
+                "---.-".to_string(),
+                Color {
+                    a: 0.4,
+                    ..palette.text
+                },
+            ),
+        };
+        let center = Point::new(bounds.width / 2.0, bounds.height / 2.0);
+        frame.fill_text(canvas::Text {
+            content,
+            position: center,
+            color,
+            size: size.into(),
+            horizontal_alignment: Horizontal::Center,
+            vertical_alignment: iced::alignment::Vertical::Center,
+            ..canvas::Text::default()
+        });
+
+        if self.drop_active {
+            frame.fill_text(canvas::Text {
+                content: "DROP".to_string(),
+                position: Point::new(center.x, center.y + size * 0.65),
+                color: palette.text,
+                size: (size * 0.25).into(),
+                horizontal_alignment: Horizontal::Center,
+                vertical_alignment: iced::alignment::Vertical::Top,
+                ..canvas::Text::default()
+            });
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+// Scrolling plot of the analyzer envelope with beat markers and drop flag
+struct EnvelopeView<'a> {
+    frame: &'a EnvelopeFrame,
+    drop_active: bool,
+}
+
+impl canvas::Program<Message> for EnvelopeView<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.palette();
+
+        // Background (tinted while a drop is flagged)
+        let background = if self.drop_active {
+            Color {
+                a: 0.35,
+                ..palette.danger
+            }
+        } else {
+            Color {
+                a: 0.1,
+                ..palette.text
+            }
+        };
+        frame.fill_rectangle(Point::ORIGIN, bounds.size(), background);
+
+        // Beat markers
+        let marker_stroke = Stroke::default()
+            .with_color(Color {
+                a: 0.6,
+                ..palette.success
+            })
+            .with_width(1.0);
+        for &marker in &self.frame.beat_markers {
+            let x = marker * bounds.width;
+            frame.stroke(
+                &Path::line(Point::new(x, 0.0), Point::new(x, bounds.height)),
+                marker_stroke,
+            );
+        }
+
+        // Envelope
+        let samples = &self.frame.samples;
+        if samples.len() >= 2 {
+            let step = bounds.width / (samples.len() - 1) as f32;
+            let envelope = Path::new(|builder| {
+                for (i, &value) in samples.iter().enumerate() {
+                    let point = Point::new(
+                        i as f32 * step,
+                        bounds.height * (1.0 - value.clamp(0.0, 1.0)),
+                    );
+                    if i == 0 {
+                        builder.move_to(point);
+                    } else {
+                        builder.line_to(point);
+                    }
+                }
+            });
+            frame.stroke(
+                &envelope,
+                Stroke::default()
+                    .with_color(palette.primary)
+                    .with_width(1.5),
+            );
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+// Coarse autocorrelation curve, or comb resonator energies, lag on the x axis
+// (short lags = fast tempos on the left)
+struct CorrelationView<'a> {
+    debug: &'a AnalysisDebug,
+}
+
+impl canvas::Program<Message> for CorrelationView<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.palette();
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            bounds.size(),
+            Color {
+                a: 0.1,
+                ..palette.text
+            },
+        );
+
+        let curve = &self.debug.coarse_curve;
+        if curve.len() < 2 {
+            return vec![frame.into_geometry()];
+        }
+
+        // Scale on the curve range so negative correlations stay visible
+        let min = curve.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max = curve.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let span = (max - min).max(1e-6);
+        let step = bounds.width / (curve.len() - 1) as f32;
+        let lag_x = |lag: usize| (lag.saturating_sub(self.debug.first_lag)) as f32 * step;
+
+        // Harmonic candidates, then raw and chosen lags
+        let mut markers: Vec<(usize, Color)> = self
+            .debug
+            .harmonic_candidates
+            .iter()
+            .map(|&lag| {
+                (
+                    lag,
+                    Color {
+                        a: 0.5,
+                        ..palette.text
+                    },
+                )
+            })
+            .collect();
+        if let Some(lag) = self.debug.raw_lag {
+            markers.push((lag, palette.danger));
+        }
+        if let Some(lag) = self.debug.chosen_lag {
+            markers.push((lag, palette.success));
+        }
+        for (lag, color) in markers {
+            let x = lag_x(lag);
+            frame.stroke(
+                &Path::line(Point::new(x, 0.0), Point::new(x, bounds.height)),
+                Stroke::default().with_color(color).with_width(1.5),
+            );
+        }
+
+        let path = Path::new(|builder| {
+            for (i, &value) in curve.iter().enumerate() {
+                let point = Point::new(
+                    i as f32 * step,
+                    bounds.height * (1.0 - (value - min) / span),
+                );
+                if i == 0 {
+                    builder.move_to(point);
+                } else {
+                    builder.line_to(point);
+                }
+            }
+        });
+        frame.stroke(
+            &path,
+            Stroke::default()
+                .with_color(palette.primary)
+                .with_width(1.5),
+        );
+
+        vec![frame.into_geometry()]
+    }
+}
+
+// Bars of the tempo histogram, most frequent bin highlighted
+struct HistogramView<'a> {
+    histogram: &'a HistogramSnapshot,
+}
+
+impl canvas::Program<Message> for HistogramView<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.palette();
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            bounds.size(),
+            Color {
+                a: 0.1,
+                ..palette.text
+            },
+        );
+
+        let bins = &self.histogram.bins;
+        let (Some(first), Some(last)) = (bins.first(), bins.last()) else {
+            return vec![frame.into_geometry()];
+        };
+
+        // One slot per bin over the observed range, with one empty slot on each side
+        let width = self.histogram.bin_width;
+        let slots = ((last.0 - first.0) / width).round() as usize + 3;
+        let slot_w = bounds.width / slots as f32;
+        let max_count = bins.iter().map(|b| b.1).max().unwrap_or(1).max(1) as f32;
+        let peak = self
+            .histogram
+            .set_tempo
+            .map(|bpm| (bpm / width).round() * width);
+
+        for &(bpm, count) in bins {
+            let slot = ((bpm - first.0) / width).round() + 1.0;
+            let h = bounds.height * count as f32 / max_count;
+            let color = if peak.is_some_and(|p| (p - bpm).abs() < width / 2.0) {
+                palette.success
+            } else {
+                palette.primary
+            };
+            frame.fill_rectangle(
+                Point::new(slot * slot_w, bounds.height - h),
+                iced::Size::new((slot_w - 1.0).max(1.0), h),
+                color,
+            );
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+// Input meter: RMS bar and peak marker in dBFS from LEVEL_FLOOR_DB to 0, red while clipping
+struct MeterView {
+    peak: f32,
+    rms: f32,
+    clipping: bool,
+}
+
+impl canvas::Program<Message> for MeterView {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.palette();
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            bounds.size(),
+            Color {
+                a: 0.1,
+                ..palette.text
+            },
+        );
+
+        let x = |level: f32| {
+            let db = (20.0 * level.max(1e-6).log10()).clamp(LEVEL_FLOOR_DB, 0.0);
+            bounds.width * (1.0 - db / LEVEL_FLOOR_DB)
+        };
+        let color = if self.clipping {
+            palette.danger
+        } else {
+            palette.primary
+        };
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            iced::Size::new(x(self.rms), bounds.height),
+            color,
+        );
+        let peak_x = x(self.peak);
+        frame.stroke(
+            &Path::line(Point::new(peak_x, 0.0), Point::new(peak_x, bounds.height)),
+            Stroke::default().with_color(color).with_width(2.0),
+        );
+
+        vec![frame.into_geometry()]
+    }
+}
+
+// Mini bars of the last detection: fine and coarse confidence with their
+// thresholds, then the analysis window level (dB from LEVEL_FLOOR_DB) with the
+// noise gate
+struct ConfidenceView {
+    fine: Option<f32>,
+    coarse: Option<f32>,
+    energy: Option<f32>,
+    thresholds: Option<(f32, f32)>,
+}
+
+impl canvas::Program<Message> for ConfidenceView {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.palette();
+        let energy_fraction = |level: f32| {
+            1.0 - (10.0 * level.max(1e-6).log10()).clamp(LEVEL_FLOOR_DB, 0.0) / LEVEL_FLOOR_DB
+        };
+        let bars = [
+            (self.fine, self.thresholds.map(|(fine, _)| fine)),
+            (self.coarse, self.thresholds.map(|(_, coarse)| coarse)),
+            (
+                self.energy.map(energy_fraction),
+                Some(energy_fraction(NOISE_GATE)),
+            ),
+        ];
+        let height = bounds.height / bars.len() as f32;
+        for (i, (value, marker)) in bars.into_iter().enumerate() {
+            let y = i as f32 * height;
+            let bar = iced::Size::new(bounds.width, (height - 2.0).max(1.0));
+            frame.fill_rectangle(
+                Point::new(0.0, y),
+                bar,
+                Color {
+                    a: 0.1,
+                    ..palette.text
+                },
+            );
+            if let Some(value) = value {
+                let below = marker.is_some_and(|marker| value < marker);
+                frame.fill_rectangle(
+                    Point::new(0.0, y),
+                    iced::Size::new(bounds.width * value.clamp(0.0, 1.0), bar.height),
+                    if below {
+                        palette.danger
+                    } else {
+                        palette.primary
+                    },
+                );
+            }
+            if let Some(marker) = marker {
+                let x = bounds.width * marker.clamp(0.0, 1.0);
+                frame.stroke(
+                    &Path::line(Point::new(x, y), Point::new(x, y + bar.height)),
+                    Stroke::default().with_color(palette.text).with_width(1.5),
+                );
+            }
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+// Sparkline of a device input level, in dBFS from LEVEL_FLOOR_DB to 0
+struct LevelView {
+    levels: Vec<f32>,
+}
+
+const LEVEL_FLOOR_DB: f32 = -60.0;
+
+impl canvas::Program<Message> for LevelView {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.palette();
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            bounds.size(),
+            Color {
+                a: 0.1,
+                ..palette.text
+            },
+        );
+        if self.levels.len() < 2 {
+            return vec![frame.into_geometry()];
+        }
+
+        let step = bounds.width / (self.levels.len() - 1) as f32;
+        let path = Path::new(|builder| {
+            for (i, &level) in self.levels.iter().enumerate() {
+                let db = (20.0 * level.max(1e-6).log10()).clamp(LEVEL_FLOOR_DB, 0.0);
+                let point = Point::new(i as f32 * step, bounds.height * db / LEVEL_FLOOR_DB);
+                if i == 0 {
+                    builder.move_to(point);
+                } else {
+                    builder.line_to(point);
+                }
+            }
+        });
+        frame.stroke(
+            &path,
+            Stroke::default()
+                .with_color(palette.primary)
+                .with_width(1.0),
+        );
+
+        vec![frame.into_geometry()]
+    }
+}
+
+// Device input level on the shared timeline: each point at its age (s) from the
+// right edge, dBFS from LEVEL_FLOOR_DB to 0
+struct LevelTimelineView {
+    points: Vec<(f32, f32)>,
+    span_s: f32,
+}
+
+impl canvas::Program<Message> for LevelTimelineView {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.palette();
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            bounds.size(),
+            Color {
+                a: 0.1,
+                ..palette.text
+            },
+        );
+
+        let visible: Vec<Point> = self
+            .points
+            .iter()
+            .filter(|(age, _)| *age <= self.span_s)
+            .map(|&(age, level)| {
+                let db = (20.0 * level.max(1e-6).log10()).clamp(LEVEL_FLOOR_DB, 0.0);
+                Point::new(
+                    bounds.width * (1.0 - age / self.span_s),
+                    bounds.height * db / LEVEL_FLOOR_DB,
+                )
+            })
+            .collect();
+        if visible.len() < 2 {
+            return vec![frame.into_geometry()];
+        }
+        let path = Path::new(|builder| {
+            builder.move_to(visible[0]);
+            for &point in &visible[1..] {
+                builder.line_to(point);
+            }
+        });
+        frame.stroke(
+            &path,
+            Stroke::default()
+                .with_color(palette.primary)
+                .with_width(1.0),
+        );
+
+        vec![frame.into_geometry()]
+    }
+}
+
+// This function runs in a background thread and does the heavy lifting
+fn run_analysis_loop(
+    tx: mpsc::Sender<GuiUpdate>,
+    rx_cmd: mpsc::Receiver<GuiCommand>,
+    config: AppConfig,
+    preferences: Preferences,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // At most 2 s of audio queued: a stalled loop drops the oldest packets
+    let (sender, receiver) = audio_channel(TARGET_SAMPLE_RATE as usize * 2);
+    let sender_clone = sender.clone(); // Keep a clone to restart audio capture
+    let mut last_ui_update = Instant::now();
+    let mut is_enabled = false;
+    let mut current_device: Option<String> = preferences.device.clone();
+    let mut link_enabled = preferences.link_enabled;
+    let mut live_device: Option<String> = None;
+
+    let mut gate_open = !config.gate.enabled;
+    let mut analyzer_config = config.analyzer_config();
+    // A hand-edited or stale preferences file must not stop the analysis
+    if let Some(analysis) = preferences.analysis {
+        match analysis.validate() {
+            Ok(()) => analysis.apply(&mut analyzer_config),
+            Err(e) => warn!("Saved analysis settings ignored: {}", e),
+        }
+    }
+    let mut pending_analysis = Some(AnalysisPreferences::from_config(&analyzer_config));
+    let mut histogram = config.tempo_histogram();
+    let mut bpm_history: std::collections::VecDeque<f32> =
+        std::collections::VecDeque::with_capacity(5);
+
+    let mut link_manager = LinkManager::new();
+    link_manager.set_quantum(preferences.link_quantum.unwrap_or(config.link.quantum));
+    let mut tempo_follow = TempoFollow::new(config.link.clone());
+    tempo_follow.set_follow(preferences.link_follow.unwrap_or(config.link.follow));
+    // Link, Art-Net, MIDI notes and the `[outputs]` sinks
+    let mut outputs = SinkRegistry::from_config(link_manager, &config);
+    info!("Outputs: {}", outputs.names().join(", "));
+
+    // Per-beat band levels only feed the Art-Net chase channels
+    let mut live = LiveAnalysis::new(
+        TARGET_SAMPLE_RATE,
+        analyzer_config,
+        config.governor.clone(),
+        outputs.wants_beat_features(),
+    )?;
+    live.analyzer_mut()
+        .set_octave_preference(preferences.octave);
+    live.set_recording(config.recording.clone());
+    live.set_key_detection(config.key.clone())?;
+    live.set_verification(config.verification.clone())?;
+    let verification = config.verification.enabled;
+    // Tempo pinned from the window, the analysis keeps running under it
+    let mut manual_bpm: Option<f32> = None;
+    live.set_beat_grid(config.beat_grid.clone());
+    live.set_statistics(config.stats.clone());
+
+    let mut audio_capture: Option<AudioCapture> = None;
+    let audio_warmup = config.audio_warmup();
+    // Plays what the analyzer hears, or a click on its beats
+    let monitor = if config.monitor.enabled {
+        AudioMonitor::new(&config.monitor)
+            .map_err(|e| error!("Monitor output unavailable: {}", e))
+            .ok()
+    } else {
+        None
+    };
+    let mut pending_notice: Option<String> = None;
+    // Sinks failing at the last check, a change is worth a notice
+    let mut failing_sinks: Vec<&'static str> = Vec::new();
+    let mut dropped_frames_total: u64 = 0;
+    let mut input_level: Option<(f32, f32)> = None;
+
+    loop {
+        // Check for GUI commands
+        while let Ok(cmd) = rx_cmd.try_recv() {
+            match cmd {
+                GuiCommand::SetDetection(enabled) => {
+                    outputs.link_mut().link_state(enabled && link_enabled);
+                    is_enabled = enabled;
+                    if enabled {
+                        if audio_capture.is_none() {
+                            info!("Starting audio capture...");
+                            // Re-create audio capture
+                            match AudioCapture::new(
+                                sender_clone.clone(),
+                                current_device.clone(),
+                                TARGET_SAMPLE_RATE,
+                                None,
+                                Some(Duration::from_millis(500)),
+                                audio_warmup,
+                            ) {
+                                Ok(capture) => audio_capture = Some(capture),
+                                Err(e) => error!("Failed to restart audio capture: {}", e),
+                            }
+                        }
+                    } else {
+                        if audio_capture.is_some() {
+                            info!("Stopping audio capture...");
+                            audio_capture = None; // Drops the capture and stops the stream
+                        }
+                        live.clear();
+                        bpm_history.clear();
+                    }
+                }
+                GuiCommand::SetDevice(device_name) => {
+                    info!("Switching device to: {:?}", device_name);
+                    current_device = device_name.clone();
+                    if let Some(capture) = &mut audio_capture {
+                        if let Err(e) = capture.set_device(device_name) {
+                            error!("Failed to switch device: {}", e);
+                        }
+                    }
+                }
+                GuiCommand::SetGate(open) => {
+                    gate_open = open;
+                    live.clear();
+                }
+                GuiCommand::SetBpm(new_bpm) => {
+                    outputs.link_mut().update_tempo(new_bpm, false, None);
+                }
+                GuiCommand::SetOverride(bpm) => {
+                    manual_bpm = bpm;
+                    match bpm {
+                        Some(bpm) => {
+                            info!("Manual tempo override: {:.1} BPM", bpm);
+                            outputs.on_result(&TempoUpdate {
+                                shown_bpm: bpm,
+                                bpm: Some(bpm),
+                                beat_age: live.analyzer().last_beat_age(),
+                                leading: true,
+                                is_drop: false,
+                                beat_offset: None,
+                                confidence: 1.0,
+                                section: live.analyzer().section(),
+                                key: live.key(),
+                            });
+                        }
+                        None => info!("Manual tempo override off"),
+                    }
+                }
+                GuiCommand::SetLink(enabled) => {
+                    info!("Ableton Link: {}", enabled);
+                    link_enabled = enabled;
+                    outputs.link_mut().link_state(is_enabled && enabled);
+                }
+                GuiCommand::SetLinkFollow(follow) => {
+                    info!("Follow Link tempo: {}", follow);
+                    tempo_follow.set_follow(follow);
+                }
+                GuiCommand::SetLinkQuantum(quantum) => {
+                    info!("Link quantum: {}", quantum);
+                    outputs.link_mut().set_quantum(quantum);
+                }
+                GuiCommand::SetAnalysis(analysis) => {
+                    let mut new_config = live.config();
+                    analysis.apply(&mut new_config);
+                    match live.set_config(new_config) {
+                        Ok(()) => {
+                            info!("Analyzer settings: {:?}", analysis);
+                            bpm_history.clear();
+                        }
+                        Err(e) => {
+                            error!("Analyzer settings rejected: {}", e)
+                        }
+                    }
+                }
+                GuiCommand::SetThresholds(thresholds) => {
+                    info!(
+                        "Confidence thresholds: fine {:.2}, coarse {:.2}",
+                        thresholds.fine_confidence, thresholds.coarse_confidence
+                    );
+                    live.set_thresholds(thresholds);
+                }
+                GuiCommand::SetOctave(octave) => {
+                    info!("Octave preference: {:?}", octave);
+                    live.analyzer_mut().set_octave_preference(octave);
+                    bpm_history.clear();
+                }
+                GuiCommand::ImportPreset(path) => match AnalyzerPreset::load(&path) {
+                    Ok(mut preset) => {
+                        preset.analyzer.pipelined = live.config().pipelined;
+                        preset.analyzer.hop_duration = live.config().hop_duration;
+                        match live.set_config(preset.analyzer) {
+                            Ok(()) => {
+                                info!("Preset '{}' imported from {:?}", preset.name, path);
+                                pending_analysis =
+                                    Some(AnalysisPreferences::from_config(&preset.analyzer));
+                                bpm_history.clear();
+                            }
+                            Err(e) => {
+                                error!("Preset {:?} rejected: {}", path, e)
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        error!("Failed to import preset {:?}: {}", path, e)
+                    }
+                },
+                GuiCommand::ExportDiagnostics(mut report) => {
+                    report.sample_rate = Some(live.sample_rate());
+                    report.analyzer = Some(live.config());
+                    report.analyzer_state = Some(live.analyzer().snapshot());
+                    pending_notice = Some(match report.export(std::path::Path::new(".")) {
+                        Ok(dir) => {
+                            info!("Diagnostics exported to {:?}", dir);
+                            format!("Saved to {}", dir.display())
+                        }
+                        Err(e) => {
+                            error!("Failed to export diagnostics: {}", e);
+                            format!("Export failed: {}", e)
+                        }
+                    });
+                }
+                GuiCommand::DumpState => {
+                    let state = live.analyzer().snapshot();
+                    pending_notice = Some(
+                        match diagnostics::dump_state(&state, std::path::Path::new(".")) {
+                            Ok(path) => {
+                                info!("Analyzer state dumped to {:?}", path);
+                                format!("Saved to {}", path.display())
+                            }
+                            Err(e) => {
+                                error!("Failed to dump state: {}", e);
+                                format!("Dump failed: {}", e)
+                            }
+                        },
+                    );
+                }
+                GuiCommand::SaveRecording => {
+                    pending_notice = Some(match live.save_recording() {
+                        Ok(path) => {
+                            info!("Recent audio saved to {:?}", path);
+                            format!("Saved to {}", path.display())
+                        }
+                        Err(e) => {
+                            error!("Failed to save recent audio: {}", e);
+                            format!("Recording failed: {}", e)
+                        }
+                    });
+                }
+                GuiCommand::SaveStats => {
+                    pending_notice = Some(match live.statistics().save(&config.stats.directory) {
+                        Ok(path) => {
+                            info!("Set statistics saved to {:?}", path);
+                            format!("Saved to {}", path.display())
+                        }
+                        Err(e) => {
+                            error!("Failed to save the set statistics: {}", e);
+                            format!("Statistics not saved: {}", e)
+                        }
+                    });
+                }
+                GuiCommand::ResetStats => {
+                    info!("Set statistics reset");
+                    live.reset_statistics();
+                }
+                GuiCommand::ExportPreset(path) => {
+                    let preset = AnalyzerPreset {
+                        name: path
+                            .file_stem()
+                            .map(|s| s.to_string_lossy().into_owned())
+                            .unwrap_or_else(|| "preset".to_string()),
+                        analyzer: live.config(),
+                    };
+                    match preset.save(&path) {
+                        Ok(()) => info!("Preset exported to {:?}", path),
+                        Err(e) => error!("Failed to export preset {:?}: {}", path, e),
+                    }
+                }
+            }
+        }
+
+        // Use recv_timeout to allow checking commands and updating UI even if no audio comes in
+        match receiver.recv_timeout(Duration::from_millis(50)) {
+            Ok(AudioMessage::Samples(packet)) => {
+                if is_enabled && gate_open {
+                    if let Some(monitor) = &monitor {
+                        monitor.push(&packet);
+                    }
+                    if let Some(features) = live.push(&packet) {
+                        outputs.on_beat_features(features);
+                    }
+                    outputs.tick(Instant::now());
+
+                    if live.hop_ready() {
+                        let processed = live.analyze();
+                        let confidence = match &processed {
+                            Ok(Some(result)) => Some(result.confidence),
+                            _ => None,
+                        };
+                        let role = tempo_follow.update(
+                            confidence,
+                            outputs.link().num_peers(),
+                            Instant::now(),
+                        );
+                        if let Ok(Some(result)) = processed {
+                            // New track: the old tempo must not linger in the average
+                            if result.tempo_change.is_some() {
+                                bpm_history.clear();
+                            }
+                            // Update history for moving average
+                            if bpm_history.len() >= 5 {
+                                bpm_history.pop_front();
+                            }
+                            bpm_history.push_back(result.bpm);
+                            histogram.push(result.bpm);
+
+                            // Calculate average
                             let avg_bpm: f32 =
                                 bpm_history.iter().sum::<f32>() / bpm_history.len() as f32;
 
-                            let bpm_to_send = Some(avg_bpm);
+                            // Sync Ableton Link with the averaged BPM, or adopt the
+                            // session tempo while following it. With `[verification]`
+                            // the outputs get the verified tempo, the window keeps
+                            // showing the fast one. A manual override holds them all
+                            let output = if verification {
+                                result.verified_bpm
+                            } else {
+                                Some(avg_bpm)
+                            };
+                            let tempo = match role {
+                                LinkRole::Leading => avg_bpm,
+                                LinkRole::Following => outputs.link_mut().get_tempo() as f32,
+                            };
+                            let output = match (manual_bpm, role) {
+                                (Some(bpm), _) => Some(bpm),
+                                (None, LinkRole::Leading) => output,
+                                (None, LinkRole::Following) => Some(tempo),
+                            };
+
                             // Send update to GUI
                             let _ = tx.send(GuiUpdate {
-                                bpm: bpm_to_send,
-                                num_peers: link_manager.num_peers(),
+                                bpm: Some(tempo),
+                                num_peers: outputs.link().num_peers(),
+                                link_role: role,
+                                link_beat: link_enabled.then(|| {
+                                    (
+                                        outputs.link_mut().beat_at_time(Instant::now()),
+                                        outputs.link_mut().get_tempo(),
+                                    )
+                                }),
+                                envelope: Some(live.analyzer().envelope_frame()),
+                                debug: Some(live.analyzer().debug().clone()),
+                                is_drop: result.is_drop,
+                                key: result.key,
+                                level: input_level.take(),
+                                last_beat: live
+                                    .analyzer()
+                                    .last_beat_age()
+                                    .map(|age| Instant::now() - age),
+                                live_device: live_device.clone(),
+                                histogram: Some(histogram.snapshot()),
+                                notice: pending_notice.take(),
+                                analysis: pending_analysis.take(),
+                                confidence: Some((result.confidence, result.coarse_confidence)),
+                                energy: Some(live.analyzer().input_level()),
+                                stats: Some(live.statistics()),
                             });
 
-                            // Sync Ableton Link
-                            // Use the averaged BPM for sync
-                            link_manager.update_tempo(
-                                avg_bpm as f64,
-                                result.is_drop,
-                                result.beat_offset,
-                            );
-                            println!(
-                                "Avg BPM: {:.1} | Raw BPM: {:.1} | Conf: {:.2}",
-                                avg_bpm, result.bpm, result.confidence
+                            if let Some(monitor) = &monitor {
+                                monitor.set_beat(result.bpm, live.analyzer().last_beat_age());
+                            }
+                            // The override holds the Link phase too: no drop re-sync
+                            outputs.on_result(&TempoUpdate {
+                                shown_bpm: tempo,
+                                bpm: output,
+                                beat_age: live.analyzer().last_beat_age(),
+                                leading: manual_bpm.is_some() || role == LinkRole::Leading,
+                                is_drop: result.is_drop,
+                                beat_offset: result.beat_offset.filter(|_| manual_bpm.is_none()),
+                                confidence: result.confidence,
+                                section: result.section,
+                                key: result.key,
+                            });
+                            info!(
+                                "Avg BPM: {:.1} | Raw BPM: {:.1} | Conf: {:.2} | Link: {:?}",
+                                avg_bpm, result.bpm, result.confidence, role
                             );
                         }
 
                         last_ui_update = Instant::now();
-
-                        new_samples_accumulator.clear();
                     }
                 } else {
-                    // Drain any remaining samples if disabled but still receiving
-                    new_samples_accumulator.clear();
+                    // Drain any remaining samples if disabled (or gated) but still receiving
+                    live.clear();
+                    if let Some(monitor) = &monitor {
+                        monitor.set_beat(0.0, None);
+                    }
+                }
+                receiver.recycle(packet);
+            }
+            Ok(AudioMessage::Level { peak, rms }) => {
+                // Highest peak since the last GUI update, so short clips are not missed
+                input_level = Some(match input_level {
+                    Some((max_peak, _)) => (max_peak.max(peak), rms),
+                    None => (peak, rms),
+                });
+                if is_enabled && gate_open {
+                    outputs.on_level(rms);
                 }
             }
             Ok(AudioMessage::Reset) => {
-                new_samples_accumulator.clear();
+                METRICS.audio_restart();
+                if let Err(e) = live.reset() {
+                    error!("Failed to warm-start the analyzer: {}", e)
+                }
+            }
+            Ok(AudioMessage::Overrun { dropped_frames }) => {
+                dropped_frames_total += dropped_frames;
+                METRICS.frames_dropped(dropped_frames);
+                error!(
+                    "Audio overrun: {} frames dropped ({} since start)",
+                    dropped_frames, dropped_frames_total
+                );
+                pending_notice = Some(format!(
+                    "Audio overrun: {} frames dropped so far",
+                    dropped_frames_total
+                ));
+            }
+            Ok(AudioMessage::DeviceChanged(name)) => {
+                info!("Live input device: {}", name);
+                live_device = Some(name);
             }
             Ok(AudioMessage::SampleRateChanged(rate)) => {
-                println!("Audio sample rate changed to: {} Hz", rate);
-                match BpmAnalyzer::new(rate, None) {
-                    Ok(new_analyzer) => {
-                        analyzer = new_analyzer;
-                        // Update HOP_SIZE to match 1 second of audio at new rate
-                        current_hop_size = (rate / 2) as usize;
-                        // Resize accumulator
-                        if new_samples_accumulator.capacity() < current_hop_size {
-                            new_samples_accumulator
-                                .reserve(current_hop_size - new_samples_accumulator.len());
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to re-initialize analyzer with rate {}: {}", rate, e)
-                    }
+                info!("Audio sample rate changed to: {} Hz", rate);
+                if let Some(monitor) = &monitor {
+                    monitor.set_input_rate(rate);
+                }
+                if let Err(e) = live.set_sample_rate(rate) {
+                    error!("Failed to re-initialize analyzer with rate {}: {}", rate, e)
                 }
             }
-            Err(mpsc::RecvTimeoutError::Timeout) => {
+            Err(RecvTimeoutError::Timeout) => {
                 // No audio received (expected if disabled)
             }
-            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        // A sink going down or back up is worth a notice, not each frame
+        let failures = outputs.failures();
+        if let Some((name, error)) = failures
+            .iter()
+            .find(|(name, _)| !failing_sinks.contains(name))
+        {
+            pending_notice = Some(format!("{} output failing: {}", name, error));
+        } else if let Some(name) = failing_sinks
+            .iter()
+            .find(|name| !failures.iter().any(|(failing, _)| failing == *name))
+        {
+            pending_notice = Some(format!("{} output back", name));
         }
+        failing_sinks = failures.into_iter().map(|(name, _)| name).collect();
 
         // Periodic UI update (for peer count) if we haven't sent one recently
-        if last_ui_update.elapsed() > Duration::from_millis(200) {
-            let link_bpm = link_manager.get_tempo();
+        if last_ui_update.elapsed() > Duration::from_millis(200)
+            || pending_notice.is_some()
+            || pending_analysis.is_some()
+        {
+            let link_bpm = outputs.link_mut().get_tempo();
             let _ = tx.send(GuiUpdate {
                 bpm: Some(link_bpm as f32), // Send Link BPM instead of None
-                num_peers: link_manager.num_peers(),
+                num_peers: outputs.link().num_peers(),
+                link_role: tempo_follow.role(),
+                link_beat: (is_enabled && link_enabled)
+                    .then(|| (outputs.link_mut().beat_at_time(Instant::now()), link_bpm)),
+                envelope: is_enabled.then(|| live.analyzer().envelope_frame()),
+                debug: is_enabled.then(|| live.analyzer().debug().clone()),
+                is_drop: false,
+                key: None,
+                level: input_level.take(),
+                last_beat: None,
+                live_device: live_device.clone(),
+                histogram: None,
+                notice: pending_notice.take(),
+                analysis: pending_analysis.take(),
+                confidence: None,
+                energy: is_enabled.then(|| live.analyzer().input_level()),
+                stats: Some(live.statistics()),
             });
             last_ui_update = Instant::now();
         }
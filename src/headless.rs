@@ -0,0 +1,301 @@
+use crate::platform::TARGET_SAMPLE_RATE;
+use rust_bpm_analyzer::config::AppConfig;
+use rust_bpm_analyzer::core_bpm::analyzer::{AnalysisResult, BpmAnalyzer};
+use rust_bpm_analyzer::core_bpm::audio::AudioMonitor;
+use rust_bpm_analyzer::core_bpm::audio_channel::AudioRecycler;
+use rust_bpm_analyzer::core_bpm::envelope_input::{EnvelopeSource, spawn_envelope_input};
+use rust_bpm_analyzer::core_bpm::meter::CLIP_LEVEL;
+use rust_bpm_analyzer::core_bpm::simulated;
+use rust_bpm_analyzer::core_bpm::{AudioCapture, AudioMessage, LiveAnalysis, audio_channel};
+use rust_bpm_analyzer::metrics::{self, METRICS};
+use rust_bpm_analyzer::network_sync::LinkManager;
+use rust_bpm_analyzer::network_sync::follow::{LinkRole, TempoFollow};
+use rust_bpm_analyzer::outputs::{SinkRegistry, TempoUpdate};
+use std::error::Error;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+// A clipping input is reported at most once per period
+const CLIP_WARNING_PERIOD: Duration = Duration::from_secs(10);
+
+/// Console analyzer (`--mode headless`, and the whole minimal build): captures the
+/// default input, prints every tempo and forwards it to Ableton Link and Art-Net.
+/// Stops when the capture ends or on Ctrl+C.
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let config = AppConfig::load();
+    if config.metrics.enabled {
+        metrics::serve(config.metrics.port);
+    }
+
+    // At most 2 s of audio queued, older packets are dropped beyond that
+    let (audio_sender, audio_receiver) = audio_channel(TARGET_SAMPLE_RATE as usize * 2);
+    // Default input, or generated audio with `--sim`
+    let _audio_capture = AudioCapture::new(
+        audio_sender,
+        simulated::cli_device(),
+        TARGET_SAMPLE_RATE,
+        None,
+        Some(Duration::from_millis(500)),
+        config.audio_warmup(),
+    )?;
+    let mut console = ConsoleAnalysis::new(&config)?;
+    console.set_recycler(audio_receiver.recycler());
+
+    info!("Listening... (Press Ctrl+C to stop)");
+
+    while let Ok(msg) = audio_receiver.recv() {
+        if let Some((result, role)) = console.handle(msg) {
+            info!(
+                "BPM: {:.1} | Drop: {} | Conf: {:.2} | CoarseConf: {:.2} | Track: {:.2} | Swing: {:?} | Key: {} | Section: {} | Link: {:?}",
+                result.bpm,
+                result.is_drop,
+                result.confidence,
+                result.coarse_confidence,
+                result.tracking_confidence,
+                result.swing,
+                result.key.map_or("-".to_string(), |key| key.camelot()),
+                result.section.name(),
+                role
+            );
+        }
+    }
+    console.finish();
+
+    Ok(())
+}
+
+/// Console analyzer on an onset envelope computed elsewhere (`--envelope`): no
+/// capture, the blocks go straight to the tempo estimation and the tempo to Ableton
+/// Link and the output sinks. Stops when the source closes.
+pub fn run_envelope(source: EnvelopeSource) -> Result<(), Box<dyn Error>> {
+    let config = AppConfig::load();
+    let mut link_manager = LinkManager::new();
+    link_manager.link_state(true);
+    link_manager.set_quantum(config.link.quantum);
+    let mut outputs = SinkRegistry::from_config(link_manager, &config);
+    info!("Outputs: {}", outputs.names().join(", "));
+
+    let mut analyzer = BpmAnalyzer::new(TARGET_SAMPLE_RATE, Some(config.analyzer_config()))?;
+    info!("Envelope expected at {:.1} Hz", analyzer.fine_rate());
+    let (sender, receiver) = mpsc::channel();
+    let _input = spawn_envelope_input(source, sender)?;
+
+    while let Ok(block) = receiver.recv() {
+        outputs.tick(Instant::now());
+        match analyzer.process_envelope(&block) {
+            Ok(Some(result)) => {
+                info!(
+                    "BPM: {:.1} | Drop: {} | Conf: {:.2} | CoarseConf: {:.2} | Track: {:.2}",
+                    result.bpm,
+                    result.is_drop,
+                    result.confidence,
+                    result.coarse_confidence,
+                    result.tracking_confidence
+                );
+                // No audio, no beat phase: the tempo alone leaves
+                outputs.on_result(&TempoUpdate {
+                    shown_bpm: result.bpm,
+                    bpm: result.verified_bpm,
+                    beat_age: None,
+                    leading: true,
+                    is_drop: result.is_drop,
+                    beat_offset: None,
+                    confidence: result.confidence,
+                    section: result.section,
+                    key: result.key,
+                });
+            }
+            Ok(None) => {}
+            Err(e) => error!("Analysis error: {}", e),
+        }
+    }
+    info!("Envelope input closed");
+
+    Ok(())
+}
+
+/// Analysis behind the console front-ends (`--mode headless` and `--mode tui`):
+/// tempo detection from the capture messages, fanned out to Ableton Link and the
+/// output sinks.
+pub struct ConsoleAnalysis {
+    live: LiveAnalysis,
+    outputs: SinkRegistry,
+    tempo_follow: TempoFollow,
+    last_clip_warning: Option<Instant>,
+    // Gives the packet buffers back to the capture callback
+    recycler: Option<AudioRecycler>,
+    monitor: Option<AudioMonitor>,
+}
+
+impl ConsoleAnalysis {
+    pub fn new(config: &AppConfig) -> Result<Self, Box<dyn Error>> {
+        // No-op when built without the `link` feature
+        let mut link_manager = LinkManager::new();
+        link_manager.link_state(true);
+        link_manager.set_quantum(config.link.quantum);
+        let outputs = SinkRegistry::from_config(link_manager, config);
+        info!("Outputs: {}", outputs.names().join(", "));
+
+        // Per-beat band levels only feed the Art-Net chase channels
+        let mut live = LiveAnalysis::new(
+            TARGET_SAMPLE_RATE,
+            config.analyzer_config(),
+            config.governor.clone(),
+            outputs.wants_beat_features(),
+        )?;
+        live.set_recording(config.recording.clone());
+        live.set_key_detection(config.key.clone())?;
+        live.set_verification(config.verification.clone())?;
+        live.set_beat_grid(config.beat_grid.clone());
+        live.set_statistics(config.stats.clone());
+
+        let monitor = if config.monitor.enabled {
+            AudioMonitor::new(&config.monitor)
+                .map_err(|e| warn!("Monitor output unavailable: {}", e))
+                .ok()
+        } else {
+            None
+        };
+
+        Ok(Self {
+            live,
+            outputs,
+            tempo_follow: TempoFollow::new(config.link.clone()),
+            last_clip_warning: None,
+            recycler: None,
+            monitor,
+        })
+    }
+
+    /// Returns each packet buffer to the capture once analyzed (see
+    /// `AudioSender::buffer`).
+    pub fn set_recycler(&mut self, recycler: AudioRecycler) {
+        self.recycler = Some(recycler);
+    }
+
+    /// Handles one capture message. Returns the detection of a completed hop with
+    /// the Link role decided for it.
+    pub fn handle(&mut self, msg: AudioMessage) -> Option<(AnalysisResult, LinkRole)> {
+        match msg {
+            AudioMessage::Samples(packet) => {
+                if let Some(monitor) = &self.monitor {
+                    monitor.push(&packet);
+                }
+                let features = self.live.push(&packet);
+                if let Some(recycler) = &self.recycler {
+                    recycler.recycle(packet);
+                }
+                if let Some(features) = features {
+                    self.outputs.on_beat_features(features);
+                }
+                self.outputs.tick(Instant::now());
+                if !self.live.hop_ready() {
+                    return None;
+                }
+
+                let processed = self.live.analyze();
+                let confidence = match &processed {
+                    Ok(Some(result)) => Some(result.confidence),
+                    _ => None,
+                };
+                let role = self.tempo_follow.update(
+                    confidence,
+                    self.outputs.link().num_peers(),
+                    Instant::now(),
+                );
+                match processed {
+                    Ok(Some(result)) => {
+                        // Our tempo leads the session, or the session tempo is adopted.
+                        // Only a verified tempo leaves (see `AnalysisResult::verified_bpm`)
+                        let (shown_bpm, bpm) = match role {
+                            LinkRole::Leading => (result.bpm, result.verified_bpm),
+                            LinkRole::Following => {
+                                let session = self.outputs.link_mut().get_tempo() as f32;
+                                (session, Some(session))
+                            }
+                        };
+                        let beat_age = self.live.analyzer().last_beat_age();
+                        if let Some(monitor) = &self.monitor {
+                            monitor.set_beat(result.bpm, beat_age);
+                        }
+                        self.outputs.on_result(&TempoUpdate {
+                            shown_bpm,
+                            bpm,
+                            beat_age,
+                            leading: role == LinkRole::Leading,
+                            is_drop: result.is_drop,
+                            beat_offset: result.beat_offset,
+                            confidence: result.confidence,
+                            section: result.section,
+                            key: result.key,
+                        });
+                        return Some((result, role));
+                    }
+                    Ok(None) => {}
+                    Err(e) => error!("Analysis error: {}", e),
+                }
+            }
+            AudioMessage::Overrun { dropped_frames } => {
+                warn!("Audio overrun: {} frames dropped", dropped_frames);
+                METRICS.frames_dropped(dropped_frames);
+            }
+            AudioMessage::Level { peak, rms } => {
+                if peak >= CLIP_LEVEL
+                    && self
+                        .last_clip_warning
+                        .is_none_or(|at| at.elapsed() >= CLIP_WARNING_PERIOD)
+                {
+                    self.last_clip_warning = Some(Instant::now());
+                    warn!("Input clipping (peak {:.2}), lower the capture gain", peak);
+                }
+                self.outputs.on_level(rms);
+            }
+            AudioMessage::DeviceChanged(name) => info!("Audio input: {}", name),
+            AudioMessage::Reset => {
+                info!("Audio stream reset, warm-starting the analyzer");
+                METRICS.audio_restart();
+                if let Err(e) = self.live.reset() {
+                    error!("Failed to warm-start the analyzer: {}", e)
+                }
+            }
+            AudioMessage::SampleRateChanged(rate) => {
+                info!("Audio sample rate changed to: {} Hz", rate);
+                if let Some(monitor) = &self.monitor {
+                    monitor.set_input_rate(rate);
+                }
+                if let Err(e) = self.live.set_sample_rate(rate) {
+                    error!("Failed to re-initialize analyzer with rate {}: {}", rate, e)
+                }
+            }
+        }
+        None
+    }
+
+    /// Writes what is still pending when the analysis stops (beat grid, set
+    /// statistics).
+    pub fn finish(&mut self) {
+        match self.live.save_beat_grid() {
+            Some(Ok(path)) => info!("Beat grid written to {:?}", path),
+            Some(Err(e)) => error!("Failed to save the beat grid: {}", e),
+            None => {}
+        }
+        match self.live.save_statistics() {
+            Some(Ok(path)) => info!("Set statistics written to {:?}", path),
+            Some(Err(e)) => error!("Failed to save the set statistics: {}", e),
+            None => {}
+        }
+    }
+
+    /// Peers of the Ableton Link session.
+    #[cfg(feature = "tui")]
+    pub fn link_peers(&self) -> usize {
+        self.outputs.link().num_peers()
+    }
+
+    /// Tempo of the Ableton Link session.
+    #[cfg(feature = "tui")]
+    pub fn link_tempo(&mut self) -> f64 {
+        self.outputs.link_mut().get_tempo()
+    }
+}
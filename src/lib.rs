@@ -0,0 +1,16 @@
+//! Analysis core of the BPM analyzer: capture and tempo detection, Link and
+//! device sync, output sinks and the config they share. The front-ends (window,
+//! console, terminal dashboard, embedded devices) live in the binary.
+
+pub mod config;
+pub mod core_bpm;
+pub mod logging;
+pub mod metrics;
+#[cfg_attr(not(feature = "midi"), path = "midi_disabled.rs")]
+pub mod midi;
+// Beat notes for lighting software, a stand-in without the `midi` feature
+#[cfg_attr(not(feature = "midi"), path = "midi_beats_disabled.rs")]
+pub mod midi_beats;
+pub mod midi_mapping;
+pub mod network_sync;
+pub mod outputs;
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{Debug, Write as _};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{EnvFilter, fmt};
+
+// Lines kept for the diagnostics bundle
+const RECENT_CAPACITY: usize = 500;
+// Warnings and errors kept for remote diagnostics
+const EVENT_CAPACITY: usize = 100;
+
+static RECENT_LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static EVENTS: Mutex<VecDeque<LogEvent>> = Mutex::new(VecDeque::new());
+static STARTED_AT: OnceLock<Instant> = OnceLock::new();
+
+/// `[log]` section of the config file. `RUST_LOG` replaces `level` and `modules`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// Level of this program: error, warn, info, debug or trace
+    pub level: String,
+    /// Level per module, e.g. `"core_bpm::audio" = "debug"`
+    pub modules: BTreeMap<String, String>,
+    /// Devices: directory of the daily log files
+    pub directory: Option<PathBuf>,
+    /// Daily files kept in `directory`
+    pub max_files: usize,
+    /// Keep warnings and errors for remote diagnostics (`GET /logs` on devices)
+    pub forward: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            modules: BTreeMap::new(),
+            directory: None,
+            max_files: 7,
+            forward: true,
+        }
+    }
+}
+
+impl LogConfig {
+    /// `EnvFilter` directives: dependencies only report warnings, this program
+    /// logs at `level` and the listed modules at their own level.
+    pub fn directives(&self) -> String {
+        let program = env!("CARGO_CRATE_NAME");
+        let mut directives = format!("warn,{}={}", program, self.level);
+        for (module, level) in &self.modules {
+            let module = module.trim_start_matches("crate::");
+            let _ = write!(directives, ",{}::{}={}", program, module, level);
+        }
+        directives
+    }
+}
+
+/// A warning or an error, as sent to remote diagnostics.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEvent {
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    pub level: String,
+    /// Module that logged it
+    pub target: String,
+    pub message: String,
+}
+
+/// Installs the `tracing` subscriber: console (unless `console` is false, the
+/// terminal UI owns the screen), daily files on devices, and the in-memory
+/// records of `recent_lines` and `recent_events`.
+pub fn init(config: &LogConfig, console: bool) {
+    STARTED_AT.get_or_init(Instant::now);
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::try_new(config.directives()).unwrap_or_else(|e| {
+            eprintln!("Invalid [log] levels: {}. Using info.", e);
+            EnvFilter::new(LogConfig::default().directives())
+        })
+    });
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(console.then(|| fmt::layer().with_target(false)))
+        .with(MemoryLayer {
+            forward: config.forward,
+        });
+    #[cfg(all(
+        feature = "embedded-hw",
+        any(target_arch = "aarch64", target_arch = "arm"),
+        target_os = "linux"
+    ))]
+    let registry = registry.with(file_layer(config));
+
+    if let Err(e) = registry.try_init() {
+        eprintln!("Logging already initialized: {}", e);
+    }
+}
+
+// Daily files `rust-bpm-analyzer.<date>.log` in `[log] directory`
+#[cfg(all(
+    feature = "embedded-hw",
+    any(target_arch = "aarch64", target_arch = "arm"),
+    target_os = "linux"
+))]
+fn file_layer<S>(config: &LogConfig) -> Option<impl Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+    let directory = config.directory.as_ref()?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(env!("CARGO_PKG_NAME"))
+        .filename_suffix("log")
+        .max_log_files(config.max_files.max(1))
+        .build(directory);
+    match appender {
+        Ok(appender) => Some(fmt::layer().with_ansi(false).with_writer(appender)),
+        Err(e) => {
+            eprintln!("Log files disabled ({:?}): {}", directory, e);
+            None
+        }
+    }
+}
+
+/// Last log lines, oldest first, for the diagnostics bundle.
+pub fn recent_lines() -> Vec<String> {
+    RECENT_LINES
+        .lock()
+        .map(|lines| lines.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Last warnings and errors, oldest first (empty with `forward = false`).
+pub fn recent_events() -> Vec<LogEvent> {
+    EVENTS
+        .lock()
+        .map(|events| events.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn push<T>(buffer: &Mutex<VecDeque<T>>, item: T, capacity: usize) {
+    if let Ok(mut buffer) = buffer.lock() {
+        if buffer.len() >= capacity {
+            buffer.pop_front();
+        }
+        buffer.push_back(item);
+    }
+}
+
+/// Keeps the records in memory. The Windows GUI has no console, so the
+/// diagnostics bundle is the only way to get its logs.
+struct MemoryLayer {
+    forward: bool,
+}
+
+impl<S: Subscriber> Layer<S> for MemoryLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut message = MessageVisitor::default();
+        event.record(&mut message);
+
+        let elapsed = STARTED_AT.get_or_init(Instant::now).elapsed();
+        push(
+            &RECENT_LINES,
+            format!(
+                "[{:>9.3}s] {:>5} {}",
+                elapsed.as_secs_f64(),
+                metadata.level(),
+                message.0
+            ),
+            RECENT_CAPACITY,
+        );
+
+        // ERROR < WARN < INFO in `tracing` ordering
+        if self.forward && *metadata.level() <= Level::WARN {
+            let time = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            push(
+                &EVENTS,
+                LogEvent {
+                    time,
+                    level: metadata.level().to_string(),
+                    target: metadata.target().to_string(),
+                    message: message.0,
+                },
+                EVENT_CAPACITY,
+            );
+        }
+    }
+}
+
+/// Formats the message of an event followed by its other fields.
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        } else {
+            let _ = write!(self.0, "{}={:?}", field.name(), value);
+        }
+    }
+}
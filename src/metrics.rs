@@ -0,0 +1,236 @@
+use serde::Deserialize;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info};
+
+/// Counters and gauges of the running analyzer, exported in the Prometheus text
+/// format. Updated from the analysis loops whether or not the export is enabled.
+pub static METRICS: Metrics = Metrics::new();
+
+static STARTED_AT: OnceLock<u64> = OnceLock::new();
+
+/// `[metrics]` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Serve `/metrics` (devices: on the HTTP API port)
+    pub enabled: bool,
+    /// Port of the `/metrics` listener of the GUI and headless modes
+    pub port: u16,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 9898,
+        }
+    }
+}
+
+pub struct Metrics {
+    frames: AtomicU64,
+    analyses: AtomicU64,
+    analysis_micros: AtomicU64,
+    // f32 bits, 0 while no tempo is detected
+    bpm: AtomicU32,
+    confidence: AtomicU32,
+    audio_restarts: AtomicU64,
+    dropped_frames: AtomicU64,
+    messages_received: AtomicU64,
+    messages_sent: AtomicU64,
+    send_errors: AtomicU64,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            frames: AtomicU64::new(0),
+            analyses: AtomicU64::new(0),
+            analysis_micros: AtomicU64::new(0),
+            bpm: AtomicU32::new(0),
+            confidence: AtomicU32::new(0),
+            audio_restarts: AtomicU64::new(0),
+            dropped_frames: AtomicU64::new(0),
+            messages_received: AtomicU64::new(0),
+            messages_sent: AtomicU64::new(0),
+            send_errors: AtomicU64::new(0),
+        }
+    }
+
+    /// Audio frames handed to the analysis
+    pub fn frames_processed(&self, frames: usize) {
+        self.frames.fetch_add(frames as u64, Ordering::Relaxed);
+    }
+
+    /// One analysis hop: its duration on the analysis thread and its tempo, if any
+    pub fn analysis(&self, duration: Duration, tempo: Option<(f32, f32)>) {
+        self.analyses.fetch_add(1, Ordering::Relaxed);
+        self.analysis_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        if let Some((bpm, confidence)) = tempo {
+            self.bpm.store(bpm.to_bits(), Ordering::Relaxed);
+            self.confidence
+                .store(confidence.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// The capture stream was reset (restart, new device or sample rate)
+    pub fn audio_restart(&self) {
+        self.audio_restarts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn frames_dropped(&self, frames: u64) {
+        self.dropped_frames.fetch_add(frames, Ordering::Relaxed);
+    }
+
+    /// Requests received by the HTTP API
+    pub fn message_received(&self) {
+        self.messages_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Art-Net frames sent
+    pub fn message_sent(&self) {
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Art-Net frames the network refused (interface down, no route)
+    pub fn message_send_failed(&self) {
+        self.send_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let load = |value: &AtomicU64| value.load(Ordering::Relaxed);
+        let gauge = |value: &AtomicU32| f32::from_bits(value.load(Ordering::Relaxed));
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(out, "# HELP bpm_analyzer_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE bpm_analyzer_{} {}", name, kind);
+            let _ = writeln!(out, "bpm_analyzer_{} {}", name, value);
+        };
+        metric(
+            "frames_processed_total",
+            "counter",
+            "Audio frames analyzed.",
+            load(&self.frames).to_string(),
+        );
+        metric(
+            "analyses_total",
+            "counter",
+            "Analysis hops run.",
+            load(&self.analyses).to_string(),
+        );
+        metric(
+            "analysis_seconds_total",
+            "counter",
+            "Time spent analyzing on the analysis thread.",
+            format!("{:.6}", load(&self.analysis_micros) as f64 / 1e6),
+        );
+        metric(
+            "bpm",
+            "gauge",
+            "Last detected tempo.",
+            gauge(&self.bpm).to_string(),
+        );
+        metric(
+            "confidence",
+            "gauge",
+            "Confidence of the last detected tempo.",
+            gauge(&self.confidence).to_string(),
+        );
+        metric(
+            "audio_restarts_total",
+            "counter",
+            "Resets of the capture stream.",
+            load(&self.audio_restarts).to_string(),
+        );
+        metric(
+            "dropped_frames_total",
+            "counter",
+            "Audio frames lost because the analysis fell behind.",
+            load(&self.dropped_frames).to_string(),
+        );
+        metric(
+            "network_messages_received_total",
+            "counter",
+            "Requests received by the HTTP API.",
+            load(&self.messages_received).to_string(),
+        );
+        metric(
+            "network_messages_sent_total",
+            "counter",
+            "Art-Net frames sent.",
+            load(&self.messages_sent).to_string(),
+        );
+        metric(
+            "network_send_errors_total",
+            "counter",
+            "Art-Net frames that failed to send.",
+            load(&self.send_errors).to_string(),
+        );
+        metric(
+            "start_time_seconds",
+            "gauge",
+            "Start time of the process since the Unix epoch.",
+            started_at().to_string(),
+        );
+        out
+    }
+}
+
+fn started_at() -> u64 {
+    *STARTED_AT.get_or_init(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    })
+}
+
+/// Serves `GET /metrics` on `port` from a background thread (GUI and headless modes,
+/// the devices use their HTTP API).
+pub fn serve(port: u16) {
+    started_at();
+    let listener = match TcpListener::bind(("0.0.0.0", port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Metrics endpoint unavailable on port {}: {}", port, e);
+            return;
+        }
+    };
+    info!("Metrics on http://0.0.0.0:{}/metrics", port);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+            let mut request_line = String::new();
+            if BufReader::new(&stream)
+                .read_line(&mut request_line)
+                .is_err()
+            {
+                continue;
+            }
+            let response = match request_line.split_whitespace().nth(1) {
+                Some("/metrics") => {
+                    let body = METRICS.render();
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                }
+                _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string(),
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+}
@@ -1,6 +1,13 @@
 use midir::{Ignore, MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
 use std::error::Error;
 use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+const INPUT_CLIENT: &str = "Rust BPM Analyzer Input";
+const OUTPUT_CLIENT: &str = "Rust BPM Analyzer Output";
+// Time between two scans of the MIDI ports (controller unplugged or plugged again)
+const RESCAN_PERIOD: Duration = Duration::from_secs(2);
 
 #[derive(Debug, Clone)]
 pub enum MidiEvent {
@@ -9,6 +16,10 @@ pub enum MidiEvent {
         note: u8,
         velocity: u8,
     },
+    NoteOff {
+        channel: u8,
+        note: u8,
+    },
     ControlChange {
         channel: u8,
         controller: u8,
@@ -16,95 +27,132 @@ pub enum MidiEvent {
     },
 }
 
+/// Change of the connected ports found by `MidiManager::rescan`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MidiPortEvent {
+    InputConnected(String),
+    InputLost(String),
+    OutputConnected(String),
+    OutputLost(String),
+}
+
 pub struct MidiManager {
+    // Clients only used to list the ports, each connection opens its own
+    scan_in: MidiInput,
+    scan_out: MidiOutput,
+    // Port followed on each side: the first one connected, reconnected whenever a
+    // port of the same name reappears
+    in_port: Option<String>,
+    out_port: Option<String>,
     // We hold the connection to keep it alive
-    _in_conn: Option<MidiInputConnection<()>>,
+    in_conn: Option<MidiInputConnection<()>>,
     out_conn: Option<MidiOutputConnection>,
+    sender: mpsc::Sender<MidiEvent>,
     receiver: mpsc::Receiver<MidiEvent>,
+    last_scan: Instant,
 }
 
 impl MidiManager {
     pub fn new() -> Result<Self, Box<dyn Error>> {
-        let (tx, rx) = mpsc::channel();
+        let (sender, receiver) = mpsc::channel();
+        let mut manager = Self {
+            scan_in: MidiInput::new(INPUT_CLIENT)?,
+            scan_out: MidiOutput::new(OUTPUT_CLIENT)?,
+            in_port: None,
+            out_port: None,
+            in_conn: None,
+            out_conn: None,
+            sender,
+            receiver,
+            last_scan: Instant::now(),
+        };
+        manager.scan();
+        Ok(manager)
+    }
+
+    pub fn try_recv(&self) -> Result<MidiEvent, mpsc::TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    /// Name of the connected input port.
+    pub fn input_port(&self) -> Option<&str> {
+        self.in_conn.as_ref().and(self.in_port.as_deref())
+    }
+
+    /// Lists the ports at most every `RESCAN_PERIOD`: drops the connections whose
+    /// port went away and reconnects when the followed port is back.
+    pub fn rescan(&mut self) -> Vec<MidiPortEvent> {
+        if self.last_scan.elapsed() < RESCAN_PERIOD {
+            return Vec::new();
+        }
+        self.last_scan = Instant::now();
+        self.scan()
+    }
+
+    fn scan(&mut self) -> Vec<MidiPortEvent> {
+        let mut events = Vec::new();
 
         // --- INPUT ---
-        let mut midi_in = MidiInput::new("Rust BPM Analyzer Input")?;
-        midi_in.ignore(Ignore::None);
-
-        let in_ports = midi_in.ports();
-        let _in_conn = if let Some(in_port) = in_ports.first() {
-            println!(
-                "Opening connection to MIDI Input port: {}",
-                midi_in.port_name(in_port)?
-            );
-
-            let conn = midi_in.connect(
-                in_port,
-                "midir-read-input",
-                move |_stamp, message, _| {
-                    if message.len() >= 3 {
-                        let status = message[0];
-                        let data1 = message[1];
-                        let data2 = message[2];
-
-                        let channel = status & 0x0F;
-                        let msg_type = status & 0xF0;
-
-                        let event = match msg_type {
-                            0x90 if data2 > 0 => Some(MidiEvent::NoteOn {
-                                channel,
-                                note: data1,
-                                velocity: data2,
-                            }),
-                            0xB0 => Some(MidiEvent::ControlChange {
-                                channel,
-                                controller: data1,
-                                value: data2,
-                            }),
-                            _ => None,
-                        };
-
-                        if let Some(e) = event {
-                            let _ = tx.send(e);
+        let in_names: Vec<String> = self
+            .scan_in
+            .ports()
+            .iter()
+            .filter_map(|port| self.scan_in.port_name(port).ok())
+            .collect();
+        match (&self.in_conn, &self.in_port) {
+            (Some(_), Some(name)) if !in_names.iter().any(|n| same_port(n, name)) => {
+                warn!("MIDI input port lost: {}", name);
+                events.push(MidiPortEvent::InputLost(name.clone()));
+                self.in_conn = None;
+            }
+            (Some(_), _) => {}
+            (None, wanted) => {
+                let port = find_port(&in_names, wanted.as_deref()).cloned();
+                if let Some(name) = port {
+                    match open_input(&name, self.sender.clone()) {
+                        Ok(conn) => {
+                            info!("Opened connection to MIDI Input port: {}", name);
+                            self.in_conn = Some(conn);
+                            self.in_port = Some(name.clone());
+                            events.push(MidiPortEvent::InputConnected(name));
                         }
+                        Err(e) => error!("Failed to connect MIDI input {}: {}", name, e),
                     }
-                },
-                (),
-            )?;
-
-            Some(conn)
-        } else {
-            None
-        };
+                }
+            }
+        }
 
         // --- OUTPUT ---
-        let midi_out = MidiOutput::new("Rust BPM Analyzer Output")?;
-        let out_ports = midi_out.ports();
-        let out_conn = if let Some(out_port) = out_ports.first() {
-            println!(
-                "Opening connection to MIDI Output port: {}",
-                midi_out.port_name(out_port)?
-            );
-            match midi_out.connect(out_port, "midir-write-output") {
-                Ok(c) => Some(c),
-                Err(e) => {
-                    eprintln!("Failed to connect MIDI output: {}", e);
-                    None
+        let out_names: Vec<String> = self
+            .scan_out
+            .ports()
+            .iter()
+            .filter_map(|port| self.scan_out.port_name(port).ok())
+            .collect();
+        match (&self.out_conn, &self.out_port) {
+            (Some(_), Some(name)) if !out_names.iter().any(|n| same_port(n, name)) => {
+                warn!("MIDI output port lost: {}", name);
+                events.push(MidiPortEvent::OutputLost(name.clone()));
+                self.out_conn = None;
+            }
+            (Some(_), _) => {}
+            (None, wanted) => {
+                let port = find_port(&out_names, wanted.as_deref()).cloned();
+                if let Some(name) = port {
+                    match open_output(&name) {
+                        Ok(conn) => {
+                            info!("Opened connection to MIDI Output port: {}", name);
+                            self.out_conn = Some(conn);
+                            self.out_port = Some(name.clone());
+                            events.push(MidiPortEvent::OutputConnected(name));
+                        }
+                        Err(e) => error!("Failed to connect MIDI output {}: {}", name, e),
+                    }
                 }
             }
-        } else {
-            None
-        };
-
-        Ok(Self {
-            _in_conn,
-            out_conn,
-            receiver: rx,
-        })
-    }
+        }
 
-    pub fn try_recv(&self) -> Result<MidiEvent, mpsc::TryRecvError> {
-        self.receiver.try_recv()
+        events
     }
 
     pub fn send_note_on(&mut self, channel: u8, note: u8, velocity: u8) {
@@ -121,3 +169,95 @@ impl MidiManager {
         }
     }
 }
+
+// Followed port if present, any port until one was connected
+fn find_port<'a>(names: &'a [String], wanted: Option<&str>) -> Option<&'a String> {
+    match wanted {
+        Some(wanted) => names.iter().find(|name| same_port(name, wanted)),
+        None => names.first(),
+    }
+}
+
+// ALSA ends port names with the client:port address, which changes when the
+// controller is plugged again
+fn same_port(a: &str, b: &str) -> bool {
+    fn base_name(name: &str) -> &str {
+        match name.rsplit_once(' ') {
+            Some((base, address))
+                if address.split(':').count() == 2
+                    && address.split(':').all(|part| {
+                        !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit())
+                    }) =>
+            {
+                base
+            }
+            _ => name,
+        }
+    }
+    base_name(a) == base_name(b)
+}
+
+fn open_input(
+    name: &str,
+    tx: mpsc::Sender<MidiEvent>,
+) -> Result<MidiInputConnection<()>, Box<dyn Error>> {
+    let mut midi_in = MidiInput::new(INPUT_CLIENT)?;
+    midi_in.ignore(Ignore::None);
+    let port = midi_in
+        .ports()
+        .into_iter()
+        .find(|port| midi_in.port_name(port).is_ok_and(|n| n == name))
+        .ok_or("port not found")?;
+    let conn = midi_in.connect(
+        &port,
+        "midir-read-input",
+        move |_stamp, message, _| {
+            if let Some(event) = parse_message(message) {
+                let _ = tx.send(event);
+            }
+        },
+        (),
+    )?;
+    Ok(conn)
+}
+
+fn open_output(name: &str) -> Result<MidiOutputConnection, Box<dyn Error>> {
+    let midi_out = MidiOutput::new(OUTPUT_CLIENT)?;
+    let port = midi_out
+        .ports()
+        .into_iter()
+        .find(|port| midi_out.port_name(port).is_ok_and(|n| n == name))
+        .ok_or("port not found")?;
+    Ok(midi_out.connect(&port, "midir-write-output")?)
+}
+
+fn parse_message(message: &[u8]) -> Option<MidiEvent> {
+    if message.len() < 3 {
+        return None;
+    }
+    let status = message[0];
+    let data1 = message[1];
+    let data2 = message[2];
+
+    let channel = status & 0x0F;
+    let msg_type = status & 0xF0;
+
+    match msg_type {
+        0x90 if data2 > 0 => Some(MidiEvent::NoteOn {
+            channel,
+            note: data1,
+            velocity: data2,
+        }),
+        // Note On with velocity 0 is a Note Off
+        0x80 | 0x90 => Some(MidiEvent::NoteOff {
+            channel,
+            note: data1,
+        }),
+        0xB0 => Some(MidiEvent::ControlChange {
+            channel,
+            controller: data1,
+            value: data2,
+        }),
+        _ => None,
+    }
+}
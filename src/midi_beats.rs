@@ -0,0 +1,278 @@
+use crate::config::MidiOutConfig;
+use crate::outputs::MetronomeConfig;
+use crate::outputs::metronome::{ClickKind, Metronome};
+use midir::{MidiOutput, MidiOutputConnection};
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+// Scheduling period of the beat output thread
+const BEAT_TICK: Duration = Duration::from_millis(2);
+// A beat found later than this (thread stalled, tempo jump) is skipped
+const BEAT_LATE: Duration = Duration::from_millis(30);
+// RMS level sent at full velocity, same scale as the audio bar
+const FULL_LEVEL: f32 = 0.6;
+
+#[derive(Default)]
+struct BeatState {
+    bpm: f32,
+    last_beat: Option<Instant>,
+    energy: f32,
+    drop: bool,
+    phrase: bool,
+}
+
+/// Sends the `[midi_out]` notes from a background thread: a note on every beat of
+/// the grid extrapolated from the last detected beat, another on downbeats, one
+/// on drops and one on phrase starts (`[trigger]`).
+pub struct MidiBeatOutput {
+    state: Arc<Mutex<BeatState>>,
+    running: Arc<AtomicBool>,
+    thread_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl MidiBeatOutput {
+    pub fn new(config: MidiOutConfig) -> Result<Self, Box<dyn Error>> {
+        config.validate()?;
+        let (mut connection, port_name) = connect(
+            config.port.as_deref(),
+            "Rust BPM Analyzer Beats",
+            "midir-beat-output",
+        )?;
+        info!(
+            "MIDI beat output to {} (channel {})",
+            port_name, config.channel
+        );
+
+        let state = Arc::new(Mutex::new(BeatState::default()));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let thread_state = state.clone();
+        let thread_running = running.clone();
+        let thread_handle = thread::spawn(move || {
+            let channel = config.channel.clamp(1, 16) - 1;
+            let note_length = Duration::from_millis(config.note_ms);
+            let mut last_sent: Option<Instant> = None;
+            let mut beat_count: u64 = 0;
+            // Notes waiting for their Note Off
+            let mut playing: Vec<(Instant, u8)> = Vec::new();
+            while thread_running.load(Ordering::Relaxed) {
+                let now = Instant::now();
+                let (beat, dropped, phrase, level) = {
+                    let Ok(mut state) = thread_state.lock() else {
+                        break;
+                    };
+                    (
+                        due_beat(&state, last_sent, now),
+                        std::mem::take(&mut state.drop),
+                        std::mem::take(&mut state.phrase),
+                        (state.energy / FULL_LEVEL).clamp(0.0, 1.0),
+                    )
+                };
+
+                let mut notes = Vec::new();
+                if dropped {
+                    notes.extend(config.drop_note.map(|note| (note, 127)));
+                    // The beat just played starts the bar
+                    beat_count = 1;
+                }
+                if phrase {
+                    notes.extend(config.phrase_note.map(|note| (note, 127)));
+                }
+                if let Some(at) = beat {
+                    last_sent = Some(at);
+                    let min = config.min_velocity.min(127) as f32;
+                    let velocity = (min + (127.0 - min) * level).round() as u8;
+                    let note = if beat_count.is_multiple_of(4) {
+                        config.downbeat_note.or(config.beat_note)
+                    } else {
+                        config.beat_note
+                    };
+                    notes.extend(note.map(|note| (note, velocity.max(1))));
+                    beat_count += 1;
+                }
+                for (note, velocity) in notes {
+                    match connection.send(&[0x90 | channel, note, velocity]) {
+                        Ok(()) => playing.push((now + note_length, note)),
+                        Err(e) => error!("MIDI beat output error: {}", e),
+                    }
+                }
+                playing.retain(|&(off_at, note)| {
+                    if off_at > now {
+                        return true;
+                    }
+                    let _ = connection.send(&[0x80 | channel, note, 0]);
+                    false
+                });
+                thread::sleep(BEAT_TICK);
+            }
+            // No note left hanging on the lighting side
+            for (_, note) in playing {
+                let _ = connection.send(&[0x80 | channel, note, 0]);
+            }
+        });
+
+        Ok(Self {
+            state,
+            running,
+            thread_handle: Some(thread_handle),
+        })
+    }
+
+    /// `beat_age` is the time elapsed since the last detected beat.
+    pub fn update_tempo(&self, bpm: f32, beat_age: Option<Duration>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.bpm = bpm;
+            if let Some(age) = beat_age {
+                state.last_beat = Instant::now().checked_sub(age);
+            }
+        }
+    }
+
+    /// Same scale as the RMS level used by the audio bar (0.0 - 0.6).
+    pub fn update_energy(&self, energy: f32) {
+        if let Ok(mut state) = self.state.lock() {
+            state.energy = energy;
+        }
+    }
+
+    pub fn notify_drop(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.drop = true;
+        }
+    }
+
+    pub fn notify_phrase(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.phrase = true;
+        }
+    }
+}
+
+impl Drop for MidiBeatOutput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.thread_handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// First output port whose name contains `port`, the first port when unset
+fn connect(
+    port: Option<&str>,
+    client_name: &str,
+    connection_name: &str,
+) -> Result<(MidiOutputConnection, String), Box<dyn Error>> {
+    let midi_out = MidiOutput::new(client_name)?;
+    let ports = midi_out.ports();
+    let found = ports
+        .iter()
+        .find(|candidate| {
+            port.is_none_or(|name| {
+                midi_out
+                    .port_name(candidate)
+                    .is_ok_and(|port_name| port_name.contains(name))
+            })
+        })
+        .ok_or("No matching MIDI output port")?;
+    let port_name = midi_out.port_name(found)?;
+    let connection = midi_out
+        .connect(found, connection_name)
+        .map_err(|e| e.to_string())?;
+    Ok((connection, port_name))
+}
+
+// Beat of the grid that just started, unless it was already sent or is too old
+fn due_beat(state: &BeatState, last_sent: Option<Instant>, now: Instant) -> Option<Instant> {
+    let anchor = state.last_beat.filter(|_| state.bpm > 0.0)?;
+    let period = Duration::from_secs_f32(60.0 / state.bpm);
+    let elapsed = now.checked_duration_since(anchor)?;
+    let beats = (elapsed.as_secs_f64() / period.as_secs_f64()).floor();
+    let beat_at = anchor + period.mul_f64(beats);
+    let fresh = now.duration_since(beat_at) <= BEAT_LATE;
+    let new = last_sent.is_none_or(|sent| beat_at > sent + period / 2);
+    (fresh && new).then_some(beat_at)
+}
+
+/// Plays the clicks of a `Metronome` as MIDI notes from a background thread
+/// (`[outputs.metronome] midi = true`), for a drum module or a DAW.
+pub struct MidiClickOutput {
+    running: Arc<AtomicBool>,
+    thread_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl MidiClickOutput {
+    pub fn new(
+        config: &MetronomeConfig,
+        metronome: Arc<Mutex<Metronome>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let (mut connection, port_name) = connect(
+            config.midi_port.as_deref(),
+            "Rust BPM Analyzer Metronome",
+            "midir-metronome",
+        )?;
+        let channel = config.channel.clamp(1, 16) - 1;
+        info!(
+            "Metronome MIDI output to {} (channel {})",
+            port_name,
+            channel + 1
+        );
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+        let config = config.clone();
+        let thread_handle = thread::spawn(move || {
+            let note_length = Duration::from_millis(config.note_ms);
+            let mut last_sent: Option<Instant> = None;
+            let mut playing: Vec<(Instant, u8)> = Vec::new();
+            while thread_running.load(Ordering::Relaxed) {
+                let now = Instant::now();
+                let click = match metronome.lock() {
+                    Ok(metronome) => metronome.due_click(last_sent, now),
+                    Err(_) => break,
+                };
+                if let Some(click) = click {
+                    last_sent = Some(click.at);
+                    let (note, velocity) = match click.kind {
+                        ClickKind::CountIn => (config.count_in_note, 127),
+                        ClickKind::Downbeat => (config.accent_note, 127),
+                        ClickKind::Beat => (config.note, 100),
+                    };
+                    match connection.send(&[0x90 | channel, note, velocity]) {
+                        Ok(()) => playing.push((now + note_length, note)),
+                        Err(e) => error!("Metronome MIDI output error: {}", e),
+                    }
+                }
+                playing.retain(|&(off_at, note)| {
+                    if off_at > now {
+                        return true;
+                    }
+                    let _ = connection.send(&[0x80 | channel, note, 0]);
+                    false
+                });
+                thread::sleep(BEAT_TICK);
+            }
+            for (_, note) in playing {
+                let _ = connection.send(&[0x80 | channel, note, 0]);
+            }
+        });
+
+        Ok(Self {
+            running,
+            thread_handle: Some(thread_handle),
+        })
+    }
+}
+
+impl Drop for MidiClickOutput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.thread_handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use crate::config::MidiOutConfig;
+use crate::outputs::MetronomeConfig;
+use crate::outputs::metronome::Metronome;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// `MidiBeatOutput` of builds without the `midi` feature: never opens.
+pub struct MidiBeatOutput;
+
+impl MidiBeatOutput {
+    pub fn new(_config: MidiOutConfig) -> Result<Self, Box<dyn Error>> {
+        Err("built without the `midi` feature".into())
+    }
+
+    pub fn update_tempo(&self, _bpm: f32, _beat_age: Option<Duration>) {}
+
+    pub fn update_energy(&self, _energy: f32) {}
+
+    pub fn notify_drop(&self) {}
+
+    pub fn notify_phrase(&self) {}
+}
+
+/// `MidiClickOutput` of builds without the `midi` feature: never opens.
+pub struct MidiClickOutput;
+
+impl MidiClickOutput {
+    pub fn new(
+        _config: &MetronomeConfig,
+        _metronome: Arc<Mutex<Metronome>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        Err("built without the `midi` feature".into())
+    }
+}
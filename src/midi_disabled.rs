@@ -0,0 +1,54 @@
+use std::error::Error;
+use std::sync::mpsc;
+
+#[derive(Debug, Clone)]
+pub enum MidiEvent {
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    NoteOff {
+        channel: u8,
+        note: u8,
+    },
+    ControlChange {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MidiPortEvent {
+    InputConnected(String),
+    InputLost(String),
+    OutputConnected(String),
+    OutputLost(String),
+}
+
+/// `MidiManager` of builds without the `midi` feature: never opens, so the GUI runs
+/// without MIDI tap and gate.
+pub struct MidiManager;
+
+impl MidiManager {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        Err("built without the `midi` feature".into())
+    }
+
+    pub fn try_recv(&self) -> Result<MidiEvent, mpsc::TryRecvError> {
+        Err(mpsc::TryRecvError::Disconnected)
+    }
+
+    pub fn input_port(&self) -> Option<&str> {
+        None
+    }
+
+    pub fn rescan(&mut self) -> Vec<MidiPortEvent> {
+        Vec::new()
+    }
+
+    pub fn send_note_on(&mut self, _channel: u8, _note: u8, _velocity: u8) {}
+
+    pub fn send_control_change(&mut self, _channel: u8, _controller: u8, _value: u8) {}
+}
@@ -0,0 +1,151 @@
+use crate::midi::MidiEvent;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// CC values from this one on count as a press (buttons send 127, then 0 on release)
+const CC_PRESSED: u8 = 64;
+
+/// What a mapped MIDI control does in the desktop app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MidiAction {
+    ToggleDetection,
+    Tap,
+    /// Cycles the octave preference (½x, 1x, 2x)
+    OctaveShift,
+    /// Automatic gain calibration of the devices on the network
+    Calibrate,
+}
+
+impl MidiAction {
+    pub const ALL: [MidiAction; 4] = [
+        MidiAction::ToggleDetection,
+        MidiAction::Tap,
+        MidiAction::OctaveShift,
+        MidiAction::Calibrate,
+    ];
+}
+
+impl fmt::Display for MidiAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MidiAction::ToggleDetection => "Start/stop",
+            MidiAction::Tap => "TAP",
+            MidiAction::OctaveShift => "Octave",
+            MidiAction::Calibrate => "Calibrate gain",
+        })
+    }
+}
+
+/// Note or CC learnt for an action.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MidiMapping {
+    pub channel: u8,
+    pub note_or_cc: u8,
+    pub is_note: bool,
+}
+
+impl MidiMapping {
+    /// Control of a Note On or Control Change, what MIDI Learn binds.
+    pub fn from_event(event: &MidiEvent) -> Option<Self> {
+        match *event {
+            MidiEvent::NoteOn { channel, note, .. } => Some(Self {
+                channel,
+                note_or_cc: note,
+                is_note: true,
+            }),
+            MidiEvent::ControlChange {
+                channel,
+                controller,
+                ..
+            } => Some(Self {
+                channel,
+                note_or_cc: controller,
+                is_note: false,
+            }),
+            MidiEvent::NoteOff { .. } => None,
+        }
+    }
+
+    // Whether `event` presses this control. Note Offs and CC releases are ignored so
+    // that a button press fires once.
+    fn pressed_by(&self, event: &MidiEvent) -> bool {
+        match *event {
+            MidiEvent::NoteOn { channel, note, .. } => {
+                self.is_note && self.channel == channel && self.note_or_cc == note
+            }
+            MidiEvent::ControlChange {
+                channel,
+                controller,
+                value,
+            } => {
+                !self.is_note
+                    && self.channel == channel
+                    && self.note_or_cc == controller
+                    && value >= CC_PRESSED
+            }
+            MidiEvent::NoteOff { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for MidiMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} (channel {})",
+            if self.is_note { "Note" } else { "CC" },
+            self.note_or_cc,
+            self.channel
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MidiBinding {
+    pub action: MidiAction,
+    pub mapping: MidiMapping,
+}
+
+/// Controls bound to actions, at most one per action and one action per control.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MidiMap {
+    bindings: Vec<MidiBinding>,
+}
+
+impl MidiMap {
+    pub fn get(&self, action: MidiAction) -> Option<MidiMapping> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.action == action)
+            .map(|binding| binding.mapping)
+    }
+
+    /// Binds `mapping` to `action`, replacing its previous control. The control is
+    /// taken away from any other action.
+    pub fn bind(&mut self, action: MidiAction, mapping: MidiMapping) {
+        self.bindings
+            .retain(|binding| binding.action != action && binding.mapping != mapping);
+        self.bindings.push(MidiBinding { action, mapping });
+    }
+
+    pub fn clear(&mut self, action: MidiAction) {
+        self.bindings.retain(|binding| binding.action != action);
+    }
+
+    /// MIDI Learn: binds the control of `event` to `action`. Returns the new mapping,
+    /// `None` for events that cannot be learnt (Note Off).
+    pub fn learn(&mut self, action: MidiAction, event: &MidiEvent) -> Option<MidiMapping> {
+        let mapping = MidiMapping::from_event(event)?;
+        self.bind(action, mapping);
+        Some(mapping)
+    }
+
+    /// Action triggered by `event`, if it presses a mapped control.
+    pub fn action(&self, event: &MidiEvent) -> Option<MidiAction> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.mapping.pressed_by(event))
+            .map(|binding| binding.action)
+    }
+}
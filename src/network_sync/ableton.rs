@@ -5,6 +5,14 @@ pub struct LinkManager {
     link: AblLink,
     session_state: SessionState,
     last_sync_time: Instant,
+    // Beats per phase, shared by the peers aligning on it
+    quantum: f64,
+}
+
+impl Default for LinkManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl LinkManager {
@@ -15,6 +23,7 @@ impl LinkManager {
             link,
             session_state: SessionState::new(),
             last_sync_time: Instant::now(),
+            quantum: 4.0,
         }
     }
 
@@ -46,7 +55,7 @@ impl LinkManager {
         let target_time = time - latency_micros;
 
         self.session_state
-            .request_beat_at_time(0.0, target_time, 4.0);
+            .request_beat_at_time(0.0, target_time, self.quantum);
         self.link.commit_app_session_state(&self.session_state);
     }
 
@@ -55,11 +64,39 @@ impl LinkManager {
         self.session_state.tempo()
     }
 
+    pub fn set_quantum(&mut self, quantum: u32) {
+        self.quantum = quantum.max(1) as f64;
+    }
+
+    /// Session beat at `time`, counted so that multiples of the quantum are downbeats.
+    pub fn beat_at_time(&mut self, time: Instant) -> f64 {
+        self.link.capture_app_session_state(&mut self.session_state);
+        self.session_state
+            .beat_at_time(self.micros_at(time), self.quantum)
+    }
+
+    /// Position in the quantum at `time`, in beats (0 on the downbeat).
+    pub fn phase_at_time(&mut self, time: Instant) -> f64 {
+        self.link.capture_app_session_state(&mut self.session_state);
+        self.session_state
+            .phase_at_time(self.micros_at(time), self.quantum)
+    }
+
+    // Link clock at an instant of the monotonic clock
+    fn micros_at(&self, time: Instant) -> i64 {
+        let now = Instant::now();
+        let offset = if time > now {
+            (time - now).as_micros() as i64
+        } else {
+            -((now - time).as_micros() as i64)
+        };
+        self.link.clock_micros() + offset
+    }
+
     pub fn link_state(&mut self, enable: bool) {
         self.link.enable(enable);
     }
 
-    #[allow(dead_code)]
     pub fn num_peers(&self) -> usize {
         self.link.num_peers() as usize
     }
@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+/// `LinkManager` of builds without the `link` feature: keeps the tempo and a local
+/// beat timeline, shares nothing.
+pub struct LinkManager {
+    tempo: f64,
+    quantum: f64,
+    // Beat reached at the last tempo change
+    origin: (f64, Instant),
+}
+
+impl Default for LinkManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinkManager {
+    pub fn new() -> Self {
+        Self {
+            tempo: 120.0,
+            quantum: 4.0,
+            origin: (0.0, Instant::now()),
+        }
+    }
+
+    pub fn update_tempo(&mut self, bpm: f64, _is_drop: bool, _beat_offset: Option<Duration>) {
+        let now = Instant::now();
+        self.origin = (self.beat_at_time(now), now);
+        self.tempo = bpm;
+    }
+
+    pub fn sync_downbeat(&mut self, _latency: Duration) {}
+
+    pub fn get_tempo(&mut self) -> f64 {
+        self.tempo
+    }
+
+    pub fn set_quantum(&mut self, quantum: u32) {
+        self.quantum = quantum.max(1) as f64;
+    }
+
+    pub fn beat_at_time(&mut self, time: Instant) -> f64 {
+        let (beat, at) = self.origin;
+        let elapsed = match time.checked_duration_since(at) {
+            Some(elapsed) => elapsed.as_secs_f64(),
+            None => -at.duration_since(time).as_secs_f64(),
+        };
+        beat + elapsed * self.tempo / 60.0
+    }
+
+    pub fn phase_at_time(&mut self, time: Instant) -> f64 {
+        self.beat_at_time(time).rem_euclid(self.quantum)
+    }
+
+    pub fn link_state(&mut self, _enable: bool) {}
+
+    pub fn num_peers(&self) -> usize {
+        0
+    }
+}
@@ -0,0 +1,300 @@
+use crate::core_bpm::beat_features::BeatFeatures;
+use crate::metrics::METRICS;
+use serde::Deserialize;
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const ARTNET_PORT: u16 = 6454;
+const FRAME_INTERVAL: Duration = Duration::from_millis(25); // 40 fps
+// While sends fail the socket is reopened at this period, a socket opened before
+// its interface went down (cable out, DHCP renewal) can stay unusable
+const REOPEN_INTERVAL: Duration = Duration::from_secs(2);
+// A tempo moving further than this (next track) restarts the bar count
+const BAR_RESET_BPM: f32 = 3.0;
+
+/// `[artnet]` section of the config file.
+/// Channels are 1-based DMX addresses, `None` leaves the value unused.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ArtNetConfig {
+    pub enabled: bool,
+    /// Node IP or broadcast address
+    pub target: String,
+    pub universe: u16,
+    /// 255 during `strobe_ms` on every beat
+    pub beat_channel: Option<u16>,
+    /// 255 during `strobe_ms` on the first beat of each bar
+    pub downbeat_channel: Option<u16>,
+    /// BPM x10 on two channels (coarse, fine)
+    pub bpm_channel: Option<u16>,
+    /// Audio level scaled to 0-255
+    pub energy_channel: Option<u16>,
+    /// 255 during `drop_hold_ms` after a drop
+    pub drop_channel: Option<u16>,
+    /// Band levels sampled on each beat, held until the next one (0-255)
+    pub kick_channel: Option<u16>,
+    pub snare_channel: Option<u16>,
+    pub brightness_channel: Option<u16>,
+    pub strobe_ms: u64,
+    pub drop_hold_ms: u64,
+}
+
+impl Default for ArtNetConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: "255.255.255.255".to_string(),
+            universe: 0,
+            beat_channel: Some(1),
+            downbeat_channel: Some(2),
+            bpm_channel: Some(3),
+            energy_channel: Some(5),
+            drop_channel: Some(6),
+            kick_channel: Some(7),
+            snare_channel: Some(8),
+            brightness_channel: Some(9),
+            strobe_ms: 50,
+            drop_hold_ms: 2000,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ArtNetState {
+    bpm: f32,
+    last_beat: Option<Instant>,
+    energy: f32,
+    last_drop: Option<Instant>,
+    features: BeatFeatures,
+    // Start of the beat being strobed and its place in the bar. The grid is
+    // re-anchored on every hop, the count carries over until a drop or a new tempo
+    counted_beat: Option<Instant>,
+    bar_beat: Option<u64>,
+}
+
+/// Delivery of the Art-Net frames, for the status displays.
+#[derive(Debug, Clone, Default)]
+pub struct ArtNetHealth {
+    pub frames_sent: u64,
+    pub send_errors: u64,
+    pub last_error: Option<String>,
+    /// Start of the current run of failed sends, `None` while frames go out
+    pub failing_since: Option<Instant>,
+}
+
+impl ArtNetHealth {
+    pub fn is_failing(&self) -> bool {
+        self.failing_since.is_some()
+    }
+}
+
+/// Streams one Art-Net universe from a background thread.
+pub struct ArtNetOutput {
+    state: Arc<Mutex<ArtNetState>>,
+    health: Arc<Mutex<ArtNetHealth>>,
+    running: Arc<AtomicBool>,
+    thread_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl ArtNetOutput {
+    pub fn new(config: ArtNetConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut socket = open_socket(&config.target)?;
+        info!(
+            "Art-Net output to {}:{} (universe {})",
+            config.target, ARTNET_PORT, config.universe
+        );
+
+        let state = Arc::new(Mutex::new(ArtNetState::default()));
+        let health = Arc::new(Mutex::new(ArtNetHealth::default()));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let thread_state = state.clone();
+        let thread_health = health.clone();
+        let thread_running = running.clone();
+        let thread_handle = thread::spawn(move || {
+            let mut sequence: u8 = 0;
+            let mut dmx = [0u8; 512];
+            let mut last_reopen = Instant::now();
+            while thread_running.load(Ordering::Relaxed) {
+                if let Ok(mut state) = thread_state.lock() {
+                    fill_dmx(&config, &mut state, &mut dmx);
+                }
+                // Sequence 0 disables reordering on the receiver side
+                sequence = sequence.wrapping_add(1).max(1);
+                let packet = art_dmx_packet(config.universe, sequence, &dmx);
+                let sent = socket.send(&packet);
+                let failing = match thread_health.lock() {
+                    Ok(mut health) => record_send(&mut health, sent),
+                    Err(_) => false,
+                };
+                if failing && last_reopen.elapsed() >= REOPEN_INTERVAL {
+                    last_reopen = Instant::now();
+                    match open_socket(&config.target) {
+                        Ok(reopened) => socket = reopened,
+                        Err(e) => warn!("Art-Net socket reopen error: {}", e),
+                    }
+                }
+                thread::sleep(FRAME_INTERVAL);
+            }
+        });
+
+        Ok(Self {
+            state,
+            health,
+            running,
+            thread_handle: Some(thread_handle),
+        })
+    }
+
+    /// `beat_age` is the time elapsed since the last detected beat.
+    pub fn update_tempo(&self, bpm: f32, beat_age: Option<Duration>) {
+        if let Ok(mut state) = self.state.lock() {
+            if (bpm - state.bpm).abs() > BAR_RESET_BPM {
+                state.bar_beat = None;
+            }
+            state.bpm = bpm;
+            if let Some(age) = beat_age {
+                state.last_beat = Instant::now().checked_sub(age);
+            }
+        }
+    }
+
+    /// Same scale as the RMS level used by the audio bar (0.0 - 0.6).
+    pub fn update_energy(&self, energy: f32) {
+        if let Ok(mut state) = self.state.lock() {
+            state.energy = energy;
+        }
+    }
+
+    /// Levels of the beat that just ended, see `BeatFeatureSampler`.
+    pub fn update_beat_features(&self, features: BeatFeatures) {
+        if let Ok(mut state) = self.state.lock() {
+            state.features = features;
+        }
+    }
+
+    pub fn notify_drop(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.last_drop = Some(Instant::now());
+            // The beat just played starts the bar
+            state.bar_beat = Some(0);
+        }
+    }
+
+    /// Delivery so far, polled by the GUI
+    pub fn health(&self) -> ArtNetHealth {
+        self.health
+            .lock()
+            .map(|health| health.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn open_socket(target: &str) -> std::io::Result<UdpSocket> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_broadcast(true)?;
+    socket.connect((target, ARTNET_PORT))?;
+    Ok(socket)
+}
+
+// Counts a send and logs the start and end of a run of failures only, not each
+// frame. Returns whether the output is failing.
+fn record_send(health: &mut ArtNetHealth, sent: std::io::Result<usize>) -> bool {
+    match sent {
+        Ok(_) => {
+            METRICS.message_sent();
+            health.frames_sent += 1;
+            if let Some(since) = health.failing_since.take() {
+                info!(
+                    "Art-Net output back after {:.1} s",
+                    since.elapsed().as_secs_f32()
+                );
+            }
+        }
+        Err(e) => {
+            METRICS.message_send_failed();
+            health.send_errors += 1;
+            if health.failing_since.is_none() {
+                warn!("Art-Net send error: {}", e);
+                health.failing_since = Some(Instant::now());
+            }
+            health.last_error = Some(e.to_string());
+        }
+    }
+    health.failing_since.is_some()
+}
+
+impl Drop for ArtNetOutput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.thread_handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn fill_dmx(config: &ArtNetConfig, state: &mut ArtNetState, dmx: &mut [u8; 512]) {
+    let mut set = |channel: Option<u16>, value: u8| {
+        if let Some(ch) = channel.filter(|&c| (1..=512).contains(&c)) {
+            dmx[ch as usize - 1] = value;
+        }
+    };
+
+    // Beat strobes from the beat grid
+    let strobe = Duration::from_millis(config.strobe_ms);
+    let (mut on_beat, mut on_downbeat) = (false, false);
+    if let Some(last_beat) = state.last_beat.filter(|_| state.bpm > 0.0) {
+        let period = Duration::from_secs_f32(60.0 / state.bpm);
+        let beats = last_beat.elapsed().as_secs_f64() / period.as_secs_f64();
+        let beat_at = last_beat + period.mul_f64(beats.floor());
+        // Counted once, whatever the re-anchoring moved the grid by
+        if state
+            .counted_beat
+            .is_none_or(|counted| beat_at > counted + period / 2)
+        {
+            state.counted_beat = Some(beat_at);
+            state.bar_beat = Some(state.bar_beat.map_or(0, |beat| beat + 1));
+        }
+        on_beat = beat_at.elapsed() < strobe;
+        on_downbeat = on_beat && state.bar_beat.is_some_and(|beat| beat.is_multiple_of(4));
+    }
+    set(config.beat_channel, if on_beat { 255 } else { 0 });
+    set(config.downbeat_channel, if on_downbeat { 255 } else { 0 });
+
+    // BPM x10 as 16 bits
+    let bpm_value = (state.bpm * 10.0).round().clamp(0.0, u16::MAX as f32) as u16;
+    set(config.bpm_channel, (bpm_value >> 8) as u8);
+    set(config.bpm_channel.map(|c| c + 1), (bpm_value & 0xFF) as u8);
+
+    let energy = (state.energy / 0.6).clamp(0.0, 1.0);
+    set(config.energy_channel, (energy * 255.0) as u8);
+
+    let in_drop = state
+        .last_drop
+        .is_some_and(|t| t.elapsed() < Duration::from_millis(config.drop_hold_ms));
+    set(config.drop_channel, if in_drop { 255 } else { 0 });
+
+    let level = |value: f32| (value.clamp(0.0, 1.0) * 255.0) as u8;
+    set(config.kick_channel, level(state.features.kick));
+    set(config.snare_channel, level(state.features.snare));
+    set(config.brightness_channel, level(state.features.brightness));
+}
+
+/// Builds an ArtDmx packet (OpCode 0x5000, protocol 14).
+fn art_dmx_packet(universe: u16, sequence: u8, dmx: &[u8; 512]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(18 + dmx.len());
+    packet.extend_from_slice(b"Art-Net\0");
+    packet.extend_from_slice(&0x5000u16.to_le_bytes());
+    packet.extend_from_slice(&14u16.to_be_bytes());
+    packet.push(sequence);
+    packet.push(0); // Physical
+    packet.push((universe & 0xFF) as u8); // SubUni
+    packet.push(((universe >> 8) & 0x7F) as u8); // Net
+    packet.extend_from_slice(&(dmx.len() as u16).to_be_bytes());
+    packet.extend_from_slice(dmx);
+    packet
+}
@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Exchanges kept for the fastest-round-trip filter
+const SAMPLES: usize = 8;
+
+/// One `GET /clock` exchange: local times around the request and the device's
+/// monotonic clock read in between (µs since the device API started).
+#[derive(Debug, Clone, Copy)]
+pub struct ClockSample {
+    pub sent: Instant,
+    pub received: Instant,
+    pub remote_us: u64,
+}
+
+impl ClockSample {
+    fn round_trip(&self) -> Duration {
+        self.received.saturating_duration_since(self.sent)
+    }
+
+    /// Local time of the device reading, assuming a symmetric path
+    fn midpoint(&self) -> Instant {
+        self.sent + self.round_trip() / 2
+    }
+}
+
+/// NTP-lite alignment of a device clock on the local one. Only the exchange
+/// with the fastest round trip of the last `SAMPLES` is trusted: queuing delays
+/// make the path asymmetric, and a fast exchange bounds the error to half of
+/// its round trip. Drift between the two clocks is negligible over that window.
+#[derive(Debug, Clone, Default)]
+pub struct ClockSync {
+    samples: VecDeque<ClockSample>,
+}
+
+impl ClockSync {
+    pub fn push(&mut self, sample: ClockSample) {
+        if self.samples.len() == SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    fn best(&self) -> Option<&ClockSample> {
+        self.samples.iter().min_by_key(|s| s.round_trip())
+    }
+
+    /// Local instant of the device time `remote_us`, `None` before the first exchange.
+    pub fn to_local(&self, remote_us: u64) -> Option<Instant> {
+        let best = self.best()?;
+        let midpoint = best.midpoint();
+        if remote_us >= best.remote_us {
+            Some(midpoint + Duration::from_micros(remote_us - best.remote_us))
+        } else {
+            midpoint.checked_sub(Duration::from_micros(best.remote_us - remote_us))
+        }
+    }
+
+    /// Bound of the alignment error.
+    pub fn uncertainty(&self) -> Option<Duration> {
+        self.best().map(|best| best.round_trip() / 2)
+    }
+}
+
+/// Offset (s) of `beat` from the beat grid through `reference` at `bpm`, in
+/// `]-period/2, period/2]`: positive when `beat` comes late.
+pub fn phase_offset(beat: Instant, reference: Instant, bpm: f32) -> f32 {
+    let period = 60.0 / bpm;
+    let delta = if beat >= reference {
+        (beat - reference).as_secs_f32()
+    } else {
+        -(reference - beat).as_secs_f32()
+    };
+    let offset = delta.rem_euclid(period);
+    if offset > period / 2.0 {
+        offset - period
+    } else {
+        offset
+    }
+}
@@ -0,0 +1,508 @@
+use super::api_auth;
+use super::clock_sync::{ClockSample, ClockSync};
+use super::discovery::DiscoveredPeer;
+use crate::core_bpm::level_history::LevelSnapshot;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Connect and read timeout of a request to a device
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+// Period of the `GET /update` polling of the devices
+const POLL_PERIOD: Duration = Duration::from_secs(2);
+
+/// Step of a device self-update.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum UpdatePhase {
+    #[default]
+    Idle,
+    Checking,
+    Downloading,
+    Installing,
+    Failed,
+}
+
+/// `GET /update` of a device: installed version, version found by the last
+/// check and progress of a running update.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DeviceUpdate {
+    pub version: String,
+    pub phase: UpdatePhase,
+    pub available: Option<String>,
+    /// Download progress (%)
+    pub progress: Option<u8>,
+    pub error: Option<String>,
+}
+
+impl DeviceUpdate {
+    /// One-line state for the devices list.
+    pub fn summary(&self) -> String {
+        match self.phase {
+            UpdatePhase::Checking => "checking for updates".to_string(),
+            UpdatePhase::Downloading => format!("downloading {}%", self.progress.unwrap_or(0)),
+            UpdatePhase::Installing => "installing".to_string(),
+            UpdatePhase::Failed => format!(
+                "update failed: {}",
+                self.error.as_deref().unwrap_or("unknown error")
+            ),
+            UpdatePhase::Idle => match &self.available {
+                Some(version) => format!("v{} available", version),
+                None => "up to date".to_string(),
+            },
+        }
+    }
+}
+
+/// `GET /status` of a device, the fields shown on the devices dashboard.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RemoteStatus {
+    pub bpm: Option<f32>,
+    pub confidence: f32,
+    pub analysis_enabled: bool,
+    /// Input RMS (0..1 full scale), `None` from devices predating the field
+    #[serde(default)]
+    pub energy: Option<f32>,
+    /// Analysis gate, `None` without gating
+    pub gate: Option<bool>,
+    /// Last beat on the device clock (µs, see `sample_clock`)
+    #[serde(default)]
+    pub beat_us: Option<u64>,
+    /// Fields of the mDNS announcement, `None` from devices predating them
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub platform: Option<String>,
+    #[serde(default)]
+    pub api: Option<u32>,
+}
+
+impl RemoteStatus {
+    /// One-word analysis state for the dashboard tiles.
+    pub fn state(&self) -> &'static str {
+        match (self.analysis_enabled, self.gate, self.bpm) {
+            (false, _, _) => "disabled",
+            (true, Some(false), _) => "waiting for gate",
+            (true, _, None) => "listening",
+            (true, _, Some(_)) => "tracking",
+        }
+    }
+}
+
+/// Update commands sent to a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateCommand {
+    /// Look for a new release (`POST /update/check`)
+    Check,
+    /// Download and install it, the device restarts (`POST /update`)
+    Start,
+}
+
+impl UpdateCommand {
+    fn path(self) -> &'static str {
+        match self {
+            UpdateCommand::Check => "/update/check",
+            UpdateCommand::Start => "/update",
+        }
+    }
+}
+
+/// HTTP/1.1 request to a device API with an optional JSON body, returning the body
+/// of a 2xx answer. The API only serves small JSON documents, so the connection is
+/// read to its end.
+fn request(
+    address: SocketAddr,
+    method: &str,
+    path: &str,
+    body: Option<&str>,
+    api_key: Option<&str>,
+) -> Result<String, String> {
+    let mut stream =
+        TcpStream::connect_timeout(&address, REQUEST_TIMEOUT).map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(REQUEST_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+
+    let body = body.unwrap_or_default();
+    let mut head = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        method,
+        path,
+        address,
+        body.len()
+    );
+    if !body.is_empty() {
+        head.push_str("Content-Type: application/json\r\n");
+    }
+    if let Some(key) = api_key {
+        let timestamp = api_auth::now_ms();
+        let signature = api_auth::sign(key, timestamp, method, path, body.as_bytes());
+        head.push_str(&format!(
+            "{}: {}\r\n{}: {}\r\n",
+            api_auth::TIMESTAMP_HEADER,
+            timestamp,
+            api_auth::SIGNATURE_HEADER,
+            signature
+        ));
+    }
+    head.push_str("\r\n");
+    head.push_str(body);
+    stream
+        .write_all(head.as_bytes())
+        .map_err(|e| e.to_string())?;
+
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .map_err(|e| e.to_string())?;
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or("malformed HTTP response")?;
+    let code: u16 = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or("malformed HTTP response")?;
+    match code {
+        200..=299 => Ok(body.to_string()),
+        401 => Err("refused, check the [api] key and the clocks".to_string()),
+        _ => Err(format!("HTTP {}", code)),
+    }
+}
+
+pub fn get_update(address: SocketAddr) -> Result<DeviceUpdate, String> {
+    let body = request(address, "GET", "/update", None, None)?;
+    serde_json::from_str(&body).map_err(|e| e.to_string())
+}
+
+/// Input level of the last minutes (`GET /levels`)
+pub fn get_levels(address: SocketAddr) -> Result<LevelSnapshot, String> {
+    let body = request(address, "GET", "/levels", None, None)?;
+    serde_json::from_str(&body).map_err(|e| e.to_string())
+}
+
+pub fn get_status(address: SocketAddr) -> Result<RemoteStatus, String> {
+    let body = request(address, "GET", "/status", None, None)?;
+    serde_json::from_str(&body).map_err(|e| e.to_string())
+}
+
+#[derive(Deserialize)]
+struct ClockReply {
+    clock_us: u64,
+}
+
+/// Reads the monotonic clock of a device (`GET /clock`) between two local
+/// timestamps. The device reads it somewhere between `sent` and `received`, so
+/// the error is at most half of that round trip, TCP handshake included.
+pub fn sample_clock(address: SocketAddr) -> Result<ClockSample, String> {
+    let sent = Instant::now();
+    let body = request(address, "GET", "/clock", None, None)?;
+    let received = Instant::now();
+    let reply: ClockReply = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    Ok(ClockSample {
+        sent,
+        received,
+        remote_us: reply.clock_us,
+    })
+}
+
+/// Starts or stops the tempo detection of a device (`POST /analysis`)
+pub fn set_analysis(
+    address: SocketAddr,
+    enabled: bool,
+    api_key: Option<&str>,
+) -> Result<(), String> {
+    let body = format!("{{\"enabled\":{}}}", enabled);
+    request(address, "POST", "/analysis", Some(&body), api_key).map(|_| ())
+}
+
+/// Starts the automatic gain calibration of a device (`POST /autogain`)
+pub fn auto_gain(address: SocketAddr, api_key: Option<&str>) -> Result<(), String> {
+    request(address, "POST", "/autogain", None, api_key).map(|_| ())
+}
+
+pub fn send_update_command(
+    address: SocketAddr,
+    command: UpdateCommand,
+    api_key: Option<&str>,
+) -> Result<(), String> {
+    request(address, "POST", command.path(), None, api_key).map(|_| ())
+}
+
+/// Devices of `[discovery] static_peers`, for venue networks that block
+/// multicast. `GET /status` answers with the same name, version, platform and
+/// API revision as the mDNS announcement, so the polled devices are listed like
+/// discovered ones.
+pub struct StaticPeers {
+    peers: Arc<Mutex<Vec<DiscoveredPeer>>>,
+    probing: Arc<AtomicBool>,
+    last_probe: Option<Instant>,
+}
+
+impl StaticPeers {
+    /// Devices at `addresses`, offline until their first answer.
+    pub fn new(addresses: &[SocketAddr]) -> Self {
+        let peers = addresses
+            .iter()
+            .map(|address| Self::unreachable(*address))
+            .collect();
+        Self {
+            peers: Arc::new(Mutex::new(peers)),
+            probing: Arc::new(AtomicBool::new(false)),
+            last_probe: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.lock().map(|p| p.is_empty()).unwrap_or(true)
+    }
+
+    /// Probes every address over unicast, at most every `POLL_PERIOD`.
+    pub fn poll(&mut self) {
+        if self.last_probe.is_some_and(|at| at.elapsed() < POLL_PERIOD)
+            || self.probing.swap(true, Ordering::SeqCst)
+        {
+            return;
+        }
+        self.last_probe = Some(Instant::now());
+        let known = self.peers();
+        let peers = self.peers.clone();
+        let probing = self.probing.clone();
+        thread::spawn(move || {
+            let probed: Vec<DiscoveredPeer> = known
+                .into_iter()
+                .map(|peer| {
+                    let Some(address) = peer.address.map(|ip| SocketAddr::new(ip, peer.port))
+                    else {
+                        return peer;
+                    };
+                    match get_status(address) {
+                        Ok(status) => DiscoveredPeer {
+                            name: status.name.unwrap_or_else(|| address.to_string()),
+                            version: status.version,
+                            platform: status.platform,
+                            api: status.api,
+                            online: true,
+                            ..peer
+                        },
+                        // Keeps the name of its last answer
+                        Err(_) => DiscoveredPeer {
+                            online: false,
+                            ..peer
+                        },
+                    }
+                })
+                .collect();
+            if let Ok(mut peers) = peers.lock() {
+                *peers = probed;
+            }
+            probing.store(false, Ordering::SeqCst);
+        });
+    }
+
+    /// Configured devices sorted by name, offline when they did not answer the
+    /// last probe.
+    pub fn peers(&self) -> Vec<DiscoveredPeer> {
+        let mut list = self.peers.lock().map(|p| p.clone()).unwrap_or_default();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        list
+    }
+
+    fn unreachable(address: SocketAddr) -> DiscoveredPeer {
+        DiscoveredPeer {
+            name: address.to_string(),
+            address: Some(address.ip()),
+            port: address.port(),
+            version: None,
+            platform: None,
+            api: None,
+            priority: None,
+            confidence: None,
+            master: false,
+            online: false,
+        }
+    }
+}
+
+type States = Arc<Mutex<HashMap<String, Result<DeviceUpdate, String>>>>;
+type Statuses = Arc<Mutex<HashMap<String, Result<RemoteStatus, String>>>>;
+
+/// Update state, analysis status, input level history and clock alignment of the
+/// devices, polled from a background thread so that the GUI never waits on the
+/// network.
+pub struct DeviceUpdates {
+    states: States,
+    statuses: Statuses,
+    levels: Arc<Mutex<HashMap<String, LevelSnapshot>>>,
+    clocks: Arc<Mutex<HashMap<String, ClockSync>>>,
+    api_key: Option<String>,
+    polling: Arc<AtomicBool>,
+    last_poll: Option<Instant>,
+}
+
+impl DeviceUpdates {
+    /// `api_key` is the `[api]` key shared with the devices.
+    pub fn new(api_key: Option<String>) -> Self {
+        Self {
+            states: Arc::new(Mutex::new(HashMap::new())),
+            statuses: Arc::new(Mutex::new(HashMap::new())),
+            levels: Arc::new(Mutex::new(HashMap::new())),
+            clocks: Arc::new(Mutex::new(HashMap::new())),
+            api_key: api_key.filter(|k| !k.is_empty()),
+            polling: Arc::new(AtomicBool::new(false)),
+            last_poll: None,
+        }
+    }
+
+    /// Refreshes the state of the online devices, at most every `POLL_PERIOD`.
+    pub fn poll(&mut self, peers: &[DiscoveredPeer]) {
+        if self.last_poll.is_some_and(|at| at.elapsed() < POLL_PERIOD)
+            || self.polling.swap(true, Ordering::SeqCst)
+        {
+            return;
+        }
+        self.last_poll = Some(Instant::now());
+        let targets: Vec<(String, SocketAddr)> = peers
+            .iter()
+            .filter(|peer| peer.online)
+            .filter_map(|peer| Some((peer.name.clone(), Self::address(peer)?)))
+            .collect();
+        let states = self.states.clone();
+        let statuses = self.statuses.clone();
+        let levels = self.levels.clone();
+        let clocks = self.clocks.clone();
+        let polling = self.polling.clone();
+        thread::spawn(move || {
+            for (name, address) in targets {
+                // Devices running an older version have no clock
+                if let (Ok(sample), Ok(mut clocks)) = (sample_clock(address), clocks.lock()) {
+                    clocks.entry(name.clone()).or_default().push(sample);
+                }
+                let status = get_status(address);
+                if let Ok(mut statuses) = statuses.lock() {
+                    statuses.insert(name.clone(), status);
+                }
+                // Devices running an older version have no history
+                if let (Ok(history), Ok(mut levels)) = (get_levels(address), levels.lock()) {
+                    levels.insert(name.clone(), history);
+                }
+                let state = get_update(address);
+                if let Ok(mut states) = states.lock() {
+                    states.insert(name, state);
+                }
+            }
+            polling.store(false, Ordering::SeqCst);
+        });
+    }
+
+    /// Last known state of a device, `None` before its first answer.
+    pub fn get(&self, name: &str) -> Option<Result<DeviceUpdate, String>> {
+        self.states.lock().ok()?.get(name).cloned()
+    }
+
+    /// Last known analysis status of a device, `None` before its first answer.
+    pub fn status(&self, name: &str) -> Option<Result<RemoteStatus, String>> {
+        self.statuses.lock().ok()?.get(name).cloned()
+    }
+
+    /// Last input level history of a device.
+    pub fn levels(&self, name: &str) -> Option<LevelSnapshot> {
+        self.levels.lock().ok()?.get(name).cloned()
+    }
+
+    /// Age (s) and level of the points of a device's input history on the local
+    /// clock, once its clock is aligned, so that all devices share one timeline.
+    pub fn level_timeline(&self, name: &str, now: Instant) -> Option<Vec<(f32, f32)>> {
+        let history = self.levels(name)?;
+        let end = self
+            .clocks
+            .lock()
+            .ok()?
+            .get(name)?
+            .to_local(history.end_us?)?;
+        Some(history.timeline(end, now))
+    }
+
+    /// Local time of the last beat of a device, once its clock is aligned.
+    pub fn last_beat(&self, name: &str) -> Option<Instant> {
+        let beat_us = match self.status(name)? {
+            Ok(status) => status.beat_us?,
+            Err(_) => return None,
+        };
+        self.clocks.lock().ok()?.get(name)?.to_local(beat_us)
+    }
+
+    /// Sends `command` to the device in the background. A refused command shows
+    /// up as the device state until the next poll.
+    pub fn send(&self, peer: &DiscoveredPeer, command: UpdateCommand) {
+        let Some(address) = Self::address(peer) else {
+            return;
+        };
+        let name = peer.name.clone();
+        let api_key = self.api_key.clone();
+        let states = self.states.clone();
+        thread::spawn(move || {
+            let state = send_update_command(address, command, api_key.as_deref())
+                .and_then(|_| get_update(address));
+            if let Ok(mut states) = states.lock() {
+                states.insert(name, state);
+            }
+        });
+    }
+
+    /// Starts or stops the detection of a device in the background. The tile shows
+    /// the new state right away, a refused command shows up as the device status
+    /// until the next poll.
+    pub fn set_analysis(&self, peer: &DiscoveredPeer, enabled: bool) {
+        let Some(address) = Self::address(peer) else {
+            return;
+        };
+        let name = peer.name.clone();
+        let api_key = self.api_key.clone();
+        let statuses = self.statuses.clone();
+        thread::spawn(move || {
+            let result = set_analysis(address, enabled, api_key.as_deref());
+            let Ok(mut statuses) = statuses.lock() else {
+                return;
+            };
+            match result {
+                Ok(()) => {
+                    if let Some(Ok(status)) = statuses.get_mut(&name) {
+                        status.analysis_enabled = enabled;
+                    }
+                }
+                Err(e) => {
+                    statuses.insert(name, Err(e));
+                }
+            }
+        });
+    }
+
+    /// Starts the gain calibration of a device in the background. A refused command
+    /// shows up as the device status until the next poll.
+    pub fn auto_gain(&self, peer: &DiscoveredPeer) {
+        let Some(address) = Self::address(peer) else {
+            return;
+        };
+        let name = peer.name.clone();
+        let api_key = self.api_key.clone();
+        let statuses = self.statuses.clone();
+        thread::spawn(move || {
+            if let (Err(e), Ok(mut statuses)) =
+                (auto_gain(address, api_key.as_deref()), statuses.lock())
+            {
+                statuses.insert(name, Err(e));
+            }
+        });
+    }
+
+    fn address(peer: &DiscoveredPeer) -> Option<SocketAddr> {
+        peer.address.map(|ip| SocketAddr::new(ip, peer.port))
+    }
+}
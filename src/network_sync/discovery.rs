@@ -0,0 +1,303 @@
+use super::election::Candidate;
+use crate::config::DiscoveryConfig;
+use mdns_sd::{IfKind, ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+pub const SERVICE_TYPE: &str = "_bpmanalyzer._udp.local.";
+// Devices re-announce themselves with a new `seq` TXT value at this period
+const HEARTBEAT_PERIOD: Duration = Duration::from_secs(5);
+// A device silent for this long is shown as offline
+const PEER_TIMEOUT: Duration = Duration::from_secs(15);
+/// Revision of the device HTTP API, bumped on incompatible changes. Announced as
+/// the `api` TXT value so that the desktop can flag devices it cannot drive.
+pub const API_VERSION: u32 = 1;
+
+/// An analyzer found on the local network.
+#[derive(Debug, Clone)]
+pub struct DiscoveredPeer {
+    pub name: String,
+    pub address: Option<IpAddr>,
+    pub port: u16,
+    pub version: Option<String>,
+    /// `<os>-<arch>` of the build, e.g. `linux-aarch64`
+    pub platform: Option<String>,
+    pub api: Option<u32>,
+    /// Election priority and confidence, announced by devices with `[election] enabled`
+    pub priority: Option<u8>,
+    pub confidence: Option<f32>,
+    /// Claims the master role of the election
+    pub master: bool,
+    pub online: bool,
+}
+
+impl DiscoveredPeer {
+    /// Whether the peer runs an older release than `version` (`major.minor.patch`).
+    pub fn is_older_than(&self, version: &str) -> bool {
+        match (
+            self.version.as_deref().and_then(parse_version),
+            parse_version(version),
+        ) {
+            (Some(peer), Some(version)) => peer < version,
+            _ => false,
+        }
+    }
+
+    /// The peer as an election candidate, if it takes part in the election.
+    pub fn candidate(&self) -> Option<Candidate> {
+        Some(Candidate {
+            name: self.name.clone(),
+            priority: self.priority?,
+            confidence: self.confidence.unwrap_or(0.0),
+            master: self.master,
+        })
+    }
+
+    /// Whether the peer serves the API revision this build speaks. Peers that do
+    /// not announce it predate the field and are assumed compatible.
+    pub fn is_compatible(&self) -> bool {
+        self.api.is_none_or(|api| api == API_VERSION)
+    }
+}
+
+/// `major.minor.patch`, ignoring a pre-release suffix
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.split(['-', '+']).next()?;
+    let mut numbers = core.split('.').map(|n| n.parse::<u64>().ok());
+    Some((numbers.next()??, numbers.next()??, numbers.next()??))
+}
+
+/// Address used to reach a peer: IPv4 first, then a routable IPv6 address. mDNS
+/// does not give the interface of IPv6 link-local addresses (`fe80::/10`), which
+/// are unusable without it.
+fn preferred_address(addresses: &HashSet<IpAddr>) -> Option<IpAddr> {
+    let mut usable: Vec<IpAddr> = addresses
+        .iter()
+        .copied()
+        .filter(|ip| match ip {
+            IpAddr::V4(_) => true,
+            IpAddr::V6(v6) => (v6.segments()[0] & 0xffc0) != 0xfe80,
+        })
+        .collect();
+    // Same choice at every resolution, the set has no order
+    usable.sort_by_key(|ip| (ip.is_ipv6(), *ip));
+    usable.first().copied()
+}
+
+/// Platform announced in the `platform` TXT value
+pub fn platform() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// Known peers with the time of their last announcement.
+#[derive(Default)]
+struct PeerRegistry {
+    peers: HashMap<String, (DiscoveredPeer, Instant)>,
+}
+
+impl PeerRegistry {
+    fn seen(&mut self, fullname: String, peer: DiscoveredPeer) {
+        self.peers.insert(fullname, (peer, Instant::now()));
+    }
+
+    fn remove(&mut self, fullname: &str) {
+        self.peers.remove(fullname);
+    }
+
+    fn snapshot(&self) -> Vec<DiscoveredPeer> {
+        self.peers
+            .values()
+            .map(|(peer, last_seen)| DiscoveredPeer {
+                online: last_seen.elapsed() < PEER_TIMEOUT,
+                ..peer.clone()
+            })
+            .collect()
+    }
+}
+
+/// mDNS-SD advertisement and browsing of `_bpmanalyzer._udp.local`.
+/// Unlike plain multicast, mDNS is usually forwarded by reflectors across VLANs.
+pub struct Discovery {
+    daemon: ServiceDaemon,
+    peers: Arc<Mutex<PeerRegistry>>,
+    // Stops the heartbeat once the announcement is withdrawn
+    withdrawn: Arc<AtomicBool>,
+    // Election priority, last confidence and master claim, announced with the
+    // heartbeat
+    election: Arc<Mutex<Option<(u8, f32, bool)>>>,
+}
+
+impl Discovery {
+    /// Daemon on the address families enabled in `config` (both by default).
+    pub fn new(config: &DiscoveryConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        config.validate()?;
+        let daemon = ServiceDaemon::new()?;
+        if !config.ipv4 {
+            daemon.disable_interface(IfKind::IPv4)?;
+        }
+        if !config.ipv6 {
+            daemon.disable_interface(IfKind::IPv6)?;
+        }
+        Ok(Self {
+            daemon,
+            peers: Arc::new(Mutex::new(PeerRegistry::default())),
+            withdrawn: Arc::new(AtomicBool::new(false)),
+            election: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Announces this instance. `port` is the control port (HTTP API).
+    /// The announcement is repeated every `HEARTBEAT_PERIOD` so that browsers
+    /// can tell a live device from one that lost power.
+    pub fn advertise(
+        &self,
+        instance_name: &str,
+        port: u16,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.daemon
+            .register(Self::service_info(instance_name, port, 0, None)?)?;
+        info!("mDNS: advertising {} on port {}", instance_name, port);
+
+        let daemon = self.daemon.clone();
+        let instance_name = instance_name.to_string();
+        let withdrawn = self.withdrawn.clone();
+        let election = self.election.clone();
+        thread::spawn(move || {
+            let mut seq: u64 = 0;
+            loop {
+                thread::sleep(HEARTBEAT_PERIOD);
+                if withdrawn.load(Ordering::SeqCst) {
+                    break;
+                }
+                seq += 1;
+                let candidate = election.lock().ok().and_then(|e| *e);
+                // A changed TXT record makes browsers resolve the service again
+                let registered = Self::service_info(&instance_name, port, seq, candidate)
+                    .map_err(|e| e.to_string())
+                    .and_then(|info| daemon.register(info).map_err(|e| e.to_string()));
+                if let Err(e) = registered {
+                    error!("mDNS heartbeat error: {}", e);
+                    break;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Takes part in the master election: `priority`, `confidence` and whether
+    /// this device holds the `master` role go out with the next heartbeat, so
+    /// rivals see them up to `HEARTBEAT_PERIOD` old.
+    pub fn announce_candidate(&self, priority: u8, confidence: f32, master: bool) {
+        if let Ok(mut election) = self.election.lock() {
+            *election = Some((priority, confidence, master));
+        }
+    }
+
+    /// Sends the mDNS goodbye for `instance_name` so that browsers drop the device
+    /// right away instead of waiting for its heartbeat to time out.
+    pub fn withdraw(&self, instance_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.withdrawn.store(true, Ordering::SeqCst);
+        let fullname = format!("{}.{}", instance_name, SERVICE_TYPE);
+        let status = self.daemon.unregister(&fullname)?;
+        // The goodbye packets are sent by the daemon thread
+        let _ = status.recv_timeout(Duration::from_secs(1));
+        info!("mDNS: withdrew {}", instance_name);
+        Ok(())
+    }
+
+    fn service_info(
+        instance_name: &str,
+        port: u16,
+        seq: u64,
+        candidate: Option<(u8, f32, bool)>,
+    ) -> Result<ServiceInfo, mdns_sd::Error> {
+        let host_name = format!("{}.local.", instance_name);
+        let seq = seq.to_string();
+        let platform = platform();
+        let api = API_VERSION.to_string();
+        let mut properties = vec![
+            ("version", env!("CARGO_PKG_VERSION").to_string()),
+            ("platform", platform),
+            ("api", api),
+            ("seq", seq),
+        ];
+        if let Some((priority, confidence, master)) = candidate {
+            properties.push(("priority", priority.to_string()));
+            properties.push(("confidence", format!("{:.2}", confidence)));
+            properties.push(("master", u8::from(master).to_string()));
+        }
+        Ok(ServiceInfo::new(
+            SERVICE_TYPE,
+            instance_name,
+            &host_name,
+            "",
+            port,
+            &properties[..],
+        )?
+        .enable_addr_auto())
+    }
+
+    /// Starts browsing in a background thread; results are available via `peers()`.
+    pub fn browse(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let receiver = self.daemon.browse(SERVICE_TYPE)?;
+        let peers = self.peers.clone();
+        thread::spawn(move || {
+            while let Ok(event) = receiver.recv() {
+                let Ok(mut peers) = peers.lock() else {
+                    break;
+                };
+                match event {
+                    ServiceEvent::ServiceResolved(info) => {
+                        let name = info
+                            .get_fullname()
+                            .trim_end_matches(SERVICE_TYPE)
+                            .trim_end_matches('.')
+                            .to_string();
+                        let peer = DiscoveredPeer {
+                            name,
+                            address: preferred_address(info.get_addresses()),
+                            port: info.get_port(),
+                            version: info.get_property_val_str("version").map(str::to_string),
+                            platform: info.get_property_val_str("platform").map(str::to_string),
+                            api: info
+                                .get_property_val_str("api")
+                                .and_then(|api| api.parse().ok()),
+                            priority: info
+                                .get_property_val_str("priority")
+                                .and_then(|priority| priority.parse().ok()),
+                            confidence: info
+                                .get_property_val_str("confidence")
+                                .and_then(|confidence| confidence.parse().ok()),
+                            master: info.get_property_val_str("master") == Some("1"),
+                            online: true,
+                        };
+                        peers.seen(info.get_fullname().to_string(), peer);
+                    }
+                    ServiceEvent::ServiceRemoved(_, fullname) => {
+                        peers.remove(&fullname);
+                    }
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Known peers sorted by name, flagged offline once their heartbeat stops.
+    pub fn peers(&self) -> Vec<DiscoveredPeer> {
+        let mut list = self.peers.lock().map(|p| p.snapshot()).unwrap_or_default();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        list
+    }
+}
+
+impl Drop for Discovery {
+    fn drop(&mut self) {
+        let _ = self.daemon.shutdown();
+    }
+}
@@ -0,0 +1,72 @@
+use super::election::Candidate;
+use crate::config::DiscoveryConfig;
+use std::net::IpAddr;
+
+/// Revision of the device HTTP API, also served by `GET /status`.
+pub const API_VERSION: u32 = 1;
+
+/// An analyzer found on the local network.
+#[derive(Debug, Clone)]
+pub struct DiscoveredPeer {
+    pub name: String,
+    pub address: Option<IpAddr>,
+    pub port: u16,
+    pub version: Option<String>,
+    pub platform: Option<String>,
+    pub api: Option<u32>,
+    pub priority: Option<u8>,
+    pub confidence: Option<f32>,
+    pub master: bool,
+    pub online: bool,
+}
+
+impl DiscoveredPeer {
+    pub fn is_older_than(&self, _version: &str) -> bool {
+        false
+    }
+
+    pub fn is_compatible(&self) -> bool {
+        true
+    }
+
+    pub fn candidate(&self) -> Option<Candidate> {
+        None
+    }
+}
+
+/// `<os>-<arch>` of the build, e.g. `linux-aarch64`
+pub fn platform() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// `Discovery` of builds without the `network` feature: never starts, so callers
+/// fall back to their "mDNS unavailable" path.
+pub struct Discovery;
+
+impl Discovery {
+    pub fn new(_config: &DiscoveryConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("built without the `network` feature".into())
+    }
+
+    pub fn advertise(
+        &self,
+        _instance_name: &str,
+        _port: u16,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    pub fn announce_candidate(&self, _priority: u8, _confidence: f32, _master: bool) {}
+
+    pub fn withdraw(&self, _instance_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    pub fn browse(&self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    pub fn peers(&self) -> Vec<DiscoveredPeer> {
+        Vec::new()
+    }
+}
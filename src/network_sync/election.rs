@@ -0,0 +1,111 @@
+use serde::Deserialize;
+
+/// `[election]` section: arbitration between devices listening to the same PA,
+/// which would otherwise push their own tempo to the Link session in turn.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ElectionConfig {
+    pub enabled: bool,
+    /// Manual rank: a higher priority is master whatever its confidence
+    pub priority: u8,
+    /// Confidence lead a device needs over the master of the same priority to
+    /// take its place, so that two close analyzers do not swap at every hop
+    pub switch_margin: f32,
+}
+
+impl Default for ElectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            priority: 0,
+            switch_margin: 0.1,
+        }
+    }
+}
+
+/// A device taking part in the election, as announced over mDNS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub name: String,
+    pub priority: u8,
+    pub confidence: f32,
+    /// Holds the master role, as it announced
+    pub master: bool,
+}
+
+/// Elects the device that commits its tempo to Link: highest priority first,
+/// then highest confidence, then the first name in alphabetical order. The
+/// others stay in monitor-only mode and follow the session.
+///
+/// A master keeps the role within `switch_margin` only while it is the one
+/// candidate claiming it. Devices that elected themselves before seeing each
+/// other (each started alone) all claim it: they fall back to the strict order,
+/// which every device computes alike.
+pub struct Election {
+    config: ElectionConfig,
+    name: String,
+    master: Option<String>,
+}
+
+impl Election {
+    /// `name` is the instance name announced over mDNS.
+    pub fn new(config: ElectionConfig, name: &str) -> Self {
+        Self {
+            config,
+            name: name.to_string(),
+            master: None,
+        }
+    }
+
+    /// Name of the elected device, `None` before the first round.
+    pub fn master(&self) -> Option<&str> {
+        self.master.as_deref()
+    }
+
+    pub fn is_master(&self) -> bool {
+        self.master.as_deref() == Some(self.name.as_str())
+    }
+
+    /// Runs a round with our own `confidence` and the other online candidates.
+    /// Returns whether we are the master.
+    pub fn update(&mut self, confidence: f32, rivals: &[Candidate]) -> bool {
+        let mut candidates: Vec<Candidate> = rivals
+            .iter()
+            .filter(|rival| rival.name != self.name)
+            .cloned()
+            .collect();
+        candidates.push(Candidate {
+            name: self.name.clone(),
+            priority: self.config.priority,
+            confidence,
+            master: self.is_master(),
+        });
+
+        let best = candidates
+            .iter()
+            .max_by(|a, b| {
+                a.priority
+                    .cmp(&b.priority)
+                    .then(a.confidence.total_cmp(&b.confidence))
+                    .then(b.name.cmp(&a.name))
+            })
+            .cloned();
+        let mut claims = candidates.iter().filter(|c| c.master);
+        let agreed = match (claims.next(), claims.next()) {
+            (Some(master), None) => Some(master),
+            _ => None,
+        };
+        let keeps_role = match (agreed, &best) {
+            (Some(master), Some(best)) => {
+                best.priority == master.priority
+                    && best.confidence < master.confidence + self.config.switch_margin
+            }
+            _ => false,
+        };
+        self.master = match agreed {
+            Some(master) if keeps_role => Some(master.name.clone()),
+            _ => best.map(|best| best.name),
+        };
+        self.is_master()
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// `[link]` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LinkConfig {
+    /// Adopt the session tempo while our own detection is unsure, instead of
+    /// pushing it to the other peers
+    pub follow: bool,
+    /// Confidence a result needs to lead the session
+    pub follow_confidence: f32,
+    /// Time without a confident result before following
+    pub follow_after_ms: u64,
+    /// Beats of a Link phase (1, 2, 4 or 8): the drop re-sync and the phase shown
+    /// by the GUI align on it
+    pub quantum: u32,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            follow: false,
+            follow_confidence: 0.6,
+            follow_after_ms: 4000,
+            quantum: 4,
+        }
+    }
+}
+
+/// Who sets the tempo of the Link session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum LinkRole {
+    /// Our detected tempo is pushed to the session
+    #[default]
+    Leading,
+    /// The session tempo is adopted, ours is not published
+    Following,
+}
+
+/// Decides after each hop whether the analyzer leads the Link session or follows it.
+/// Without peers there is nobody to follow, so it always leads.
+pub struct TempoFollow {
+    config: LinkConfig,
+    last_confident: Option<Instant>,
+    role: LinkRole,
+}
+
+impl TempoFollow {
+    pub fn new(config: LinkConfig) -> Self {
+        Self {
+            config,
+            last_confident: None,
+            role: LinkRole::Leading,
+        }
+    }
+
+    pub fn role(&self) -> LinkRole {
+        self.role
+    }
+
+    pub fn set_follow(&mut self, follow: bool) {
+        self.config.follow = follow;
+        if !follow {
+            self.role = LinkRole::Leading;
+        }
+    }
+
+    /// Updates the role with the confidence of the last analysis (`None` when it gave
+    /// no tempo) and the number of Link peers. Only publish our tempo while leading.
+    pub fn update(&mut self, confidence: Option<f32>, peers: usize, now: Instant) -> LinkRole {
+        if confidence.is_some_and(|c| c >= self.config.follow_confidence) {
+            self.last_confident = Some(now);
+        }
+        let unsure = self.last_confident.is_none_or(|at| {
+            now.duration_since(at) >= Duration::from_millis(self.config.follow_after_ms)
+        });
+        self.role = if self.config.follow && peers > 0 && unsure {
+            LinkRole::Following
+        } else {
+            LinkRole::Leading
+        };
+        self.role
+    }
+}
@@ -1,2 +1,16 @@
+// Stand-ins with the same API are built when the `link` or `network` feature is off
+#[cfg_attr(not(feature = "link"), path = "ableton_disabled.rs")]
 pub mod ableton;
+// Signed commands of the device HTTP API
+pub mod api_auth;
+pub mod artnet;
+// Alignment of the device clocks, used by the devices dashboard
+pub mod clock_sync;
+// Client of the device HTTP API, used by the desktop window
+#[cfg(feature = "gui")]
+pub mod device_api;
+#[cfg_attr(not(feature = "network"), path = "discovery_disabled.rs")]
+pub mod discovery;
+pub mod election;
+pub mod follow;
 pub use ableton::LinkManager;
@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+/// Beat of the grid extrapolated from the last detection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BeatEvent {
+    pub at: Instant,
+    pub bpm: f32,
+    /// Position in the bar, 0 on downbeats. Bars are counted from the last drop.
+    pub beat_in_bar: u8,
+    /// Beats since the last drop, 0 on the drop beat
+    pub count: u64,
+}
+
+/// Beat grid between two detections, for the sinks without a clock of their
+/// own. Polled from the run loops, so a beat comes out on the first poll after
+/// it, up to half a period late; later than that it is skipped.
+#[derive(Debug, Clone, Default)]
+pub struct BeatClock {
+    bpm: f32,
+    anchor: Option<Instant>,
+    last_beat: Option<Instant>,
+    count: u64,
+}
+
+impl BeatClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tempo and time of a beat of the grid.
+    pub fn set(&mut self, bpm: f32, anchor: Instant) {
+        self.bpm = bpm;
+        self.anchor = Some(anchor);
+    }
+
+    /// The next beat starts a bar (drop).
+    pub fn restart_bar(&mut self) {
+        self.count = 0;
+    }
+
+    /// Beat of the grid that started since the last poll, if any.
+    pub fn poll(&mut self, now: Instant) -> Option<BeatEvent> {
+        let anchor = self.anchor.filter(|_| self.bpm > 0.0)?;
+        let period = Duration::from_secs_f32(60.0 / self.bpm);
+        let elapsed = now.checked_duration_since(anchor)?;
+        let beats = (elapsed.as_secs_f64() / period.as_secs_f64()).floor();
+        let at = anchor + period.mul_f64(beats);
+        // An anchor moved by the next detection lands close to the beat just sent
+        if self.last_beat.is_some_and(|last| at <= last + period / 2) {
+            return None;
+        }
+        self.last_beat = Some(at);
+        let count = self.count;
+        // A skipped beat still counts, the bar stays in place
+        self.count += 1;
+        if now.duration_since(at) > period / 2 {
+            return None;
+        }
+        Some(BeatEvent {
+            at,
+            bpm: self.bpm,
+            beat_in_bar: (count % 4) as u8,
+            count,
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use super::beat_clock::BeatEvent;
+use super::{BroadcastConfig, OutputMessage, OutputSink, TempoUpdate};
+use crate::core_bpm::sections::Section;
+use std::error::Error;
+use std::net::UdpSocket;
+use tracing::{info, warn};
+
+/// `[outputs.broadcast]`: one JSON datagram per tempo, beat, drop and section
+/// (see `OutputMessage`) to a broadcast address, for receivers that do not
+/// speak Link or OSC.
+pub struct BroadcastSink {
+    socket: UdpSocket,
+    failing: Option<String>,
+}
+
+impl BroadcastSink {
+    pub fn new(config: &BroadcastConfig) -> Result<Self, Box<dyn Error>> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_broadcast(true)?;
+        socket.connect(&config.target)?;
+        info!("Network broadcast to {}", config.target);
+        Ok(Self {
+            socket,
+            failing: None,
+        })
+    }
+
+    fn send(&mut self, message: &OutputMessage) {
+        let Ok(datagram) = serde_json::to_vec(message) else {
+            return;
+        };
+        match self.socket.send(&datagram) {
+            Ok(_) => self.failing = None,
+            Err(e) => {
+                if self.failing.is_none() {
+                    warn!("Network broadcast error: {}", e);
+                }
+                self.failing = Some(e.to_string());
+            }
+        }
+    }
+}
+
+impl OutputSink for BroadcastSink {
+    fn name(&self) -> &'static str {
+        "Broadcast"
+    }
+
+    fn on_result(&mut self, update: &TempoUpdate) {
+        if let Some(message) = OutputMessage::tempo(update) {
+            self.send(&message);
+        }
+    }
+
+    fn on_beat(&mut self, beat: &BeatEvent) {
+        self.send(&OutputMessage::beat(beat));
+    }
+
+    fn on_drop(&mut self, update: &TempoUpdate) {
+        self.send(&OutputMessage::Drop {
+            bpm: update.shown_bpm,
+        });
+    }
+
+    fn on_section(&mut self, section: Section) {
+        self.send(&OutputMessage::Section { section });
+    }
+
+    fn failure(&self) -> Option<String> {
+        self.failing.clone()
+    }
+}
@@ -0,0 +1,79 @@
+use super::metronome::Metronome;
+use super::{MetronomeConfig, OutputSink, TempoUpdate};
+use crate::core_bpm::audio::OutputDevice;
+use crate::midi_beats::MidiClickOutput;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::info;
+
+/// `[outputs.metronome]`: a live click for drummers playing along with the DJ,
+/// on an output device and/or as MIDI notes. Both follow the same `Metronome`.
+pub struct ClickTrackSink {
+    metronome: Arc<Mutex<Metronome>>,
+    // Stopped when dropped
+    _audio: Option<cpal::Stream>,
+    _midi: Option<MidiClickOutput>,
+}
+
+impl ClickTrackSink {
+    pub fn new(config: &MetronomeConfig) -> Result<Self, Box<dyn Error>> {
+        config.validate()?;
+        let metronome = Arc::new(Mutex::new(Metronome::new(
+            config.beats_per_bar,
+            config.count_in,
+            config.accent,
+        )));
+        let audio = if config.audio {
+            let output = OutputDevice::open(config.device.as_deref())?;
+            info!("Metronome output: {}", output.name);
+            let rate = output.sample_rate();
+            let volume = config.volume.clamp(0.0, 1.0);
+            let clicks = metronome.clone();
+            Some(output.play(move |out, playback| {
+                // Never wait on the analysis thread in the audio callback
+                match clicks.try_lock() {
+                    Ok(metronome) => metronome.render(out, rate, playback, volume),
+                    Err(_) => out.fill(0.0),
+                }
+            })?)
+        } else {
+            None
+        };
+        let midi = if config.midi {
+            Some(MidiClickOutput::new(config, metronome.clone())?)
+        } else {
+            None
+        };
+        if audio.is_none() && midi.is_none() {
+            return Err("neither `audio` nor `midi` is enabled".into());
+        }
+        Ok(Self {
+            metronome,
+            _audio: audio,
+            _midi: midi,
+        })
+    }
+}
+
+impl OutputSink for ClickTrackSink {
+    fn name(&self) -> &'static str {
+        "Metronome"
+    }
+
+    fn on_result(&mut self, update: &TempoUpdate) {
+        let now = Instant::now();
+        let beat = update.beat_age.and_then(|age| now.checked_sub(age));
+        if let (Some(bpm), Some(beat), Ok(mut metronome)) =
+            (update.bpm, beat, self.metronome.lock())
+        {
+            metronome.set_beat(bpm, beat, now);
+        }
+    }
+
+    fn on_drop(&mut self, _update: &TempoUpdate) {
+        if let Ok(mut metronome) = self.metronome.lock() {
+            metronome.restart_bar(Instant::now());
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::core_bpm::monitor::{CLICK_HZ, click_sample};
+use std::time::{Duration, Instant};
+
+// A new tempo further than this from the current one (relative) counts in again
+const RELOCK: f32 = 0.04;
+// Clicks stop this long after the last detection (analysis stopped or gated)
+const HOLD: Duration = Duration::from_secs(8);
+// A click found later than this (thread stalled) is skipped
+const CLICK_LATE: Duration = Duration::from_millis(30);
+
+/// Role of a click in the bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClickKind {
+    /// Before the click starts, after a lock or a tempo change
+    CountIn,
+    /// First beat of the bar, when accented
+    Downbeat,
+    Beat,
+}
+
+impl ClickKind {
+    /// Pitch of the audio click, beats sound like the monitor click.
+    pub fn frequency(self) -> f32 {
+        match self {
+            ClickKind::CountIn => 2000.0,
+            ClickKind::Downbeat => 1500.0,
+            ClickKind::Beat => CLICK_HZ,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Click {
+    pub at: Instant,
+    pub kind: ClickKind,
+    /// Position in the bar, 0 on downbeats
+    pub beat_in_bar: u8,
+}
+
+// Beat grid the clicks follow. Times are seconds from `bar`, a downbeat
+#[derive(Debug, Clone, Copy)]
+struct Grid {
+    bpm: f32,
+    bar: Instant,
+    // First click, and first one after the count-in
+    start: f64,
+    count_in_end: f64,
+    updated: Instant,
+}
+
+impl Grid {
+    fn period(&self) -> f64 {
+        60.0 / self.bpm as f64
+    }
+
+    // Seconds from the downbeat to `at`, negative before it
+    fn offset(&self, at: Instant) -> f64 {
+        seconds_between(self.bar, at)
+    }
+}
+
+/// Click track phase-locked to the beat grid of the analyzer: a click on every
+/// beat, accented on downbeats. A lock, or a tempo change, first plays
+/// `count_in` clicks of the new tempo, the click then starts on a downbeat.
+/// Bars restart on drops, like `BeatClock`.
+///
+/// Rendered as audio by the output stream (`render`) and polled by the MIDI
+/// thread (`due_click`).
+#[derive(Debug, Clone)]
+pub struct Metronome {
+    beats_per_bar: u8,
+    count_in: u8,
+    accent: bool,
+    grid: Option<Grid>,
+}
+
+impl Metronome {
+    pub fn new(beats_per_bar: u8, count_in: u8, accent: bool) -> Self {
+        Self {
+            beats_per_bar: beats_per_bar.max(1),
+            count_in,
+            accent,
+            grid: None,
+        }
+    }
+
+    /// Follows the analyzer: `bpm`, a beat at `beat`. Close to the current
+    /// tempo, the grid moves onto the beat and the bar stays in place.
+    pub fn set_beat(&mut self, bpm: f32, beat: Instant, now: Instant) {
+        if bpm <= 0.0 {
+            self.stop();
+            return;
+        }
+        let grid = match self.grid.filter(|grid| {
+            (bpm / grid.bpm - 1.0).abs() <= RELOCK && now.duration_since(grid.updated) <= HOLD
+        }) {
+            Some(grid) => {
+                // Same beat index in the bar as on the current grid
+                let index = (grid.offset(beat) / grid.period()).round();
+                let bar = shift(beat, -index * 60.0 / bpm as f64);
+                let moved = seconds_between(grid.bar, bar);
+                Grid {
+                    bpm,
+                    bar,
+                    start: grid.start - moved,
+                    count_in_end: grid.count_in_end - moved,
+                    updated: now,
+                }
+            }
+            None => {
+                // Count in from the next beat, the click starts on a downbeat
+                let period = 60.0 / bpm as f64;
+                let next = (seconds_between(beat, now) / period).ceil().max(0.0);
+                let first = shift(beat, next * period);
+                let count_in = self.count_in as f64 * period;
+                Grid {
+                    bpm,
+                    bar: shift(first, count_in),
+                    start: -count_in,
+                    count_in_end: 0.0,
+                    updated: now,
+                }
+            }
+        };
+        self.grid = Some(grid);
+    }
+
+    /// The beat nearest to `now` starts a bar (drop).
+    pub fn restart_bar(&mut self, now: Instant) {
+        if let Some(grid) = &mut self.grid {
+            let period = grid.period();
+            let nearest = (grid.offset(now) / period).round() * period;
+            grid.bar = shift(grid.bar, nearest);
+            grid.start -= nearest;
+            grid.count_in_end -= nearest;
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.grid = None;
+    }
+
+    /// Tempo of the clicks, `None` while stopped.
+    pub fn bpm(&self) -> Option<f32> {
+        self.grid.map(|grid| grid.bpm)
+    }
+
+    // Click on the beat `index` beats from the downbeat, if any
+    fn click_at(&self, grid: &Grid, index: f64) -> Option<ClickKind> {
+        let offset = index * grid.period();
+        // Rounding: a click on the very first beat
+        if offset < grid.start - 1e-6 {
+            return None;
+        }
+        let in_bar = index.rem_euclid(self.beats_per_bar as f64) as u8;
+        Some(if offset < grid.count_in_end - 1e-6 {
+            ClickKind::CountIn
+        } else if in_bar == 0 && self.accent {
+            ClickKind::Downbeat
+        } else {
+            ClickKind::Beat
+        })
+    }
+
+    /// Click that started since `last_sent`, unless it is too old. Polled by
+    /// the MIDI thread.
+    pub fn due_click(&self, last_sent: Option<Instant>, now: Instant) -> Option<Click> {
+        let grid = self
+            .grid
+            .filter(|grid| now.duration_since(grid.updated) <= HOLD)?;
+        let period = grid.period();
+        let index = (grid.offset(now) / period).floor();
+        let kind = self.click_at(&grid, index)?;
+        let at = shift(grid.bar, index * period);
+        let fresh = now.checked_duration_since(at)? <= CLICK_LATE;
+        let new = last_sent.is_none_or(|sent| seconds_between(sent, at) > period / 2.0);
+        (fresh && new).then_some(Click {
+            at,
+            kind,
+            beat_in_bar: index.rem_euclid(self.beats_per_bar as f64) as u8,
+        })
+    }
+
+    /// Fills `out` (mono, `rate` Hz) with the clicks, the first sample played
+    /// at `playback`.
+    pub fn render(&self, out: &mut [f32], rate: u32, playback: Instant, volume: f32) {
+        let Some(grid) = self
+            .grid
+            .filter(|grid| playback.saturating_duration_since(grid.updated) <= HOLD)
+        else {
+            out.fill(0.0);
+            return;
+        };
+        let period = grid.period();
+        let first = grid.offset(playback);
+        for (i, sample) in out.iter_mut().enumerate() {
+            let offset = first + i as f64 / rate as f64;
+            let index = (offset / period).floor();
+            let since = offset - index * period;
+            *sample = match self.click_at(&grid, index) {
+                Some(kind) => click_sample(since as f32, kind.frequency()) * volume,
+                None => 0.0,
+            };
+        }
+    }
+}
+
+// Seconds from `from` to `to`, negative when `to` is earlier
+fn seconds_between(from: Instant, to: Instant) -> f64 {
+    match to.checked_duration_since(from) {
+        Some(after) => after.as_secs_f64(),
+        None => -from.duration_since(to).as_secs_f64(),
+    }
+}
+
+fn shift(at: Instant, seconds: f64) -> Instant {
+    let by = Duration::from_secs_f64(seconds.abs());
+    if seconds >= 0.0 {
+        at + by
+    } else {
+        at.checked_sub(by).unwrap_or(at)
+    }
+}
@@ -0,0 +1,493 @@
+// Outputs of the tempo: each sink gets the detections, the beats of the grid
+// and the drops from the run loops through `SinkRegistry`
+pub mod beat_clock;
+pub mod broadcast;
+pub mod click_track;
+pub mod metronome;
+pub mod osc;
+// Stand-in that never opens without the `websocket` feature
+#[cfg_attr(not(feature = "websocket"), path = "websocket_disabled.rs")]
+pub mod websocket;
+
+use crate::config::AppConfig;
+use crate::core_bpm::beat_features::BeatFeatures;
+use crate::core_bpm::key::Key;
+use crate::core_bpm::sections::Section;
+use crate::midi_beats::MidiBeatOutput;
+use crate::network_sync::LinkManager;
+use crate::network_sync::artnet::ArtNetOutput;
+use beat_clock::{BeatClock, BeatEvent};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tracing::error;
+
+/// What the sinks get after a hop with a detection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoUpdate {
+    /// Tempo shown to the user: the detection, or the Link session tempo while
+    /// following it
+    pub shown_bpm: f32,
+    /// Tempo passed on to the outputs, `None` until `[verification]` confirmed one
+    pub bpm: Option<f32>,
+    /// Time since the last beat of the grid
+    pub beat_age: Option<Duration>,
+    /// The analyzer (or a manual override) leads the Link session
+    pub leading: bool,
+    pub is_drop: bool,
+    /// Age of the last beat on drops, for the Link downbeat re-sync
+    pub beat_offset: Option<Duration>,
+    pub confidence: f32,
+    /// Part of the track, see `SectionClassifier`
+    pub section: Section,
+    /// Musical key, with `[key] enabled`
+    pub key: Option<Key>,
+}
+
+/// Destination of the tempo. Only `on_result` is required, sinks with a clock of
+/// their own (Art-Net, MIDI notes) can ignore `on_beat`.
+pub trait OutputSink {
+    /// Shown in the logs and the failure notices
+    fn name(&self) -> &'static str;
+
+    /// Detection of an analysis hop.
+    fn on_result(&mut self, update: &TempoUpdate);
+
+    /// Beat of the grid extrapolated from the last detection (see `BeatClock`).
+    fn on_beat(&mut self, _beat: &BeatEvent) {}
+
+    /// Drop detected on this hop, after `on_result`.
+    fn on_drop(&mut self, _update: &TempoUpdate) {}
+
+    /// The track moved to another section, after `on_result` and `on_drop`.
+    fn on_section(&mut self, _section: Section) {}
+
+    /// First beat of a phrase (`[trigger]`), after its `on_beat`.
+    fn on_phrase(&mut self, _beat: &BeatEvent) {}
+
+    /// Input RMS level (0.0 - 0.6), at the capture level rate.
+    fn on_level(&mut self, _rms: f32) {}
+
+    /// Band levels of the beat that just ended, see `BeatFeatureSampler`.
+    fn on_beat_features(&mut self, _features: BeatFeatures) {}
+
+    /// Whether the analysis should compute `on_beat_features`.
+    fn wants_beat_features(&self) -> bool {
+        false
+    }
+
+    /// Last error while the sink is failing, `None` while it delivers.
+    fn failure(&self) -> Option<String> {
+        None
+    }
+}
+
+/// JSON messages of the WebSocket and broadcast sinks.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum OutputMessage {
+    Tempo {
+        bpm: f32,
+        confidence: f32,
+        leading: bool,
+        /// Camelot notation, `null` until a key is detected
+        key: Option<Key>,
+    },
+    Beat {
+        bpm: f32,
+        /// 1 to 4, 1 on downbeats
+        beat: u8,
+    },
+    Drop {
+        bpm: f32,
+    },
+    Section {
+        section: Section,
+    },
+}
+
+impl OutputMessage {
+    fn tempo(update: &TempoUpdate) -> Option<Self> {
+        update.bpm.map(|bpm| Self::Tempo {
+            bpm,
+            confidence: update.confidence,
+            leading: update.leading,
+            key: update.key,
+        })
+    }
+
+    fn beat(beat: &BeatEvent) -> Self {
+        Self::Beat {
+            bpm: beat.bpm,
+            beat: beat.beat_in_bar + 1,
+        }
+    }
+}
+
+/// `[outputs]` section: sinks on top of Link, `[artnet]` and `[midi_out]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct OutputsConfig {
+    pub osc: OscConfig,
+    pub websocket: WebSocketConfig,
+    pub broadcast: BroadcastConfig,
+    pub metronome: MetronomeConfig,
+}
+
+/// `[outputs.osc]` section.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OscConfig {
+    pub enabled: bool,
+    /// Receiver `host:port`
+    pub target: String,
+    /// Address prefix of the messages
+    pub prefix: String,
+}
+
+impl Default for OscConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: "127.0.0.1:9000".to_string(),
+            prefix: "/bpm".to_string(),
+        }
+    }
+}
+
+/// `[outputs.websocket]` section.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WebSocketConfig {
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 9001,
+        }
+    }
+}
+
+/// `[outputs.broadcast]` section.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BroadcastConfig {
+    pub enabled: bool,
+    /// Broadcast (or unicast) `address:port`
+    pub target: String,
+}
+
+impl Default for BroadcastConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: "255.255.255.255:9002".to_string(),
+        }
+    }
+}
+
+/// `[outputs.metronome]` section. The notes default to the General MIDI wood
+/// blocks and claves, on the drum channel.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MetronomeConfig {
+    pub enabled: bool,
+    /// Click on an output device
+    pub audio: bool,
+    /// Output device, the default one when unset
+    pub device: Option<String>,
+    /// 0.0 - 1.0
+    pub volume: f32,
+    /// Click as MIDI notes (needs the `midi` feature)
+    pub midi: bool,
+    /// Part of the output port name, the first port when unset
+    pub midi_port: Option<String>,
+    /// MIDI channel, 1-16
+    pub channel: u8,
+    pub note: u8,
+    /// First beat of each bar, with `accent`
+    pub accent_note: u8,
+    pub count_in_note: u8,
+    /// Time before the Note Off
+    pub note_ms: u64,
+    pub beats_per_bar: u8,
+    /// Clicks counted in after a lock or a tempo change, 0 to start right away
+    pub count_in: u8,
+    /// Accent on downbeats
+    pub accent: bool,
+}
+
+impl Default for MetronomeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            audio: true,
+            device: None,
+            volume: 0.8,
+            midi: false,
+            midi_port: None,
+            channel: 10,
+            note: 77,
+            accent_note: 76,
+            count_in_note: 75,
+            note_ms: 30,
+            beats_per_bar: 4,
+            count_in: 4,
+            accent: true,
+        }
+    }
+}
+
+impl MetronomeConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=16).contains(&self.channel) {
+            return Err("channel must be between 1 and 16".to_string());
+        }
+        if [self.note, self.accent_note, self.count_in_note]
+            .iter()
+            .any(|&note| note > 127)
+        {
+            return Err("notes must be between 0 and 127".to_string());
+        }
+        if self.beats_per_bar == 0 {
+            return Err("beats_per_bar must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Sinks the run loops fan out to. Link is kept apart from the others: the
+/// loops also read the session back (tempo, peers, phase) through `link_mut`.
+pub struct SinkRegistry {
+    link: LinkManager,
+    sinks: Vec<Box<dyn OutputSink>>,
+    clock: BeatClock,
+    // Last section passed on, `None` before the first result
+    section: Option<Section>,
+    // Phrase length with `[trigger] enabled`, phrases start on drops
+    beats_per_phrase: Option<u64>,
+}
+
+impl SinkRegistry {
+    pub fn new(link: LinkManager) -> Self {
+        Self {
+            link,
+            sinks: Vec::new(),
+            clock: BeatClock::new(),
+            section: None,
+            beats_per_phrase: None,
+        }
+    }
+
+    /// Link, and the sinks enabled in the config file. A sink that fails to open
+    /// is logged and left out.
+    pub fn from_config(link: LinkManager, config: &AppConfig) -> Self {
+        let mut registry = Self::new(link);
+        if config.trigger.enabled {
+            registry.set_phrase(config.trigger.bars * config.trigger.beats_per_bar);
+        }
+        if config.artnet.enabled {
+            registry.open("Art-Net", || ArtNetOutput::new(config.artnet.clone()));
+        }
+        if config.midi_out.enabled {
+            registry.open("MIDI beat", || MidiBeatOutput::new(config.midi_out.clone()));
+        }
+        let outputs = &config.outputs;
+        if outputs.osc.enabled {
+            registry.open("OSC", || osc::OscSink::new(&outputs.osc));
+        }
+        if outputs.websocket.enabled {
+            registry.open("WebSocket", || {
+                websocket::WebSocketSink::new(&outputs.websocket)
+            });
+        }
+        if outputs.broadcast.enabled {
+            registry.open("Broadcast", || {
+                broadcast::BroadcastSink::new(&outputs.broadcast)
+            });
+        }
+        if outputs.metronome.enabled {
+            registry.open("Metronome", || {
+                click_track::ClickTrackSink::new(&outputs.metronome)
+            });
+        }
+        registry
+    }
+
+    fn open<S: OutputSink + 'static>(
+        &mut self,
+        name: &str,
+        open: impl FnOnce() -> Result<S, Box<dyn std::error::Error>>,
+    ) {
+        match open() {
+            Ok(sink) => self.register(Box::new(sink)),
+            Err(e) => error!("{} output init error: {}", name, e),
+        }
+    }
+
+    pub fn register(&mut self, sink: Box<dyn OutputSink>) {
+        self.sinks.push(sink);
+    }
+
+    /// Sends `on_phrase` every `beats` beats from the last drop.
+    pub fn set_phrase(&mut self, beats: u32) {
+        self.beats_per_phrase = Some(beats.max(1) as u64);
+    }
+
+    pub fn link(&self) -> &LinkManager {
+        &self.link
+    }
+
+    pub fn link_mut(&mut self) -> &mut LinkManager {
+        &mut self.link
+    }
+
+    /// Names of the registered sinks, Link first.
+    pub fn names(&self) -> Vec<&'static str> {
+        std::iter::once(self.link.name())
+            .chain(self.sinks.iter().map(|sink| sink.name()))
+            .collect()
+    }
+
+    fn each(&mut self, mut f: impl FnMut(&mut dyn OutputSink)) {
+        f(&mut self.link);
+        for sink in &mut self.sinks {
+            f(sink.as_mut());
+        }
+    }
+
+    /// Fans a detection out, then the drop if there is one and the section when
+    /// it changed. The beat clock follows the tempo passed on.
+    pub fn on_result(&mut self, update: &TempoUpdate) {
+        if let (Some(bpm), Some(age)) = (update.bpm, update.beat_age)
+            && let Some(anchor) = Instant::now().checked_sub(age)
+        {
+            self.clock.set(bpm, anchor);
+        }
+        self.each(|sink| sink.on_result(update));
+        if update.is_drop {
+            self.clock.restart_bar();
+            self.each(|sink| sink.on_drop(update));
+        }
+        if self.section != Some(update.section) {
+            self.section = Some(update.section);
+            self.each(|sink| sink.on_section(update.section));
+        }
+    }
+
+    /// Sends the beat of the grid that started since the last call, if any. The
+    /// loops call it with every capture message.
+    pub fn tick(&mut self, now: Instant) {
+        if let Some(beat) = self.clock.poll(now) {
+            self.each(|sink| sink.on_beat(&beat));
+            if self
+                .beats_per_phrase
+                .is_some_and(|beats| beat.count.is_multiple_of(beats))
+            {
+                self.each(|sink| sink.on_phrase(&beat));
+            }
+        }
+    }
+
+    pub fn on_level(&mut self, rms: f32) {
+        self.each(|sink| sink.on_level(rms));
+    }
+
+    pub fn on_beat_features(&mut self, features: BeatFeatures) {
+        self.each(|sink| sink.on_beat_features(features));
+    }
+
+    pub fn wants_beat_features(&self) -> bool {
+        self.sinks.iter().any(|sink| sink.wants_beat_features())
+    }
+
+    /// (name, last error) of the sinks currently failing.
+    pub fn failures(&self) -> Vec<(&'static str, String)> {
+        self.sinks
+            .iter()
+            .filter_map(|sink| sink.failure().map(|e| (sink.name(), e)))
+            .collect()
+    }
+}
+
+// Pushes the tempo to the session while leading, the drop re-aligns the downbeat
+impl OutputSink for LinkManager {
+    fn name(&self) -> &'static str {
+        "Link"
+    }
+
+    fn on_result(&mut self, update: &TempoUpdate) {
+        if let Some(bpm) = update.bpm.filter(|_| update.leading) {
+            self.update_tempo(bpm as f64, false, None);
+        }
+    }
+
+    fn on_drop(&mut self, update: &TempoUpdate) {
+        if let Some(bpm) = update.bpm.filter(|_| update.leading) {
+            self.update_tempo(bpm as f64, true, update.beat_offset);
+        }
+    }
+}
+
+impl OutputSink for ArtNetOutput {
+    fn name(&self) -> &'static str {
+        "Art-Net"
+    }
+
+    fn on_result(&mut self, update: &TempoUpdate) {
+        if let Some(bpm) = update.bpm {
+            self.update_tempo(bpm, update.beat_age);
+        }
+    }
+
+    fn on_drop(&mut self, _update: &TempoUpdate) {
+        self.notify_drop();
+    }
+
+    fn on_level(&mut self, rms: f32) {
+        self.update_energy(rms);
+    }
+
+    fn on_beat_features(&mut self, features: BeatFeatures) {
+        self.update_beat_features(features);
+    }
+
+    fn wants_beat_features(&self) -> bool {
+        true
+    }
+
+    fn failure(&self) -> Option<String> {
+        let health = self.health();
+        health
+            .is_failing()
+            .then(|| health.last_error.unwrap_or_default())
+    }
+}
+
+// Beat notes for lighting software, on the thread of `MidiBeatOutput`
+impl OutputSink for MidiBeatOutput {
+    fn name(&self) -> &'static str {
+        "MIDI"
+    }
+
+    fn on_result(&mut self, update: &TempoUpdate) {
+        if let Some(bpm) = update.bpm {
+            self.update_tempo(bpm, update.beat_age);
+        }
+    }
+
+    fn on_drop(&mut self, _update: &TempoUpdate) {
+        self.notify_drop();
+    }
+
+    fn on_phrase(&mut self, _beat: &BeatEvent) {
+        self.notify_phrase();
+    }
+
+    fn on_level(&mut self, rms: f32) {
+        self.update_energy(rms);
+    }
+}
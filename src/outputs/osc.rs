@@ -0,0 +1,121 @@
+use super::beat_clock::BeatEvent;
+use super::{OscConfig, OutputSink, TempoUpdate};
+use crate::core_bpm::key::Key;
+use crate::core_bpm::sections::Section;
+use std::error::Error;
+use std::net::UdpSocket;
+use tracing::{info, warn};
+
+/// OSC argument, big-endian on the wire.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OscArg<'a> {
+    Int(i32),
+    Float(f32),
+    Str(&'a str),
+}
+
+/// Encodes an OSC 1.0 message: address, type tags, then the arguments, each
+/// padded to 4 bytes.
+pub fn osc_message(address: &str, args: &[OscArg<'_>]) -> Vec<u8> {
+    let mut tags = String::from(",");
+    for arg in args {
+        tags.push(match arg {
+            OscArg::Int(_) => 'i',
+            OscArg::Float(_) => 'f',
+            OscArg::Str(_) => 's',
+        });
+    }
+    let mut packet = Vec::new();
+    push_padded(&mut packet, address.as_bytes());
+    push_padded(&mut packet, tags.as_bytes());
+    for arg in args {
+        match arg {
+            OscArg::Int(value) => packet.extend_from_slice(&value.to_be_bytes()),
+            OscArg::Float(value) => packet.extend_from_slice(&value.to_be_bytes()),
+            OscArg::Str(value) => push_padded(&mut packet, value.as_bytes()),
+        }
+    }
+    packet
+}
+
+// OSC strings end with at least one NUL, up to a multiple of 4 bytes
+fn push_padded(packet: &mut Vec<u8>, bytes: &[u8]) {
+    packet.extend_from_slice(bytes);
+    let padding = 4 - bytes.len() % 4;
+    packet.extend(std::iter::repeat_n(0, padding));
+}
+
+/// `[outputs.osc]`: `<prefix>/tempo f`, `<prefix>/beat i`, `<prefix>/drop`,
+/// `<prefix>/section s`, `<prefix>/key s` (Camelot) and `<prefix>/phrase` over
+/// UDP, for VJ and lighting software (Resolume, TouchDesigner, QLC+).
+pub struct OscSink {
+    socket: UdpSocket,
+    prefix: String,
+    // Last key sent, the key is only sent when it changes
+    key: Option<Key>,
+    failing: Option<String>,
+}
+
+impl OscSink {
+    pub fn new(config: &OscConfig) -> Result<Self, Box<dyn Error>> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_broadcast(true)?;
+        socket.connect(&config.target)?;
+        info!("OSC output to {} ({})", config.target, config.prefix);
+        Ok(Self {
+            socket,
+            prefix: config.prefix.trim_end_matches('/').to_string(),
+            key: None,
+            failing: None,
+        })
+    }
+
+    fn send(&mut self, path: &str, args: &[OscArg<'_>]) {
+        let packet = osc_message(&format!("{}/{}", self.prefix, path), args);
+        match self.socket.send(&packet) {
+            Ok(_) => self.failing = None,
+            Err(e) => {
+                if self.failing.is_none() {
+                    warn!("OSC send error: {}", e);
+                }
+                self.failing = Some(e.to_string());
+            }
+        }
+    }
+}
+
+impl OutputSink for OscSink {
+    fn name(&self) -> &'static str {
+        "OSC"
+    }
+
+    fn on_result(&mut self, update: &TempoUpdate) {
+        if let Some(bpm) = update.bpm {
+            self.send("tempo", &[OscArg::Float(bpm)]);
+        }
+        if let Some(key) = update.key.filter(|&key| self.key != Some(key)) {
+            self.key = Some(key);
+            self.send("key", &[OscArg::Str(&key.camelot())]);
+        }
+    }
+
+    fn on_beat(&mut self, beat: &BeatEvent) {
+        self.send("beat", &[OscArg::Int(beat.beat_in_bar as i32 + 1)]);
+    }
+
+    fn on_drop(&mut self, _update: &TempoUpdate) {
+        self.send("drop", &[]);
+    }
+
+    fn on_section(&mut self, section: Section) {
+        self.send("section", &[OscArg::Str(section.name())]);
+    }
+
+    fn on_phrase(&mut self, _beat: &BeatEvent) {
+        self.send("phrase", &[]);
+    }
+
+    fn failure(&self) -> Option<String> {
+        self.failing.clone()
+    }
+}
@@ -0,0 +1,96 @@
+use super::beat_clock::BeatEvent;
+use super::{OutputMessage, OutputSink, TempoUpdate, WebSocketConfig};
+use crate::core_bpm::sections::Section;
+use std::error::Error;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+use tracing::{info, warn};
+use tungstenite::{Message, WebSocket};
+
+// New clients are accepted between two messages at least this often
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+// A client that does not take a message in this time is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// `[outputs.websocket]`: pushes every tempo, beat, drop and section as JSON
+/// (see `OutputMessage`) to the connected WebSocket clients, for browser
+/// overlays and visuals. Clients only listen, what they send is ignored.
+pub struct WebSocketSink {
+    // Messages for the server thread, which owns the clients
+    sender: Sender<String>,
+}
+
+impl WebSocketSink {
+    pub fn new(config: &WebSocketConfig) -> Result<Self, Box<dyn Error>> {
+        let listener = TcpListener::bind(("0.0.0.0", config.port))?;
+        listener.set_nonblocking(true)?;
+        info!("WebSocket output on port {}", config.port);
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || serve(listener, receiver));
+        Ok(Self { sender })
+    }
+
+    fn send(&self, message: &OutputMessage) {
+        if let Ok(text) = serde_json::to_string(message) {
+            let _ = self.sender.send(text);
+        }
+    }
+}
+
+// Accepts the clients and fans the messages out to them. Stops with the sink.
+fn serve(listener: TcpListener, receiver: Receiver<String>) {
+    let mut clients: Vec<WebSocket<TcpStream>> = Vec::new();
+    loop {
+        while let Ok((stream, address)) = listener.accept() {
+            match accept(stream) {
+                Ok(client) => {
+                    info!("WebSocket client {} connected", address);
+                    clients.push(client);
+                }
+                Err(e) => warn!("WebSocket handshake with {} failed: {}", address, e),
+            }
+        }
+        match receiver.recv_timeout(ACCEPT_INTERVAL) {
+            Ok(text) => {
+                clients.retain_mut(|client| client.send(Message::text(text.clone())).is_ok())
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+fn accept(stream: TcpStream) -> Result<WebSocket<TcpStream>, Box<dyn Error>> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(WRITE_TIMEOUT))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    tungstenite::accept(stream).map_err(|e| e.to_string().into())
+}
+
+impl OutputSink for WebSocketSink {
+    fn name(&self) -> &'static str {
+        "WebSocket"
+    }
+
+    fn on_result(&mut self, update: &TempoUpdate) {
+        if let Some(message) = OutputMessage::tempo(update) {
+            self.send(&message);
+        }
+    }
+
+    fn on_beat(&mut self, beat: &BeatEvent) {
+        self.send(&OutputMessage::beat(beat));
+    }
+
+    fn on_drop(&mut self, update: &TempoUpdate) {
+        self.send(&OutputMessage::Drop {
+            bpm: update.shown_bpm,
+        });
+    }
+
+    fn on_section(&mut self, section: Section) {
+        self.send(&OutputMessage::Section { section });
+    }
+}
@@ -0,0 +1,19 @@
+use super::{OutputSink, TempoUpdate, WebSocketConfig};
+use std::error::Error;
+
+/// `WebSocketSink` of builds without the `websocket` feature: never opens.
+pub struct WebSocketSink;
+
+impl WebSocketSink {
+    pub fn new(_config: &WebSocketConfig) -> Result<Self, Box<dyn Error>> {
+        Err("built without the `websocket` feature".into())
+    }
+}
+
+impl OutputSink for WebSocketSink {
+    fn name(&self) -> &'static str {
+        "WebSocket"
+    }
+
+    fn on_result(&mut self, _update: &TempoUpdate) {}
+}
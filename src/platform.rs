@@ -0,0 +1,146 @@
+use std::error::Error;
+
+// Capture rate of every mode: the ARM boards analyze at 12 kHz to spare their CPU
+pub const TARGET_SAMPLE_RATE: u32 = if cfg!(all(
+    any(target_arch = "aarch64", target_arch = "arm"),
+    target_os = "linux"
+)) {
+    12000
+} else {
+    48000
+};
+
+/// Front-end started by `main`, chosen with `--mode` or detected at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Desktop window (`gui` feature)
+    Gui,
+    /// Device stack of the Linux ARM boards (`embedded-hw` feature)
+    Embedded,
+    /// Console analyzer, part of every build
+    Headless,
+    /// Full-screen terminal dashboard (`tui` feature), for SSH sessions
+    Tui,
+}
+
+impl Mode {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "gui" => Some(Mode::Gui),
+            "embedded" => Some(Mode::Embedded),
+            "headless" => Some(Mode::Headless),
+            "tui" => Some(Mode::Tui),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Gui => "GUI",
+            Mode::Embedded => "embedded",
+            Mode::Headless => "headless",
+            Mode::Tui => "TUI",
+        }
+    }
+
+    /// Whether this build contains the mode.
+    pub fn is_available(self) -> bool {
+        match self {
+            Mode::Gui => cfg!(feature = "gui"),
+            Mode::Embedded => cfg!(all(
+                feature = "embedded-hw",
+                any(target_arch = "aarch64", target_arch = "arm"),
+                target_os = "linux"
+            )),
+            Mode::Headless => true,
+            Mode::Tui => cfg!(feature = "tui"),
+        }
+    }
+
+    /// Mode used without `--mode`: the window when a desktop session is running, else
+    /// the device stack on the boards, else the console analyzer.
+    pub fn detect() -> Self {
+        if Mode::Gui.is_available() && has_display() {
+            Mode::Gui
+        } else if Mode::Embedded.is_available() {
+            Mode::Embedded
+        } else {
+            Mode::Headless
+        }
+    }
+}
+
+// Linux needs an X11 or Wayland session to open a window, other systems always have one
+fn has_display() -> bool {
+    !cfg!(target_os = "linux")
+        || std::env::var_os("DISPLAY").is_some()
+        || std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// Value of the `--mode <gui|embedded|headless|tui>` command line flag (`--tui`
+/// is short for `--mode tui`).
+fn cli_mode() -> Option<String> {
+    if rust_bpm_analyzer::config::cli_option("--tui").is_some() {
+        return Some("tui".to_string());
+    }
+    rust_bpm_analyzer::config::cli_option("--mode").flatten()
+}
+
+fn selected_mode() -> Result<Mode, Box<dyn Error>> {
+    let Some(name) = cli_mode() else {
+        return Ok(Mode::detect());
+    };
+    let mode = Mode::parse(&name).ok_or_else(|| {
+        format!(
+            "Unknown mode '{}', expected gui, embedded, headless or tui",
+            name
+        )
+    })?;
+    if !mode.is_available() {
+        return Err(format!("Mode '{}' is not available in this build", name).into());
+    }
+    Ok(mode)
+}
+
+pub fn run() -> Result<(), Box<dyn Error>> {
+    // Debug helper: print the analyzer state of a headless device and exit
+    #[cfg(feature = "gui")]
+    if let Some(target) = crate::diagnostics::cli_dump_state() {
+        return crate::diagnostics::print_remote_state(&target);
+    }
+    // Offline analysis of audio files, printed to stdout
+    let files = rust_bpm_analyzer::core_bpm::offline::cli_files();
+    if !files.is_empty() {
+        let config = rust_bpm_analyzer::config::AppConfig::load().analyzer_config();
+        return rust_bpm_analyzer::core_bpm::offline::run_cli(&files, config);
+    }
+
+    // Tempo of an onset envelope computed elsewhere, in the console
+    if let Some(source) = rust_bpm_analyzer::core_bpm::envelope_input::cli_source()? {
+        rust_bpm_analyzer::logging::init(&rust_bpm_analyzer::config::AppConfig::load().log, true);
+        tracing::info!("Starting envelope input Mode...");
+        return crate::headless::run_envelope(source);
+    }
+
+    let mode = selected_mode()?;
+    // The terminal UI draws over the whole screen, its log lines stay in memory
+    rust_bpm_analyzer::logging::init(
+        &rust_bpm_analyzer::config::AppConfig::load().log,
+        mode != Mode::Tui,
+    );
+    tracing::info!("Starting {} Mode...", mode.name());
+    match mode {
+        #[cfg(feature = "gui")]
+        Mode::Gui => crate::gui::run(),
+        #[cfg(all(
+            feature = "embedded-hw",
+            any(target_arch = "aarch64", target_arch = "arm"),
+            target_os = "linux"
+        ))]
+        Mode::Embedded => tokio::runtime::Runtime::new()?.block_on(crate::embedded::run()),
+        #[cfg(feature = "tui")]
+        Mode::Tui => crate::tui::run(),
+        // Modes missing from the build were rejected above
+        _ => crate::headless::run(),
+    }
+}
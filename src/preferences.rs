@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::error;
+
+use rust_bpm_analyzer::core_bpm::analyzer::{BpmAnalyzerConfig, OctavePreference};
+use rust_bpm_analyzer::midi_mapping::{MidiAction, MidiMap, MidiMapping};
+
+const APP_NAME: &str = "rust-bpm-analyzer";
+const PREFERENCES_NAME: &str = "preferences";
+
+/// GUI settings remembered between runs, stored as TOML in the user config directory
+/// (e.g. `~/.config/rust-bpm-analyzer/preferences.toml` on Linux).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// Last selected input device
+    pub device: Option<String>,
+    pub link_enabled: bool,
+    /// Name of an iced built-in theme
+    pub theme: String,
+    /// Accent color replacing the theme primary color (`#RRGGBB`)
+    pub accent: Option<String>,
+    /// Background flash on beats and drops in the main view
+    pub background_flash: bool,
+    pub octave: OctavePreference,
+    /// Analyzer overrides, only set once changed in the settings screen
+    pub analysis: Option<AnalysisPreferences>,
+    /// MIDI notes or CCs mapped to actions (TAP, start/stop...)
+    pub midi: MidiMap,
+    /// TAP mapping of older versions, moved into `midi` on load
+    #[serde(skip_serializing)]
+    pub midi_tap: Option<MidiMapping>,
+    /// Window options of the performance display
+    pub performance_fullscreen: bool,
+    pub always_on_top: bool,
+    /// Follow the Link session tempo while unsure, only set once changed
+    /// (`[link] follow` of the config file otherwise)
+    pub link_follow: Option<bool>,
+    /// Beats of a Link phase, only set once changed (`[link] quantum` otherwise)
+    pub link_quantum: Option<u32>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            device: None,
+            link_enabled: true,
+            theme: "Dracula".to_string(),
+            accent: None,
+            background_flash: false,
+            octave: OctavePreference::Normal,
+            analysis: None,
+            midi: MidiMap::default(),
+            midi_tap: None,
+            performance_fullscreen: true,
+            always_on_top: false,
+            link_follow: None,
+            link_quantum: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisPreferences {
+    pub min_bpm: f32,
+    pub max_bpm: f32,
+    pub fine_confidence: f32,
+    pub coarse_confidence: f32,
+}
+
+impl AnalysisPreferences {
+    pub fn from_config(config: &BpmAnalyzerConfig) -> Self {
+        Self {
+            min_bpm: config.min_bpm,
+            max_bpm: config.max_bpm,
+            fine_confidence: config.thresholds.fine_confidence,
+            coarse_confidence: config.thresholds.coarse_confidence,
+        }
+    }
+
+    pub fn apply(&self, config: &mut BpmAnalyzerConfig) {
+        config.min_bpm = self.min_bpm;
+        config.max_bpm = self.max_bpm;
+        config.thresholds.fine_confidence = self.fine_confidence;
+        config.thresholds.coarse_confidence = self.coarse_confidence;
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let mut config = BpmAnalyzerConfig::default();
+        self.apply(&mut config);
+        config.validate()
+    }
+}
+
+impl Preferences {
+    /// Loads the saved preferences, or the defaults on first run or error.
+    pub fn load() -> Self {
+        let loaded: Result<Self, _> = confy::load(APP_NAME, PREFERENCES_NAME);
+        match loaded {
+            Ok(mut preferences) => {
+                if let Some(tap) = preferences.midi_tap.take() {
+                    preferences.midi.bind(MidiAction::Tap, tap);
+                }
+                preferences
+            }
+            Err(e) => {
+                error!("Invalid preferences: {}. Using defaults.", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = confy::store(APP_NAME, PREFERENCES_NAME, self) {
+            error!("Failed to save preferences: {}", e);
+        }
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        confy::get_configuration_file_path(APP_NAME, PREFERENCES_NAME).ok()
+    }
+}
@@ -0,0 +1,206 @@
+use iced::futures::Stream;
+use iced::futures::channel::mpsc::unbounded;
+use std::error::Error;
+use std::thread;
+use std::time::Duration;
+use tray_icon::menu::{CheckMenuItem, Menu, MenuEvent, MenuId, MenuItem, PredefinedMenuItem};
+use tray_icon::{Icon, MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+
+const SHOW_ID: &str = "show";
+const DETECTION_ID: &str = "detection";
+const LINK_ID: &str = "link";
+const QUIT_ID: &str = "quit";
+
+// The GUI stops drawing (and ticking) while hidden, the tray keeps its BPM fresh with this
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy)]
+pub enum TrayAction {
+    Show,
+    ToggleDetection,
+    ToggleLink,
+    Quit,
+    Refresh,
+}
+
+/// What the tray displays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrayState {
+    pub bpm: Option<f32>,
+    pub detection: bool,
+    pub link: bool,
+}
+
+/// Tray icon with a BPM tooltip and quick toggles (feature `tray`).
+pub struct Tray {
+    state: TrayState,
+    // tray-icon needs a gtk event loop on Linux, which iced does not run: the icon
+    // lives on its own gtk thread and receives the states through a channel
+    #[cfg(target_os = "linux")]
+    updates: std::sync::mpsc::Sender<TrayState>,
+    #[cfg(not(target_os = "linux"))]
+    widgets: TrayWidgets,
+}
+
+impl Tray {
+    /// Must be called on the GUI thread once its event loop runs (first frame).
+    #[cfg(not(target_os = "linux"))]
+    pub fn new(state: TrayState) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            state,
+            widgets: TrayWidgets::build(&state)?,
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn new(state: TrayState) -> Result<Self, Box<dyn Error>> {
+        use std::sync::mpsc::{self, RecvTimeoutError};
+
+        let (updates, rx_updates) = mpsc::channel::<TrayState>();
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<(), String>>();
+        thread::spawn(move || {
+            if let Err(e) = gtk::init() {
+                let _ = ready_tx.send(Err(e.to_string()));
+                return;
+            }
+            let widgets = match TrayWidgets::build(&state) {
+                Ok(widgets) => widgets,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e.to_string()));
+                    return;
+                }
+            };
+            let _ = ready_tx.send(Ok(()));
+
+            loop {
+                while gtk::events_pending() {
+                    gtk::main_iteration();
+                }
+                match rx_updates.recv_timeout(Duration::from_millis(20)) {
+                    Ok(state) => widgets.apply(&state),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+
+        ready_rx.recv()??;
+        Ok(Self { state, updates })
+    }
+
+    pub fn update(&mut self, state: TrayState) {
+        if state == self.state {
+            return;
+        }
+        self.state = state;
+        #[cfg(target_os = "linux")]
+        let _ = self.updates.send(state);
+        #[cfg(not(target_os = "linux"))]
+        self.widgets.apply(&state);
+    }
+}
+
+struct TrayWidgets {
+    icon: TrayIcon,
+    detection: CheckMenuItem,
+    link: CheckMenuItem,
+}
+
+impl TrayWidgets {
+    fn build(state: &TrayState) -> Result<Self, Box<dyn Error>> {
+        let detection =
+            CheckMenuItem::with_id(DETECTION_ID, "Detection", true, state.detection, None);
+        let link = CheckMenuItem::with_id(LINK_ID, "Ableton Link", true, state.link, None);
+        let menu = Menu::new();
+        menu.append_items(&[
+            &MenuItem::with_id(SHOW_ID, "Show window", true, None),
+            &PredefinedMenuItem::separator(),
+            &detection,
+            &link,
+            &PredefinedMenuItem::separator(),
+            &MenuItem::with_id(QUIT_ID, "Quit", true, None),
+        ])?;
+
+        let icon = TrayIconBuilder::new()
+            .with_menu(Box::new(menu))
+            .with_icon(load_icon()?)
+            .with_tooltip(tooltip(state))
+            .build()?;
+
+        let widgets = Self {
+            icon,
+            detection,
+            link,
+        };
+        widgets.apply(state);
+        Ok(widgets)
+    }
+
+    fn apply(&self, state: &TrayState) {
+        let _ = self.icon.set_tooltip(Some(tooltip(state)));
+        // Shown next to the icon in the macOS menu bar
+        self.icon.set_title(Some(bpm_text(state)));
+        self.detection.set_checked(state.detection);
+        self.link.set_checked(state.link);
+    }
+}
+
+fn bpm_text(state: &TrayState) -> String {
+    match state.bpm {
+        Some(bpm) if state.detection => format!("{:.1}", bpm),
+        _ => "---.-".to_string(),
+    }
+}
+
+fn tooltip(state: &TrayState) -> String {
+    format!("BPM Analyzer: {} BPM", bpm_text(state))
+}
+
+fn load_icon() -> Result<Icon, Box<dyn Error>> {
+    let image = image::load_from_memory(include_bytes!("../assets/icon.png"))?.into_rgba8();
+    let (width, height) = image.dimensions();
+    Ok(Icon::from_rgba(image.into_raw(), width, height)?)
+}
+
+fn action_for(id: &MenuId) -> Option<TrayAction> {
+    match id.as_ref() {
+        SHOW_ID => Some(TrayAction::Show),
+        DETECTION_ID => Some(TrayAction::ToggleDetection),
+        LINK_ID => Some(TrayAction::ToggleLink),
+        QUIT_ID => Some(TrayAction::Quit),
+        _ => None,
+    }
+}
+
+/// Menu clicks, left clicks on the icon (show the window) and periodic refreshes.
+/// Used as an iced subscription so that actions arrive even while the window is hidden.
+pub fn actions() -> impl Stream<Item = TrayAction> {
+    let (sender, receiver) = unbounded();
+
+    let menu_sender = sender.clone();
+    MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+        if let Some(action) = action_for(&event.id) {
+            let _ = menu_sender.unbounded_send(action);
+        }
+    }));
+
+    let icon_sender = sender.clone();
+    TrayIconEvent::set_event_handler(Some(move |event: TrayIconEvent| {
+        if let TrayIconEvent::Click {
+            button: MouseButton::Left,
+            button_state: MouseButtonState::Up,
+            ..
+        } = event
+        {
+            let _ = icon_sender.unbounded_send(TrayAction::Show);
+        }
+    }));
+
+    thread::spawn(move || {
+        while sender.unbounded_send(TrayAction::Refresh).is_ok() {
+            thread::sleep(REFRESH_INTERVAL);
+        }
+    });
+
+    receiver
+}
@@ -0,0 +1,400 @@
+use crate::headless::ConsoleAnalysis;
+use crate::platform::TARGET_SAMPLE_RATE;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Alignment, Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use rust_bpm_analyzer::config::AppConfig;
+use rust_bpm_analyzer::core_bpm::analyzer::AnalysisResult;
+use rust_bpm_analyzer::core_bpm::audio_channel::{AudioReceiver, TryRecvError};
+use rust_bpm_analyzer::core_bpm::meter::CLIP_LEVEL;
+use rust_bpm_analyzer::core_bpm::simulated;
+use rust_bpm_analyzer::core_bpm::{AudioCapture, AudioMessage, audio_channel};
+use rust_bpm_analyzer::logging;
+use rust_bpm_analyzer::metrics;
+use rust_bpm_analyzer::network_sync::follow::LinkRole;
+use std::error::Error;
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+// Redraw period, also the longest wait for a key press
+const FRAME: Duration = Duration::from_millis(50);
+// The BPM panel flashes red for this long after a drop
+const DROP_FLASH: Duration = Duration::from_millis(1500);
+// Without a detection for this long the BPM is greyed out
+const STALE: Duration = Duration::from_secs(5);
+// Floor of the input level gauge
+const LEVEL_FLOOR_DB: f32 = -60.0;
+
+// 3x5 glyphs of the big BPM digits, '#' cells are filled
+const DIGITS: [[&str; 5]; 10] = [
+    ["###", "# #", "# #", "# #", "###"],
+    [" # ", "## ", " # ", " # ", "###"],
+    ["###", "  #", "###", "#  ", "###"],
+    ["###", "  #", "###", "  #", "###"],
+    ["# #", "# #", "###", "  #", "  #"],
+    ["###", "#  ", "###", "  #", "###"],
+    ["###", "#  ", "###", "# #", "###"],
+    ["###", "  #", "  #", "  #", "  #"],
+    ["###", "# #", "###", "# #", "###"],
+    ["###", "# #", "###", "  #", "###"],
+];
+const DOT: [&str; 5] = [" ", " ", " ", " ", "#"];
+const DASH: [&str; 5] = ["   ", "   ", "###", "   ", "   "];
+
+/// Full-screen terminal dashboard (`--tui`): big BPM, confidence and level
+/// gauges, drop flashes, the Link session and the audio inputs. Runs the same
+/// analysis as the console analyzer, over SSH or in any terminal.
+/// Quits with `q`, `Esc` or Ctrl+C.
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let config = AppConfig::load();
+    if config.metrics.enabled {
+        metrics::serve(config.metrics.port);
+    }
+
+    // At most 2 s of audio queued, older packets are dropped beyond that
+    let (audio_sender, audio_receiver) = audio_channel(TARGET_SAMPLE_RATE as usize * 2);
+    // Default input, or generated audio with `--sim`
+    let device = simulated::cli_device();
+    let mut audio_capture = AudioCapture::new(
+        audio_sender,
+        device.clone(),
+        TARGET_SAMPLE_RATE,
+        None,
+        Some(Duration::from_millis(500)),
+        config.audio_warmup(),
+    )?;
+    let mut console = ConsoleAnalysis::new(&config)?;
+    console.set_recycler(audio_receiver.recycler());
+    let mut dashboard = Dashboard::new(
+        AudioCapture::list_devices().unwrap_or_default(),
+        device.or_else(AudioCapture::default_device_name),
+    );
+
+    // Restores the terminal on panics too
+    let mut terminal = ratatui::init();
+    let result = event_loop(
+        &mut terminal,
+        &mut dashboard,
+        &mut console,
+        &mut audio_capture,
+        &audio_receiver,
+    );
+    ratatui::restore();
+    console.finish();
+    result
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    dashboard: &mut Dashboard,
+    console: &mut ConsoleAnalysis,
+    audio_capture: &mut AudioCapture,
+    audio_receiver: &AudioReceiver,
+) -> Result<(), Box<dyn Error>> {
+    loop {
+        loop {
+            match audio_receiver.try_recv() {
+                Ok(msg) => dashboard.handle(msg, console),
+                Err(TryRecvError::Empty) => break,
+                // The capture ended
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+        dashboard.link = (console.link_peers(), console.link_tempo());
+        terminal.draw(|frame| dashboard.render(frame))?;
+
+        if !event::poll(FRAME)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            // Raw mode delivers Ctrl+C as a key press instead of a signal
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Up => dashboard.inputs.select_previous(),
+            KeyCode::Down => dashboard.inputs.select_next(),
+            KeyCode::Enter => {
+                let Some(name) = dashboard.selected_input() else {
+                    continue;
+                };
+                info!("Switching audio input to {}", name);
+                if let Err(e) = audio_capture.set_device(Some(name)) {
+                    error!("Failed to switch audio input: {}", e);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// What the dashboard shows, updated from the capture messages.
+struct Dashboard {
+    detection: Option<(AnalysisResult, LinkRole, Instant)>,
+    drop_at: Option<Instant>,
+    peak: f32,
+    rms: f32,
+    /// Peers and tempo of the Ableton Link session
+    link: (usize, f64),
+    devices: Vec<String>,
+    /// Input actually captured
+    input: Option<String>,
+    inputs: ListState,
+}
+
+impl Dashboard {
+    fn new(devices: Vec<String>, input: Option<String>) -> Self {
+        let selected = input
+            .as_ref()
+            .and_then(|input| devices.iter().position(|name| name == input))
+            .unwrap_or(0);
+        Self {
+            detection: None,
+            drop_at: None,
+            peak: 0.0,
+            rms: 0.0,
+            link: (0, 0.0),
+            devices,
+            input,
+            inputs: ListState::default().with_selected(Some(selected)),
+        }
+    }
+
+    fn handle(&mut self, msg: AudioMessage, console: &mut ConsoleAnalysis) {
+        match &msg {
+            AudioMessage::Level { peak, rms } => {
+                self.peak = *peak;
+                self.rms = *rms;
+            }
+            AudioMessage::DeviceChanged(name) => self.input = Some(name.clone()),
+            _ => {}
+        }
+        if let Some((result, role)) = console.handle(msg) {
+            if result.is_drop {
+                self.drop_at = Some(Instant::now());
+            }
+            self.detection = Some((result, role, Instant::now()));
+        }
+    }
+
+    fn selected_input(&self) -> Option<String> {
+        self.inputs
+            .selected()
+            .and_then(|index| self.devices.get(index))
+            .cloned()
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let [top, confidence, coarse, level, bottom, help] = Layout::vertical([
+            Constraint::Length(9),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [bpm, info] =
+            Layout::horizontal([Constraint::Min(40), Constraint::Length(34)]).areas(top);
+        let [inputs, log] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(bottom);
+
+        self.render_bpm(frame, bpm);
+        self.render_info(frame, info);
+        let result = self.detection.map(|(result, _, _)| result);
+        let confidence_of = |value: f32| {
+            gauge(
+                value,
+                format!("{:.0} %", value * 100.0),
+                confidence_color(value),
+            )
+        };
+        frame.render_widget(
+            confidence_of(result.map_or(0.0, |r| r.confidence))
+                .block(Block::bordered().title(" Confidence ")),
+            confidence,
+        );
+        frame.render_widget(
+            confidence_of(result.map_or(0.0, |r| r.coarse_confidence))
+                .block(Block::bordered().title(" Coarse confidence ")),
+            coarse,
+        );
+        self.render_level(frame, level);
+        self.render_inputs(frame, inputs);
+        render_log(frame, log);
+        frame.render_widget(
+            Line::from(" q quit   ↑/↓ select an input   Enter switch to it").dim(),
+            help,
+        );
+    }
+
+    fn render_bpm(&self, frame: &mut Frame, area: Rect) {
+        let flashing = self.drop_at.is_some_and(|at| at.elapsed() < DROP_FLASH);
+        let stale = self
+            .detection
+            .is_none_or(|(_, _, at)| at.elapsed() >= STALE);
+        let (title, style) = if flashing {
+            (" DROP ", Style::new().fg(Color::Black).bg(Color::Red))
+        } else if stale {
+            (" BPM ", Style::new().fg(Color::DarkGray))
+        } else {
+            (" BPM ", Style::new().fg(Color::Cyan))
+        };
+        let text = self.detection.map_or("---".to_string(), |(result, _, _)| {
+            format!("{:.1}", result.bpm)
+        });
+        let mut lines = vec![Line::default()];
+        lines.extend(big_text(&text));
+        frame.render_widget(
+            Paragraph::new(lines)
+                .alignment(Alignment::Center)
+                .style(style)
+                .block(Block::bordered().title(title.bold()).border_style(style)),
+            area,
+        );
+    }
+
+    fn render_info(&self, frame: &mut Frame, area: Rect) {
+        let result = self.detection.map(|(result, _, _)| result);
+        let role = match self.detection {
+            Some((_, LinkRole::Following, _)) => "following",
+            _ => "leading",
+        };
+        let (peers, tempo) = self.link;
+        let link = if peers == 0 {
+            "no peers".to_string()
+        } else {
+            format!("{} peer(s), {:.1} BPM, {}", peers, tempo, role)
+        };
+        let lines = vec![
+            info_line(
+                "Key",
+                result.and_then(|r| r.key).map_or("-".to_string(), |key| {
+                    format!("{} ({})", key.camelot(), key)
+                }),
+            ),
+            info_line(
+                "Swing",
+                result
+                    .and_then(|r| r.swing)
+                    .map_or("-".to_string(), |swing| format!("{:.0} %", swing)),
+            ),
+            info_line("Link", link),
+            info_line(
+                "Input",
+                self.input.clone().unwrap_or_else(|| "-".to_string()),
+            ),
+        ];
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Analysis ")),
+            area,
+        );
+    }
+
+    fn render_level(&self, frame: &mut Frame, area: Rect) {
+        let db = 20.0 * self.peak.max(1e-6).log10();
+        let color = if self.peak >= CLIP_LEVEL {
+            Color::Red
+        } else {
+            Color::Green
+        };
+        let label = format!(
+            "peak {:.1} dBFS, rms {:.1} dBFS",
+            db,
+            20.0 * self.rms.max(1e-6).log10()
+        );
+        frame.render_widget(
+            gauge(1.0 - db / LEVEL_FLOOR_DB, label, color)
+                .block(Block::bordered().title(" Input level ")),
+            area,
+        );
+    }
+
+    fn render_inputs(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .devices
+            .iter()
+            .map(|name| {
+                let marker = if self.input.as_ref() == Some(name) {
+                    "● "
+                } else {
+                    "  "
+                };
+                ListItem::new(format!("{}{}", marker, name))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(" Audio inputs "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.inputs);
+    }
+}
+
+fn render_log(frame: &mut Frame, area: Rect) {
+    let lines = logging::recent_lines();
+    let start = lines
+        .len()
+        .saturating_sub(area.height.saturating_sub(2) as usize);
+    let text: Vec<Line> = lines[start..]
+        .iter()
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(text).block(Block::bordered().title(" Log ")),
+        area,
+    );
+}
+
+fn gauge(ratio: f32, label: String, color: Color) -> Gauge<'static> {
+    Gauge::default()
+        .gauge_style(Style::new().fg(color))
+        .ratio(ratio.clamp(0.0, 1.0) as f64)
+        .label(label)
+}
+
+fn confidence_color(confidence: f32) -> Color {
+    if confidence >= 0.6 {
+        Color::Green
+    } else if confidence >= 0.3 {
+        Color::Yellow
+    } else {
+        Color::Red
+    }
+}
+
+fn info_line(name: &str, value: String) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(
+            format!("{:<7}", name),
+            Style::new().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(value),
+    ])
+}
+
+// Rows of `text` in the big glyphs, each cell two characters wide
+fn big_text(text: &str) -> Vec<Line<'static>> {
+    let mut rows = vec![String::new(); 5];
+    for c in text.chars() {
+        let glyph = match c.to_digit(10) {
+            Some(digit) => DIGITS[digit as usize],
+            None if c == '.' => DOT,
+            None => DASH,
+        };
+        for (row, pattern) in rows.iter_mut().zip(glyph) {
+            for cell in pattern.chars() {
+                row.push_str(if cell == '#' { "██" } else { "  " });
+            }
+            row.push(' ');
+        }
+    }
+    rows.into_iter().map(Line::from).collect()
+}
@@ -0,0 +1,470 @@
+//! Convergence of `BpmAnalyzer` on synthetic signals at known tempos.
+//! Run with `cargo test`.
+
+use rust_bpm_analyzer::core_bpm::analyzer::{
+    AnalysisResult, BpmAnalyzer, BpmAnalyzerConfig, OctavePreference, TempoEstimator,
+};
+use rust_bpm_analyzer::core_bpm::decimator::Downsampler;
+use rust_bpm_analyzer::core_bpm::preset::AnalyzerPreset;
+use rust_bpm_analyzer::core_bpm::{software_agc, testsig};
+use std::time::Duration;
+
+const SAMPLE_RATE: u32 = 44100;
+// Tolerance on the reported BPM
+const TOLERANCE: f32 = 1.0;
+// Seconds of signal fed after the window is full
+const SETTLE_S: f32 = 4.0;
+
+fn analyzer(octave: OctavePreference) -> BpmAnalyzer {
+    let mut analyzer = BpmAnalyzer::new(SAMPLE_RATE, None).expect("analyzer");
+    analyzer.set_octave_preference(octave);
+    analyzer
+}
+
+fn signal_len() -> usize {
+    let window = BpmAnalyzerConfig::default().window_duration.as_secs_f32();
+    (SAMPLE_RATE as f32 * (window + SETTLE_S)) as usize
+}
+
+/// Feeds `signal` in half-second hops, like the capture loop, and returns the last result.
+fn run(analyzer: &mut BpmAnalyzer, signal: &[f32]) -> Option<AnalysisResult> {
+    let hop = (SAMPLE_RATE / 2) as usize;
+    let mut last = None;
+    for chunk in signal.chunks(hop) {
+        if let Some(result) = analyzer.process(chunk).expect("process") {
+            last = Some(result);
+        }
+    }
+    last
+}
+
+/// BPM picked by the octave correction on the last coarse search.
+fn coarse_bpm(analyzer: &BpmAnalyzer) -> f32 {
+    let debug = analyzer.debug();
+    let lag = debug.chosen_lag.expect("no coarse search");
+    debug.coarse_rate * 60.0 / lag as f32
+}
+
+fn assert_bpm(signal: &[f32], expected: f32) {
+    let mut analyzer = analyzer(OctavePreference::Normal);
+    let result = run(&mut analyzer, signal)
+        .unwrap_or_else(|| panic!("no tempo detected, expected {:.1}", expected));
+    assert!(
+        (result.bpm - expected).abs() <= TOLERANCE,
+        "expected {:.1} BPM, detected {:.1}",
+        expected,
+        result.bpm
+    );
+}
+
+#[test]
+fn click_tracks_converge() {
+    for bpm in [100.0, 124.0, 128.0, 140.0, 174.0, 200.0, 290.0] {
+        let signal = testsig::click_track(SAMPLE_RATE, bpm, signal_len());
+        assert_bpm(&signal, bpm);
+    }
+}
+
+#[test]
+fn fractional_tempo_converges() {
+    let signal = testsig::click_track(SAMPLE_RATE, 127.3, signal_len());
+    assert_bpm(&signal, 127.3);
+}
+
+#[test]
+fn swung_kick_patterns_converge() {
+    for (bpm, swing) in [(120.0, 0.0), (124.0, 0.08), (128.0, 0.17)] {
+        let signal = testsig::kick_pattern(SAMPLE_RATE, bpm, swing, signal_len());
+        assert_bpm(&signal, bpm);
+    }
+}
+
+#[test]
+fn noisy_click_track_converges() {
+    let mut signal = testsig::click_track(SAMPLE_RATE, 130.0, signal_len());
+    testsig::add_noise(&mut signal, 6.0, 0x2801);
+    assert_bpm(&signal, 130.0);
+}
+
+#[test]
+fn polyphase_downsampler_converges() {
+    let config = BpmAnalyzerConfig {
+        fine_downsampler: Downsampler::Polyphase,
+        coarse_downsampler: Downsampler::Polyphase,
+        ..Default::default()
+    };
+    let mut analyzer = BpmAnalyzer::new(SAMPLE_RATE, Some(config)).expect("analyzer");
+    for (bpm, swing) in [(128.0, 0.0), (174.0, 0.1)] {
+        let mut signal = testsig::kick_pattern(SAMPLE_RATE, bpm, swing, signal_len());
+        testsig::add_noise(&mut signal, 6.0, 0x2802);
+        let result = run(&mut analyzer, &signal).expect("no tempo detected");
+        assert!(
+            (result.bpm - bpm).abs() <= TOLERANCE,
+            "expected {:.1} BPM, detected {:.1}",
+            bpm,
+            result.bpm
+        );
+    }
+}
+
+#[test]
+fn software_agc_follows_a_level_drop() {
+    let config = BpmAnalyzerConfig {
+        software_agc: software_agc::SoftwareAgcConfig {
+            enabled: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut analyzer = BpmAnalyzer::new(SAMPLE_RATE, Some(config)).expect("analyzer");
+    let mut signal = testsig::kick_pattern(SAMPLE_RATE, 126.0, 0.0, signal_len());
+    testsig::add_noise(&mut signal, 12.0, 0x2828);
+    // Loud intro, then the track 12 dB lower
+    let half = signal.len() / 2;
+    for x in &mut signal[..half] {
+        *x *= 4.0;
+    }
+    let result = run(&mut analyzer, &signal).expect("no tempo detected");
+    assert!(
+        (result.bpm - 126.0).abs() <= TOLERANCE,
+        "detected {:.1}",
+        result.bpm
+    );
+}
+
+#[test]
+fn pipelined_analysis_converges() {
+    let config = BpmAnalyzerConfig {
+        pipelined: true,
+        ..Default::default()
+    };
+    let mut analyzer = BpmAnalyzer::new(SAMPLE_RATE, Some(config)).expect("analyzer");
+    let signal = testsig::kick_pattern(SAMPLE_RATE, 128.0, 0.0, signal_len());
+    let hop = (SAMPLE_RATE / 2) as usize;
+    let mut last = None;
+    for chunk in signal.chunks(hop) {
+        if let Some(result) = analyzer.process(chunk).expect("process") {
+            last = Some(result);
+        }
+        // Paced like the capture loop so each window gets through both threads
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let result = last.expect("no tempo detected");
+    assert!(
+        (result.bpm - 128.0).abs() <= TOLERANCE,
+        "detected {:.1}",
+        result.bpm
+    );
+}
+
+// Short hits far apart in noise, the case the resonators are meant for
+#[test]
+fn comb_filter_estimator_converges() {
+    let config = BpmAnalyzerConfig {
+        estimator: TempoEstimator::CombFilter,
+        ..Default::default()
+    };
+    let mut analyzer = BpmAnalyzer::new(SAMPLE_RATE, Some(config)).expect("analyzer");
+    for bpm in [100.0, 128.0, 174.0] {
+        let mut signal = testsig::click_track(SAMPLE_RATE, bpm, signal_len());
+        testsig::add_noise(&mut signal, 6.0, 0x2856);
+        let result = run(&mut analyzer, &signal).expect("no tempo detected");
+        assert!(
+            (result.bpm - bpm).abs() <= TOLERANCE,
+            "expected {:.1} BPM, detected {:.1}",
+            bpm,
+            result.bpm
+        );
+    }
+    let debug = analyzer.debug();
+    assert_eq!(debug.estimator, TempoEstimator::CombFilter);
+    assert!(!debug.coarse_curve.is_empty());
+}
+
+// The correlation peaks on the stabs, at 4:3 of the kick
+#[test]
+fn ioi_fusion_settles_dotted_stabs() {
+    let config = BpmAnalyzerConfig {
+        estimator: TempoEstimator::IoiFusion,
+        ..Default::default()
+    };
+    for bpm in [128.0, 140.0, 150.0] {
+        let mut analyzer = BpmAnalyzer::new(SAMPLE_RATE, Some(config)).expect("analyzer");
+        let signal = testsig::dotted_stabs(SAMPLE_RATE, bpm, signal_len());
+        let result = run(&mut analyzer, &signal).expect("no tempo detected");
+        assert!(
+            (result.bpm - bpm).abs() <= TOLERANCE,
+            "expected {:.1} BPM, detected {:.1}",
+            bpm,
+            result.bpm
+        );
+        assert!(!analyzer.debug().tempo_hypotheses.is_empty());
+    }
+}
+
+#[test]
+fn silence_gives_no_tempo() {
+    let signal = vec![0.0; signal_len()];
+    assert!(run(&mut analyzer(OctavePreference::Normal), &signal).is_none());
+}
+
+// The off-beat hits make 2x a plausible octave, weaker than the kick
+#[test]
+fn normal_octave_keeps_the_kick_tempo() {
+    let signal = testsig::kick_pattern(SAMPLE_RATE, 120.0, 0.0, signal_len());
+    let mut analyzer = analyzer(OctavePreference::Normal);
+    run(&mut analyzer, &signal);
+    let bpm = coarse_bpm(&analyzer);
+    assert!(
+        (bpm - 120.0).abs() < 5.0,
+        "expected ~120 BPM, got {:.1}",
+        bpm
+    );
+}
+
+#[test]
+fn double_octave_picks_the_faster_tempo() {
+    let signal = testsig::kick_pattern(SAMPLE_RATE, 120.0, 0.0, signal_len());
+    let mut analyzer = analyzer(OctavePreference::Double);
+    run(&mut analyzer, &signal);
+    let bpm = coarse_bpm(&analyzer);
+    assert!(
+        (bpm - 240.0).abs() < 10.0,
+        "expected ~240 BPM, got {:.1}",
+        bpm
+    );
+}
+
+#[test]
+fn half_octave_picks_the_slower_tempo() {
+    let signal = testsig::click_track(SAMPLE_RATE, 240.0, signal_len());
+    let mut analyzer = analyzer(OctavePreference::Half);
+    run(&mut analyzer, &signal);
+    let bpm = coarse_bpm(&analyzer);
+    assert!(
+        (bpm - 120.0).abs() < 5.0,
+        "expected ~120 BPM, got {:.1}",
+        bpm
+    );
+}
+
+#[test]
+fn next_track_tempo_replaces_the_reference() {
+    let mut analyzer = analyzer(OctavePreference::Normal);
+    let first = testsig::kick_pattern(SAMPLE_RATE, 124.0, 0.0, signal_len());
+    let result = run(&mut analyzer, &first).expect("first tempo");
+    assert!((result.bpm - 124.0).abs() <= TOLERANCE);
+
+    let second = testsig::kick_pattern(SAMPLE_RATE, 140.0, 0.0, signal_len());
+    let hop = (SAMPLE_RATE / 2) as usize;
+    let mut changes = Vec::new();
+    let mut last = None;
+    for chunk in second.chunks(hop) {
+        if let Some(result) = analyzer.process(chunk).expect("process") {
+            changes.extend(result.tempo_change);
+            last = Some(result);
+        }
+    }
+    assert_eq!(changes.len(), 1, "{:?}", changes);
+    assert!(
+        (changes[0].from - 124.0).abs() <= TOLERANCE,
+        "{:?}",
+        changes
+    );
+    assert!((changes[0].to - 140.0).abs() <= TOLERANCE, "{:?}", changes);
+    assert!((last.expect("second tempo").bpm - 140.0).abs() <= TOLERANCE);
+}
+
+#[test]
+fn restored_reference_holds_until_a_tempo_change() {
+    let mut analyzer = analyzer(OctavePreference::Normal);
+    analyzer.restore_reference(124.0);
+
+    let signal = testsig::kick_pattern(SAMPLE_RATE, 140.0, 0.0, signal_len());
+    let hop = (SAMPLE_RATE / 2) as usize;
+    let mut results = Vec::new();
+    for chunk in signal.chunks(hop) {
+        if let Some(result) = analyzer.process(chunk).expect("process") {
+            results.push(result);
+        }
+    }
+    let first = results.first().expect("first tempo");
+    assert!((first.bpm - 124.0).abs() <= TOLERANCE, "{:.1}", first.bpm);
+    let changes: Vec<_> = results.iter().filter_map(|r| r.tempo_change).collect();
+    assert_eq!(changes.len(), 1, "{:?}", changes);
+    assert!(
+        (changes[0].from - 124.0).abs() <= TOLERANCE,
+        "{:?}",
+        changes
+    );
+    assert!((results.last().unwrap().bpm - 140.0).abs() <= TOLERANCE);
+}
+
+#[test]
+fn snapshot_restores_the_tempo_in_a_new_analyzer() {
+    let mut before = analyzer(OctavePreference::Half);
+    let signal = testsig::kick_pattern(SAMPLE_RATE, 124.0, 0.0, signal_len());
+    run(&mut before, &signal).expect("tempo before the snapshot");
+    // Written to disk and read back, as before an update
+    let json = serde_json::to_string(&before.snapshot()).expect("serialize");
+    let state = serde_json::from_str(&json).expect("deserialize");
+
+    let mut after = BpmAnalyzer::new(48000, None).expect("analyzer");
+    after.restore(&state);
+    let restored = after.snapshot();
+    assert_eq!(restored.octave, OctavePreference::Half);
+    assert_eq!(restored.reference_bpm, before.snapshot().reference_bpm);
+    assert_eq!(restored.history.len(), before.snapshot().history.len());
+
+    // The restored tempo holds until the tracker moves to the next one
+    let signal = testsig::kick_pattern(48000, 140.0, 0.0, 48000 * 6);
+    let mut changes = Vec::new();
+    for chunk in signal.chunks(24000) {
+        if let Some(result) = after.process(chunk).expect("process") {
+            changes.extend(result.tempo_change);
+        }
+    }
+    assert_eq!(changes.len(), 1, "{:?}", changes);
+    assert!(
+        (changes[0].from - 124.0).abs() <= TOLERANCE,
+        "{:?}",
+        changes
+    );
+}
+
+#[test]
+fn warm_start_keeps_the_tempo_across_a_rate_change() {
+    let mut analyzer = analyzer(OctavePreference::Normal);
+    // 16 beats, so that the clicks at the new rate carry on in phase
+    let signal = testsig::click_track(SAMPLE_RATE, 128.0, SAMPLE_RATE as usize * 15 / 2);
+    run(&mut analyzer, &signal).expect("tempo before the change");
+
+    analyzer.reset_soft(48000).expect("rate change");
+    // The first half second at the new rate gives the tempo back
+    let hop = testsig::click_track(48000, 128.0, 24000);
+    let result = analyzer
+        .process(&hop)
+        .expect("process")
+        .expect("no tempo after the warm start");
+    assert!((result.bpm - 128.0).abs() <= TOLERANCE, "{:.1}", result.bpm);
+    assert!(result.tempo_change.is_none());
+}
+
+#[test]
+fn sixteen_bit_input_converges() {
+    let mut analyzer = analyzer(OctavePreference::Normal);
+    let signal: Vec<i16> = testsig::click_track(SAMPLE_RATE, 128.0, signal_len())
+        .iter()
+        .map(|&x| (x * i16::MAX as f32) as i16)
+        .collect();
+    let hop = (SAMPLE_RATE / 2) as usize;
+    let mut last = None;
+    for chunk in signal.chunks(hop) {
+        if let Some(result) = analyzer.process_i16(chunk).expect("process") {
+            last = Some(result);
+        }
+    }
+    let result = last.expect("no tempo detected");
+    assert!((result.bpm - 128.0).abs() <= TOLERANCE, "{:.1}", result.bpm);
+}
+
+#[test]
+fn quarter_second_hops_converge() {
+    let config = BpmAnalyzerConfig {
+        hop_duration: Duration::from_millis(250),
+        ..Default::default()
+    };
+    let mut analyzer = BpmAnalyzer::new(SAMPLE_RATE, Some(config)).expect("analyzer");
+    let signal = testsig::kick_pattern(SAMPLE_RATE, 128.0, 0.0, signal_len());
+
+    // Capture packets of 10 ms, the analyzer schedules the hops
+    let mut hops = 0;
+    let mut last = None;
+    for packet in signal.chunks((SAMPLE_RATE / 100) as usize) {
+        analyzer.push_samples(packet);
+        if analyzer.hop_ready() {
+            hops += 1;
+            if let Some(result) = analyzer.process_pending().expect("process") {
+                last = Some(result);
+            }
+        }
+    }
+
+    assert_eq!(hops, signal.len() / (SAMPLE_RATE / 4) as usize);
+    let result = last.expect("no tempo detected");
+    assert!(
+        (result.bpm - 128.0).abs() <= TOLERANCE,
+        "detected {:.1}",
+        result.bpm
+    );
+}
+
+#[test]
+fn hop_below_250_ms_is_rejected() {
+    let config = BpmAnalyzerConfig {
+        hop_duration: Duration::from_millis(100),
+        ..Default::default()
+    };
+    assert!(BpmAnalyzer::new(SAMPLE_RATE, Some(config)).is_err());
+}
+
+#[test]
+fn preset_with_an_empty_bpm_range_is_rejected() {
+    let path = std::env::temp_dir().join(format!("bpm-preset-{}.toml", std::process::id()));
+    for analyzer in [
+        "min_bpm = 0.0",
+        "min_bpm = 180.0\nmax_bpm = 120.0",
+        "window_ms = 200",
+    ] {
+        std::fs::write(&path, format!("name = \"bad\"\n[analyzer]\n{}\n", analyzer))
+            .expect("write preset");
+        assert!(AnalyzerPreset::load(&path).is_err(), "{}", analyzer);
+    }
+    std::fs::write(&path, "name = \"good\"\n[analyzer]\nmin_bpm = 90.0\n").expect("write");
+    let preset = AnalyzerPreset::load(&path).expect("valid preset");
+    assert!(BpmAnalyzer::new(SAMPLE_RATE, Some(preset.analyzer)).is_ok());
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn drop_is_detected_on_an_external_envelope() {
+    let mut analyzer = analyzer(OctavePreference::Normal);
+    let rate = analyzer.fine_rate();
+    // Onset envelope of a 128 BPM beat, a decaying peak on every beat over a
+    // floor: a breakdown, then the full beat comes back for the last 3 s
+    let period = rate * 60.0 / 128.0;
+    let drop_at = (rate * 12.0) as usize;
+    let envelope: Vec<f32> = (0..(rate * 15.0) as usize)
+        .map(|i| {
+            let level = if i < drop_at { 0.55 } else { 1.0 };
+            level * (0.1 + 0.9 * (-(i as f32 % period) / (rate * 0.1)).exp())
+        })
+        .collect();
+    let hop = (rate / 2.0) as usize;
+    let (breakdown, drop) = envelope.split_at(drop_at);
+    for chunk in breakdown.chunks(hop) {
+        let result = analyzer.process_envelope(chunk).expect("process_envelope");
+        assert!(!result.is_some_and(|r| r.is_drop), "drop in the breakdown");
+    }
+    let dropped = drop.chunks(hop).any(|chunk| {
+        analyzer
+            .process_envelope(chunk)
+            .expect("process_envelope")
+            .is_some_and(|r| r.is_drop)
+    });
+    assert!(dropped, "no drop detected on the envelope");
+}
+
+#[test]
+fn self_check_leaves_the_live_analyzer_alone() {
+    let mut analyzer = analyzer(OctavePreference::Normal);
+    let signal = testsig::kick_pattern(SAMPLE_RATE, 124.0, 0.0, signal_len());
+    run(&mut analyzer, &signal).expect("tempo before the check");
+    let before = analyzer.snapshot();
+
+    let detected = analyzer.self_check(128.0)().expect("self-check");
+    assert!((detected - 128.0).abs() <= TOLERANCE, "{:.1}", detected);
+    let after = analyzer.snapshot();
+    assert_eq!(after.reference_bpm, before.reference_bpm);
+    assert_eq!(after.history.len(), before.history.len());
+}
@@ -0,0 +1,84 @@
+//! Signed commands of the device HTTP API. Run with `cargo test`.
+
+use rust_bpm_analyzer::network_sync::api_auth::{self, CommandVerifier, MAX_SKEW_MS};
+
+const KEY: &str = "secret";
+const NOW: u64 = 1_700_000_000_000;
+const BODY: &[u8] = br#"{"open": true}"#;
+
+fn verify(
+    verifier: &mut CommandVerifier,
+    timestamp: u64,
+    signature: &str,
+    path: &str,
+    body: &[u8],
+) -> Result<(), String> {
+    verifier.verify(
+        Some(&timestamp.to_string()),
+        Some(signature),
+        "POST",
+        path,
+        body,
+        NOW,
+    )
+}
+
+#[test]
+fn signature_matches_the_shell_recipe() {
+    // printf '%s\nPOST\n/gate\n%s' "$ts" "$body" | openssl dgst -sha256 -hmac "$KEY"
+    assert_eq!(
+        api_auth::sign(KEY, NOW, "POST", "/gate", BODY),
+        "5a6d40d53a7bb250157493f5926c619905452df336bf0afca60338e52dab7f77"
+    );
+}
+
+#[test]
+fn signed_command_is_accepted_once() {
+    let mut verifier = CommandVerifier::new(KEY.to_string());
+    let signature = api_auth::sign(KEY, NOW, "POST", "/gate", BODY);
+    assert_eq!(
+        verify(&mut verifier, NOW, &signature, "/gate", BODY),
+        Ok(())
+    );
+    // Sniffed and sent again within the window
+    assert!(verify(&mut verifier, NOW, &signature, "/gate", BODY).is_err());
+}
+
+#[test]
+fn stale_timestamp_is_refused() {
+    let mut verifier = CommandVerifier::new(KEY.to_string());
+    let sent = NOW - MAX_SKEW_MS - 1;
+    let signature = api_auth::sign(KEY, sent, "POST", "/gate", BODY);
+    assert!(verify(&mut verifier, sent, &signature, "/gate", BODY).is_err());
+}
+
+#[test]
+fn tampered_command_is_refused() {
+    let mut verifier = CommandVerifier::new(KEY.to_string());
+    let signature = api_auth::sign(KEY, NOW, "POST", "/gate", BODY);
+    // Body, path or key changed
+    assert!(
+        verify(
+            &mut verifier,
+            NOW,
+            &signature,
+            "/gate",
+            br#"{"open": false}"#
+        )
+        .is_err()
+    );
+    assert!(verify(&mut verifier, NOW, &signature, "/shutdown", BODY).is_err());
+    let forged = api_auth::sign("guess", NOW, "POST", "/gate", BODY);
+    assert!(verify(&mut verifier, NOW, &forged, "/gate", BODY).is_err());
+    // Missing headers
+    assert!(
+        verifier
+            .verify(None, Some(&signature), "POST", "/gate", BODY, NOW)
+            .is_err()
+    );
+    assert!(
+        verifier
+            .verify(Some(&NOW.to_string()), None, "POST", "/gate", BODY, NOW)
+            .is_err()
+    );
+}
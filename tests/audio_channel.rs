@@ -0,0 +1,130 @@
+//! Drop-oldest policy and overrun accounting of the capture channel.
+
+use rust_bpm_analyzer::core_bpm::audio_channel::{
+    AudioMessage, RecvTimeoutError, TryRecvError, audio_channel,
+};
+use std::time::Duration;
+
+fn packet(len: usize) -> AudioMessage {
+    AudioMessage::Samples(vec![0.0; len])
+}
+
+fn samples_len(message: AudioMessage) -> usize {
+    match message {
+        AudioMessage::Samples(samples) => samples.len(),
+        other => panic!("expected samples, got {:?}", other),
+    }
+}
+
+#[test]
+fn stalled_consumer_drops_the_oldest_packets() {
+    let (sender, receiver) = audio_channel(1000);
+    for len in [400, 300, 500, 200] {
+        sender.send(packet(len)).unwrap();
+    }
+    // The 500 packet overflows the 1000 frames and pushes out the 400 one
+    match receiver.try_recv().unwrap() {
+        AudioMessage::Overrun { dropped_frames } => assert_eq!(dropped_frames, 400),
+        other => panic!("expected an overrun, got {:?}", other),
+    }
+    assert_eq!(samples_len(receiver.try_recv().unwrap()), 300);
+    assert_eq!(samples_len(receiver.try_recv().unwrap()), 500);
+    assert_eq!(samples_len(receiver.try_recv().unwrap()), 200);
+    assert_eq!(receiver.try_recv().unwrap_err(), TryRecvError::Empty);
+}
+
+#[test]
+fn control_messages_are_never_dropped() {
+    let (sender, receiver) = audio_channel(100);
+    sender.send(AudioMessage::Reset).unwrap();
+    sender.send(packet(80)).unwrap();
+    sender.send(AudioMessage::SampleRateChanged(44100)).unwrap();
+    sender.send(packet(80)).unwrap();
+
+    // The gap stays where the packet was, between the control messages
+    assert!(matches!(receiver.try_recv().unwrap(), AudioMessage::Reset));
+    assert!(matches!(
+        receiver.try_recv().unwrap(),
+        AudioMessage::Overrun { dropped_frames: 80 }
+    ));
+    assert!(matches!(
+        receiver.try_recv().unwrap(),
+        AudioMessage::SampleRateChanged(44100)
+    ));
+    assert_eq!(samples_len(receiver.try_recv().unwrap()), 80);
+}
+
+#[test]
+fn consecutive_drops_make_one_overrun() {
+    let (sender, receiver) = audio_channel(100);
+    for _ in 0..3 {
+        sender.send(packet(80)).unwrap();
+    }
+    assert!(matches!(
+        receiver.try_recv().unwrap(),
+        AudioMessage::Overrun {
+            dropped_frames: 160
+        }
+    ));
+    assert_eq!(samples_len(receiver.try_recv().unwrap()), 80);
+    assert_eq!(receiver.try_recv().unwrap_err(), TryRecvError::Empty);
+}
+
+#[test]
+fn dropped_frames_count_interleaved_channels() {
+    let (sender, receiver) = audio_channel(100);
+    sender.set_channels(2);
+    sender.send(packet(160)).unwrap();
+    sender.send(packet(160)).unwrap();
+    assert!(matches!(
+        receiver.try_recv().unwrap(),
+        AudioMessage::Overrun { dropped_frames: 80 }
+    ));
+}
+
+#[test]
+fn disconnects_once_the_queue_is_drained() {
+    let (sender, receiver) = audio_channel(1000);
+    let second = sender.clone();
+    sender.send(packet(10)).unwrap();
+    drop(sender);
+    assert_eq!(
+        receiver
+            .recv_timeout(Duration::from_millis(10))
+            .map(samples_len),
+        Ok(10)
+    );
+    assert_eq!(
+        receiver
+            .recv_timeout(Duration::from_millis(10))
+            .unwrap_err(),
+        RecvTimeoutError::Timeout
+    );
+    drop(second);
+    assert!(receiver.recv().is_err());
+
+    let (sender, receiver) = audio_channel(1000);
+    drop(receiver);
+    assert!(sender.send(packet(10)).is_err());
+}
+
+#[test]
+fn recycled_buffers_are_handed_back_to_the_sender() {
+    let (sender, receiver) = audio_channel(1000);
+    let mut buffer = sender.buffer();
+    buffer.extend_from_slice(&[0.5; 256]);
+    sender.send(AudioMessage::Samples(buffer)).unwrap();
+
+    let AudioMessage::Samples(packet) = receiver.try_recv().unwrap() else {
+        panic!("expected samples");
+    };
+    let address = packet.as_ptr();
+    receiver.recycle(packet);
+
+    let reused = sender.buffer();
+    assert!(reused.is_empty());
+    assert!(reused.capacity() >= 256);
+    assert_eq!(reused.as_ptr(), address);
+    // Nothing left to reuse: a new buffer
+    assert_eq!(sender.buffer().capacity(), 0);
+}
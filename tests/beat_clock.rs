@@ -0,0 +1,121 @@
+//! Beat grid extrapolated between two detections for the output sinks.
+
+use rust_bpm_analyzer::core_bpm::sections::Section;
+use rust_bpm_analyzer::network_sync::LinkManager;
+use rust_bpm_analyzer::outputs::beat_clock::{BeatClock, BeatEvent};
+use rust_bpm_analyzer::outputs::{OutputSink, SinkRegistry, TempoUpdate};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// 120 BPM
+const PERIOD: Duration = Duration::from_millis(500);
+// Poll period of the run loops (capture packets)
+const POLL: Duration = Duration::from_millis(10);
+
+// Beat positions in the bar sent while polling from `start` for `length`
+fn beats(clock: &mut BeatClock, start: Instant, length: Duration) -> Vec<u8> {
+    let mut beats = Vec::new();
+    let mut now = start;
+    while now < start + length {
+        if let Some(beat) = clock.poll(now) {
+            beats.push(beat.beat_in_bar);
+        }
+        now += POLL;
+    }
+    beats
+}
+
+#[test]
+fn one_beat_per_period() {
+    let start = Instant::now();
+    let mut clock = BeatClock::new();
+    assert!(clock.poll(start).is_none());
+
+    clock.set(120.0, start);
+    assert_eq!(beats(&mut clock, start, PERIOD * 5), vec![0, 1, 2, 3, 0]);
+}
+
+#[test]
+fn moved_anchor_does_not_repeat_the_beat() {
+    let start = Instant::now();
+    let mut clock = BeatClock::new();
+    clock.set(120.0, start);
+    assert_eq!(
+        beats(&mut clock, start, Duration::from_millis(100)),
+        vec![0]
+    );
+
+    // The next detection places the same beat 20 ms later
+    clock.set(120.0, start + Duration::from_millis(20));
+    let later = start + Duration::from_millis(100);
+    assert_eq!(beats(&mut clock, later, PERIOD), vec![1]);
+}
+
+#[test]
+fn drop_restarts_the_bar() {
+    let start = Instant::now();
+    let mut clock = BeatClock::new();
+    clock.set(120.0, start);
+    assert_eq!(beats(&mut clock, start, PERIOD * 2), vec![0, 1]);
+
+    clock.restart_bar();
+    assert_eq!(
+        beats(&mut clock, start + PERIOD * 2, PERIOD * 2),
+        vec![0, 1]
+    );
+}
+
+#[test]
+fn late_poll_skips_the_beat_but_keeps_the_bar() {
+    let start = Instant::now();
+    let mut clock = BeatClock::new();
+    clock.set(120.0, start);
+    assert!(clock.poll(start).is_some());
+
+    // Loop stalled past half of the next beat
+    assert!(clock.poll(start + PERIOD + PERIOD * 3 / 4).is_none());
+    let beat = clock.poll(start + PERIOD * 2).expect("next beat");
+    assert_eq!(beat.beat_in_bar, 2);
+}
+
+// Beat counts of the phrase starts fanned out by the registry
+struct Phrases(Arc<Mutex<Vec<u64>>>);
+
+impl OutputSink for Phrases {
+    fn name(&self) -> &'static str {
+        "Phrases"
+    }
+
+    fn on_result(&mut self, _update: &TempoUpdate) {}
+
+    fn on_phrase(&mut self, beat: &BeatEvent) {
+        self.0.lock().unwrap().push(beat.count);
+    }
+}
+
+#[test]
+fn registry_sends_the_phrase_starts() {
+    let phrases = Arc::new(Mutex::new(Vec::new()));
+    let mut registry = SinkRegistry::new(LinkManager::new());
+    registry.register(Box::new(Phrases(phrases.clone())));
+    registry.set_phrase(8);
+    registry.on_result(&TempoUpdate {
+        shown_bpm: 120.0,
+        bpm: Some(120.0),
+        beat_age: Some(Duration::ZERO),
+        leading: false,
+        is_drop: false,
+        beat_offset: None,
+        confidence: 1.0,
+        section: Section::Steady,
+        key: None,
+    });
+
+    let start = Instant::now();
+    let mut now = start;
+    while now < start + PERIOD * 20 {
+        registry.tick(now);
+        now += POLL;
+    }
+    assert_eq!(*phrases.lock().unwrap(), vec![0, 8, 16]);
+}
@@ -0,0 +1,88 @@
+//! Beat phase loop tracking onsets across analysis hops.
+
+use rust_bpm_analyzer::core_bpm::beat_pll::BeatPll;
+
+// Hop length that does not line up with the beats
+const HOP_S: f32 = 0.37;
+
+// Age of the last beat of a grid at `bpm` starting at 0 s, at time `t`
+fn true_age(t: f32, bpm: f32) -> f32 {
+    t.rem_euclid(60.0 / bpm)
+}
+
+// Runs `hops` hops observing every beat of a grid at `bpm` with the loop at `analyzer_bpm`
+fn track(pll: &mut BeatPll, analyzer_bpm: f32, bpm: f32, hops: usize) -> f32 {
+    pll.set_tempo(analyzer_bpm);
+    let mut t = 0.0;
+    for _ in 0..hops {
+        t += HOP_S;
+        pll.advance(HOP_S);
+        pll.observe(true_age(t, bpm));
+    }
+    t
+}
+
+// Gap between two beat ages, modulo the beat period
+fn phase_error(a: f32, b: f32, bpm: f32) -> f32 {
+    let period = 60.0 / bpm;
+    let error = (a - b).rem_euclid(period);
+    error.min(period - error)
+}
+
+#[test]
+fn locks_on_a_steady_grid() {
+    let mut pll = BeatPll::new();
+    assert_eq!(pll.beat_age(), None);
+
+    let t = track(&mut pll, 128.0, 128.0, 12);
+    let age = pll.beat_age().expect("not locked");
+    assert!(phase_error(age, true_age(t, 128.0), 128.0) < 0.001);
+    let stats = pll.stats().expect("no stats");
+    assert!(stats.locked);
+    assert!(stats.jitter_ms < 1.0, "jitter {:.2} ms", stats.jitter_ms);
+}
+
+#[test]
+fn corrects_the_rounded_analyzer_tempo() {
+    // Analyzer tempo rounded to 0.1 BPM, the music is a bit faster
+    let mut pll = BeatPll::new();
+    let t = track(&mut pll, 124.0, 124.06, 150);
+
+    let age = pll.beat_age().expect("not locked");
+    assert!(
+        phase_error(age, true_age(t, 124.06), 124.06) < 0.002,
+        "phase off by {:.4} s",
+        phase_error(age, true_age(t, 124.06), 124.06)
+    );
+    let drift = pll.stats().expect("no stats").drift_bpm;
+    assert!((drift - 0.06).abs() < 0.01, "drift {:.3} BPM", drift);
+}
+
+#[test]
+fn one_late_hit_barely_moves_the_grid() {
+    let mut pll = BeatPll::new();
+    let mut t = track(&mut pll, 128.0, 128.0, 12);
+    t += HOP_S;
+    pll.advance(HOP_S);
+    // Onset 50 ms late (flam, swung hat)
+    pll.observe(true_age(t, 128.0) - 0.05);
+
+    let age = pll.beat_age().expect("lost the lock");
+    let error = phase_error(age, true_age(t, 128.0), 128.0);
+    assert!(error < 0.02, "grid moved by {:.3} s", error);
+}
+
+#[test]
+fn new_tempo_relocks() {
+    let mut pll = BeatPll::new();
+    track(&mut pll, 128.0, 128.0, 12);
+    assert!(pll.beat_age().is_some());
+
+    // Small move: same track, the phase is kept
+    pll.set_tempo(128.3);
+    assert!(pll.beat_age().is_some());
+    // Next track
+    pll.set_tempo(174.0);
+    assert_eq!(pll.beat_age(), None);
+    assert_eq!(pll.stats(), None);
+}
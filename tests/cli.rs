@@ -0,0 +1,36 @@
+//! Command line options shared by the modes. Run with `cargo test`.
+
+use rust_bpm_analyzer::config::{parse_option, parse_values};
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn option_value_follows_the_flag_or_an_equal_sign() {
+    let line = args("--mode headless --preset=club.toml");
+    assert_eq!(
+        parse_option(line.clone(), "--mode"),
+        Some(Some("headless".to_string()))
+    );
+    assert_eq!(
+        parse_option(line.clone(), "--preset"),
+        Some(Some("club.toml".to_string()))
+    );
+    assert_eq!(parse_option(line, "--sim"), None);
+}
+
+#[test]
+fn option_without_value_is_told_apart() {
+    // `--sim` alone selects the generated kicks, a flag is not its file
+    assert_eq!(parse_option(args("--sim --tui"), "--sim"), Some(None));
+    assert_eq!(parse_option(args("--sim"), "--sim"), Some(None));
+    // A longer flag with the same start is another option
+    assert_eq!(parse_option(args("--simulate x"), "--sim"), None);
+}
+
+#[test]
+fn repeated_values_stop_at_the_next_flag() {
+    let line = args("--analyze a.wav b.wav --json out.json c.wav --analyze=d.wav");
+    assert_eq!(parse_values(line, "--analyze"), ["a.wav", "b.wav", "d.wav"]);
+}
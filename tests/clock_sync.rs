@@ -0,0 +1,58 @@
+//! Alignment of device clocks and beat phase comparison.
+
+use rust_bpm_analyzer::network_sync::clock_sync::{ClockSample, ClockSync, phase_offset};
+use std::time::{Duration, Instant};
+
+fn sample(sent: Instant, round_trip_ms: u64, remote_us: u64) -> ClockSample {
+    ClockSample {
+        sent,
+        received: sent + Duration::from_millis(round_trip_ms),
+        remote_us,
+    }
+}
+
+#[test]
+fn unsynced_before_the_first_exchange() {
+    let sync = ClockSync::default();
+    assert!(sync.to_local(1_000).is_none());
+    assert!(sync.uncertainty().is_none());
+}
+
+#[test]
+fn trusts_the_fastest_exchange() {
+    let start = Instant::now();
+    let mut sync = ClockSync::default();
+    // Device clock at 10 s when the local clock is at `start + 1 ms`
+    sync.push(sample(start, 2, 10_000_000));
+    // Queued reply: the device read its clock early in a slow exchange
+    sync.push(sample(start + Duration::from_secs(2), 40, 12_001_000));
+
+    assert_eq!(sync.uncertainty(), Some(Duration::from_millis(1)));
+    let beat = sync.to_local(10_500_000).unwrap();
+    assert_eq!(beat, start + Duration::from_millis(501));
+    let earlier = sync.to_local(9_000_000).unwrap();
+    assert_eq!(earlier + Duration::from_millis(999), start);
+}
+
+#[test]
+fn keeps_the_last_exchanges_only() {
+    let start = Instant::now();
+    let mut sync = ClockSync::default();
+    sync.push(sample(start, 1, 0));
+    for i in 1..=8 {
+        sync.push(sample(start + Duration::from_secs(i), 10, i * 1_000_000));
+    }
+    assert_eq!(sync.uncertainty(), Some(Duration::from_millis(5)));
+}
+
+#[test]
+fn phase_offset_wraps_to_the_nearest_beat() {
+    let reference = Instant::now();
+    // 120 BPM: a beat every 500 ms
+    let late = reference + Duration::from_millis(1_020);
+    assert!((phase_offset(late, reference, 120.0) - 0.020).abs() < 1e-4);
+    let early = reference + Duration::from_millis(980);
+    assert!((phase_offset(early, reference, 120.0) + 0.020).abs() < 1e-4);
+    let before = reference - Duration::from_millis(510);
+    assert!((phase_offset(before, reference, 120.0) + 0.010).abs() < 1e-4);
+}
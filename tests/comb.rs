@@ -0,0 +1,71 @@
+//! Comb resonator bank on synthetic onset envelopes. Run with `cargo test`.
+
+use rust_bpm_analyzer::core_bpm::{comb, testsig};
+
+// Coarse envelope rate of the analyzer at 44.1 kHz, and its 2 s window
+const RATE: f32 = 1002.0;
+const WINDOW: usize = 2004;
+// Lags of 310 and 100 BPM
+const MIN_LAG: usize = 193;
+const MAX_LAG: usize = 601;
+
+/// (bpm of the strongest resonator, contrast) on `envelope`.
+fn strongest(envelope: &[f32]) -> (f32, f32) {
+    let (mut onsets, mut output, mut energies) = (Vec::new(), Vec::new(), Vec::new());
+    comb::onsets(envelope, RATE, &mut onsets);
+    comb::resonator_energies(&onsets, RATE, MIN_LAG, MAX_LAG, &mut output, &mut energies);
+    assert_eq!(energies.len(), MAX_LAG - MIN_LAG + 1);
+    let (index, contrast) = comb::best_resonator(&energies).expect("no resonance");
+    (RATE * 60.0 / (MIN_LAG + index) as f32, contrast)
+}
+
+/// Envelope with a 10 ms hit at every beat.
+fn hits(bpm: f32) -> Vec<f32> {
+    let period = RATE * 60.0 / bpm;
+    (0..WINDOW)
+        .map(|i| {
+            let phase = i as f32 % period;
+            if phase < RATE * 0.01 { 1.0 } else { 0.0 }
+        })
+        .collect()
+}
+
+#[test]
+fn hits_resonate_at_their_period() {
+    for bpm in [110.0, 128.0, 174.0] {
+        let (found, contrast) = strongest(&hits(bpm));
+        // Multiples of the period resonate too: the octave is the analyzer's job
+        let ratio = bpm / found;
+        assert!(
+            (ratio - ratio.round()).abs() < 0.02,
+            "{:.1} BPM resonates at {:.1}",
+            bpm,
+            found
+        );
+        assert!(contrast > 0.5, "contrast {:.2} at {:.1} BPM", contrast, bpm);
+    }
+}
+
+#[test]
+fn noise_has_a_low_contrast() {
+    let envelope: Vec<f32> = testsig::white_noise(WINDOW, 1.0, 0x2856)
+        .iter()
+        .map(|x| x.abs())
+        .collect();
+    let (_, contrast) = strongest(&envelope);
+    assert!(contrast < 0.4, "contrast {:.2}", contrast);
+}
+
+#[test]
+fn silence_does_not_resonate() {
+    let mut energies = Vec::new();
+    comb::resonator_energies(
+        &[0.0; WINDOW],
+        RATE,
+        MIN_LAG,
+        MAX_LAG,
+        &mut Vec::new(),
+        &mut energies,
+    );
+    assert!(comb::best_resonator(&energies).is_none());
+}
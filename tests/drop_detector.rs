@@ -0,0 +1,69 @@
+//! Drop detection on the envelope and on the return of the sub band. Run with
+//! `cargo test`.
+
+use rust_bpm_analyzer::core_bpm::drop_detector;
+use rust_bpm_analyzer::core_bpm::drop_detector::DropDetector;
+use std::f32::consts::PI;
+
+const RATE: u32 = 8000;
+
+// `seconds` of a 60 Hz bass and a 1 kHz lead at the given amplitudes
+fn mix(seconds: f32, bass: f32, lead: f32) -> Vec<f32> {
+    (0..(seconds * RATE as f32) as usize)
+        .map(|i| {
+            let t = i as f32 / RATE as f32;
+            bass * (2.0 * PI * 60.0 * t).sin() + lead * (2.0 * PI * 1000.0 * t).sin()
+        })
+        .collect()
+}
+
+fn detector() -> DropDetector {
+    DropDetector::new(RATE).unwrap()
+}
+
+#[test]
+fn bass_back_after_a_breakdown_is_a_drop() {
+    let mut detector = detector();
+    detector.push(&mix(8.0, 0.02, 0.3));
+    assert!(!detector.is_bass_return());
+    detector.push(&mix(1.0, 0.5, 0.3));
+    assert!(detector.is_bass_return());
+    assert!(detector.sub_surge() > 100.0, "{}", detector.sub_surge());
+}
+
+#[test]
+fn riser_without_bass_is_no_drop() {
+    let mut detector = detector();
+    detector.push(&mix(8.0, 0.1, 0.1));
+    detector.push(&mix(1.0, 0.1, 0.8));
+    assert!(!detector.is_bass_return());
+}
+
+#[test]
+fn louder_mix_is_no_drop() {
+    let mut detector = detector();
+    detector.push(&mix(8.0, 0.1, 0.1));
+    // The sub band surges, but no more than the rest
+    detector.push(&mix(1.0, 0.4, 0.4));
+    assert!(detector.sub_surge() > 3.0);
+    assert!(!detector.is_bass_return());
+}
+
+#[test]
+fn no_drop_before_the_history_is_full() {
+    let mut detector = detector();
+    detector.push(&mix(2.0, 0.0, 0.3));
+    detector.push(&mix(1.0, 0.5, 0.3));
+    assert!(!detector.is_bass_return());
+    assert_eq!(detector.sub_surge(), 0.0);
+}
+
+#[test]
+fn envelope_surge_compares_the_window_halves() {
+    let window: Vec<f32> = [vec![0.1; 100], vec![0.5; 100]].concat();
+    assert!(drop_detector::envelope_surge(&window, 1.4));
+    // Too quiet
+    let quiet: Vec<f32> = window.iter().map(|x| x * 0.1).collect();
+    assert!(!drop_detector::envelope_surge(&quiet, 1.4));
+    assert!(!drop_detector::envelope_surge(&[0.5; 200], 1.4));
+}
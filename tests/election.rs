@@ -0,0 +1,101 @@
+//! Master election between analyzers listening to the same PA.
+
+use rust_bpm_analyzer::network_sync::election::{Candidate, Election, ElectionConfig};
+
+fn election(name: &str, priority: u8) -> Election {
+    Election::new(
+        ElectionConfig {
+            enabled: true,
+            priority,
+            ..ElectionConfig::default()
+        },
+        name,
+    )
+}
+
+fn rival(name: &str, priority: u8, confidence: f32) -> Candidate {
+    Candidate {
+        name: name.to_string(),
+        priority,
+        confidence,
+        master: false,
+    }
+}
+
+// `election`'s announcement, as its rivals see it
+fn announced(name: &str, election: &Election, confidence: f32) -> Candidate {
+    Candidate {
+        master: election.is_master(),
+        ..rival(name, 0, confidence)
+    }
+}
+
+#[test]
+fn alone_is_master() {
+    let mut election = election("booth", 0);
+    assert!(election.master().is_none());
+    assert!(election.update(0.2, &[]));
+    assert_eq!(election.master(), Some("booth"));
+}
+
+#[test]
+fn most_confident_wins() {
+    let mut election = election("booth", 0);
+    assert!(!election.update(0.5, &[rival("stage", 0, 0.8)]));
+    assert_eq!(election.master(), Some("stage"));
+}
+
+#[test]
+fn priority_beats_confidence() {
+    let mut election = election("booth", 1);
+    assert!(election.update(0.3, &[rival("stage", 0, 0.9)]));
+}
+
+#[test]
+fn master_keeps_the_role_within_the_margin() {
+    let mut election = election("booth", 0);
+    assert!(election.update(0.7, &[rival("stage", 0, 0.6)]));
+    // Slightly more confident: not enough to switch
+    assert!(election.update(0.7, &[rival("stage", 0, 0.75)]));
+    assert!(!election.update(0.7, &[rival("stage", 0, 0.85)]));
+}
+
+#[test]
+fn two_self_elected_masters_settle_on_one() {
+    // Each started alone and elected itself
+    let mut booth = election("booth", 0);
+    let mut stage = election("stage", 0);
+    assert!(booth.update(0.7, &[]));
+    assert!(stage.update(0.75, &[]));
+    // Within the switch margin of each other, rounds on the last announcements
+    for booth_confidence in [0.7, 0.7, 0.8] {
+        let booth_view = [announced("stage", &stage, 0.75)];
+        let stage_view = [announced("booth", &booth, booth_confidence)];
+        booth.update(booth_confidence, &booth_view);
+        stage.update(0.75, &stage_view);
+        assert!(!booth.is_master());
+        assert!(stage.is_master());
+        assert_eq!(booth.master(), Some("stage"));
+    }
+}
+
+#[test]
+fn ties_go_to_the_first_name() {
+    let mut first = election("booth", 0);
+    let mut second = election("stage", 0);
+    assert!(first.update(0.5, &[rival("stage", 0, 0.5)]));
+    assert!(!second.update(0.5, &[rival("booth", 0, 0.5)]));
+}
+
+#[test]
+fn a_vanished_master_is_replaced() {
+    let mut election = election("booth", 0);
+    assert!(!election.update(0.5, &[rival("stage", 1, 0.9)]));
+    assert!(election.update(0.5, &[]));
+}
+
+#[test]
+fn ignores_its_own_announcement() {
+    let mut election = election("booth", 0);
+    assert!(election.update(0.5, &[rival("booth", 3, 0.1)]));
+}
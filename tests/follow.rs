@@ -0,0 +1,41 @@
+//! Leading/following decision of the Link follow mode.
+
+use rust_bpm_analyzer::network_sync::follow::{LinkConfig, LinkRole, TempoFollow};
+use std::time::{Duration, Instant};
+
+fn following() -> TempoFollow {
+    TempoFollow::new(LinkConfig {
+        follow: true,
+        ..LinkConfig::default()
+    })
+}
+
+#[test]
+fn leads_without_peers() {
+    let mut follow = following();
+    let now = Instant::now();
+    assert_eq!(follow.update(None, 0, now), LinkRole::Leading);
+    assert_eq!(follow.update(Some(0.1), 0, now), LinkRole::Leading);
+}
+
+#[test]
+fn follows_the_session_until_a_confident_tempo() {
+    let mut follow = following();
+    let start = Instant::now();
+    assert_eq!(follow.update(Some(0.9), 2, start), LinkRole::Leading);
+    // Unsure results keep the lead for follow_after_ms
+    let later = start + Duration::from_millis(3000);
+    assert_eq!(follow.update(Some(0.3), 2, later), LinkRole::Leading);
+    let later = start + Duration::from_millis(4500);
+    assert_eq!(follow.update(None, 2, later), LinkRole::Following);
+    assert_eq!(follow.update(Some(0.7), 2, later), LinkRole::Leading);
+}
+
+#[test]
+fn disabling_follow_takes_the_lead_back() {
+    let mut follow = following();
+    assert_eq!(follow.update(None, 1, Instant::now()), LinkRole::Following);
+    follow.set_follow(false);
+    assert_eq!(follow.role(), LinkRole::Leading);
+    assert_eq!(follow.update(None, 1, Instant::now()), LinkRole::Leading);
+}
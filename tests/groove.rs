@@ -0,0 +1,45 @@
+//! Swing estimation on synthetic kick patterns. Run with `cargo test`.
+
+use rust_bpm_analyzer::core_bpm::{groove, testsig};
+
+const SAMPLE_RATE: u32 = 44100;
+// Mean-square level per 2 samples, like the analyzer's raw buffer
+const STEP: usize = 2;
+
+fn swing(bpm: f32, swing: f32) -> Option<f32> {
+    // A 2 s analysis window
+    let signal = testsig::kick_pattern(SAMPLE_RATE, bpm, swing, SAMPLE_RATE as usize * 2);
+    let energy: Vec<f32> = signal
+        .chunks(STEP)
+        .map(|chunk| chunk.iter().map(|x| x * x).sum::<f32>() / chunk.len() as f32)
+        .collect();
+    groove::estimate_swing(&energy, (SAMPLE_RATE / STEP as u32) as f32, bpm)
+}
+
+#[test]
+fn straight_and_shuffled_off_beats() {
+    for (bpm, share, expected) in [
+        (124.0, 0.0, 50.0),
+        (120.0, 0.1, 60.0),
+        (128.0, 1.0 / 6.0, 66.7),
+    ] {
+        let found = swing(bpm, share).expect("swing estimated");
+        assert!(
+            (found - expected).abs() <= 3.0,
+            "{} BPM: expected {:.1}%, found {:.1}%",
+            bpm,
+            expected,
+            found
+        );
+    }
+}
+
+#[test]
+fn no_off_beats_no_swing() {
+    let signal = testsig::click_track(SAMPLE_RATE, 128.0, SAMPLE_RATE as usize * 2);
+    let energy: Vec<f32> = signal.iter().map(|x| x * x).collect();
+    assert_eq!(
+        groove::estimate_swing(&energy, SAMPLE_RATE as f32, 128.0),
+        None
+    );
+}
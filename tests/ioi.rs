@@ -0,0 +1,77 @@
+//! Onset picking and tempo induction from inter-onset intervals. Run with
+//! `cargo test`.
+
+use rust_bpm_analyzer::core_bpm::ioi;
+use rust_bpm_analyzer::core_bpm::ioi::{Onset, TempoHypothesis};
+
+const RATE: f32 = 1000.0;
+
+fn onsets(times_s: &[f32], strength: f32) -> Vec<Onset> {
+    times_s
+        .iter()
+        .map(|t| Onset {
+            at: (t * RATE) as usize,
+            strength,
+        })
+        .collect()
+}
+
+fn beats(bpm: f32, count: usize) -> Vec<f32> {
+    (0..count).map(|i| i as f32 * 60.0 / bpm).collect()
+}
+
+#[test]
+fn steady_beat_gives_its_tempo_first() {
+    let hypotheses = ioi::induce(&onsets(&beats(128.0, 8), 1.0), RATE, 100.0, 310.0);
+    let best = hypotheses.first().expect("no hypothesis");
+    assert!((best.bpm - 128.0).abs() < 1.0, "best {:.1}", best.bpm);
+    assert_eq!(best.weight, 1.0);
+}
+
+#[test]
+fn loud_kick_outweighs_light_syncopation() {
+    // Kick at 120 BPM, light hits every dotted 8th (160 BPM)
+    let mut all = onsets(&beats(120.0, 8), 1.0);
+    all.extend(onsets(&beats(160.0, 10)[1..], 0.2));
+    all.sort_by_key(|onset| onset.at);
+    all.dedup_by_key(|onset| onset.at);
+    let hypotheses = ioi::induce(&all, RATE, 100.0, 310.0);
+    let support = |bpm| ioi::support(&hypotheses, bpm, 0.03);
+    assert!(support(120.0) > support(160.0), "{:?}", hypotheses);
+}
+
+#[test]
+fn support_counts_octaves_not_thirds() {
+    let hypotheses = [
+        TempoHypothesis {
+            bpm: 240.0,
+            weight: 1.0,
+        },
+        TempoHypothesis {
+            bpm: 160.0,
+            weight: 0.5,
+        },
+    ];
+    assert_eq!(ioi::support(&hypotheses, 120.0, 0.03), 1.0);
+    assert_eq!(ioi::support(&hypotheses, 160.0, 0.03), 0.5);
+}
+
+#[test]
+fn onsets_are_the_peaks_of_the_strength() {
+    let mut strength = vec![0.0; 1000];
+    for at in [100, 130, 500] {
+        strength[at] = 1.0;
+        strength[at + 1] = 0.5;
+    }
+    // The second peak is within the minimum gap of the first
+    strength[130] = 0.8;
+    let mut picked = Vec::new();
+    ioi::pick_onsets(&strength, RATE, &mut picked);
+    let positions: Vec<usize> = picked.iter().map(|onset| onset.at).collect();
+    assert_eq!(positions, [100, 500]);
+}
+
+#[test]
+fn too_few_onsets_give_no_hypothesis() {
+    assert!(ioi::induce(&onsets(&[0.0, 0.5], 1.0), RATE, 100.0, 310.0).is_empty());
+}
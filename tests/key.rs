@@ -0,0 +1,69 @@
+//! Key estimation on synthetic chord progressions. Run with `cargo test`.
+
+use rust_bpm_analyzer::core_bpm::key::{Key, KeyConfig, KeyDetector};
+use rust_bpm_analyzer::core_bpm::testsig;
+use std::f32::consts::PI;
+
+const SAMPLE_RATE: u32 = 44100;
+
+// Chords of two seconds each (MIDI notes), repeated over 16 seconds, over kicks
+fn progression(chords: &[[u32; 3]]) -> Vec<f32> {
+    let chord_len = SAMPLE_RATE as usize * 2;
+    let len = chord_len * 8;
+    let mut signal = testsig::kick_pattern(SAMPLE_RATE, 124.0, 0.0, len);
+    for (i, sample) in signal.iter_mut().enumerate() {
+        let chord = chords[(i / chord_len) % chords.len()];
+        let t = i as f32 / SAMPLE_RATE as f32;
+        for note in chord {
+            let freq = 440.0 * 2f32.powf((note as f32 - 69.0) / 12.0);
+            *sample += 0.1 * (2.0 * PI * freq * t).sin();
+        }
+    }
+    signal
+}
+
+fn detect(signal: &[f32]) -> Option<Key> {
+    let mut detector = KeyDetector::new(SAMPLE_RATE, &KeyConfig::default()).unwrap();
+    for packet in signal.chunks(512) {
+        detector.push(packet);
+    }
+    detector.key()
+}
+
+#[test]
+fn camelot_wheel() {
+    let camelot = |tonic, minor| Key { tonic, minor }.camelot();
+    assert_eq!(camelot(0, false), "8B");
+    assert_eq!(camelot(9, true), "8A");
+    assert_eq!(camelot(7, false), "9B");
+    assert_eq!(camelot(6, true), "11A");
+    assert_eq!(camelot(1, false), "3B");
+}
+
+#[test]
+fn major_and_minor_progressions() {
+    // G - C - D - Em
+    let g_major = progression(&[[55, 59, 62], [60, 64, 67], [62, 66, 69], [64, 67, 71]]);
+    assert_eq!(
+        detect(&g_major),
+        Some(Key {
+            tonic: 7,
+            minor: false
+        })
+    );
+    // Am - Dm - E - Am
+    let a_minor = progression(&[[57, 60, 64], [62, 65, 69], [64, 68, 71], [57, 60, 64]]);
+    assert_eq!(
+        detect(&a_minor),
+        Some(Key {
+            tonic: 9,
+            minor: true
+        })
+    );
+}
+
+#[test]
+fn no_key_before_enough_audio() {
+    let signal = progression(&[[60, 64, 67]]);
+    assert_eq!(detect(&signal[..SAMPLE_RATE as usize * 4]), None);
+}
@@ -0,0 +1,52 @@
+//! Input level history served to the desktop window. Run with `cargo test`.
+
+use rust_bpm_analyzer::core_bpm::level_history::{LevelHistory, LevelSnapshot};
+use rust_bpm_analyzer::network_sync::clock_sync::{ClockSample, ClockSync};
+use std::time::{Duration, Instant};
+
+#[test]
+fn one_mean_point_per_interval_over_the_period() {
+    let mut history = LevelHistory::new(Duration::from_secs(1), Duration::from_secs(3));
+    let start = Instant::now();
+    // Ten packets per second: a loud second, then silence
+    let mut points = 0;
+    for i in 0..=50 {
+        let rms = if i < 10 { 0.2 } else { 0.0 };
+        if history.push_at(rms, start + Duration::from_millis(100 * i)) {
+            points += 1;
+        }
+    }
+    assert_eq!(points, 5);
+
+    let snapshot = history.snapshot();
+    assert_eq!(snapshot.interval_s, 1.0);
+    // Only the last three seconds are kept, all silent
+    assert_eq!(snapshot.levels, vec![0.0; 3]);
+}
+
+#[test]
+fn devices_share_the_local_timeline() {
+    let start = Instant::now();
+    let now = start + Duration::from_secs(60);
+    // Device clocks started 5 s and 40 s before `start`, read in a 2 ms exchange
+    let device = |offset_s: u64, end_us: u64| {
+        let mut clock = ClockSync::default();
+        clock.push(ClockSample {
+            sent: start,
+            received: start + Duration::from_millis(2),
+            remote_us: offset_s * 1_000_000 + 1_000,
+        });
+        let history = LevelSnapshot {
+            interval_s: 1.0,
+            levels: vec![0.0, 0.1, 0.1],
+            end_us: Some(end_us),
+        };
+        history.timeline(clock.to_local(end_us).expect("aligned"), now)
+    };
+    // Both histories end 57 s after `start`, 3 s before `now`
+    let first = device(5, 62_000_000);
+    let second = device(40, 97_000_000);
+    assert_eq!(first, second);
+    let ages: Vec<f32> = first.iter().map(|&(age, _)| age.round()).collect();
+    assert_eq!(ages, vec![5.0, 4.0, 3.0]);
+}
@@ -0,0 +1,111 @@
+//! Hop scheduling and re-initialization of the live analysis shared by the front-ends.
+
+use rust_bpm_analyzer::core_bpm::analyzer::{BpmAnalyzerConfig, OctavePreference};
+use rust_bpm_analyzer::core_bpm::governor::GovernorConfig;
+use rust_bpm_analyzer::core_bpm::live::LiveAnalysis;
+use rust_bpm_analyzer::core_bpm::testsig;
+use rust_bpm_analyzer::core_bpm::verification::VerificationConfig;
+
+const SAMPLE_RATE: u32 = 48000;
+// Capture packets of 10 ms, much smaller than a hop
+const PACKET: usize = (SAMPLE_RATE / 100) as usize;
+
+fn live(beat_features: bool) -> LiveAnalysis {
+    LiveAnalysis::new(
+        SAMPLE_RATE,
+        BpmAnalyzerConfig::default(),
+        GovernorConfig::default(),
+        beat_features,
+    )
+    .expect("live analysis")
+}
+
+#[test]
+fn small_packets_converge_in_half_second_hops() {
+    let mut live = live(true);
+    let window = BpmAnalyzerConfig::default().window_duration.as_secs_f32();
+    let len = (SAMPLE_RATE as f32 * (window + 4.0)) as usize;
+    let signal = testsig::kick_pattern(SAMPLE_RATE, 126.0, 0.0, len);
+
+    let mut hops = 0;
+    let mut features = 0;
+    let mut last = None;
+    for packet in signal.chunks(PACKET) {
+        if live.push(packet).is_some() {
+            features += 1;
+        }
+        if live.hop_ready() {
+            hops += 1;
+            if let Some(result) = live.analyze().expect("analyze") {
+                last = Some(result);
+            }
+        }
+    }
+
+    assert_eq!(hops, len / (SAMPLE_RATE / 2) as usize);
+    let result = last.expect("no tempo detected");
+    assert!(
+        (result.bpm - 126.0).abs() <= 1.0,
+        "detected {:.1}",
+        result.bpm
+    );
+    // Without `[verification]` the detected tempo goes out as is
+    assert_eq!(result.verified_bpm, Some(result.bpm));
+    // Every hop counts as analyzed, the ones before the window filled up are not locked
+    let stats = live.statistics();
+    assert!((stats.analyzed_s - hops as f64 * 0.5).abs() < 1e-6);
+    assert!(stats.locked_percent > 0.0 && stats.locked_percent < 100.0);
+    assert!((stats.average_bpm.expect("no average") - 126.0).abs() <= 1.0);
+    // The grid is set from the detected tempo, band levels follow every beat after it
+    assert!(features > 0);
+}
+
+#[test]
+fn sample_rate_change_keeps_the_octave_and_drops_queued_audio() {
+    let mut live = live(false);
+    live.analyzer_mut()
+        .set_octave_preference(OctavePreference::Double);
+    live.push(&vec![0.0; (SAMPLE_RATE / 2) as usize]);
+    assert!(live.hop_ready());
+
+    live.set_sample_rate(44100).expect("rate change");
+    assert_eq!(live.sample_rate(), 44100);
+    assert!(!live.hop_ready());
+    assert_eq!(
+        live.analyzer().octave_preference(),
+        OctavePreference::Double
+    );
+}
+
+#[test]
+fn verified_tempo_waits_for_the_long_window() {
+    let mut live = live(false);
+    live.set_verification(VerificationConfig {
+        enabled: true,
+        window_s: 6,
+        tolerance: 1.5,
+        interval_hops: 2,
+    })
+    .expect("verification");
+    let len = SAMPLE_RATE as usize * 10;
+    let signal = testsig::kick_pattern(SAMPLE_RATE, 126.0, 0.0, len);
+
+    let mut results = Vec::new();
+    for packet in signal.chunks(PACKET) {
+        live.push(packet);
+        if live.hop_ready()
+            && let Some(result) = live.analyze().expect("analyze")
+        {
+            results.push(result);
+        }
+    }
+
+    // The fast track reports long before the 6 s window has filled up
+    let first = results.first().expect("no tempo detected");
+    assert_eq!(first.verified_bpm, None);
+    let verified = results
+        .last()
+        .and_then(|result| result.verified_bpm)
+        .expect("tempo never verified");
+    assert!((verified - 126.0).abs() <= 1.0, "verified {:.1}", verified);
+}
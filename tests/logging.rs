@@ -0,0 +1,40 @@
+//! Levels of the `[log]` section and the records kept for the diagnostics.
+//! Run with `cargo test`.
+
+use rust_bpm_analyzer::logging;
+use rust_bpm_analyzer::logging::LogConfig;
+use std::collections::BTreeMap;
+
+#[test]
+fn module_levels_follow_the_program_level() {
+    let config = LogConfig {
+        level: "debug".to_string(),
+        modules: BTreeMap::from([("core_bpm::audio".to_string(), "trace".to_string())]),
+        ..LogConfig::default()
+    };
+    assert_eq!(
+        config.directives(),
+        "warn,rust_bpm_analyzer=debug,rust_bpm_analyzer::core_bpm::audio=trace"
+    );
+}
+
+#[test]
+fn warnings_are_kept_for_remote_diagnostics() {
+    logging::init(&LogConfig::default(), true);
+    // Targets of the analyzer modules, this test crate only gets the warnings
+    tracing::info!(target: "rust_bpm_analyzer::core_bpm::audio", "stream started");
+    tracing::warn!(target: "rust_bpm_analyzer::core_bpm::audio", "stream restarted");
+
+    let events = logging::recent_events();
+    assert!(
+        events
+            .iter()
+            .any(|e| e.level == "WARN" && e.message == "stream restarted")
+    );
+    assert!(events.iter().all(|e| e.message != "stream started"));
+    assert!(
+        logging::recent_lines()
+            .iter()
+            .any(|line| line.ends_with("INFO stream started"))
+    );
+}
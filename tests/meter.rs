@@ -0,0 +1,21 @@
+//! Peak/RMS metering of the capture packets. Run with `cargo test`.
+
+use rust_bpm_analyzer::core_bpm::meter::{CLIP_LEVEL, LevelMeter};
+use std::time::Duration;
+
+#[test]
+fn one_level_per_period_of_interleaved_audio() {
+    // 50 ms of stereo at 1 kHz: 100 samples
+    let mut meter = LevelMeter::new(1000, 2, Duration::from_millis(50));
+    assert_eq!(meter.push(&[0.5; 60]), None);
+    let (peak, rms) = meter.push(&[-0.5; 40]).expect("level after a period");
+    assert_eq!(peak, 0.5);
+    assert!((rms - 0.5).abs() < 1e-6);
+
+    // A single full-scale sample in a quiet period is caught by the peak
+    let mut packet = vec![0.01; 100];
+    packet[42] = -1.0;
+    let (peak, rms) = meter.push(&packet).unwrap();
+    assert!(peak >= CLIP_LEVEL);
+    assert!(rms < 0.2);
+}
@@ -0,0 +1,24 @@
+//! Prometheus export of the analysis counters. Run with `cargo test`.
+
+use rust_bpm_analyzer::metrics::METRICS;
+use std::time::Duration;
+
+#[test]
+fn exports_counters_and_last_tempo() {
+    METRICS.frames_processed(24000);
+    METRICS.analysis(Duration::from_millis(20), Some((128.0, 0.75)));
+    METRICS.analysis(Duration::from_millis(30), None);
+    METRICS.audio_restart();
+    METRICS.message_send_failed();
+
+    let text = METRICS.render();
+    assert!(text.contains("# TYPE bpm_analyzer_frames_processed_total counter"));
+    assert!(text.contains("bpm_analyzer_frames_processed_total 24000\n"));
+    assert!(text.contains("bpm_analyzer_analyses_total 2\n"));
+    assert!(text.contains("bpm_analyzer_analysis_seconds_total 0.050000\n"));
+    // A hop without tempo keeps the last one
+    assert!(text.contains("bpm_analyzer_bpm 128\n"));
+    assert!(text.contains("bpm_analyzer_confidence 0.75\n"));
+    assert!(text.contains("bpm_analyzer_audio_restarts_total 1\n"));
+    assert!(text.contains("bpm_analyzer_network_send_errors_total 1\n"));
+}
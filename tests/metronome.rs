@@ -0,0 +1,107 @@
+//! Click track phase-locked to the beat grid. Run with `cargo test`.
+
+use rust_bpm_analyzer::outputs::metronome::{ClickKind, Metronome};
+use std::time::{Duration, Instant};
+
+// 120 BPM
+const PERIOD: Duration = Duration::from_millis(500);
+// Poll period of the MIDI thread
+const POLL: Duration = Duration::from_millis(2);
+const RATE: u32 = 8000;
+
+// Clicks sent while polling from `start` for `length`
+fn clicks(metronome: &Metronome, start: Instant, length: Duration) -> Vec<(ClickKind, u8)> {
+    let mut clicks = Vec::new();
+    let mut last_sent = None;
+    let mut now = start;
+    while now < start + length {
+        if let Some(click) = metronome.due_click(last_sent, now) {
+            last_sent = Some(click.at);
+            clicks.push((click.kind, click.beat_in_bar));
+        }
+        now += POLL;
+    }
+    clicks
+}
+
+#[test]
+fn counts_in_then_accents_the_downbeats() {
+    let start = Instant::now();
+    let mut metronome = Metronome::new(4, 2, true);
+    metronome.set_beat(120.0, start, start);
+    let kinds: Vec<ClickKind> = clicks(&metronome, start, PERIOD * 7)
+        .into_iter()
+        .map(|(kind, _)| kind)
+        .collect();
+    use ClickKind::*;
+    assert_eq!(
+        kinds,
+        [CountIn, CountIn, Downbeat, Beat, Beat, Beat, Downbeat]
+    );
+}
+
+#[test]
+fn close_tempo_keeps_the_bar_and_follows_the_beat() {
+    let start = Instant::now();
+    let mut metronome = Metronome::new(4, 0, true);
+    metronome.set_beat(120.0, start, start);
+    // Two beats later, the detection is 10 ms late and slightly slower
+    let now = start + PERIOD * 2 + Duration::from_millis(100);
+    metronome.set_beat(119.0, start + PERIOD * 2 + Duration::from_millis(10), now);
+    let polled = clicks(&metronome, now, PERIOD * 3);
+    let positions: Vec<u8> = polled.iter().map(|&(_, beat)| beat).collect();
+    assert_eq!(positions, [3, 0, 1]);
+    assert_eq!(polled[0].0, ClickKind::Beat);
+    assert_eq!(metronome.bpm(), Some(119.0));
+}
+
+#[test]
+fn tempo_change_counts_in_again() {
+    let start = Instant::now();
+    let mut metronome = Metronome::new(4, 4, true);
+    metronome.set_beat(120.0, start, start);
+    let now = start + PERIOD * 8;
+    metronome.set_beat(140.0, now, now);
+    let polled = clicks(&metronome, now, Duration::from_secs(2));
+    assert!(
+        polled[..4]
+            .iter()
+            .all(|&(kind, _)| kind == ClickKind::CountIn)
+    );
+    assert_eq!(polled[4].0, ClickKind::Downbeat);
+}
+
+#[test]
+fn drop_restarts_the_bar() {
+    let start = Instant::now();
+    let mut metronome = Metronome::new(4, 0, true);
+    metronome.set_beat(120.0, start, start);
+    // Drop on the second beat
+    metronome.restart_bar(start + PERIOD + Duration::from_millis(20));
+    let positions: Vec<u8> = clicks(&metronome, start + PERIOD, PERIOD * 4)
+        .into_iter()
+        .map(|(_, beat)| beat)
+        .collect();
+    assert_eq!(positions, [0, 1, 2, 3]);
+}
+
+#[test]
+fn rendered_clicks_land_on_the_beats() {
+    let start = Instant::now();
+    let mut metronome = Metronome::new(4, 0, false);
+    metronome.set_beat(120.0, start, start);
+    let mut out = vec![1.0; RATE as usize];
+    metronome.render(&mut out, RATE, start, 1.0);
+    let peak = |from_ms: usize, to_ms: usize| {
+        out[from_ms * RATE as usize / 1000..to_ms * RATE as usize / 1000]
+            .iter()
+            .fold(0.0f32, |peak, s| peak.max(s.abs()))
+    };
+    assert!(peak(0, 20) > 0.5);
+    assert!(peak(500, 520) > 0.5);
+    assert_eq!(peak(100, 480), 0.0);
+
+    metronome.stop();
+    metronome.render(&mut out, RATE, start, 1.0);
+    assert!(out.iter().all(|&s| s == 0.0));
+}
@@ -0,0 +1,75 @@
+//! Bindings of incoming MIDI notes and CCs to the desktop actions.
+
+use rust_bpm_analyzer::midi::MidiEvent;
+use rust_bpm_analyzer::midi_mapping::{MidiAction, MidiMap};
+
+fn note_on(note: u8) -> MidiEvent {
+    MidiEvent::NoteOn {
+        channel: 0,
+        note,
+        velocity: 100,
+    }
+}
+
+fn cc(controller: u8, value: u8) -> MidiEvent {
+    MidiEvent::ControlChange {
+        channel: 0,
+        controller,
+        value,
+    }
+}
+
+#[test]
+fn learnt_controls_trigger_their_action_once_per_press() {
+    let mut map = MidiMap::default();
+    map.learn(MidiAction::Tap, &note_on(36))
+        .expect("note learnt");
+    map.learn(MidiAction::ToggleDetection, &cc(20, 127))
+        .expect("CC learnt");
+
+    assert_eq!(map.action(&note_on(36)), Some(MidiAction::Tap));
+    assert_eq!(
+        map.action(&MidiEvent::NoteOff {
+            channel: 0,
+            note: 36
+        }),
+        None
+    );
+    assert_eq!(map.action(&cc(20, 127)), Some(MidiAction::ToggleDetection));
+    // Button release
+    assert_eq!(map.action(&cc(20, 0)), None);
+    // Same note on another channel
+    assert_eq!(
+        map.action(&MidiEvent::NoteOn {
+            channel: 1,
+            note: 36,
+            velocity: 100
+        }),
+        None
+    );
+}
+
+#[test]
+fn a_control_belongs_to_one_action() {
+    let mut map = MidiMap::default();
+    map.learn(MidiAction::Tap, &note_on(36));
+    map.learn(MidiAction::Tap, &note_on(37));
+    assert_eq!(map.action(&note_on(36)), None);
+
+    map.learn(MidiAction::OctaveShift, &note_on(37));
+    assert_eq!(map.get(MidiAction::Tap), None);
+    assert_eq!(map.action(&note_on(37)), Some(MidiAction::OctaveShift));
+
+    assert_eq!(
+        map.learn(
+            MidiAction::Calibrate,
+            &MidiEvent::NoteOff {
+                channel: 0,
+                note: 40
+            }
+        ),
+        None
+    );
+    map.clear(MidiAction::OctaveShift);
+    assert_eq!(map.action(&note_on(37)), None);
+}
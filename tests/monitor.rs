@@ -0,0 +1,71 @@
+//! Monitor output mix: input passthrough and beat click. Run with `cargo test`.
+
+use rust_bpm_analyzer::core_bpm::monitor::{MonitorMix, MonitorSource};
+use std::time::{Duration, Instant};
+
+const RATE: u32 = 48000;
+
+fn render(mix: &mut MonitorMix, len: usize, at: Instant) -> Vec<f32> {
+    let mut out = vec![1.0; len];
+    mix.render(&mut out, at);
+    out
+}
+
+fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0, |peak, s| peak.max(s.abs()))
+}
+
+#[test]
+fn input_plays_through_at_the_same_rate() {
+    let mut mix = MonitorMix::new(RATE, MonitorSource::Input, 1.0);
+    let input: Vec<f32> = (0..100).map(|i| i as f32 / 200.0).collect();
+    mix.push_input(&input, Instant::now());
+    // The first sample of the stream ramps up from silence
+    let out = render(&mut mix, 120, Instant::now());
+    assert_eq!(&out[1..100], &input[..99]);
+    assert!(out[100..].iter().all(|&s| s == 0.0), "{:?}", &out[100..]);
+}
+
+#[test]
+fn input_is_resampled_to_the_output_rate() {
+    let mut mix = MonitorMix::new(RATE, MonitorSource::Input, 1.0);
+    mix.set_input_rate(RATE / 2);
+    mix.push_input(&[0.5; 1000], Instant::now());
+    assert_eq!(mix.queued(), 2000);
+    let out = render(&mut mix, 2000, Instant::now());
+    assert!(out[2..].iter().all(|&s| s == 0.5));
+}
+
+#[test]
+fn queued_input_is_bounded() {
+    let mut mix = MonitorMix::new(RATE, MonitorSource::Input, 0.5);
+    for _ in 0..6 {
+        mix.push_input(&[0.1; RATE as usize / 2], Instant::now());
+    }
+    assert_eq!(mix.queued(), RATE as usize);
+}
+
+#[test]
+fn click_lands_on_the_beats() {
+    let mut mix = MonitorMix::new(RATE, MonitorSource::Click, 1.0);
+    let now = Instant::now();
+    // 120 BPM, a beat 100 ms ago: the next ones 400 and 900 ms from now
+    mix.set_beat(120.0, Duration::from_millis(100), now);
+    let out = render(&mut mix, RATE as usize, now);
+    let window = |ms: usize| &out[ms * RATE as usize / 1000..(ms + 20) * RATE as usize / 1000];
+    assert!(peak(window(400)) > 0.5);
+    assert!(peak(window(900)) > 0.5);
+    assert_eq!(peak(window(0)), 0.0);
+    assert_eq!(peak(window(600)), 0.0);
+}
+
+#[test]
+fn silent_without_input_or_beat() {
+    let mut mix = MonitorMix::new(RATE, MonitorSource::Both, 1.0);
+    assert_eq!(peak(&render(&mut mix, 4800, Instant::now())), 0.0);
+    // The click alone ignores the input
+    let mut mix = MonitorMix::new(RATE, MonitorSource::Click, 1.0);
+    mix.push_input(&[0.5; 4800], Instant::now());
+    assert_eq!(mix.queued(), 0);
+    assert_eq!(peak(&render(&mut mix, 4800, Instant::now())), 0.0);
+}
@@ -0,0 +1,160 @@
+//! Tempo segmentation of the offline analysis on synthetic mixes.
+
+use rust_bpm_analyzer::core_bpm::analyzer::BpmAnalyzerConfig;
+use rust_bpm_analyzer::core_bpm::beat_grid::{BeatGridConfig, BeatGridRecorder};
+use rust_bpm_analyzer::core_bpm::offline::TempoSegment;
+use rust_bpm_analyzer::core_bpm::{cues, offline, testsig};
+use std::time::Duration;
+
+const SAMPLE_RATE: u32 = 44100;
+
+fn seconds(s: f32) -> usize {
+    (SAMPLE_RATE as f32 * s) as usize
+}
+
+fn segments(signal: &[f32]) -> Vec<TempoSegment> {
+    offline::analyze_samples(signal, SAMPLE_RATE, BpmAnalyzerConfig::default()).expect("analysis")
+}
+
+#[test]
+fn single_tempo_gives_one_segment() {
+    let signal = testsig::kick_pattern(SAMPLE_RATE, 126.0, 0.0, seconds(30.0));
+    let segments = segments(&signal);
+    assert_eq!(segments.len(), 1, "{:?}", segments);
+    assert!((segments[0].bpm - 126.0).abs() <= 1.0, "{:?}", segments);
+    assert!(segments[0].start_s < 3.0 && segments[0].end_s > 29.0);
+}
+
+#[test]
+fn tempo_change_splits_segments() {
+    let mut signal = testsig::kick_pattern(SAMPLE_RATE, 124.0, 0.0, seconds(30.0));
+    signal.extend(testsig::kick_pattern(
+        SAMPLE_RATE,
+        174.0,
+        0.0,
+        seconds(30.0),
+    ));
+    let segments = segments(&signal);
+
+    assert_eq!(segments.len(), 2, "{:?}", segments);
+    assert!((segments[0].bpm - 124.0).abs() <= 1.0, "{:?}", segments);
+    assert!((segments[1].bpm - 174.0).abs() <= 1.0, "{:?}", segments);
+    let boundary = segments[1].start_s;
+    assert!(
+        (boundary - 30.0).abs() <= 3.0,
+        "boundary at {:.1} s",
+        boundary
+    );
+}
+
+#[test]
+fn silence_between_tracks_closes_the_segment() {
+    let mut signal = testsig::click_track(SAMPLE_RATE, 128.0, seconds(20.0));
+    signal.extend(vec![0.0; seconds(10.0)]);
+    signal.extend(testsig::click_track(SAMPLE_RATE, 128.0, seconds(20.0)));
+    let segments = segments(&signal);
+
+    assert_eq!(segments.len(), 2, "{:?}", segments);
+    assert!(segments[0].end_s <= 21.0, "{:?}", segments);
+    assert!(segments[1].start_s >= 29.0, "{:?}", segments);
+}
+
+#[test]
+fn breakdown_and_phrases_become_cues() {
+    // 32 bars, 16-bar breakdown at a fifth of the level, 32 bars (one bar = 1.875 s)
+    let bar_s = 4.0 * 60.0 / 128.0;
+    let mut signal = testsig::kick_pattern(SAMPLE_RATE, 128.0, 0.0, seconds(32.0 * bar_s));
+    let breakdown = testsig::kick_pattern(SAMPLE_RATE, 128.0, 0.0, seconds(16.0 * bar_s));
+    signal.extend(breakdown.iter().map(|x| x * 0.2));
+    signal.extend(testsig::kick_pattern(
+        SAMPLE_RATE,
+        128.0,
+        0.0,
+        seconds(32.0 * bar_s),
+    ));
+
+    let segments = segments(&signal);
+    let envelope = cues::KickEnvelope::new(&signal, SAMPLE_RATE).expect("envelope");
+    let duration_s = signal.len() as f32 / SAMPLE_RATE as f32;
+    let grids = cues::beat_grids(&envelope, &segments, duration_s);
+    let cues = cues::detect_cues(&envelope, &grids);
+
+    let find = |kind: cues::CueKind| {
+        cues.iter()
+            .find(|cue| cue.kind == kind)
+            .map(|cue| cue.time_s)
+            .unwrap_or_else(|| panic!("no {:?} cue in {:?}", kind, cues))
+    };
+    let down = find(cues::CueKind::EnergyDown);
+    let up = find(cues::CueKind::EnergyUp);
+    assert!(
+        (down - 32.0 * bar_s).abs() <= 2.0 * bar_s,
+        "down at {:.1} s",
+        down
+    );
+    assert!(
+        (up - 48.0 * bar_s).abs() <= 2.0 * bar_s,
+        "up at {:.1} s",
+        up
+    );
+
+    let phrases: Vec<f32> = cues
+        .iter()
+        .filter(|cue| matches!(cue.kind, cues::CueKind::Phrase | cues::CueKind::Section))
+        .map(|cue| cue.time_s)
+        .collect();
+    assert!(phrases.len() >= 4, "{:?}", cues);
+    for pair in phrases.windows(2) {
+        assert!(
+            ((pair[1] - pair[0]) - 16.0 * bar_s).abs() < 0.5,
+            "{:?}",
+            phrases
+        );
+    }
+}
+
+#[test]
+fn live_beat_grid_follows_the_beats_and_tempo_changes() {
+    let mut recorder = BeatGridRecorder::new(BeatGridConfig::default());
+    let hop = Duration::from_millis(500);
+    // 128 BPM for 30 s then 100 BPM, the last beat reported every other hop only
+    let mut beat_s = 0.0;
+    let mut position_s = 0.0;
+    for index in 0..120 {
+        recorder.advance(hop);
+        position_s += 0.5;
+        let bpm = if position_s <= 30.0 { 128.0 } else { 100.0 };
+        while beat_s + 60.0 / bpm <= position_s {
+            beat_s += 60.0 / bpm;
+        }
+        if index % 2 == 0 {
+            let age = Duration::from_secs_f64(position_s - beat_s);
+            recorder.record(bpm as f32, Some(age));
+        }
+    }
+
+    let export = recorder.export();
+    let bpms: Vec<f32> = export.markers.iter().map(|marker| marker.bpm).collect();
+    assert_eq!(bpms, vec![128.0, 100.0]);
+    let change_s = export.markers[1].time_s;
+    assert!((change_s - 30.0).abs() < 1.0, "change at {:.2} s", change_s);
+    for pair in export.beats.windows(2) {
+        let period = if pair[1] <= change_s {
+            60.0 / 128.0
+        } else {
+            60.0 / 100.0
+        };
+        // Spread evenly between the reported beats, the hop with the change aside
+        let interval = pair[1] - pair[0];
+        assert!(
+            (interval - period).abs() < 0.01 || (pair[1] - change_s).abs() < 1.0,
+            "{:.3} s between {:.3} and {:.3}",
+            interval,
+            pair[0],
+            pair[1]
+        );
+    }
+    let csv = export.to_csv();
+    assert!(csv.starts_with("time_s,bpm\n"));
+    assert_eq!(csv.lines().count(), export.beats.len() + 1);
+}
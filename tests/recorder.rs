@@ -0,0 +1,50 @@
+//! Ring buffer of the recent input and automatic low-confidence captures.
+//! Run with `cargo test`.
+
+use rust_bpm_analyzer::core_bpm::recorder::{InputRecorder, LowConfidenceWatch, RecordingConfig};
+use std::time::{Duration, Instant};
+
+const SAMPLE_RATE: u32 = 8000;
+
+#[test]
+fn keeps_only_the_last_seconds_in_the_wav_file() {
+    let mut recorder = InputRecorder::new(2, SAMPLE_RATE);
+    // 3 s: the first second is dropped
+    for second in 0..3 {
+        let level = 0.25 * second as f32;
+        recorder.push(&vec![level; SAMPLE_RATE as usize]);
+    }
+    assert_eq!(recorder.duration(), Duration::from_secs(2));
+
+    let directory = std::env::temp_dir().join(format!("bpm-recorder-{}", std::process::id()));
+    let path = recorder.save(&directory).expect("capture saved");
+    let mut reader = hound::WavReader::open(&path).expect("valid WAV");
+    assert_eq!(reader.spec().sample_rate, SAMPLE_RATE);
+    let samples: Vec<i16> = reader.samples::<i16>().map(|s| s.unwrap()).collect();
+    assert_eq!(samples.len(), 2 * SAMPLE_RATE as usize);
+    assert_eq!(samples[0], (0.25 * i16::MAX as f32) as i16);
+    assert_eq!(samples[samples.len() - 1], (0.5 * i16::MAX as f32) as i16);
+    let _ = std::fs::remove_dir_all(directory);
+}
+
+#[test]
+fn low_confidence_stretch_fires_once_per_cooldown() {
+    let config = RecordingConfig {
+        low_confidence_hops: 3,
+        cooldown_s: 60,
+        ..RecordingConfig::default()
+    };
+    let mut watch = LowConfidenceWatch::new(&config);
+    let start = Instant::now();
+
+    // Silence in the middle of the stretch restarts the count
+    let hops = [Some(0.1), Some(0.2), None, Some(0.1), Some(0.1)];
+    assert!(hops.iter().all(|&c| !watch.update(c, start)));
+    assert!(watch.update(Some(0.1), start));
+
+    for _ in 0..5 {
+        assert!(!watch.update(Some(0.1), start + Duration::from_secs(30)));
+    }
+    // Still confused after the cooldown
+    assert!(watch.update(Some(0.1), start + Duration::from_secs(61)));
+}
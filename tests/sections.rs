@@ -0,0 +1,142 @@
+//! Section labels from band energies, onsets and spectral tilt. Run with
+//! `cargo test`.
+
+use rust_bpm_analyzer::core_bpm::sections::{
+    FeatureTracker, Section, SectionClassifier, SectionFeatures,
+};
+use std::f32::consts::PI;
+
+const RATE: u32 = 8000;
+// Analyzer hop
+const HOP_S: f32 = 0.5;
+
+fn features(sub: f32, high: f32, onset_rate: f32) -> SectionFeatures {
+    SectionFeatures {
+        sub,
+        mid: 0.1,
+        high,
+        onset_rate,
+        seconds: HOP_S,
+    }
+}
+
+// Feeds `seconds` of the same features, returns the last label
+fn play(classifier: &mut SectionClassifier, seconds: f32, f: SectionFeatures) -> Section {
+    let mut section = classifier.section();
+    for _ in 0..(seconds / HOP_S) as usize {
+        section = classifier.update(f, false);
+    }
+    section
+}
+
+#[test]
+fn intro_until_the_drop_then_steady() {
+    let mut classifier = SectionClassifier::new();
+    assert_eq!(
+        play(&mut classifier, 10.0, features(1.0, 0.05, 4.0)),
+        Section::Intro
+    );
+    assert_eq!(
+        classifier.update(features(1.0, 0.05, 4.0), true),
+        Section::Drop
+    );
+    assert_eq!(
+        play(&mut classifier, 10.0, features(1.0, 0.05, 4.0)),
+        Section::Drop
+    );
+    assert_eq!(
+        play(&mut classifier, 10.0, features(1.0, 0.05, 4.0)),
+        Section::Steady
+    );
+}
+
+#[test]
+fn bass_out_is_a_breakdown_after_the_hold() {
+    let mut classifier = SectionClassifier::new();
+    play(&mut classifier, 20.0, features(1.0, 0.05, 4.0));
+    // Recent window fully quiet after 2 s, then held for 1.5 s
+    assert_eq!(
+        play(&mut classifier, 2.5, features(0.05, 0.05, 4.0)),
+        Section::Intro
+    );
+    assert_eq!(
+        play(&mut classifier, 0.5, features(0.05, 0.05, 4.0)),
+        Section::Breakdown
+    );
+    // The bass coming back without a drop
+    assert_eq!(
+        play(&mut classifier, 4.0, features(1.0, 0.05, 4.0)),
+        Section::Intro
+    );
+}
+
+#[test]
+fn snare_roll_builds_up_until_the_drop() {
+    let mut classifier = SectionClassifier::new();
+    play(&mut classifier, 20.0, features(1.0, 0.05, 4.0));
+    assert_eq!(
+        play(&mut classifier, 8.0, features(0.05, 0.05, 2.0)),
+        Section::Breakdown
+    );
+    assert_eq!(
+        play(&mut classifier, 3.0, features(0.05, 0.05, 8.0)),
+        Section::BuildUp
+    );
+    // Name in the OSC and JSON messages
+    assert_eq!(classifier.section().name(), "build_up");
+    // Levelled off, still building up
+    assert_eq!(
+        play(&mut classifier, 8.0, features(0.05, 0.05, 8.0)),
+        Section::BuildUp
+    );
+    assert_eq!(
+        classifier.update(features(1.0, 0.05, 8.0), true),
+        Section::Drop
+    );
+}
+
+#[test]
+fn riser_builds_up() {
+    let mut classifier = SectionClassifier::new();
+    play(&mut classifier, 20.0, features(1.0, 0.05, 4.0));
+    assert_eq!(
+        play(&mut classifier, 8.0, features(0.05, 0.05, 0.0)),
+        Section::Breakdown
+    );
+    let mut section = Section::Breakdown;
+    for step in 1..=8 {
+        section = classifier.update(features(0.05, 0.05 * 1.4f32.powi(step), 0.0), false);
+    }
+    assert_eq!(section, Section::BuildUp);
+}
+
+#[test]
+fn tracker_counts_onsets_and_splits_the_bands() {
+    let mut tracker = FeatureTracker::new(RATE).unwrap();
+    // 2 s of a 20 Hz bass, with a 300 Hz blip every 250 ms
+    let samples: Vec<f32> = (0..2 * RATE as usize)
+        .map(|i| {
+            let t = i as f32 / RATE as f32;
+            let blip = if t % 0.25 < 0.02 { 0.5 } else { 0.0 };
+            0.5 * (2.0 * PI * 20.0 * t).sin() + blip * (2.0 * PI * 300.0 * t).sin()
+        })
+        .collect();
+    tracker.push(&samples);
+    let features = tracker.take();
+    assert!(
+        (features.seconds - 2.0).abs() < 0.02,
+        "{}",
+        features.seconds
+    );
+    // The first blip has no context yet
+    assert!(
+        (3.0..=4.0).contains(&features.onset_rate),
+        "{}",
+        features.onset_rate
+    );
+    assert!(features.sub > features.mid && features.mid > features.high);
+    assert!(features.tilt() < 0.1);
+
+    // Nothing pushed since
+    assert_eq!(tracker.take().seconds, 0.0);
+}
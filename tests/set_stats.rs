@@ -0,0 +1,52 @@
+//! Statistics of a set accumulated hop by hop.
+
+use rust_bpm_analyzer::core_bpm::set_stats::SetStats;
+use std::time::Duration;
+
+const HOP: Duration = Duration::from_millis(500);
+
+#[test]
+fn empty_set() {
+    let report = SetStats::new().report();
+    assert_eq!(report.drops, 0);
+    assert_eq!(report.analyzed_s, 0.0);
+    assert_eq!(report.locked_percent, 0.0);
+    assert_eq!(report.average_bpm, None);
+    assert_eq!(report.min_bpm, None);
+}
+
+#[test]
+fn average_is_weighted_by_the_time_held() {
+    let mut stats = SetStats::new();
+    for _ in 0..4 {
+        stats.record(HOP, None);
+    }
+    for _ in 0..9 {
+        stats.record(HOP, Some((120.0, false)));
+    }
+    for _ in 0..3 {
+        stats.record(HOP, Some((128.0, false)));
+    }
+
+    let report = stats.report();
+    assert_eq!(report.analyzed_s, 8.0);
+    assert_eq!(report.locked_percent, 75.0);
+    assert_eq!(report.average_bpm, Some(122.0));
+    assert_eq!(report.min_bpm, Some(120.0));
+    assert_eq!(report.max_bpm, Some(128.0));
+}
+
+#[test]
+fn drop_over_several_hops_counts_once() {
+    let mut stats = SetStats::new();
+    let hops = [false, true, true, true, false, true, false];
+    for is_drop in hops {
+        stats.record(HOP, Some((128.0, is_drop)));
+    }
+    // A hop without tempo in between ends the drop too
+    stats.record(HOP, Some((128.0, true)));
+    stats.record(HOP, None);
+    stats.record(HOP, Some((128.0, true)));
+
+    assert_eq!(stats.report().drops, 4);
+}
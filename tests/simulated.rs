@@ -0,0 +1,62 @@
+//! Audio of the simulated input device (`--sim`).
+
+use rust_bpm_analyzer::core_bpm::analyzer::BpmAnalyzer;
+use rust_bpm_analyzer::core_bpm::simulated::{SIMULATED_DEVICE, SimulatedSource};
+
+const SAMPLE_RATE: u32 = 48000;
+
+#[test]
+fn analyzer_follows_the_generated_ramp() {
+    let mut source = SimulatedSource::open(SIMULATED_DEVICE, SAMPLE_RATE)
+        .expect("simulated device")
+        .expect("kick source");
+    let mut analyzer = BpmAnalyzer::new(SAMPLE_RATE, None).expect("analyzer");
+    let mut detected = None;
+    // 20 s in half-second hops
+    for _ in 0..40 {
+        let hop = source.next_packet(SAMPLE_RATE as usize / 2);
+        if let Some(result) = analyzer.process(&hop).expect("analysis") {
+            detected = Some(result.bpm);
+        }
+    }
+    let expected = source.bpm().expect("kick tempo");
+    let detected = detected.expect("tempo detected");
+    // The analysis window lags a little behind the ramp
+    assert!(
+        (detected - expected).abs() <= 2.5,
+        "detected {:.1}, generated {:.1}",
+        detected,
+        expected
+    );
+}
+
+#[test]
+fn wav_file_loops_and_real_devices_are_left_alone() {
+    let path = std::env::temp_dir().join(format!("bpm-sim-{}.wav", std::process::id()));
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 8000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).expect("wav");
+    for sample in [0i16, 8192, 16384] {
+        writer.write_sample(sample).expect("sample");
+    }
+    writer.finalize().expect("wav");
+
+    let name = format!("{}: {}", SIMULATED_DEVICE, path.display());
+    let mut source = SimulatedSource::open(&name, SAMPLE_RATE)
+        .expect("simulated device")
+        .expect("wav source");
+    // The file keeps its own rate
+    assert_eq!(source.sample_rate(), 8000);
+    assert_eq!(
+        source.next_packet(7),
+        vec![0.0, 0.25, 0.5, 0.0, 0.25, 0.5, 0.0]
+    );
+    assert_eq!(source.next_packet(2), vec![0.25, 0.5]);
+    let _ = std::fs::remove_file(path);
+
+    assert!(SimulatedSource::open("USB Audio Device", SAMPLE_RATE).is_none());
+}
@@ -0,0 +1,156 @@
+//! Soak test of the live analysis: hours of the simulated program (tempo changes,
+//! silences, noise bursts) fed through `LiveAnalysis` as fast as possible, with the
+//! defaults of the config file, checking that memory and state sizes stay bounded.
+//!
+//! The short run is part of `cargo test`. The full 24 h run is ignored by default:
+//! `SOAK_HOURS=24 cargo test --release --test soak -- --ignored --nocapture`
+//! (allocations are counted per process, keep it the only test running).
+
+use rust_bpm_analyzer::config::AppConfig;
+use rust_bpm_analyzer::core_bpm::governor::GovernorConfig;
+use rust_bpm_analyzer::core_bpm::histogram::TempoHistogram;
+use rust_bpm_analyzer::core_bpm::live::LiveAnalysis;
+use rust_bpm_analyzer::core_bpm::simulated::SimulatedSource;
+use rust_bpm_analyzer::core_bpm::tempo_tracker;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+// Headless capture rate (see `platform::TARGET_SAMPLE_RATE`) and capture packet size
+const SAMPLE_RATE: u32 = 12000;
+const PACKET: usize = 512;
+// Memory may grow this much after the warm-up (scratch buffers reaching their peak size)
+const MEMORY_SLACK: usize = 1 << 20;
+// Same as the `[analysis]` defaults
+const HISTOGRAM_PERIOD: Duration = Duration::from_secs(600);
+
+/// Bytes currently allocated by the process.
+struct CountingAllocator;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn soak(hours: f32) {
+    let packets = (hours * 3600.0 * SAMPLE_RATE as f32 / PACKET as f32) as usize;
+    let warmup = packets / 24;
+    let checkpoint = (packets / 48).max(1);
+
+    let config = AppConfig::default();
+    let analyzer_config = config.analyzer_config();
+    // Below the measured load of any hop, so the governor steps the resolution down
+    let governor = GovernorConfig {
+        enabled: true,
+        cpu_budget: 1e-6,
+    };
+    let mut live =
+        LiveAnalysis::new(SAMPLE_RATE, analyzer_config, governor, true).expect("live analysis");
+    live.set_recording(config.recording.clone());
+    live.set_key_detection(config.key.clone()).expect("key");
+    live.set_verification(config.verification.clone())
+        .expect("verification");
+    let mut histogram = TempoHistogram::new(HISTOGRAM_PERIOD, 1.0);
+    let mut source = SimulatedSource::program(SAMPLE_RATE, 0x2804);
+    // Histogram entries are dated on the program clock, hours go by in minutes
+    let start = Instant::now();
+    let mut window = analyzer_config.window_duration;
+    let mut baseline = None;
+    let mut results = 0;
+    let mut resolution_changes = 0;
+
+    for index in 0..packets {
+        let packet = source.next_packet(PACKET);
+        live.push(&packet);
+        drop(packet);
+
+        while live.hop_ready() {
+            let processed = live.analyze().expect("analyze");
+            let state = live.analyzer().snapshot();
+            assert!(
+                state.history.len() <= tempo_tracker::OBSERVATIONS,
+                "history: {:?}",
+                state.history
+            );
+            assert!(state.rejections.len() <= 16);
+            for buffer in &state.buffers {
+                assert!(buffer.len <= buffer.capacity, "{:?}", buffer);
+            }
+            if state.config.window_duration != window {
+                window = state.config.window_duration;
+                resolution_changes += 1;
+            }
+
+            if let Some(result) = processed {
+                results += 1;
+                assert!(result.bpm.is_finite() && result.bpm > 0.0, "{:?}", result);
+                let played = (index + 1) * PACKET;
+                let now = start + Duration::from_secs_f64(played as f64 / SAMPLE_RATE as f64);
+                histogram.push_at(result.bpm, now);
+            }
+        }
+
+        let live_bytes = LIVE_BYTES.load(Ordering::Relaxed);
+        // After the warm-up and a first detection, which builds the FFT plans
+        if baseline.is_none() && index >= warmup && results > 0 {
+            baseline = Some(live_bytes);
+        }
+        if let Some(baseline) = baseline
+            && index % checkpoint == 0
+        {
+            println!(
+                "{:6.2} h: {} results, {} KiB allocated (baseline {} KiB)",
+                (index * PACKET) as f32 / SAMPLE_RATE as f32 / 3600.0,
+                results,
+                live_bytes / 1024,
+                baseline / 1024
+            );
+            assert!(
+                live_bytes <= baseline + MEMORY_SLACK,
+                "memory grew from {} to {} bytes",
+                baseline,
+                live_bytes
+            );
+        }
+    }
+
+    // Histogram keeps at most one detection per hop over its period
+    let hop_s = analyzer_config.hop_duration.as_secs_f32();
+    let max_entries = (HISTOGRAM_PERIOD.as_secs_f32() / hop_s) as u32 + 1;
+    assert!(histogram.snapshot().total <= max_entries);
+    assert!(results > 0, "no tempo detected during the soak");
+    assert!(
+        resolution_changes > 0,
+        "the governor never changed the resolution"
+    );
+}
+
+#[test]
+fn soak_one_hour() {
+    soak(1.0);
+}
+
+#[test]
+#[ignore]
+fn soak_full_day() {
+    let hours = std::env::var("SOAK_HOURS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(24.0);
+    soak(hours);
+}
@@ -0,0 +1,89 @@
+//! Tempo tracker decoding on sequences of detections. Run with `cargo test`.
+
+use rust_bpm_analyzer::core_bpm::tempo_tracker;
+use rust_bpm_analyzer::core_bpm::tempo_tracker::{TempoTracker, Tracked};
+use std::time::Instant;
+
+// Analyzer defaults: 100-310 BPM, 3 BPM tolerance, 2 hops for a change
+fn tracker() -> TempoTracker {
+    TempoTracker::new(100.0, 310.0, 3.0, 2)
+}
+
+/// Feeds `bpms` and returns the outcome of each update.
+fn track(tracker: &mut TempoTracker, bpms: &[f32]) -> Vec<Tracked> {
+    let now = Instant::now();
+    bpms.iter().map(|&bpm| tracker.update(bpm, now)).collect()
+}
+
+#[test]
+fn steady_detections_give_their_median() {
+    let mut tracker = tracker();
+    let detections = [127.8, 128.1, 128.0, 128.3];
+    let tracked = track(&mut tracker, &[detections, detections].concat());
+    let last = tracked.last().unwrap();
+    assert!((last.bpm - 128.1).abs() < 0.01, "{:?}", last);
+    assert!(last.confidence > 0.9, "{:?}", last);
+    assert!(tracked.iter().all(|t| t.changed_from.is_none()));
+}
+
+#[test]
+fn stray_detection_is_absorbed() {
+    let mut tracker = tracker();
+    let tracked = track(&mut tracker, &[124.0, 124.0, 124.0, 161.0, 124.0]);
+    assert!(tracked.iter().all(|t| t.bpm == 124.0), "{:?}", tracked);
+    assert!(tracked.iter().all(|t| t.changed_from.is_none()));
+    // The stray detection leaves some doubt
+    assert!(
+        tracked[3].confidence < tracked[2].confidence,
+        "{:?}",
+        tracked
+    );
+}
+
+#[test]
+fn new_tempo_takes_over_after_the_change_hops() {
+    let mut tracker = tracker();
+    let tracked = track(
+        &mut tracker,
+        &[124.0, 124.0, 124.0, 140.0, 140.0, 124.0, 140.0],
+    );
+    assert_eq!(tracked[3].bpm, 124.0);
+    assert_eq!(tracked[4].bpm, 140.0);
+    assert_eq!(tracked[4].changed_from, Some(124.0));
+    // A detection of the previous tempo does not bring it back
+    assert!(tracked[5..].iter().all(|t| t.bpm == 140.0), "{:?}", tracked);
+    let changes = tracked.iter().filter(|t| t.changed_from.is_some()).count();
+    assert_eq!(changes, 1);
+}
+
+#[test]
+fn octave_slips_are_not_tempo_changes() {
+    let mut tracker = tracker();
+    let tracked = track(&mut tracker, &[174.0, 174.0, 174.0, 87.0, 174.0]);
+    assert!(
+        tracked.iter().all(|t| t.changed_from.is_none()),
+        "{:?}",
+        tracked
+    );
+    assert_eq!(tracked.last().unwrap().bpm, 174.0);
+}
+
+#[test]
+fn seeded_tempo_holds_until_a_change() {
+    let mut tracker = tracker();
+    tracker.seed(124.0, Instant::now());
+    let tracked = track(&mut tracker, &[140.0, 140.0]);
+    assert_eq!(tracked[0].bpm, 124.0);
+    assert_eq!(tracked[1].changed_from, Some(124.0));
+    assert_eq!(tracked[1].bpm, 140.0);
+}
+
+#[test]
+fn observations_are_bounded() {
+    let mut tracker = tracker();
+    track(&mut tracker, &[128.0; 3 * tempo_tracker::OBSERVATIONS]);
+    assert_eq!(tracker.observations().count(), tempo_tracker::OBSERVATIONS);
+    tracker.clear();
+    assert!(tracker.is_empty());
+    assert_eq!(tracker.tempo(), None);
+}